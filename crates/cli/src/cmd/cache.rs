@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use crate::Result;
+use crate::error;
+use clap::{Parser, Subcommand};
+use edo::context::Addr;
+use edo::storage::{CacheSelector, FsckOptions, Id};
+use edo::util::format_size;
+use snafu::{OptionExt, ensure};
+
+use crate::Args;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Inspect and maintain the artifact caches", long_about = None)]
+pub struct Cache {
+    #[clap(subcommand)]
+    command: CacheCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum CacheCommand {
+    /// Check that every artifact in a cache points at intact blobs
+    Fsck {
+        /// Cache to check: `local`, `build`, `output` or `source:<name>`
+        #[arg(long, default_value = "local", value_parser = parse_cache)]
+        cache: CacheSelector,
+        /// Re-hash every blob instead of only checking that it exists
+        #[arg(long)]
+        verify: bool,
+        /// Delete artifacts that reference missing or corrupt blobs
+        #[arg(long)]
+        drop_broken: bool,
+        /// Delete blobs no artifact references
+        #[arg(long)]
+        delete_orphans: bool,
+        /// Shorthand for `--drop-broken --delete-orphans`
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Count the artifacts, blobs and unfinished layers of a cache
+    Info {
+        /// Cache to count: `local`, `build`, `output` or `source:<name>`
+        #[arg(long, default_value = "local", value_parser = parse_cache)]
+        cache: CacheSelector,
+    },
+    /// List the generations of an artifact kept in a cache, newest first
+    History {
+        /// Transform address (`//project/name`), artifact id or id prefix
+        target: String,
+        /// Cache to list: `local`, `build`, `output` or `source:<name>`
+        #[arg(long, default_value = "local", value_parser = parse_cache)]
+        cache: CacheSelector,
+    },
+}
+
+fn parse_cache(value: &str) -> std::result::Result<CacheSelector, String> {
+    value
+        .parse()
+        .map_err(|e: edo::storage::StorageError| e.to_string())
+}
+
+impl Cache {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        match &self.command {
+            CacheCommand::Fsck {
+                cache,
+                verify,
+                drop_broken,
+                delete_orphans,
+                repair,
+            } => {
+                // Remote caches are only known once the project is loaded
+                let ctx = if *cache == CacheSelector::Local {
+                    super::init_context(&args, HashMap::default()).await?
+                } else {
+                    super::create_context(&args, HashMap::default(), true).await?
+                };
+                let options = FsckOptions::builder()
+                    .verify(*verify)
+                    .drop_broken(*drop_broken || *repair)
+                    .delete_orphans(*delete_orphans || *repair)
+                    .build();
+                let report = ctx.storage().fsck(cache, &options).await?;
+                for issue in report.issues.iter() {
+                    println!("{issue}");
+                }
+                for id in report.dropped.iter() {
+                    println!("dropped {id}");
+                }
+                for digest in report.removed.iter() {
+                    println!("removed {digest}");
+                }
+                if !report.orphans_checked {
+                    println!("the {cache} cache cannot list its blobs, orphans were not checked");
+                }
+                println!(
+                    "checked {} artifacts and {} layers in the {cache} cache: {} problems, {} artifacts dropped, {} blobs removed",
+                    report.artifacts,
+                    report.layers,
+                    report.issues.len(),
+                    report.dropped.len(),
+                    report.removed.len()
+                );
+                ensure!(
+                    report.is_clean(),
+                    error::CacheInconsistentSnafu {
+                        cache: cache.to_string(),
+                        count: report.issues.len(),
+                    }
+                );
+            }
+            CacheCommand::Info { cache } => {
+                let ctx = if *cache == CacheSelector::Local {
+                    super::init_context(&args, HashMap::default()).await?
+                } else {
+                    super::create_context(&args, HashMap::default(), true).await?
+                };
+                let info = ctx.storage().info(cache).await?;
+                let count = |x: Option<usize>| x.map_or("unknown".to_string(), |x| x.to_string());
+                println!("{cache} cache");
+                println!("artifacts     {}", info.artifacts);
+                println!("blobs         {}", count(info.blobs));
+                println!("unreferenced  {}", count(info.unreferenced));
+                match info.temporary {
+                    Some(temporary) => println!(
+                        "temporary     {temporary} ({}, {} stale)",
+                        format_size(info.temporary_bytes),
+                        info.stale
+                    ),
+                    None => println!("temporary     none kept"),
+                }
+            }
+            CacheCommand::History { target, cache } => {
+                // Only addresses need the project, to compute the current id
+                let (ctx, prefix, current) = if target.starts_with("//") {
+                    let ctx = super::create_context(&args, HashMap::default(), true).await?;
+                    let addr = Addr::parse(target)?;
+                    let transform =
+                        ctx.get_transform(&addr)
+                            .context(error::UnknownTransformSnafu {
+                                addr: addr.to_string(),
+                            })?;
+                    let id = transform.get_unique_id(&ctx.get_handle()).await?;
+                    (ctx, id.prefix(), Some(id))
+                } else {
+                    let ctx = if *cache == CacheSelector::Local {
+                        super::init_context(&args, HashMap::default()).await?
+                    } else {
+                        super::create_context(&args, HashMap::default(), true).await?
+                    };
+                    // Full ids are listed with the other generations of their prefix
+                    let prefix = target
+                        .parse::<Id>()
+                        .map(|x| x.prefix())
+                        .unwrap_or(target.clone());
+                    (ctx, prefix, None)
+                };
+                let history = ctx.storage().history(cache, &prefix).await?;
+                if history.is_empty() {
+                    println!("no generations of {prefix} in the {cache} cache");
+                }
+                for (index, generation) in history.iter().enumerate() {
+                    let saved = generation.saved.map_or("unknown".to_string(), |x| {
+                        x.format("%Y-%m-%d %H:%M:%S").to_string()
+                    });
+                    let marker = if current.as_ref() == Some(&generation.id) {
+                        " (current)"
+                    } else {
+                        ""
+                    };
+                    println!("{index:>3}  {saved:<19}  {}{marker}", generation.id);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,76 +1,244 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::Result;
+use crate::cmd::util::{Target, decompress, resolve_tag, select_generation};
 use crate::error;
-use async_compression::tokio::bufread::BzDecoder;
-use async_compression::tokio::bufread::GzipDecoder;
-use async_compression::tokio::bufread::LzmaDecoder;
-use async_compression::tokio::bufread::XzDecoder;
-use async_compression::tokio::bufread::ZstdDecoder;
 use clap::Parser;
-use edo::context::Addr;
-use edo::storage::Compression;
-use edo::storage::MediaType;
-use snafu::ResultExt;
-use std::pin::Pin;
+use edo::context::{Addr, Context, Progress};
+use edo::storage::{BuildStream, Id, MediaType, export_oci_layout, oci_ref_name};
+use edo::transform::results_id;
+use snafu::{OptionExt, ResultExt, ensure};
 use tokio::fs::create_dir_all;
 use tokio::io::BufReader;
 use tokio_tar::Archive;
+use tracing::Instrument;
 
+use super::{Contexts, HostContexts};
 use crate::Args;
 
 #[derive(Parser, Debug, Clone)]
 #[clap(version, about = "Checkout an artifact to local directory", long_about = None)]
 pub struct Checkout {
+    /// Transform or source address (`//project/name`), artifact id, or alias
+    /// published with `edo publish` (`name:tag`)
     addr: String,
-    output: PathBuf,
+    #[clap(required_unless_present = "oci_layout")]
+    output: Option<PathBuf>,
+    /// Checkout the named source of the transform instead of its output
+    #[clap(long)]
+    source: Option<String>,
+    /// Write the artifact as an image to the OCI image layout at this
+    /// directory instead of extracting it, for `skopeo` or `crane` to consume
+    #[clap(long, value_name = "DIR", conflicts_with_all = ["output", "source"])]
+    oci_layout: Option<PathBuf>,
+    /// Checkout the results captured by the transform's `capture` list instead of its output
+    #[clap(long, conflicts_with = "source")]
+    results: bool,
+    /// Checkout an earlier generation of the artifact kept in the local
+    /// cache, numbered as `edo cache history` lists them (0 is the newest)
+    #[clap(long, conflicts_with = "source")]
+    generation: Option<usize>,
+    /// Checkout the artifact built with `edo run --override` against these overrides
+    #[clap(long = "override", value_name = "ADDR=PATH", value_parser = crate::cmd::util::parse_override)]
+    overrides: Vec<(String, PathBuf)>,
     #[clap(long = "arg", short = 'a', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
     args: Option<Vec<(String, String)>>,
 }
 
 impl Checkout {
     pub async fn run(&self, args: Args) -> Result<()> {
-        let ctx = super::create_context(
-            &args,
-            self.args
-                .clone()
-                .map(HashMap::from_iter)
-                .unwrap_or_default(),
-            true,
-        )
-        .await?;
-        let addr = Addr::parse(self.addr.as_str())?;
-        let transform = ctx.get_transform(&addr).unwrap();
-        let handle = ctx.get_handle();
-        let id = transform.get_unique_id(&handle).await?;
-        let artifact = ctx.storage().safe_open(&id).await?;
-        if !self.output.exists() {
-            create_dir_all(&self.output).await.context(error::IoSnafu)?;
-        }
-        for layer in artifact.layers() {
-            // Do different things depending on the media_type
-            let reader = BufReader::new(ctx.storage().safe_read(layer).await?);
-            match layer.media_type() {
-                MediaType::Tar(compression) => {
-                    let reader: Pin<Box<dyn tokio::io::AsyncRead>> = match compression {
-                        Compression::Bzip2 => Box::pin(BzDecoder::new(reader)),
-                        Compression::Lz => Box::pin(LzmaDecoder::new(reader)),
-                        Compression::Xz => Box::pin(XzDecoder::new(reader)),
-                        Compression::Gzip => Box::pin(GzipDecoder::new(reader)),
-                        Compression::Zstd => Box::pin(ZstdDecoder::new(reader)),
-                        _ => Box::pin(reader),
-                    };
-                    let mut archive = Archive::new(reader);
-                    archive.unpack(&self.output).await.context(error::IoSnafu)?;
+        self.run_in(&HostContexts, args).await
+    }
+
+    /// Checks out from a context built by `contexts`.
+    pub async fn run_in(&self, contexts: &impl Contexts, args: Args) -> Result<()> {
+        let variables = self
+            .args
+            .clone()
+            .map(HashMap::from_iter)
+            .unwrap_or_default();
+        let target = Target::parse(self.addr.as_str())?;
+        let addr = match target {
+            Target::Addr(addr) => addr,
+            target => {
+                ensure!(
+                    self.source.is_none(),
+                    error::UnknownTransformSnafu {
+                        addr: self.addr.clone()
+                    }
+                );
+                // Artifact ids are resolved against storage alone, aliases
+                // need the output cache the project declares
+                let ctx = if target.needs_project() {
+                    super::create_context_in(contexts, &args, variables, true).await?
+                } else {
+                    super::init_context_in(contexts, &args, variables).await?
+                };
+                let mut id = match target {
+                    Target::Tag(tag) => resolve_tag(ctx.storage(), &tag).await?,
+                    Target::Id(id) => id,
+                    Target::Addr(_) => unreachable!(),
+                };
+                if self.results {
+                    id = results_id(&id);
                 }
-                value => {
-                    tracing::error!(
-                        "skipping artifact layer with media_type {value} as we do not know how to extract it"
-                    );
+                if let Some(generation) = self.generation {
+                    id = select_generation(ctx.storage(), &id, generation).await?;
                 }
+                return self.extract(&ctx, &id).await;
             }
+        };
+        let ctx = super::create_context_in(contexts, &args, variables, true).await?;
+        for (addr, path) in self.overrides.iter() {
+            ctx.add_override(&Addr::parse(addr)?, path).await?;
+        }
+        if let Some(name) = self.source.as_ref() {
+            ensure_transform(&ctx, &addr)?;
+            let source = resolve_source(&ctx, &addr, name)?;
+            return self.checkout_source(&ctx, &source).await;
+        }
+        let Some(transform) = ctx.get_transform(&addr) else {
+            ensure!(
+                self.oci_layout.is_none(),
+                error::UnknownTransformSnafu {
+                    addr: addr.to_string()
+                }
+            );
+            return self.checkout_source(&ctx, &addr).await;
+        };
+        let handle = ctx.get_handle();
+        let mut id = transform.get_unique_id(&handle).await?;
+        if self.results {
+            id = results_id(&id);
+        }
+        if let Some(generation) = self.generation {
+            id = select_generation(ctx.storage(), &id, generation).await?;
         }
+        self.extract(&ctx, &id).await
+    }
+
+    /// Extract every tar layer of the artifact `id` into the output
+    /// directory, or export it to the OCI image layout when one is given
+    ///
+    /// Layers are unpacked as they are read from the cache holding the
+    /// artifact, it is never downloaded to the local cache first.
+    async fn extract(&self, ctx: &Context, id: &Id) -> Result<()> {
+        if let Some(dir) = self.oci_layout.as_ref() {
+            let artifact = ctx
+                .storage()
+                .find_build(id, true)
+                .await?
+                .context(error::ArtifactNotFoundSnafu { id: id.to_string() })?;
+            let digest = export_oci_layout(ctx.storage(), &artifact, dir).await?;
+            println!(
+                "{}:{} {digest}",
+                dir.display(),
+                oci_ref_name(artifact.config().id())
+            );
+            return Ok(());
+        }
+        let stream = ctx
+            .storage()
+            .stream_build(id)
+            .await?
+            .context(error::ArtifactNotFoundSnafu { id: id.to_string() })?;
+        let output = self.output();
+        if !output.exists() {
+            create_dir_all(output).await.context(error::IoSnafu)?;
+        }
+        let span = tracing::info_span!("checkout", id = id.to_string());
+        let progress = Progress::for_span(span.clone());
+        progress.set_total_bytes(
+            stream
+                .artifact()
+                .layers()
+                .iter()
+                .map(|x| *x.size() as u64)
+                .sum(),
+        );
+        unpack(&stream, &progress, output).instrument(span).await
+    }
+
+    /// The output directory, which clap requires unless `--oci-layout` is given
+    fn output(&self) -> &Path {
+        self.output.as_deref().unwrap_or(Path::new("."))
+    }
+
+    /// Stage a source into the output directory exactly as a transform would
+    /// see it, fetching it first if it is not cached.
+    async fn checkout_source(&self, ctx: &Context, addr: &Addr) -> Result<()> {
+        let source = ctx
+            .get_source(addr)
+            .await?
+            .context(error::UnknownTargetSnafu {
+                addr: addr.to_string(),
+            })?;
+        let id = source.get_unique_id().await?;
+        let log = ctx.log().create(&format!("checkout-{id}")).await?;
+        source.cache(&log, ctx.storage()).await?;
+        // Stage through a local environment rooted at the output directory,
+        // it is never cleaned so the staged tree is left in place
+        let env = ctx
+            .get_handle()
+            .create_environment(&log, &Addr::parse("//default")?, self.output())
+            .await?;
+        env.setup(&log, ctx.storage()).await?;
+        env.up(&log).await?;
+        let result = source.stage(&log, ctx.storage(), &env, Path::new("")).await;
+        env.down(&log).await?;
+        result?;
         Ok(())
     }
 }
+
+// Unpack the tar layers of a streamed artifact into `output`
+async fn unpack(stream: &BuildStream, progress: &Progress, output: &Path) -> Result<()> {
+    for layer in stream.artifact().layers() {
+        // Do different things depending on the media_type
+        match layer.media_type() {
+            MediaType::Tar(compression) => {
+                let reader = progress.wrap_read(stream.read(layer).await?);
+                let mut archive = Archive::new(decompress(BufReader::new(reader), compression));
+                archive.unpack(output).await.context(error::IoSnafu)?;
+            }
+            value => {
+                progress.advance(*layer.size() as u64);
+                tracing::error!(
+                    "skipping artifact layer with media_type {value} as we do not know how to extract it"
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn ensure_transform(ctx: &Context, addr: &Addr) -> Result<()> {
+    ensure!(
+        ctx.get_transform(addr).is_some(),
+        error::UnknownTransformSnafu {
+            addr: addr.to_string()
+        }
+    );
+    Ok(())
+}
+
+/// Resolve a source name the way a transform's `source` list does: absolute
+/// addresses are used as is, anything else is relative to the transform's project.
+fn resolve_source(ctx: &Context, transform: &Addr, name: &str) -> Result<Addr> {
+    let candidate = if name.starts_with("//") {
+        Addr::parse(name)?
+    } else {
+        transform
+            .parent()
+            .map(|x| x.join(name))
+            .unwrap_or(Addr::parse(name)?)
+    };
+    ctx.get_element_sources(transform)
+        .into_iter()
+        .find(|x| *x == candidate)
+        .context(error::UnknownTransformSourceSnafu {
+            transform: transform.clone(),
+            name,
+        })
+}
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use crate::Args;
+use crate::Result;
+use clap::{CommandFactory, Parser};
+use edo::context::LogVerbosity;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Print a shell completion script", long_about = None)]
+pub struct Completions {
+    /// Shell to complete for
+    #[arg(value_parser = ["bash", "zsh", "fish"])]
+    shell: String,
+}
+
+impl Completions {
+    pub async fn run(&self, _args: Args) -> Result<()> {
+        let spec = Spec::new(&Args::command());
+        let script = match self.shell.as_str() {
+            "bash" => spec.bash(),
+            "zsh" => spec.zsh(),
+            _ => spec.fish(),
+        };
+        print!("{script}");
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(about = "Print the project addresses starting with a prefix", long_about = None)]
+pub struct Complete {
+    /// Address prefix to complete
+    #[arg(default_value = "//")]
+    prefix: String,
+}
+
+impl Complete {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        // Anything logged would be read as a completion candidate
+        let ctx = super::init_context_with(&args, HashMap::default(), LogVerbosity::Quiet).await?;
+        for addr in ctx.project_index().await?.complete(&self.prefix) {
+            println!("{addr}");
+        }
+        Ok(())
+    }
+}
+
+/// A named option of a command.
+struct Flag {
+    short: Option<char>,
+    long: Option<String>,
+    help: String,
+    takes_value: bool,
+}
+
+impl Flag {
+    fn words(&self) -> Vec<String> {
+        self.short
+            .map(|x| format!("-{x}"))
+            .into_iter()
+            .chain(self.long.iter().map(|x| format!("--{x}")))
+            .collect()
+    }
+}
+
+/// A visible subcommand.
+struct Subcommand {
+    name: String,
+    about: String,
+    flags: Vec<Flag>,
+    subcommands: Vec<String>,
+}
+
+/// What the completion scripts are generated from: the visible part of the
+/// clap command tree.
+struct Spec {
+    globals: Vec<Flag>,
+    subcommands: Vec<Subcommand>,
+}
+
+fn flags(cmd: &clap::Command) -> Vec<Flag> {
+    cmd.get_arguments()
+        .filter(|x| !x.is_positional() && !x.is_hide_set())
+        .map(|x| Flag {
+            short: x.get_short(),
+            long: x.get_long().map(str::to_string),
+            help: x.get_help().map(|x| x.to_string()).unwrap_or_default(),
+            takes_value: x.get_action().takes_values(),
+        })
+        .collect()
+}
+
+fn visible(cmd: &clap::Command) -> impl Iterator<Item = &clap::Command> {
+    cmd.get_subcommands().filter(|x| !x.is_hide_set())
+}
+
+impl Spec {
+    fn new(cmd: &clap::Command) -> Self {
+        Self {
+            globals: flags(cmd),
+            subcommands: visible(cmd)
+                .map(|x| Subcommand {
+                    name: x.get_name().to_string(),
+                    about: x.get_about().map(|x| x.to_string()).unwrap_or_default(),
+                    flags: flags(x),
+                    subcommands: visible(x).map(|x| x.get_name().to_string()).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Global options whose value is the next word, as a shell case pattern.
+    fn valued_globals(&self) -> String {
+        self.globals
+            .iter()
+            .filter(|x| x.takes_value)
+            .flat_map(|x| x.words())
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+
+    fn names(&self) -> String {
+        self.subcommands
+            .iter()
+            .map(|x| x.name.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Shell case arms mapping each subcommand to `body(options, words)`.
+    fn arms(&self, body: impl Fn(&str, &str) -> String) -> String {
+        let words = |flags: &[Flag]| {
+            flags
+                .iter()
+                .flat_map(|x| x.words())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let mut arms = format!(
+            "        \"\") {} ;;\n",
+            body(&words(&self.globals), &self.names())
+        );
+        for cmd in self.subcommands.iter() {
+            arms.push_str(&format!(
+                "        {}) {} ;;\n",
+                cmd.name,
+                body(&words(&cmd.flags), &cmd.subcommands.join(" "))
+            ));
+        }
+        arms
+    }
+
+    fn bash(&self) -> String {
+        format!(
+            r#"# bash completion for edo, load with: source <(edo completions bash)
+_edo() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" cmd="" opts="" words="" i
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${{COMP_WORDS[i]}}" in
+            {valued}) ((i++)) ;;
+            -*) ;;
+            *) cmd="${{COMP_WORDS[i]}}"; break ;;
+        esac
+    done
+    case "$cmd" in
+{arms}    esac
+    case "$cur" in
+        /*) COMPREPLY=($(edo __complete "$cur" 2>/dev/null)) ;;
+        -*) COMPREPLY=($(compgen -W "$opts" -- "$cur")) ;;
+        *)
+            if [[ -n "$words" ]]; then
+                COMPREPLY=($(compgen -W "$words" -- "$cur"))
+            else
+                COMPREPLY=($(compgen -f -- "$cur"))
+            fi
+            ;;
+    esac
+}}
+complete -F _edo edo
+"#,
+            valued = self.valued_globals(),
+            arms = self.arms(|opts, words| format!("opts=\"{opts}\"; words=\"{words}\"")),
+        )
+    }
+
+    fn zsh(&self) -> String {
+        format!(
+            r#"#compdef edo
+# zsh completion for edo, load with: source <(edo completions zsh)
+_edo() {{
+    local cur="${{words[CURRENT]}}" cmd="" opts="" subs="" i
+    for ((i = 2; i < CURRENT; i++)); do
+        case "${{words[i]}}" in
+            {valued}) ((i++)) ;;
+            -*) ;;
+            *) cmd="${{words[i]}}"; break ;;
+        esac
+    done
+    case "$cmd" in
+{arms}    esac
+    case "$cur" in
+        /*) compadd -Q -- ${{(f)"$(edo __complete "$cur" 2>/dev/null)"}} ;;
+        -*) compadd -- ${{=opts}} ;;
+        *)
+            if [[ -n "$subs" ]]; then
+                compadd -- ${{=subs}}
+            else
+                _files
+            fi
+            ;;
+    esac
+}}
+if [[ "${{funcstack[1]}}" == "_edo" ]]; then
+    _edo "$@"
+else
+    compdef _edo edo
+fi
+"#,
+            valued = self.valued_globals(),
+            arms = self.arms(|opts, subs| format!("opts=\"{opts}\"; subs=\"{subs}\"")),
+        )
+    }
+
+    fn fish(&self) -> String {
+        let quote = |x: &str| format!("'{}'", x.replace('\\', "\\\\").replace('\'', "\\'"));
+        let flag = |condition: &str, flag: &Flag| {
+            let mut line = format!("complete -c edo -n {}", quote(condition));
+            if let Some(short) = flag.short {
+                line.push_str(&format!(" -s {short}"));
+            }
+            if let Some(long) = flag.long.as_ref() {
+                line.push_str(&format!(" -l {long}"));
+            }
+            if flag.takes_value {
+                line.push_str(" -r");
+            }
+            if !flag.help.is_empty() {
+                line.push_str(&format!(" -d {}", quote(&flag.help)));
+            }
+            line.push('\n');
+            line
+        };
+        let mut script = String::from(
+            "# fish completion for edo, load with: edo completions fish | source\ncomplete -c edo -f\n",
+        );
+        for global in self.globals.iter() {
+            script.push_str(&flag("__fish_use_subcommand", global));
+        }
+        for cmd in self.subcommands.iter() {
+            script.push_str(&format!(
+                "complete -c edo -n __fish_use_subcommand -a {} -d {}\n",
+                cmd.name,
+                quote(&cmd.about)
+            ));
+            let condition = format!("__fish_seen_subcommand_from {}", cmd.name);
+            for option in cmd.flags.iter() {
+                script.push_str(&flag(&condition, option));
+            }
+            if !cmd.subcommands.is_empty() {
+                script.push_str(&format!(
+                    "complete -c edo -n {} -a {}\n",
+                    quote(&condition),
+                    quote(&cmd.subcommands.join(" "))
+                ));
+            }
+        }
+        script.push_str(
+            "complete -c edo -n 'string match -q -- \"/*\" (commandline -ct)' -a '(edo __complete (commandline -ct) 2>/dev/null)'\n",
+        );
+        script
+    }
+}
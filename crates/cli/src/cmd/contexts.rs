@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use edo::context::{Context, LogVerbosity};
+
+use crate::Args;
+use crate::Result;
+
+/// Builds the contexts commands operate on.
+///
+/// Commands take one through their `run_in` method so their behavior can be
+/// exercised against in-memory storage, fake farms and a log that leaves
+/// the process's tracing alone, instead of a session in `.edo`.
+pub trait Contexts {
+    /// Creates a context logging at `verbosity`, with the core components
+    /// and the default farm registered, without loading the project.
+    async fn init(
+        &self,
+        args: &Args,
+        variables: HashMap<String, String>,
+        verbosity: LogVerbosity,
+    ) -> Result<Context>;
+}
+
+/// The contexts of a regular invocation: `--storage` or `.edo` in the
+/// current directory, logging to the console.
+pub struct HostContexts;
+
+impl Contexts for HostContexts {
+    async fn init(
+        &self,
+        args: &Args,
+        variables: HashMap<String, String>,
+        verbosity: LogVerbosity,
+    ) -> Result<Context> {
+        let ctx = Context::init(
+            args.storage.clone(),
+            args.config.clone(),
+            variables,
+            verbosity,
+        )
+        .await?;
+        super::register(&ctx).await?;
+        Ok(ctx)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use std::collections::HashMap;
+
+    use clap::Parser;
+    use edo::context::{Config, Context, LogManager, LogVerbosity};
+    use edo::storage::{Backend, InMemoryBackend};
+    use tempfile::TempDir;
+
+    use super::Contexts;
+    use crate::{Args, Result};
+
+    /// Contexts sharing one in-memory local cache, for a project in a
+    /// temporary directory.
+    pub(crate) struct MemoryContexts {
+        pub(crate) dir: TempDir,
+        pub(crate) backend: Backend,
+    }
+
+    impl MemoryContexts {
+        pub(crate) fn new() -> Self {
+            Self {
+                dir: TempDir::new().unwrap(),
+                backend: Backend::new(InMemoryBackend::new()),
+            }
+        }
+
+        /// Parses `edo <command...>` as the command line of a test.
+        pub(crate) fn args(command: &[&str]) -> Args {
+            Args::parse_from(std::iter::once("edo").chain(command.iter().copied()))
+        }
+    }
+
+    impl Contexts for MemoryContexts {
+        async fn init(
+            &self,
+            _args: &Args,
+            variables: HashMap<String, String>,
+            verbosity: LogVerbosity,
+        ) -> Result<Context> {
+            let data = self.dir.path().join(".edo");
+            let log = LogManager::detached(data.join("logs"), verbosity).await?;
+            // Never read the user config of whoever runs the tests
+            let config = Config::load(Some(self.dir.path().join("config.toml"))).await?;
+            let ctx = Context::init_with_backend(
+                self.dir.path(),
+                &data,
+                config,
+                variables,
+                log,
+                self.backend.clone(),
+            )
+            .await?;
+            crate::cmd::register(&ctx).await?;
+            Ok(ctx)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use edo::context::LogVerbosity;
+
+    use super::Contexts;
+    use super::test_support::MemoryContexts;
+    use crate::cmd::{Checkout, List, Prune};
+
+    #[tokio::test]
+    async fn checkout_extracts_an_artifact_from_memory() {
+        let contexts = MemoryContexts::new();
+        let args = MemoryContexts::args(&["list"]);
+        let ctx = contexts
+            .init(&args, Default::default(), LogVerbosity::Quiet)
+            .await
+            .unwrap();
+        let tree = contexts.dir.path().join("tree");
+        std::fs::create_dir_all(&tree).unwrap();
+        std::fs::write(tree.join("hello.txt"), "hello").unwrap();
+        let artifact = ctx.storage().inject("hello", &tree).await.unwrap();
+
+        let out = contexts.dir.path().join("out");
+        let id = artifact.config().id().to_string();
+        Checkout::parse_from(["checkout", id.as_str(), out.to_str().unwrap()])
+            .run_in(&contexts, args)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(out.join("hello.txt")).unwrap(),
+            "hello"
+        );
+    }
+
+    #[tokio::test]
+    async fn prune_all_empties_the_local_cache() {
+        let contexts = MemoryContexts::new();
+        let args = MemoryContexts::args(&["prune", "--all"]);
+        let ctx = contexts
+            .init(&args, Default::default(), LogVerbosity::Quiet)
+            .await
+            .unwrap();
+        ctx.storage()
+            .inject("hello", contexts.dir.path())
+            .await
+            .unwrap();
+        assert_eq!(contexts.backend.list().await.unwrap().len(), 1);
+
+        Prune::parse_from(["prune", "--all"])
+            .run_in(&contexts, args)
+            .await
+            .unwrap();
+        assert!(contexts.backend.list().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn list_loads_the_project_in_its_directory() {
+        let contexts = MemoryContexts::new();
+        std::fs::write(
+            contexts.dir.path().join("edo.toml"),
+            "schema-version = \"1\"\n\n[transform.hello]\nkind        = \"script\"\ninterpreter = \"sh\"\ncommands    = [\"true\"]\n",
+        )
+        .unwrap();
+        List::parse_from(["list"])
+            .run_in(&contexts, MemoryContexts::args(&["list"]))
+            .await
+            .unwrap();
+    }
+}
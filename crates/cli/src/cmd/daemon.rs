@@ -0,0 +1,572 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::Args;
+use crate::Result;
+use crate::error;
+use clap::{Parser, Subcommand};
+use edo::context::{
+    Addr, Context, Event, EventKind, LogManager, LogVerbosity, NodeOutcome, ProjectIndex,
+    parent_cache,
+};
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, ensure};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::{Mutex, Notify};
+
+/// Socket the daemon listens on, in the storage directory.
+const SOCKET: &str = "daemon.sock";
+/// File the daemon's console output goes to, in the storage directory.
+const LOG: &str = "daemon.log";
+/// Set to make `edo list` and `edo run` ignore a running daemon.
+const NO_DAEMON: &str = "EDO_NO_DAEMON";
+/// How long `edo daemon start` waits for the daemon to answer.
+const START_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Keep the project loaded in the background between commands", long_about = None)]
+pub struct Daemon {
+    #[clap(subcommand)]
+    command: DaemonCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum DaemonCommand {
+    /// Start a daemon for this project in the background
+    Start,
+    /// Run the daemon in the foreground
+    Serve,
+    /// Show whether a daemon is running and what it holds
+    Status,
+    /// Stop the running daemon
+    Stop,
+}
+
+impl Daemon {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        // A daemon would outlive the transform and hold on to its workspace
+        if matches!(self.command, DaemonCommand::Start | DaemonCommand::Serve) {
+            ensure!(parent_cache().is_none(), error::NestedDaemonSnafu);
+        }
+        match self.command {
+            DaemonCommand::Start => start(&args).await,
+            DaemonCommand::Serve => serve(&args).await,
+            DaemonCommand::Status => {
+                if !delegate(&args, Command::Status).await? {
+                    println!("no daemon is running");
+                }
+                Ok(())
+            }
+            DaemonCommand::Stop => {
+                if delegate(&args, Command::Stop).await? {
+                    println!("daemon stopped");
+                } else {
+                    println!("no daemon is running");
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Work a client asks the daemon for.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub(super) enum Command {
+    /// `edo list`
+    List,
+    /// `edo run` of a single address
+    Run {
+        addr: String,
+        offline: bool,
+        jobs: Option<u64>,
+    },
+    Status,
+    Stop,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Request {
+    /// Directory the client runs in, which must be the daemon's project.
+    cwd: PathBuf,
+    /// The client's `--config`, which must match the daemon's.
+    config: Option<PathBuf>,
+    command: Command,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Status {
+    pid: u32,
+    project: PathBuf,
+    /// Times the project was loaded, once plus once per change of its files.
+    loads: u64,
+    /// `list` and `run` requests served.
+    requests: u64,
+    uptime_s: u64,
+    /// Why the project last failed to load, until its files change.
+    error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "reply", rename_all = "snake_case")]
+enum Reply {
+    /// A line to print.
+    Line {
+        line: String,
+    },
+    /// Progress of a run.
+    Event {
+        event: Event,
+    },
+    Status {
+        status: Status,
+    },
+    /// The daemon cannot serve the request, the client does the work itself.
+    Unavailable {
+        reason: String,
+    },
+    /// The request finished, with the error it failed with.
+    Done {
+        error: Option<String>,
+    },
+}
+
+/// The storage directory shared by the daemon and its clients.
+fn storage_dir(args: &Args) -> Result<PathBuf> {
+    let cwd = std::env::current_dir().context(error::IoSnafu)?;
+    Ok(match args.storage.as_ref() {
+        Some(path) => cwd.join(path),
+        None => cwd.join(".edo"),
+    })
+}
+
+async fn start(args: &Args) -> Result<()> {
+    let storage = storage_dir(args)?;
+    if let Some(status) = query(args).await? {
+        println!(
+            "a daemon is already running for {} (pid {})",
+            status.project.display(),
+            status.pid
+        );
+        return Ok(());
+    }
+    std::fs::create_dir_all(&storage).context(error::IoSnafu)?;
+    let log_path = storage.join(LOG);
+    let log = std::fs::File::create(&log_path).context(error::IoSnafu)?;
+    let mut cmd = std::process::Command::new(std::env::current_exe().context(error::IoSnafu)?);
+    cmd.arg("--storage").arg(&storage);
+    if let Some(config) = args.config.as_ref() {
+        cmd.arg("--config").arg(config);
+    }
+    if args.trace {
+        cmd.arg("--trace");
+    } else if args.debug {
+        cmd.arg("--debug");
+    }
+    cmd.args(["daemon", "serve"])
+        .stdin(Stdio::null())
+        .stdout(log.try_clone().context(error::IoSnafu)?)
+        .stderr(log);
+    // Keep the daemon out of the terminal's process group, so a Ctrl-C in
+    // the shell that started it does not stop it
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+    let mut child = cmd.spawn().context(error::IoSnafu)?;
+
+    let started = Instant::now();
+    while started.elapsed() < START_TIMEOUT {
+        if let Some(status) = query(args).await? {
+            println!("daemon started (pid {})", status.pid);
+            if let Some(e) = status.error {
+                println!("the project failed to load: {e}");
+            }
+            return Ok(());
+        }
+        if let Some(exit) = child.try_wait().context(error::IoSnafu)? {
+            return error::DaemonStartSnafu {
+                reason: format!("it exited with {exit}, see {}", log_path.display()),
+            }
+            .fail();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    error::DaemonStartSnafu {
+        reason: format!(
+            "it did not answer within {}s, see {}",
+            START_TIMEOUT.as_secs(),
+            log_path.display()
+        ),
+    }
+    .fail()
+}
+
+async fn serve(args: &Args) -> Result<()> {
+    let storage = storage_dir(args)?;
+    std::fs::create_dir_all(&storage).context(error::IoSnafu)?;
+    let socket = storage.join(SOCKET);
+    if socket.exists() {
+        if UnixStream::connect(&socket).await.is_ok() {
+            return error::DaemonRunningSnafu { path: socket }.fail();
+        }
+        // Left behind by a daemon that did not shut down cleanly
+        std::fs::remove_file(&socket).context(error::IoSnafu)?;
+    }
+    let verbosity = if args.trace {
+        LogVerbosity::Trace
+    } else if args.debug {
+        LogVerbosity::Debug
+    } else {
+        LogVerbosity::Quiet
+    };
+    let log = LogManager::init(storage.join("logs"), verbosity).await?;
+    let mut warm = Warm {
+        args: args.clone(),
+        storage,
+        project: std::env::current_dir().context(error::IoSnafu)?,
+        log,
+        ctx: None,
+        fingerprint: None,
+        error: None,
+        loads: 0,
+        requests: 0,
+        started: Instant::now(),
+    };
+    // Load before listening, so the first request finds the project warm
+    let _ = warm.context().await;
+
+    let listener = UnixListener::bind(&socket).context(error::IoSnafu)?;
+    let warm = Arc::new(Mutex::new(warm));
+    let stop = Arc::new(Notify::new());
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let warm = warm.clone();
+                    let stop = stop.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle(stream, warm, stop).await {
+                            tracing::warn!("failed to serve a daemon request: {e}");
+                        }
+                    });
+                }
+                Err(e) => tracing::warn!("failed to accept a daemon connection: {e}"),
+            },
+            _ = stop.notified() => break,
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    let _ = std::fs::remove_file(&socket);
+    Ok(())
+}
+
+/// The project state the daemon keeps between requests.
+struct Warm {
+    args: Args,
+    storage: PathBuf,
+    project: PathBuf,
+    log: LogManager,
+    ctx: Option<Context>,
+    /// Fingerprint of the build files `ctx` or `error` came from.
+    fingerprint: Option<String>,
+    error: Option<String>,
+    loads: u64,
+    requests: u64,
+    started: Instant,
+}
+
+impl Warm {
+    /// The loaded project, reloaded first when its build files or lock
+    /// changed since the last load.
+    async fn context(&mut self) -> std::result::Result<Context, String> {
+        let fingerprint = ProjectIndex::fingerprint(&self.project).map_err(|e| e.to_string())?;
+        if self.fingerprint.as_ref() != Some(&fingerprint) {
+            // Release the old context's storage before opening it again
+            self.ctx = None;
+            self.loads += 1;
+            match self.load().await {
+                Ok(ctx) => {
+                    self.ctx = Some(ctx);
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+            // Loading writes the lock file the first time, which must not
+            // count as a change
+            self.fingerprint = ProjectIndex::fingerprint(&self.project).ok();
+        }
+        match (self.ctx.as_ref(), self.error.as_ref()) {
+            (Some(ctx), _) => Ok(ctx.clone()),
+            (None, Some(e)) => Err(e.clone()),
+            (None, None) => Err("the project is not loaded".to_string()),
+        }
+    }
+
+    async fn load(&self) -> Result<Context> {
+        let ctx = Context::init_with_log(
+            Some(&self.storage),
+            self.args.config.as_ref(),
+            HashMap::new(),
+            self.log.clone(),
+        )
+        .await?;
+        super::register(&ctx).await?;
+        ctx.load_project(true).await?;
+        Ok(ctx)
+    }
+
+    fn status(&self) -> Status {
+        Status {
+            pid: std::process::id(),
+            project: self.project.clone(),
+            loads: self.loads,
+            requests: self.requests,
+            uptime_s: self.started.elapsed().as_secs(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+async fn send(write: &mut OwnedWriteHalf, reply: &Reply) -> Result<()> {
+    let mut line = serde_json::to_string(reply).unwrap_or_default();
+    line.push('\n');
+    write
+        .write_all(line.as_bytes())
+        .await
+        .context(error::IoSnafu)
+}
+
+/// Serves one request, one at a time: runs share the warm context and its
+/// settings.
+async fn handle(stream: UnixStream, warm: Arc<Mutex<Warm>>, stop: Arc<Notify>) -> Result<()> {
+    let (read, mut write) = stream.into_split();
+    let Some(line) = BufReader::new(read)
+        .lines()
+        .next_line()
+        .await
+        .context(error::IoSnafu)?
+    else {
+        return Ok(());
+    };
+    let request: Request = match serde_json::from_str(&line) {
+        Ok(request) => request,
+        Err(e) => {
+            let error = Some(format!("malformed request: {e}"));
+            return send(&mut write, &Reply::Done { error }).await;
+        }
+    };
+    let mut warm = warm.lock().await;
+    match request.command {
+        Command::Status => {
+            let status = warm.status();
+            send(&mut write, &Reply::Status { status }).await?;
+            return send(&mut write, &Reply::Done { error: None }).await;
+        }
+        Command::Stop => {
+            send(&mut write, &Reply::Done { error: None }).await?;
+            stop.notify_one();
+            return Ok(());
+        }
+        _ => {}
+    }
+    if request.cwd != warm.project || request.config != warm.args.config {
+        let reason = format!(
+            "the daemon serves {} with config {:?}",
+            warm.project.display(),
+            warm.args.config
+        );
+        return send(&mut write, &Reply::Unavailable { reason }).await;
+    }
+    warm.requests += 1;
+    let ctx = match warm.context().await {
+        Ok(ctx) => ctx,
+        Err(e) => return send(&mut write, &Reply::Done { error: Some(e) }).await,
+    };
+    let error = match request.command {
+        Command::Run {
+            addr,
+            offline,
+            jobs,
+        } => match Addr::parse(&addr) {
+            Ok(addr) => run(&ctx, &mut write, &addr, offline, jobs).await?,
+            Err(e) => Some(e.to_string()),
+        },
+        _ => {
+            for line in ctx.transform_listing() {
+                send(&mut write, &Reply::Line { line }).await?;
+            }
+            None
+        }
+    };
+    send(&mut write, &Reply::Done { error }).await
+}
+
+/// Whether a run's event is sent to the client.
+fn forwarded(event: &Event) -> bool {
+    matches!(
+        event.kind,
+        EventKind::Cached { .. } | EventKind::Started { .. } | EventKind::Finished { .. }
+    )
+}
+
+/// Runs `addr` in the warm context, sending its progress to the client.
+/// Returns the error the run failed with.
+async fn run(
+    ctx: &Context,
+    write: &mut OwnedWriteHalf,
+    addr: &Addr,
+    offline: bool,
+    jobs: Option<u64>,
+) -> Result<Option<String>> {
+    ctx.storage().set_offline(offline).await;
+    ctx.scheduler().set_jobs(jobs);
+    let mut events = ctx.events().subscribe();
+    let result = {
+        let run = ctx.run(addr);
+        tokio::pin!(run);
+        loop {
+            tokio::select! {
+                result = &mut run => break Ok(result),
+                event = events.recv() => match event {
+                    Ok(event) if forwarded(&event) => {
+                        // A client that went away cancels its run
+                        if let Err(e) = send(write, &Reply::Event { event }).await {
+                            break Err(e);
+                        }
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break Ok((&mut run).await),
+                },
+            }
+        }
+    };
+    // The next request starts from the defaults again
+    ctx.storage().set_offline(false).await;
+    ctx.scheduler().set_jobs(None);
+    let result = result?;
+    loop {
+        match events.try_recv() {
+            Ok(event) if forwarded(&event) => send(write, &Reply::Event { event }).await?,
+            Ok(_) | Err(TryRecvError::Lagged(_)) => {}
+            Err(_) => break,
+        }
+    }
+    Ok(result.err().map(|e| e.to_string()))
+}
+
+/// Connects to the daemon of this storage directory and sends it `command`.
+async fn connect(args: &Args, command: Command) -> Result<Option<Lines<BufReader<OwnedReadHalf>>>> {
+    let socket = storage_dir(args)?.join(SOCKET);
+    if !socket.exists() {
+        return Ok(None);
+    }
+    let Ok(stream) = UnixStream::connect(&socket).await else {
+        return Ok(None);
+    };
+    let (read, mut write) = stream.into_split();
+    let request = Request {
+        cwd: std::env::current_dir().context(error::IoSnafu)?,
+        config: args.config.clone(),
+        command,
+    };
+    let mut line = serde_json::to_string(&request).unwrap_or_default();
+    line.push('\n');
+    write
+        .write_all(line.as_bytes())
+        .await
+        .context(error::IoSnafu)?;
+    Ok(Some(BufReader::new(read).lines()))
+}
+
+/// Asks the running daemon for its status.
+async fn query(args: &Args) -> Result<Option<Status>> {
+    let Some(mut replies) = connect(args, Command::Status).await? else {
+        return Ok(None);
+    };
+    while let Some(line) = replies.next_line().await.context(error::IoSnafu)? {
+        if let Ok(Reply::Status { status }) = serde_json::from_str(&line) {
+            return Ok(Some(status));
+        }
+    }
+    Ok(None)
+}
+
+/// Hands `command` to the daemon serving this project and prints what it
+/// answers. Returns `false` when no daemon can serve it, so the caller does
+/// the work itself.
+pub(super) async fn delegate(args: &Args, command: Command) -> Result<bool> {
+    // Edo running inside a transform never hands its work to a daemon
+    if std::env::var_os(NO_DAEMON).is_some() || parent_cache().is_some() {
+        return Ok(false);
+    }
+    let Some(mut replies) = connect(args, command).await? else {
+        return Ok(false);
+    };
+    let logs = storage_dir(args)?.join("logs");
+    let mut log_names: HashMap<Addr, String> = HashMap::new();
+    while let Some(line) = replies.next_line().await.context(error::IoSnafu)? {
+        let reply: Reply = serde_json::from_str(&line).map_err(|e| {
+            error::DaemonSnafu {
+                message: format!("malformed reply: {e}"),
+            }
+            .build()
+        })?;
+        match reply {
+            Reply::Line { line } => println!("{line}"),
+            Reply::Event { event } => match event.kind {
+                EventKind::Cached { addr } => println!("cached  {addr}"),
+                EventKind::Started { addr, log, .. } => {
+                    log_names.insert(addr, log);
+                }
+                EventKind::Finished {
+                    addr,
+                    outcome,
+                    duration_ms,
+                } => {
+                    let seconds = duration_ms as f64 / 1000.0;
+                    // Padding needs the string, the outcome ignores widths
+                    let status = outcome.to_string();
+                    match (outcome, log_names.get(&addr)) {
+                        (NodeOutcome::Failed, Some(log)) => println!(
+                            "{status:<7} {addr} ({seconds:.1}s), see {}",
+                            logs.join(log).display()
+                        ),
+                        _ => println!("{status:<7} {addr} ({seconds:.1}s)"),
+                    }
+                }
+                _ => {}
+            },
+            Reply::Status { status } => print_status(&status),
+            Reply::Unavailable { reason } => {
+                tracing::debug!("running without the daemon: {reason}");
+                return Ok(false);
+            }
+            Reply::Done { error } => {
+                return match error {
+                    Some(message) => error::DaemonSnafu { message }.fail(),
+                    None => Ok(true),
+                };
+            }
+        }
+    }
+    error::DaemonSnafu {
+        message: "the daemon stopped before finishing the request".to_string(),
+    }
+    .fail()
+}
+
+fn print_status(status: &Status) {
+    println!("pid:      {}", status.pid);
+    println!("project:  {}", status.project.display());
+    println!("loads:    {}", status.loads);
+    println!("requests: {}", status.requests);
+    println!("uptime:   {}s", status.uptime_s);
+    if let Some(e) = status.error.as_ref() {
+        println!("error:    {e}");
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use console::{Term, style, truncate_str};
+use edo::context::{Event, NodeState, RunState, format_eta};
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::oneshot;
+
+/// How often the screen is redrawn.
+const REFRESH: Duration = Duration::from_millis(200);
+/// Output lines kept for each running transform.
+const TAIL: usize = 3;
+/// Finished transforms listed under "recent".
+const RECENT: usize = 5;
+
+/// Lays the dashboard for `state` out for a terminal `width` columns wide
+/// and `height` rows tall.
+fn render(state: &RunState, started: Instant, width: usize, height: usize) -> Vec<String> {
+    let total = state.nodes.len();
+    let done = state.nodes.values().filter(|x| x.state.is_done()).count();
+    let (cached, built, failed) = (
+        state.count(NodeState::Cached),
+        state.count(NodeState::Built),
+        state.count(NodeState::Failed),
+    );
+    let running = state.running();
+    let targets = state
+        .targets
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut lines = vec![
+        format!(
+            "{} {targets}  {}",
+            style("edo").bold(),
+            style(elapsed(started.elapsed())).dim()
+        ),
+        format!(
+            "{} {done}/{total}  running {}  built {}  cached {}  failed {}",
+            bar(done, total, 30),
+            running.len(),
+            style(built).green(),
+            style(cached).cyan(),
+            if failed > 0 {
+                style(failed).red().bold()
+            } else {
+                style(failed)
+            },
+        ),
+    ];
+
+    let mut levels: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+    for node in state.nodes.values() {
+        let level = levels.entry(node.level).or_default();
+        level.1 += 1;
+        if node.state.is_done() {
+            level.0 += 1;
+        }
+    }
+    lines.push(format!(
+        "levels {}",
+        levels
+            .iter()
+            .map(|(level, (done, total))| format!("{level}:{done}/{total}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+    ));
+    let finished = cached + built + failed;
+    if let Some(rate) = (cached * 100).checked_div(finished) {
+        lines.push(format!(
+            "cache  {cached} of {finished} served from the cache ({rate}%)"
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push(style("running").bold().to_string());
+    let now = Utc::now();
+    for (addr, node) in running {
+        let since = node
+            .started
+            .and_then(|x| (now - x).to_std().ok())
+            .unwrap_or_default();
+        let eta = match node.estimate_ms.map(Duration::from_millis) {
+            Some(expected) => match expected.checked_sub(since) {
+                Some(left) => format!("  eta {}", format_eta(left)),
+                None => "  over estimate".to_string(),
+            },
+            None => String::new(),
+        };
+        lines.push(format!(
+            "  {addr}  {}",
+            style(format!("{}{eta}", elapsed(since))).dim()
+        ));
+        for line in node.tail.iter() {
+            lines.push(format!("    {}", style(line).dim()));
+        }
+    }
+    let recent: Vec<_> = state
+        .finished_order
+        .iter()
+        .rev()
+        .filter_map(|x| state.nodes.get(x).map(|node| (x, node)))
+        .filter(|x| x.1.state != NodeState::Cached)
+        .take(RECENT)
+        .collect();
+    if !recent.is_empty() {
+        lines.push(String::new());
+        lines.push(style("recent").bold().to_string());
+        for (addr, node) in recent {
+            let mark = match node.state {
+                NodeState::Failed => style("✘").red(),
+                _ => style("✔").green(),
+            };
+            lines.push(format!(
+                "  {mark} {addr}  {:.1}s",
+                node.duration_ms.unwrap_or_default() as f64 / 1000.0
+            ));
+        }
+    }
+    lines.truncate(height);
+    lines
+        .into_iter()
+        .map(|x| truncate_str(&x, width, "…").to_string())
+        .collect()
+}
+
+/// One line describing the finished run.
+fn summary(state: &RunState, started: Instant) -> String {
+    let total = state.nodes.len();
+    format!(
+        "{total} transform{} in {}: {} built, {} cached, {} failed",
+        if total == 1 { "" } else { "s" },
+        elapsed(started.elapsed()),
+        state.count(NodeState::Built),
+        state.count(NodeState::Cached),
+        state.count(NodeState::Failed),
+    )
+}
+
+fn bar(done: usize, total: usize, width: usize) -> String {
+    let filled = (done * width).checked_div(total).unwrap_or_default();
+    format!(
+        "[{}{}]",
+        style("█".repeat(filled)).green(),
+        "░".repeat(width - filled)
+    )
+}
+
+fn elapsed(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+/// A full screen view of a run, drawn on the alternate screen of stderr
+/// from the context's events until [`Dashboard::finish`] is called.
+///
+/// It is drawn from its own thread, as commands block the runtime the build
+/// runs on.
+pub struct Dashboard {
+    started: Instant,
+    stop: oneshot::Sender<()>,
+    handle: Option<std::thread::JoinHandle<RunState>>,
+}
+
+impl Dashboard {
+    pub fn start(events: Receiver<Event>) -> Self {
+        let started = Instant::now();
+        let (stop, stopped) = oneshot::channel();
+        let handle = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .and_then(|runtime| {
+                std::thread::Builder::new()
+                    .name("edo-dashboard".to_string())
+                    .spawn(move || runtime.block_on(draw(events, started, stopped)))
+            })
+            .ok();
+        Self {
+            started,
+            stop,
+            handle,
+        }
+    }
+
+    /// Restores the screen and prints a summary of the run.
+    pub async fn finish(self) {
+        let _ = self.stop.send(());
+        let Some(handle) = self.handle else {
+            return;
+        };
+        if let Ok(Ok(state)) = tokio::task::spawn_blocking(move || handle.join()).await {
+            let term = Term::stderr();
+            let _ = term.write_line(&summary(&state, self.started));
+            for (addr, node) in state.nodes.iter() {
+                if node.state == NodeState::Failed {
+                    let _ = term.write_line(&format!("  {} {addr}", style("failed").red()));
+                }
+            }
+        }
+    }
+}
+
+async fn draw(
+    mut events: Receiver<Event>,
+    started: Instant,
+    mut stopped: oneshot::Receiver<()>,
+) -> RunState {
+    let mut term = Term::stderr();
+    let mut state = RunState::new(TAIL);
+    let _ = term.write_all(b"\x1b[?1049h");
+    let _ = term.hide_cursor();
+    let mut tick = tokio::time::interval(REFRESH);
+    loop {
+        tokio::select! {
+            _ = &mut stopped => break,
+            event = events.recv() => match event {
+                Ok(event) => state.apply(&event),
+                // A missed output line is not worth stopping over
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            },
+            _ = tick.tick() => {
+                let (height, width) = term.size();
+                let frame = render(&state, started, width as usize, height as usize);
+                // Overwrite in place rather than clearing, which flickers
+                let _ = term.write_str(&format!("\x1b[H{}\x1b[J", frame.join("\x1b[K\n")));
+                let _ = term.flush();
+            }
+        }
+    }
+    while let Ok(event) = events.try_recv() {
+        state.apply(&event);
+    }
+    let _ = term.show_cursor();
+    let _ = term.write_all(b"\x1b[?1049l");
+    let _ = term.flush();
+    state
+}
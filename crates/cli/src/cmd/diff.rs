@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::Result;
+use crate::cmd::util::{Target, decompress, resolve_tag};
+use crate::error;
+use clap::Parser;
+use edo::context::Context;
+use edo::storage::{Artifact, Layer, MediaType};
+use futures::StreamExt;
+use snafu::{OptionExt, ResultExt};
+use tokio::io::{AsyncReadExt, BufReader};
+use tokio_tar::Archive;
+
+use crate::Args;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Compare the contents and metadata of two artifacts", long_about = None)]
+pub struct Diff {
+    /// Transform address (`//project/name`), artifact id, or alias (`name:tag`)
+    left: String,
+    /// Transform address (`//project/name`), artifact id, or alias (`name:tag`)
+    right: String,
+    #[clap(long = "arg", short = 'a', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
+    args: Option<Vec<(String, String)>>,
+}
+
+impl Diff {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let variables = self
+            .args
+            .clone()
+            .map(HashMap::from_iter)
+            .unwrap_or_default();
+        let left = Target::parse(&self.left)?;
+        let right = Target::parse(&self.right)?;
+        // Only evaluate the project when an address has to be resolved
+        let ctx = if left.needs_project() || right.needs_project() {
+            super::create_context(&args, variables, true).await?
+        } else {
+            super::init_context(&args, variables).await?
+        };
+        let left = resolve(&ctx, &left).await?;
+        let right = resolve(&ctx, &right).await?;
+        let lines = compare(&ctx, &left, &right).await?;
+
+        println!("--- {}", left.config().id());
+        println!("+++ {}", right.config().id());
+        if lines.is_empty() {
+            println!("artifacts are identical");
+        }
+        for line in lines {
+            println!("{line}");
+        }
+        Ok(())
+    }
+}
+
+/// Compare two artifacts, returning one line per difference
+///
+/// Layers are paired by position. Tar layers whose digests differ are
+/// opened from the local cache and compared entry by entry.
+pub(crate) async fn compare(
+    ctx: &Context,
+    left: &Artifact,
+    right: &Artifact,
+) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    diff_config(left, right, &mut lines);
+    let count = left.layers().len().max(right.layers().len());
+    for index in 0..count {
+        match (left.layers().get(index), right.layers().get(index)) {
+            (Some(a), Some(b)) => diff_layer(ctx, index, a, b, &mut lines).await?,
+            (Some(a), None) => lines.push(format!("- layer {index}: {}", describe(a))),
+            (None, Some(b)) => lines.push(format!("+ layer {index}: {}", describe(b))),
+            (None, None) => {}
+        }
+    }
+    Ok(lines)
+}
+
+/// Resolve an address, artifact id or alias to an artifact in the local or build cache
+async fn resolve(ctx: &Context, target: &Target) -> Result<Artifact> {
+    let id = match target {
+        Target::Addr(addr) => {
+            let transform = ctx
+                .get_transform(addr)
+                .context(error::UnknownTransformSnafu {
+                    addr: addr.to_string(),
+                })?;
+            transform.get_unique_id(&ctx.get_handle()).await?
+        }
+        Target::Id(id) => id.clone(),
+        Target::Tag(tag) => resolve_tag(ctx.storage(), tag).await?,
+    };
+    ctx.storage()
+        .find_build(&id, true)
+        .await?
+        .context(error::ArtifactNotFoundSnafu { id: id.to_string() })
+}
+
+fn describe(layer: &Layer) -> String {
+    format!(
+        "{} {} ({} bytes)",
+        layer.media_type(),
+        layer.digest(),
+        layer.size()
+    )
+}
+
+fn diff_config(left: &Artifact, right: &Artifact, lines: &mut Vec<String>) {
+    let (a, b) = (left.config(), right.config());
+    for provide in a.provides().difference(b.provides()) {
+        lines.push(format!("- provides {provide}"));
+    }
+    for provide in b.provides().difference(a.provides()) {
+        lines.push(format!("+ provides {provide}"));
+    }
+    let mut before = BTreeMap::new();
+    let mut after = BTreeMap::new();
+    flatten(
+        "requires",
+        &serde_json::to_value(a.requires()).unwrap_or_default(),
+        &mut before,
+    );
+    flatten(
+        "requires",
+        &serde_json::to_value(b.requires()).unwrap_or_default(),
+        &mut after,
+    );
+    flatten("metadata", a.metadata(), &mut before);
+    flatten("metadata", b.metadata(), &mut after);
+    diff_maps(&before, &after, lines, |key, value| {
+        format!("{key} = {value}")
+    });
+}
+
+/// Flatten a JSON value into `path = value` pairs so nested changes are reported individually
+fn flatten(prefix: &str, value: &serde_json::Value, out: &mut BTreeMap<String, String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                flatten(&format!("{prefix}.{key}"), value, out);
+            }
+        }
+        serde_json::Value::Null => {}
+        value => {
+            out.insert(prefix.to_string(), value.to_string());
+        }
+    }
+}
+
+fn diff_maps<V: PartialEq>(
+    before: &BTreeMap<String, V>,
+    after: &BTreeMap<String, V>,
+    lines: &mut Vec<String>,
+    show: impl Fn(&str, &V) -> String,
+) {
+    for (key, value) in before {
+        match after.get(key) {
+            None => lines.push(format!("- {}", show(key, value))),
+            Some(other) if other != value => {
+                lines.push(format!("- {}", show(key, value)));
+                lines.push(format!("+ {}", show(key, other)));
+            }
+            Some(_) => {}
+        }
+    }
+    for (key, value) in after {
+        if !before.contains_key(key) {
+            lines.push(format!("+ {}", show(key, value)));
+        }
+    }
+}
+
+async fn diff_layer(
+    ctx: &Context,
+    index: usize,
+    left: &Layer,
+    right: &Layer,
+    lines: &mut Vec<String>,
+) -> Result<()> {
+    if left.digest() == right.digest() && left.media_type() == right.media_type() {
+        return Ok(());
+    }
+    lines.push(format!("- layer {index}: {}", describe(left)));
+    lines.push(format!("+ layer {index}: {}", describe(right)));
+    if let (Some(before), Some(after)) = (list(ctx, left).await?, list(ctx, right).await?) {
+        diff_maps(&before, &after, lines, |path, entry| {
+            format!("  {path} {entry}")
+        });
+    }
+    Ok(())
+}
+
+/// A single entry inside a tar layer
+#[derive(PartialEq)]
+struct Entry {
+    kind: String,
+    size: u64,
+    mode: u32,
+    mtime: u64,
+    digest: String,
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "({}, {} bytes, mode {:o}, mtime {}, blake3:{})",
+            self.kind, self.size, self.mode, self.mtime, self.digest
+        )
+    }
+}
+
+/// List the entries of a tar layer, or `None` for layers that are not tarballs
+async fn list(ctx: &Context, layer: &Layer) -> Result<Option<BTreeMap<String, Entry>>> {
+    let MediaType::Tar(compression) = layer.media_type() else {
+        return Ok(None);
+    };
+    let reader = BufReader::new(ctx.storage().safe_read(layer).await?);
+    let mut archive = Archive::new(decompress(reader, compression));
+    let mut entries = archive.entries().context(error::IoSnafu)?;
+    let mut listing = BTreeMap::new();
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry.context(error::IoSnafu)?;
+        let path = entry
+            .path()
+            .context(error::IoSnafu)?
+            .to_string_lossy()
+            .to_string();
+        let header = entry.header();
+        let kind = format!("{:?}", header.entry_type()).to_lowercase();
+        let size = header.size().context(error::IoSnafu)?;
+        let mode = header.mode().context(error::IoSnafu)?;
+        let mtime = header.mtime().context(error::IoSnafu)?;
+        let mut hasher = blake3::Hasher::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = entry.read(&mut buffer).await.context(error::IoSnafu)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        listing.insert(
+            path,
+            Entry {
+                kind,
+                size,
+                mode,
+                mtime,
+                digest: hasher.finalize().to_hex().to_string(),
+            },
+        );
+    }
+    Ok(Some(listing))
+}
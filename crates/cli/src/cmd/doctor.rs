@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use crate::Args;
+use crate::Result;
+use crate::error;
+use clap::Parser;
+use edo::context::{ContextError, HealthCheck, HealthReport, HealthStatus, LogVerbosity};
+use snafu::ensure;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Check the caches, container runtimes and registries builds rely on", long_about = None)]
+pub struct Doctor {
+    /// Print the checks as JSON
+    #[clap(long)]
+    json: bool,
+}
+
+impl Doctor {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        // The report says what went wrong, log lines would only repeat it
+        let ctx = super::init_context_with(&args, HashMap::default(), LogVerbosity::Quiet).await?;
+        // A project that does not load is reported, the caches and farms it
+        // managed to register are still checked
+        let project = match ctx.load_project(true).await {
+            Ok(()) => HealthCheck::ok(
+                "load",
+                format!("{} transforms", ctx.transform_addrs().len()),
+            ),
+            Err(ContextError::DependencyChange) => HealthCheck::warning(
+                "lock file",
+                "dependencies changed since edo.lock.json was written",
+                "run `edo update`",
+            ),
+            Err(e) => HealthCheck::error(
+                "load",
+                e.to_string(),
+                "fix the build files, `edo lsp` and `edo fmt --check` point at the problem",
+            ),
+        };
+        let mut reports = vec![HealthReport {
+            subject: "project".to_string(),
+            checks: vec![project],
+        }];
+        reports.extend(ctx.health().await);
+
+        if self.json {
+            println!("{:#}", serde_json::to_value(&reports).unwrap_or_default());
+        } else {
+            for report in reports.iter() {
+                println!("{}", report.subject);
+                for check in report.checks.iter() {
+                    println!(
+                        "  {:<8} {}: {}",
+                        check.status.to_string(),
+                        check.name,
+                        check.detail
+                    );
+                    if let Some(remedy) = check.remedy.as_ref() {
+                        println!("  {:<8} fix: {remedy}", "");
+                    }
+                }
+            }
+        }
+        let count = |status| {
+            reports
+                .iter()
+                .flat_map(|x| x.checks.iter())
+                .filter(|x| x.status == status)
+                .count()
+        };
+        let errors = count(HealthStatus::Error);
+        if !self.json {
+            println!(
+                "\n{errors} errors, {} warnings",
+                count(HealthStatus::Warning)
+            );
+        }
+        ensure!(errors == 0, error::UnhealthySnafu { count: errors });
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use crate::Args;
+use crate::Result;
+use crate::error;
+use clap::{Parser, ValueEnum};
+use edo::context::{Addr, LogVerbosity, query_xml};
+use serde_json::json;
+use snafu::OptionExt;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// The format of `bazel query --output=xml`
+    BazelQueryXml,
+    /// One object per transform
+    Json,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Describe transforms' inputs, outputs and dependencies for other build analysis tools", long_about = None)]
+pub struct ExportTargets {
+    /// Transforms to describe with their dependencies, every transform when omitted
+    addrs: Vec<String>,
+    #[clap(long, value_enum, default_value = "json")]
+    format: Format,
+    #[clap(long = "arg", short = 'a', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
+    args: Option<Vec<(String, String)>>,
+}
+
+impl ExportTargets {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let variables = self
+            .args
+            .clone()
+            .map(HashMap::from_iter)
+            .unwrap_or_default();
+        // Stdout carries the description, nothing else may be written to it
+        let ctx = super::init_context_with(&args, variables, LogVerbosity::Quiet).await?;
+        ctx.load_project(true).await?;
+        let mut targets = Vec::new();
+        for addr in self.addrs.iter() {
+            let addr = ctx.resolve_alias(&Addr::parse(addr)?);
+            if ctx.get_transform(&addr).is_some() {
+                targets.push(addr);
+            } else {
+                targets.extend(
+                    ctx.get_matrix(&addr)
+                        .context(error::UnknownTransformSnafu {
+                            addr: addr.to_string(),
+                        })?,
+                );
+            }
+        }
+        if self.addrs.is_empty() {
+            targets = ctx.transform_addrs();
+        }
+        let described = ctx.export_targets(&targets).await?;
+        match self.format {
+            Format::BazelQueryXml => print!("{}", query_xml(&described)),
+            Format::Json => println!("{:#}", json!({ "targets": described })),
+        }
+        Ok(())
+    }
+}
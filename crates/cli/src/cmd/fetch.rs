@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use crate::Args;
+use crate::Result;
+use clap::Parser;
+use edo::context::Addr;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Fetch everything needed to build transforms without building them", long_about = None)]
+pub struct Fetch {
+    /// Transforms to fetch for, every transform in the project when omitted
+    addrs: Vec<String>,
+    #[clap(long = "arg", short = 'a', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
+    args: Option<Vec<(String, String)>>,
+}
+
+impl Fetch {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let ctx = super::create_context(
+            &args,
+            self.args
+                .clone()
+                .map(HashMap::from_iter)
+                .unwrap_or_default(),
+            true,
+        )
+        .await?;
+        let targets = if self.addrs.is_empty() {
+            ctx.transform_addrs()
+        } else {
+            self.addrs
+                .iter()
+                .map(|x| Addr::parse(x))
+                .collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        ctx.fetch(&targets).await?;
+        println!("fetched {} transforms into the local cache", targets.len());
+        Ok(())
+    }
+}
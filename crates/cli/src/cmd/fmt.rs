@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use crate::Args;
+use crate::Result;
+use crate::error;
+use clap::Parser;
+use edo::context::{build_file_paths, format_build_file};
+use snafu::{ResultExt, ensure};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Format the project's build files", long_about = None)]
+pub struct Fmt {
+    /// Build files to format, every build file of the project by default
+    files: Vec<PathBuf>,
+    /// Only list the files that are not formatted, and fail if there are any
+    #[clap(long)]
+    check: bool,
+}
+
+impl Fmt {
+    pub async fn run(&self, _args: Args) -> Result<()> {
+        let files = if self.files.is_empty() {
+            let root = std::env::current_dir().context(error::IoSnafu)?;
+            build_file_paths(&root)?
+                .into_iter()
+                .map(|x| x.strip_prefix(&root).map(|x| x.to_path_buf()).unwrap_or(x))
+                .collect()
+        } else {
+            self.files.clone()
+        };
+        let mut unformatted = 0usize;
+        for path in files {
+            let text = tokio::fs::read_to_string(&path)
+                .await
+                .context(error::IoSnafu)?;
+            let Some(formatted) = format_build_file(&path, &text)
+                .context(error::FormatFileSnafu { path: path.clone() })?
+            else {
+                continue;
+            };
+            if formatted == text {
+                continue;
+            }
+            unformatted += 1;
+            println!("{}", path.display());
+            if !self.check {
+                tokio::fs::write(&path, formatted)
+                    .await
+                    .context(error::IoSnafu)?;
+            }
+        }
+        ensure!(
+            !self.check || unformatted == 0,
+            error::UnformattedSnafu { count: unformatted }
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::convert::Infallible;
+use std::fmt::{self, Write};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::Args;
+use crate::Result;
+use crate::error;
+use clap::Parser;
+use edo::context::{Addr, Context, LogVerbosity, NodeOutcome};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::json;
+use snafu::{OptionExt, ResultExt};
+use tokio::net::TcpListener;
+
+use super::serve::{json_response, respond};
+
+const PAGE: &str = include_str!("graph.html");
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Print the transform graph in DOT, or browse it", long_about = None)]
+pub struct Graph {
+    /// Transforms to draw with their dependencies, every transform when omitted
+    addrs: Vec<String>,
+    /// Serve an interactive view of the graph on this address (e.g. `127.0.0.1:7878`) instead
+    #[clap(long, value_name = "ADDR")]
+    serve: Option<SocketAddr>,
+    #[clap(long = "arg", short = 'a', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
+    args: Option<Vec<(String, String)>>,
+}
+
+/// Whether a transform's artifact would have to be built.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CacheState {
+    /// The build cache holds the artifact.
+    Cached,
+    /// The artifact has to be built.
+    Missing,
+    /// The artifact has to be built, and the last run failed to build it.
+    Failed,
+    /// The artifact id could not be computed.
+    Unknown,
+}
+
+impl fmt::Display for CacheState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cached => "cached",
+            Self::Missing => "missing",
+            Self::Failed => "failed",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+impl CacheState {
+    fn color(&self) -> &'static str {
+        match self {
+            Self::Cached => "#9ee6f2",
+            Self::Missing => "#dddddd",
+            Self::Failed => "#f5a3ab",
+            Self::Unknown => "#ffe08a",
+        }
+    }
+}
+
+/// A transform in the graph.
+#[derive(Debug)]
+struct GraphNode {
+    /// Transforms that must be built first.
+    depends: Vec<Addr>,
+    /// Content-addressed id of the transform's artifact.
+    id: Option<String>,
+    state: CacheState,
+    /// Why the last run failed to build the transform, or why its id could
+    /// not be computed.
+    error: Option<String>,
+}
+
+impl Graph {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let variables = self
+            .args
+            .clone()
+            .map(HashMap::from_iter)
+            .unwrap_or_default();
+        // Stdout carries the DOT output, nothing else may be written to it
+        let ctx = super::init_context_with(&args, variables, LogVerbosity::Quiet).await?;
+        ctx.load_project(true).await?;
+        let mut targets = Vec::new();
+        for addr in self.addrs.iter() {
+            let addr = ctx.resolve_alias(&Addr::parse(addr)?);
+            if ctx.get_transform(&addr).is_some() {
+                targets.push(addr);
+            } else {
+                targets.extend(
+                    ctx.get_matrix(&addr)
+                        .context(error::UnknownTransformSnafu {
+                            addr: addr.to_string(),
+                        })?,
+                );
+            }
+        }
+        if self.addrs.is_empty() {
+            targets = ctx.transform_addrs();
+        }
+        let nodes = walk(&ctx, &targets).await?;
+        match self.serve {
+            Some(addr) => serve(addr, nodes).await,
+            None => {
+                print!("{}", dot(&nodes));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Collects `targets` and everything they depend on, with the cache state of
+/// each.
+async fn walk(ctx: &Context, targets: &[Addr]) -> Result<BTreeMap<Addr, GraphNode>> {
+    // Only failures of artifacts that did not change since count
+    let failures: HashMap<Addr, (Option<String>, Option<String>)> = ctx
+        .runs()
+        .latest()
+        .await?
+        .map(|run| {
+            run.nodes
+                .into_iter()
+                .filter(|x| x.outcome == NodeOutcome::Failed)
+                .map(|x| (x.addr, (x.id, x.error)))
+                .collect()
+        })
+        .unwrap_or_default();
+    let handle = ctx.get_handle();
+    let mut nodes = BTreeMap::new();
+    let mut queue: VecDeque<Addr> = targets.iter().cloned().collect();
+    while let Some(addr) = queue.pop_front() {
+        if nodes.contains_key(&addr) {
+            continue;
+        }
+        let transform = ctx
+            .get_transform(&addr)
+            .context(error::UnknownTransformSnafu {
+                addr: addr.to_string(),
+            })?;
+        let depends: Vec<Addr> = transform
+            .depends()
+            .await?
+            .iter()
+            .map(|x| ctx.resolve_alias(x))
+            .collect();
+        queue.extend(depends.iter().cloned());
+        let node = match handle.unique_id(&addr, &transform).await {
+            Ok(id) => {
+                let cached = ctx.storage().find_build(&id, true).await?.is_some();
+                let id = id.to_string();
+                let failure = failures
+                    .get(&addr)
+                    .filter(|(failed, _)| failed.as_ref() == Some(&id));
+                let (state, error) = if cached {
+                    (CacheState::Cached, None)
+                } else if let Some((_, error)) = failure {
+                    (CacheState::Failed, error.clone())
+                } else {
+                    (CacheState::Missing, None)
+                };
+                GraphNode {
+                    depends,
+                    id: Some(id),
+                    state,
+                    error,
+                }
+            }
+            Err(e) => GraphNode {
+                depends,
+                id: None,
+                state: CacheState::Unknown,
+                error: Some(e.to_string()),
+            },
+        };
+        nodes.insert(addr, node);
+    }
+    Ok(nodes)
+}
+
+/// Renders the graph in Graphviz DOT, with edges pointing from a dependency
+/// to the transforms that depend on it.
+fn dot(nodes: &BTreeMap<Addr, GraphNode>) -> String {
+    let mut out = String::from("digraph edo {\n  node [shape=box, style=filled];\n");
+    for (addr, node) in nodes.iter() {
+        let _ = writeln!(
+            out,
+            "  \"{addr}\" [fillcolor=\"{}\", tooltip=\"{}\"];",
+            node.state.color(),
+            node.state
+        );
+    }
+    for (addr, node) in nodes.iter() {
+        for dep in node.depends.iter() {
+            let _ = writeln!(out, "  \"{dep}\" -> \"{addr}\";");
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Serves the graph page and its JSON API until interrupted.
+async fn serve(addr: SocketAddr, nodes: BTreeMap<Addr, GraphNode>) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .context(error::ServeGraphSnafu { addr })?;
+    let addr = listener
+        .local_addr()
+        .context(error::ServeGraphSnafu { addr })?;
+    // The same shape as the graph of `edo run --serve`
+    let graph: BTreeMap<String, &Vec<Addr>> = nodes
+        .iter()
+        .map(|(addr, node)| (addr.to_string(), &node.depends))
+        .collect();
+    let graph = Arc::new(json!(graph));
+    let nodes = Arc::new(json!(
+        nodes
+            .into_iter()
+            .map(|(addr, node)| (
+                addr.to_string(),
+                json!({
+                    "id": node.id,
+                    "state": node.state.to_string(),
+                    "error": node.error,
+                })
+            ))
+            .collect::<BTreeMap<_, _>>()
+    ));
+    println!("serving the graph on http://{addr}");
+    loop {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .context(error::ServeGraphSnafu { addr })?;
+        let graph = graph.clone();
+        let nodes = nodes.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |req| {
+                let response = if req.method() != Method::GET {
+                    respond(StatusCode::METHOD_NOT_ALLOWED, "text/plain", "".into())
+                } else {
+                    match req.uri().path() {
+                        "/" => respond(StatusCode::OK, "text/html; charset=utf-8", PAGE.into()),
+                        "/api/graph" => json_response(graph.as_ref().clone()),
+                        "/api/nodes" => json_response(nodes.as_ref().clone()),
+                        _ => respond(StatusCode::NOT_FOUND, "text/plain", "not found\n".into()),
+                    }
+                };
+                async move { Ok::<_, Infallible>(response) }
+            });
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::debug!("graph connection closed: {e}");
+            }
+        });
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::Args;
+use crate::Result;
+use crate::error;
+use clap::Parser;
+use snafu::{ResultExt, ensure};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Create a starter project", long_about = None)]
+pub struct Init {
+    /// Directory to create the project in
+    #[arg(default_value = ".")]
+    dir: PathBuf,
+    /// Template to create, as `<provider>:<template>` or the name of a builtin
+    /// template: `c` or `rust` built on this machine, or `image` built in a
+    /// container
+    #[arg(long, short = 't', default_value = "c")]
+    template: String,
+    /// Name of the sample package, and of the program it builds
+    #[arg(long, default_value = "hello")]
+    name: String,
+    /// Share built artifacts through this S3 bucket
+    #[arg(long, value_name = "BUCKET")]
+    s3_bucket: Option<String>,
+    /// Parameter passed to the template
+    #[clap(long = "param", short = 'p', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
+    params: Option<Vec<(String, String)>>,
+    /// List the available templates instead
+    #[clap(long)]
+    list: bool,
+}
+
+impl Init {
+    pub async fn run(&self, mut args: Args) -> Result<()> {
+        args.storage.get_or_insert_with(|| self.dir.join(".edo"));
+        let ctx = super::init_context(&args, HashMap::default()).await?;
+        if self.list {
+            for (template, info) in ctx.registry().template_list() {
+                println!("{template:<24} {}", info.description);
+            }
+            return Ok(());
+        }
+        let mut params: BTreeMap<String, String> = self
+            .params
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        params.insert("name".to_string(), self.name.clone());
+        if let Some(bucket) = self.s3_bucket.as_ref() {
+            params.insert("s3-bucket".to_string(), bucket.clone());
+        }
+        let files = ctx.registry().render_template(&self.template, &params)?;
+        // Never touch a file the user already has
+        for file in files.iter() {
+            let path = self.dir.join(&file.path);
+            ensure!(!path.exists(), error::ProjectExistsSnafu { path });
+        }
+        create_dir(&self.dir.join(".edo")).await?;
+        for file in files.iter() {
+            let path = self.dir.join(&file.path);
+            if let Some(parent) = path.parent() {
+                create_dir(parent).await?;
+            }
+            tokio::fs::write(&path, &file.contents)
+                .await
+                .context(error::IoSnafu)?;
+            println!("created {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+async fn create_dir(path: &Path) -> Result<()> {
+    tokio::fs::create_dir_all(path)
+        .await
+        .context(error::IoSnafu)
+}
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use crate::Result;
+use crate::error;
+use clap::Parser;
+use edo::context::Addr;
+use snafu::OptionExt;
+
+use crate::Args;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Show how a transform's artifact id is derived", long_about = None)]
+pub struct Inspect {
+    /// Transform address (`//project/name`)
+    addr: String,
+    /// Print every component of the cache key and its digest
+    #[clap(long)]
+    key: bool,
+    #[clap(long = "arg", short = 'a', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
+    args: Option<Vec<(String, String)>>,
+}
+
+impl Inspect {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let variables = self
+            .args
+            .clone()
+            .map(HashMap::from_iter)
+            .unwrap_or_default();
+        let ctx = super::create_context(&args, variables, true).await?;
+        let addr = Addr::parse(&self.addr)?;
+        let transform = ctx
+            .get_transform(&addr)
+            .context(error::UnknownTransformSnafu {
+                addr: self.addr.clone(),
+            })?;
+        let key = transform.cache_key(&ctx.get_handle()).await?;
+        if self.key {
+            println!("{key}");
+        } else {
+            println!("{}", key.id());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use crate::Args;
+use crate::Result;
+use clap::Parser;
+use edo::context::{Addr, LicenseReport, LogVerbosity};
+use serde_json::json;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Report the licenses of the sources a transform's artifact is built from", long_about = None)]
+pub struct Licenses {
+    /// Transform address (`//project/name`)
+    addr: String,
+    /// Print the report as JSON
+    #[clap(long)]
+    json: bool,
+    #[clap(long = "arg", short = 'a', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
+    args: Option<Vec<(String, String)>>,
+}
+
+impl Licenses {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let variables = self
+            .args
+            .clone()
+            .map(HashMap::from_iter)
+            .unwrap_or_default();
+        let ctx = super::init_context_with(&args, variables, LogVerbosity::Quiet).await?;
+        ctx.load_project(true).await?;
+        let report = ctx.license_report(&Addr::parse(&self.addr)?).await?;
+        if self.json {
+            println!("{:#}", json!(report));
+        } else {
+            print_report(&report);
+        }
+        Ok(())
+    }
+}
+
+fn print_report(report: &LicenseReport) {
+    let licenses: Vec<&str> = report.licenses.iter().map(|x| x.as_str()).collect();
+    println!("{} ({}): {}", report.addr, report.id, licenses.join(", "));
+    for source in report.sources.iter() {
+        let licenses: Vec<&str> = source.licenses.iter().map(|x| x.as_str()).collect();
+        println!(
+            "  {}: {} ({})",
+            source.addr,
+            licenses.join(", "),
+            source.origin
+        );
+    }
+    for violation in report.violations.iter() {
+        println!("  violation: {violation}");
+    }
+}
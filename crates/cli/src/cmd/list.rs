@@ -1,3 +1,4 @@
+use super::{Contexts, HostContexts};
 use crate::Args;
 use crate::Result;
 use clap::Parser;
@@ -5,12 +6,31 @@ use std::collections::HashMap;
 
 #[derive(Parser, Debug, Clone)]
 #[clap(version, about = "List all transforms", long_about = None)]
-pub struct List {}
+pub struct List {
+    /// Only list the transforms whose artifacts provide this capability
+    #[clap(long)]
+    provides: Option<String>,
+}
 
 impl List {
     pub async fn run(&self, args: Args) -> Result<()> {
-        let ctx = super::create_context(&args, HashMap::default(), true).await?;
-        ctx.print_transforms();
+        if self.provides.is_none() && super::delegate(&args, super::Command::List).await? {
+            return Ok(());
+        }
+        self.run_in(&HostContexts, args).await
+    }
+
+    /// Lists the transforms of a context built by `contexts`.
+    pub async fn run_in(&self, contexts: &impl Contexts, args: Args) -> Result<()> {
+        let ctx = super::create_context_in(contexts, &args, HashMap::default(), true).await?;
+        match self.provides.as_ref() {
+            Some(capability) => {
+                for addr in ctx.find_providing(capability) {
+                    println!("{addr}");
+                }
+            }
+            None => ctx.print_transforms(),
+        }
         Ok(())
     }
 }
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use crate::Args;
+use crate::Result;
+use crate::error;
+use clap::Parser;
+use edo::context::{Addr, LogVerbosity};
+use edo::scheduler::log_cache;
+use edo::storage::CacheSelector;
+use snafu::{OptionExt, ResultExt};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Print the shipped log of the build of a transform's current artifact", long_about = None)]
+pub struct Logs {
+    /// Transform address (`//project/name`)
+    addr: String,
+    /// Fetch the log uploaded by whichever machine built the artifact
+    /// instead of reading the local cache
+    #[clap(long)]
+    remote: bool,
+    /// Cache to fetch the log from with --remote: `build`, `output`,
+    /// `source:<name>` or the address of a cache (default: the
+    /// `[scheduler] upload_logs` cache, or `build`)
+    #[clap(long, value_parser = parse_cache)]
+    cache: Option<CacheSelector>,
+    #[clap(long = "arg", short = 'a', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
+    args: Option<Vec<(String, String)>>,
+}
+
+fn parse_cache(value: &str) -> std::result::Result<CacheSelector, String> {
+    value
+        .parse()
+        .map_err(|e: edo::storage::StorageError| e.to_string())
+}
+
+impl Logs {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let variables = self
+            .args
+            .clone()
+            .map(HashMap::from_iter)
+            .unwrap_or_default();
+        // Only the log itself is printed, so it can be piped
+        let ctx = super::init_context_with(&args, variables, LogVerbosity::Quiet).await?;
+        ctx.load_project(true).await?;
+        let addr = Addr::parse(&self.addr)?;
+        let transform = ctx
+            .get_transform(&addr)
+            .context(error::UnknownTransformSnafu {
+                addr: self.addr.clone(),
+            })?;
+        let id = ctx.get_handle().unique_id(&addr, &transform).await?;
+
+        // Logs are only kept once shipped, the log directory is emptied
+        // on every start
+        let cache = if self.remote {
+            self.cache
+                .clone()
+                .or_else(|| log_cache(ctx.config()))
+                .unwrap_or(CacheSelector::Build)
+        } else {
+            CacheSelector::Local
+        };
+        let mut reader =
+            ctx.storage()
+                .find_log(&id, &cache)
+                .await?
+                .context(error::LogNotFoundSnafu {
+                    addr,
+                    id: id.to_string(),
+                    cache: cache.to_string(),
+                })?;
+        tokio::io::copy(&mut reader, &mut tokio::io::stdout())
+            .await
+            .context(error::IoSnafu)?;
+        Ok(())
+    }
+}
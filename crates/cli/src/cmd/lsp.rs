@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::Args;
+use crate::Result;
+use clap::Parser;
+use edo::context::{
+    Completion, Context, Diagnostic, LogVerbosity, ProjectOutline, Severity, Span, completion_at,
+};
+use tower_lsp::jsonrpc;
+use tower_lsp::lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse, InitializeParams,
+    InitializeResult, InitializedParams, Location, OneOf, Position, Range, ServerCapabilities,
+    ServerInfo, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use tower_lsp::{Client, LanguageServer, LspService, Server};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Serve a language server for build files on stdio", long_about = None)]
+pub struct Lsp {}
+
+impl Lsp {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        // Stdout carries the protocol, nothing else may be written to it
+        let ctx = super::init_context_with(&args, HashMap::default(), LogVerbosity::Quiet).await?;
+        let (service, socket) = LspService::new(|client| Backend {
+            client,
+            ctx,
+            workspace: Mutex::new(Workspace::default()),
+        });
+        Server::new(tokio::io::stdin(), tokio::io::stdout(), socket)
+            .serve(service)
+            .await;
+        Ok(())
+    }
+}
+
+/// The build files of the open project, and the text each was outlined from.
+#[derive(Default)]
+struct Workspace {
+    project: ProjectOutline,
+    texts: HashMap<PathBuf, String>,
+}
+
+impl Workspace {
+    fn open(root: &Path) -> Self {
+        // A project that cannot be walked is served without files, they are
+        // added as the editor opens them
+        let project = ProjectOutline::scan(root).unwrap_or_else(|_| ProjectOutline::new(root));
+        let texts = project
+            .files()
+            .filter_map(|x| Some((x.to_path_buf(), std::fs::read_to_string(x).ok()?)))
+            .collect();
+        Self { project, texts }
+    }
+
+    /// Re-outlines the file at `path`, only the edited file is parsed again.
+    fn update(&mut self, path: &Path, text: String) {
+        if self.project.update(path, &text) {
+            self.texts.insert(path.to_path_buf(), text);
+        }
+    }
+
+    fn range(&self, path: &Path, span: &Span) -> Range {
+        let text = self.texts.get(path).map(String::as_str).unwrap_or_default();
+        Range::new(position(text, span.start), position(text, span.end))
+    }
+}
+
+struct Backend {
+    client: Client,
+    ctx: Context,
+    workspace: Mutex<Workspace>,
+}
+
+impl Backend {
+    /// Checks every build file, a change to one file can define or remove
+    /// addresses the others refer to.
+    async fn publish(&self) {
+        let diagnostics: Vec<(Url, Vec<tower_lsp::lsp_types::Diagnostic>)> = {
+            let workspace = self.workspace.lock().unwrap();
+            workspace
+                .project
+                .files()
+                .filter_map(|path| {
+                    let uri = Url::from_file_path(path).ok()?;
+                    let diagnostics = workspace
+                        .project
+                        .check(path, self.ctx.registry())
+                        .iter()
+                        .map(|x| to_lsp(&workspace, path, x))
+                        .collect();
+                    Some((uri, diagnostics))
+                })
+                .collect()
+        };
+        for (uri, diagnostics) in diagnostics {
+            self.client
+                .publish_diagnostics(uri, diagnostics, None)
+                .await;
+        }
+    }
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for Backend {
+    async fn initialize(&self, params: InitializeParams) -> jsonrpc::Result<InitializeResult> {
+        #[allow(deprecated)]
+        let root = params
+            .workspace_folders
+            .and_then(|x| x.into_iter().next())
+            .map(|x| x.uri)
+            .or(params.root_uri)
+            .and_then(|x| x.to_file_path().ok())
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        *self.workspace.lock().unwrap() = Workspace::open(&root);
+        Ok(InitializeResult {
+            capabilities: ServerCapabilities {
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                    TextDocumentSyncKind::FULL,
+                )),
+                definition_provider: Some(OneOf::Left(true)),
+                completion_provider: Some(CompletionOptions {
+                    trigger_characters: Some(vec!["\"".to_string(), "/".to_string()]),
+                    ..CompletionOptions::default()
+                }),
+                ..ServerCapabilities::default()
+            },
+            server_info: Some(ServerInfo {
+                name: "edo".to_string(),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            }),
+        })
+    }
+
+    async fn initialized(&self, _: InitializedParams) {
+        self.publish().await;
+    }
+
+    async fn shutdown(&self) -> jsonrpc::Result<()> {
+        Ok(())
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        if let Ok(path) = params.text_document.uri.to_file_path() {
+            self.workspace
+                .lock()
+                .unwrap()
+                .update(&path, params.text_document.text);
+            self.publish().await;
+        }
+    }
+
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        // Changes are requested in full, the last one holds the whole text
+        let Some(change) = params.content_changes.into_iter().last() else {
+            return;
+        };
+        if let Ok(path) = params.text_document.uri.to_file_path() {
+            self.workspace.lock().unwrap().update(&path, change.text);
+            self.publish().await;
+        }
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        // Unsaved edits are dropped, go back to what is on disk
+        let Ok(path) = params.text_document.uri.to_file_path() else {
+            return;
+        };
+        {
+            let mut workspace = self.workspace.lock().unwrap();
+            match std::fs::read_to_string(&path) {
+                Ok(text) => workspace.update(&path, text),
+                Err(_) => {
+                    workspace.project.remove(&path);
+                    workspace.texts.remove(&path);
+                }
+            }
+        }
+        self.publish().await;
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> jsonrpc::Result<Option<GotoDefinitionResponse>> {
+        let position = params.text_document_position_params;
+        let Ok(path) = position.text_document.uri.to_file_path() else {
+            return Ok(None);
+        };
+        let workspace = self.workspace.lock().unwrap();
+        let Some(text) = workspace.texts.get(&path) else {
+            return Ok(None);
+        };
+        let location = workspace
+            .project
+            .reference_at(&path, offset(text, position.position))
+            .and_then(|x| workspace.project.definition(&x.target))
+            .and_then(|(file, definition)| {
+                Some(Location::new(
+                    Url::from_file_path(file).ok()?,
+                    workspace.range(file, &definition.span),
+                ))
+            });
+        Ok(location.map(GotoDefinitionResponse::Scalar))
+    }
+
+    async fn completion(
+        &self,
+        params: CompletionParams,
+    ) -> jsonrpc::Result<Option<CompletionResponse>> {
+        let position = params.text_document_position;
+        let Ok(path) = position.text_document.uri.to_file_path() else {
+            return Ok(None);
+        };
+        let workspace = self.workspace.lock().unwrap();
+        let Some(text) = workspace.texts.get(&path) else {
+            return Ok(None);
+        };
+        let registry = self.ctx.registry();
+        let items: Vec<CompletionItem> = match completion_at(text, offset(text, position.position))
+        {
+            Some(Completion::Kind(component)) => registry
+                .kinds()
+                .into_iter()
+                .filter(|x| x.0 == component)
+                .map(|(component, kind)| CompletionItem {
+                    detail: registry.schema(&component, &kind).map(|x| x.description),
+                    kind: Some(CompletionItemKind::CLASS),
+                    ..CompletionItem::new_simple(kind, String::new())
+                })
+                .collect(),
+            Some(Completion::Address(component)) => workspace
+                .project
+                .addresses(&component)
+                .into_iter()
+                .map(|addr| CompletionItem {
+                    kind: Some(CompletionItemKind::REFERENCE),
+                    ..CompletionItem::new_simple(addr.to_string(), component.to_string())
+                })
+                .collect(),
+            None => return Ok(None),
+        };
+        Ok(Some(CompletionResponse::Array(items)))
+    }
+}
+
+fn to_lsp(
+    workspace: &Workspace,
+    path: &Path,
+    diagnostic: &Diagnostic,
+) -> tower_lsp::lsp_types::Diagnostic {
+    tower_lsp::lsp_types::Diagnostic {
+        range: workspace.range(path, &diagnostic.span),
+        severity: Some(match diagnostic.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+        }),
+        source: Some("edo".to_string()),
+        message: diagnostic.message.clone(),
+        ..tower_lsp::lsp_types::Diagnostic::default()
+    }
+}
+
+/// Byte offset of an editor position, which counts UTF-16 code units.
+fn offset(text: &str, position: Position) -> usize {
+    let mut start = 0;
+    for _ in 0..position.line {
+        match text[start..].find('\n') {
+            Some(end) => start += end + 1,
+            None => return text.len(),
+        }
+    }
+    let mut units = 0;
+    for (index, char) in text[start..].char_indices() {
+        if units >= position.character as usize || char == '\n' {
+            return start + index;
+        }
+        units += char.len_utf16();
+    }
+    text.len()
+}
+
+/// Editor position of a byte offset.
+fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map(|x| x + 1).unwrap_or(0);
+    Position::new(
+        before.matches('\n').count() as u32,
+        before[line_start..].encode_utf16().count() as u32,
+    )
+}
@@ -1,20 +1,68 @@
+mod cache;
 mod checkout;
+mod completions;
+mod contexts;
+mod daemon;
+mod dashboard;
+mod diff;
+mod doctor;
+mod export_targets;
+mod fetch;
+mod fmt;
+mod graph;
+mod init;
+mod inspect;
+mod licenses;
 mod list;
+mod logs;
+mod lsp;
 mod prune;
+mod publish;
 mod run;
+mod runs;
+mod schema;
+mod serve;
+mod status;
 mod update;
 mod util;
+mod vendor;
+mod verify_repro;
+mod warm;
+mod why;
 
 use std::collections::{BTreeMap, HashMap};
 
+pub use cache::*;
 pub use checkout::*;
+pub use completions::*;
+pub use contexts::*;
+pub use daemon::*;
+pub use diff::*;
+pub use doctor::*;
 use edo::context::Node;
 use edo::context::{Addr, Context, LogVerbosity};
 use edo_core::register_core;
+pub use export_targets::*;
+pub use fetch::*;
+pub use fmt::*;
+pub use graph::*;
+pub use init::*;
+pub use inspect::*;
+pub use licenses::*;
 pub use list::*;
+pub use logs::*;
+pub use lsp::*;
 pub use prune::*;
+pub use publish::*;
 pub use run::*;
+pub use runs::*;
+pub use schema::*;
+pub use status::*;
 pub use update::*;
+pub use vendor::*;
+pub use verify_repro::*;
+pub use warm::*;
+pub use why::*;
 
 use crate::Args;
 use crate::Result;
@@ -23,6 +71,34 @@ pub async fn create_context(
     args: &Args,
     variables: HashMap<String, String>,
     locked: bool,
+) -> Result<Context> {
+    create_context_in(&HostContexts, args, variables, locked).await
+}
+
+/// Like [`create_context`], with the context built by `contexts`.
+pub async fn create_context_in(
+    contexts: &impl Contexts,
+    args: &Args,
+    variables: HashMap<String, String>,
+    locked: bool,
+) -> Result<Context> {
+    let ctx = init_context_in(contexts, args, variables).await?;
+    // Now load the current project
+    ctx.load_project(locked).await?;
+    Ok(ctx)
+}
+
+/// Creates a context with all core components and the default farm registered,
+/// without loading the project.
+pub async fn init_context(args: &Args, variables: HashMap<String, String>) -> Result<Context> {
+    init_context_in(&HostContexts, args, variables).await
+}
+
+/// Like [`init_context`], with the context built by `contexts`.
+pub async fn init_context_in(
+    contexts: &impl Contexts,
+    args: &Args,
+    variables: HashMap<String, String>,
 ) -> Result<Context> {
     let verbosity = if args.trace {
         LogVerbosity::Trace
@@ -31,22 +107,27 @@ pub async fn create_context(
     } else {
         LogVerbosity::Info
     };
-    let ctx = Context::init(
-        args.storage.clone(),
-        args.config.clone(),
-        variables,
-        verbosity,
-    )
-    .await?;
+    contexts.init(args, variables, verbosity).await
+}
+
+/// Like [`init_context`], logging at `verbosity` regardless of the flags.
+pub async fn init_context_with(
+    args: &Args,
+    variables: HashMap<String, String>,
+    verbosity: LogVerbosity,
+) -> Result<Context> {
+    HostContexts.init(args, variables, verbosity).await
+}
+
+/// Registers the core component handlers and the default farm with `ctx`.
+async fn register(ctx: &Context) -> Result<()> {
     // Register all core component handlers
-    register_core(&ctx);
+    register_core(ctx);
     // Register a local farm in the project directory
     ctx.add_farm(
         &Addr::parse("//default").unwrap(),
         &Node::new_definition("environment", "local", "default", BTreeMap::new()),
     )
     .await?;
-    // Now load the current project
-    ctx.load_project(locked).await?;
-    Ok(ctx)
+    Ok(())
 }
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use crate::Result;
 use clap::Parser;
 
+use super::{Contexts, HostContexts};
 use crate::Args;
 
 #[derive(Parser, Debug, Clone)]
@@ -10,14 +11,19 @@ use crate::Args;
 pub struct Prune {
     #[arg(short, long)]
     all: bool,
-    // Prune the logs as well
+    // Prune the logs, and the run summaries pointing at them, as well
     #[arg(short, long)]
     logs: bool,
 }
 
 impl Prune {
     pub async fn run(&self, args: Args) -> Result<()> {
-        let ctx = super::create_context(&args, HashMap::default(), true).await?;
+        self.run_in(&HostContexts, args).await
+    }
+
+    /// Prunes a context built by `contexts`.
+    pub async fn run_in(&self, contexts: &impl Contexts, args: Args) -> Result<()> {
+        let ctx = super::create_context_in(contexts, &args, HashMap::default(), true).await?;
         // Prune the local cache
         if self.all {
             ctx.storage().prune_local_all().await?;
@@ -26,6 +32,7 @@ impl Prune {
         }
         if self.logs || self.all {
             ctx.log().clear().await?;
+            ctx.runs().clear().await?;
         }
         Ok(())
     }
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::Args;
+use crate::Result;
+use crate::error;
+use clap::Parser;
+use edo::context::{Addr, LogVerbosity};
+use edo::storage::publish_tags;
+use snafu::{OptionExt, ResultExt};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Publish a transform's current artifact to the output cache under human-friendly aliases", long_about = None)]
+pub struct Publish {
+    /// Transform address (`//project/name`)
+    addr: String,
+    /// Alias template to point at the artifact, such as
+    /// `myapp:{version}-{arch}` or `latest` (default: `[config.publish] tags`)
+    #[clap(long = "tag", short = 't', value_name = "TEMPLATE")]
+    tags: Vec<String>,
+    /// Write the license report of the artifact to this file as JSON
+    #[clap(long, value_name = "FILE")]
+    license_report: Option<PathBuf>,
+    #[clap(long = "arg", short = 'a', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
+    args: Option<Vec<(String, String)>>,
+}
+
+impl Publish {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let variables = self
+            .args
+            .clone()
+            .map(HashMap::from_iter)
+            .unwrap_or_default();
+        // Only the aliases are printed, so release tooling can read them
+        let ctx = super::init_context_with(&args, variables, LogVerbosity::Quiet).await?;
+        ctx.load_project(true).await?;
+        let addr = Addr::parse(&self.addr)?;
+        let transform = ctx
+            .get_transform(&addr)
+            .context(error::UnknownTransformSnafu {
+                addr: self.addr.clone(),
+            })?;
+        let id = ctx.get_handle().unique_id(&addr, &transform).await?;
+        // Artifacts built elsewhere are published from the build cache
+        ctx.storage()
+            .find_build(&id, true)
+            .await?
+            .context(error::ArtifactNotFoundSnafu { id: id.to_string() })?;
+
+        // Nothing is published under licenses the policy does not allow
+        let report = ctx.check_licenses(&addr).await?;
+        if let Some(path) = self.license_report.as_ref() {
+            std::fs::write(path, format!("{:#}", serde_json::json!(report)))
+                .context(error::IoSnafu)?;
+        }
+
+        let tags = if self.tags.is_empty() {
+            publish_tags(ctx.config())
+        } else {
+            self.tags.clone()
+        };
+        println!("{id}");
+        for tag in ctx.storage().publish(&id, &tags).await? {
+            println!("{tag}");
+        }
+        Ok(())
+    }
+}
@@ -1,32 +1,168 @@
 use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 use crate::Result;
+use crate::error;
 use clap::Parser;
-use edo::context::Addr;
+use edo::context::{Addr, LogVerbosity};
+use edo::scheduler::KeepWorkspace;
+use snafu::ensure;
 
+use super::dashboard::Dashboard;
+use super::serve::StatusServer;
+use super::{Contexts, HostContexts};
 use crate::Args;
 
 #[derive(Parser, Debug, Clone)]
 #[clap(version, about = "Run a transform", long_about = None)]
 pub struct Run {
-    addr: String,
+    #[clap(required_unless_present = "affected_by")]
+    addr: Option<String>,
+    /// Build every transform affected by the files changed in a git revision range (e.g. `main...HEAD`)
+    #[clap(long, value_name = "REV_RANGE", conflicts_with = "addr")]
+    affected_by: Option<String>,
+    /// Build only from the local cache, failing if a source has not been fetched
+    #[clap(long)]
+    offline: bool,
+    /// Run at most N transforms at once, overriding `[scheduler] workers`
+    #[clap(long, short = 'j', value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    jobs: Option<u64>,
+    /// Keep the workspace of failed transforms, or of every transform with `=always`, under `.edo/debug`
+    #[clap(
+        long,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "failed",
+        value_parser = ["failed", "always"]
+    )]
+    keep_workspace: Option<String>,
+    /// Show a full screen dashboard of the build, when stderr is a terminal
+    #[clap(long)]
+    ui: bool,
+    /// Serve a status page and JSON API for the run on this address (e.g. `127.0.0.1:7878`)
+    #[clap(long, value_name = "ADDR")]
+    serve: Option<SocketAddr>,
+    /// Use a locally built directory or tarball as the artifact of a transform instead of building it, disabling uploads to the build cache
+    #[clap(long = "override", value_name = "ADDR=PATH", value_parser = crate::cmd::util::parse_override)]
+    overrides: Vec<(String, PathBuf)>,
     #[clap(long = "arg", short = 'a', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
     args: Option<Vec<(String, String)>>,
 }
 
 impl Run {
     pub async fn run(&self, args: Args) -> Result<()> {
-        let ctx = super::create_context(
-            &args,
-            self.args
-                .clone()
-                .map(HashMap::from_iter)
-                .unwrap_or_default(),
-            true,
-        )
-        .await?;
-        let addr = Addr::parse(self.addr.as_str())?;
-        ctx.run(&addr).await?;
+        // A running daemon builds plain runs of one address in its warm context
+        if let Some(addr) = self.addr.as_ref()
+            && !self.ui
+            && self.serve.is_none()
+            && self.keep_workspace.is_none()
+            && self.args.is_none()
+            && self.overrides.is_empty()
+        {
+            let command = super::Command::Run {
+                addr: addr.clone(),
+                offline: self.offline,
+                jobs: self.jobs,
+            };
+            if super::delegate(&args, command).await? {
+                return Ok(());
+            }
+        }
+        self.run_in(&HostContexts, args).await
+    }
+
+    /// Runs the build in a context built by `contexts`.
+    pub async fn run_in(&self, contexts: &impl Contexts, args: Args) -> Result<()> {
+        let variables = self
+            .args
+            .clone()
+            .map(HashMap::from_iter)
+            .unwrap_or_default();
+        // Without a terminal to draw on, fall back to the regular progress bars
+        let ui = self.ui && console::Term::stderr().is_term();
+        let ctx = if ui {
+            // The dashboard owns the screen, so nothing else may write to it
+            let ctx = contexts
+                .init(&args, variables, LogVerbosity::Silent)
+                .await?;
+            ctx.load_project(true).await?;
+            ctx
+        } else {
+            super::create_context_in(contexts, &args, variables, true).await?
+        };
+        ctx.storage().set_offline(self.offline).await;
+        for (addr, path) in self.overrides.iter() {
+            ctx.add_override(&Addr::parse(addr)?, path).await?;
+        }
+        ctx.scheduler().set_jobs(self.jobs);
+        ctx.scheduler()
+            .set_keep_workspace(match self.keep_workspace.as_deref() {
+                Some("always") => KeepWorkspace::Always,
+                Some(_) => KeepWorkspace::Failed,
+                None => KeepWorkspace::Never,
+            });
+        let affected = match self.affected_by.as_ref() {
+            Some(range) => {
+                let changed = changed_files(ctx.project_dir(), range)?;
+                let targets: Vec<Addr> = ctx.affected_by(&changed).await?.into_iter().collect();
+                if targets.is_empty() {
+                    println!("no transforms are affected by {range}");
+                    return Ok(());
+                }
+                for target in targets.iter() {
+                    println!("affected: {target}");
+                }
+                Some(targets)
+            }
+            None => None,
+        };
+        let addr = Addr::parse(self.addr.as_deref().unwrap_or_default())?;
+        let _server = match self.serve {
+            Some(serve) => Some(StatusServer::start(serve, &ctx).await?),
+            None => None,
+        };
+        let dashboard = ui.then(|| Dashboard::start(ctx.events().subscribe()));
+        let result = match affected {
+            Some(targets) => ctx.run_all(&targets).await,
+            None => ctx.run(&addr).await,
+        };
+        if let Some(dashboard) = dashboard {
+            dashboard.finish().await;
+        }
+        result?;
         Ok(())
     }
 }
+
+/// Lists the files changed in `range` as absolute paths
+fn changed_files(dir: &Path, range: &str) -> Result<Vec<PathBuf>> {
+    let root = git(dir, &["rev-parse", "--show-toplevel"], range)?;
+    let root = PathBuf::from(root.trim());
+    let files = git(dir, &["diff", "--name-only", "--no-renames", range], range)?;
+    Ok(files.lines().map(|x| root.join(x)).collect())
+}
+
+fn git(dir: &Path, args: &[&str], range: &str) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| {
+            error::GitDiffSnafu {
+                range,
+                reason: e.to_string(),
+            }
+            .build()
+        })?;
+    ensure!(
+        output.status.success(),
+        error::GitDiffSnafu {
+            range,
+            reason: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+    );
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
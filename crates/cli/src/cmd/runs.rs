@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use crate::Result;
+use clap::{Parser, Subcommand};
+use snafu::ResultExt;
+
+use crate::Args;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Browse the summaries of previous runs", long_about = None)]
+pub struct Runs {
+    #[clap(subcommand)]
+    command: RunsCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum RunsCommand {
+    /// List recorded runs, oldest first
+    List,
+    /// Show the summary of a run
+    Show {
+        /// Run id, a unique prefix of one, or `latest`
+        #[arg(default_value = "latest")]
+        id: String,
+        /// Print the raw JSON summary
+        #[arg(long)]
+        json: bool,
+        /// Print the external accesses made during the run instead
+        #[arg(long)]
+        audit: bool,
+    },
+}
+
+impl Runs {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let ctx = super::init_context(&args, HashMap::default()).await?;
+        match &self.command {
+            RunsCommand::List => {
+                for run in ctx.runs().list().await? {
+                    let duration = run
+                        .duration_ms()
+                        .map(|x| format!("{x}ms"))
+                        .unwrap_or_else(|| "-".to_string());
+                    let targets = run
+                        .targets
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    println!(
+                        "{} {} {} {duration} {targets}",
+                        run.id,
+                        run.started.to_rfc3339(),
+                        run.status
+                    );
+                }
+            }
+            RunsCommand::Show { id, json, audit } => {
+                let run = ctx.runs().find(id).await?;
+                if *audit && *json {
+                    let path = ctx.runs().audit_path(&run.id);
+                    if path.exists() {
+                        println!(
+                            "{}",
+                            std::fs::read_to_string(path).context(crate::error::IoSnafu)?
+                        );
+                    } else {
+                        println!("[]");
+                    }
+                } else if *audit {
+                    for access in ctx.runs().load_audit(&run.id).await? {
+                        println!("{access}");
+                    }
+                } else if *json {
+                    println!(
+                        "{}",
+                        std::fs::read_to_string(ctx.runs().path().join(format!("{}.json", run.id)))
+                            .context(crate::error::IoSnafu)?
+                    );
+                } else {
+                    print!("{run}");
+                }
+            }
+        }
+        Ok(())
+    }
+}
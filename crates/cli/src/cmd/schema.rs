@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use crate::Args;
+use crate::Result;
+use crate::error;
+use clap::Parser;
+use edo::context::{Component, JSON_SCHEMA_DIALECT, LogVerbosity};
+use snafu::OptionExt;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Print the JSON Schema of build files", long_about = None)]
+pub struct Schema {
+    /// Only print the schema of a kind, as `[<component>:]<kind>`, e.g.
+    /// `script` or `transform:script`
+    kind: Option<String>,
+    /// List the kinds instead
+    #[clap(long, conflicts_with = "kind")]
+    list: bool,
+}
+
+impl Schema {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        // Anything logged would end up in the schema
+        let ctx = super::init_context_with(&args, HashMap::default(), LogVerbosity::Quiet).await?;
+        let registry = ctx.registry();
+        if self.list {
+            for (component, kind) in registry.kinds() {
+                let description = registry
+                    .schema(&component, &kind)
+                    .map(|x| x.description)
+                    .unwrap_or_default();
+                println!("{:<32} {description}", format!("{component}:{kind}"));
+            }
+            return Ok(());
+        }
+        let Some(name) = self.kind.as_ref() else {
+            println!("{:#}", registry.json_schema());
+            return Ok(());
+        };
+        let (component, kind) = match name.split_once(':') {
+            Some((component, kind)) => (
+                Some(
+                    Component::parse(component)
+                        .context(error::UnknownKindSnafu { kind: name.clone() })?,
+                ),
+                kind,
+            ),
+            None => (None, name.as_str()),
+        };
+        let matches: Vec<(Component, String)> = registry
+            .kinds()
+            .into_iter()
+            .filter(|x| x.1 == kind && component.as_ref().is_none_or(|c| *c == x.0))
+            .collect();
+        let (component, kind) = match matches.as_slice() {
+            [found] => found.clone(),
+            [] => return error::UnknownKindSnafu { kind: name.clone() }.fail(),
+            _ => {
+                return error::AmbiguousKindSnafu {
+                    kind: name.clone(),
+                    choices: matches
+                        .iter()
+                        .map(|(component, kind)| format!("{component}:{kind}"))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                }
+                .fail();
+            }
+        };
+        let mut schema = registry.kind_json_schema(&component, &kind);
+        schema["$schema"] = JSON_SCHEMA_DIALECT.into();
+        println!("{schema:#}");
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use edo::context::{Addr, Context, Event, NodeState, RunState};
+use http_body_util::Full;
+use hyper::body::Incoming;
+use hyper::header::CONTENT_TYPE;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::json;
+use snafu::ResultExt;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::oneshot;
+
+use crate::Result;
+use crate::error;
+
+/// Output lines kept for each running transform.
+const TAIL: usize = 20;
+
+const PAGE: &str = include_str!("serve.html");
+
+/// What every request is answered from.
+struct Site {
+    state: Mutex<RunState>,
+    logs: PathBuf,
+    build_cache: bool,
+    offline: bool,
+}
+
+impl Site {
+    fn status(&self) -> serde_json::Value {
+        let state = self.state.lock().unwrap();
+        let hits = state.count(NodeState::Cached);
+        let executed = state.count(NodeState::Built) + state.count(NodeState::Failed);
+        json!({
+            "run": *state,
+            "cache": {
+                "build_cache": self.build_cache,
+                "offline": self.offline,
+                "hits": hits,
+                "executed": executed,
+                "hit_rate": (hits * 100).checked_div(hits + executed),
+            },
+        })
+    }
+
+    fn graph(&self) -> serde_json::Value {
+        let state = self.state.lock().unwrap();
+        let graph: BTreeMap<String, &Vec<Addr>> = state
+            .nodes
+            .iter()
+            .map(|(addr, node)| (addr.to_string(), &node.depends))
+            .collect();
+        json!(graph)
+    }
+
+    /// Reads the log of the transform named in `query`.
+    async fn log(&self, query: Option<&str>) -> Option<String> {
+        let addr = url::form_urlencoded::parse(query?.as_bytes())
+            .find(|x| x.0 == "addr")
+            .map(|x| x.1.to_string())?;
+        let addr = Addr::parse(&addr).ok()?;
+        // Only files the run reported are served, never arbitrary paths
+        let log = self.state.lock().unwrap().nodes.get(&addr)?.log.clone()?;
+        tokio::fs::read_to_string(self.logs.join(log)).await.ok()
+    }
+
+    async fn handle(&self, req: Request<Incoming>) -> Response<Full<Bytes>> {
+        if req.method() != Method::GET {
+            return respond(StatusCode::METHOD_NOT_ALLOWED, "text/plain", "".into());
+        }
+        match req.uri().path() {
+            "/" => respond(StatusCode::OK, "text/html; charset=utf-8", PAGE.into()),
+            "/api/status" => json_response(self.status()),
+            "/api/graph" => json_response(self.graph()),
+            "/api/logs" => match self.log(req.uri().query()).await {
+                Some(log) => respond(StatusCode::OK, "text/plain; charset=utf-8", log),
+                None => respond(StatusCode::NOT_FOUND, "text/plain", "no log\n".into()),
+            },
+            _ => respond(StatusCode::NOT_FOUND, "text/plain", "not found\n".into()),
+        }
+    }
+}
+
+pub(super) fn respond(
+    status: StatusCode,
+    content_type: &str,
+    body: String,
+) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    if let Ok(value) = content_type.parse() {
+        response.headers_mut().insert(CONTENT_TYPE, value);
+    }
+    response
+}
+
+pub(super) fn json_response(value: serde_json::Value) -> Response<Full<Bytes>> {
+    respond(StatusCode::OK, "application/json", value.to_string())
+}
+
+/// Serves a status page and JSON API for the current run over HTTP, built
+/// from the context's events, until dropped.
+///
+/// The server runs on its own thread: commands block the runtime the build
+/// runs on, and the status must stay reachable while they do.
+pub struct StatusServer {
+    stop: Option<oneshot::Sender<()>>,
+}
+
+impl StatusServer {
+    pub async fn start(addr: SocketAddr, ctx: &Context) -> Result<Self> {
+        let listener = std::net::TcpListener::bind(addr).context(error::ServeSnafu { addr })?;
+        listener
+            .set_nonblocking(true)
+            .context(error::ServeSnafu { addr })?;
+        let addr = listener.local_addr().context(error::ServeSnafu { addr })?;
+        let site = Arc::new(Site {
+            state: Mutex::new(RunState::new(TAIL)),
+            logs: ctx.log().path().to_path_buf(),
+            build_cache: ctx.storage().has_build_cache().await,
+            offline: ctx.storage().is_offline().await,
+        });
+        let events = ctx.events().subscribe();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context(error::ServeSnafu { addr })?;
+        let (stop, stopped) = oneshot::channel();
+        std::thread::Builder::new()
+            .name("edo-status".to_string())
+            .spawn(move || runtime.block_on(serve(listener, site, events, stopped)))
+            .context(error::ServeSnafu { addr })?;
+        tracing::info!("serving the build status on http://{addr}");
+        Ok(Self { stop: Some(stop) })
+    }
+}
+
+impl Drop for StatusServer {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+    }
+}
+
+async fn serve(
+    listener: std::net::TcpListener,
+    site: Arc<Site>,
+    mut events: Receiver<Event>,
+    mut stopped: oneshot::Receiver<()>,
+) {
+    let Ok(listener) = TcpListener::from_std(listener) else {
+        return;
+    };
+    let mut following = true;
+    loop {
+        tokio::select! {
+            _ = &mut stopped => break,
+            event = events.recv(), if following => match event {
+                Ok(event) => site.state.lock().unwrap().apply(&event),
+                // A missed output line is not worth stopping over
+                Err(RecvError::Lagged(_)) => {}
+                // Keep answering with the final state
+                Err(RecvError::Closed) => following = false,
+            },
+            accepted = listener.accept() => {
+                let Ok((stream, _)) = accepted else {
+                    continue;
+                };
+                let site = site.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| {
+                        let site = site.clone();
+                        async move { Ok::<_, Infallible>(site.handle(req).await) }
+                    });
+                    if let Err(e) = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        tracing::debug!("status connection closed: {e}");
+                    }
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use crate::Args;
+use crate::Result;
+use clap::Parser;
+use edo::context::{ContextError, LogVerbosity, WorkspaceStatus};
+use serde_json::json;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Show which local sources changed and which transforms a run would rebuild", long_about = None)]
+pub struct Status {
+    /// Print the status as JSON
+    #[clap(long)]
+    json: bool,
+    #[clap(long = "arg", short = 'a', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
+    args: Option<Vec<(String, String)>>,
+}
+
+impl Status {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let variables = self
+            .args
+            .clone()
+            .map(HashMap::from_iter)
+            .unwrap_or_default();
+        // The report says everything there is to say, log lines would only repeat it
+        let ctx = super::init_context_with(&args, variables, LogVerbosity::Quiet).await?;
+        let created = !ctx.project_dir().join("edo.lock.json").exists();
+        // A stale lock is reported rather than resolved again, what would
+        // rebuild is only known once `edo update` resolved the dependencies
+        let lock = match ctx.load_project(true).await {
+            Ok(()) if created => "created",
+            Ok(()) => "current",
+            Err(ContextError::DependencyChange) => "stale",
+            Err(e) => return Err(e.into()),
+        };
+        let status = if lock == "stale" {
+            None
+        } else {
+            Some(ctx.status().await?)
+        };
+
+        if self.json {
+            println!(
+                "{:#}",
+                json!({
+                    "lock": lock,
+                    "dirty": status.as_ref().map(|x| &x.dirty),
+                    "stale": status.as_ref().map(|x| &x.stale),
+                })
+            );
+            return Ok(());
+        }
+        println!(
+            "lock file: {}",
+            match lock {
+                "created" => "created, there was none",
+                "stale" => "out of date with the build files, run `edo update`",
+                _ => "up to date",
+            }
+        );
+        if let Some(status) = status.as_ref() {
+            print_status(status);
+        }
+        Ok(())
+    }
+}
+
+fn print_status(status: &WorkspaceStatus) {
+    if status.dirty.is_empty() {
+        println!("local sources: unchanged");
+    } else {
+        println!("local sources changed:");
+        for addr in status.dirty.iter() {
+            println!("  {addr}");
+        }
+    }
+    if status.stale.is_empty() {
+        println!("transforms: all built");
+        return;
+    }
+    println!("transforms to rebuild:");
+    for transform in status.stale.iter() {
+        println!("  {}: {}", transform.addr, transform.reasons.join(", "));
+    }
+}
@@ -1,16 +1,45 @@
 use std::collections::HashMap;
 
+use super::{Contexts, HostContexts};
 use crate::Args;
 use crate::Result;
 use clap::Parser;
 
 #[derive(Parser, Debug, Clone)]
 #[clap(version, about = "Update edo lock to latest state", long_about = None)]
-pub struct Update {}
+pub struct Update {
+    /// Re-resolve all dependencies and explain how the named package was resolved
+    #[clap(long)]
+    explain: Option<String>,
+}
 
 impl Update {
     pub async fn run(&self, args: Args) -> Result<()> {
-        let _ = super::create_context(&args, HashMap::default(), false).await?;
+        self.run_in(&HostContexts, args).await
+    }
+
+    /// Updates the lock of a context built by `contexts`.
+    pub async fn run_in(&self, contexts: &impl Contexts, args: Args) -> Result<()> {
+        if let Some(package) = self.explain.as_ref() {
+            let ctx = super::init_context_in(contexts, &args, HashMap::default()).await?;
+            ctx.refresh_project().await?;
+            let explanations: Vec<_> = ctx
+                .explanations()
+                .into_iter()
+                .filter(|x| x.name == *package || x.addr.to_string() == *package)
+                .collect();
+            if explanations.is_empty() {
+                println!("no dependency named {package} was resolved");
+            }
+            for explanation in explanations {
+                println!("{explanation}");
+            }
+            return Ok(());
+        }
+        // Resolve everything again and drop the source pins, which are
+        // recorded anew as sources are fetched
+        let ctx = super::init_context_in(contexts, &args, HashMap::default()).await?;
+        ctx.refresh_project().await?;
         Ok(())
     }
 }
@@ -1,3 +1,13 @@
+use async_compression::tokio::bufread::{
+    BzDecoder, GzipDecoder, LzmaDecoder, XzDecoder, ZstdDecoder,
+};
+use edo::context::Addr;
+use edo::storage::{CacheSelector, Compression, Id, Storage, is_tag};
+use snafu::OptionExt;
+use std::pin::Pin;
+use std::str::FromStr;
+use tokio::io::{AsyncBufRead, AsyncRead};
+
 /// Parse a single key-value pair
 pub(crate) fn parse_key_val<T, U>(
     s: &str,
@@ -13,3 +23,89 @@ where
         .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{s}`"))?;
     Ok((s[..pos].parse()?, s[pos + 1..].parse()?))
 }
+
+/// Parse an `ADDR=PATH` override, splitting after any variant of the
+/// address as those contain `=` themselves
+pub(crate) fn parse_override(
+    s: &str,
+) -> Result<(String, std::path::PathBuf), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let start = s.rfind(']').unwrap_or(0);
+    let pos = s[start..]
+        .find('=')
+        .map(|x| x + start)
+        .ok_or_else(|| format!("invalid ADDR=PATH: no `=` found in `{s}`"))?;
+    Ok((s[..pos].to_string(), s[pos + 1..].into()))
+}
+
+/// What a command operates on: a project address or a raw artifact id
+pub(crate) enum Target {
+    /// A transform or source address (`//project/name`), resolved by loading the project
+    Addr(Addr),
+    /// An artifact id or reference (`name@version#digest/arch`), resolved directly against storage
+    Id(Id),
+    /// An alias published to the output cache (`name:tag`), see `edo publish`
+    Tag(String),
+}
+
+impl Target {
+    pub(crate) fn parse(input: &str) -> crate::Result<Self> {
+        if input.starts_with("//") {
+            Ok(Self::Addr(Addr::parse(input)?))
+        } else if is_tag(input) {
+            Ok(Self::Tag(input.to_string()))
+        } else {
+            Ok(Self::Id(Id::from_str(input)?))
+        }
+    }
+
+    /// Returns `true` when resolving this target requires evaluating the
+    /// project, or the caches it declares
+    pub(crate) fn needs_project(&self) -> bool {
+        matches!(self, Self::Addr(_) | Self::Tag(_))
+    }
+}
+
+/// The id the alias `tag` points at in the output cache, copied into the
+/// local cache so it is read from there
+pub(crate) async fn resolve_tag(storage: &Storage, tag: &str) -> crate::Result<Id> {
+    let id = storage
+        .resolve_tag(tag)
+        .await?
+        .context(crate::error::TagNotFoundSnafu { tag })?;
+    storage.import(&id, &CacheSelector::Output).await?;
+    Ok(id)
+}
+
+/// Wrap `reader` in the decoder matching `compression`
+pub(crate) fn decompress<R>(reader: R, compression: &Compression) -> Pin<Box<dyn AsyncRead + Send>>
+where
+    R: AsyncBufRead + Send + 'static,
+{
+    match compression {
+        Compression::Bzip2 => Box::pin(BzDecoder::new(reader)),
+        Compression::Lz => Box::pin(LzmaDecoder::new(reader)),
+        Compression::Xz => Box::pin(XzDecoder::new(reader)),
+        Compression::Gzip => Box::pin(GzipDecoder::new(reader)),
+        Compression::Zstd => Box::pin(ZstdDecoder::new(reader)),
+        Compression::None => Box::pin(reader),
+    }
+}
+
+/// The id of generation `generation` of the artifacts sharing the prefix of
+/// `id` in the local cache, 0 being the newest as listed by `edo cache history`
+pub(crate) async fn select_generation(
+    storage: &Storage,
+    id: &Id,
+    generation: usize,
+) -> crate::Result<Id> {
+    let prefix = id.prefix();
+    let history = storage.history(&CacheSelector::Local, &prefix).await?;
+    let count = history.len();
+    history.into_iter().nth(generation).map(|x| x.id).context(
+        crate::error::GenerationNotFoundSnafu {
+            prefix,
+            generation,
+            count,
+        },
+    )
+}
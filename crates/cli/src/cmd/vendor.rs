@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::Args;
+use crate::Result;
+use clap::Parser;
+use edo::context::SourceBundle;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Copy every external source into a bundle committed with the project", long_about = None)]
+pub struct Vendor {
+    /// Bundle directory, `[bundle] path` or `vendor` in the project when omitted
+    #[clap(long)]
+    dir: Option<PathBuf>,
+}
+
+impl Vendor {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let ctx = super::create_context(&args, HashMap::default(), true).await?;
+        let configured = SourceBundle::path(ctx.config(), ctx.project_dir());
+        let dir = self.dir.clone().unwrap_or_else(|| configured.clone());
+        let bundle = ctx.bundle_sources(&dir).await?;
+        for (addr, source) in bundle.sources.iter() {
+            println!("{addr}  {}", source.id);
+        }
+        println!(
+            "vendored {} sources into {}",
+            bundle.sources.len(),
+            dir.display()
+        );
+        if std::path::absolute(&dir).ok() != std::path::absolute(&configured).ok() {
+            println!(
+                "builds only use the bundle in {}, set `[bundle] path` to use this one",
+                configured.display()
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use crate::Result;
+use crate::error;
+use clap::Parser;
+use edo::context::Addr;
+use snafu::OptionExt;
+
+use crate::Args;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Build a transform twice and check the artifacts match", long_about = None)]
+pub struct VerifyRepro {
+    addr: String,
+    #[clap(long = "arg", short = 'a', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
+    args: Option<Vec<(String, String)>>,
+}
+
+impl VerifyRepro {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let ctx = super::create_context(
+            &args,
+            self.args
+                .clone()
+                .map(HashMap::from_iter)
+                .unwrap_or_default(),
+            true,
+        )
+        .await?;
+        let addr = Addr::parse(self.addr.as_str())?;
+        let transform = ctx
+            .get_transform(&addr)
+            .context(error::UnknownTransformSnafu {
+                addr: self.addr.clone(),
+            })?;
+        let id = transform.get_unique_id(&ctx.get_handle()).await?;
+
+        // The first build may well come straight from the cache
+        ctx.run(&addr).await?;
+        let original = ctx
+            .storage()
+            .find_build(&id, true)
+            .await?
+            .context(error::ArtifactNotFoundSnafu { id: id.to_string() })?;
+
+        ctx.rebuild(&addr).await?;
+        let rebuilt = ctx.storage().safe_open(&id).await?;
+        let lines = super::diff::compare(&ctx, &original, &rebuilt).await;
+        // Put the original artifact back so verification leaves the cache as it found it,
+        // its layers are still in the local cache as blobs are never overwritten
+        ctx.storage().safe_save(&original).await?;
+
+        let lines = lines?;
+        if lines.is_empty() {
+            println!("{addr} is reproducible: {id}");
+            return Ok(());
+        }
+        println!("--- {id} (cached)");
+        println!("+++ {id} (rebuilt)");
+        for line in lines {
+            println!("{line}");
+        }
+        error::NotReproducibleSnafu { addr }.fail()
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::Args;
+use crate::Result;
+use clap::Parser;
+use edo::context::RunSummary;
+use edo::storage::{CacheSelector, Id};
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Download the artifacts of a previous run into the local cache", long_about = None)]
+pub struct Warm {
+    /// Run summary to warm from, a `runs/<id>.json` file or a run id, a
+    /// unique prefix of one or `latest`
+    #[clap(long, default_value = "latest")]
+    from: String,
+    /// Cache to download from: `build`, `output`, `source:<name>` or the
+    /// address of a cache such as `//edo-build-cache`
+    #[clap(long, default_value = "build", value_parser = parse_cache)]
+    backend: CacheSelector,
+}
+
+fn parse_cache(value: &str) -> std::result::Result<CacheSelector, String> {
+    value
+        .parse()
+        .map_err(|e: edo::storage::StorageError| e.to_string())
+}
+
+impl Warm {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let ctx = super::create_context(&args, HashMap::default(), true).await?;
+        let path = Path::new(&self.from);
+        let summary = if path.is_file() {
+            RunSummary::read(path).await?
+        } else {
+            ctx.runs().find(&self.from).await?
+        };
+        let mut total = 0;
+        let mut missing = Vec::new();
+        for (addr, id) in summary.artifacts() {
+            total += 1;
+            let id: Id = id.parse()?;
+            if ctx.storage().import(&id, &self.backend).await? {
+                println!("{addr}  {id}");
+            } else {
+                missing.push(addr);
+            }
+        }
+        println!(
+            "warmed {} of {total} artifacts from the {} cache",
+            total - missing.len(),
+            self.backend
+        );
+        for addr in missing {
+            println!("missing {addr}");
+        }
+        Ok(())
+    }
+}
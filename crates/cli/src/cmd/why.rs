@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use crate::Args;
+use crate::Result;
+use clap::Parser;
+use edo::context::{Addr, LogVerbosity, RebuildCause};
+use serde_json::json;
+
+#[derive(Parser, Debug, Clone)]
+#[clap(version, about = "Explain why a transform is not a cache hit", long_about = None)]
+pub struct Why {
+    /// Transform address (`//project/name`)
+    addr: String,
+    /// Print the explanation as JSON
+    #[clap(long)]
+    json: bool,
+    #[clap(long = "arg", short = 'a', value_parser = crate::cmd::util::parse_key_val::<String, String>)]
+    args: Option<Vec<(String, String)>>,
+}
+
+impl Why {
+    pub async fn run(&self, args: Args) -> Result<()> {
+        let variables = self
+            .args
+            .clone()
+            .map(HashMap::from_iter)
+            .unwrap_or_default();
+        let ctx = super::init_context_with(&args, variables, LogVerbosity::Quiet).await?;
+        ctx.load_project(true).await?;
+        let cause = ctx.why(&Addr::parse(&self.addr)?).await?;
+        if self.json {
+            println!("{:#}", json!(cause));
+        } else {
+            print_cause(&cause);
+        }
+        Ok(())
+    }
+}
+
+fn print_cause(cause: &RebuildCause) {
+    if cause.built {
+        println!("{} is a cache hit: {}", cause.addr, cause.id);
+    } else {
+        println!("{} is not built: {}", cause.addr, cause.id);
+    }
+    let Some(run) = cause.run.as_ref() else {
+        if !cause.built {
+            println!("  no recorded run computed its key, it was never built");
+        }
+        return;
+    };
+    if cause.changes.is_empty() {
+        if !cause.built {
+            println!(
+                "  its inputs are unchanged since run {run}, the artifact is no longer in any cache"
+            );
+        }
+        return;
+    }
+    println!(
+        "  changed since run {run} built it as {}:",
+        cause.previous.as_deref().unwrap_or("an unknown id")
+    );
+    for change in cause.changes.iter() {
+        println!("    {change}");
+    }
+}
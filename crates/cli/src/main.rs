@@ -1,5 +1,9 @@
 use clap::Parser;
-use cmd::{Checkout, List, Prune, Run, Update};
+use cmd::{
+    Cache, Checkout, Complete, Completions, Daemon, Diff, Doctor, ExportTargets, Fetch, Fmt, Graph,
+    Init, Inspect, Licenses, List, Logs, Lsp, Prune, Publish, Run, Runs, Schema, Status, Update,
+    Vendor, VerifyRepro, Warm, Why,
+};
 use std::path::PathBuf;
 
 mod cmd;
@@ -14,6 +18,79 @@ pub mod error {
     pub enum Error {
         #[snafu(display("io error: {source}"))]
         Io { source: std::io::Error },
+        #[snafu(display("no transform found with addr '{addr}'"))]
+        UnknownTransform { addr: String },
+        #[snafu(display("no transform or source found with addr '{addr}'"))]
+        UnknownTarget { addr: String },
+        #[snafu(display("{transform} does not use a source named '{name}'"))]
+        UnknownTransformSource {
+            transform: edo::context::Addr,
+            name: String,
+        },
+        #[snafu(display(
+            "the {cache} cache holds no log of {addr} ({id}), see [scheduler] upload_logs"
+        ))]
+        LogNotFound {
+            addr: edo::context::Addr,
+            id: String,
+            cache: String,
+        },
+        #[snafu(display("no artifact with id '{id}' in the local or build cache"))]
+        ArtifactNotFound { id: String },
+        #[snafu(display("the output cache has no artifact tagged '{tag}', see edo publish"))]
+        TagNotFound { tag: String },
+        #[snafu(display(
+            "the local cache holds {count} generations of {prefix}, there is no generation {generation}"
+        ))]
+        GenerationNotFound {
+            prefix: String,
+            generation: usize,
+            count: usize,
+        },
+        #[snafu(display("{addr} is not reproducible, the rebuilt artifact differs"))]
+        NotReproducible { addr: edo::context::Addr },
+        #[snafu(display(
+            "found {count} problems in the {cache} cache, rerun with --repair to fix them"
+        ))]
+        CacheInconsistent { cache: String, count: usize },
+        #[snafu(display("failed to list the files changed in {range}: {reason}"))]
+        GitDiff { range: String, reason: String },
+        #[snafu(display("{} already exists, refusing to overwrite it", path.display()))]
+        ProjectExists { path: std::path::PathBuf },
+        #[snafu(display("'{name}' is not a valid package name, use letters, digits, '_' and '-'"))]
+        InvalidProjectName { name: String },
+        #[snafu(display("failed to format {}: {source}", path.display()))]
+        FormatFile {
+            path: std::path::PathBuf,
+            #[snafu(source(from(edo::context::ContextError, Box::new)))]
+            source: Box<edo::context::ContextError>,
+        },
+        #[snafu(display("{count} build files are not formatted, run `edo fmt` to format them"))]
+        Unformatted { count: usize },
+        #[snafu(display("{count} checks failed, see the fixes above"))]
+        Unhealthy { count: usize },
+        #[snafu(display("no component kind named '{kind}', see `edo schema --list`"))]
+        UnknownKind { kind: String },
+        #[snafu(display("'{kind}' names several kinds, pick one of {choices}"))]
+        AmbiguousKind { kind: String, choices: String },
+        #[snafu(display("failed to serve the build status on {addr}: {source}"))]
+        Serve {
+            addr: std::net::SocketAddr,
+            source: std::io::Error,
+        },
+        #[snafu(display("failed to serve the graph on {addr}: {source}"))]
+        ServeGraph {
+            addr: std::net::SocketAddr,
+            source: std::io::Error,
+        },
+        #[snafu(display("a daemon already listens on {}", path.display()))]
+        DaemonRunning { path: std::path::PathBuf },
+        #[snafu(display("failed to start the daemon, {reason}"))]
+        DaemonStart { reason: String },
+        #[snafu(display("{message}"))]
+        Daemon { message: String },
+        #[snafu(display("edo cannot start a daemon inside a transform"))]
+        NestedDaemon,
         #[snafu(transparent)]
         Context { source: edo::context::ContextError },
         #[snafu(transparent)]
@@ -50,11 +127,35 @@ pub struct Args {
 
 #[derive(Parser, Debug, Clone)]
 enum Commands {
+    Cache(Cache),
     Checkout(Checkout),
+    Completions(Completions),
+    #[command(name = "__complete", hide = true)]
+    Complete(Complete),
+    Daemon(Daemon),
+    Diff(Diff),
+    Doctor(Doctor),
+    ExportTargets(ExportTargets),
+    Fetch(Fetch),
+    Fmt(Fmt),
+    Graph(Graph),
+    Init(Init),
+    Inspect(Inspect),
     Run(Run),
+    Runs(Runs),
     Prune(Prune),
+    Publish(Publish),
+    Schema(Schema),
+    Status(Status),
     Update(Update),
+    Licenses(Licenses),
     List(List),
+    Logs(Logs),
+    Lsp(Lsp),
+    Vendor(Vendor),
+    VerifyRepro(VerifyRepro),
+    Warm(Warm),
+    Why(Why),
 }
 
 #[tokio::main]
@@ -63,11 +164,34 @@ async fn main() -> Result<()> {
     let args = Args::parse();
 
     match args.clone().command {
+        Commands::Cache(cmd) => cmd.run(args.clone()).await?,
         Commands::Checkout(cmd) => cmd.run(args.clone()).await?,
+        Commands::Completions(cmd) => cmd.run(args.clone()).await?,
+        Commands::Complete(cmd) => cmd.run(args.clone()).await?,
+        Commands::Daemon(cmd) => cmd.run(args.clone()).await?,
+        Commands::Diff(cmd) => cmd.run(args.clone()).await?,
+        Commands::Doctor(cmd) => cmd.run(args.clone()).await?,
+        Commands::ExportTargets(cmd) => cmd.run(args.clone()).await?,
+        Commands::Fetch(cmd) => cmd.run(args.clone()).await?,
+        Commands::Fmt(cmd) => cmd.run(args.clone()).await?,
+        Commands::Graph(cmd) => cmd.run(args.clone()).await?,
+        Commands::Init(cmd) => cmd.run(args.clone()).await?,
+        Commands::Inspect(cmd) => cmd.run(args.clone()).await?,
         Commands::Run(cmd) => cmd.run(args.clone()).await?,
+        Commands::Runs(cmd) => cmd.run(args.clone()).await?,
         Commands::Prune(cmd) => cmd.run(args.clone()).await?,
+        Commands::Publish(cmd) => cmd.run(args.clone()).await?,
+        Commands::Schema(cmd) => cmd.run(args.clone()).await?,
+        Commands::Status(cmd) => cmd.run(args.clone()).await?,
+        Commands::Lsp(cmd) => cmd.run(args.clone()).await?,
         Commands::Update(cmd) => cmd.run(args.clone()).await?,
+        Commands::Licenses(cmd) => cmd.run(args.clone()).await?,
         Commands::List(cmd) => cmd.run(args.clone()).await?,
+        Commands::Logs(cmd) => cmd.run(args.clone()).await?,
+        Commands::Vendor(cmd) => cmd.run(args.clone()).await?,
+        Commands::VerifyRepro(cmd) => cmd.run(args.clone()).await?,
+        Commands::Warm(cmd) => cmd.run(args.clone()).await?,
+        Commands::Why(cmd) => cmd.run(args.clone()).await?,
     }
     Ok(())
 }
@@ -1,12 +1,19 @@
 use async_trait::async_trait;
 use dashmap::DashMap;
-use edo::context::{Addr, Context, Definable, FromNode, Log, Node};
-use edo::environment::{Command, EnvResult, Environment, EnvironmentImpl, FarmImpl};
+use edo::context::{
+    Addr, Context, Definable, Describe, FieldType, FromNode, HealthCheck, KindSchema, Log, Node,
+};
+use edo::environment::{
+    Command, EnvResult, Environment, EnvironmentImpl, FarmImpl, HostAccess, HostInfo,
+    HostRequirements,
+};
 use edo::record;
 use edo::source::Source;
-use edo::storage::{Id, Storage};
+use edo::storage::{Artifact, Compression, Config, Id, MediaType, Storage};
+use edo::transform::{SOURCE_DATE_EPOCH, faketime};
 use edo::util::{
-    Reader, Writer, cmd_collect_out, cmd_noinput, cmd_noredirect, cmd_nulled, from_dash,
+    Reader, Writer, archive_dir, clear_dir, cmd_collect_out, cmd_noinput, cmd_noredirect,
+    cmd_nulled, copy_tree, from_dash,
 };
 use snafu::ResultExt;
 use snafu::{OptionExt, ensure};
@@ -14,27 +21,118 @@ use std::collections::HashMap;
 use std::env;
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::fs::{File, create_dir_all, remove_file};
 use tracing::Instrument;
 use uuid::Uuid;
 use which::which;
 
+use super::packages::Packages;
+
+/// Label recording the digest of the content an image was loaded or
+/// provisioned from, so images are told apart by what they hold rather than
+/// by the name they are tagged with.
+const DIGEST_LABEL: &str = "dev.edo.digest";
+
 /// Container environment farm creates environments that run inside of a container
 /// on a container engine like: finch, podman or docker
 pub struct ContainerFarm {
     config: ContainerConfig,
     addr: Addr,
     user: String,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    host: HostAccess,
+    /// What the transforms it runs need of the host.
+    requires: HostRequirements,
+    /// Architectures besides the host's the engine runs through emulation.
+    emulate: Vec<String>,
     source: Source,
+    packages: Option<Packages>,
+    /// libfaketime preloaded to start the clock at `SOURCE_DATE_EPOCH`.
+    faketime: Option<String>,
 }
 
 /// Configuration for the container runtime (e.g. which CLI binary to use).
+///
+/// Rootless runtimes usually need `userns` (e.g. `keep-id`) or explicit
+/// `uidmap` / `gidmap` entries so the bind mounted workspace stays writable
+/// from inside the container.
 #[derive(Default, Clone)]
 pub struct ContainerConfig {
     runtime: Option<String>,
     cli: PathBuf,
     network: bool,
+    userns: Option<String>,
+    uidmap: Vec<String>,
+    gidmap: Vec<String>,
+}
+
+impl ContainerConfig {
+    /// Arguments passing the user namespace settings to the runtime.
+    fn userns_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(userns) = self.userns.as_ref() {
+            args.push(format!("--userns={userns}"));
+        }
+        for map in self.uidmap.iter() {
+            args.extend(["--uidmap".to_string(), map.clone()]);
+        }
+        for map in self.gidmap.iter() {
+            args.extend(["--gidmap".to_string(), map.clone()]);
+        }
+        args
+    }
+}
+
+/// Reads an optional list of strings from `node`.
+fn string_list(node: &Node, field: &str) -> Result<Vec<String>, error::Error> {
+    let Some(list) = node.get(field) else {
+        return Ok(Vec::new());
+    };
+    list.as_list()
+        .and_then(|x| x.iter().map(|x| x.as_string()).collect())
+        .context(error::FieldSnafu {
+            field,
+            type_: "list of strings",
+        })
+}
+
+/// Reads `faketime`, either `true` for the library of the host's
+/// architecture in Debian's `faketime` package or the path of the library.
+fn faketime_field(node: &Node) -> Result<Option<String>, error::Error> {
+    let Some(value) = node.get("faketime") else {
+        return Ok(None);
+    };
+    match (value.as_bool(), value.as_string()) {
+        (Some(false), _) => Ok(None),
+        (Some(true), _) => Ok(Some(format!(
+            "/usr/lib/{}-linux-gnu/faketime/libfaketime.so.1",
+            std::env::consts::ARCH
+        ))),
+        (_, Some(path)) => Ok(Some(path)),
+        _ => error::FieldSnafu {
+            field: "faketime",
+            type_: "boolean or path of libfaketime",
+        }
+        .fail(),
+    }
+}
+
+/// Reads an optional non-negative id from `node`.
+fn id_field(node: &Node, field: &str) -> Result<Option<u32>, error::Error> {
+    let Some(value) = node.get(field) else {
+        return Ok(None);
+    };
+    value
+        .as_int()
+        .and_then(|x| u32::try_from(x).ok())
+        .map(Some)
+        .context(error::FieldSnafu {
+            field,
+            type_: "non-negative integer",
+        })
 }
 
 #[async_trait]
@@ -50,6 +148,9 @@ impl FromNode for ContainerConfig {
         Ok(Self {
             runtime,
             network,
+            userns: node.get("userns").and_then(|x| x.as_string()),
+            uidmap: string_list(node, "uidmap")?,
+            gidmap: string_list(node, "gidmap")?,
             ..Default::default()
         })
     }
@@ -64,6 +165,16 @@ impl FromNode for ContainerFarm {
             .get("user")
             .and_then(|x| x.as_string())
             .unwrap_or("root".into());
+        // A non-root build user can be given an explicit identity, which is
+        // needed when the image does not define the user itself
+        let uid = id_field(node, "uid")?;
+        let gid = id_field(node, "gid")?;
+        ensure!(
+            user != "root" || (uid.is_none() && gid.is_none()),
+            error::RootIdentitySnafu
+        );
+        let host = HostAccess::from_node(node)?;
+        let requires = HostRequirements::from_node(node)?;
         let source_node = node.get("source").context(error::NoSourceSnafu)?;
         let source = source_node
             .as_list()
@@ -74,11 +185,58 @@ impl FromNode for ContainerFarm {
             addr: addr.clone(),
             config: ContainerConfig::default(),
             user,
+            uid,
+            gid,
+            host,
+            requires,
+            emulate: string_list(node, "emulate")?,
             source,
+            packages: Packages::from_node(node)?,
+            faketime: faketime_field(node)?,
         })
     }
 }
 
+impl Describe for ContainerFarm {
+    fn describe() -> KindSchema {
+        KindSchema::new("Runs transforms in containers of an image")
+            .required(
+                "source",
+                crate::transform::sources_type(),
+                "Image source the containers run",
+            )
+            .field(
+                "user",
+                FieldType::String,
+                "User the commands run as, root when unset",
+            )
+            .field("uid", FieldType::Integer, "User id of a non-root user")
+            .field("gid", FieldType::Integer, "Group id of a non-root user")
+            .field(
+                "packages",
+                FieldType::list(FieldType::String),
+                "Packages installed into the image",
+            )
+            .field(
+                "package_manager",
+                FieldType::choice(["apt", "dnf", "yum", "apk"]),
+                "Package manager installing them, apt when unset",
+            )
+            .field(
+                "emulate",
+                FieldType::list(FieldType::String),
+                "Architectures besides the host's the engine can run through emulation, such as binfmt QEMU",
+            )
+            .field(
+                "faketime",
+                FieldType::one_of([FieldType::Boolean, FieldType::String]),
+                "Preload libfaketime, or the library at this path, to start the clock at SOURCE_DATE_EPOCH",
+            )
+            .fields(HostAccess::fields())
+            .fields(HostRequirements::fields())
+    }
+}
+
 #[async_trait]
 impl Definable<edo::environment::error::EnvironmentError, ContainerConfig> for ContainerFarm {
     fn key() -> &'static str {
@@ -101,45 +259,185 @@ impl Definable<edo::environment::error::EnvironmentError, ContainerConfig> for C
     }
 }
 
-unsafe impl Send for ContainerFarm {}
-unsafe impl Sync for ContainerFarm {}
+/// The last line a command wrote, usually the error it failed with.
+fn last_line(output: &[u8]) -> String {
+    String::from_utf8_lossy(output)
+        .lines()
+        .rfind(|x| !x.trim().is_empty())
+        .unwrap_or("no output")
+        .trim()
+        .to_string()
+}
 
-#[async_trait]
-impl FarmImpl for ContainerFarm {
-    async fn setup(&self, log: &Log, storage: &Storage) -> EnvResult<()> {
-        // Fetch our source image
-        trace!(component = "environment", type = "container", "fetching image for environments");
-        let artifact = self
+impl ContainerFarm {
+    /// The address of this farm usable in image and artifact names.
+    fn slug(&self) -> String {
+        self.addr
+            .to_string()
+            .strip_prefix("//")
+            .unwrap_or(self.addr.to_string().as_str())
+            .replace('/', "-")
+    }
+
+    /// The digest of the source image artifact.
+    async fn source_digest(&self) -> EnvResult<String> {
+        let id = self
             .source
-            .cache(log, storage)
+            .get_unique_id()
             .await
             .context(error::SourceSnafu)?;
+        Ok(id.digest().clone())
+    }
 
-        // Get the image name tag
-        let name = format!(
-            "edo-{}",
-            self.addr
-                .to_string()
-                .strip_prefix("//")
-                .unwrap_or(self.addr.to_string().as_str())
-                .replace('/', "-")
-        );
-        // First we want to check if the image already exists, if so skip the next step
-        trace!(component = "environment", type = "container", "check if the image is already loaded into the container runtime");
-        if cmd_nulled(
+    /// The digest of the image transforms run in: the source image, or the
+    /// image derived from it by installing the declared packages.
+    async fn image_digest(&self) -> EnvResult<String> {
+        let digest = self.source_digest().await?;
+        Ok(match self.packages.as_ref() {
+            Some(packages) => packages.digest(&digest),
+            None => digest,
+        })
+    }
+
+    /// The runtime tag of the source image, `edo-<addr>:<artifact digest>`.
+    async fn base_tag(&self) -> EnvResult<String> {
+        Ok(format!(
+            "edo-{}:{}",
+            self.slug(),
+            self.source_digest().await?.replace(':', "-")
+        ))
+    }
+
+    /// The runtime tag of the image transforms run in, `edo-<addr>:<image digest>`.
+    async fn image_tag(&self) -> EnvResult<String> {
+        Ok(format!(
+            "edo-{}:{}",
+            self.slug(),
+            self.image_digest().await?.replace(':', "-")
+        ))
+    }
+
+    /// Returns `true` when the runtime already holds an image tagged `name`.
+    fn has_image(&self, name: &str) -> EnvResult<bool> {
+        Ok(cmd_nulled(
             ".",
             &self.config.cli,
-            ["image", "inspect", name.as_str()],
+            ["image", "inspect", name],
             &HashMap::new(),
         )
-        .context(error::RuntimeSnafu)?
-        {
-            info!(component = "environment", type = "container", "image already loaded into container engine, if this is incorrect please remove {name} first.");
-            return Ok(());
+        .context(error::RuntimeSnafu)?)
+    }
+
+    /// Returns `true` when the image tagged `name` is labelled with `digest`.
+    ///
+    /// Tags can be moved by anyone using the runtime, the label is only set
+    /// by edo on the content it loaded.
+    fn is_labelled(&self, log: &Log, name: &str, digest: &str) -> EnvResult<bool> {
+        if !self.has_image(name)? {
+            return Ok(false);
         }
-        // The image source stores an oci image as an oci archive in the first layer
+        let format = format!("{{{{ index .Config.Labels \"{DIGEST_LABEL}\" }}}}");
+        let output = cmd_collect_out(
+            ".",
+            log,
+            &self.config.cli,
+            ["image", "inspect", "--format", format.as_str(), name],
+            &HashMap::new(),
+        )
+        .context(error::RuntimeSnafu)?;
+        Ok(String::from_utf8_lossy(&output).trim() == digest)
+    }
+
+    /// Finds an image labelled with `digest`, whichever farm loaded it.
+    fn find_image(&self, log: &Log, digest: &str) -> EnvResult<Option<String>> {
+        let filter = format!("label={DIGEST_LABEL}={digest}");
+        let output = cmd_collect_out(
+            ".",
+            log,
+            &self.config.cli,
+            ["images", "-q", "--no-trunc", "--filter", filter.as_str()],
+            &HashMap::new(),
+        )
+        .context(error::RuntimeSnafu)?;
+        Ok(String::from_utf8_lossy(&output)
+            .lines()
+            .map(|x| x.trim())
+            .find(|x| !x.is_empty())
+            .map(|x| x.to_string()))
+    }
+
+    /// Makes the image holding `digest` available as `name` without loading
+    /// it, when the runtime already holds it under this or another farm's
+    /// tag. Returns `false` when it has to be loaded.
+    fn reuse(&self, log: &Log, name: &str, digest: &str) -> EnvResult<bool> {
+        if self.is_labelled(log, name, digest)? {
+            return Ok(true);
+        }
+        let Some(image) = self.find_image(log, digest)? else {
+            return Ok(false);
+        };
+        record!(log, "tag_image", "{:?} tag {image} {name}", self.config.cli);
+        Ok(cmd_noinput(
+            ".",
+            log,
+            &self.config.cli,
+            ["tag", image.as_str(), name],
+            &HashMap::new(),
+        )
+        .context(error::RuntimeSnafu)?)
+    }
+
+    /// Replaces the image tagged `name` with one labelled with `digest`.
+    ///
+    /// Labels cannot be added to an existing image, so a build only adding
+    /// the label derives a new image config sharing all of its layers.
+    async fn label(&self, log: &Log, name: &str, digest: &str) -> EnvResult<()> {
+        let dir = env::temp_dir().join(Uuid::now_v7().to_string());
+        create_dir_all(&dir).await.context(error::IoSnafu)?;
+        tokio::fs::write(
+            dir.join("Dockerfile"),
+            format!("FROM {name}\nLABEL {DIGEST_LABEL}=\"{digest}\"\n"),
+        )
+        .await
+        .context(error::IoSnafu)?;
+        record!(
+            log,
+            "label_image",
+            "{:?} build -t {name} {dir:?}",
+            self.config.cli
+        );
+        let labelled = cmd_noinput(
+            ".",
+            log,
+            &self.config.cli,
+            ["build", "-q", "-t", name, dir.to_str().unwrap()],
+            &HashMap::new(),
+        )
+        .context(error::RuntimeSnafu);
+        tokio::fs::remove_dir_all(&dir)
+            .await
+            .context(error::IoSnafu)?;
+        ensure!(
+            labelled?,
+            error::LabelSnafu {
+                image: name.to_string()
+            }
+        );
+        Ok(())
+    }
+
+    /// Loads the image archive stored in the first layer of `artifact` into
+    /// the runtime, tags it `name` and labels it with `digest`.
+    async fn load(
+        &self,
+        log: &Log,
+        storage: &Storage,
+        artifact: &Artifact,
+        name: &str,
+        digest: &str,
+    ) -> EnvResult<()> {
         let layer = artifact.layers().first().unwrap();
-        let mut reader = storage.safe_read(&layer).await?;
+        let mut reader = storage.safe_read(layer).await?;
 
         let path = env::temp_dir().join(Uuid::now_v7().to_string());
         let mut archive = File::create(&path).await.context(error::IoSnafu)?;
@@ -175,12 +473,13 @@ impl FarmImpl for ContainerFarm {
                 ".",
                 log,
                 &self.config.cli,
-                ["tag", string.trim(), name.as_str()],
+                ["tag", string.trim(), name],
                 &HashMap::new(),
             )
             .context(error::RuntimeSnafu)?;
-            info!("image loaded into container runtime");
             remove_file(&path).await.context(error::IoSnafu)?;
+            self.label(log, name, digest).await?;
+            info!("image loaded into container runtime");
             Ok(())
         }
         .instrument(info_span!(
@@ -192,27 +491,263 @@ impl FarmImpl for ContainerFarm {
         .await
     }
 
+    /// Makes the image with `packages` installed available as `name`.
+    ///
+    /// The image is built once from `base` and stored as an artifact, so
+    /// later setups and other machines sharing the build cache load it
+    /// instead of installing the packages again.
+    async fn provision(
+        &self,
+        log: &Log,
+        storage: &Storage,
+        packages: &Packages,
+        base: &str,
+        name: &str,
+    ) -> EnvResult<()> {
+        let digest = self.image_digest().await?;
+        let id = Id::builder()
+            .name(format!("{}-packages", self.slug()))
+            .digest(digest.clone())
+            .build();
+        if let Some(artifact) = storage.find_build(&id, true).await? {
+            trace!(component = "environment", type = "container", "loading provisioned image {id}");
+            return self.load(log, storage, &artifact, name, &digest).await;
+        }
+        let span = info_span!(
+            target: "container",
+            "provisioning environment image",
+            id = id.to_string(),
+            log = log.log_name()
+        );
+        async move {
+            // Provisioning needs the network to reach package repositories,
+            // the builds using the resulting image do not
+            let container = format!("edo-provision-{}", Uuid::now_v7());
+            let script = packages.script();
+            let args = [
+                "run",
+                "--name",
+                container.as_str(),
+                "--security-opt",
+                "label=disable",
+                "-u",
+                "0:0",
+                base,
+                "sh",
+                "-c",
+                script.as_str(),
+            ];
+            record!(log, "provision", "{:?} {}", self.config.cli, args.join(" "));
+            let installed = cmd_noinput(".", log, &self.config.cli, args, &HashMap::new())
+                .context(error::RuntimeSnafu)?;
+            if installed {
+                record!(
+                    log,
+                    "commit",
+                    "{:?} commit {container} {name}",
+                    self.config.cli
+                );
+                cmd_noinput(
+                    ".",
+                    log,
+                    &self.config.cli,
+                    ["commit", container.as_str(), name],
+                    &HashMap::new(),
+                )
+                .context(error::RuntimeSnafu)?;
+                self.label(log, name, &digest).await?;
+            }
+            cmd_noinput(
+                ".",
+                log,
+                &self.config.cli,
+                ["rm", container.as_str()],
+                &HashMap::new(),
+            )
+            .context(error::RuntimeSnafu)?;
+            ensure!(
+                installed && self.has_image(name)?,
+                error::ProvisionSnafu {
+                    image: name.to_string(),
+                    packages: packages.to_string(),
+                }
+            );
+
+            // Keep the image as an artifact so it is only ever built once
+            let path = env::temp_dir().join(Uuid::now_v7().to_string());
+            record!(
+                log,
+                "save_image",
+                "{:?} save -o {path:?} {name}",
+                self.config.cli
+            );
+            cmd_noinput(
+                ".",
+                log,
+                &self.config.cli,
+                ["save", "-o", path.to_str().unwrap(), name],
+                &HashMap::new(),
+            )
+            .context(error::RuntimeSnafu)?;
+            let mut archive = File::open(&path).await.context(error::IoSnafu)?;
+            let mut writer = storage.safe_start_layer().await?;
+            tokio::io::copy(&mut archive, &mut writer)
+                .await
+                .context(error::IoSnafu)?;
+            let layer = storage
+                .safe_finish_layer(&MediaType::Tar(Compression::None), None, &writer)
+                .await?;
+            remove_file(&path).await.context(error::IoSnafu)?;
+            let artifact = Artifact::builder()
+                .media_type(MediaType::Manifest)
+                .config(
+                    Config::builder()
+                        .id(id.clone())
+                        .metadata(serde_json::json!({
+                            "base": base,
+                            "packages": packages.to_string(),
+                        }))
+                        .build(),
+                )
+                .layers(vec![layer])
+                .build();
+            storage.safe_save(&artifact).await?;
+            storage.upload_build(&id).await?;
+            info!("provisioned environment image {name}");
+            Ok(())
+        }
+        .instrument(span)
+        .await
+    }
+}
+
+unsafe impl Send for ContainerFarm {}
+unsafe impl Sync for ContainerFarm {}
+
+#[async_trait]
+impl FarmImpl for ContainerFarm {
+    async fn setup(&self, log: &Log, storage: &Storage) -> EnvResult<()> {
+        // Fetch our source image
+        trace!(component = "environment", type = "container", "fetching image for environments");
+        let artifact = self
+            .source
+            .cache(log, storage)
+            .await
+            .context(error::SourceSnafu)?;
+
+        // Images are labelled with the digest of what they were loaded from,
+        // so one is only reused when the runtime holds exactly the content
+        // we stored, whichever farm loaded it
+        let name = self.image_tag().await?;
+        trace!(component = "environment", type = "container", "check if the image is already loaded into the container runtime");
+        if self.reuse(log, &name, &self.image_digest().await?)? {
+            info!(component = "environment", type = "container", "image {name} already loaded into container engine");
+            return Ok(());
+        }
+        let base = self.base_tag().await?;
+        let digest = self.source_digest().await?;
+        if base == name || !self.reuse(log, &base, &digest)? {
+            // The image source stores an oci image as an oci archive in the first layer
+            self.load(log, storage, &artifact, &base, &digest).await?;
+        }
+        if let Some(packages) = self.packages.as_ref() {
+            self.provision(log, storage, packages, &base, &name).await?;
+        }
+        Ok(())
+    }
+
+    fn host_access(&self) -> HostAccess {
+        self.host.clone()
+    }
+
+    fn host(&self) -> HostInfo {
+        // Engines on other systems run their containers in a Linux VM
+        HostInfo {
+            os: "linux".to_string(),
+            ..HostInfo::local()
+        }
+    }
+
+    fn host_requires(&self) -> HostRequirements {
+        self.requires.clone()
+    }
+
+    fn platforms(&self) -> Vec<String> {
+        let mut platforms = vec![std::env::consts::ARCH.to_string()];
+        platforms.extend(self.emulate.iter().cloned());
+        platforms
+    }
+
+    async fn identity(&self) -> EnvResult<Option<String>> {
+        Ok(Some(format!("container:{}", self.image_digest().await?)))
+    }
+
+    async fn health(&self) -> Vec<HealthCheck> {
+        let cli = self.config.cli.display().to_string();
+        let version = match tokio::process::Command::new(&self.config.cli)
+            .arg("--version")
+            .output()
+            .await
+        {
+            Ok(output) if output.status.success() => HealthCheck::ok(
+                "runtime",
+                format!(
+                    "{}, at {cli}",
+                    String::from_utf8_lossy(&output.stdout).trim()
+                ),
+            ),
+            Ok(output) => HealthCheck::error(
+                "runtime",
+                format!("{cli} --version failed: {}", last_line(&output.stderr)),
+                "reinstall the container runtime, or pick another with `runtime` in the `[container]` configuration",
+            ),
+            Err(e) => HealthCheck::error(
+                "runtime",
+                format!("cannot run {cli}: {e}"),
+                "install podman, finch or docker, or pick one with `runtime` in the `[container]` configuration",
+            ),
+        };
+        // The CLI can be installed without its engine (daemon or VM) running
+        let engine = match tokio::process::Command::new(&self.config.cli)
+            .arg("info")
+            .output()
+            .await
+        {
+            Ok(output) if output.status.success() => {
+                HealthCheck::ok("engine", format!("{cli} info answered"))
+            }
+            Ok(output) => HealthCheck::error(
+                "engine",
+                last_line(&output.stderr),
+                "start the container engine, such as with `podman machine start`, `finch vm start` or by starting the docker daemon",
+            ),
+            Err(e) => HealthCheck::error(
+                "engine",
+                format!("cannot run {cli}: {e}"),
+                "install podman, finch or docker",
+            ),
+        };
+        vec![version, engine]
+    }
+
     async fn create(&self, _log: &Log, path: &Path) -> EnvResult<Environment> {
         trace!(component = "environment", type = "container", "creating new container environment with workspace at {}", path.display());
+        let image_tag = self.image_tag().await?;
         // Generate a random name
         let mut generator = names::Generator::default();
         let name = generator.next().unwrap();
-        let image_tag = format!(
-            "edo-{}",
-            self.addr
-                .to_string()
-                .strip_prefix("//")
-                .unwrap_or(self.addr.to_string().as_str())
-                .replace('/', "-")
-        );
         Ok(Environment::new(Container {
             name,
             config: self.config.clone(),
             user: self.user.clone(),
+            uid: self.uid,
+            gid: self.gid,
+            host: Mutex::new(self.host.clone()),
             path: path.to_path_buf(),
             running: AtomicBool::new(false),
             tag: image_tag,
             env: DashMap::new(),
+            faketime: self.faketime.clone(),
         }))
     }
 }
@@ -222,10 +757,83 @@ pub struct Container {
     config: ContainerConfig,
     name: String,
     user: String,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    host: Mutex<HostAccess>,
     path: PathBuf,
     tag: String,
     running: AtomicBool,
     env: DashMap<String, String>,
+    faketime: Option<String>,
+}
+
+impl Container {
+    /// Home directory of the build user, where the workspace is mounted.
+    fn home(&self) -> PathBuf {
+        if self.user == "root" {
+            PathBuf::from("/root")
+        } else {
+            PathBuf::from(format!("/home/{}", self.user))
+        }
+    }
+
+    /// Arguments exposing the farm's and transform's host mounts and devices.
+    fn host_args(&self) -> Vec<String> {
+        let host = self.host.lock().unwrap();
+        let mut args = Vec::new();
+        for mount in host.mounts.iter() {
+            args.push("--mount".into());
+            args.push(format!(
+                "src={},dst={},type=bind{}",
+                mount.source.display(),
+                mount.target.display(),
+                if mount.readonly { ",readonly" } else { "" }
+            ));
+        }
+        for device in host.devices.iter() {
+            args.push("--device".into());
+            args.push(device.display().to_string());
+        }
+        args
+    }
+
+    /// Arguments selecting the identity commands run as. Without an explicit
+    /// uid a non-root user is whatever the image runs as by default.
+    fn user_args(&self) -> Vec<String> {
+        if self.user == "root" {
+            vec!["-u".into(), "0:0".into()]
+        } else if let Some(uid) = self.uid {
+            vec![
+                "-u".into(),
+                format!("{uid}:{}", self.gid.unwrap_or(uid)),
+                "--env".into(),
+                format!("HOME={}", self.home().display()),
+            ]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Arguments passing the environment variables, one `--env` each. With
+    /// faketime and a `SOURCE_DATE_EPOCH` the clock also starts at the epoch.
+    fn env_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for entry in self.env.iter() {
+            args.push("--env".into());
+            args.push(format!("{}={}", entry.key(), entry.value()));
+        }
+        let epoch = self
+            .env
+            .get(SOURCE_DATE_EPOCH)
+            .and_then(|x| x.value().parse::<i64>().ok());
+        if let (Some(lib), Some(time)) = (self.faketime.as_ref(), epoch.and_then(faketime)) {
+            args.push("--env".into());
+            args.push(format!("FAKETIME={time}"));
+            args.push("--env".into());
+            args.push(format!("LD_PRELOAD={lib}"));
+        }
+        args
+    }
 }
 
 unsafe impl Send for Container {}
@@ -236,10 +844,8 @@ impl EnvironmentImpl for Container {
     async fn expand(&self, path: &Path) -> EnvResult<PathBuf> {
         Ok(if path.starts_with("/") {
             path.to_path_buf()
-        } else if self.user == "root" {
-            Path::new("/root").join(path)
         } else {
-            Path::new(&format!("/home/{}", self.user)).join(path)
+            self.home().join(path)
         })
     }
 
@@ -282,32 +888,16 @@ impl EnvironmentImpl for Container {
             if !self.config.network {
                 args.push("--network=none".to_string());
             }
-            if self.user == "root" {
-                args.push("--mount".to_string());
-                args.push(format!(
-                    "src={},dst=/root,type=bind",
-                    std::path::absolute(self.path.clone()).unwrap().display()
-                ));
-                args.push("-u".into());
-                args.push("0:0".into());
-            } else {
-                let home_path = format!("/home/{}", self.user);
-                args.push("--mount".into());
-                args.push(format!(
-                    "src={},dst={home_path},type=bind",
-                    std::path::absolute(self.path.clone()).unwrap().display()
-                ));
-            }
-            if !self.env.is_empty() {
-                args.push("--env".into());
-                let env_list = self
-                    .env
-                    .iter()
-                    .map(|x| format!("{}={}", x.key(), x.value()))
-                    .collect::<Vec<_>>()
-                    .join(",");
-                args.push(env_list);
-            }
+            args.extend(self.config.userns_args());
+            args.push("--mount".into());
+            args.push(format!(
+                "src={},dst={},type=bind",
+                std::path::absolute(self.path.clone()).unwrap().display(),
+                self.home().display()
+            ));
+            args.extend(self.user_args());
+            args.extend(self.host_args());
+            args.extend(self.env_args());
             args.push("--name".into());
             args.push(self.name.clone());
             args.push(self.tag.clone());
@@ -354,6 +944,25 @@ impl EnvironmentImpl for Container {
         Ok(())
     }
 
+    async fn snapshot(&self, log: &Log, dir: &Path) -> EnvResult<bool> {
+        // Staging only writes to the workspace directory
+        record!(log, "snapshot", "copying {:?} to {:?}", self.path, dir);
+        copy_tree(&self.path, dir)
+            .await
+            .context(error::SnapshotSnafu)?;
+        Ok(true)
+    }
+
+    async fn restore(&self, log: &Log, dir: &Path) -> EnvResult<bool> {
+        record!(log, "restore", "copying {:?} to {:?}", dir, self.path);
+        // The running container mounts the directory, only replace its contents
+        clear_dir(&self.path).await.context(error::SnapshotSnafu)?;
+        copy_tree(dir, &self.path)
+            .await
+            .context(error::SnapshotSnafu)?;
+        Ok(true)
+    }
+
     async fn create_dir(&self, path: &Path) -> EnvResult<()> {
         let path = self.path.join(path);
         trace!(component = "environment", type = "container", "creating directory at {}", path.display());
@@ -365,12 +974,12 @@ impl EnvironmentImpl for Container {
 
     async fn write(&self, path: &Path, mut reader: Reader) -> EnvResult<()> {
         let file_path = self.path.join(path);
-        if let Some(parent) = file_path.parent() {
-            if !parent.exists() {
-                tokio::fs::create_dir_all(parent)
-                    .await
-                    .context(error::CreateDirectorySnafu)?;
-            }
+        if let Some(parent) = file_path.parent()
+            && !parent.exists()
+        {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .context(error::CreateDirectorySnafu)?;
         }
         trace!(component = "environment", type = "container", "writing contents to file at {}", file_path.display());
         let mut file = File::create(&file_path)
@@ -414,9 +1023,14 @@ impl EnvironmentImpl for Container {
                 .context(error::ReadFileSnafu)?;
         } else {
             trace!(component = "environment", type = "container", "archiving directory at {}", file_path.display());
+            // Packed with the build's epoch, if it has one, as the newest
+            // time any entry carries
+            let epoch = self
+                .env
+                .get(SOURCE_DATE_EPOCH)
+                .and_then(|x| x.value().parse::<u64>().ok());
             let mut archive = tokio_tar::Builder::new(writer);
-            archive
-                .append_dir_all(".", &file_path)
+            archive_dir(&mut archive, &file_path, epoch)
                 .await
                 .context(error::ArchiveSnafu)?;
             archive.finish().await.context(error::ArchiveSnafu)?;
@@ -425,27 +1039,15 @@ impl EnvironmentImpl for Container {
     }
 
     fn shell(&self, path: &Path) -> EnvResult<()> {
-        let work_dir = Path::new("/root").join(path);
+        let work_dir = self.home().join(path);
         let mut args = vec![
             "exec".to_string(),
             "-it".to_string(),
             "--workdir".to_string(),
             format!("{}", work_dir.display()),
         ];
-        if self.user == "root" {
-            args.push("-u".into());
-            args.push("0:0".into());
-        }
-        if !self.env.is_empty() {
-            args.push("--env".into());
-            let env_list = self
-                .env
-                .iter()
-                .map(|x| format!("{}={}", x.key(), x.value()))
-                .collect::<Vec<_>>()
-                .join(",");
-            args.push(env_list);
-        }
+        args.extend(self.user_args());
+        args.extend(self.env_args());
         args.push(self.name.clone());
         let mut run_args = args.clone();
         run_args.push("sh".into());
@@ -454,8 +1056,15 @@ impl EnvironmentImpl for Container {
         Ok(())
     }
 
+    fn expose(&self, access: &HostAccess) -> EnvResult<()> {
+        let mut host = self.host.lock().unwrap();
+        host.mounts.extend(access.mounts.iter().cloned());
+        host.devices.extend(access.devices.iter().cloned());
+        Ok(())
+    }
+
     async fn cmd(&self, log: &Log, id: &Id, path: &Path, cmd: &str) -> EnvResult<bool> {
-        let work_dir = Path::new("/root").join(path);
+        let work_dir = self.home().join(path);
         trace!(component = "environment", type = "container", "running command in {}", work_dir.display());
         async move {
             let mut args = vec![
@@ -464,20 +1073,8 @@ impl EnvironmentImpl for Container {
                 "--workdir".to_string(),
                 format!("{}", work_dir.display()),
             ];
-            if self.user == "root" {
-                args.push("-u".into());
-                args.push("0:0".into());
-            }
-            if !self.env.is_empty() {
-                args.push("--env".into());
-                let env_list = self
-                    .env
-                    .iter()
-                    .map(|x| format!("{}={}", x.key(), x.value()))
-                    .collect::<Vec<_>>()
-                    .join(",");
-                args.push(env_list);
-            }
+            args.extend(self.user_args());
+            args.extend(self.env_args());
             args.push(self.name.clone());
             let mut run_args = args.clone();
             run_args.push("sh".into());
@@ -498,7 +1095,7 @@ impl EnvironmentImpl for Container {
     }
 
     async fn run(&self, log: &Log, id: &Id, path: &Path, command: &Command) -> EnvResult<bool> {
-        let work_dir = Path::new("/root").join(path);
+        let work_dir = self.home().join(path);
         trace!(component = "environment", type = "container", "running command in {}", work_dir.display());
         async move {
             let mut args = vec![
@@ -507,20 +1104,8 @@ impl EnvironmentImpl for Container {
                 "--workdir".to_string(),
                 format!("{}", work_dir.display()),
             ];
-            if self.user == "root" {
-                args.push("-u".into());
-                args.push("0:0".into());
-            }
-            if !self.env.is_empty() {
-                args.push("--env".into());
-                let env_list = self
-                    .env
-                    .iter()
-                    .map(|x| format!("{}={}", x.key(), x.value()))
-                    .collect::<Vec<_>>()
-                    .join(",");
-                args.push(env_list);
-            }
+            args.extend(self.user_args());
+            args.extend(self.env_args());
             args.push(self.name.clone());
             let mut run_args = args.clone();
             run_args.push("sh".into());
@@ -574,6 +1159,10 @@ pub mod error {
         Extract { source: std::io::Error },
         #[snafu(display("io error occured setting up container environment: {source}"))]
         Io { source: std::io::Error },
+        #[snafu(display("container environment field '{field}' should be a '{type_}'"))]
+        Field { field: String, type_: String },
+        #[snafu(display("failed to label image {image} with its digest, see the setup log"))]
+        Label { image: String },
         #[snafu(display("failed to load oci image into container runtime: {source}"))]
         Load { source: std::io::Error },
         #[snafu(display(
@@ -584,8 +1173,16 @@ pub mod error {
         NoSource,
         #[snafu(display("file does not exist: {}", path.display()))]
         NotFound { path: PathBuf },
+        #[snafu(display("'{name}' is not a valid package name"))]
+        PackageName { name: String },
+        #[snafu(display("failed to install {packages} into {image}, see the setup log"))]
+        Provision { image: String, packages: String },
         #[snafu(display("failed to read file: {source}"))]
         ReadFile { source: std::io::Error },
+        #[snafu(display("failed to copy a workspace snapshot: {source}"))]
+        Snapshot { source: std::io::Error },
+        #[snafu(display("uid and gid can only be set for a non-root container user"))]
+        RootIdentity,
         #[snafu(display("failed to execute runtime: {source}"))]
         Runtime { source: std::io::Error },
         #[snafu(display("{source}"))]
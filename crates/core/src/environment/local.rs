@@ -1,9 +1,14 @@
 use async_trait::async_trait;
 use dashmap::DashMap;
-use edo::context::{Addr, Context, FromNode, Log, Node};
-use edo::environment::{Command, EnvResult, Environment, EnvironmentImpl, FarmImpl};
+use edo::context::{Addr, Context, Describe, FromNode, KindSchema, Log, Node};
+use edo::environment::{
+    Command, EnvResult, Environment, EnvironmentImpl, FarmImpl, HostAccess, error::HostAccessSnafu,
+};
 use edo::storage::{Id, Storage};
-use edo::util::{Reader, Writer, cmd, cmd_noinput, cmd_noredirect, from_dash};
+use edo::transform::SOURCE_DATE_EPOCH;
+use edo::util::{
+    Reader, Writer, archive_dir, clear_dir, cmd, cmd_noinput, cmd_noredirect, copy_tree, from_dash,
+};
 use edo::{non_configurable, record};
 use snafu::{ResultExt, ensure};
 use std::io::Cursor;
@@ -33,6 +38,16 @@ impl FarmImpl for LocalFarm {
             env: DashMap::new(),
         }))
     }
+
+    async fn identity(&self) -> EnvResult<Option<String>> {
+        // Builds use whatever toolchain the host has, the platform is all we
+        // can vouch for
+        Ok(Some(format!(
+            "local:{}-{}",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        )))
+    }
 }
 
 #[async_trait]
@@ -46,6 +61,12 @@ impl FromNode for LocalFarm {
 
 non_configurable!(LocalFarm, error::Error);
 
+impl Describe for LocalFarm {
+    fn describe() -> KindSchema {
+        KindSchema::new("Runs transforms on this machine")
+    }
+}
+
 /// A local build environment rooted at a filesystem path.
 pub struct LocalEnv {
     path: PathBuf,
@@ -138,12 +159,12 @@ impl EnvironmentImpl for LocalEnv {
 
     async fn write(&self, path: &Path, mut reader: Reader) -> EnvResult<()> {
         let file_path = self.path.join(path);
-        if let Some(parent) = file_path.parent() {
-            if !parent.exists() {
-                tokio::fs::create_dir_all(parent)
-                    .await
-                    .context(error::CreateDirectorySnafu)?;
-            }
+        if let Some(parent) = file_path.parent()
+            && !parent.exists()
+        {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .context(error::CreateDirectorySnafu)?;
         }
         trace!(component = "environment", type = "local", "writing contents to file at {}", file_path.display());
         let mut file = File::create(&file_path)
@@ -189,9 +210,14 @@ impl EnvironmentImpl for LocalEnv {
                 .context(error::ReadFileSnafu)?;
         } else {
             trace!(component = "environment", type = "local", "archiving directory at {}", file_path.display());
+            // Packed with the build's epoch, if it has one, as the newest
+            // time any entry carries
+            let epoch = self
+                .env
+                .get(SOURCE_DATE_EPOCH)
+                .and_then(|x| x.value().parse::<u64>().ok());
             let mut archive = tokio_tar::Builder::new(writer);
-            archive
-                .append_dir_all(".", &file_path)
+            archive_dir(&mut archive, &file_path, epoch)
                 .await
                 .context(error::ArchiveSnafu)?;
             archive.finish().await.context(error::ArchiveSnafu)?;
@@ -250,6 +276,41 @@ impl EnvironmentImpl for LocalEnv {
             .context(error::FailedSnafu)?;
         Ok(())
     }
+
+    async fn snapshot(&self, log: &Log, dir: &Path) -> EnvResult<bool> {
+        record!(log, "snapshot", "copying {:?} to {:?}", self.path, dir);
+        copy_tree(&self.path, dir)
+            .await
+            .context(error::SnapshotSnafu)?;
+        Ok(true)
+    }
+
+    async fn restore(&self, log: &Log, dir: &Path) -> EnvResult<bool> {
+        record!(log, "restore", "copying {:?} to {:?}", dir, self.path);
+        clear_dir(&self.path).await.context(error::SnapshotSnafu)?;
+        copy_tree(dir, &self.path)
+            .await
+            .context(error::SnapshotSnafu)?;
+        Ok(true)
+    }
+
+    fn expose(&self, access: &HostAccess) -> EnvResult<()> {
+        // Local builds already see the host, so host paths are available as
+        // long as a mount does not ask to move them
+        for mount in access.mounts.iter() {
+            ensure!(
+                mount.source == mount.target,
+                HostAccessSnafu {
+                    reason: format!(
+                        "local environments cannot mount {} at {}",
+                        mount.source.display(),
+                        mount.target.display()
+                    ),
+                }
+            );
+        }
+        Ok(())
+    }
 }
 
 pub mod error {
@@ -283,6 +344,8 @@ pub mod error {
         PathRequired,
         #[snafu(display("failed to read file: {source}"))]
         ReadFile { source: std::io::Error },
+        #[snafu(display("failed to copy a workspace snapshot: {source}"))]
+        Snapshot { source: std::io::Error },
         #[snafu(display("failed to remove a directory: {source}"))]
         RemoveDirectory { source: std::io::Error },
         #[snafu(display("failed to write to file: {source}"))]
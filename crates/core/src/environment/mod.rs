@@ -2,6 +2,12 @@
 pub mod container;
 /// Local environment implementation.
 pub mod local;
+/// Environments with nix packages on the PATH.
+pub mod nix;
+/// Packages installed into derived container images.
+pub mod packages;
 
 pub use container::{Container, ContainerConfig, ContainerFarm};
 pub use local::{LocalEnv, LocalFarm};
+pub use nix::NixFarm;
+pub use packages::{PackageManager, Packages};
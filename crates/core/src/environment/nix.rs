@@ -0,0 +1,371 @@
+use async_trait::async_trait;
+use edo::context::{
+    Addr, Context, Definable, Describe, FieldType, FromNode, HealthCheck, KindSchema, Log, Node,
+};
+use edo::environment::{
+    EnvResult, Environment, Farm, FarmImpl, HostAccess, HostInfo, HostMount, HostRequirements,
+};
+use edo::storage::Storage;
+use edo::util::cmd_noinput;
+use edo::{non_configurable, record};
+use snafu::{OptionExt, ResultExt, ensure};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{ContainerFarm, LocalFarm};
+
+/// Fields of a nix farm that are not passed on to the containers it runs.
+const NIX_FIELDS: [&str; 5] = ["packages", "package_manager", "nixpkgs", "flake", "lock"];
+/// `PATH` of containers after the profile, as most images set it.
+const CONTAINER_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+/// The nix store, mounted read-only into containers.
+const NIX_STORE: &str = "/nix/store";
+/// Flags enabling the flake commands on installs that did not.
+const FEATURES: [&str; 2] = ["--extra-experimental-features", "nix-command flakes"];
+
+/// A farm that realizes nix packages, or a flake output, into a profile and
+/// runs commands with the profile first on the `PATH`.
+///
+/// Commands run on this machine unless the farm has an image `source`, then
+/// they run in containers of it with the host's `/nix/store` mounted
+/// read-only. The flake lock pinning the packages is hashed into the farm's
+/// identity, so updating it rebuilds the transforms run in the farm.
+pub struct NixFarm {
+    /// Project directory relative flake references resolve against.
+    dir: PathBuf,
+    /// Installables realized into the profile, such as `nixpkgs#hello`.
+    installables: Vec<String>,
+    /// Digest of the flake lock pinning the installables.
+    lock: Option<String>,
+    /// Profile the installables are realized into.
+    profile: PathBuf,
+    /// Farm the commands run in.
+    runner: Farm,
+    container: bool,
+}
+
+unsafe impl Send for NixFarm {}
+unsafe impl Sync for NixFarm {}
+
+/// Reads an optional list of strings from `node`.
+fn string_list(node: &Node, field: &str) -> Result<Vec<String>, error::Error> {
+    let Some(list) = node.get(field) else {
+        return Ok(Vec::new());
+    };
+    list.as_list()
+        .and_then(|x| x.iter().map(|x| x.as_string()).collect())
+        .context(error::FieldSnafu {
+            field,
+            type_: "list of strings",
+        })
+}
+
+/// Reads an optional string from `node`.
+fn string_field(node: &Node, field: &str) -> Result<Option<String>, error::Error> {
+    node.get(field)
+        .map(|x| {
+            x.as_string().context(error::FieldSnafu {
+                field,
+                type_: "string",
+            })
+        })
+        .transpose()
+}
+
+/// The directory of a flake reference to a path, such as `./nix#toolchain`
+/// or `path:nix`, `None` for flakes elsewhere.
+fn local_flake(flake: &str) -> Option<&str> {
+    let reference = flake.split(['#', '?']).next().unwrap_or(flake);
+    match reference.strip_prefix("path:") {
+        Some(path) => Some(path),
+        None if reference.starts_with('.') || reference.starts_with('/') => Some(reference),
+        None => None,
+    }
+}
+
+#[async_trait]
+impl FromNode for NixFarm {
+    type Error = error::Error;
+
+    async fn from_node(addr: &Addr, node: &Node, ctx: &Context) -> Result<Self, Self::Error> {
+        let nixpkgs = string_field(node, "nixpkgs")?.unwrap_or("nixpkgs".into());
+        // Bare names are attributes of nixpkgs, anything else an installable
+        let mut installables: Vec<String> = string_list(node, "packages")?
+            .into_iter()
+            .map(|x| {
+                if x.contains('#') {
+                    x
+                } else {
+                    format!("{nixpkgs}#{x}")
+                }
+            })
+            .collect();
+        let flake = string_field(node, "flake")?;
+        installables.extend(flake.clone());
+        ensure!(!installables.is_empty(), error::NoInstallablesSnafu);
+
+        let dir = ctx.project_dir().to_path_buf();
+        let lock = match string_field(node, "lock")? {
+            Some(path) => Some(dir.join(path)),
+            None => flake
+                .as_deref()
+                .and_then(local_flake)
+                .map(|x| dir.join(x).join("flake.lock"))
+                .filter(|x| x.exists()),
+        };
+        let lock = match lock {
+            Some(path) => {
+                let content = tokio::fs::read(&path)
+                    .await
+                    .context(error::LockSnafu { path })?;
+                Some(blake3::hash(&content).to_hex().to_string())
+            }
+            None => None,
+        };
+
+        let container = node.get("source").is_some();
+        let runner = if container {
+            let mut table = node.get_table().unwrap_or_default();
+            for field in NIX_FIELDS {
+                table.remove(field);
+            }
+            let node = Node::new_definition(
+                &node.get_id().unwrap_or("environment".into()),
+                "container",
+                &node.get_name().unwrap_or_default(),
+                table,
+            );
+            Farm::new(ContainerFarm::new(addr, &node, ctx).await?)
+        } else {
+            Farm::new(LocalFarm::default())
+        };
+        let addr = addr.to_string();
+        let slug = addr.strip_prefix("//").unwrap_or(&addr).replace('/', "-");
+        Ok(Self {
+            dir,
+            installables,
+            lock,
+            profile: ctx.data_dir().join("nix").join(slug).join("profile"),
+            runner,
+            container,
+        })
+    }
+}
+
+non_configurable!(NixFarm, error::Error);
+
+impl Describe for NixFarm {
+    fn describe() -> KindSchema {
+        let shared = ContainerFarm::describe()
+            .fields
+            .into_iter()
+            .filter(|x| x.name != "source" && !NIX_FIELDS.contains(&x.name.as_str()));
+        KindSchema::new("Runs transforms with nix packages or a flake output on the PATH")
+            .field(
+                "packages",
+                FieldType::list(FieldType::String),
+                "Attributes of nixpkgs, or installables, realized into the profile",
+            )
+            .field(
+                "nixpkgs",
+                FieldType::String,
+                "Flake the attributes in packages come from, nixpkgs when unset",
+            )
+            .field(
+                "flake",
+                FieldType::String,
+                "Flake output realized into the profile, such as ./nix#toolchain",
+            )
+            .field(
+                "lock",
+                FieldType::String,
+                "flake.lock hashed into transform ids, the one of a local flake when unset",
+            )
+            .field(
+                "source",
+                crate::transform::sources_type(),
+                "Image source to run the commands in containers of, this machine when unset",
+            )
+            .fields(shared)
+    }
+}
+
+#[async_trait]
+impl FarmImpl for NixFarm {
+    async fn setup(&self, log: &Log, storage: &Storage) -> EnvResult<()> {
+        self.runner.setup(log, storage).await?;
+        // Realize into a fresh profile so removed packages do not linger
+        let dir = self.profile.parent().unwrap_or(&self.profile);
+        if dir.exists() {
+            tokio::fs::remove_dir_all(dir)
+                .await
+                .context(error::ProfileSnafu)?;
+        }
+        tokio::fs::create_dir_all(dir)
+            .await
+            .context(error::ProfileSnafu)?;
+        let mut args: Vec<String> = FEATURES.map(String::from).to_vec();
+        args.extend([
+            "profile".into(),
+            "install".into(),
+            "--profile".into(),
+            self.profile.display().to_string(),
+        ]);
+        args.extend(self.installables.iter().cloned());
+        record!(log, "nix", "nix {}", args.join(" "));
+        let realized =
+            cmd_noinput(&self.dir, log, "nix", args, &HashMap::new()).context(error::NixSnafu)?;
+        ensure!(
+            realized,
+            error::RealizeSnafu {
+                installables: self.installables.join(" ")
+            }
+        );
+        info!(component = "environment", type = "nix", "realized {} into {}", self.installables.join(" "), self.profile.display());
+        Ok(())
+    }
+
+    async fn create(&self, log: &Log, path: &Path) -> EnvResult<Environment> {
+        // Resolve the profile, a chain of links, so containers need only the store
+        let profile =
+            tokio::fs::canonicalize(&self.profile)
+                .await
+                .context(error::NotRealizedSnafu {
+                    path: self.profile.clone(),
+                })?;
+        let env = self.runner.create(log, path).await?;
+        let bin = profile.join("bin").display().to_string();
+        let search = if self.container {
+            env.expose(&HostAccess {
+                mounts: vec![HostMount {
+                    source: NIX_STORE.into(),
+                    target: NIX_STORE.into(),
+                    readonly: true,
+                }],
+                devices: Vec::new(),
+            })?;
+            format!("{bin}:{CONTAINER_PATH}")
+        } else {
+            match std::env::var("PATH") {
+                Ok(host) => format!("{bin}:{host}"),
+                Err(_) => bin,
+            }
+        };
+        env.set_env("PATH", &search).await?;
+        Ok(env)
+    }
+
+    fn host_access(&self) -> HostAccess {
+        self.runner.host_access()
+    }
+
+    fn platforms(&self) -> Vec<String> {
+        self.runner.platforms()
+    }
+
+    fn host(&self) -> HostInfo {
+        self.runner.host()
+    }
+
+    fn host_requires(&self) -> HostRequirements {
+        self.runner.host_requires()
+    }
+
+    async fn identity(&self) -> EnvResult<Option<String>> {
+        let mut hash = blake3::Hasher::new();
+        for installable in self.installables.iter() {
+            hash.update(format!("installable={installable}\n").as_bytes());
+        }
+        if let Some(lock) = self.lock.as_ref() {
+            hash.update(format!("lock={lock}\n").as_bytes());
+        }
+        if let Some(runner) = self.runner.identity().await? {
+            hash.update(format!("runner={runner}\n").as_bytes());
+        }
+        Ok(Some(format!("nix:{}", hash.finalize().to_hex())))
+    }
+
+    async fn health(&self) -> Vec<HealthCheck> {
+        let nix = match tokio::process::Command::new("nix")
+            .arg("--version")
+            .output()
+            .await
+        {
+            Ok(output) if output.status.success() => HealthCheck::ok(
+                "nix",
+                String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            ),
+            Ok(output) => HealthCheck::error(
+                "nix",
+                format!(
+                    "nix --version failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+                "reinstall nix",
+            ),
+            Err(e) => HealthCheck::error(
+                "nix",
+                format!("cannot run nix: {e}"),
+                "install nix, see https://nixos.org/download",
+            ),
+        };
+        let mut checks = vec![nix];
+        checks.extend(self.runner.health().await);
+        checks
+    }
+}
+
+pub mod error {
+    use snafu::Snafu;
+    use std::path::PathBuf;
+
+    use edo::{context::error::ContextError, environment::error::EnvironmentError};
+
+    #[derive(Snafu, Debug)]
+    #[snafu(visibility(pub))]
+    pub enum Error {
+        #[snafu(transparent)]
+        Context { source: ContextError },
+        #[snafu(transparent)]
+        Environment { source: EnvironmentError },
+        #[snafu(display("nix environment field '{field}' should be a '{type_}'"))]
+        Field { field: String, type_: String },
+        #[snafu(display("failed to read the flake lock {}: {source}", path.display()))]
+        Lock {
+            path: PathBuf,
+            source: std::io::Error,
+        },
+        #[snafu(display("failed to execute nix: {source}"))]
+        Nix { source: std::io::Error },
+        #[snafu(display("nix environments need packages or a flake"))]
+        NoInstallables,
+        #[snafu(display(
+            "the nix profile at {} has not been realized, run the transform to set it up: {source}",
+            path.display()
+        ))]
+        NotRealized {
+            path: PathBuf,
+            source: std::io::Error,
+        },
+        #[snafu(display("failed to prepare the nix profile directory: {source}"))]
+        Profile { source: std::io::Error },
+        #[snafu(display("failed to realize {installables}, see the setup log"))]
+        Realize { installables: String },
+    }
+
+    impl From<Error> for EnvironmentError {
+        fn from(value: Error) -> Self {
+            Self::Implementation {
+                source: Box::new(value),
+            }
+        }
+    }
+
+    impl From<Error> for ContextError {
+        fn from(value: Error) -> Self {
+            Self::Component {
+                source: Box::new(value),
+            }
+        }
+    }
+}
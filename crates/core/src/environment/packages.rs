@@ -0,0 +1,134 @@
+use edo::context::Node;
+use snafu::{OptionExt, ensure};
+use std::fmt;
+
+use super::container::error;
+
+/// A package manager that can install packages into an environment image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PackageManager {
+    /// Debian and Ubuntu `apt-get`.
+    #[default]
+    Apt,
+    /// Fedora, RHEL and Amazon Linux `dnf`.
+    Dnf,
+    /// Older RHEL and Amazon Linux `yum`.
+    Yum,
+    /// Alpine `apk`.
+    Apk,
+}
+
+impl PackageManager {
+    /// Looks a manager up by the name used in environment definitions.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "apt" => Some(Self::Apt),
+            "dnf" => Some(Self::Dnf),
+            "yum" => Some(Self::Yum),
+            "apk" => Some(Self::Apk),
+            _ => None,
+        }
+    }
+
+    /// The shell command installing `packages` and clearing the package cache.
+    fn install(&self, packages: &str) -> String {
+        match self {
+            Self::Apt => format!(
+                "apt-get update && DEBIAN_FRONTEND=noninteractive apt-get install -y --no-install-recommends {packages} && rm -rf /var/lib/apt/lists/*"
+            ),
+            Self::Dnf => format!("dnf install -y {packages} && dnf clean all"),
+            Self::Yum => format!("yum install -y {packages} && yum clean all"),
+            Self::Apk => format!("apk add --no-cache {packages}"),
+        }
+    }
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Apt => "apt",
+            Self::Dnf => "dnf",
+            Self::Yum => "yum",
+            Self::Apk => "apk",
+        })
+    }
+}
+
+/// Packages an environment declares it needs, installed once into a derived
+/// image instead of by every transform.
+///
+/// ```toml
+/// [environment.build]
+/// kind            = "container"
+/// source          = ["//images/debian"]
+/// packages        = ["build-essential", "cmake"]
+/// package_manager = "apt"  # apt (default), dnf, yum or apk
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Packages {
+    manager: PackageManager,
+    names: Vec<String>,
+}
+
+impl Packages {
+    /// Reads the `packages` and `package_manager` fields of an environment,
+    /// returning `None` when no packages are declared.
+    pub fn from_node(node: &Node) -> Result<Option<Self>, error::Error> {
+        let Some(list) = node.get("packages") else {
+            return Ok(None);
+        };
+        let mut names: Vec<String> = list
+            .as_list()
+            .and_then(|x| x.iter().map(|x| x.as_string()).collect())
+            .context(error::FieldSnafu {
+                field: "packages",
+                type_: "list of strings",
+            })?;
+        for name in names.iter() {
+            // Names end up in a shell command, so only allow what package
+            // managers accept in a name and version constraint
+            ensure!(
+                !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|x| x.is_ascii_alphanumeric() || "+-._:=~*".contains(x)),
+                error::PackageNameSnafu { name: name.clone() }
+            );
+        }
+        names.sort();
+        names.dedup();
+        if names.is_empty() {
+            return Ok(None);
+        }
+        let manager = match node.get("package_manager") {
+            Some(value) => value
+                .as_string()
+                .and_then(|x| PackageManager::parse(&x))
+                .context(error::FieldSnafu {
+                    field: "package_manager",
+                    type_: "one of apt, dnf, yum or apk",
+                })?,
+            None => PackageManager::default(),
+        };
+        Ok(Some(Self { manager, names }))
+    }
+
+    /// The shell command installing the packages.
+    pub fn script(&self) -> String {
+        self.manager.install(&self.names.join(" "))
+    }
+
+    /// A digest of the image derived from the base image with digest `base`.
+    pub fn digest(&self, base: &str) -> String {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(base.as_bytes());
+        hasher.update(self.script().as_bytes());
+        base16::encode_lower(hasher.finalize().as_bytes())
+    }
+}
+
+impl fmt::Display for Packages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.manager, self.names.join(", "))
+    }
+}
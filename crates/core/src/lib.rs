@@ -2,22 +2,25 @@
 extern crate tracing;
 
 use edo::{
-    context::{Context, Definable, DefinableNoContext},
+    context::{Component, Context, Definable, DefinableNoContext, Describe, TemplateProvider},
     environment::Farm,
     source::{Source, Vendor},
     storage::Backend,
     transform::Transform,
 };
-use environment::{ContainerFarm, LocalFarm};
+use environment::{ContainerFarm, LocalFarm, NixFarm};
+use scaffold::CoreTemplates;
 use source::{GitSource, ImageSource, LocalSource, RemoteSource, VendorSource};
 use std::sync::Arc;
-use storage::S3Backend;
+use storage::{ExternalBackend, S3Backend};
 use transform::{ComposeTransform, ImportTransform, ScriptTransform};
 use vendor::ImageVendor;
 
 use crate::transform::{CargoVendorTransform, GoVendorTransform};
 /// Environments and Farms
 pub mod environment;
+/// Project templates
+pub mod scaffold;
 /// Sources
 pub mod source;
 /// Storage backends
@@ -27,17 +30,31 @@ pub mod transform;
 /// Vendors
 pub mod vendor;
 
-/// Registers all built-in component implementations (sources, transforms, environments, storage backends, vendors) with the given context.
+/// Registers all built-in component implementations (sources, transforms, environments, storage backends, vendors) and project templates with the given context.
 pub fn register_core(ctx: &Context) {
     let registry = ctx.registry();
+    registry.register_templates("core", TemplateProvider::new(CoreTemplates));
     registry.register_backend(
         "s3",
         Arc::new(async |addr, node, ctx: Context| {
             Ok(Backend::new(
-                S3Backend::new(&addr, &node, ctx.config()).await?,
+                S3Backend::new(&addr, &node, ctx.config())
+                    .await?
+                    .with_credentials(ctx.credentials())
+                    .await?,
             ))
         }),
     );
+    for kind in ["oci-layout", "bazel-disk"] {
+        registry.register_backend(
+            kind,
+            Arc::new(async |addr, node, ctx: Context| {
+                Ok(Backend::new(
+                    ExternalBackend::new(&addr, &node, ctx.config()).await?,
+                ))
+            }),
+        );
+    }
     registry.register_farm(
         "local",
         Arc::new(async |addr, node, ctx| Ok(Farm::new(LocalFarm::new(&addr, &node, &ctx).await?))),
@@ -48,6 +65,10 @@ pub fn register_core(ctx: &Context) {
             Ok(Farm::new(ContainerFarm::new(&addr, &node, &ctx).await?))
         }),
     );
+    registry.register_farm(
+        "nix",
+        Arc::new(async |addr, node, ctx| Ok(Farm::new(NixFarm::new(&addr, &node, &ctx).await?))),
+    );
     registry.register_source(
         "git",
         Arc::new(async |addr, node, ctx| {
@@ -124,6 +145,31 @@ pub fn register_core(ctx: &Context) {
             Ok(Vendor::new(ImageVendor::new(&addr, &node, &ctx).await?))
         }),
     );
+    describe_core(ctx);
+}
+
+/// Describes the definitions of every built-in kind, for `edo schema`.
+fn describe_core(ctx: &Context) {
+    use Component::*;
+    let registry = ctx.registry();
+    registry.describe(StorageBackend, "s3", S3Backend::describe());
+    for kind in ["oci-layout", "bazel-disk"] {
+        registry.describe(StorageBackend, kind, ExternalBackend::describe());
+    }
+    registry.describe(Environment, "local", LocalFarm::describe());
+    registry.describe(Environment, "container", ContainerFarm::describe());
+    registry.describe(Environment, "nix", NixFarm::describe());
+    registry.describe(Source, "git", GitSource::describe());
+    registry.describe(Source, "local", LocalSource::describe());
+    registry.describe(Source, "image", ImageSource::describe());
+    registry.describe(Source, "remote", RemoteSource::describe());
+    registry.describe(Source, "vendor", VendorSource::describe());
+    registry.describe(Transform, "compose", ComposeTransform::describe());
+    registry.describe(Transform, "import", ImportTransform::describe());
+    registry.describe(Transform, "script", ScriptTransform::describe());
+    registry.describe(Transform, "cargo-vendor", CargoVendorTransform::describe());
+    registry.describe(Transform, "go-vendor", GoVendorTransform::describe());
+    registry.describe(Vendor, "image", ImageVendor::describe());
 }
 /// Error types for the core plugin.
 pub mod error {
@@ -0,0 +1,193 @@
+//! The project templates `edo init` offers out of the box.
+
+use edo::context::{ContextResult, error};
+use edo::context::{ScaffoldFile, TemplateInfo, TemplateProviderImpl};
+use std::collections::BTreeMap;
+
+/// The root build file, holding the schema version and the caches.
+const ROOT: &str = r#"schema-version = "1"
+
+# Artifacts are kept in .edo by default. Uncomment to share built artifacts
+# through an S3 bucket as well:
+#
+# [cache.build]
+# kind   = "s3"
+# bucket = "my-build-cache"
+"#;
+
+/// The root build file when `--s3-bucket` is given.
+const ROOT_S3: &str = r#"schema-version = "1"
+
+# Built artifacts are shared through S3, on top of the local cache in .edo
+[cache.build]
+kind   = "s3"
+bucket = "{bucket}"
+"#;
+
+const RUST: &str = r#"schema-version = "1"
+
+# Everything under {name}/code is staged into the build-root
+[source.code]
+kind       = "local"
+path       = "{name}/code"
+out        = "."
+is_archive = false
+
+# Builds run directly on this machine
+[environment.local]
+kind = "local"
+
+# Run with `edo run //{name}/build`, then `edo checkout //{name}/build out`
+[transform.build]
+kind        = "script"
+environment = "//{name}/local"
+source      = ["code"]
+commands    = [
+  "cargo build --release --offline",
+  "mkdir -p {{install-root}}/bin",
+  "cp target/release/{name} {{install-root}}/bin/{name}",
+]
+"#;
+
+const RUST_MANIFEST: &str = r#"[package]
+name    = "{name}"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+"#;
+
+const RUST_MAIN: &str = r#"fn main() {
+    println!("Hello from {name}!");
+}
+"#;
+
+const C: &str = r#"schema-version = "1"
+
+# Everything under {name}/code is staged into the build-root
+[source.code]
+kind       = "local"
+path       = "{name}/code"
+out        = "."
+is_archive = false
+
+# Builds run directly on this machine
+[environment.local]
+kind = "local"
+
+# Run with `edo run //{name}/build`, then `edo checkout //{name}/build out`
+[transform.build]
+kind        = "script"
+environment = "//{name}/local"
+source      = ["code"]
+commands    = [
+  "mkdir -p {{install-root}}/bin",
+  "cc -O2 -o {{install-root}}/bin/{name} hello.c",
+]
+"#;
+
+const IMAGE: &str = r#"schema-version = "1"
+
+# Where images are pulled from
+[vendor.public-ecr]
+kind = "image"
+uri  = "public.ecr.aws/docker/library"
+
+# The image builds run in, pinned in edo.lock.json by `edo update`
+[requires.gcc]
+kind = "image"
+at   = "=14.3.0"
+
+# Builds run in a container of that image, through docker, podman or finch
+[environment.gcc]
+kind   = "container"
+source = ["//{name}/gcc"]
+
+# Everything under {name}/code is staged into the build-root
+[source.code]
+kind       = "local"
+path       = "{name}/code"
+out        = "."
+is_archive = false
+
+# Run `edo update` once, then `edo run //{name}/build`
+[transform.build]
+kind        = "script"
+environment = "//{name}/gcc"
+source      = ["code"]
+commands    = [
+  "mkdir -p {{install-root}}/bin",
+  "gcc -O2 -o {{install-root}}/bin/{name} hello.c",
+]
+"#;
+
+const C_MAIN: &str = r#"#include <stdio.h>
+
+int main(void) {
+    printf("Hello from {name}!\n");
+    return 0;
+}
+"#;
+
+/// The builtin templates, registered as the `core` provider.
+///
+/// Every template writes a root `edo.toml` and a package named after the
+/// `name` parameter (default `hello`). A `s3-bucket` parameter adds a shared
+/// build cache to the root `edo.toml`.
+pub struct CoreTemplates;
+
+impl TemplateProviderImpl for CoreTemplates {
+    fn templates(&self) -> Vec<TemplateInfo> {
+        [
+            ("c", "a C program built on this machine"),
+            ("image", "a C program built in a container image"),
+            ("rust", "a rust binary built on this machine"),
+        ]
+        .map(|(name, description)| TemplateInfo {
+            name: name.to_string(),
+            description: description.to_string(),
+        })
+        .to_vec()
+    }
+
+    fn render(
+        &self,
+        name: &str,
+        params: &BTreeMap<String, String>,
+    ) -> ContextResult<Vec<ScaffoldFile>> {
+        let package = params.get("name").map(String::as_str).unwrap_or("hello");
+        snafu::ensure!(
+            !package.is_empty()
+                && package
+                    .chars()
+                    .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-'),
+            error::TemplateSnafu {
+                template: format!("core:{name}"),
+                reason: format!(
+                    "'{package}' is not a valid package name, use letters, digits, '_' and '-'"
+                ),
+            }
+        );
+        let root = match params.get("s3-bucket") {
+            Some(bucket) => ROOT_S3.replace("{bucket}", bucket),
+            None => ROOT.to_string(),
+        };
+        let files: &[(&str, &str)] = match name {
+            "rust" => &[
+                ("edo.toml", RUST),
+                ("code/Cargo.toml", RUST_MANIFEST),
+                ("code/src/main.rs", RUST_MAIN),
+            ],
+            "image" => &[("edo.toml", IMAGE), ("code/hello.c", C_MAIN)],
+            _ => &[("edo.toml", C), ("code/hello.c", C_MAIN)],
+        };
+        let mut result = vec![ScaffoldFile::new("edo.toml", root)];
+        for (path, text) in files {
+            result.push(ScaffoldFile::new(
+                format!("{package}/{path}"),
+                text.replace("{name}", package),
+            ));
+        }
+        Ok(result)
+    }
+}
@@ -1,10 +1,13 @@
 use async_trait::async_trait;
-use edo::context::{Addr, Context, FromNode, Log, Node, non_configurable};
+use edo::context::{
+    Addr, Context, Credentials, Describe, FieldType, FromNode, KindSchema, Log, Node, Progress,
+    non_configurable,
+};
 use edo::environment::Environment;
 use edo::record;
-use edo::source::{SourceImpl, SourceResult};
-use edo::storage::{Artifact, Compression, Config, Id, MediaType, Storage};
-use edo::util::cmd_noinput;
+use edo::source::{SourceImpl, SourceResult, license_field};
+use edo::storage::{Access, AccessKind, Artifact, Compression, Config, Id, MediaType, Storage};
+use edo::util::{cmd_collect_out, cmd_noinput};
 use snafu::{OptionExt, ResultExt};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -17,13 +20,14 @@ pub struct GitSource {
     url: String,
     reference: String,
     out: PathBuf,
+    credentials: Credentials,
 }
 
 #[async_trait]
 impl FromNode for GitSource {
     type Error = error::Error;
 
-    async fn from_node(_addr: &Addr, node: &Node, _: &Context) -> Result<Self, error::Error> {
+    async fn from_node(_addr: &Addr, node: &Node, ctx: &Context) -> Result<Self, error::Error> {
         node.validate_keys(&["url", "ref", "out"])?;
         let url = node
             .get("url")
@@ -53,12 +57,23 @@ impl FromNode for GitSource {
             url,
             reference,
             out: PathBuf::from(out),
+            credentials: ctx.credentials().clone(),
         })
     }
 }
 
 non_configurable!(GitSource, error::Error);
 
+impl Describe for GitSource {
+    fn describe() -> KindSchema {
+        KindSchema::new("A git repository checked out at a revision")
+            .required("url", FieldType::String, "Repository to clone")
+            .required("ref", FieldType::String, "Revision to check out")
+            .required("out", FieldType::String, "Where the checkout is staged")
+            .fields([license_field()])
+    }
+}
+
 #[async_trait]
 impl SourceImpl for GitSource {
     async fn get_unique_id(&self) -> SourceResult<Id> {
@@ -76,7 +91,27 @@ impl SourceImpl for GitSource {
         trace!(component = "source", type = "git", "cloning git repository: git clone -b {} {}", self.reference, self.url);
         record!(log, "clone", "git clone -b {} {}", self.reference, self.url);
         async move {
+            // Cloning, archiving and saving are reported as three steps
+            let progress = Progress::current();
+            progress.set_total(3);
+            progress.message("cloning");
             let temp = tempdir().context(error::TempDirectorySnafu)?;
+            // Credentials go through the environment, so they never show up
+            // in the command line or the log
+            let mut env = HashMap::new();
+            if (self.url.starts_with("http://") || self.url.starts_with("https://"))
+                && let Some(credential) = self.credentials.get(&self.url).await?
+            {
+                env.insert("GIT_CONFIG_COUNT".to_string(), "1".to_string());
+                env.insert(
+                    "GIT_CONFIG_KEY_0".to_string(),
+                    "http.extraHeader".to_string(),
+                );
+                env.insert(
+                    "GIT_CONFIG_VALUE_0".to_string(),
+                    format!("Authorization: {}", credential.authorization()),
+                );
+            }
             cmd_noinput(
                 ".",
                 log,
@@ -88,9 +123,20 @@ impl SourceImpl for GitSource {
                     self.url.clone(),
                     temp.path().to_string_lossy().to_string(),
                 ],
+                &env,
+            )
+            .context(error::GitSnafu)?;
+            // The commit the reference resolved to is pinned in the lock file
+            let commit = cmd_collect_out(
+                temp.path(),
+                log,
+                "git",
+                ["rev-parse", "HEAD"],
                 &HashMap::new(),
             )
             .context(error::GitSnafu)?;
+            let commit = String::from_utf8_lossy(&commit).trim().to_string();
+            progress.advance(1);
             // Make our initial artifact manifest
             let mut artifact = Artifact::builder()
                 .media_type(MediaType::Manifest)
@@ -98,7 +144,8 @@ impl SourceImpl for GitSource {
                     Config::builder()
                         .metadata(serde_json::json!({
                             "repository": self.url,
-                            "reference": self.reference
+                            "reference": self.reference,
+                            "commit": commit
                         }))
                         .id(id.clone())
                         .build(),
@@ -106,6 +153,7 @@ impl SourceImpl for GitSource {
                 .build();
 
             // Now we want to open a single layer which we will archive the source
+            progress.message("archiving");
             let mut writer = storage.safe_start_layer().await?;
             let mut archive = tokio_tar::Builder::new(writer.clone());
             archive
@@ -120,8 +168,15 @@ impl SourceImpl for GitSource {
                     .safe_finish_layer(&MediaType::Tar(Compression::None), None, &writer)
                     .await?,
             );
+            progress.advance(1);
             // Now save the artifact itself
+            progress.message("saving");
             storage.safe_save(&artifact).await?;
+            progress.advance(1);
+            storage.audit().record(artifact.layers().iter().fold(
+                Access::new(AccessKind::Fetch, self.url.clone()).subject(self.reference.clone()),
+                |access, layer| access.digest(layer.digest().to_string()),
+            ));
             Ok(artifact.clone())
         }
         .instrument(info_span!(
@@ -157,6 +212,21 @@ impl SourceImpl for GitSource {
         env.unpack(&out_path, reader).await?;
         Ok(())
     }
+
+    fn resolved(&self, artifact: &Artifact) -> Option<String> {
+        artifact.config().metadata()["commit"]
+            .as_str()
+            .map(str::to_string)
+    }
+
+    fn owned_paths(&self) -> Vec<PathBuf> {
+        // Only repositories cloned from the project tree own local files
+        let path = self.url.strip_prefix("file://").unwrap_or(&self.url);
+        if path.contains("://") || !Path::new(path).exists() {
+            return Vec::new();
+        }
+        vec![PathBuf::from(path)]
+    }
 }
 
 pub mod error {
@@ -0,0 +1,531 @@
+//! Layer filtering and flattening for OCI images.
+//!
+//! An image source can reduce the image it pulls before it is stored: keep
+//! only some of its layers, drop paths from every layer, or squash the
+//! layers into a single root filesystem layer. The rewrite works on an
+//! unpacked OCI image layout and updates every manifest and config that
+//! references the layers, so the result can still be loaded by a container
+//! runtime.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+use edo::context::Node;
+use edo::storage::{Compression, DigestAlgorithm};
+use edo::util::glob_match;
+use futures::StreamExt;
+use serde_json::{Value, json};
+use snafu::{OptionExt, ResultExt, ensure};
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+use tokio_tar::{Archive, Builder, EntryType, Header};
+
+use super::oci::error;
+
+type Result<T> = std::result::Result<T, error::ImageSourceError>;
+type LayerReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// Media type of the uncompressed layers written by a rewrite.
+const LAYER_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar";
+/// Prefix marking a file deleted from the layers below.
+const WHITEOUT: &str = ".wh.";
+/// Marks a directory whose contents in the layers below are hidden.
+const OPAQUE: &str = ".wh..wh..opq";
+/// Longest link target that fits into a plain tar header.
+const MAX_LINK_NAME: usize = 100;
+
+/// How an image is reduced before it is stored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LayerFilter {
+    /// Indexes of the layers to keep, base layer first; negative indexes
+    /// count from the top layer.
+    layers: Option<Vec<i64>>,
+    /// Glob patterns of paths removed from every layer along with their contents.
+    exclude: Vec<String>,
+    /// Squash the kept layers into one.
+    flatten: bool,
+}
+
+impl LayerFilter {
+    /// Reads the optional `layers`, `exclude` and `flatten` fields of an image source.
+    pub fn from_node(node: &Node) -> Result<Self> {
+        let layers = match node.get("layers") {
+            Some(list) => Some(
+                list.as_list()
+                    .context(error::FieldSnafu {
+                        field: "layers",
+                        type_: "list of integers",
+                    })?
+                    .iter()
+                    .map(|x| {
+                        x.as_int().context(error::FieldSnafu {
+                            field: "layers",
+                            type_: "list of integers",
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            None => None,
+        };
+        let exclude = match node.get("exclude") {
+            Some(list) => list
+                .as_list()
+                .context(error::FieldSnafu {
+                    field: "exclude",
+                    type_: "list of strings",
+                })?
+                .iter()
+                .map(|x| {
+                    x.as_string()
+                        .map(|x| x.trim_matches('/').to_string())
+                        .context(error::FieldSnafu {
+                            field: "exclude",
+                            type_: "list of strings",
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        let flatten = match node.get("flatten") {
+            Some(flag) => flag.as_bool().context(error::FieldSnafu {
+                field: "flatten",
+                type_: "bool",
+            })?,
+            None => false,
+        };
+        Ok(Self {
+            layers,
+            exclude,
+            flatten,
+        })
+    }
+
+    /// Returns `true` when the image is stored as pulled.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_none() && self.exclude.is_empty() && !self.flatten
+    }
+
+    /// A stable description of the filter, folded into the source id so a
+    /// filtered image never shares an artifact with the unfiltered one.
+    pub fn fingerprint(&self) -> String {
+        format!(
+            "layers={:?};exclude={:?};flatten={}",
+            self.layers, self.exclude, self.flatten
+        )
+    }
+
+    /// Rewrites the OCI image layout unpacked at `root` in place.
+    pub async fn apply(&self, root: &Path) -> Result<()> {
+        let index_path = root.join("index.json");
+        let mut index = read_json(&index_path).await?;
+        let manifests = index
+            .get_mut("manifests")
+            .and_then(|x| x.as_array_mut())
+            .context(error::LayoutSnafu {
+                reason: "index.json has no manifests",
+            })?;
+        for descriptor in manifests.iter_mut() {
+            let manifest = self
+                .rewrite_manifest(root, &read_json(&blob_path(root, descriptor)?).await?)
+                .await?;
+            set_descriptor(descriptor, &write_blob(root, &manifest).await?);
+        }
+        tokio::fs::write(
+            &index_path,
+            serde_json::to_vec(&index).context(error::SerializeSnafu)?,
+        )
+        .await
+        .context(error::IoSnafu)?;
+        remove_unreferenced(root, &index).await
+    }
+
+    async fn rewrite_manifest(&self, root: &Path, manifest: &Value) -> Result<Value> {
+        let mut manifest = manifest.clone();
+        let mut config = read_json(&blob_path(root, &manifest["config"])?).await?;
+        let layers = manifest["layers"]
+            .as_array()
+            .cloned()
+            .context(error::LayoutSnafu {
+                reason: "image manifest has no layers",
+            })?;
+        let diff_ids =
+            config["rootfs"]["diff_ids"]
+                .as_array()
+                .cloned()
+                .context(error::LayoutSnafu {
+                    reason: "image config has no rootfs diff ids",
+                })?;
+        ensure!(
+            layers.len() == diff_ids.len(),
+            error::LayoutSnafu {
+                reason: "image manifest and config disagree on the number of layers",
+            }
+        );
+        let selected = self.select(layers.len())?;
+        let kept: Vec<Value> = selected.iter().map(|x| layers[*x].clone()).collect();
+
+        let (new_layers, new_diff_ids) = if self.flatten {
+            let (descriptor, diff_id) = self.flatten_layers(root, &kept).await?;
+            (vec![descriptor], vec![diff_id])
+        } else if !self.exclude.is_empty() {
+            let mut new_layers = Vec::new();
+            let mut new_diff_ids = Vec::new();
+            for layer in kept.iter() {
+                let (descriptor, diff_id) = self.filter_layer(root, layer).await?;
+                new_layers.push(descriptor);
+                new_diff_ids.push(diff_id);
+            }
+            (new_layers, new_diff_ids)
+        } else {
+            let diff_ids = selected.iter().map(|x| diff_ids[*x].clone()).collect();
+            (kept, diff_ids)
+        };
+
+        // History entries that created a layer follow the layers, the rest
+        // only describe configuration and are always kept.
+        if let Some(history) = config.get("history").and_then(|x| x.as_array()) {
+            let mut layer = 0;
+            let mut new_history = Vec::new();
+            for entry in history.iter() {
+                if entry["empty_layer"].as_bool().unwrap_or(false) {
+                    new_history.push(entry.clone());
+                    continue;
+                }
+                if !self.flatten && selected.contains(&layer) {
+                    new_history.push(entry.clone());
+                }
+                layer += 1;
+            }
+            if self.flatten {
+                new_history.push(json!({
+                    "created_by": format!("edo: flattened {} layers", selected.len()),
+                }));
+            }
+            config["history"] = Value::Array(new_history);
+        }
+        config["rootfs"]["diff_ids"] = Value::Array(new_diff_ids);
+        manifest["layers"] = Value::Array(new_layers);
+        set_descriptor(&mut manifest["config"], &write_blob(root, &config).await?);
+        Ok(manifest)
+    }
+
+    /// Resolves the configured layer indexes against an image with `count` layers.
+    fn select(&self, count: usize) -> Result<Vec<usize>> {
+        let Some(layers) = self.layers.as_ref() else {
+            return Ok((0..count).collect());
+        };
+        let mut selected = BTreeSet::new();
+        for index in layers.iter() {
+            let resolved = if *index < 0 {
+                count as i64 + index
+            } else {
+                *index
+            };
+            ensure!(
+                resolved >= 0 && (resolved as usize) < count,
+                error::LayerIndexSnafu {
+                    index: *index,
+                    count
+                }
+            );
+            selected.insert(resolved as usize);
+        }
+        Ok(selected.into_iter().collect())
+    }
+
+    fn excluded(&self, path: &str) -> bool {
+        self.exclude
+            .iter()
+            .any(|x| glob_match(&format!("{x}/**"), path))
+    }
+
+    /// Copies `layer` without the excluded paths.
+    async fn filter_layer(&self, root: &Path, layer: &Value) -> Result<(Value, Value)> {
+        let mut writer = LayerWriter::create(root).await?;
+        let mut archive = Archive::new(open_layer(root, layer).await?);
+        let mut entries = archive.entries().context(error::IoSnafu)?;
+        while let Some(entry) = entries.next().await {
+            let entry = entry.context(error::IoSnafu)?;
+            let path = normalize(&entry.path().context(error::IoSnafu)?);
+            if !self.excluded(&path) {
+                writer.append(entry).await?;
+            }
+        }
+        writer.finish(root).await
+    }
+
+    /// Squashes `layers`, base first, into a single layer.
+    ///
+    /// The layers are read top down first to work out which entry of which
+    /// layer is visible once whiteouts are applied, then bottom up to write
+    /// the visible entries so hard links always follow their targets.
+    async fn flatten_layers(&self, root: &Path, layers: &[Value]) -> Result<(Value, Value)> {
+        let mut visible = vec![BTreeSet::new(); layers.len()];
+        let mut seen = BTreeSet::new();
+        let mut hidden = BTreeSet::new();
+        let mut opaque = BTreeSet::new();
+        for (position, layer) in layers.iter().enumerate().rev() {
+            let mut new_hidden = Vec::new();
+            let mut new_opaque = Vec::new();
+            let mut archive = Archive::new(open_layer(root, layer).await?);
+            let mut entries = archive.entries().context(error::IoSnafu)?;
+            while let Some(entry) = entries.next().await {
+                let entry = entry.context(error::IoSnafu)?;
+                let path = normalize(&entry.path().context(error::IoSnafu)?);
+                let (parent, name) = match path.rsplit_once('/') {
+                    Some((parent, name)) => (parent.to_string(), name),
+                    None => (String::new(), path.as_str()),
+                };
+                if name == OPAQUE {
+                    new_opaque.push(parent);
+                    continue;
+                }
+                if let Some(target) = name.strip_prefix(WHITEOUT) {
+                    new_hidden.push(join(&parent, target));
+                    continue;
+                }
+                if path.is_empty()
+                    || self.excluded(&path)
+                    || is_hidden(&path, &hidden, &opaque)
+                    || !seen.insert(path.clone())
+                {
+                    continue;
+                }
+                visible[position].insert(path);
+            }
+            hidden.extend(new_hidden);
+            opaque.extend(new_opaque);
+        }
+
+        let mut writer = LayerWriter::create(root).await?;
+        let mut written = BTreeSet::new();
+        for (position, layer) in layers.iter().enumerate() {
+            let mut archive = Archive::new(open_layer(root, layer).await?);
+            let mut entries = archive.entries().context(error::IoSnafu)?;
+            while let Some(entry) = entries.next().await {
+                let entry = entry.context(error::IoSnafu)?;
+                let path = normalize(&entry.path().context(error::IoSnafu)?);
+                if !visible[position].remove(&path) {
+                    continue;
+                }
+                if entry.header().entry_type() == EntryType::Link {
+                    let target = entry
+                        .link_name()
+                        .context(error::IoSnafu)?
+                        .map(|x| normalize(&x))
+                        .unwrap_or_default();
+                    if !written.contains(&target) {
+                        warn!(
+                            component = "source",
+                            type = "oci",
+                            "dropping hard link {path} as its target {target} was removed"
+                        );
+                        continue;
+                    }
+                }
+                writer.append(entry).await?;
+                written.insert(path);
+            }
+        }
+        writer.finish(root).await
+    }
+}
+
+/// An uncompressed layer being written into the layout's blob directory.
+struct LayerWriter {
+    path: PathBuf,
+    builder: Builder<File>,
+}
+
+impl LayerWriter {
+    async fn create(root: &Path) -> Result<Self> {
+        let path = root.join(format!("blobs/layer-{}.tar", uuid::Uuid::now_v7()));
+        let file = File::create(&path).await.context(error::IoSnafu)?;
+        Ok(Self {
+            path,
+            builder: Builder::new(file),
+        })
+    }
+
+    async fn append<R: AsyncRead + Unpin>(&mut self, entry: tokio_tar::Entry<R>) -> Result<()> {
+        let path = entry.path().context(error::IoSnafu)?.into_owned();
+        let mut header = entry.header().clone();
+        // Long link targets are carried by a GNU extension entry, the
+        // header itself only holds a truncated copy
+        if let Some(target) = entry.link_name().context(error::IoSnafu)?
+            && target.as_os_str().len() > MAX_LINK_NAME
+        {
+            let mut data = target.as_os_str().as_encoded_bytes().to_vec();
+            data.push(0);
+            let mut long = Header::new_gnu();
+            long.as_gnu_mut()
+                .context(error::LayoutSnafu {
+                    reason: "failed to create a long link name header",
+                })?
+                .name[..13]
+                .copy_from_slice(b"././@LongLink");
+            long.set_entry_type(EntryType::GNULongLink);
+            long.set_mode(0o644);
+            long.set_size(data.len() as u64);
+            long.set_cksum();
+            self.builder
+                .append(&long, data.as_slice())
+                .await
+                .context(error::IoSnafu)?;
+        }
+        self.builder
+            .append_data(&mut header, path, entry)
+            .await
+            .context(error::IoSnafu)
+    }
+
+    /// Moves the finished layer to its content address, returning its
+    /// descriptor and diff id.
+    async fn finish(self, root: &Path) -> Result<(Value, Value)> {
+        let mut file = self.builder.into_inner().await.context(error::IoSnafu)?;
+        tokio::io::AsyncWriteExt::flush(&mut file)
+            .await
+            .context(error::IoSnafu)?;
+        drop(file);
+        let mut hasher = DigestAlgorithm::Sha256.hasher();
+        let mut reader = File::open(&self.path).await.context(error::IoSnafu)?;
+        let mut buffer = vec![0; 64 * 1024];
+        let mut size = 0;
+        loop {
+            let read = reader.read(&mut buffer).await.context(error::IoSnafu)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            size += read as u64;
+        }
+        let digest = format!("sha256:{}", hasher.finalize());
+        tokio::fs::rename(&self.path, root.join("blobs/sha256").join(&digest[7..]))
+            .await
+            .context(error::IoSnafu)?;
+        Ok((
+            json!({
+                "mediaType": LAYER_MEDIA_TYPE,
+                "digest": digest,
+                "size": size,
+            }),
+            Value::String(digest),
+        ))
+    }
+}
+
+/// A descriptor's digest and size.
+struct Blob {
+    digest: String,
+    size: u64,
+}
+
+fn set_descriptor(descriptor: &mut Value, blob: &Blob) {
+    descriptor["digest"] = Value::String(blob.digest.clone());
+    descriptor["size"] = json!(blob.size);
+}
+
+fn blob_path(root: &Path, descriptor: &Value) -> Result<PathBuf> {
+    let digest = descriptor["digest"].as_str().context(error::LayoutSnafu {
+        reason: "descriptor has no digest",
+    })?;
+    let (algorithm, hex) = digest.split_once(':').context(error::LayoutSnafu {
+        reason: format!("malformed digest '{digest}'"),
+    })?;
+    Ok(root.join("blobs").join(algorithm).join(hex))
+}
+
+async fn read_json(path: &Path) -> Result<Value> {
+    let content = tokio::fs::read(path).await.context(error::IoSnafu)?;
+    serde_json::from_slice(&content).context(error::SerializeSnafu)
+}
+
+async fn write_blob(root: &Path, value: &Value) -> Result<Blob> {
+    let content = serde_json::to_vec(value).context(error::SerializeSnafu)?;
+    let mut hasher = DigestAlgorithm::Sha256.hasher();
+    hasher.update(&content);
+    let hex = hasher.finalize();
+    tokio::fs::write(root.join("blobs/sha256").join(&hex), &content)
+        .await
+        .context(error::IoSnafu)?;
+    Ok(Blob {
+        digest: format!("sha256:{hex}"),
+        size: content.len() as u64,
+    })
+}
+
+async fn open_layer(root: &Path, layer: &Value) -> Result<LayerReader> {
+    let media_type = layer["mediaType"].as_str().unwrap_or(LAYER_MEDIA_TYPE);
+    let file = File::open(blob_path(root, layer)?)
+        .await
+        .context(error::IoSnafu)?;
+    let reader = BufReader::new(file);
+    let (_, compression) = Compression::detect(media_type)?;
+    Ok(match compression {
+        Compression::Gzip => Box::pin(GzipDecoder::new(reader)),
+        Compression::Zstd => Box::pin(ZstdDecoder::new(reader)),
+        Compression::None => Box::pin(reader),
+        _ => {
+            return error::LayoutSnafu {
+                reason: format!("unsupported layer media type '{media_type}'"),
+            }
+            .fail();
+        }
+    })
+}
+
+/// Deletes every blob no longer referenced from `index`.
+async fn remove_unreferenced(root: &Path, index: &Value) -> Result<()> {
+    let mut referenced = BTreeSet::new();
+    for descriptor in index["manifests"].as_array().into_iter().flatten() {
+        let manifest_path = blob_path(root, descriptor)?;
+        let manifest = read_json(&manifest_path).await?;
+        referenced.insert(manifest_path);
+        referenced.insert(blob_path(root, &manifest["config"])?);
+        for layer in manifest["layers"].as_array().into_iter().flatten() {
+            referenced.insert(blob_path(root, layer)?);
+        }
+    }
+    let blobs = root.join("blobs/sha256");
+    let mut entries = tokio::fs::read_dir(&blobs).await.context(error::IoSnafu)?;
+    while let Some(entry) = entries.next_entry().await.context(error::IoSnafu)? {
+        if !referenced.contains(&entry.path()) {
+            tokio::fs::remove_file(entry.path())
+                .await
+                .context(error::IoSnafu)?;
+        }
+    }
+    Ok(())
+}
+
+/// Strips the `./` and `/` decorations tar paths may carry.
+fn normalize(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let path = path.trim_start_matches("./").trim_matches('/');
+    if path == "." {
+        String::new()
+    } else {
+        path.to_string()
+    }
+}
+
+fn join(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{parent}/{name}")
+    }
+}
+
+/// Whether a whiteout in an upper layer removed `path`.
+fn is_hidden(path: &str, hidden: &BTreeSet<String>, opaque: &BTreeSet<String>) -> bool {
+    if hidden.contains(path) || opaque.contains("") {
+        return true;
+    }
+    path.match_indices('/').any(|(at, _)| {
+        let ancestor = &path[..at];
+        hidden.contains(ancestor) || opaque.contains(ancestor)
+    })
+}
@@ -1,20 +1,52 @@
 use async_trait::async_trait;
-use edo::context::{Addr, Context, FromNode, Log, Node, non_configurable};
+use edo::context::{
+    Addr, Context, Describe, FieldType, FromNode, KindSchema, Log, Node, non_configurable,
+};
 use edo::environment::Environment;
 use edo::record;
-use edo::source::{SourceImpl, SourceResult};
+use edo::source::{SourceImpl, SourceResult, license_field};
 use edo::storage::{Artifact, Compression, Config, Id, MediaType, Storage};
+use edo::util::glob_files;
 use merkle_hash::MerkleTree;
-use snafu::{OptionExt, ResultExt};
+use snafu::{OptionExt, ResultExt, ensure};
 use std::path::{Path, PathBuf, absolute};
 use tokio::{fs::File, io::AsyncWriteExt};
 use tokio_tar::Builder;
 
 /// A source backed by a local filesystem path.
+///
+/// A directory can be narrowed to the files matching the optional `include`
+/// and `exclude` glob patterns, relative to `path`. The matched files, not
+/// the whole directory, then determine the source digest.
 pub struct LocalSource {
     path: PathBuf,
     out: PathBuf,
     is_archive: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+/// Reads an optional list of glob patterns, rejecting ones that escape the source directory.
+fn patterns(node: &Node, field: &str) -> Result<Vec<String>, error::Error> {
+    let Some(list) = node.get(field) else {
+        return Ok(Vec::new());
+    };
+    let mut patterns = Vec::new();
+    for entry in list.as_list().context(error::FieldSnafu {
+        field,
+        type_: "list of strings",
+    })? {
+        let pattern = entry.as_string().context(error::FieldSnafu {
+            field,
+            type_: "list of strings",
+        })?;
+        ensure!(
+            !pattern.starts_with('/') && !pattern.split('/').any(|x| x == ".."),
+            error::PatternSnafu { pattern }
+        );
+        patterns.push(pattern);
+    }
+    Ok(patterns)
 }
 
 #[async_trait]
@@ -51,23 +83,85 @@ impl FromNode for LocalSource {
             path: PathBuf::from(path),
             out: PathBuf::from(out),
             is_archive,
+            include: patterns(node, "include")?,
+            exclude: patterns(node, "exclude")?,
         })
     }
 }
 
 non_configurable!(LocalSource, error::Error);
 
+impl Describe for LocalSource {
+    fn describe() -> KindSchema {
+        KindSchema::new("A file or directory of the project")
+            .required(
+                "path",
+                FieldType::String,
+                "Path, relative to the project root",
+            )
+            .required("out", FieldType::String, "Where the files are staged")
+            .required(
+                "is_archive",
+                FieldType::Boolean,
+                "Extract the file as an archive",
+            )
+            .field(
+                "include",
+                FieldType::list(FieldType::String),
+                "Globs of the files to use, relative to path",
+            )
+            .field(
+                "exclude",
+                FieldType::list(FieldType::String),
+                "Globs of the files to leave out, relative to path",
+            )
+            .fields([license_field()])
+    }
+}
+
+impl LocalSource {
+    /// Returns the files selected by the glob patterns, or `None` when the
+    /// whole path is used.
+    fn selected(&self) -> Result<Option<Vec<PathBuf>>, error::Error> {
+        if (self.include.is_empty() && self.exclude.is_empty()) || !self.path.is_dir() {
+            return Ok(None);
+        }
+        let files =
+            glob_files(&self.path, &self.include, &self.exclude).context(error::ReadFileSnafu)?;
+        Ok(Some(files))
+    }
+}
+
+/// Digests the sorted file list and contents so the id changes whenever the
+/// expansion or any matched file does.
+fn digest_files(root: &Path, files: &[PathBuf]) -> Result<String, error::Error> {
+    let mut hasher = blake3::Hasher::new();
+    for file in files {
+        hasher.update(file.to_string_lossy().as_bytes());
+        hasher.update(&[0]);
+        let mut reader = std::fs::File::open(root.join(file)).context(error::ReadFileSnafu)?;
+        let mut content = blake3::Hasher::new();
+        std::io::copy(&mut reader, &mut content).context(error::ReadFileSnafu)?;
+        hasher.update(content.finalize().as_bytes());
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
 #[async_trait]
 impl SourceImpl for LocalSource {
     async fn get_unique_id(&self) -> SourceResult<Id> {
-        // The digest should be calculated as a merkle hash of the source files
-        let apath = absolute(&self.path).context(error::AbsoluteSnafu)?;
-        let merkle = MerkleTree::builder(apath.to_string_lossy().as_ref())
-            .build()
-            .context(error::MerkleSnafu)?;
-        let hash = merkle.root.item.hash;
-        // Local files will never be precached usually
-        let digest = base16::encode_lower(hash.as_slice());
+        let digest = if let Some(files) = self.selected()? {
+            digest_files(&self.path, &files)?
+        } else {
+            // The digest should be calculated as a merkle hash of the source files
+            let apath = absolute(&self.path).context(error::AbsoluteSnafu)?;
+            let merkle = MerkleTree::builder(apath.to_string_lossy().as_ref())
+                .build()
+                .context(error::MerkleSnafu)?;
+            let hash = merkle.root.item.hash;
+            // Local files will never be precached usually
+            base16::encode_lower(hash.as_slice())
+        };
 
         let id = Id::builder()
             .name(
@@ -112,10 +206,19 @@ impl SourceImpl for LocalSource {
                 self.path
             );
             let mut archive = Builder::new(writer.clone());
-            archive
-                .append_dir_all(".", &self.path)
-                .await
-                .context(error::ArchiveSnafu)?;
+            if let Some(files) = self.selected()? {
+                for file in files.iter() {
+                    archive
+                        .append_path_with_name(self.path.join(file), file)
+                        .await
+                        .context(error::ArchiveSnafu)?;
+                }
+            } else {
+                archive
+                    .append_dir_all(".", &self.path)
+                    .await
+                    .context(error::ArchiveSnafu)?;
+            }
             archive.finish().await.context(error::ArchiveSnafu)?;
             MediaType::Tar(Compression::None)
         };
@@ -156,6 +259,14 @@ impl SourceImpl for LocalSource {
         }
         Ok(())
     }
+
+    fn owned_paths(&self) -> Vec<PathBuf> {
+        vec![self.path.clone()]
+    }
+
+    fn needs_network(&self) -> bool {
+        false
+    }
 }
 
 pub mod error {
@@ -171,6 +282,10 @@ pub mod error {
         Archive { source: std::io::Error },
         #[snafu(display("local source definition field '{field}' should be a '{type_}'"))]
         Field { field: String, type_: String },
+        #[snafu(display(
+            "glob pattern '{pattern}' must be relative to the source path and stay inside it"
+        ))]
+        Pattern { pattern: String },
         #[snafu(display("failed to calculate merkle hash of directory: {source}"))]
         Merkle {
             source: merkle_hash::error::IndexingError,
@@ -1,5 +1,6 @@
 /// Git source implementation.
 pub mod git;
+mod layers;
 /// Local filesystem source implementation.
 pub mod local;
 /// OCI image source implementation.
@@ -6,17 +6,27 @@ use snafu::{OptionExt, ResultExt};
 use std::collections::BTreeSet;
 use std::path::Path;
 
-use edo::context::{Addr, Context, FromNode, Log, Node, non_configurable};
+use edo::context::{
+    Addr, Context, Describe, FieldType, FromNode, KindSchema, Log, Node, non_configurable,
+};
 use edo::environment::Environment;
-use edo::source::{SourceImpl, SourceResult};
-use edo::storage::{Artifact, Compression, Config, Id, MediaType, Storage};
+use edo::source::{SourceImpl, SourceResult, license_field};
+use edo::storage::{Access, AccessKind, Artifact, Compression, Config, Id, MediaType, Storage};
+use tempfile::tempdir;
+use tokio::fs::File;
+
+use super::layers::LayerFilter;
 
 /// A OCI Image source is used to fetch
 /// an oci image to use as a container image
+///
+/// The optional `layers`, `exclude` and `flatten` fields reduce the image
+/// at fetch time, see [`LayerFilter`].
 pub struct ImageSource {
     uri: Uri,
     digest: String,
     platform: Platform,
+    filter: LayerFilter,
 }
 
 #[async_trait]
@@ -54,21 +64,59 @@ impl FromNode for ImageSource {
             uri: Uri::new(&url).await.context(error::OciSnafu)?,
             platform,
             digest,
+            filter: LayerFilter::from_node(node)?,
         })
     }
 }
 
 non_configurable!(ImageSource, error::ImageSourceError);
 
+impl Describe for ImageSource {
+    fn describe() -> KindSchema {
+        KindSchema::new("The filesystem of an OCI image")
+            .required("url", FieldType::String, "Image to pull")
+            .required("ref", FieldType::String, "Digest of the image")
+            .field(
+                "platform",
+                FieldType::String,
+                "Platform to pull, e.g. linux/arm64",
+            )
+            .field(
+                "layers",
+                FieldType::list(FieldType::Integer),
+                "Indexes of the layers to keep, negative ones count from the top",
+            )
+            .field(
+                "exclude",
+                FieldType::list(FieldType::String),
+                "Globs of paths removed from every layer",
+            )
+            .field(
+                "flatten",
+                FieldType::Boolean,
+                "Squash the kept layers into one",
+            )
+            .fields([license_field()])
+    }
+}
+
 /// A OCI Filesystem source is used to fetch
 /// an oci artifact or image using ocilot as a filesystem archive
 
 #[async_trait]
 impl SourceImpl for ImageSource {
     async fn get_unique_id(&self) -> SourceResult<Id> {
+        let digest = if self.filter.is_empty() {
+            self.digest.clone()
+        } else {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update(self.digest.as_bytes());
+            hasher.update(self.filter.fingerprint().as_bytes());
+            hasher.finalize().to_hex().to_string()
+        };
         let id = Id::builder()
             .name(self.uri.to_string())
-            .digest(self.digest.clone())
+            .digest(digest)
             .build();
         trace!(component = "source", type = "oci", "calculated id to be {id}");
         Ok(id)
@@ -89,9 +137,9 @@ impl SourceImpl for ImageSource {
         let hash_bytes = hasher.finalize();
         let digest = base16::encode_lower(hash_bytes.as_bytes());
         ensure!(
-            *id.digest() == digest,
+            self.digest == digest,
             error::DigestSnafu {
-                actual: id.digest().clone(),
+                actual: self.digest.clone(),
                 expected: digest.clone()
             }
         );
@@ -115,10 +163,44 @@ impl SourceImpl for ImageSource {
             self.uri,
             self.platform
         );
-        index
-            .to_oci(&self.uri, Some(self.platform.clone()), writer.clone())
-            .await
-            .context(error::OciSnafu)?;
+        if self.filter.is_empty() {
+            index
+                .to_oci(&self.uri, Some(self.platform.clone()), writer.clone())
+                .await
+                .context(error::OciSnafu)?;
+        } else {
+            // Unpack the image layout so its layers can be rewritten before
+            // it is archived into storage
+            let temp = tempdir().context(error::IoSnafu)?;
+            let archive = temp.path().join("image.tar");
+            let layout = temp.path().join("layout");
+            let file = File::create(&archive).await.context(error::IoSnafu)?;
+            index
+                .to_oci(&self.uri, Some(self.platform.clone()), file)
+                .await
+                .context(error::OciSnafu)?;
+            let file = File::open(&archive).await.context(error::IoSnafu)?;
+            tokio_tar::Archive::new(file)
+                .unpack(&layout)
+                .await
+                .context(error::IoSnafu)?;
+            tokio::fs::remove_file(&archive)
+                .await
+                .context(error::IoSnafu)?;
+            record!(
+                log,
+                "filter",
+                "reducing image layers: {}",
+                self.filter.fingerprint()
+            );
+            self.filter.apply(&layout).await?;
+            let mut builder = tokio_tar::Builder::new(writer.clone());
+            builder
+                .append_dir_all(".", &layout)
+                .await
+                .context(error::IoSnafu)?;
+            builder.finish().await.context(error::IoSnafu)?;
+        }
         let layer = storage
             .safe_finish_layer(
                 &MediaType::Oci(Compression::None),
@@ -128,6 +210,19 @@ impl SourceImpl for ImageSource {
             .await?;
         artifact.layers_mut().push(layer);
         storage.safe_save(&artifact).await?;
+        // The registry manifests pulled identify the image, the layer what was stored
+        storage.audit().record(
+            index
+                .manifests()
+                .iter()
+                .map(|x| x.digest().to_string())
+                .chain(artifact.layers().iter().map(|x| x.digest().to_string()))
+                .fold(
+                    Access::new(AccessKind::Fetch, self.uri.to_string())
+                        .subject(artifact.config().id().to_string()),
+                    |access, digest| access.digest(digest),
+                ),
+        );
         Ok(artifact.clone())
     }
 
@@ -142,6 +237,11 @@ impl SourceImpl for ImageSource {
         // TODO: Implement the parallel extract here
         Ok(())
     }
+
+    fn resolved(&self, _artifact: &Artifact) -> Option<String> {
+        // Fetching checks the image against this digest of its manifests
+        Some(self.digest.clone())
+    }
 }
 
 pub mod error {
@@ -159,6 +259,10 @@ pub mod error {
         },
         #[snafu(display("image has digest '{actual}' when expecting '{expected}"))]
         Digest { actual: String, expected: String },
+        #[snafu(display("image has {count} layers, there is no layer {index}"))]
+        LayerIndex { index: i64, count: usize },
+        #[snafu(display("image layout is malformed: {reason}"))]
+        Layout { reason: String },
         #[snafu(display("image source oci error: {source}"))]
         Oci { source: ocilot::error::Error },
         #[snafu(display("image source definition requires a field '{field}' with type '{type_}"))]
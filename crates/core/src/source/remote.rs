@@ -9,10 +9,13 @@ use tokio_util::io::StreamReader;
 use tracing::Instrument;
 use url::Url;
 
-use edo::context::{Addr, Context, FromNode, Log, Node, non_configurable};
+use edo::context::{
+    Addr, Context, Credentials, Describe, FieldType, FromNode, KindSchema, Log, Node, Progress,
+    non_configurable,
+};
 use edo::environment::Environment;
-use edo::source::{SourceImpl, SourceResult};
-use edo::storage::{Artifact, Compression, Config, Id, MediaType, Storage};
+use edo::source::{SourceImpl, SourceResult, license_field};
+use edo::storage::{Access, AccessKind, Artifact, Compression, Config, Id, MediaType, Storage};
 
 /// A source that fetches a file from a remote URL and stores it as an artifact.
 pub struct RemoteSource {
@@ -20,6 +23,7 @@ pub struct RemoteSource {
     digest: String,
     out: PathBuf,
     is_archive: bool,
+    credentials: Credentials,
 }
 
 #[async_trait]
@@ -29,7 +33,7 @@ impl FromNode for RemoteSource {
     async fn from_node(
         _: &Addr,
         node: &Node,
-        _: &Context,
+        ctx: &Context,
     ) -> Result<Self, error::RemoteSourceError> {
         node.validate_keys(&["url", "out", "ref"])?;
         let url = node
@@ -65,12 +69,28 @@ impl FromNode for RemoteSource {
             out: PathBuf::from(out),
             is_archive,
             digest,
+            credentials: ctx.credentials().clone(),
         })
     }
 }
 
 non_configurable!(RemoteSource, error::RemoteSourceError);
 
+impl Describe for RemoteSource {
+    fn describe() -> KindSchema {
+        KindSchema::new("A file downloaded from a url")
+            .required("url", FieldType::String, "Where to download the file from")
+            .required("ref", FieldType::String, "Expected digest of the file")
+            .required("out", FieldType::String, "Where the file is staged")
+            .field(
+                "is_archive",
+                FieldType::Boolean,
+                "Extract the file as an archive",
+            )
+            .fields([license_field()])
+    }
+}
+
 #[async_trait]
 impl SourceImpl for RemoteSource {
     async fn get_unique_id(&self) -> SourceResult<Id> {
@@ -88,13 +108,15 @@ impl SourceImpl for RemoteSource {
         trace!(component = "source", type = "remote", "fetching remote file from {}", self.url);
         let url = self.url.clone();
         async move {
+            let progress = Progress::current();
             record!(log, "fetch", "fetching artifact from {url}");
             let client = reqwest::Client::new();
-            let response = client
-                .get(url.clone())
-                .send()
-                .await
-                .context(error::RequestSnafu)?;
+            let mut request = client.get(url.clone());
+            if let Some(credential) = self.credentials.get(url.as_str()).await? {
+                request =
+                    request.header(reqwest::header::AUTHORIZATION, credential.authorization());
+            }
+            let response = request.send().await.context(error::RequestSnafu)?;
             ensure!(
                 response.status().is_success(),
                 error::FailedSnafu {
@@ -102,9 +124,14 @@ impl SourceImpl for RemoteSource {
                     message: response.text().await.context(error::RequestSnafu)?
                 }
             );
+            if let Some(length) = response.content_length() {
+                progress.set_total_bytes(length);
+            }
+            progress.message("downloading");
             // Now we create a stream reader over the body
-            let mut reader =
-                StreamReader::new(response.bytes_stream().map_err(std::io::Error::other));
+            let mut reader = progress.wrap_read(StreamReader::new(
+                response.bytes_stream().map_err(std::io::Error::other),
+            ));
 
             let mut artifact = Artifact::builder()
                 .config(
@@ -136,6 +163,11 @@ impl SourceImpl for RemoteSource {
                 }
             );
             storage.safe_save(&artifact).await?;
+            storage.audit().record(
+                Access::new(AccessKind::Fetch, url.to_string())
+                    .subject(id.to_string())
+                    .digest(layer.digest().to_string()),
+            );
             Ok(artifact.clone())
         }
         .instrument(info_span!(
@@ -170,6 +202,10 @@ impl SourceImpl for RemoteSource {
         }
         Ok(())
     }
+
+    fn resolved(&self, artifact: &Artifact) -> Option<String> {
+        artifact.layers().first().map(|x| x.digest().to_string())
+    }
 }
 
 pub mod error {
@@ -3,9 +3,11 @@ use std::fs::OpenOptions;
 use std::path::{Path, PathBuf, absolute};
 
 use async_trait::async_trait;
-use edo::context::{Addr, Context, FromNode, Log, Node, non_configurable};
+use edo::context::{
+    Addr, Context, Describe, FieldType, FromNode, KindSchema, Log, Node, non_configurable,
+};
 use edo::environment::Environment;
-use edo::source::{SourceImpl, SourceResult};
+use edo::source::{SourceImpl, SourceResult, license_field};
 use edo::storage::{Artifact, Compression, Config, Id, MediaType, Storage};
 use edo::util::{cmd_noinput, cmd_pipeout, copy_r};
 use merkle_hash::MerkleTree;
@@ -90,6 +92,34 @@ impl FromNode for VendorSource {
 
 non_configurable!(VendorSource, error::VendorError);
 
+impl Describe for VendorSource {
+    fn describe() -> KindSchema {
+        KindSchema::new("The third-party dependencies of a project directory")
+            .required("path", FieldType::String, "Project directory")
+            .required(
+                "inside",
+                FieldType::String,
+                "Directory of the project to vendor in",
+            )
+            .required(
+                "out",
+                FieldType::String,
+                "Where the vendored tree is staged",
+            )
+            .field(
+                "rust",
+                FieldType::Boolean,
+                "Vendor the crates of Cargo projects",
+            )
+            .field(
+                "go",
+                FieldType::list(FieldType::String),
+                "Go module directories to vendor",
+            )
+            .fields([license_field()])
+    }
+}
+
 #[async_trait]
 impl SourceImpl for VendorSource {
     async fn get_unique_id(&self) -> SourceResult<Id> {
@@ -218,6 +248,10 @@ impl SourceImpl for VendorSource {
 
         Ok(())
     }
+
+    fn owned_paths(&self) -> Vec<PathBuf> {
+        vec![self.path.clone()]
+    }
 }
 
 pub mod error {
@@ -0,0 +1,43 @@
+use edo::storage::StorageError;
+use snafu::Snafu;
+use std::path::PathBuf;
+
+/// Errors that can occur when reading from an external cache.
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum Error {
+    #[snafu(display("failed to index external cache at {}: {source}", path.display()))]
+    Index {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("failed to join external cache indexing task: {source}"))]
+    Join { source: tokio::task::JoinError },
+    #[snafu(display("external cache does not contain a blob with digest '{digest}'"))]
+    LayerMissing { digest: String },
+    #[snafu(display("external cache does not contain an artifact with id: {id}"))]
+    NotFound { id: edo::storage::Id },
+    #[snafu(display("external cache definitions must specify a path"))]
+    PathNotSpecified,
+    #[snafu(display("external cache directory {} does not exist", path.display()))]
+    PathMissing { path: PathBuf },
+    #[snafu(display("failed to open blob {} in external cache: {source}", path.display()))]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("external caches are read-only and do not support {operation}"))]
+    ReadOnly { operation: String },
+    #[snafu(display(
+        "unknown external cache kind '{kind}', expected one of 'oci-layout' or 'bazel-disk'"
+    ))]
+    UnknownLayout { kind: String },
+}
+
+impl From<Error> for StorageError {
+    fn from(value: Error) -> Self {
+        Self::Implementation {
+            source: Box::new(value),
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use async_trait::async_trait;
+use edo::{
+    context::{Addr, Config, Describe, FieldType, FromNodeNoContext, KindSchema, Node},
+    non_configurable_no_context,
+    storage::{
+        Artifact, BackendImpl, Compression, Config as ArtifactConfig, Id, Layer, MediaType,
+        StorageResult,
+    },
+    util::{Reader, Writer},
+};
+use ocilot::models::Platform;
+use serde_json::json;
+use snafu::{OptionExt, ResultExt, ensure};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::sync::OnceCell;
+
+mod error;
+
+/// The on-disk format of an external cache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExternalLayout {
+    /// An OCI image layout, as written by `buildkit --cache-to type=local` or
+    /// `skopeo copy oci:`. Blobs live at `blobs/<algorithm>/<hex>`.
+    Oci,
+    /// A Bazel `--disk_cache` directory. Blobs live at `cas/<hex[..2]>/<hex>`.
+    Bazel,
+}
+
+impl ExternalLayout {
+    /// Returns the layout registered under the backend `kind`.
+    pub fn from_kind(kind: &str) -> StorageResult<Self> {
+        match kind {
+            "oci-layout" => Ok(Self::Oci),
+            "bazel-disk" => Ok(Self::Bazel),
+            kind => Ok(error::UnknownLayoutSnafu { kind }.fail()?),
+        }
+    }
+
+    /// The directory holding content addressed blobs, relative to the cache root.
+    fn blob_dir(&self) -> &'static str {
+        match self {
+            Self::Oci => "blobs",
+            Self::Bazel => "cas",
+        }
+    }
+}
+
+impl fmt::Display for ExternalLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Oci => "oci-layout",
+            Self::Bazel => "bazel-disk",
+        })
+    }
+}
+
+/// A blob found in an external cache.
+#[derive(Clone, Debug)]
+struct Blob {
+    path: PathBuf,
+    // The digest the external tool stored the blob under, e.g. `sha256:<hex>`
+    native: String,
+    size: usize,
+}
+
+/// A read-only storage backend over a cache populated by another build tool.
+///
+/// External caches address blobs by their sha256 digest while edo addresses
+/// them by blake3, so the first lookup hashes every blob in the cache and
+/// keeps an in-memory index from blake3 digest to blob. Any artifact id whose
+/// digest matches a blob is served as a single uncompressed file layer,
+/// which is exactly what a `remote` source with the same `ref` would fetch.
+/// This lets a migrating organization register its existing cache as a
+/// source cache and reuse downloads that are already there.
+pub struct ExternalBackend {
+    root: PathBuf,
+    layout: ExternalLayout,
+    index: OnceCell<BTreeMap<String, Blob>>,
+}
+
+#[async_trait]
+impl FromNodeNoContext for ExternalBackend {
+    type Error = edo::storage::StorageError;
+
+    async fn from_node(
+        _addr: &Addr,
+        node: &Node,
+        _config: &Config,
+    ) -> std::result::Result<Self, Self::Error> {
+        node.validate_keys(&["path"])?;
+        let path = node
+            .get("path")
+            .and_then(|x| x.as_string())
+            .context(error::PathNotSpecifiedSnafu)?;
+        let layout = ExternalLayout::from_kind(&node.get_kind().unwrap_or_default())?;
+        Self::new_(path, layout)
+    }
+}
+
+non_configurable_no_context!(ExternalBackend, edo::storage::StorageError);
+
+impl Describe for ExternalBackend {
+    fn describe() -> KindSchema {
+        KindSchema::new("A read-only cache populated by another build tool").required(
+            "path",
+            FieldType::String,
+            "Directory holding the cache",
+        )
+    }
+}
+
+impl ExternalBackend {
+    /// Opens the external cache rooted at `path`.
+    pub fn new_(path: impl AsRef<Path>, layout: ExternalLayout) -> StorageResult<Self> {
+        let root = path.as_ref().to_path_buf();
+        trace!(
+            section = "storage",
+            component = "backend",
+            variant = "external",
+            "opening {layout} cache at {}",
+            root.display()
+        );
+        ensure!(root.is_dir(), error::PathMissingSnafu { path: root });
+        Ok(Self {
+            root,
+            layout,
+            index: OnceCell::new(),
+        })
+    }
+
+    /// Returns the layout of this cache.
+    pub fn layout(&self) -> ExternalLayout {
+        self.layout
+    }
+
+    async fn index(&self) -> StorageResult<&BTreeMap<String, Blob>> {
+        self.index
+            .get_or_try_init(|| async {
+                let dir = self.root.join(self.layout.blob_dir());
+                let layout = self.layout;
+                let index = tokio::task::spawn_blocking(move || build_index(&dir, layout))
+                    .await
+                    .context(error::JoinSnafu)??;
+                debug!(
+                    section = "storage",
+                    component = "backend",
+                    variant = "external",
+                    "indexed {} blobs in {layout} cache at {}",
+                    index.len(),
+                    self.root.display()
+                );
+                Ok(index)
+            })
+            .await
+    }
+
+    fn id_for(digest: &str, blob: &Blob) -> Id {
+        Id::builder()
+            .name(blob.native.clone())
+            .digest(digest.to_string())
+            .build()
+    }
+
+    fn read_only<T>(operation: &str) -> StorageResult<T> {
+        Ok(error::ReadOnlySnafu { operation }.fail()?)
+    }
+}
+
+/// Hash every blob under `dir`, keyed by its blake3 digest.
+fn build_index(dir: &Path, layout: ExternalLayout) -> StorageResult<BTreeMap<String, Blob>> {
+    let mut index = BTreeMap::new();
+    if !dir.exists() {
+        return Ok(index);
+    }
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current).context(error::IndexSnafu {
+            path: current.clone(),
+        })? {
+            let path = entry
+                .context(error::IndexSnafu {
+                    path: current.clone(),
+                })?
+                .path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let Some(hex) = path.file_name().and_then(|x| x.to_str()) else {
+                continue;
+            };
+            if hex.len() < 32 || !hex.chars().all(|x| x.is_ascii_hexdigit()) {
+                continue;
+            }
+            let native = match layout {
+                // blobs/<algorithm>/<hex>
+                ExternalLayout::Oci => {
+                    let algorithm = current
+                        .file_name()
+                        .and_then(|x| x.to_str())
+                        .unwrap_or("sha256");
+                    format!("{algorithm}:{hex}")
+                }
+                ExternalLayout::Bazel => format!("sha256:{hex}"),
+            };
+            let mut hasher = blake3::Hasher::new();
+            let mut file =
+                std::fs::File::open(&path).context(error::IndexSnafu { path: path.clone() })?;
+            let size = std::io::copy(&mut file, &mut hasher)
+                .context(error::IndexSnafu { path: path.clone() })?;
+            index.insert(
+                hasher.finalize().to_hex().to_string(),
+                Blob {
+                    path,
+                    native,
+                    size: size as usize,
+                },
+            );
+        }
+    }
+    Ok(index)
+}
+
+#[async_trait]
+impl BackendImpl for ExternalBackend {
+    async fn list(&self) -> StorageResult<BTreeSet<Id>> {
+        Ok(self
+            .index()
+            .await?
+            .iter()
+            .map(|(digest, blob)| Self::id_for(digest, blob))
+            .collect())
+    }
+
+    async fn has(&self, id: &Id) -> StorageResult<bool> {
+        Ok(self.index().await?.contains_key(id.digest()))
+    }
+
+    async fn has_many(&self, ids: &[Id]) -> StorageResult<BTreeSet<Id>> {
+        let index = self.index().await?;
+        Ok(ids
+            .iter()
+            .filter(|x| index.contains_key(x.digest()))
+            .cloned()
+            .collect())
+    }
+
+    async fn open(&self, id: &Id) -> StorageResult<Artifact> {
+        let blob = self
+            .index()
+            .await?
+            .get(id.digest())
+            .context(error::NotFoundSnafu { id: id.clone() })?;
+        Ok(Artifact::builder()
+            .media_type(MediaType::Manifest)
+            .config(
+                ArtifactConfig::builder()
+                    .id(id.clone())
+                    .metadata(json!({
+                        "external": {
+                            "layout": self.layout.to_string(),
+                            "digest": blob.native,
+                        }
+                    }))
+                    .build(),
+            )
+            .layers(vec![
+                Layer::builder()
+                    .media_type(MediaType::File(Compression::None))
+                    .digest(id.digest().clone())
+                    .size(blob.size)
+                    .build(),
+            ])
+            .build())
+    }
+
+    async fn save(&self, _artifact: &Artifact) -> StorageResult<()> {
+        Self::read_only("save")
+    }
+
+    async fn del(&self, _id: &Id) -> StorageResult<()> {
+        Self::read_only("del")
+    }
+
+    async fn copy(&self, _from: &Id, _to: &Id) -> StorageResult<()> {
+        Self::read_only("copy")
+    }
+
+    async fn prune(&self, _id: &Id) -> StorageResult<()> {
+        Self::read_only("prune")
+    }
+
+    async fn prune_all(&self) -> StorageResult<()> {
+        Self::read_only("prune_all")
+    }
+
+    async fn read(&self, layer: &Layer) -> StorageResult<Reader> {
+        let digest = layer.digest().digest();
+        let blob = self
+            .index()
+            .await?
+            .get(&digest)
+            .context(error::LayerMissingSnafu { digest })?;
+        Ok(Reader::new(File::open(&blob.path).await.context(
+            error::ReadSnafu {
+                path: blob.path.clone(),
+            },
+        )?))
+    }
+
+    async fn start_layer(&self) -> StorageResult<Writer> {
+        Self::read_only("start_layer")
+    }
+
+    async fn finish_layer(
+        &self,
+        _media_type: &MediaType,
+        _platform: Option<Platform>,
+        _writer: &Writer,
+    ) -> StorageResult<Layer> {
+        Self::read_only("finish_layer")
+    }
+}
@@ -1,3 +1,5 @@
+mod external;
 mod s3;
 
+pub use external::*;
 pub use s3::*;
@@ -2,31 +2,45 @@ use async_trait::async_trait;
 use aws_config::{BehaviorVersion, SdkConfig};
 use aws_sdk_s3::{
     client::Client,
+    error::DisplayErrorContext,
     primitives::ByteStream,
-    types::{CompletedMultipartUpload, CompletedPart},
+    types::{CompletedMultipartUpload, CompletedPart, ServerSideEncryption, StorageClass},
 };
 use edo::{
-    context::{Addr, Config, FromNodeNoContext, Node},
+    context::{
+        Addr, Config, Credentials, Describe, FieldType, FromNodeNoContext, HealthCheck, KindSchema,
+        Node,
+    },
     non_configurable_no_context,
-    storage::{Artifact, BackendImpl, Id, Layer, MediaType, StorageResult},
+    storage::{
+        Artifact, BackendImpl, Compression, DEFAULT_HISTORY, DigestAlgorithm, Generation, Id,
+        Layer, LayerDigest, LayerKey, MediaType, StorageResult, TemporaryBlob, TransferPolicy,
+        digest_setting, encryption_field, encryption_setting, history_setting, verify_setting,
+    },
     util::{Reader, Writer},
 };
 use ocilot::models::Platform;
-use snafu::{OptionExt, ResultExt};
-use std::collections::BTreeSet;
+use snafu::{OptionExt, ResultExt, ensure};
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tokio::{fs::OpenOptions, io::AsyncReadExt};
 use uuid::Uuid;
 
-use edo::storage::Catalog;
+use edo::storage::{Catalog, cache_settings};
 
 mod error;
 mod reader;
 
 type Result<T> = std::result::Result<T, error::Error>;
 const CHUNK_SIZE: usize = 10 * 1024 * 1024; // 10mb
+/// How long a downloaded catalog answers lookups before it is downloaded again
+const DEFAULT_CATALOG_TTL: Duration = Duration::from_secs(60);
+
+/// A downloaded catalog and when it was downloaded.
+type Snapshot = (Instant, Arc<Catalog>);
 
 /// An S3-backed storage backend for artifact caching and retrieval.
 pub struct S3Backend {
@@ -34,6 +48,19 @@ pub struct S3Backend {
     bucket: String,
     prefix: Option<PathBuf>,
     catalog_key: String,
+    compression: Option<Compression>,
+    transfer: TransferPolicy,
+    verify: bool,
+    algorithm: DigestAlgorithm,
+    history: usize,
+    encryption: Option<LayerKey>,
+    blob_prefix: String,
+    kms_key_id: Option<String>,
+    storage_class: Option<StorageClass>,
+    tags: BTreeMap<String, String>,
+    catalog_ttl: Duration,
+    /// The catalog last downloaded and when, reused for `catalog_ttl`
+    snapshot: Arc<Mutex<Option<Snapshot>>>,
 }
 
 unsafe impl Send for S3Backend {}
@@ -54,17 +81,144 @@ impl FromNodeNoContext for S3Backend {
             .and_then(|x| x.as_string())
             .context(error::BucketNotSpecifiedSnafu)?;
         let prefix = node.get("prefix").and_then(|x| x.as_string());
-        Self::new_(
+        let compression = node
+            .get("compression")
+            .and_then(|x| x.as_string())
+            .map(|x| x.parse::<Compression>())
+            .transpose()?;
+        Ok(Self::new_(
             &aws_config::load_defaults(BehaviorVersion::latest()).await,
             bucket.as_str(),
             prefix.map(PathBuf::from),
         )
-        .await
+        .await?
+        .with_compression(compression)
+        .with_transfer_policy(TransferPolicy::from_node(node)?)
+        .with_verify_reads(verify_setting(node, true)?)
+        .with_digest_algorithm(digest_setting(node, DigestAlgorithm::default())?)
+        .with_history(history_setting(node, DEFAULT_HISTORY)?)
+        .with_encryption(encryption_setting(node).await?)
+        .with_blob_prefix(string_setting(node, "blob_prefix")?)
+        .with_kms_key(string_setting(node, "kms_key_id")?)
+        .with_storage_class(storage_class_setting(node)?)
+        .with_tags(tags_setting(node)?)
+        .with_catalog_ttl(catalog_ttl_setting(node)?))
     }
 }
 
 non_configurable_no_context!(S3Backend, edo::storage::StorageError);
 
+impl Describe for S3Backend {
+    fn describe() -> KindSchema {
+        KindSchema::new("A cache in an S3 bucket")
+            .required("bucket", FieldType::String, "Bucket holding the cache")
+            .field(
+                "prefix",
+                FieldType::String,
+                "Key prefix of the cache in the bucket",
+            )
+            .field(
+                "compression",
+                FieldType::choice(["zstd", "gzip", "bzip2", "lzma", "xz", "none"]),
+                "Compression of uploaded layers",
+            )
+            .field(
+                "blob_prefix",
+                FieldType::String,
+                "Key prefix of the layers below `prefix`, `blobs` by default",
+            )
+            .field(
+                "kms_key_id",
+                FieldType::String,
+                "KMS key the bucket encrypts uploaded objects with (SSE-KMS)",
+            )
+            .field(
+                "storage_class",
+                FieldType::choice(StorageClass::values().iter().copied()),
+                "Storage class of uploaded layers, such as INTELLIGENT_TIERING",
+            )
+            .field(
+                "tags",
+                FieldType::table(FieldType::String),
+                "Tags applied to every uploaded object",
+            )
+            .field(
+                "catalog_ttl",
+                FieldType::Integer,
+                "Seconds a downloaded catalog answers lookups, 60 by default, 0 to always download it",
+            )
+            .fields(cache_settings())
+            .fields([encryption_field()])
+    }
+}
+
+/// Reads the optional string `key` of a cache definition.
+fn string_setting(node: &Node, key: &str) -> StorageResult<Option<String>> {
+    node.get(key)
+        .map(|x| {
+            x.as_string().context(edo::storage::error::SettingSnafu {
+                key,
+                reason: "expected a string",
+            })
+        })
+        .transpose()
+}
+
+/// Reads the optional `storage_class` of a cache definition.
+fn storage_class_setting(node: &Node) -> StorageResult<Option<StorageClass>> {
+    let Some(class) = string_setting(node, "storage_class")? else {
+        return Ok(None);
+    };
+    snafu::ensure!(
+        StorageClass::values().contains(&class.as_str()),
+        edo::storage::error::SettingSnafu {
+            key: "storage_class",
+            reason: format!(
+                "unknown storage class '{class}', expected one of {}",
+                StorageClass::values().join(", ")
+            ),
+        }
+    );
+    Ok(Some(StorageClass::from(class.as_str())))
+}
+
+/// Reads the optional `catalog_ttl` of a cache definition, in seconds.
+fn catalog_ttl_setting(node: &Node) -> StorageResult<Duration> {
+    match node.get("catalog_ttl") {
+        Some(value) => value
+            .as_int()
+            .and_then(|x| u64::try_from(x).ok())
+            .map(Duration::from_secs)
+            .context(edo::storage::error::SettingSnafu {
+                key: "catalog_ttl",
+                reason: "expected a number of seconds",
+            }),
+        None => Ok(DEFAULT_CATALOG_TTL),
+    }
+}
+
+/// Reads the optional `tags` table of a cache definition.
+fn tags_setting(node: &Node) -> StorageResult<BTreeMap<String, String>> {
+    let Some(tags) = node.get("tags") else {
+        return Ok(BTreeMap::new());
+    };
+    let tags = tags.as_table().context(edo::storage::error::SettingSnafu {
+        key: "tags",
+        reason: "expected a table of strings",
+    })?;
+    tags.iter()
+        .map(|(name, value)| {
+            let value = value
+                .as_string()
+                .context(edo::storage::error::SettingSnafu {
+                    key: format!("tags.{name}"),
+                    reason: "expected a string",
+                })?;
+            Ok((name.clone(), value))
+        })
+        .collect()
+}
+
 impl S3Backend {
     /// Creates a new S3 backend with the given SDK configuration, bucket, and optional key prefix.
     pub async fn new_(
@@ -95,15 +249,149 @@ impl S3Backend {
             bucket: bucket.into(),
             prefix,
             catalog_key,
+            compression: None,
+            transfer: TransferPolicy::default(),
+            verify: true,
+            algorithm: DigestAlgorithm::default(),
+            history: DEFAULT_HISTORY,
+            encryption: None,
+            blob_prefix: "blobs".to_string(),
+            kms_key_id: None,
+            storage_class: None,
+            tags: BTreeMap::new(),
+            catalog_ttl: DEFAULT_CATALOG_TTL,
+            snapshot: Arc::new(Mutex::new(None)),
         })
     }
 
-    /// Returns the S3 key prefix for blob storage.
+    /// Authenticates with the credentials the helper configured for
+    /// `s3://<bucket>` returns, keeping the default AWS credential chain when
+    /// it has none.
+    pub async fn with_credentials(mut self, credentials: &Credentials) -> StorageResult<Self> {
+        let server = format!("s3://{}", self.bucket);
+        if let Some(credential) = credentials.get(&server).await? {
+            let credential = aws_sdk_s3::config::Credentials::new(
+                credential.username,
+                credential.secret,
+                credential.session_token,
+                None,
+                "edo-credential-helper",
+            );
+            let sdk_config = aws_config::defaults(BehaviorVersion::latest())
+                .credentials_provider(credential)
+                .load()
+                .await;
+            self.client = Arc::new(Client::new(&sdk_config));
+        }
+        Ok(self)
+    }
+
+    /// Re-hashes layers read from the bucket when `verify` is set, the default.
+    pub fn with_verify_reads(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Addresses layers written to the bucket by `algorithm`.
+    pub fn with_digest_algorithm(mut self, algorithm: DigestAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Keeps the `history` newest previous generations of an artifact when pruning.
+    pub fn with_history(mut self, history: usize) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Copies layers to and from the bucket following `policy`.
+    pub fn with_transfer_policy(mut self, policy: TransferPolicy) -> Self {
+        self.transfer = policy;
+        self
+    }
+
+    /// Stores uncompressed tar layers compressed with `compression`.
+    pub fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Encrypts layers uploaded to the bucket with `key`, layers are then
+    /// only readable with the same key.
+    pub fn with_encryption(mut self, key: Option<LayerKey>) -> Self {
+        self.encryption = key;
+        self
+    }
+
+    /// Stores layers under `prefix` below the cache prefix instead of
+    /// `blobs`, so lifecycle rules can target them apart from the catalog.
+    pub fn with_blob_prefix(mut self, prefix: Option<String>) -> Self {
+        if let Some(prefix) = prefix {
+            self.blob_prefix = prefix.trim_matches('/').to_string();
+        }
+        self
+    }
+
+    /// Has S3 encrypt every uploaded object with the KMS key `key_id`.
+    pub fn with_kms_key(mut self, key_id: Option<String>) -> Self {
+        self.kms_key_id = key_id;
+        self
+    }
+
+    /// Uploads layers in `class`, the catalog stays in the bucket default.
+    pub fn with_storage_class(mut self, class: Option<StorageClass>) -> Self {
+        self.storage_class = class;
+        self
+    }
+
+    /// Tags every uploaded object with `tags`.
+    pub fn with_tags(mut self, tags: BTreeMap<String, String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Reuses a downloaded catalog for lookups during `ttl`, a zero `ttl`
+    /// downloads it for every lookup.
+    pub fn with_catalog_ttl(mut self, ttl: Duration) -> Self {
+        self.catalog_ttl = ttl;
+        self
+    }
+
+    /// Returns the S3 key prefix for blob storage, blobs live under `<algorithm>/<hex>` below it.
     pub fn blob_key(&self) -> PathBuf {
         if let Some(prefix) = self.prefix.as_ref() {
-            prefix.join("blobs/blake3")
+            prefix.join(&self.blob_prefix)
         } else {
-            PathBuf::from("blobs/blake3")
+            PathBuf::from(&self.blob_prefix)
+        }
+    }
+
+    /// Server-side encryption requested on upload.
+    fn server_side_encryption(&self) -> Option<ServerSideEncryption> {
+        self.kms_key_id
+            .as_ref()
+            .map(|_| ServerSideEncryption::AwsKms)
+    }
+
+    /// The `tags` as the URL-encoded query S3 expects on upload.
+    fn tagging(&self) -> Option<String> {
+        if self.tags.is_empty() {
+            return None;
+        }
+        Some(
+            url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(self.tags.iter())
+                .finish(),
+        )
+    }
+
+    /// Returns the S3 key of the blob of `layer`, encrypted blobs carry the id
+    /// of their key so they never collide with plain or rotated copies.
+    pub fn blob_path(&self, layer: &Layer) -> PathBuf {
+        let path = self.blob_key().join(layer.digest().path());
+        match layer.encryption() {
+            Some(encryption) => path.with_extension(&encryption.key),
+            None => path,
         }
     }
 
@@ -131,11 +419,44 @@ impl S3Backend {
             .await
             .context(error::GetSnafu)?;
         let bytes = response.body.collect().await.unwrap();
-        let catalog: Catalog =
-            serde_json::from_slice(bytes.to_vec().as_slice()).context(error::DeserializeSnafu)?;
+        Catalog::from_slice(bytes.to_vec().as_slice())
+    }
+
+    /// Returns the catalog for lookups, downloading it only when the copy
+    /// downloaded last is older than the `catalog_ttl`.
+    ///
+    /// Changes to the catalog always start from a fresh [`load`](Self::load).
+    async fn cached(&self) -> StorageResult<Arc<Catalog>> {
+        if let Some((loaded, catalog)) = self.snapshot.lock().unwrap().as_ref()
+            && loaded.elapsed() < self.catalog_ttl
+        {
+            return Ok(catalog.clone());
+        }
+        let catalog = Arc::new(self.load().await?);
+        if !self.catalog_ttl.is_zero() {
+            *self.snapshot.lock().unwrap() = Some((Instant::now(), catalog.clone()));
+        }
         Ok(catalog)
     }
 
+    /// Checks whether the blob of `layer` is stored, failing on anything but
+    /// a missing object.
+    async fn has_blob(&self, layer: &Layer) -> StorageResult<bool> {
+        let key = self.blob_path(layer);
+        match self
+            .client
+            .head_object()
+            .bucket(self.bucket.clone())
+            .key(key.to_string_lossy())
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if e.as_service_error().is_some_and(|x| x.is_not_found()) => Ok(false),
+            Err(e) => Err(error::Error::Check { source: e }.into()),
+        }
+    }
+
     /// Waits for any existing lock file to be released before proceeding.
     pub async fn wait_for_lock(&self) -> StorageResult<()> {
         let mut interval = tokio::time::interval(Duration::from_secs(1));
@@ -173,6 +494,8 @@ impl S3Backend {
             .bucket(self.bucket.clone())
             .key(format!("{}.lock", self.catalog_key))
             .body(ByteStream::from_static(b"lock"))
+            .set_server_side_encryption(self.server_side_encryption())
+            .set_ssekms_key_id(self.kms_key_id.clone())
             .send()
             .await
             .context(error::PutSnafu)?;
@@ -183,6 +506,9 @@ impl S3Backend {
             .bucket(self.bucket.clone())
             .key(self.catalog_key.clone())
             .body(ByteStream::from(bytes))
+            .set_server_side_encryption(self.server_side_encryption())
+            .set_ssekms_key_id(self.kms_key_id.clone())
+            .set_tagging(self.tagging())
             .send()
             .await
             .context(error::PutSnafu);
@@ -194,31 +520,57 @@ impl S3Backend {
             .send()
             .await
             .context(error::DeleteSnafu)?;
+        // The cached catalog misses this change, download it again next time
+        self.snapshot.lock().unwrap().take();
         let _ = result?;
         Ok(())
     }
 }
 
+/// Whether `path` is a layer this backend writes in the temp directory,
+/// which other programs share.
+fn is_temporary(path: &std::path::Path) -> bool {
+    let extension = path.extension().and_then(|x| x.to_str());
+    let stem = path.file_stem().and_then(|x| x.to_str());
+    matches!(extension, Some("tmp" | "sealed"))
+        && stem.is_some_and(|x| Uuid::parse_str(x).is_ok())
+        && path.parent() == Some(std::env::temp_dir().as_path())
+}
+
 #[async_trait]
 impl BackendImpl for S3Backend {
     async fn list(&self) -> StorageResult<BTreeSet<Id>> {
-        let catalog = self.load().await?;
+        let catalog = self.cached().await?;
         Ok(catalog.list_all())
     }
 
     async fn has(&self, id: &Id) -> StorageResult<bool> {
-        let catalog = self.load().await?;
+        let catalog = self.cached().await?;
         Ok(catalog.has(id))
     }
 
+    async fn has_many(&self, ids: &[Id]) -> StorageResult<BTreeSet<Id>> {
+        let catalog = self.cached().await?;
+        Ok(ids.iter().filter(|x| catalog.has(x)).cloned().collect())
+    }
+
     async fn open(&self, id: &Id) -> StorageResult<Artifact> {
-        let catalog = self.load().await?;
+        let catalog = self.cached().await?;
         let artifact = catalog
             .get(id)
             .context(error::NotFoundSnafu { id: id.clone() })?;
         Ok(artifact.clone())
     }
 
+    async fn has_layers(&self, artifact: &Artifact) -> StorageResult<bool> {
+        for layer in artifact.layers() {
+            if !self.has_blob(layer).await? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     async fn save(&self, artifact: &Artifact) -> StorageResult<()> {
         let mut catalog = self.load().await?;
         catalog.add(artifact);
@@ -240,8 +592,7 @@ impl BackendImpl for S3Backend {
         catalog.del(id);
         self.flush(&catalog).await?;
         for layer in artifact.layers() {
-            let digest = layer.digest().digest();
-            let key = self.blob_key().join(digest);
+            let key = self.blob_path(layer);
             if catalog.count(layer) <= 0 {
                 self.client
                     .delete_object()
@@ -277,10 +628,7 @@ impl BackendImpl for S3Backend {
         // To prune historical artifacts we want to load our catalog for the id prefix
         let catalog = self.load().await?;
 
-        for entry in catalog.matching(id) {
-            if entry == *id {
-                continue;
-            }
+        for entry in catalog.stale(id, self.history) {
             info!(
                 section = "storage",
                 component = "backend",
@@ -292,23 +640,68 @@ impl BackendImpl for S3Backend {
         Ok(())
     }
 
+    async fn history(&self, prefix: &str) -> StorageResult<Vec<Generation>> {
+        Ok(self.cached().await?.history(prefix))
+    }
+
+    async fn tag(&self, tag: &str, id: &Id) -> StorageResult<()> {
+        let mut catalog = self.load().await?;
+        ensure!(catalog.has(id), error::NotFoundSnafu { id: id.clone() });
+        catalog.tag(tag, id);
+        self.flush(&catalog).await?;
+        Ok(())
+    }
+
+    async fn resolve_tag(&self, tag: &str) -> StorageResult<Option<Id>> {
+        Ok(self.cached().await?.resolve(tag).cloned())
+    }
+
     async fn prune_all(&self) -> StorageResult<()> {
         let result = error::PruneAllSnafu {}.fail();
         result.map_err(|e| e.into())
     }
 
+    async fn refresh(&self) -> StorageResult<()> {
+        self.snapshot.lock().unwrap().take();
+        Ok(())
+    }
+
     async fn read(&self, layer: &Layer) -> StorageResult<Reader> {
-        // A Read is a pretty simple operation, we just want to load the correct blob file
-        let blob_digest = layer.digest().digest();
-        let blob_file = self.blob_key().join(blob_digest);
-        Ok(Reader::new(
-            reader::ObjectReader::new(
-                self.client.clone(),
-                self.bucket.as_str(),
-                blob_file.to_str().unwrap(),
-            )
-            .await?,
-        ))
+        self.read_from(layer, 0).await
+    }
+
+    async fn read_from(&self, layer: &Layer, offset: u64) -> StorageResult<Reader> {
+        // A Read is a pretty simple operation, we just want to load the correct blob file.
+        // Resuming only needs the ranged requests to start further into the blob
+        let blob_file = self.blob_path(layer);
+        if layer.encryption().is_none() {
+            return Ok(Reader::new(
+                reader::ObjectReader::new(
+                    self.client.clone(),
+                    self.bucket.as_str(),
+                    blob_file.to_str().unwrap(),
+                )
+                .await?
+                .starting_at(offset),
+            ));
+        }
+        let key = self
+            .encryption
+            .as_ref()
+            .context(edo::storage::error::EncryptionStateSnafu {
+                digest: layer.digest().to_string(),
+                state: "encrypted",
+                configured: "without encryption",
+            })?;
+        key.check(layer)?;
+        let reader = reader::ObjectReader::new(
+            self.client.clone(),
+            self.bucket.as_str(),
+            blob_file.to_str().unwrap(),
+        )
+        .await?
+        .starting_at(LayerKey::encrypted_offset(offset));
+        Ok(Reader::new(key.decrypt(layer, reader, offset)))
     }
 
     async fn start_layer(&self) -> StorageResult<Writer> {
@@ -317,7 +710,7 @@ impl BackendImpl for S3Backend {
         // Due to issues wrapping a multipart upload we actually write to a local file then upload it all
         // when layer is finished
         let tmp_file_path = std::env::temp_dir().join(tmp_name.clone());
-        Ok(Writer::new(
+        let writer = Writer::new(
             tmp_file_path.to_string_lossy().to_string(),
             OpenOptions::new()
                 .create(true)
@@ -326,7 +719,9 @@ impl BackendImpl for S3Backend {
                 .open(&tmp_file_path)
                 .await
                 .context(error::TempSnafu)?,
-        ))
+        );
+        writer.set_algorithm(self.algorithm);
+        Ok(writer)
     }
 
     async fn finish_layer(
@@ -338,16 +733,29 @@ impl BackendImpl for S3Backend {
         // The writer will contain the temporary file name to use
         let tmp_path = std::env::temp_dir().join(writer.target());
         // Now we want to calculate the digest
-        let digest = writer.finish().await;
-        let target_path = self.blob_key().join(digest.clone());
+        let digest = LayerDigest::new(writer.algorithm(), writer.finish().await);
+        // Encrypted layers upload a sealed copy of the temporary file
+        let upload_path = match self.encryption.as_ref() {
+            Some(key) => {
+                let sealed = tmp_path.with_extension("sealed");
+                key.encrypt_file(&digest, &tmp_path, &sealed).await?;
+                tokio::fs::remove_file(&tmp_path)
+                    .await
+                    .context(error::TempSnafu)?;
+                sealed
+            }
+            None => tmp_path,
+        };
         let layer = Layer::builder()
-            .digest(digest.clone())
+            .digest(digest)
             .media_type(media_type.clone())
             .size(writer.size())
             .maybe_platform(platform)
+            .maybe_encryption(self.encryption.as_ref().map(|x| x.encryption()))
             .build();
+        let target_path = self.blob_path(&layer);
 
-        let mut file = tokio::fs::File::open(&tmp_path)
+        let mut file = tokio::fs::File::open(&upload_path)
             .await
             .context(error::TempSnafu)?;
         let file_size = file.metadata().await.context(error::TempSnafu)?.len();
@@ -359,6 +767,10 @@ impl BackendImpl for S3Backend {
                 .create_multipart_upload()
                 .bucket(self.bucket.clone())
                 .key(target_path.to_str().unwrap())
+                .set_server_side_encryption(self.server_side_encryption())
+                .set_ssekms_key_id(self.kms_key_id.clone())
+                .set_storage_class(self.storage_class.clone())
+                .set_tagging(self.tagging())
                 .send()
                 .await
                 .context(error::StartSnafu)?;
@@ -419,14 +831,216 @@ impl BackendImpl for S3Backend {
                 .bucket(self.bucket.clone())
                 .key(target_path.to_str().unwrap())
                 .body(ByteStream::from(buffer))
+                .set_server_side_encryption(self.server_side_encryption())
+                .set_ssekms_key_id(self.kms_key_id.clone())
+                .set_storage_class(self.storage_class.clone())
+                .set_tagging(self.tagging())
                 .send()
                 .await
                 .context(error::PutSnafu)?;
         }
         // Now we can delete the temporary file
-        tokio::fs::remove_file(&tmp_path)
+        tokio::fs::remove_file(&upload_path)
             .await
             .context(error::TempSnafu)?;
         Ok(layer)
     }
+
+    async fn temporary_blobs(&self) -> StorageResult<Option<Vec<TemporaryBlob>>> {
+        // Layers are written to `<uuid>.tmp` in the temp directory, and
+        // sealed into `<uuid>.sealed` when encrypted, until uploaded
+        let dir = std::env::temp_dir();
+        let mut temporary = Vec::new();
+        let mut entries = tokio::fs::read_dir(&dir).await.context(error::TempSnafu)?;
+        while let Some(entry) = entries.next_entry().await.context(error::TempSnafu)? {
+            let path = entry.path();
+            if !is_temporary(&path) {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            temporary.push(TemporaryBlob {
+                target: path.to_string_lossy().to_string(),
+                size: metadata.len(),
+                modified: metadata.modified().context(error::TempSnafu)?,
+            });
+        }
+        Ok(Some(temporary))
+    }
+
+    async fn remove_temporary(&self, target: &str) -> StorageResult<()> {
+        let tmp_path = std::env::temp_dir().join(target);
+        if is_temporary(&tmp_path) && tmp_path.exists() {
+            tokio::fs::remove_file(&tmp_path)
+                .await
+                .context(error::TempSnafu)?;
+        }
+        Ok(())
+    }
+
+    async fn blobs(&self) -> StorageResult<Option<BTreeSet<String>>> {
+        let prefix = format!("{}/", self.blob_key().display());
+        let mut blobs = BTreeSet::new();
+        let mut token = None;
+        loop {
+            let output = self
+                .client
+                .list_objects_v2()
+                .bucket(self.bucket.clone())
+                .prefix(prefix.clone())
+                .set_continuation_token(token)
+                .send()
+                .await
+                .context(error::ListSnafu)?;
+            for object in output.contents() {
+                // `<algorithm>/<hex>` keys are reported as `<algorithm>:<hex>`,
+                // dropping the key id of encrypted blobs
+                if let Some((algorithm, hex)) = object
+                    .key()
+                    .and_then(|x| x.strip_prefix(&prefix))
+                    .and_then(|x| x.split_once('/'))
+                {
+                    let hex = hex.split('.').next().unwrap_or(hex);
+                    blobs.insert(format!("{algorithm}:{hex}"));
+                }
+            }
+            token = output.next_continuation_token().map(|x| x.to_string());
+            if token.is_none() {
+                break;
+            }
+        }
+        Ok(Some(blobs))
+    }
+
+    async fn remove_blob(&self, digest: &str) -> StorageResult<()> {
+        // The plain blob and every encrypted copy share the digest as prefix
+        let key = self.blob_key().join(LayerDigest::from(digest).path());
+        let key = key.to_str().unwrap();
+        let output = self
+            .client
+            .list_objects_v2()
+            .bucket(self.bucket.clone())
+            .prefix(key)
+            .send()
+            .await
+            .context(error::ListSnafu)?;
+        for object in output.contents() {
+            let Some(found) = object.key() else {
+                continue;
+            };
+            if found != key && !found.starts_with(&format!("{key}.")) {
+                continue;
+            }
+            self.client
+                .delete_object()
+                .bucket(self.bucket.clone())
+                .key(found)
+                .send()
+                .await
+                .context(error::DeleteSnafu)?;
+        }
+        Ok(())
+    }
+
+    fn compression(&self) -> Option<Compression> {
+        self.compression.clone()
+    }
+
+    fn transfer_policy(&self) -> TransferPolicy {
+        self.transfer.clone()
+    }
+
+    fn verify_reads(&self) -> bool {
+        self.verify
+    }
+
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        self.algorithm
+    }
+
+    fn location(&self) -> Option<String> {
+        Some(match self.prefix.as_ref() {
+            Some(prefix) => format!("s3://{}/{}", self.bucket, prefix.display()),
+            None => format!("s3://{}", self.bucket),
+        })
+    }
+
+    async fn health(&self) -> Vec<HealthCheck> {
+        let bucket = &self.bucket;
+        if let Err(e) = self.client.head_bucket().bucket(bucket).send().await {
+            return vec![HealthCheck::error(
+                "bucket access",
+                format!("cannot reach bucket {bucket}: {}", DisplayErrorContext(&e)),
+                "check that the bucket exists and that AWS credentials are set, such as with `aws sso login` or AWS_PROFILE",
+            )];
+        }
+        let mut checks = vec![HealthCheck::ok(
+            "bucket access",
+            format!("bucket {bucket} is reachable"),
+        )];
+        let prefix = self
+            .prefix
+            .as_ref()
+            .map(|x| format!("{}/", x.display()))
+            .unwrap_or_default();
+        checks.push(
+            match self
+                .client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(&prefix)
+                .max_keys(1)
+                .send()
+                .await
+            {
+                Ok(_) => HealthCheck::ok("read", "objects can be listed"),
+                Err(e) => HealthCheck::error(
+                    "read",
+                    format!("cannot list objects: {}", DisplayErrorContext(&e)),
+                    format!("grant s3:ListBucket and s3:GetObject on {bucket}/{prefix}*"),
+                ),
+            },
+        );
+        // Builds upload artifacts and the catalog, so a probe object is
+        // written and removed again
+        let probe = format!("{prefix}.edo-doctor-{}", Uuid::now_v7());
+        checks.push(
+            match self
+                .client
+                .put_object()
+                .bucket(bucket)
+                .key(&probe)
+                .body(ByteStream::from_static(b"edo"))
+                .send()
+                .await
+            {
+                Ok(_) => match self
+                    .client
+                    .delete_object()
+                    .bucket(bucket)
+                    .key(&probe)
+                    .send()
+                    .await
+                {
+                    Ok(_) => HealthCheck::ok("write", "objects can be written and deleted"),
+                    Err(e) => HealthCheck::warning(
+                        "write",
+                        format!("cannot delete objects: {}", DisplayErrorContext(&e)),
+                        format!(
+                            "grant s3:DeleteObject on {bucket}/{prefix}*, uploads delete the catalog lock, then remove {probe}"
+                        ),
+                    ),
+                },
+                Err(e) => HealthCheck::warning(
+                    "write",
+                    format!("cannot write objects: {}", DisplayErrorContext(&e)),
+                    format!(
+                        "grant s3:PutObject on {bucket}/{prefix}* for builds to upload, reading the cache still works"
+                    ),
+                ),
+            },
+        );
+        checks
+    }
 }
@@ -41,6 +41,12 @@ impl ObjectReader {
         })
     }
 
+    /// Starts reading at `position` instead of the beginning of the object.
+    pub fn starting_at(mut self, position: u64) -> Self {
+        self.position = position.min(self.size);
+        self
+    }
+
     fn start_request(&mut self, size: usize) {
         let client = self.client.clone();
         let bucket = self.bucket.clone();
@@ -16,11 +16,11 @@
 
 use async_trait::async_trait;
 use edo::{
-    context::{Addr, Context, FromNode, Handle, Log, Node},
+    context::{Addr, Context, Describe, FieldType, FromNode, Handle, KindSchema, Log, Node},
     environment::{Environment, Vfs},
     non_configurable,
     source::Source,
-    storage::{Artifact, Compression, Config, Id, MediaType},
+    storage::{Artifact, Compression, Id, MediaType},
     transform::{TransformError, TransformImpl, TransformResult, TransformStatus},
     util::Reader,
 };
@@ -99,6 +99,27 @@ impl FromNode for CargoVendorTransform {
 
 non_configurable!(CargoVendorTransform, error::Error);
 
+impl Describe for CargoVendorTransform {
+    fn describe() -> KindSchema {
+        KindSchema::new("Vendors the crates the Cargo projects in its sources depend on")
+            .field(
+                "source",
+                super::sources_type(),
+                "Sources holding Cargo projects",
+            )
+            .field(
+                "environment",
+                FieldType::String,
+                "Environment cargo runs in, //default when unset",
+            )
+            .field(
+                "cargo_tomls",
+                FieldType::table(FieldType::list(FieldType::String)),
+                "Cargo.toml files to vendor for, by source name",
+            )
+    }
+}
+
 #[async_trait]
 impl TransformImpl for CargoVendorTransform {
     async fn environment(&self) -> TransformResult<Addr> {
@@ -238,22 +259,14 @@ directory = ".cargo/vendor"
             env.write(cargo_config.path(), reader).await?;
 
             // Now we build an artifact containing an archive of the resulting vendoring
-            let mut artifact = Artifact::builder()
-                .config(Config::builder().id(id).build())
-                .media_type(MediaType::Manifest)
-                .build();
+            let mut tx = ctx.storage().begin_artifact(&id);
 
-            let writer = ctx.storage().safe_start_layer().await?;
+            let writer = tx.start_layer().await?;
             env.read(install_root.path(), writer.clone()).await?;
+            tx.finish_layer(&MediaType::Tar(Compression::None), None, &writer)
+                .await?;
 
-            artifact.layers_mut().push(
-                ctx.storage()
-                    .safe_finish_layer(&MediaType::Tar(Compression::None), None, &writer)
-                    .await?,
-            );
-            ctx.storage().safe_save(&artifact).await?;
-
-            Ok::<Artifact, TransformError>(artifact)
+            Ok::<Artifact, TransformError>(tx.commit().await?)
         }
         .await
         {
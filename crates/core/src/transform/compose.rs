@@ -1,11 +1,19 @@
 use async_trait::async_trait;
 use edo::{
-    context::{Addr, Context, FromNode, Handle, Log, Node, non_configurable},
+    context::{
+        Addr, Context, Describe, FieldType, FromNode, Handle, KindSchema, Log, Node,
+        non_configurable,
+    },
     environment::Environment,
-    storage::{Artifact, Compression, Config, Id, MediaType},
-    transform::{TransformImpl, TransformResult, TransformStatus},
+    storage::{Compression, Id, MediaType},
+    transform::{
+        CacheKey, Contract, KeyKind, KeyPolicy, TransformImpl, TransformResult, TransformStatus,
+        add_contract, add_provides, declared_metadata, declared_provides, metadata_field,
+        provides_field, record_contract, record_provides, select_layers,
+    },
 };
 use snafu::OptionExt;
+use std::collections::BTreeSet;
 use std::path::Path;
 
 /// A transform that composes multiple dependency artifacts into a single output artifact.
@@ -13,6 +21,11 @@ pub struct ComposeTransform {
     pub addr: Addr,
     pub arch: Option<String>,
     pub depends: Vec<Addr>,
+    pub policy: KeyPolicy,
+    /// Metadata declared on the artifact.
+    pub contract: Contract,
+    /// Capabilities the artifact provides.
+    pub provides: BTreeSet<String>,
 }
 
 #[async_trait]
@@ -34,12 +47,32 @@ impl FromNode for ComposeTransform {
             addr: addr.clone(),
             arch,
             depends,
+            policy: KeyPolicy::from_config(ctx.config())?,
+            contract: declared_metadata(node)?,
+            provides: declared_provides(node)?,
         })
     }
 }
 
 non_configurable!(ComposeTransform, error::Error);
 
+impl Describe for ComposeTransform {
+    fn describe() -> KindSchema {
+        KindSchema::new("Merges the artifacts of other transforms into one")
+            .field(
+                "depends",
+                FieldType::list(FieldType::String),
+                "Transforms whose artifacts are merged",
+            )
+            .field(
+                "arch",
+                FieldType::String,
+                "Architecture of the merged artifact",
+            )
+            .fields([metadata_field(), provides_field()])
+    }
+}
+
 #[async_trait]
 impl TransformImpl for ComposeTransform {
     async fn environment(&self) -> TransformResult<Addr> {
@@ -48,30 +81,28 @@ impl TransformImpl for ComposeTransform {
     }
 
     async fn get_unique_id(&self, ctx: &Handle) -> TransformResult<Id> {
-        let mut hash = blake3::Hasher::new();
+        let id = self.cache_key(ctx).await?.id();
+        trace!(component = "transform", type = "compose", "id is calculated to be {id}");
+        Ok(id)
+    }
+
+    async fn cache_key(&self, ctx: &Handle) -> TransformResult<CacheKey> {
+        let mut key = CacheKey::new(self.addr.to_id(), &self.policy);
         let mut depend = self.depends.clone();
         depend.sort();
         for depend in depend.iter() {
             let t = ctx.get(depend).context(error::NotFoundSnafu {
                 addr: depend.clone(),
             })?;
-            let id = t.get_unique_id(ctx).await?;
-            hash.update(id.digest().as_bytes());
+            let id = ctx.unique_id(depend, &t).await?;
+            key.add_digest(KeyKind::Depend, depend.to_string(), id.digest());
         }
-        let hash_bytes = hash.finalize();
-        let digest = base16::encode_lower(hash_bytes.as_bytes());
-        let arch = self
-            .arch
-            .as_ref()
-            .map(|arch| ctx.args().get("arch").cloned().unwrap_or(arch.clone()));
-
-        let id = Id::builder()
-            .name(self.addr.to_id())
-            .digest(digest)
-            .maybe_arch(arch)
-            .build();
-        trace!(component = "transform", type = "compose", "id is calculated to be {id}");
-        Ok(id.clone())
+        if let Some(arch) = self.arch.as_ref() {
+            key.add_platform(ctx.args().get("arch").unwrap_or(arch));
+        }
+        add_contract(&mut key, &self.contract);
+        add_provides(&mut key, &self.provides);
+        Ok(key)
     }
 
     async fn depends(&self) -> TransformResult<Vec<Addr>> {
@@ -91,11 +122,12 @@ impl TransformImpl for ComposeTransform {
             let t = ctx
                 .get(&dep)
                 .context(error::NotFoundSnafu { addr: dep.clone() })?;
-            let id = t.get_unique_id(ctx).await?;
+            let id = ctx.unique_id(&dep, &t).await?;
             // TODO: We need to find a more portable way to do this than just assuming archives
             trace!(component = "transform", type = "compose", "staging dependencies {dep} with id {id} into install-root");
             let artifact = ctx.storage().safe_open(&id).await?;
-            for layer in artifact.layers() {
+            let arch = self.arch.as_deref().unwrap_or(std::env::consts::ARCH);
+            for layer in select_layers(&artifact, arch)? {
                 let reader = ctx.storage().safe_read(layer).await?;
                 match layer.media_type() {
                     MediaType::Tar(..) => {
@@ -123,22 +155,17 @@ impl TransformImpl for ComposeTransform {
             let id = self.get_unique_id(ctx).await?;
 
             // Create the artifact manifest
-            let mut artifact = Artifact::builder()
-                .config(Config::builder().id(id.clone()).build())
-                .media_type(MediaType::Manifest)
-                .build();
+            let mut tx = ctx.storage().begin_artifact(&id);
+            record_contract(tx.artifact_mut(), &self.contract);
+            record_provides(tx.artifact_mut(), &self.provides);
 
             // A Compose transform combines physically all the child dependents,
             // we should add a Combine transform that just does a layer collection.
-            let writer = ctx.storage().safe_start_layer().await?;
+            let writer = tx.start_layer().await?;
             env.read(Path::new("install-root"), writer.clone()).await?;
-            artifact.layers_mut().push(
-                ctx.storage()
-                    .safe_finish_layer(&MediaType::Tar(Compression::None), None, &writer)
-                    .await?,
-            );
-            ctx.storage().safe_save(&artifact).await?;
-            Ok(artifact)
+            tx.finish_layer(&MediaType::Tar(Compression::None), None, &writer)
+                .await?;
+            Ok(tx.commit().await?)
         }
         .await
         {
@@ -23,11 +23,11 @@
 
 use async_trait::async_trait;
 use edo::{
-    context::{Addr, Context, FromNode, Handle, Log, Node},
+    context::{Addr, Context, Describe, FieldType, FromNode, Handle, KindSchema, Log, Node},
     environment::{Environment, Vfs},
     non_configurable,
     source::Source,
-    storage::{Artifact, Compression, Config, Id, MediaType},
+    storage::{Artifact, Compression, Id, MediaType},
     transform::{TransformError, TransformImpl, TransformResult, TransformStatus},
 };
 use snafu::OptionExt;
@@ -97,6 +97,23 @@ impl FromNode for GoVendorTransform {
 
 non_configurable!(GoVendorTransform, error::Error);
 
+impl Describe for GoVendorTransform {
+    fn describe() -> KindSchema {
+        KindSchema::new("Vendors the modules the Go modules in its source depend on")
+            .required("source", FieldType::String, "Source holding the Go modules")
+            .field(
+                "environment",
+                FieldType::String,
+                "Environment go runs in, //default when unset",
+            )
+            .field(
+                "modules",
+                FieldType::list(FieldType::String),
+                "Module directories to vendor, the source root when unset",
+            )
+    }
+}
+
 #[async_trait]
 impl TransformImpl for GoVendorTransform {
     async fn environment(&self) -> TransformResult<Addr> {
@@ -199,22 +216,12 @@ impl TransformImpl for GoVendorTransform {
             }
             // Now we build an artifact containing an archive of the resulting vendor directories
             // that can overlay with the source
-            let writer = ctx.storage().safe_start_layer().await?;
+            let mut tx = ctx.storage().begin_artifact(&id);
+            let writer = tx.start_layer().await?;
             env.read(install_root.path(), writer.clone()).await?;
-            let layer = ctx.storage().safe_finish_layer(&MediaType::Tar(Compression::None), None, &writer).await?;
-
-            let artifact = Artifact::builder()
-                .config(Config::builder()
-                    .id(id)
-                    .build()
-                )
-                .media_type(MediaType::Manifest)
-                .layers(vec![layer])
-                .build();
-
-            ctx.storage().safe_save(&artifact).await?;
+            tx.finish_layer(&MediaType::Tar(Compression::None), None, &writer).await?;
 
-            Ok::<Artifact, TransformError>(artifact)
+            Ok::<Artifact, TransformError>(tx.commit().await?)
         }
         .await
         {
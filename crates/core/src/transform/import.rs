@@ -1,16 +1,28 @@
 use async_trait::async_trait;
-use edo::context::{Addr, Context, FromNode, Handle, Log, Node, non_configurable};
+use edo::context::{
+    Addr, Context, Describe, FromNode, Handle, KindSchema, Log, Node, non_configurable,
+};
 use edo::environment::Environment;
 use edo::source::Source;
-use edo::storage::{Artifact, Compression, Config, Id, MediaType};
-use edo::transform::{TransformImpl, TransformResult, TransformStatus};
+use edo::storage::{Compression, Id, MediaType};
+use edo::transform::{
+    CacheKey, Contract, KeyKind, KeyPolicy, TransformImpl, TransformResult, TransformStatus,
+    add_contract, add_provides, declared_metadata, declared_provides, metadata_field,
+    provides_field, record_contract, record_provides,
+};
 use indexmap::IndexMap;
+use std::collections::BTreeSet;
 use std::path::Path;
 
 /// A transform that imports sources directly into the build environment as an artifact.
 pub struct ImportTransform {
     pub addr: Addr,
     pub sources: IndexMap<String, Source>,
+    pub policy: KeyPolicy,
+    /// Metadata declared on the artifact.
+    pub contract: Contract,
+    /// Capabilities the artifact provides.
+    pub provides: BTreeSet<String>,
 }
 
 #[async_trait]
@@ -25,12 +37,23 @@ impl FromNode for ImportTransform {
                 type_: type_.to_string(),
             })
             .await?,
+            policy: KeyPolicy::from_config(ctx.config())?,
+            contract: declared_metadata(node)?,
+            provides: declared_provides(node)?,
         })
     }
 }
 
 non_configurable!(ImportTransform, error::Error);
 
+impl Describe for ImportTransform {
+    fn describe() -> KindSchema {
+        KindSchema::new("Saves sources as an artifact as they are")
+            .field("source", super::sources_type(), "Sources to import")
+            .fields([metadata_field(), provides_field()])
+    }
+}
+
 #[async_trait]
 impl TransformImpl for ImportTransform {
     async fn environment(&self) -> TransformResult<Addr> {
@@ -38,27 +61,26 @@ impl TransformImpl for ImportTransform {
         Ok(addr)
     }
 
-    async fn get_unique_id(&self, _ctx: &Handle) -> TransformResult<Id> {
-        let mut hash = blake3::Hasher::new();
-        for source in self.sources.values() {
-            hash.update(source.get_unique_id().await?.digest().as_bytes());
-        }
-        let hash_bytes = hash.finalize();
-        let digest = base16::encode_lower(hash_bytes.as_bytes());
-        let id = Id::builder()
-            .name(
-                self.addr
-                    .to_string()
-                    .strip_prefix("//")
-                    .unwrap()
-                    .to_string(),
-            )
-            .digest(digest)
-            .build();
+    async fn get_unique_id(&self, ctx: &Handle) -> TransformResult<Id> {
+        let id = self.cache_key(ctx).await?.id();
         trace!(component = "transform", type = "import", "calculated id to be {id}");
         Ok(id)
     }
 
+    async fn cache_key(&self, _ctx: &Handle) -> TransformResult<CacheKey> {
+        let mut key = CacheKey::new(self.addr.to_id(), &self.policy);
+        for (name, source) in self.sources.iter() {
+            key.add_digest(
+                KeyKind::Source,
+                name.clone(),
+                source.get_unique_id().await?.digest(),
+            );
+        }
+        add_contract(&mut key, &self.contract);
+        add_provides(&mut key, &self.provides);
+        Ok(key)
+    }
+
     async fn depends(&self) -> TransformResult<Vec<Addr>> {
         Ok(Vec::new())
     }
@@ -66,7 +88,7 @@ impl TransformImpl for ImportTransform {
     async fn prepare(&self, log: &Log, ctx: &Handle) -> TransformResult<()> {
         for (addr, source) in self.sources.iter() {
             trace!(component = "transform", type = "import", "fetching source {addr}");
-            source.fetch(log, ctx.storage()).await?;
+            source.cache(log, ctx.storage()).await?;
         }
         Ok(())
     }
@@ -90,19 +112,14 @@ impl TransformImpl for ImportTransform {
         // Transform is simply as we just archive the output directory
         match async move {
             let id = self.get_unique_id(ctx).await?;
-            let mut artifact = Artifact::builder()
-                .config(Config::builder().id(id.clone()).build())
-                .media_type(MediaType::Manifest)
-                .build();
-            let writer = ctx.storage().safe_start_layer().await?;
+            let mut tx = ctx.storage().begin_artifact(&id);
+            record_contract(tx.artifact_mut(), &self.contract);
+            record_provides(tx.artifact_mut(), &self.provides);
+            let writer = tx.start_layer().await?;
             env.read(Path::new("output"), writer.clone()).await?;
-            artifact.layers_mut().push(
-                ctx.storage()
-                    .safe_finish_layer(&MediaType::Tar(Compression::None), None, &writer)
-                    .await?,
-            );
-            ctx.storage().safe_save(&artifact).await?;
-            Ok(artifact)
+            tx.finish_layer(&MediaType::Tar(Compression::None), None, &writer)
+                .await?;
+            Ok(tx.commit().await?)
         }
         .await
         {
@@ -4,7 +4,7 @@ pub mod go_vendor;
 pub mod import;
 pub mod script;
 
-use edo::context::{Addr, Context, ContextError, Node};
+use edo::context::{Addr, Context, ContextError, FieldType, Node};
 use edo::source::Source;
 use indexmap::IndexMap;
 
@@ -28,6 +28,11 @@ where
     parse_sources_with_name("source", addr, node, ctx, field_error).await
 }
 
+/// Describes a `source` key, naming one source or a list of them.
+pub fn sources_type() -> FieldType {
+    FieldType::one_of([FieldType::String, FieldType::list(FieldType::String)])
+}
+
 pub async fn parse_sources_with_name<E, F>(
     field: &str,
     addr: &Addr,
@@ -1,26 +1,93 @@
-use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
 
-use edo::context::{Addr, Context, FromNode, Handle, Log, Node, non_configurable};
-use edo::environment::Environment;
+use edo::context::{
+    Addr, Context, Describe, FieldType, FromNode, Handle, KindSchema, Log, Node, VARIANT_KEY,
+    non_configurable,
+};
+use edo::environment::{Environment, HostAccess, HostRequirements, Placeholders, Step};
 use edo::source::Source;
-use edo::storage::{Artifact, Compression, Config, Id, MediaType};
-use edo::transform::{TransformError, TransformImpl, TransformResult, TransformStatus};
+use edo::storage::{Artifact, ArtifactTransaction, Compression, Id, MediaType};
+use edo::transform::{
+    CacheKey, Contract, KeyKind, KeyPolicy, SOURCE_DATE_EPOCH, SOURCE_DATE_EPOCH_FIELD,
+    TransformImpl, TransformResult, TransformStatus, add_contract, add_provides, capture_script,
+    declared_metadata, declared_provides, epoch_field, metadata_field, provides_field,
+    record_contract, record_provides, results_id, results_media_type, select_layers,
+    source_date_epoch,
+};
+use edo::util::Writer;
 
 use async_trait::async_trait;
 use indexmap::IndexMap;
 use ocilot::models::Platform;
-use snafu::OptionExt;
+use serde::Deserialize;
+use snafu::{OptionExt, ResultExt, ensure};
+use tokio::io::AsyncReadExt;
+
+/// What a `commands` entry may be, for error messages.
+const COMMAND_TYPE: &str = "list of strings, argument lists or command tables";
 
 /// A transform that executes shell commands in a build environment to produce an artifact.
+///
+/// Each entry of `commands` is a line of shell script, an argument list run
+/// without shell quoting (`["python3", "build.py", "--release"]`), or a table
+/// holding either `run` or `argv` plus an optional `interpreter` for `run`,
+/// `env` table and working directory `dir`.
+///
+/// `pre` and `post` take the same entries and run before and after
+/// `commands`, each in its own section of the log. When any of them fails,
+/// the `on_failure` entries run, for instance to collect diagnostics, before
+/// the results are captured and the transform fails.
+///
+/// With a `source_date_epoch`, its own or the project's, the commands run
+/// with `SOURCE_DATE_EPOCH` set and the epoch is recorded in the artifact.
+///
+/// A `metadata` table is recorded on the artifact for dependents, which bind
+/// entries of it to template variables with `consume`.
+///
+/// The artifact is a single tar of the install-root, or of its `artifact`
+/// path. With `outputs`, the commands instead write a manifest to that path
+/// of the install-root, one JSON object per line such as
+/// `{"path": "linux-arm64", "media_type": "tar", "platform": "linux/arm64"}`,
+/// and every entry is saved as its own layer.
 pub struct ScriptTransform {
     pub addr: Addr,
     pub arch: Option<String>,
     pub environment: Addr,
     pub depends: Vec<Addr>,
-    pub commands: Vec<String>,
+    pub commands: Vec<Step>,
+    /// Commands run before `commands`.
+    pub pre: Vec<Step>,
+    /// Commands run after `commands` succeeded.
+    pub post: Vec<Step>,
+    /// Commands run when `pre`, `commands` or `post` failed.
+    pub on_failure: Vec<Step>,
     pub interpreter: String,
     pub artifact: Option<PathBuf>,
+    /// Manifest in the install-root naming the layers of the artifact.
+    pub outputs: Option<PathBuf>,
     pub sources: IndexMap<String, Source>,
+    pub variant: BTreeMap<String, String>,
+    pub host: HostAccess,
+    /// What the commands need of the machine they run on.
+    pub requires: HostRequirements,
+    /// Whether the commands run edo themselves, with the local cache
+    /// mounted read-only, see [`NESTED_CACHE_ENV`](edo::context::NESTED_CACHE_ENV).
+    pub nested: bool,
+    /// Shell globs, relative to the build-root, of files saved as a results
+    /// artifact whether or not the commands succeed.
+    pub capture: Vec<String>,
+    /// Which inputs are hashed into the unique id, from the `[ids]` config.
+    pub policy: KeyPolicy,
+    /// The time the commands see, in seconds since 1970.
+    pub epoch: Option<i64>,
+    /// Metadata declared on the artifact.
+    pub contract: Contract,
+    /// Capabilities the artifact provides.
+    pub provides: BTreeSet<String>,
+    /// Template variables bound to the metadata a dependency declared, as
+    /// the dependency and the name of the entry.
+    pub consume: BTreeMap<String, (Addr, String)>,
 }
 
 #[async_trait]
@@ -47,25 +114,10 @@ impl FromNode for ScriptTransform {
         } else {
             "bash".to_string()
         };
-        let mut commands: Vec<String> = Vec::new();
-        for line in node
-            .get("commands")
-            .unwrap()
-            .as_list()
-            .context(error::FieldSnafu {
-                field: "commands",
-                type_: "list of strings",
-            })?
-        {
-            commands.push(
-                line.as_string()
-                    .context(error::FieldSnafu {
-                        field: "commands",
-                        type_: "list of strings",
-                    })?
-                    .clone(),
-            );
-        }
+        let commands = parse_commands(node, "commands")?;
+        let pre = parse_commands(node, "pre")?;
+        let post = parse_commands(node, "post")?;
+        let on_failure = parse_commands(node, "on_failure")?;
         let artifact = if let Some(n) = node.get("artifact") {
             Some(PathBuf::from(n.as_string().context(error::FieldSnafu {
                 field: "artifact",
@@ -74,16 +126,101 @@ impl FromNode for ScriptTransform {
         } else {
             None
         };
+        let outputs = if let Some(n) = node.get("outputs") {
+            Some(PathBuf::from(n.as_string().context(error::FieldSnafu {
+                field: "outputs",
+                type_: "string",
+            })?))
+        } else {
+            None
+        };
+        ensure!(
+            artifact.is_none() || outputs.is_none(),
+            error::ConflictSnafu {
+                first: "artifact",
+                second: "outputs",
+            }
+        );
+        let mut variant = BTreeMap::new();
+        if let Some(n) = node.get(VARIANT_KEY) {
+            for (key, value) in n.as_table().context(error::FieldSnafu {
+                field: VARIANT_KEY,
+                type_: "table of strings",
+            })? {
+                variant.insert(
+                    key,
+                    value.as_string().context(error::FieldSnafu {
+                        field: VARIANT_KEY,
+                        type_: "table of strings",
+                    })?,
+                );
+            }
+        }
+        let consume = parse_consume(node)?;
+        // Commands may only use the builtin placeholders, variant axes, user
+        // args and consumed metadata
+        let mut placeholders = Placeholders::new();
+        for key in variant
+            .keys()
+            .chain(ctx.args().keys())
+            .chain(consume.keys())
+        {
+            placeholders.define(key);
+        }
+        for (field, steps) in [
+            ("commands", &commands),
+            ("pre", &pre),
+            ("post", &post),
+            ("on_failure", &on_failure),
+        ] {
+            for step in steps.iter() {
+                step.validate(&placeholders)
+                    .context(error::TemplateSnafu { field })?;
+            }
+        }
         let field_error = |field: &str, type_: &str| error::Error::Field {
             field: field.to_string(),
             type_: type_.to_string(),
         };
+        let mut capture = Vec::new();
+        if let Some(n) = node.get("capture") {
+            for pattern in n.as_list().context(error::FieldSnafu {
+                field: "capture",
+                type_: "list of strings",
+            })? {
+                capture.push(pattern.as_string().context(error::FieldSnafu {
+                    field: "capture",
+                    type_: "list of strings",
+                })?);
+            }
+        }
+        let host = HostAccess::from_node(node)?;
+        let requires = HostRequirements::from_node(node)?;
+        let nested = match node.get("nested") {
+            Some(flag) => flag.as_bool().context(error::FieldSnafu {
+                field: "nested",
+                type_: "boolean",
+            })?,
+            None => false,
+        };
+        let policy = KeyPolicy::from_config(ctx.config())?;
+        let epoch = source_date_epoch(node, ctx.config())?;
         let depends = super::parse_depends(node, "depends", field_error).await?;
+        // Only dependencies are built before the transform
+        if let Some((name, _)) = consume.iter().find(|(_, (x, _))| !depends.contains(x)) {
+            return error::FieldSnafu {
+                field: format!("consume.{name}"),
+                type_: "transform listed in depends",
+            }
+            .fail();
+        }
         let sources = super::parse_sources(addr, node, ctx, field_error).await?;
         Ok(Self {
             addr: addr.clone(),
             arch: if let Some(arch) = ctx.args().get("arch") {
                 Some(arch.clone())
+            } else if let Some(arch) = variant.get("arch") {
+                Some(arch.clone())
             } else {
                 node.get("arch").and_then(|x| x.as_string())
             },
@@ -91,14 +228,487 @@ impl FromNode for ScriptTransform {
             depends,
             interpreter,
             commands,
+            pre,
+            post,
+            on_failure,
             sources,
             artifact,
+            outputs,
+            variant,
+            host,
+            requires,
+            nested,
+            capture,
+            policy,
+            epoch,
+            contract: declared_metadata(node)?,
+            provides: declared_provides(node)?,
+            consume,
         })
     }
 }
 
 non_configurable!(ScriptTransform, error::Error);
 
+impl Describe for ScriptTransform {
+    fn describe() -> KindSchema {
+        let command = FieldType::one_of([
+            FieldType::String,
+            FieldType::list(FieldType::String),
+            FieldType::table(FieldType::Any),
+        ]);
+        KindSchema::new("Runs commands in an environment to build an artifact")
+            .required(
+                "commands",
+                FieldType::list(command.clone()),
+                "Shell lines, argument lists or tables of run/argv, interpreter, env and dir",
+            )
+            .field(
+                "pre",
+                FieldType::list(command.clone()),
+                "Commands run before commands, in their own log section",
+            )
+            .field(
+                "post",
+                FieldType::list(command.clone()),
+                "Commands run after commands succeed, in their own log section",
+            )
+            .field(
+                "on_failure",
+                FieldType::list(command),
+                "Commands run when pre, commands or post fail, before results are captured",
+            )
+            .field(
+                "environment",
+                FieldType::String,
+                "Environment to run in, //default when unset",
+            )
+            .field(
+                "interpreter",
+                FieldType::String,
+                "Shell running the commands, bash when unset",
+            )
+            .field(
+                "source",
+                super::sources_type(),
+                "Sources staged in the build-root",
+            )
+            .field(
+                "depends",
+                FieldType::list(FieldType::String),
+                "Transforms whose artifacts are staged in the build-root",
+            )
+            .field(
+                "artifact",
+                FieldType::String,
+                "File or directory saved as the artifact instead of the install-root",
+            )
+            .field(
+                "outputs",
+                FieldType::String,
+                "JSON lines manifest the commands write in the install-root, naming the path, media_type and platform of each layer",
+            )
+            .field(
+                "capture",
+                FieldType::list(FieldType::String),
+                "Globs of files saved as results even when the commands fail",
+            )
+            .field(
+                "arch",
+                FieldType::String,
+                "Architecture the artifact is built for",
+            )
+            .field(
+                "nested",
+                FieldType::Boolean,
+                "Lets the commands run edo, reading the local cache without writing to it",
+            )
+            .field(
+                "consume",
+                FieldType::table(FieldType::one_of([
+                    FieldType::String,
+                    FieldType::table(FieldType::String),
+                ])),
+                "Template variables bound to the metadata of a dependency, as its address or a table of from and key",
+            )
+            .fields([epoch_field(), metadata_field(), provides_field()])
+            .fields(HostAccess::fields())
+            .fields(HostRequirements::fields())
+    }
+}
+
+/// Parses `consume`, which maps each variable either to the address of a
+/// dependency declaring metadata of the same name, or to a table naming the
+/// dependency in `from` and the entry in `key`.
+fn parse_consume(node: &Node) -> Result<BTreeMap<String, (Addr, String)>, error::Error> {
+    let mut consume = BTreeMap::new();
+    let Some(table) = node.get("consume") else {
+        return Ok(consume);
+    };
+    let table = table.as_table().context(error::FieldSnafu {
+        field: "consume",
+        type_: "table",
+    })?;
+    for (name, value) in table {
+        let (from, key) = match value.as_string() {
+            Some(from) => (from, name.clone()),
+            None => {
+                let string = |field: &str| {
+                    value
+                        .get(field)
+                        .and_then(|x| x.as_string())
+                        .context(error::FieldSnafu {
+                            field: format!("consume.{name}.{field}"),
+                            type_: "string",
+                        })
+                };
+                (string("from")?, string("key")?)
+            }
+        };
+        consume.insert(name, (Addr::parse(&from)?, key));
+    }
+    Ok(consume)
+}
+
+/// One line of the outputs manifest, see [`ScriptTransform`].
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OutputEntry {
+    /// File or directory relative to the install-root.
+    path: String,
+    /// Media type without its `vnd.edo.artifact.v1.` prefix, such as `tar`.
+    media_type: String,
+    /// Platform as `<os>/<arch>`, the transform's when unset.
+    platform: Option<String>,
+}
+
+/// Parses the outputs manifest at `manifest`, returning the path, media type
+/// and platform of each layer it names.
+fn parse_outputs(
+    manifest: &Path,
+    content: &str,
+) -> Result<Vec<(PathBuf, MediaType, Option<Platform>)>, error::Error> {
+    let mut outputs = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = |reason: String| error::Error::Outputs {
+            path: manifest.to_path_buf(),
+            line: index + 1,
+            reason,
+        };
+        let entry: OutputEntry = serde_json::from_str(line).map_err(|e| invalid(e.to_string()))?;
+        let path = PathBuf::from(&entry.path);
+        if !path
+            .components()
+            .all(|x| matches!(x, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(invalid(format!(
+                "{} is not a path inside the install-root",
+                entry.path
+            )));
+        }
+        let media_type =
+            MediaType::from_short_name(&entry.media_type).map_err(|e| invalid(e.to_string()))?;
+        let platform = entry
+            .platform
+            .map(|x| match x.split_once('/') {
+                Some((os, architecture)) if !os.is_empty() && !architecture.is_empty() => {
+                    Ok(Platform::builder()
+                        .os(os)
+                        .architecture(architecture)
+                        .build())
+                }
+                _ => Err(invalid(format!(
+                    "platform {x} is not written as <os>/<arch>"
+                ))),
+            })
+            .transpose()?;
+        outputs.push((path, media_type, platform));
+    }
+    ensure!(
+        !outputs.is_empty(),
+        error::NoOutputsSnafu {
+            path: manifest.to_path_buf()
+        }
+    );
+    Ok(outputs)
+}
+
+/// Reads the file at `path` in `env` into a string.
+async fn read_string(env: &Environment, path: &Path) -> TransformResult<String> {
+    let (write, mut read) = tokio::io::duplex(64 * 1024);
+    let writer = Writer::new(path.display().to_string(), write);
+    // The writer is dropped once the file is copied, ending the read
+    let (result, content) = tokio::join!(env.read(path, writer), async {
+        let mut content = String::new();
+        read.read_to_string(&mut content).await.map(|_| content)
+    });
+    result?;
+    Ok(content.context(error::ReadOutputsSnafu { path })?)
+}
+
+/// Parses the optional list of commands in `field`, `commands` being
+/// required by the caller.
+fn parse_commands(node: &Node, field: &str) -> Result<Vec<Step>, error::Error> {
+    let Some(list) = node.get(field) else {
+        return Ok(Vec::new());
+    };
+    list.as_list()
+        .context(error::FieldSnafu {
+            field,
+            type_: COMMAND_TYPE,
+        })?
+        .iter()
+        .map(parse_command)
+        .collect()
+}
+
+/// Parses one entry of `commands`, see [`ScriptTransform`].
+fn parse_command(node: &Node) -> Result<Step, error::Error> {
+    let field = |field: &str, type_: &str| error::Error::Field {
+        field: field.to_string(),
+        type_: type_.to_string(),
+    };
+    let strings = |node: &Node, name: &str| {
+        node.as_list()
+            .and_then(|x| x.iter().map(|x| x.as_string()).collect::<Option<Vec<_>>>())
+            .filter(|x| !x.is_empty())
+            .ok_or_else(|| field(name, "non-empty list of strings"))
+    };
+    if let Some(line) = node.as_string() {
+        return Ok(Step::script(&line));
+    }
+    if node.as_list().is_some() {
+        return Ok(Step::argv(&strings(node, "commands")?));
+    }
+    let table = node
+        .as_table()
+        .ok_or_else(|| field("commands", COMMAND_TYPE))?;
+    if let Some(key) = table
+        .keys()
+        .find(|x| !["run", "argv", "interpreter", "env", "dir"].contains(&x.as_str()))
+    {
+        return Err(field(
+            &format!("commands.{key}"),
+            "one of run, argv, interpreter, env or dir",
+        ));
+    }
+    let string = |name: &str| {
+        table
+            .get(name)
+            .map(|x| {
+                x.as_string()
+                    .ok_or_else(|| field(&format!("commands.{name}"), "string"))
+            })
+            .transpose()
+    };
+    let mut step = match (string("run")?, table.get("argv")) {
+        (Some(run), None) => Step::script(&run),
+        (None, Some(argv)) => Step::argv(&strings(argv, "commands.argv")?),
+        _ => return Err(field("commands", "table with exactly one of run or argv")),
+    };
+    step.interpreter = string("interpreter")?;
+    if step.interpreter.is_some() && table.contains_key("argv") {
+        return Err(field(
+            "commands.interpreter",
+            "string next to run, not argv",
+        ));
+    }
+    step.dir = string("dir")?;
+    if let Some(env) = table.get("env") {
+        for (key, value) in env
+            .as_table()
+            .ok_or_else(|| field("commands.env", "table of strings"))?
+        {
+            let value = value
+                .as_string()
+                .ok_or_else(|| field("commands.env", "table of strings"))?;
+            step.env.insert(key, value);
+        }
+    }
+    Ok(step)
+}
+
+impl ScriptTransform {
+    /// Runs the commands and saves the install-root as the artifact `id`.
+    async fn build(
+        &self,
+        log: &Log,
+        ctx: &Handle,
+        env: &Environment,
+        id: &Id,
+    ) -> TransformResult<Artifact> {
+        let mut cmd = env.defer_cmd(log, id);
+        cmd.set_interpreter(self.interpreter.as_str());
+        cmd.trace_steps();
+        cmd.create_named_dir("build-root", "build-root").await?;
+        cmd.create_named_dir("install-root", "install-root").await?;
+        if let Some(arch) = self.arch.as_ref() {
+            cmd.set("arch", arch.as_str())?;
+        } else {
+            cmd.set("arch", std::env::consts::ARCH)?;
+        }
+        for (key, value) in self.variant.iter() {
+            if key == "arch" {
+                continue;
+            }
+            cmd.set(key, value)?;
+        }
+        for (key, value) in ctx.args() {
+            if key == "arch" {
+                continue;
+            }
+            cmd.set(key, value)?;
+        }
+        for (name, (from, key)) in self.consume.iter() {
+            let value = match ctx.metadata_value(from, key).await? {
+                serde_json::Value::String(value) => value,
+                value => value.to_string(),
+            };
+            cmd.set(name, &value)?;
+        }
+        if let Some(epoch) = self.epoch {
+            env.set_env(SOURCE_DATE_EPOCH, &epoch.to_string()).await?;
+        }
+
+        let mut pre = cmd.section("pre");
+        for step in self.pre.iter() {
+            pre.run_step(step).await?;
+        }
+        // Once pre has its own section the main commands need one too
+        let mut main = if self.pre.is_empty() {
+            cmd.clone()
+        } else {
+            cmd.section("main")
+        };
+        for command in self.commands.iter() {
+            main.run_step(command).await?;
+        }
+        let mut post = cmd.section("post");
+        for step in self.post.iter() {
+            post.run_step(step).await?;
+        }
+
+        let mut result = Ok(());
+        for (steps, command) in [
+            (&self.pre, &pre),
+            (&self.commands, &main),
+            (&self.post, &post),
+        ] {
+            if steps.is_empty() {
+                continue;
+            }
+            result = command.send("{{build-root}}").await;
+            if result.is_err() {
+                break;
+            }
+        }
+        if let Err(e) = result {
+            if !self.on_failure.is_empty() {
+                let mut on_failure = cmd.section("on_failure");
+                for step in self.on_failure.iter() {
+                    on_failure.run_step(step).await?;
+                }
+                if let Err(failure) = on_failure.send("{{build-root}}").await {
+                    warn!(component = "transform", type = "script", "on_failure commands of {} failed: {failure}", self.addr);
+                }
+            }
+            return Err(e.into());
+        }
+
+        // The result of a script transform is everything put in the install-root
+        let mut tx = ctx.storage().begin_artifact(id);
+        if let Some(epoch) = self.epoch {
+            *tx.artifact_mut().config_mut().metadata_mut() =
+                serde_json::json!({ SOURCE_DATE_EPOCH_FIELD: epoch });
+        }
+        record_contract(tx.artifact_mut(), &self.contract);
+        record_provides(tx.artifact_mut(), &self.provides);
+
+        if let Some(manifest) = self.outputs.as_ref() {
+            self.save_outputs(env, &mut tx, manifest).await?;
+            return Ok(tx.commit().await?);
+        }
+
+        // Open a layer to store the result in, discarded if reading it fails
+        let writer = tx.start_layer().await?;
+        let mut apath = PathBuf::from("install-root");
+        if let Some(path) = self.artifact.as_ref() {
+            apath = apath.join(path);
+        }
+        env.read(apath.as_path(), writer.clone()).await?;
+        tx.finish_layer(
+            &MediaType::Tar(Compression::None),
+            Some(self.default_platform()),
+            &writer,
+        )
+        .await?;
+        Ok(tx.commit().await?)
+    }
+
+    /// Saves each entry of the outputs manifest at `manifest`, relative to
+    /// the install-root, as a layer of the artifact in the order listed.
+    async fn save_outputs(
+        &self,
+        env: &Environment,
+        tx: &mut ArtifactTransaction,
+        manifest: &Path,
+    ) -> TransformResult<()> {
+        let root = Path::new("install-root");
+        let content = read_string(env, &root.join(manifest)).await?;
+        for (path, media_type, platform) in parse_outputs(manifest, &content)? {
+            let writer = tx.start_layer().await?;
+            env.read(&root.join(&path), writer.clone()).await?;
+            let platform = platform.unwrap_or_else(|| self.default_platform());
+            tx.finish_layer(&media_type, Some(platform), &writer)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// The platform of layers the commands did not name one for.
+    fn default_platform(&self) -> Platform {
+        Platform::builder()
+            .os(std::env::consts::OS)
+            .architecture(
+                self.arch
+                    .clone()
+                    .unwrap_or(std::env::consts::OS.to_string()),
+            )
+            .build()
+    }
+
+    /// Saves the files matching `capture` as the results artifact of `id`.
+    async fn capture(
+        &self,
+        log: &Log,
+        ctx: &Handle,
+        env: &Environment,
+        id: &Id,
+    ) -> TransformResult<()> {
+        let root = Path::new("capture-root");
+        env.create_dir(root).await?;
+        let dest = env.expand(root).await?;
+        let results = results_id(id);
+        let mut cmd = env.defer_cmd(log, &results);
+        cmd.run(&capture_script(&self.capture, &dest.to_string_lossy()))
+            .await?;
+        cmd.send("build-root").await?;
+
+        let mut tx = ctx.storage().begin_artifact(&results);
+        *tx.artifact_mut().media_type_mut() = results_media_type();
+        let writer = tx.start_layer().await?;
+        env.read(root, writer.clone()).await?;
+        tx.finish_layer(&MediaType::Tar(Compression::None), None, &writer)
+            .await?;
+        tx.commit().await?;
+        info!(component = "transform", type = "script", "captured the results of {} as {results}", self.addr);
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl TransformImpl for ScriptTransform {
     async fn environment(&self) -> TransformResult<Addr> {
@@ -106,9 +716,15 @@ impl TransformImpl for ScriptTransform {
     }
 
     async fn get_unique_id(&self, ctx: &Handle) -> TransformResult<Id> {
+        let id = self.cache_key(ctx).await?.id();
+        trace!(component = "transform", type = "script", "id is calculated to be {id}");
+        Ok(id)
+    }
+
+    async fn cache_key(&self, ctx: &Handle) -> TransformResult<CacheKey> {
         // Digest will be a merkle hash of:
         // all sources digest + script contents
-        let mut hash = blake3::Hasher::new();
+        let mut key = CacheKey::new(self.addr.to_id(), &self.policy);
         let mut depends = self.depends.clone();
         depends.sort();
         for depend in depends.iter() {
@@ -116,28 +732,86 @@ impl TransformImpl for ScriptTransform {
             let t = ctx.get(depend).context(error::NotFoundSnafu {
                 addr: depend.clone(),
             })?;
-            let id = t.get_unique_id(ctx).await?;
-            hash.update(id.digest().as_bytes());
+            let id = ctx.unique_id(depend, &t).await?;
+            key.add_digest(KeyKind::Depend, depend.to_string(), id.digest());
         }
-        for source in self.sources.values() {
+        for (name, source) in self.sources.iter() {
             let source_id = source.get_unique_id().await?;
-            hash.update(source_id.digest().as_bytes());
-        }
-        let script = self.commands.join("\n");
-        hash.update(script.as_bytes());
-        let hash_bytes = hash.finalize();
-        let digest = base16::encode_lower(hash_bytes.as_bytes());
-        let arch = self
-            .arch
-            .as_ref()
-            .map(|arch| ctx.args().get("arch").cloned().unwrap_or(arch.clone()));
-        let id = Id::builder()
-            .name(self.addr.to_id())
-            .digest(digest)
-            .maybe_arch(arch)
-            .build();
-        trace!(component = "transform", type = "script", "id is calculated to be {id}");
-        Ok(id.clone())
+            key.add_digest(KeyKind::Source, name.clone(), source_id.digest());
+        }
+        for (name, value) in self.variant.iter() {
+            key.add_content(KeyKind::Variant, name.clone(), &format!("{name}={value}"));
+        }
+        // Plain lines render as themselves, keeping their ids unchanged
+        let commands = self
+            .commands
+            .iter()
+            .map(|x| x.render(|x| Ok(x.to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+        key.add_commands(&commands);
+        // Hooks only change the id of the transforms that declare them
+        for (name, steps) in [("pre", &self.pre), ("post", &self.post)] {
+            if steps.is_empty() {
+                continue;
+            }
+            let commands = steps
+                .iter()
+                .map(|x| x.render(|x| Ok(x.to_string())))
+                .collect::<Result<Vec<_>, _>>()?;
+            key.add_command_section(name, &commands);
+        }
+        if !self.capture.is_empty() {
+            key.add_content(
+                KeyKind::Command,
+                "capture",
+                &format!("capture={}", self.capture.join(" ")),
+            );
+        }
+        if let Some(outputs) = self.outputs.as_ref() {
+            key.add_content(
+                KeyKind::Command,
+                "outputs",
+                &format!("outputs={}", outputs.display()),
+            );
+        }
+        if !self.host.is_empty() {
+            key.add_content(KeyKind::Host, "host", &self.host.fingerprint());
+        }
+        if self.nested {
+            key.add_content(KeyKind::Command, "nested", "nested=true");
+        }
+        add_contract(&mut key, &self.contract);
+        add_provides(&mut key, &self.provides);
+        // The scheduler may run the transform in another farm that can
+        // execute its architecture, whose toolchain is the one built with
+        let environment =
+            ctx.platform_farm(&self.environment, self.arch.as_deref(), &self.requires)?;
+        if let Some(farm) = ctx.get_farm(&environment)
+            && let Some(identity) = farm.identity().await?
+        {
+            key.add_content(
+                KeyKind::Environment,
+                environment.to_string(),
+                &format!("environment={identity}"),
+            );
+        }
+        key.add_content(
+            KeyKind::Environment,
+            "interpreter",
+            &format!("interpreter={}", self.interpreter),
+        );
+        // Only set epochs are hashed, keeping the ids of other transforms
+        if let Some(epoch) = self.epoch {
+            key.add_content(
+                KeyKind::Environment,
+                SOURCE_DATE_EPOCH_FIELD,
+                &format!("{SOURCE_DATE_EPOCH_FIELD}={epoch}"),
+            );
+        }
+        if let Some(arch) = self.arch.as_ref() {
+            key.add_platform(ctx.args().get("arch").unwrap_or(arch));
+        }
+        Ok(key)
     }
 
     async fn depends(&self) -> TransformResult<Vec<Addr>> {
@@ -163,10 +837,11 @@ impl TransformImpl for ScriptTransform {
             let t = ctx
                 .get(&dep)
                 .context(error::NotFoundSnafu { addr: dep.clone() })?;
-            let id = t.get_unique_id(ctx).await?;
+            let id = ctx.unique_id(&dep, &t).await?;
             trace!(component = "transform", type = "script", "staging dependency {dep} with id {id}");
             let artifact = ctx.storage().safe_open(&id).await?;
-            for layer in artifact.layers() {
+            let arch = self.arch.as_deref().unwrap_or(std::env::consts::ARCH);
+            for layer in select_layers(&artifact, arch)? {
                 let reader = ctx.storage().safe_read(layer).await?;
                 match layer.media_type() {
                     MediaType::Tar(..) => {
@@ -190,77 +865,23 @@ impl TransformImpl for ScriptTransform {
     }
 
     async fn transform(&self, log: &Log, ctx: &Handle, env: &Environment) -> TransformStatus {
-        match async move {
-            // Run the script in our environment
-            let id = self.get_unique_id(ctx).await?;
-            let mut cmd = env.defer_cmd(log, &id);
-            cmd.set_interpreter(self.interpreter.as_str());
-            cmd.create_named_dir("build-root", "build-root").await?;
-            cmd.create_named_dir("install-root", "install-root").await?;
-            if let Some(arch) = self.arch.as_ref() {
-                cmd.set("arch", arch.as_str())?;
-            } else {
-                cmd.set("arch", std::env::consts::ARCH)?;
-            }
-            for (key, value) in ctx.args() {
-                if key == "arch" {
-                    continue;
+        let result = match self.get_unique_id(ctx).await {
+            Ok(id) => {
+                let result = self.build(log, ctx, env, &id).await;
+                // Results are captured whether or not the build succeeded
+                if !self.capture.is_empty()
+                    && let Err(e) = self.capture(log, ctx, env, &id).await
+                {
+                    warn!(component = "transform", type = "script", "failed to capture the results of {}: {e}", self.addr);
                 }
-                cmd.set(key, value)?;
+                result
             }
-
-            for command in self.commands.iter() {
-                cmd.run(command).await?;
-            }
-
-            cmd.send("{{build-root}}").await?;
-
-            // The result of a script transform is everything put in the install-root
-            let mut artifact = Artifact::builder()
-                .config(Config::builder().id(id.clone()).build())
-                .media_type(MediaType::Manifest)
-                .build();
-
-            // Open a layer to store the result in
-            let writer = ctx.storage().safe_start_layer().await?;
-            let mut apath = PathBuf::from("install-root");
-            if let Some(path) = self.artifact.as_ref() {
-                apath = apath.join(path);
-            }
-            env.read(apath.as_path(), writer.clone()).await?;
-            artifact.layers_mut().push(
-                ctx.storage()
-                    .safe_finish_layer(
-                        &MediaType::Tar(Compression::None),
-                        Some(
-                            Platform::builder()
-                                .os(std::env::consts::OS)
-                                .architecture(
-                                    self.arch
-                                        .clone()
-                                        .unwrap_or(std::env::consts::OS.to_string()),
-                                )
-                                .build(),
-                        ),
-                        &writer,
-                    )
-                    .await?,
-            );
-            ctx.storage().safe_save(&artifact).await?;
-            Ok::<Artifact, TransformError>(artifact)
-        }
-        .await
-        .as_ref()
-        {
-            Ok(artifact) => TransformStatus::Success(artifact.clone()),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(artifact) => TransformStatus::Success(artifact),
             // We always assume a script transform is retryable
-            Err(e) => TransformStatus::Retryable(
-                Some(log.path()),
-                error::Error::Failed {
-                    message: e.to_string(),
-                }
-                .into(),
-            ),
+            Err(e) => TransformStatus::Retryable(Some(log.path()), e),
         }
     }
 
@@ -272,13 +893,31 @@ impl TransformImpl for ScriptTransform {
         env.shell(Path::new("build-root"))?;
         Ok(())
     }
+
+    fn host_access(&self) -> HostAccess {
+        self.host.clone()
+    }
+
+    fn nested(&self) -> bool {
+        self.nested
+    }
+
+    fn host_requires(&self) -> HostRequirements {
+        self.requires.clone()
+    }
+
+    fn platform(&self) -> Option<String> {
+        self.arch.clone()
+    }
 }
 
 pub mod error {
     use snafu::Snafu;
+    use std::path::PathBuf;
 
     use edo::{
         context::{Addr, ContextError},
+        environment::EnvironmentError,
         transform::TransformError,
     };
 
@@ -290,14 +929,42 @@ pub mod error {
             #[snafu(source(from(ContextError, Box::new)))]
             source: Box<ContextError>,
         },
+        #[snafu(transparent)]
+        Environment {
+            #[snafu(source(from(EnvironmentError, Box::new)))]
+            source: Box<EnvironmentError>,
+        },
+        #[snafu(display(
+            "script transform fields '{first}' and '{second}' cannot be set together"
+        ))]
+        Conflict { first: String, second: String },
         #[snafu(display("{message}"))]
         Failed { message: String },
         #[snafu(display(
             "script transform definitions require a field '{field}' with type_ '{type_}'"
         ))]
         Field { field: String, type_: String },
+        #[snafu(display("script transform field '{field}' is invalid: {source}"))]
+        Template {
+            field: String,
+            #[snafu(source(from(EnvironmentError, Box::new)))]
+            source: Box<EnvironmentError>,
+        },
         #[snafu(display("could not find dependent transform with address {addr}"))]
         NotFound { addr: Addr },
+        #[snafu(display("the outputs manifest {} lists no outputs", path.display()))]
+        NoOutputs { path: PathBuf },
+        #[snafu(display("line {line} of the outputs manifest {} is invalid: {reason}", path.display()))]
+        Outputs {
+            path: PathBuf,
+            line: usize,
+            reason: String,
+        },
+        #[snafu(display("failed to read the outputs manifest {}: {source}", path.display()))]
+        ReadOutputs {
+            path: PathBuf,
+            source: std::io::Error,
+        },
     }
 
     impl From<Error> for TransformError {
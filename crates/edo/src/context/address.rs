@@ -73,6 +73,11 @@ impl Addr {
         }
     }
 
+    /// Returns `true` if `prefix` is this address or one of its ancestors.
+    pub fn starts_with(&self, prefix: &Addr) -> bool {
+        self.0.starts_with(&prefix.0)
+    }
+
     /// Returns the address segments joined by `/` without the leading `//` prefix.
     pub fn to_id(&self) -> String {
         self.0.join("/")
@@ -161,6 +166,15 @@ mod tests {
         assert_eq!(addr("//only").parent(), None);
     }
 
+    #[test]
+    fn starts_with_matches_ancestors() {
+        let a = addr("//a/b/c");
+        assert!(a.starts_with(&addr("//a/b")));
+        assert!(a.starts_with(&a));
+        assert!(a.starts_with(&Addr::default()));
+        assert!(!a.starts_with(&addr("//a/bc")));
+    }
+
     #[test]
     fn to_id_strips_double_slash_prefix() {
         let a = addr("//seg1/seg2");
@@ -0,0 +1,158 @@
+//! Change-based target selection.
+//!
+//! Maps files changed in the project tree back to the sources that own them
+//! and from there to every transform that would have to be rebuilt: the
+//! transforms and environments using those sources, the transforms built in
+//! an affected environment, and everything that depends on them.
+
+use super::schema::BuildFormat;
+use super::{Addr, Context, ContextResult};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::{Component, Path, PathBuf};
+
+/// Maps paths in the project tree to the sources read from them.
+#[derive(Clone, Debug, Default)]
+pub struct OwnershipIndex {
+    owners: Vec<(PathBuf, Addr)>,
+}
+
+impl OwnershipIndex {
+    /// Indexes the paths owned by every source defined in the project at `ctx`.
+    pub async fn build(ctx: &Context) -> ContextResult<Self> {
+        let mut index = Self::default();
+        for addr in ctx.source_addrs() {
+            let Some(source) = ctx.get_source(&addr).await? else {
+                continue;
+            };
+            for path in source.owned_paths() {
+                index.insert(&ctx.project_dir().join(path), &addr);
+            }
+        }
+        Ok(index)
+    }
+
+    /// Records that `source` reads from `path` and everything below it.
+    pub fn insert(&mut self, path: &Path, source: &Addr) {
+        self.owners.push((normalize(path), source.clone()));
+    }
+
+    /// Returns the sources reading from `file`.
+    pub fn owners(&self, file: &Path) -> BTreeSet<Addr> {
+        let file = normalize(file);
+        self.owners
+            .iter()
+            .filter(|(path, _)| file.starts_with(path))
+            .map(|(_, source)| source.clone())
+            .collect()
+    }
+}
+
+/// Lexically resolves `.` and `..` so paths from git and from source definitions compare equal.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normal.pop();
+            }
+            component => normal.push(component),
+        }
+    }
+    normal
+}
+
+impl Context {
+    /// Returns every transform that has to be rebuilt when the absolute
+    /// paths in `changed` are modified, in address order.
+    ///
+    /// Changing a build file such as `edo.toml` affects every transform it
+    /// declares.
+    pub async fn affected_by(&self, changed: &[PathBuf]) -> ContextResult<BTreeSet<Addr>> {
+        let index = OwnershipIndex::build(self).await?;
+        let root = normalize(self.project_dir());
+        let mut sources = BTreeSet::new();
+        let mut packages = BTreeSet::new();
+        for file in changed {
+            sources.extend(index.owners(file));
+            let file = normalize(file);
+            // A changed build file affects its whole package
+            if BuildFormat::detect(&file).is_some()
+                && let Some(dir) = file.parent().and_then(|x| x.strip_prefix(&root).ok())
+            {
+                packages.insert(dir.components().fold(Addr::default(), |x, y| {
+                    x.join(&y.as_os_str().to_string_lossy())
+                }));
+            }
+        }
+
+        // Elements reading from a changed source, or declared in a changed file
+        let mut affected = BTreeSet::new();
+        for entry in self.element_sources.iter() {
+            if entry.value().iter().any(|x| sources.contains(x)) {
+                affected.insert(entry.key().clone());
+            }
+        }
+        let mut dependents: BTreeMap<Addr, Vec<Addr>> = BTreeMap::new();
+        let transforms: Vec<_> = self
+            .transforms
+            .iter()
+            .map(|x| (x.key().clone(), x.value().clone()))
+            .collect();
+        for (addr, transform) in transforms.iter() {
+            if packages.contains(&addr.parent().unwrap_or_default()) {
+                affected.insert(addr.clone());
+            }
+            for dep in transform.depends().await? {
+                dependents
+                    .entry(self.resolve_alias(&dep))
+                    .or_default()
+                    .push(addr.clone());
+            }
+            let environment = self.resolve_alias(&transform.environment().await?);
+            dependents
+                .entry(environment)
+                .or_default()
+                .push(addr.clone());
+        }
+
+        // Reverse dependency closure
+        let mut queue: VecDeque<Addr> = affected.iter().cloned().collect();
+        while let Some(addr) = queue.pop_front() {
+            for dependent in dependents.get(&addr).into_iter().flatten() {
+                if affected.insert(dependent.clone()) {
+                    queue.push_back(dependent.clone());
+                }
+            }
+        }
+        Ok(affected
+            .into_iter()
+            .filter(|x| self.transforms.contains_key(x))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owners_cover_everything_below_their_path() {
+        let mut index = OwnershipIndex::default();
+        let lib = Addr::parse("//lib/src").unwrap();
+        let docs = Addr::parse("//docs/src").unwrap();
+        index.insert(Path::new("/repo/lib"), &lib);
+        index.insert(Path::new("/repo/docs/./pages"), &docs);
+
+        assert_eq!(
+            index.owners(Path::new("/repo/lib/src/main.rs")),
+            BTreeSet::from([lib.clone()])
+        );
+        assert_eq!(
+            index.owners(Path::new("/repo/docs/extra/../pages/index.md")),
+            BTreeSet::from([docs])
+        );
+        assert!(index.owners(Path::new("/repo/library/main.rs")).is_empty());
+        assert!(index.owners(Path::new("/repo/README.md")).is_empty());
+    }
+}
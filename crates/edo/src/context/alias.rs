@@ -0,0 +1,173 @@
+//! Forwarding addresses for renamed or moved definitions.
+//!
+//! An `[alias.<name>]` table in an `edo.toml` keeps an old address working
+//! after the transform, environment or source behind it has moved. Every
+//! lookup through [`Aliases::resolve`] follows the alias to its target and
+//! logs a deprecation warning the first time each alias is used.
+
+use super::{Addr, ArcMap, ContextResult, error};
+use dashmap::DashSet;
+use snafu::ensure;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// A single forwarding address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Alias {
+    target: Addr,
+    message: Option<String>,
+}
+
+impl Alias {
+    /// Creates an alias forwarding to `target`, with an optional note shown
+    /// alongside the deprecation warning.
+    pub fn new(target: &Addr, message: Option<String>) -> Self {
+        Self {
+            target: target.clone(),
+            message,
+        }
+    }
+
+    /// Returns the address this alias forwards to.
+    pub fn target(&self) -> &Addr {
+        &self.target
+    }
+
+    /// Returns the note shown alongside the deprecation warning, if any.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+/// The aliases declared by a project, shared between a
+/// [`Context`](super::Context) and the [`Handle`](super::Handle)s it creates.
+#[derive(Clone, Default)]
+pub struct Aliases {
+    entries: ArcMap<Addr, Alias>,
+    // Aliases that have already been warned about
+    warned: Arc<DashSet<Addr>>,
+}
+
+impl Aliases {
+    /// Records that `addr` forwards to `alias`.
+    pub fn insert(&self, addr: &Addr, alias: Alias) {
+        self.entries.insert(addr.clone(), alias);
+    }
+
+    /// Returns the alias declared at `addr`, if any.
+    pub fn get(&self, addr: &Addr) -> Option<Alias> {
+        self.entries.get(addr).map(|x| x.value().clone())
+    }
+
+    /// Returns every alias, ordered by address.
+    pub fn list(&self) -> Vec<(Addr, Alias)> {
+        let mut list: Vec<(Addr, Alias)> = self
+            .entries
+            .iter()
+            .map(|x| (x.key().clone(), x.value().clone()))
+            .collect();
+        list.sort_by(|x, y| x.0.cmp(&y.0));
+        list
+    }
+
+    /// Follows `addr` through any chain of aliases to the address it names.
+    ///
+    /// Addresses that are not aliases are returned unchanged.
+    pub fn resolve(&self, addr: &Addr) -> Addr {
+        let mut current = addr.clone();
+        let mut seen = BTreeSet::new();
+        while let Some(alias) = self.get(&current) {
+            if !seen.insert(current.clone()) {
+                // Cycles are rejected by check, stop rather than spin
+                break;
+            }
+            if self.warned.insert(current.clone()) {
+                match alias.message() {
+                    Some(message) => warn!(
+                        target: "context",
+                        "{current} is deprecated, use {} instead: {message}",
+                        alias.target()
+                    ),
+                    None => warn!(
+                        target: "context",
+                        "{current} is deprecated, use {} instead",
+                        alias.target()
+                    ),
+                }
+            }
+            current = alias.target().clone();
+        }
+        current
+    }
+
+    /// Ensures no alias shadows a definition, points nowhere, or forwards back to itself.
+    ///
+    /// `defined` reports whether an address names a real definition.
+    pub fn check(&self, defined: impl Fn(&Addr) -> bool) -> ContextResult<()> {
+        for (addr, _) in self.list() {
+            ensure!(!defined(&addr), error::AliasShadowsSnafu { addr });
+            let mut current = addr.clone();
+            let mut seen = BTreeSet::from([addr.clone()]);
+            while let Some(alias) = self.get(&current) {
+                current = alias.target().clone();
+                ensure!(
+                    seen.insert(current.clone()),
+                    error::AliasCycleSnafu { addr: addr.clone() }
+                );
+            }
+            ensure!(
+                defined(&current),
+                error::AliasTargetSnafu {
+                    addr: addr.clone(),
+                    target: current,
+                }
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ContextError;
+
+    fn addr(value: &str) -> Addr {
+        Addr::parse(value).unwrap()
+    }
+
+    fn aliases(entries: &[(&str, &str)]) -> Aliases {
+        let aliases = Aliases::default();
+        for (from, to) in entries {
+            aliases.insert(&addr(from), Alias::new(&addr(to), None));
+        }
+        aliases
+    }
+
+    #[test]
+    fn chains_are_followed_to_the_definition() {
+        let aliases = aliases(&[("//old/a", "//mid/a"), ("//mid/a", "//new/a")]);
+        assert_eq!(aliases.resolve(&addr("//old/a")), addr("//new/a"));
+        assert_eq!(aliases.resolve(&addr("//new/a")), addr("//new/a"));
+        assert!(aliases.check(|x| *x == addr("//new/a")).is_ok());
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let aliases = aliases(&[("//a", "//b"), ("//b", "//a")]);
+        let err = aliases.check(|_| false).unwrap_err();
+        assert!(matches!(err, ContextError::AliasCycle { .. }), "{err}");
+        // Resolution still terminates
+        aliases.resolve(&addr("//a"));
+    }
+
+    #[test]
+    fn dangling_and_shadowing_aliases_are_rejected() {
+        let aliases = aliases(&[("//old", "//missing")]);
+        let err = aliases.check(|_| false).unwrap_err();
+        assert!(matches!(err, ContextError::AliasTarget { .. }), "{err}");
+
+        let err = aliases.check(|_| true).unwrap_err();
+        assert!(matches!(err, ContextError::AliasShadows { .. }), "{err}");
+    }
+}
@@ -1,22 +1,32 @@
 //! Project loading and build orchestration.
 //!
-//! This module contains [`Project`], which walks a directory tree for `edo.toml`
-//! files, resolves dependencies through vendors, manages the lock file, and
-//! registers plugins, environments, and transforms with the [`super::Context`].
+//! This module contains [`Project`], which walks a directory tree for build
+//! files (`edo.toml`, `*.edo.toml`, `edo.yaml`, `*.edo.yaml`), resolves dependencies through vendors, manages the lock file, and
+//! registers plugins, environments, transforms, and aliases with the
+//! [`super::Context`].
 //! It also re-exports the [`non_configurable!`] and
 //! [`non_configurable_no_context!`] convenience macros.
 
 use super::Context;
 use super::address::Addr;
+use super::alias::{Alias, Aliases};
 use super::lock::Lock;
-use super::{ContextResult as Result, FromNode, Node, error};
-use crate::context::schema::Schema;
+use super::matrix;
+use super::visibility::{VISIBILITY_KEY, Visibility};
+use super::{ContextResult as Result, FromNode, Node, ProjectDefinitions, error};
+use crate::context::schema::{BuildFormat, Schema};
 use crate::source::{Dependency, Resolver};
+use futures::{StreamExt, TryStreamExt, stream};
 use snafu::{OptionExt, ResultExt};
 use std::collections::{BTreeMap, HashMap};
-use std::fs::{File, read, read_dir};
+use std::fs::{read, read_dir};
 use std::path::{Path, PathBuf};
 
+/// How many caches, farms, transforms or vendor resolutions a project load
+/// creates at once. Plugins are shared, so this bounds the load on them and
+/// on the remote caches and registries they reach out to.
+const LOAD_CONCURRENCY: usize = 16;
+
 /// Intermediate representation of a loaded edo project.
 ///
 /// Holds the parsed configuration nodes collected from `edo.toml` files before
@@ -30,7 +40,12 @@ pub struct Project {
     vendors: BTreeMap<Addr, Node>,
     environments: BTreeMap<Addr, Node>,
     transforms: BTreeMap<Addr, Node>,
+    matrices: BTreeMap<Addr, Vec<Addr>>,
     need_resolution: BTreeMap<Addr, Node>,
+    sources: BTreeMap<Addr, Node>,
+    element_sources: BTreeMap<Addr, Vec<Addr>>,
+    aliases: Aliases,
+    visibility: BTreeMap<Addr, Visibility>,
 }
 
 fn handle_sources(namespace: &Addr, node: &Node, _sources: &BTreeMap<Addr, Node>) -> Result<Node> {
@@ -69,6 +84,52 @@ fn handle_sources(namespace: &Addr, node: &Node, _sources: &BTreeMap<Addr, Node>
     Ok(Node::new_definition(&id, &kind, &name, table))
 }
 
+/// Removes the `visibility` key from a transform definition, returning its parsed rules.
+fn take_visibility(namespace: &Addr, node: &Node) -> Result<Option<Visibility>> {
+    let mut table = node.get_table().context(error::NodeSnafu)?;
+    let Some(value) = table.remove(VISIBILITY_KEY) else {
+        return Ok(None);
+    };
+    node.set_table(table);
+    Ok(Some(Visibility::from_node(namespace, &value)?))
+}
+
+/// Paths of the build files of the project at `root`, in the order
+/// [`Project`] loads them.
+pub fn build_file_paths(root: &Path) -> Result<Vec<PathBuf>> {
+    Ok(build_files(&Addr::default(), root)?
+        .into_iter()
+        .map(|x| x.1)
+        .collect())
+}
+
+/// Lists the build files under `directory`, whose package is `namespace`,
+/// with the package each declares into, in the order [`Project`] loads them.
+pub(crate) fn build_files(
+    namespace: &Addr,
+    directory: &Path,
+) -> Result<Vec<(Addr, PathBuf, BuildFormat)>> {
+    let mut entries = read_dir(directory)
+        .context(error::IoSnafu)?
+        .map(|x| x.map(|x| x.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .context(error::IoSnafu)?;
+    // Several build files may share a directory, load them in a stable order
+    entries.sort();
+    let mut files = Vec::new();
+    for path in entries {
+        if path.is_file()
+            && let Some(format) = BuildFormat::detect(&path)
+        {
+            files.push((namespace.clone(), path, format));
+        } else if path.is_dir() {
+            let dir_name = path.file_name().and_then(|x| x.to_str()).unwrap();
+            files.extend(build_files(&namespace.join(dir_name), &path)?);
+        }
+    }
+    Ok(files)
+}
+
 impl Project {
     fn calculate_digest(&self) -> Result<String> {
         let mut hasher = blake3::Hasher::new();
@@ -81,11 +142,26 @@ impl Project {
         Ok(base16::encode_lower(digest.as_bytes()))
     }
 
-    /// Loads all `edo.toml` files under `path`, resolves dependencies, and registers
+    /// Loads all build files under `path`, resolves dependencies, and registers
     /// plugins, environments, and transforms with the given [`Context`].
     pub async fn load<P: AsRef<Path>>(path: P, ctx: &Context, error_on_lock: bool) -> Result<()> {
+        let mut project = Self::scan(path.as_ref())?;
+        project.build(ctx, error_on_lock, false).await?;
+        Ok(())
+    }
+
+    /// Like [`Project::load`] but always re-resolves dependencies, ignoring an
+    /// up to date lock file, so that resolution explanations are recorded on
+    /// the [`Context`].
+    pub async fn refresh<P: AsRef<Path>>(path: P, ctx: &Context) -> Result<()> {
+        let mut project = Self::scan(path.as_ref())?;
+        project.build(ctx, false, true).await?;
+        Ok(())
+    }
+
+    fn scan(path: &Path) -> Result<Self> {
         let mut project = Self {
-            project_path: path.as_ref().to_path_buf(),
+            project_path: path.to_path_buf(),
             config_nodes: BTreeMap::new(),
             source_caches: BTreeMap::new(),
             build_cache: None,
@@ -93,13 +169,24 @@ impl Project {
             vendors: BTreeMap::new(),
             environments: BTreeMap::new(),
             transforms: BTreeMap::new(),
+            matrices: BTreeMap::new(),
             need_resolution: BTreeMap::new(),
+            sources: BTreeMap::new(),
+            element_sources: BTreeMap::new(),
+            aliases: Aliases::default(),
+            visibility: BTreeMap::new(),
         };
         let mut sources = BTreeMap::new();
-        project.walk(&Addr::default(), path.as_ref(), &mut sources)?;
+        project.walk(&Addr::default(), path, &mut sources)?;
+        project.aliases.check(|addr| {
+            project.transforms.contains_key(addr)
+                || project.matrices.contains_key(addr)
+                || project.environments.contains_key(addr)
+                || sources.contains_key(addr)
+        })?;
         project.resolve_sources(&sources)?;
-        project.build(ctx, error_on_lock).await?;
-        Ok(())
+        project.sources = sources;
+        Ok(project)
     }
 
     fn walk(
@@ -108,18 +195,8 @@ impl Project {
         directory: &Path,
         sources: &mut BTreeMap<Addr, Node>,
     ) -> Result<()> {
-        let read = read_dir(directory).context(error::IoSnafu)?;
-        for entry in read {
-            let entry = entry.context(error::IoSnafu)?;
-            let path = entry.path();
-            if path.is_file() && path.file_name().and_then(|x| x.to_str()).unwrap() == "edo.toml" {
-                // This is a barkml defined build file
-                sources.extend(self.load_toml(namespace, &path)?);
-            } else if path.is_dir() {
-                let dir_name = path.file_name().and_then(|x| x.to_str()).unwrap();
-                let addr = namespace.join(dir_name);
-                self.walk(&addr, &path, sources)?;
-            }
+        for (namespace, path, format) in build_files(namespace, directory)? {
+            sources.extend(self.load_file(&namespace, &path, format)?);
         }
         Ok(())
     }
@@ -138,11 +215,17 @@ impl Project {
             if let Some(source) = table.get("source") {
                 if let Some(list) = source.as_list() {
                     let mut items = Vec::new();
+                    let mut addrs = Vec::new();
                     for entry in list.iter() {
-                        let addr = Addr::parse(&entry.as_string().context(error::FieldSnafu {
-                            field: "source",
-                            type_: "string",
-                        })?)?;
+                        let addr =
+                            self.aliases
+                                .resolve(&Addr::parse(&entry.as_string().context(
+                                    error::FieldSnafu {
+                                        field: "source",
+                                        type_: "string",
+                                    },
+                                )?)?);
+                        addrs.push(addr.clone());
                         items.push(
                             sources
                                 .get(&addr)
@@ -153,11 +236,16 @@ impl Project {
                         );
                     }
                     table.insert("source".to_string(), Node::new_list(items));
+                    self.element_sources.insert(name.clone(), addrs);
                 } else {
-                    let addr = Addr::parse(&source.as_string().context(error::FieldSnafu {
-                        field: "source",
-                        type_: "string",
-                    })?)?;
+                    let addr = self
+                        .aliases
+                        .resolve(&Addr::parse(&source.as_string().context(
+                            error::FieldSnafu {
+                                field: "source",
+                                type_: "string",
+                            },
+                        )?)?);
                     table.insert(
                         "source".to_string(),
                         sources
@@ -167,6 +255,7 @@ impl Project {
                             })?
                             .clone(),
                     );
+                    self.element_sources.insert(name.clone(), vec![addr]);
                 }
             }
             node.set_table(table);
@@ -174,10 +263,15 @@ impl Project {
         Ok(())
     }
 
-    fn load_toml(&mut self, namespace: &Addr, file: &Path) -> Result<BTreeMap<Addr, Node>> {
+    fn load_file(
+        &mut self,
+        namespace: &Addr,
+        file: &Path,
+        format: BuildFormat,
+    ) -> Result<BTreeMap<Addr, Node>> {
         debug!(component = "project", "loading transforms from {file:?}");
         let config_bytes = read(file).context(error::IoSnafu)?;
-        let config: Schema = toml::from_slice(&config_bytes).context(error::DeserializeSnafu)?;
+        let config = format.parse(&config_bytes)?;
         match config {
             Schema::V1(config) => {
                 for (name, node) in config.get_config()? {
@@ -197,41 +291,138 @@ impl Project {
                     let addr = namespace.join(&name);
                     self.source_caches.insert(addr, node.clone());
                 }
-                self.build_cache = config.get_build_cache()?;
-                self.output_cache = config.get_output_cache()?;
+                // Files that declare no cache keep the one declared elsewhere
+                if let Some(node) = config.get_build_cache()? {
+                    self.build_cache = Some(node);
+                }
+                if let Some(node) = config.get_output_cache()? {
+                    self.output_cache = Some(node);
+                }
                 for (name, node) in config.get_environments()? {
                     let addr = namespace.join(&name);
                     let cnode = handle_sources(namespace, &node, &sources)?;
                     self.environments.insert(addr, cnode);
                 }
+                let package = config.get_package()?;
+                let default_visibility = package
+                    .get(VISIBILITY_KEY)
+                    .map(|node| Visibility::from_node(namespace, node))
+                    .transpose()?;
                 for (name, node) in config.get_transforms()? {
                     let addr = namespace.join(&name);
                     let cnode = handle_sources(namespace, &node, &sources)?;
-                    self.transforms.insert(addr, cnode);
+                    let visibility =
+                        take_visibility(namespace, &cnode)?.or(default_visibility.clone());
+                    if let Some(visibility) = visibility.as_ref() {
+                        self.visibility.insert(addr.clone(), visibility.clone());
+                    }
+                    if let Some(variants) = matrix::expand(&addr, &cnode)? {
+                        // Each matrix combination is registered as its own transform,
+                        // the base address only names the group.
+                        let mut members = Vec::new();
+                        for (vaddr, vnode) in variants {
+                            members.push(vaddr.clone());
+                            if let Some(visibility) = visibility.as_ref() {
+                                self.visibility.insert(vaddr.clone(), visibility.clone());
+                            }
+                            self.transforms.insert(vaddr, vnode);
+                        }
+                        self.matrices.insert(addr, members);
+                    } else {
+                        self.transforms.insert(addr, cnode);
+                    }
                 }
                 for (name, node) in config.get_vendors()? {
                     let addr = namespace.join(&name);
                     self.vendors.insert(addr, node);
                 }
+                for (name, (target, message)) in config.get_aliases()? {
+                    let target = if target.starts_with("//") {
+                        Addr::parse(&target)?
+                    } else {
+                        namespace.join(&target)
+                    };
+                    self.aliases
+                        .insert(&namespace.join(&name), Alias::new(&target, message));
+                }
                 Ok(sources)
             }
         }
     }
 
+    /// Hands the parsed definitions to every registered
+    /// [`ProjectHook`](super::ProjectHook) so they can be rewritten before
+    /// resolution.
+    async fn before_resolve(&mut self, ctx: &Context) -> Result<()> {
+        let hooks = ctx.registry().hooks();
+        if hooks.is_empty() {
+            return Ok(());
+        }
+        let mut definitions = ProjectDefinitions {
+            sources: std::mem::take(&mut self.sources),
+            environments: std::mem::take(&mut self.environments),
+            transforms: std::mem::take(&mut self.transforms),
+            vendors: std::mem::take(&mut self.vendors),
+        };
+        let outcome = async {
+            for hook in hooks {
+                hook.before_resolve(ctx, &mut definitions).await?;
+            }
+            Ok(())
+        }
+        .await;
+        self.sources = definitions.sources;
+        self.environments = definitions.environments;
+        self.transforms = definitions.transforms;
+        self.vendors = definitions.vendors;
+        outcome
+    }
+
     /// Resolves dependencies, registers plugins/environments/transforms, and
-    /// writes the lock file.
-    pub async fn build(&mut self, ctx: &Context, error_on_lock: bool) -> Result<()> {
+    /// writes the lock file. When `refresh` is set an up to date lock file is
+    /// ignored and dependencies are resolved again.
+    pub async fn build(&mut self, ctx: &Context, error_on_lock: bool, refresh: bool) -> Result<()> {
+        self.before_resolve(ctx).await?;
         // Calculate the digest of the project configuration
         let digest = self.calculate_digest()?;
         ctx.add_config(&self.config_nodes);
+        for (addr, alias) in self.aliases.list() {
+            ctx.add_alias(&addr, alias);
+        }
+        for (addr, visibility) in self.visibility.iter() {
+            ctx.add_visibility(addr, visibility);
+        }
+        // Sources are shared nodes, so definitions recorded here still see
+        // the data assigned by dependency resolution below
+        for (addr, node) in self.sources.iter() {
+            ctx.add_source_definition(addr, node);
+        }
+        for (addr, sources) in self.element_sources.iter() {
+            ctx.add_element_sources(addr, sources);
+        }
+        for (addr, node) in self.vendors.iter() {
+            ctx.add_vendor_definition(addr, node);
+        }
+        // Resolve all storage backends, an up to date lock file uses them too
+        self.add_caches(ctx).await?;
+        // A source bundle in the repository comes before every source cache
+        ctx.use_bundle(&self.project_path).await?;
         // Check for an existing lockfile
         let lock_file = self.project_path.join("edo.lock.json");
-        if lock_file.exists() {
-            let mut file = File::open(&lock_file).context(error::IoSnafu)?;
-            let lock: Lock = serde_json::from_reader(&mut file).context(error::SerializeSnafu)?;
+        // Source pins outlive dependency changes, only a refresh drops them
+        let mut pins = BTreeMap::new();
+        if lock_file.exists() && !refresh {
+            let lock = Lock::from_slice(&read(&lock_file).context(error::IoSnafu)?)?;
+            pins = lock.sources().clone();
+            pins.retain(|addr, _| {
+                self.sources.contains_key(addr)
+                    || self.transforms.contains_key(addr)
+                    || self.environments.contains_key(addr)
+            });
             // Now check if the digests match, if so then we should use the lockfile to resolve our unresolved nodes
             if lock.digest() == digest {
                 info!(target: "project", "no changes detected in project, reusing lock resolution file");
+                ctx.source_lock().load(&lock_file, &pins);
                 for (addr, node) in self.need_resolution.iter() {
                     let resolved = lock
                         .content()
@@ -239,32 +430,15 @@ impl Project {
                         .context(error::MalformedLockSnafu { addr: addr.clone() })?;
                     node.set_data(&resolved.data());
                 }
-                for (addr, node) in self.environments.iter() {
-                    ctx.add_farm(addr, node).await?;
+                for hook in ctx.registry().hooks() {
+                    hook.after_lock(ctx, &lock).await?;
                 }
-
-                for (addr, node) in self.transforms.iter() {
-                    ctx.add_transform(addr, node).await?;
-                }
-                return Ok(());
+                return self.add_elements(ctx).await;
             } else if lock.digest() != digest && error_on_lock {
                 return error::DependencyChangeSnafu {}.fail();
             }
         }
 
-        // Resolve all storage backends
-        for (addr, node) in self.source_caches.iter() {
-            ctx.add_cache(addr, node).await?;
-        }
-        if let Some(node) = self.build_cache.as_ref() {
-            ctx.add_cache(&Addr::parse("//edo-build-cache")?, node)
-                .await?;
-        }
-        if let Some(node) = self.output_cache.as_ref() {
-            ctx.add_cache(&Addr::parse("//edo-output-cache")?, node)
-                .await?;
-        }
-
         // Vendor's are only used during project resolution
         // Now we should create a resolver
         let mut resolver = Resolver::default();
@@ -279,6 +453,15 @@ impl Project {
                 "register vendor {addr}"
             );
             resolver.add_vendor(&addr.to_string(), vendor.clone());
+            if let Some(priority) = node.get("priority") {
+                resolver.set_vendor_priority(
+                    &addr.to_string(),
+                    priority.as_int().context(error::FieldSnafu {
+                        field: "priority",
+                        type_: "integer",
+                    })?,
+                );
+            }
         }
 
         // Now for every node needing resolution we need to get the vendor field to resolve
@@ -300,43 +483,52 @@ impl Project {
         // Now that we have built the databases we want to run the resolution
         // unfortunately due to resolvo using its own async through rayno hidden behind only
         // synchronous calls we have to use spawn_blocking here
+        let explainer = resolver.clone();
+        let requires = need_resolution.clone();
         let resolved = tokio::task::spawn_blocking(move || resolver.resolve(need_resolution))
             .await
             .unwrap()?;
-
-        // Create the new lock
-        let mut lock = Lock::new(digest);
-
-        for (addr, (vendor_name, name, version)) in resolved.iter() {
+        for dep in requires.iter() {
+            let selected = resolved
+                .get(&dep.addr)
+                .map(|(vendor, _, version)| (vendor.clone(), version.clone()));
+            let explanation = explainer.explain(dep, selected);
             debug!(
                 section = "context",
                 component = "project",
-                "resolved {addr} to {name}@{version} from vendor {vendor_name}"
+                "resolution of {}:\n{explanation}",
+                dep.addr
             );
-            let vendor = vendors.get(vendor_name).unwrap();
+            ctx.add_explanation(explanation);
+        }
+
+        // Create the new lock
+        let mut lock = Lock::new(digest);
+        *lock.sources_mut() = pins;
+
+        // Vendors fetch what they resolved to independently of each other
+        let pending: Vec<_> = resolved
+            .iter()
+            .map(|(addr, (vendor_name, name, version))| {
+                debug!(
+                    section = "context",
+                    component = "project",
+                    "resolved {addr} to {name}@{version} from vendor {vendor_name}"
+                );
+                let vendor = vendors.get(vendor_name).unwrap();
+                async move { vendor.resolve(name, version).await.map(|x| (addr, x)) }
+            })
+            .collect();
+        let mut resolutions = stream::iter(pending).buffer_unordered(LOAD_CONCURRENCY);
+        while let Some((addr, resolved)) = resolutions.try_next().await? {
             let target = assigners.get(addr).unwrap();
-            let resolved = vendor.resolve(name, version).await?;
             lock.content_mut().insert(addr.clone(), resolved.clone());
             target.set_data(&resolved.data());
         }
-
-        for (addr, node) in self.environments.iter() {
-            debug!(
-                section = "context",
-                component = "project",
-                "adding environment farm {addr}"
-            );
-            ctx.add_farm(addr, node).await?;
-        }
-
-        for (addr, node) in self.transforms.iter() {
-            debug!(
-                section = "context",
-                component = "project",
-                "adding transform {addr}"
-            );
-            ctx.add_transform(addr, node).await?;
+        for hook in ctx.registry().hooks() {
+            hook.after_lock(ctx, &lock).await?;
         }
+        self.add_elements(ctx).await?;
 
         // Write out the lock file
         let mut file = std::fs::OpenOptions::new()
@@ -347,6 +539,85 @@ impl Project {
             .context(error::IoSnafu)?;
 
         serde_json::to_writer_pretty(&mut file, &lock).context(error::SerializeSnafu)?;
+        ctx.source_lock().load(&lock_file, lock.sources());
+        Ok(())
+    }
+}
+
+impl Project {
+    /// Creates the backends of every cache at once, then registers them in
+    /// address order, which is the priority of the source caches.
+    async fn add_caches(&self, ctx: &Context) -> Result<()> {
+        let mut caches: Vec<(Addr, &Node)> = self
+            .source_caches
+            .iter()
+            .map(|(addr, node)| (addr.clone(), node))
+            .collect();
+        if let Some(node) = self.build_cache.as_ref() {
+            caches.push((Addr::parse("//edo-build-cache")?, node));
+        }
+        if let Some(node) = self.output_cache.as_ref() {
+            caches.push((Addr::parse("//edo-output-cache")?, node));
+        }
+        let pending: Vec<_> = caches
+            .iter()
+            .map(|(addr, node)| {
+                debug!(
+                    section = "context",
+                    component = "project",
+                    "adding a storage backend {addr}"
+                );
+                ctx.create_cache(addr, node)
+            })
+            .collect();
+        let backends: Vec<_> = stream::iter(pending)
+            .buffered(LOAD_CONCURRENCY)
+            .try_collect()
+            .await?;
+        for ((addr, node), backend) in caches.iter().zip(backends) {
+            ctx.register_cache(addr, node, backend).await?;
+        }
+        Ok(())
+    }
+
+    /// Registers the environment farms, then the transforms and matrices,
+    /// creating up to [`LOAD_CONCURRENCY`] of each at once.
+    async fn add_elements(&self, ctx: &Context) -> Result<()> {
+        let farms: Vec<_> = self
+            .environments
+            .iter()
+            .map(|(addr, node)| {
+                debug!(
+                    section = "context",
+                    component = "project",
+                    "adding environment farm {addr}"
+                );
+                ctx.add_farm(addr, node)
+            })
+            .collect();
+        stream::iter(farms)
+            .buffer_unordered(LOAD_CONCURRENCY)
+            .try_collect::<()>()
+            .await?;
+        let transforms: Vec<_> = self
+            .transforms
+            .iter()
+            .map(|(addr, node)| {
+                debug!(
+                    section = "context",
+                    component = "project",
+                    "adding transform {addr}"
+                );
+                ctx.add_transform(addr, node)
+            })
+            .collect();
+        stream::iter(transforms)
+            .buffer_unordered(LOAD_CONCURRENCY)
+            .try_collect::<()>()
+            .await?;
+        for (addr, members) in self.matrices.iter() {
+            ctx.add_matrix(addr, members);
+        }
         Ok(())
     }
 }
@@ -422,7 +693,12 @@ mod tests {
             vendors: BTreeMap::new(),
             environments: BTreeMap::new(),
             transforms: BTreeMap::new(),
+            matrices: BTreeMap::new(),
             need_resolution: BTreeMap::new(),
+            sources: BTreeMap::new(),
+            element_sources: BTreeMap::new(),
+            aliases: Aliases::default(),
+            visibility: BTreeMap::new(),
         }
     }
 
@@ -579,9 +855,9 @@ mod tests {
         );
     }
 
-    // ── Project::load_toml tests ──────────────────────────────────────────────
+    // ── Project::load_file tests ──────────────────────────────────────────────
 
-    /// load_toml populates all major sections from a complete edo.toml.
+    /// load_file populates all major sections from a complete edo.toml.
     #[test]
     fn load_toml_populates_all_sections() {
         let dir = TempDir::new().unwrap();
@@ -619,7 +895,7 @@ at = "=1.0.0"
         let ns = Addr::default();
         let mut project = empty_project(dir.path());
         let sources = project
-            .load_toml(&ns, &dir.path().join("edo.toml"))
+            .load_file(&ns, &dir.path().join("edo.toml"), BuildFormat::Toml)
             .expect("load_toml ok");
 
         // sources map must contain the regular source "foo" and "bar" (requires)
@@ -668,13 +944,47 @@ at = "=1.0.0"
         write_edo_toml(dir.path(), "this is = not = valid");
         let ns = Addr::default();
         let mut project = empty_project(dir.path());
-        let result = project.load_toml(&ns, &dir.path().join("edo.toml"));
+        let result = project.load_file(&ns, &dir.path().join("edo.toml"), BuildFormat::Toml);
         assert!(
             matches!(result, Err(error::ContextError::Deserialize { .. })),
             "expected Deserialize error, got: {result:?}",
         );
     }
 
+    /// Transform visibility is stripped from the definition and falls back to the package default.
+    #[test]
+    fn load_toml_records_visibility() {
+        let dir = TempDir::new().unwrap();
+        write_edo_toml(
+            dir.path(),
+            r#"schema-version = "1"
+[package]
+visibility = "private"
+
+[transform.inner]
+kind = "script"
+
+[transform.shared]
+kind       = "script"
+visibility = "public"
+"#,
+        );
+        let ns = addr("//lib");
+        let mut project = empty_project(dir.path());
+        project
+            .load_file(&ns, &dir.path().join("edo.toml"), BuildFormat::Toml)
+            .expect("load ok");
+
+        let inner = &project.visibility[&addr("//lib/inner")];
+        assert!(!inner.allows(&addr("//lib/inner"), &addr("//app/main")));
+        let shared = &project.visibility[&addr("//lib/shared")];
+        assert!(shared.allows(&addr("//lib/shared"), &addr("//app/main")));
+        let table = project.transforms[&addr("//lib/shared")]
+            .get_table()
+            .unwrap();
+        assert!(!table.contains_key(VISIBILITY_KEY));
+    }
+
     /// A source block without a `kind` field returns a Field error.
     #[test]
     fn load_toml_missing_kind_errors() {
@@ -684,7 +994,7 @@ at = "=1.0.0"
         write_edo_toml(dir.path(), content);
         let ns = Addr::default();
         let mut project = empty_project(dir.path());
-        let result = project.load_toml(&ns, &dir.path().join("edo.toml"));
+        let result = project.load_file(&ns, &dir.path().join("edo.toml"), BuildFormat::Toml);
         match result {
             Err(error::ContextError::Field { ref field, .. }) => {
                 assert_eq!(field, "kind");
@@ -724,6 +1034,47 @@ at = "=1.0.0"
         );
     }
 
+    /// walk loads every TOML and YAML build file of a directory into its
+    /// package, and a file without caches keeps the ones declared elsewhere.
+    #[test]
+    fn walk_loads_every_build_file_format() {
+        let dir = TempDir::new().unwrap();
+        write_edo_toml(
+            dir.path(),
+            "schema-version = \"1\"\n[cache.build]\nkind = \"local\"\npath = \"/tmp/b\"\n",
+        );
+        let sub = dir.path().join("sub");
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(
+            sub.join("edo.yaml"),
+            "schema-version: \"1\"\nsource:\n  a:\n    kind: local\n    path: x\n",
+        )
+        .unwrap();
+        std::fs::write(
+            sub.join("tools.edo.yml"),
+            "schema-version: \"1\"\ntransform:\n  t:\n    kind: script\n    source: a\n",
+        )
+        .unwrap();
+        std::fs::write(
+            sub.join("extra.edo.toml"),
+            "schema-version = \"1\"\n[source.b]\nkind = \"local\"\npath = \"y\"\n",
+        )
+        .unwrap();
+
+        let ns = Addr::default();
+        let mut project = empty_project(dir.path());
+        let mut sources = BTreeMap::new();
+        project
+            .walk(&ns, dir.path(), &mut sources)
+            .expect("walk ok");
+
+        let sub_ns = ns.join("sub");
+        assert!(sources.contains_key(&sub_ns.join("a")));
+        assert!(sources.contains_key(&sub_ns.join("b")));
+        assert!(project.transforms.contains_key(&sub_ns.join("t")));
+        assert!(project.build_cache.is_some());
+    }
+
     /// walk on an empty directory succeeds and adds nothing to sources.
     #[test]
     fn walk_empty_directory_is_ok() {
@@ -778,6 +1129,43 @@ at = "=1.0.0"
             tbl.get("source").unwrap().as_string().as_deref(),
             Some("real source"),
         );
+        assert_eq!(project.element_sources[&addr("//t")], vec![src_addr]);
+    }
+
+    /// resolve_sources follows an alias to the source it forwards to.
+    #[test]
+    fn resolve_sources_follows_aliases() {
+        let dir = TempDir::new().unwrap();
+        let src_addr = addr("//ns/s");
+        let mut sources = BTreeMap::new();
+        sources.insert(
+            src_addr.clone(),
+            Node::new_string("real source".to_string()),
+        );
+
+        let mut table = BTreeMap::new();
+        table.insert(
+            "source".to_string(),
+            Node::new_string("//ns/old".to_string()),
+        );
+        let transform_node = def_node("transform", "script", "t", table);
+
+        let mut project = empty_project(dir.path());
+        project
+            .aliases
+            .insert(&addr("//ns/old"), Alias::new(&src_addr, None));
+        project.transforms.insert(addr("//t"), transform_node);
+        project
+            .resolve_sources(&sources)
+            .expect("resolve_sources ok");
+
+        let t = project.transforms.get(&addr("//t")).unwrap();
+        let tbl = t.get_table().unwrap();
+        assert_eq!(
+            tbl.get("source").unwrap().as_string().as_deref(),
+            Some("real source"),
+        );
+        assert_eq!(project.element_sources[&addr("//t")], vec![src_addr]);
     }
 
     /// resolve_sources rewrites a list of source addresses to actual nodes.
@@ -812,6 +1200,7 @@ at = "=1.0.0"
         assert_eq!(items.len(), 2);
         assert_eq!(items[0].as_string().as_deref(), Some("node-a"));
         assert_eq!(items[1].as_string().as_deref(), Some("node-b"));
+        assert_eq!(project.element_sources[&addr("//t")], vec![sa, sb]);
     }
 
     /// resolve_sources returns NotValidSource when the referenced addr is absent.
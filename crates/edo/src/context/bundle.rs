@@ -0,0 +1,105 @@
+//! In-repository source bundles, written by `edo vendor`.
+//!
+//! A bundle is a directory of the project, `vendor/` unless `[bundle] path`
+//! names another one, holding every source fetched from elsewhere as a local
+//! cache next to an `index.json` listing them. A project with a bundle
+//! consults it before any other source cache, offline too, so a checkout of
+//! the repository builds without reaching out to the network.
+
+use super::{Addr, Config, ContextResult, error};
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the index file of a bundle.
+pub const BUNDLE_INDEX: &str = "index.json";
+/// Directory of the bundle, relative to the project root, unless configured.
+pub const DEFAULT_BUNDLE_PATH: &str = "vendor";
+
+/// The index of a source bundle.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceBundle {
+    /// Every bundled source by address.
+    pub sources: BTreeMap<Addr, BundledSource>,
+}
+
+/// A source stored in a bundle.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundledSource {
+    /// The unique id the source is stored under.
+    pub id: String,
+    /// The source kind, such as `git`.
+    pub kind: String,
+    /// Where the source was fetched from, if it has a url.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl SourceBundle {
+    /// The bundle directory of the project at `project`, from the
+    /// `[bundle] path` setting.
+    pub fn path(config: &Config, project: &Path) -> PathBuf {
+        let path = config
+            .get("bundle")
+            .and_then(|x| x.get("path"))
+            .and_then(|x| x.as_string())
+            .unwrap_or_else(|| DEFAULT_BUNDLE_PATH.to_string());
+        project.join(path)
+    }
+
+    /// Reads the index of the bundle in `dir`, `None` when there is none.
+    pub async fn load(dir: &Path) -> ContextResult<Option<Self>> {
+        let index = dir.join(BUNDLE_INDEX);
+        if !index.exists() {
+            return Ok(None);
+        }
+        let bytes = tokio::fs::read(&index).await.context(error::IoSnafu)?;
+        Ok(Some(
+            serde_json::from_slice(&bytes).context(error::SerializeSnafu)?,
+        ))
+    }
+
+    /// Writes the index of the bundle in `dir`.
+    pub async fn save(&self, dir: &Path) -> ContextResult<()> {
+        let bytes = serde_json::to_vec_pretty(self).context(error::SerializeSnafu)?;
+        tokio::fs::write(dir.join(BUNDLE_INDEX), bytes)
+            .await
+            .context(error::IoSnafu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn index_round_trips_and_is_optional() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(SourceBundle::load(dir.path()).await.unwrap(), None);
+        let mut bundle = SourceBundle::default();
+        bundle.sources.insert(
+            Addr::parse("//p/upstream").unwrap(),
+            BundledSource {
+                id: "repo@main-6d61696e".into(),
+                kind: "git".into(),
+                url: Some("https://example.com/repo.git".into()),
+            },
+        );
+        bundle.save(dir.path()).await.unwrap();
+        assert_eq!(SourceBundle::load(dir.path()).await.unwrap(), Some(bundle));
+    }
+
+    #[tokio::test]
+    async fn path_defaults_to_vendor() {
+        let dir = TempDir::new().unwrap();
+        let config = Config::load(Some(&dir.path().join("missing.toml")))
+            .await
+            .unwrap();
+        assert_eq!(
+            SourceBundle::path(&config, Path::new("/project")),
+            PathBuf::from("/project/vendor")
+        );
+    }
+}
@@ -0,0 +1,399 @@
+//! Credentials for the hosts sources, vendors and caches reach.
+//!
+//! Secrets never appear in build files. Instead the `[credentials]` table of
+//! the user config (the file passed with `--config`, or `~/.config/edo.toml`)
+//! names a helper that is asked for them, per host:
+//!
+//! ```toml
+//! [credentials]
+//! helper = "docker-credential-pass"                      # hosts without a helper of their own
+//! hosts  = { "ghcr.io" = "gh-credential", "*.corp" = "corp-credential --realm build" }
+//! ```
+//!
+//! Helpers speak the docker credential helper protocol, so existing ones
+//! work unchanged: edo runs `<helper> get`, writes the server (a url, a git
+//! remote or `s3://<bucket>`) to its stdin and reads
+//! `{"ServerURL": "..", "Username": "..", "Secret": ".."}` from its stdout. An
+//! optional `SessionToken` carries temporary AWS credentials. A helper that
+//! prints nothing, or fails with `credentials not found`, has none for that
+//! server. Components can register helpers of their own with
+//! [`Credentials::register_helper`], which are used when a helper name matches.
+//!
+//! Keys of `hosts` are [`glob_match`] patterns. An entry naming the host
+//! exactly wins over patterns, and any entry wins over `helper`. Like the [`Policy`](super::Policy), the table is
+//! read before any project config is merged.
+
+use super::{Config, ContextResult as Result, Node, error};
+use crate::util::glob_match;
+use async_trait::async_trait;
+use base64::Engine;
+use dashmap::DashMap;
+use serde::Deserialize;
+use snafu::{OptionExt, ResultExt, ensure};
+use std::fmt;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+/// Key holding the credential helpers in the user config.
+pub const CREDENTIALS_KEY: &str = "credentials";
+
+/// What a docker credential helper prints when it has nothing for a server.
+const NOT_FOUND: &str = "credentials not found";
+
+/// A username and secret returned by a credential helper.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+pub struct Credential {
+    /// The user to authenticate as, `<token>` when `secret` is a bearer token.
+    #[serde(rename = "Username", default)]
+    pub username: String,
+    /// The password or token.
+    #[serde(rename = "Secret")]
+    pub secret: String,
+    /// A session token accompanying temporary credentials, used by S3.
+    #[serde(rename = "SessionToken", default)]
+    pub session_token: Option<String>,
+}
+
+impl Credential {
+    /// The value of an HTTP `Authorization` header carrying the credential.
+    pub fn authorization(&self) -> String {
+        if self.username.is_empty() || self.username == "<token>" {
+            format!("Bearer {}", self.secret)
+        } else {
+            let pair = format!("{}:{}", self.username, self.secret);
+            format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(pair)
+            )
+        }
+    }
+}
+
+// Secrets must never end up in logs
+impl fmt::Debug for Credential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credential")
+            .field("username", &self.username)
+            .field("secret", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Something that can be asked for the credentials of a server.
+#[async_trait]
+pub trait CredentialHelper: Send + Sync {
+    /// Returns the credentials for `server`, or `None` when there are none.
+    async fn get(&self, server: &str) -> Result<Option<Credential>>;
+}
+
+/// A credential helper run as an executable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternalHelper {
+    /// The command line the helper is run with, before `get`.
+    command: Vec<String>,
+}
+
+impl ExternalHelper {
+    /// A helper run as `command get`, where `command` is split on whitespace.
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.split_whitespace().map(str::to_string).collect(),
+        }
+    }
+}
+
+impl fmt::Display for ExternalHelper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.command.join(" "))
+    }
+}
+
+#[async_trait]
+impl CredentialHelper for ExternalHelper {
+    async fn get(&self, server: &str) -> Result<Option<Credential>> {
+        let helper = self.to_string();
+        let (program, args) = self
+            .command
+            .split_first()
+            .context(error::CredentialHelperSnafu {
+                helper: helper.clone(),
+                message: "the helper command is empty",
+            })?;
+        let mut child = tokio::process::Command::new(program)
+            .args(args)
+            .arg("get")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context(error::CredentialHelperIoSnafu {
+                helper: helper.clone(),
+            })?;
+        // A helper may exit without reading the server, its exit status says
+        // what happened then
+        if let Some(mut stdin) = child.stdin.take()
+            && let Err(e) = stdin.write_all(server.as_bytes()).await
+            && e.kind() != std::io::ErrorKind::BrokenPipe
+        {
+            return Err(e).context(error::CredentialHelperIoSnafu {
+                helper: helper.clone(),
+            });
+        }
+        let output = child
+            .wait_with_output()
+            .await
+            .context(error::CredentialHelperIoSnafu {
+                helper: helper.clone(),
+            })?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stdout.contains(NOT_FOUND) || stderr.contains(NOT_FOUND) {
+                return Ok(None);
+            }
+            return error::CredentialHelperSnafu {
+                helper,
+                message: format!("{} for {server}: {}", output.status, stderr.trim()),
+            }
+            .fail();
+        }
+        if stdout.trim().is_empty() {
+            return Ok(None);
+        }
+        let credential: Credential = serde_json::from_str(&stdout).map_err(|e| {
+            error::CredentialHelperSnafu {
+                helper: helper.clone(),
+                message: format!("unreadable answer for {server}: {e}"),
+            }
+            .build()
+        })?;
+        Ok(Some(credential))
+    }
+}
+
+/// The credential helpers configured for each host, with the answers they
+/// gave so far.
+#[derive(Clone, Default)]
+pub struct Credentials {
+    /// The helper of hosts without one of their own.
+    helper: Option<String>,
+    /// Host patterns and their helpers.
+    hosts: Vec<(String, String)>,
+    /// Helpers registered by components, by name.
+    registered: Arc<DashMap<String, Arc<dyn CredentialHelper>>>,
+    /// Answers by server, so each helper is asked once per server.
+    answers: Arc<DashMap<String, Option<Credential>>>,
+}
+
+impl Credentials {
+    /// Reads the `[credentials]` table of the user config.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let Some(node) = config.get(CREDENTIALS_KEY) else {
+            return Ok(Self::default());
+        };
+        let table = node.as_table().context(error::FieldSnafu {
+            field: CREDENTIALS_KEY,
+            type_: "table",
+        })?;
+        let command = |key: String, node: &Node| {
+            node.as_string().context(error::FieldSnafu {
+                field: format!("{CREDENTIALS_KEY}.{key}"),
+                type_: "string",
+            })
+        };
+        let helper = table
+            .get("helper")
+            .map(|x| command("helper".into(), x))
+            .transpose()?;
+        let mut hosts = Vec::new();
+        if let Some(node) = table.get("hosts") {
+            let entries = node.as_table().context(error::FieldSnafu {
+                field: format!("{CREDENTIALS_KEY}.hosts"),
+                type_: "table of host patterns to helpers",
+            })?;
+            for (pattern, node) in entries.iter() {
+                hosts.push((pattern.clone(), command(format!("hosts.{pattern}"), node)?));
+            }
+        }
+        Ok(Self {
+            helper,
+            hosts,
+            ..Default::default()
+        })
+    }
+
+    /// Registers a helper components provide, used in place of an executable
+    /// when the config names it.
+    pub fn register_helper(&self, name: &str, helper: Arc<dyn CredentialHelper>) {
+        self.registered.insert(name.to_string(), helper);
+    }
+
+    /// Returns whether any helper is configured.
+    pub fn is_configured(&self) -> bool {
+        self.helper.is_some() || !self.hosts.is_empty()
+    }
+
+    /// The helper configured for the host of `server`.
+    fn helper_for(&self, server: &str) -> Option<&str> {
+        let host = super::policy::host(server);
+        host.as_ref()
+            .and_then(|host| {
+                self.hosts
+                    .iter()
+                    .find(|(pattern, _)| pattern == host)
+                    .or_else(|| {
+                        self.hosts
+                            .iter()
+                            .find(|(pattern, _)| glob_match(pattern, host))
+                    })
+            })
+            .map(|(_, helper)| helper.as_str())
+            .or(self.helper.as_deref())
+    }
+
+    /// Asks the helper configured for the host of `server` for its
+    /// credentials, returning `None` when no helper is configured or the
+    /// helper has none.
+    pub async fn get(&self, server: &str) -> Result<Option<Credential>> {
+        let Some(helper) = self.helper_for(server) else {
+            return Ok(None);
+        };
+        if let Some(answer) = self.answers.get(server) {
+            return Ok(answer.value().clone());
+        }
+        let registered = self.registered.get(helper).map(|x| x.value().clone());
+        let answer = match registered {
+            Some(registered) => registered.get(server).await?,
+            None => ExternalHelper::new(helper).get(server).await?,
+        };
+        trace!(
+            component = "credentials",
+            "helper {helper} {} credentials for {server}",
+            if answer.is_some() {
+                "returned"
+            } else {
+                "has no"
+            }
+        );
+        ensure!(
+            answer.as_ref().is_none_or(|x| !x.secret.is_empty()),
+            error::CredentialHelperSnafu {
+                helper,
+                message: format!("empty secret for {server}"),
+            }
+        );
+        self.answers.insert(server.to_string(), answer.clone());
+        Ok(answer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn config(toml: &str) -> Config {
+        let nodes: BTreeMap<String, Node> = toml::from_str(toml).unwrap();
+        let config = Config::default();
+        config.merge(&nodes);
+        config
+    }
+
+    /// Writes an executable helper answering `answer` and logging each
+    /// server it is asked about to `asked`.
+    fn helper(dir: &std::path::Path, name: &str, answer: &str) -> String {
+        let path = dir.join(name);
+        std::fs::write(
+            &path,
+            format!(
+                "#!/bin/sh\ntest \"$1\" = get || exit 2\ncat >> {}/asked\necho >> {}/asked\n{answer}\n",
+                dir.display(),
+                dir.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.display().to_string()
+    }
+
+    #[tokio::test]
+    async fn hosts_use_their_helper_once_per_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let corp = helper(
+            dir.path(),
+            "corp",
+            r#"echo '{"ServerURL":"x","Username":"me","Secret":"s3cret"}'"#,
+        );
+        let other = helper(
+            dir.path(),
+            "other",
+            "echo credentials not found in native keychain; exit 1",
+        );
+        let credentials = Credentials::from_config(&config(&format!(
+            "[credentials]\nhelper = \"{other}\"\nhosts = {{ \"*.corp\" = \"{corp}\" }}\n"
+        )))
+        .unwrap();
+        assert!(credentials.is_configured());
+
+        let url = "https://files.corp/a.tar.gz";
+        let credential = credentials.get(url).await.unwrap().unwrap();
+        assert_eq!(credential.username, "me");
+        assert_eq!(credential.authorization(), "Basic bWU6czNjcmV0");
+        assert!(!format!("{credential:?}").contains("s3cret"));
+        credentials.get(url).await.unwrap();
+        assert!(
+            credentials
+                .get("https://example.com/x")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let asked = std::fs::read_to_string(dir.path().join("asked")).unwrap();
+        assert_eq!(asked, format!("{url}\nhttps://example.com/x\n"));
+    }
+
+    #[tokio::test]
+    async fn failing_helpers_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let broken = helper(dir.path(), "broken", "echo locked >&2; exit 1");
+        let credentials =
+            Credentials::from_config(&config(&format!("[credentials]\nhelper = \"{broken}\"")))
+                .unwrap();
+        let err = credentials.get("s3://bucket").await.unwrap_err();
+        assert!(err.to_string().contains("locked"), "{err}");
+
+        assert!(!Credentials::default().is_configured());
+        assert!(
+            Credentials::default()
+                .get("s3://bucket")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(Credentials::from_config(&config("credentials = 1")).is_err());
+    }
+
+    #[tokio::test]
+    async fn registered_helpers_are_used_by_name() {
+        struct Fixed;
+
+        #[async_trait]
+        impl CredentialHelper for Fixed {
+            async fn get(&self, _server: &str) -> Result<Option<Credential>> {
+                Ok(Some(Credential {
+                    username: "<token>".into(),
+                    secret: "abc".into(),
+                    session_token: None,
+                }))
+            }
+        }
+
+        let credentials =
+            Credentials::from_config(&config("[credentials]\nhelper = \"fixed\"")).unwrap();
+        credentials.register_helper("fixed", Arc::new(Fixed));
+        let credential = credentials.get("git@host:repo.git").await.unwrap().unwrap();
+        assert_eq!(credential.authorization(), "Bearer abc");
+    }
+}
@@ -0,0 +1,245 @@
+//! Descriptions of the fields a component kind reads from its definition.
+//!
+//! Components implement [`Describe`] and describe every kind they register with
+//! [`Registry::describe`](super::Registry::describe), next to its handler.
+//! [`Registry::json_schema`](super::Registry::json_schema) turns the
+//! descriptions into a JSON Schema of the whole build file, which editors use
+//! to validate and complete `edo.toml` and `edo.yaml` files.
+
+use super::Component;
+use super::visibility::VISIBILITY_KEY;
+use serde_json::{Map, Value, json};
+
+/// JSON Schema dialect of the generated schemas.
+pub const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// The value a definition field holds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldType {
+    /// Any value.
+    Any,
+    /// A string.
+    String,
+    /// An integer.
+    Integer,
+    /// `true` or `false`.
+    Boolean,
+    /// One of the listed strings.
+    Choice(Vec<String>),
+    /// A list of values of the given type.
+    List(Box<FieldType>),
+    /// A table whose values have the given type.
+    Table(Box<FieldType>),
+    /// A value of any of the given types.
+    OneOf(Vec<FieldType>),
+}
+
+impl FieldType {
+    /// One of `choices`.
+    pub fn choice<'a>(choices: impl IntoIterator<Item = &'a str>) -> Self {
+        Self::Choice(choices.into_iter().map(str::to_string).collect())
+    }
+
+    /// A list of `item`.
+    pub fn list(item: FieldType) -> Self {
+        Self::List(Box::new(item))
+    }
+
+    /// A table of `value`.
+    pub fn table(value: FieldType) -> Self {
+        Self::Table(Box::new(value))
+    }
+
+    /// Any of `types`.
+    pub fn one_of(types: impl IntoIterator<Item = FieldType>) -> Self {
+        Self::OneOf(types.into_iter().collect())
+    }
+
+    /// The JSON Schema of the value.
+    pub fn to_json(&self) -> Value {
+        match self {
+            Self::Any => json!({}),
+            Self::String => json!({ "type": "string" }),
+            Self::Integer => json!({ "type": "integer" }),
+            Self::Boolean => json!({ "type": "boolean" }),
+            Self::Choice(choices) => json!({ "type": "string", "enum": choices }),
+            Self::List(item) => json!({ "type": "array", "items": item.to_json() }),
+            Self::Table(value) => {
+                json!({ "type": "object", "additionalProperties": value.to_json() })
+            }
+            Self::OneOf(types) => {
+                json!({ "anyOf": types.iter().map(|x| x.to_json()).collect::<Vec<_>>() })
+            }
+        }
+    }
+}
+
+/// A field of a definition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldSchema {
+    /// Key of the field.
+    pub name: String,
+    /// What the field holds.
+    pub type_: FieldType,
+    /// Whether definitions must set the field.
+    pub required: bool,
+    /// One line describing the field.
+    pub description: String,
+}
+
+impl FieldSchema {
+    /// An optional field.
+    pub fn new(name: &str, type_: FieldType, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            type_,
+            required: false,
+            description: description.to_string(),
+        }
+    }
+
+    /// Makes the field required.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+}
+
+/// Describes the definitions of a component kind.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KindSchema {
+    /// One line describing the kind.
+    pub description: String,
+    /// The fields the kind reads, besides `kind`.
+    pub fields: Vec<FieldSchema>,
+}
+
+impl KindSchema {
+    /// A kind without fields.
+    pub fn new(description: &str) -> Self {
+        Self {
+            description: description.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Adds an optional field.
+    pub fn field(mut self, name: &str, type_: FieldType, description: &str) -> Self {
+        self.fields.push(FieldSchema::new(name, type_, description));
+        self
+    }
+
+    /// Adds a required field.
+    pub fn required(mut self, name: &str, type_: FieldType, description: &str) -> Self {
+        self.fields
+            .push(FieldSchema::new(name, type_, description).required());
+        self
+    }
+
+    /// Adds every field of `fields`.
+    pub fn fields(mut self, fields: impl IntoIterator<Item = FieldSchema>) -> Self {
+        self.fields.extend(fields);
+        self
+    }
+
+    /// The JSON Schema of a definition of `kind`. Keys the schema does not
+    /// describe are allowed, as components may read more than they describe.
+    pub fn to_json(&self, kind: &str) -> Value {
+        let mut properties = Map::new();
+        properties.insert("kind".to_string(), json!({ "const": kind }));
+        let mut required = vec![Value::from("kind")];
+        for field in self.fields.iter() {
+            let mut schema = field.type_.to_json();
+            if !field.description.is_empty()
+                && let Some(object) = schema.as_object_mut()
+            {
+                object.insert("description".to_string(), field.description.clone().into());
+            }
+            properties.insert(field.name.clone(), schema);
+            if field.required {
+                required.push(field.name.clone().into());
+            }
+        }
+        let mut schema = json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        });
+        if !self.description.is_empty() {
+            schema["description"] = self.description.clone().into();
+        }
+        schema
+    }
+}
+
+/// Implemented by components that describe the definitions they are
+/// created from.
+pub trait Describe {
+    /// Describes the definitions of the component's kind.
+    fn describe() -> KindSchema;
+}
+
+/// Fields the project loader reads from every definition of `component`,
+/// whatever its kind.
+pub fn common_fields(component: &Component) -> Vec<FieldSchema> {
+    match component {
+        Component::Transform => vec![
+            FieldSchema::new(
+                VISIBILITY_KEY,
+                FieldType::one_of([FieldType::String, FieldType::list(FieldType::String)]),
+                "Packages allowed to depend on the transform",
+            ),
+            FieldSchema::new(
+                "matrix",
+                FieldType::table(FieldType::list(FieldType::String)),
+                "Axes to build a variant of the transform for each combination of",
+            ),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_schemas_require_their_kind_and_required_fields() {
+        let schema = KindSchema::new("Fetches a file")
+            .required("url", FieldType::String, "Where to fetch from")
+            .field(
+                "mode",
+                FieldType::choice(["fast", "safe"]),
+                "How to fetch it",
+            )
+            .field(
+                "tags",
+                FieldType::table(FieldType::list(FieldType::Integer)),
+                "",
+            );
+        assert_eq!(
+            schema.to_json("remote"),
+            json!({
+                "type": "object",
+                "description": "Fetches a file",
+                "properties": {
+                    "kind": { "const": "remote" },
+                    "url": { "type": "string", "description": "Where to fetch from" },
+                    "mode": {
+                        "type": "string",
+                        "enum": ["fast", "safe"],
+                        "description": "How to fetch it"
+                    },
+                    "tags": {
+                        "type": "object",
+                        "additionalProperties": {
+                            "type": "array",
+                            "items": { "type": "integer" }
+                        }
+                    }
+                },
+                "required": ["kind", "url"]
+            })
+        );
+    }
+}
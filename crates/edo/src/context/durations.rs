@@ -0,0 +1,222 @@
+//! Execution times of earlier runs, used to estimate how long the next one
+//! takes.
+//!
+//! Every transform a run executes records its duration under the prefix of
+//! its artifact id (see [`Id::prefix`]), which stays the same when its
+//! inputs change, so a rebuild after an edit is estimated from the builds
+//! before it. An estimate is the median of the last few durations, which a
+//! single slow or interrupted build does not move. Estimates only come from
+//! recorded durations, never from the rate a build progresses at, so they
+//! stay put while a transform runs for hours without printing anything.
+
+use super::{NodeOutcome, RunSummary, error};
+use crate::storage::Id;
+use crate::util::{SchemaExtra, schema_version};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use snafu::{ResultExt, ensure};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+use tokio::fs::{create_dir_all, read, write};
+
+use super::ContextResult;
+
+/// Version of the duration history format this edo writes.
+pub const DURATIONS_VERSION: u64 = 1;
+
+/// Durations kept per transform.
+const KEPT: usize = 5;
+
+/// Recent execution times of transforms, keyed by artifact id prefix.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Durations {
+    #[serde(default)]
+    version: u64,
+    /// Milliseconds each of the last runs took, oldest first.
+    #[serde(default)]
+    transforms: BTreeMap<String, Vec<u64>>,
+    // Fields a newer edo added without bumping the version, kept as they are
+    #[serde(flatten)]
+    extra: SchemaExtra,
+}
+
+impl Default for Durations {
+    fn default() -> Self {
+        Self {
+            version: DURATIONS_VERSION,
+            transforms: BTreeMap::new(),
+            extra: SchemaExtra::new(),
+        }
+    }
+}
+
+impl Durations {
+    /// Parses a duration history, refusing one a newer edo wrote in a format
+    /// this one cannot read.
+    pub fn from_slice(bytes: &[u8]) -> ContextResult<Self> {
+        let document: Value = serde_json::from_slice(bytes).context(error::SerializeSnafu)?;
+        let version = schema_version(&document);
+        ensure!(
+            version <= DURATIONS_VERSION,
+            error::DurationsVersionSnafu {
+                version,
+                supported: DURATIONS_VERSION,
+            }
+        );
+        let mut durations: Self =
+            serde_json::from_value(document).context(error::SerializeSnafu)?;
+        durations.version = DURATIONS_VERSION;
+        Ok(durations)
+    }
+
+    /// Loads the history at `path`, empty if none was recorded yet.
+    ///
+    /// Estimates are only ever advisory, so a history that cannot be read is
+    /// replaced with an empty one after a warning.
+    pub async fn load(path: &Path) -> Self {
+        let bytes = match read(path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                warn!(
+                    component = "context",
+                    "ignoring unreadable duration history: {e}"
+                );
+                return Self::default();
+            }
+        };
+        Self::from_slice(&bytes).unwrap_or_else(|e| {
+            warn!(
+                component = "context",
+                "ignoring unreadable duration history {}: {e}",
+                path.display()
+            );
+            Self::default()
+        })
+    }
+
+    /// Writes the history to `path`.
+    pub async fn save(&self, path: &Path) -> ContextResult<()> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent).await.context(error::IoSnafu)?;
+        }
+        let content = serde_json::to_string_pretty(self).context(error::SerializeSnafu)?;
+        write(path, content).await.context(error::IoSnafu)?;
+        Ok(())
+    }
+
+    /// Records that the transform producing artifacts prefixed `prefix` took
+    /// `duration`, forgetting the oldest duration beyond the last few.
+    pub fn record(&mut self, prefix: &str, duration: Duration) {
+        let durations = self.transforms.entry(prefix.to_string()).or_default();
+        durations.push(duration.as_millis() as u64);
+        if durations.len() > KEPT {
+            durations.drain(..durations.len() - KEPT);
+        }
+    }
+
+    /// Records the duration of every transform `summary` built. Failed and
+    /// cancelled transforms stopped early, so they are left out.
+    pub fn record_run(&mut self, summary: &RunSummary) {
+        for node in summary.nodes.iter() {
+            if node.outcome != NodeOutcome::Built {
+                continue;
+            }
+            let Some(id) = node.id.as_ref().and_then(|x| x.parse::<Id>().ok()) else {
+                continue;
+            };
+            self.record(&id.prefix(), Duration::from_millis(node.duration_ms));
+        }
+    }
+
+    /// How long the transform producing artifacts prefixed `prefix` is
+    /// expected to take, if it ran before.
+    pub fn estimate(&self, prefix: &str) -> Option<Duration> {
+        let mut durations = self.transforms.get(prefix)?.clone();
+        if durations.is_empty() {
+            return None;
+        }
+        durations.sort_unstable();
+        let middle = durations.len() / 2;
+        let median = if durations.len() % 2 == 0 {
+            (durations[middle - 1] + durations[middle]) / 2
+        } else {
+            durations[middle]
+        };
+        Some(Duration::from_millis(median))
+    }
+}
+
+/// Renders `duration` for a progress line, such as `1h02m` or `4m05s`.
+pub fn format_eta(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m{s:02}s"),
+        (h, m, _) => format!("{h}h{m:02}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{Addr, NodeSummary};
+    use tempfile::TempDir;
+
+    #[test]
+    fn estimates_are_the_median_of_recent_durations() {
+        let mut durations = Durations::default();
+        assert_eq!(durations.estimate("app"), None);
+        for ms in [100_000, 10, 400, 300, 200, 250] {
+            durations.record("app", Duration::from_millis(ms));
+        }
+        // The oldest, much slower run was forgotten and the fast outlier
+        // does not pull the estimate down
+        assert_eq!(durations.estimate("app"), Some(Duration::from_millis(250)));
+        durations.record("app", Duration::from_millis(500));
+        assert_eq!(durations.estimate("app"), Some(Duration::from_millis(300)));
+        assert_eq!(format_eta(Duration::from_secs(42)), "42s");
+        assert_eq!(format_eta(Duration::from_secs(245)), "4m05s");
+        assert_eq!(format_eta(Duration::from_secs(3725)), "1h02m");
+    }
+
+    #[tokio::test]
+    async fn built_transforms_are_recorded_by_id_prefix() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("durations.json");
+        let mut summary = RunSummary::start(&[Addr::parse("//a").unwrap()]);
+        for (outcome, digest, ms) in [
+            (NodeOutcome::Built, "aaa", 1200),
+            (NodeOutcome::Failed, "bbb", 5),
+        ] {
+            summary.nodes.push(NodeSummary {
+                addr: Addr::parse("//a").unwrap(),
+                id: Some(format!("a-blake3:{digest}")),
+                outcome,
+                duration_ms: ms,
+                uploaded: false,
+                error: None,
+                log: None,
+                tail: Vec::new(),
+                failure: None,
+                workspace: None,
+                key: Vec::new(),
+                estimate_ms: None,
+            });
+        }
+        let mut durations = Durations::load(&path).await;
+        durations.record_run(&summary);
+        durations.save(&path).await.unwrap();
+
+        let loaded = Durations::load(&path).await;
+        assert_eq!(loaded, durations);
+        assert_eq!(loaded.estimate("a"), Some(Duration::from_millis(1200)));
+
+        tokio::fs::write(&path, r#"{"version": 9, "transforms": {}}"#)
+            .await
+            .unwrap();
+        assert!(Durations::from_slice(&std::fs::read(&path).unwrap()).is_err());
+        assert_eq!(Durations::load(&path).await, Durations::default());
+    }
+}
@@ -13,6 +13,26 @@ use super::Addr;
 #[derive(Snafu, Debug)]
 #[snafu(visibility(pub))]
 pub enum ContextError {
+    /// An alias forwards back to itself.
+    #[snafu(display("alias '{addr}' forms a cycle"))]
+    AliasCycle {
+        /// The alias the cycle was found from.
+        addr: Addr,
+    },
+    /// An alias is declared at an address that already has a definition.
+    #[snafu(display("alias '{addr}' shadows an existing definition"))]
+    AliasShadows {
+        /// The address declared twice.
+        addr: Addr,
+    },
+    /// An alias forwards to an address with no definition.
+    #[snafu(display("alias '{addr}' points at '{target}', which is not defined"))]
+    AliasTarget {
+        /// The dangling alias.
+        addr: Addr,
+        /// The undefined address it ends at.
+        target: Addr,
+    },
     /// A required field was missing or had the wrong type.
     #[snafu(display("expected a field named '{field}' with a type of {type_}"))]
     Field {
@@ -21,6 +41,22 @@ pub enum ContextError {
         /// Expected type description.
         type_: String,
     },
+    /// A credential helper failed or gave an unreadable answer.
+    #[snafu(display("credential helper '{helper}' failed: {message}"))]
+    CredentialHelper {
+        /// The helper's command line.
+        helper: String,
+        /// What went wrong.
+        message: String,
+    },
+    /// A credential helper could not be run.
+    #[snafu(display("failed to run credential helper '{helper}': {source}"))]
+    CredentialHelperIo {
+        /// The helper's command line.
+        helper: String,
+        /// The underlying I/O error.
+        source: std::io::Error,
+    },
     /// The user's home directory could not be determined.
     #[snafu(display("failed to find home directory"))]
     Home,
@@ -30,6 +66,15 @@ pub enum ContextError {
         /// The underlying I/O error.
         source: std::io::Error,
     },
+    /// edo runs inside a transform and was asked to use the cache of the
+    /// edo process running that transform as its own.
+    #[snafu(display(
+        "edo runs inside a transform of the edo process using {path}, pass another --storage"
+    ))]
+    NestedStorage {
+        /// The parent's local cache.
+        path: String,
+    },
     /// Dependencies changed since the lockfile was generated.
     #[snafu(display("dependencies have changed, run edo update to update the lockfile"))]
     DependencyChange,
@@ -39,12 +84,45 @@ pub enum ContextError {
         /// The underlying TOML deserialization error.
         source: toml::de::Error,
     },
+    /// YAML deserialization failed.
+    #[snafu(display("failed to deserialize yaml: {source}"))]
+    DeserializeYaml {
+        /// The underlying YAML deserialization error.
+        source: serde_yaml::Error,
+    },
     /// Logging subsystem initialization failed.
     #[snafu(display("failed to initialize logging: {source}"))]
     Log {
         /// The underlying tracing initialization error.
         source: TryInitError,
     },
+    /// The lockfile is not valid JSON, or not a lockfile.
+    #[snafu(display("invalid edo.lock.json: {source}"))]
+    LockFormat {
+        /// The underlying JSON error.
+        source: serde_json::Error,
+    },
+    /// The lockfile was written by a newer edo in a format this one cannot read.
+    #[snafu(display(
+        "edo.lock.json has schema version {version}, but this edo only reads up to version {supported}; it was written by a newer edo, upgrade edo to use it"
+    ))]
+    LockVersion {
+        /// The version the lockfile records.
+        version: u64,
+        /// The newest version this edo reads.
+        supported: u64,
+    },
+    /// The duration history was written by a newer edo in a format this one
+    /// cannot read.
+    #[snafu(display(
+        "duration history has schema version {version}, but this edo only reads up to version {supported}"
+    ))]
+    DurationsVersion {
+        /// The version the history records.
+        version: u64,
+        /// The newest version this edo reads.
+        supported: u64,
+    },
     /// The lockfile is missing resolution data for an address.
     #[snafu(display("lockfile is missing resolution data for: {addr}"))]
     MalformedLock {
@@ -81,6 +159,88 @@ pub enum ContextError {
         /// The address that was looked up.
         addr: Addr,
     },
+    /// No transform was found for the given address.
+    #[snafu(display("no transform found with addr '{addr}'"))]
+    NoTransformFound {
+        /// The address that was looked up.
+        addr: Addr,
+    },
+    /// A transform's artifact does not declare the metadata a dependent reads.
+    #[snafu(display("{addr} does not declare the metadata '{key}' in its `metadata` table"))]
+    MissingMetadata {
+        /// The transform that was expected to declare it.
+        addr: Addr,
+        /// The metadata entry that was read.
+        key: String,
+    },
+    /// Neither the requested farm nor any other can execute an architecture.
+    #[snafu(display(
+        "{farm} cannot execute {arch} (it runs {platforms}) and no other environment farm can"
+    ))]
+    UnsupportedPlatform {
+        /// The farm the transform asked for.
+        farm: Addr,
+        /// The architecture the transform builds for.
+        arch: String,
+        /// The architectures the farm executes, comma separated.
+        platforms: String,
+    },
+    /// Neither the requested farm nor any other meets a transform's host requirements.
+    #[snafu(display(
+        "{farm} does not meet host_requires: {unmet}{}",
+        if *routed { ", and no other environment farm does" } else { "" }
+    ))]
+    UnmetHostRequirements {
+        /// The farm the transform asked for.
+        farm: Addr,
+        /// What the farm's host lacks, semicolon separated.
+        unmet: String,
+        /// Whether other farms were considered.
+        routed: bool,
+    },
+    /// A registered project hook rejected the project.
+    #[snafu(display("project rejected by hook '{hook}': {reason}"))]
+    Hook {
+        /// Name the hook was registered under.
+        hook: String,
+        /// Why the project was rejected.
+        reason: String,
+    },
+    /// A definition uses something the [`Policy`](super::Policy) does not allow.
+    #[snafu(display("'{addr}' violates the policy: {subject} '{value}' is {reason}"))]
+    Policy {
+        /// Address of the offending definition.
+        addr: Addr,
+        /// What was checked, e.g. `source kind` or `host`.
+        subject: String,
+        /// The rejected value.
+        value: String,
+        /// Which rule rejected it.
+        reason: String,
+    },
+    /// An artifact is built from sources under licenses the
+    /// [`Policy`](super::Policy) does not allow.
+    #[snafu(display("'{addr}' violates the license policy: {violations}"))]
+    LicensePolicy {
+        /// Address of the transform building the artifact.
+        addr: Addr,
+        /// The rejected licenses and the sources under them.
+        violations: String,
+    },
+    /// A project template is unknown or rendered an invalid file.
+    #[snafu(display("template '{template}' is invalid: {reason}"))]
+    Template {
+        /// The template, or the file it rendered.
+        template: String,
+        /// Why it was rejected.
+        reason: String,
+    },
+    /// Formatting a build file would have changed what it declares.
+    #[snafu(display("cannot format the build file: {reason}"))]
+    Format {
+        /// Why the formatted file was rejected.
+        reason: String,
+    },
     /// No plugin is loaded for the given address.
     #[snafu(display("no plugin loaded with addr '{addr}'"))]
     NoPlugin {
@@ -88,12 +248,36 @@ pub enum ContextError {
         addr: Addr,
     },
     /// No loaded plugin supports the requested component kind.
-    #[snafu(display("no implementation is loaded that supports a {component} of kind {kind}"))]
+    #[snafu(display(
+        "no implementation is loaded that supports a {component} of kind {kind}{hint}"
+    ))]
     NoProvider {
         /// The component type being requested.
         component: String,
         /// The kind discriminator that no plugin supports.
         kind: String,
+        /// Close matches and the supported kinds, empty when unknown.
+        hint: String,
+    },
+    /// No recorded run matches the requested id.
+    #[snafu(display("no run found matching '{id}'"))]
+    NoRun {
+        /// The run id or prefix that was looked up.
+        id: String,
+    },
+    /// More than one recorded run matches the requested id prefix.
+    #[snafu(display("'{id}' matches more than one run, use a longer prefix"))]
+    AmbiguousRun {
+        /// The ambiguous run id prefix.
+        id: String,
+    },
+    /// A run summary file could not be parsed.
+    #[snafu(display("failed to read run summary {}: {source}", path.display()))]
+    RunSummary {
+        /// Path to the summary file.
+        path: std::path::PathBuf,
+        /// The underlying JSON error.
+        source: serde_json::Error,
     },
     /// The block is not a transform definition.
     #[snafu(display("block is not a transform definition"))]
@@ -104,6 +288,12 @@ pub enum ContextError {
         /// The invalid source id.
         id: String,
     },
+    /// A visibility rule could not be parsed.
+    #[snafu(display("invalid visibility rule '{value}'"))]
+    Visibility {
+        /// The rule as written.
+        value: String,
+    },
     /// The block is not a vendor definition.
     #[snafu(display("block is not a vendor definition"))]
     NotVendor,
@@ -157,6 +347,18 @@ pub enum ContextError {
 mod tests {
     use super::*;
 
+    #[test]
+    fn display_alias_target() {
+        let e = ContextError::AliasTarget {
+            addr: Addr::parse("//old/emit").unwrap(),
+            target: Addr::parse("//new/emit").unwrap(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "alias '//old/emit' points at '//new/emit', which is not defined"
+        );
+    }
+
     #[test]
     fn display_field() {
         let e = ContextError::Field {
@@ -184,6 +386,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn display_lock_version() {
+        let e = ContextError::LockVersion {
+            version: 2,
+            supported: 1,
+        };
+        assert_eq!(
+            e.to_string(),
+            "edo.lock.json has schema version 2, but this edo only reads up to version 1; it was written by a newer edo, upgrade edo to use it"
+        );
+    }
+
     #[test]
     fn display_malformed_lock() {
         let addr = Addr::parse("//x/y").unwrap();
@@ -245,6 +459,19 @@ mod tests {
         assert_eq!(e.to_string(), "no environment found with addr '//x/y'");
     }
 
+    #[test]
+    fn display_unsupported_platform() {
+        let e = ContextError::UnsupportedPlatform {
+            farm: Addr::parse("//default").unwrap(),
+            arch: "riscv64".to_string(),
+            platforms: "x86_64".to_string(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "//default cannot execute riscv64 (it runs x86_64) and no other environment farm can"
+        );
+    }
+
     #[test]
     fn display_no_plugin() {
         let addr = Addr::parse("//x/y").unwrap();
@@ -252,11 +479,38 @@ mod tests {
         assert_eq!(e.to_string(), "no plugin loaded with addr '//x/y'");
     }
 
+    #[test]
+    fn display_hook() {
+        let e = ContextError::Hook {
+            hook: "mirrors".into(),
+            reason: "//p/src does not use an approved mirror".into(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "project rejected by hook 'mirrors': //p/src does not use an approved mirror"
+        );
+    }
+
+    #[test]
+    fn display_policy() {
+        let e = ContextError::Policy {
+            addr: Addr::parse("//p/src").unwrap(),
+            subject: "host".into(),
+            value: "example.com".into(),
+            reason: "not in policy.hosts.allow".into(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "'//p/src' violates the policy: host 'example.com' is not in policy.hosts.allow"
+        );
+    }
+
     #[test]
     fn display_no_provider() {
         let e = ContextError::NoProvider {
             component: "storage".into(),
             kind: "s3".into(),
+            hint: String::new(),
         };
         assert_eq!(
             e.to_string(),
@@ -0,0 +1,404 @@
+//! Build events published while a run progresses.
+//!
+//! The [`EventBus`] lives on the [`LogManager`](super::LogManager), so
+//! everything holding a log handle can publish to it: the scheduler reports
+//! the build graph and each transform's progress, and every [`Log`](super::Log)
+//! forwards the command output it records. Frontends such as the dashboard
+//! subscribe to follow a run without parsing console output.
+//!
+//! Publishing is a no-op while nobody is subscribed. A [`RunState`] folds the
+//! events of one run back into the state of each transform.
+
+use super::{Addr, NodeOutcome, RunStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use tokio::sync::broadcast;
+
+/// Number of events a slow subscriber may fall behind before it misses some.
+const CAPACITY: usize = 4096;
+
+/// What happened.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventKind {
+    /// A run of `targets` started.
+    RunStarted {
+        /// The requested targets, after matrix expansion.
+        targets: Vec<Addr>,
+    },
+    /// A transform is part of the build, after the transforms it depends on.
+    Planned {
+        /// The transform.
+        addr: Addr,
+        /// Transforms in the same build that must finish first.
+        depends: Vec<Addr>,
+    },
+    /// A transform's artifact was found in the build cache.
+    Cached {
+        /// The transform.
+        addr: Addr,
+    },
+    /// A transform started executing.
+    Started {
+        /// The transform.
+        addr: Addr,
+        /// Name of the log file its output is written to.
+        log: String,
+        /// How long earlier runs took to execute it, in milliseconds.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        estimate_ms: Option<u64>,
+    },
+    /// A line of command output was recorded.
+    Output {
+        /// Name of the log file the line was written to.
+        log: String,
+        /// The line, without escape sequences.
+        line: String,
+    },
+    /// A transform finished executing.
+    Finished {
+        /// The transform.
+        addr: Addr,
+        /// Whether it was built or failed.
+        outcome: NodeOutcome,
+        /// Wall time spent, in milliseconds.
+        duration_ms: u64,
+    },
+    /// The run finished.
+    RunFinished {
+        /// Overall result of the run.
+        status: RunStatus,
+    },
+}
+
+/// A timestamped [`EventKind`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    /// When the event was published.
+    pub time: DateTime<Utc>,
+    /// What happened.
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// Broadcasts [`Event`]s to every subscriber. Clones share the same bus.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CAPACITY).0,
+        }
+    }
+}
+
+impl EventBus {
+    /// Publishes `kind` to every current subscriber.
+    pub fn publish(&self, kind: EventKind) {
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+        let _ = self.sender.send(Event {
+            time: Utc::now(),
+            kind,
+        });
+    }
+
+    /// Returns `true` while anybody is subscribed.
+    pub fn is_observed(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    /// Receives every event published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+/// Where a transform is in the run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeState {
+    /// Waiting for its dependencies or a worker.
+    Pending,
+    /// Served from the build cache.
+    Cached,
+    /// Executing.
+    Running,
+    /// Executed and produced an artifact.
+    Built,
+    /// Executed and failed.
+    Failed,
+}
+
+impl NodeState {
+    /// Returns `true` once the transform will not change any more.
+    pub fn is_done(&self) -> bool {
+        matches!(self, Self::Cached | Self::Built | Self::Failed)
+    }
+}
+
+/// A transform as seen through the events of a run.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeProgress {
+    /// Transforms that must finish first.
+    pub depends: Vec<Addr>,
+    /// Length of the longest dependency chain below the transform.
+    pub level: usize,
+    /// Where the transform is in the run.
+    pub state: NodeState,
+    /// When it started executing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started: Option<DateTime<Utc>>,
+    /// How long earlier runs took to execute it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate_ms: Option<u64>,
+    /// Wall time spent executing, once finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Name of the log file its output is written to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log: Option<String>,
+    /// The last lines of output while running.
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
+    pub tail: VecDeque<String>,
+}
+
+/// The state of a run, rebuilt by [`applying`](RunState::apply) its events
+/// in order.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RunState {
+    /// The requested targets.
+    pub targets: Vec<Addr>,
+    /// When the run started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started: Option<DateTime<Utc>>,
+    /// When the run finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished: Option<DateTime<Utc>>,
+    /// Overall result, once finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<RunStatus>,
+    /// Every planned transform.
+    pub nodes: BTreeMap<Addr, NodeProgress>,
+    /// Transforms in the order they finished.
+    pub finished_order: Vec<Addr>,
+    /// Output lines kept per running transform.
+    #[serde(skip)]
+    tail: usize,
+    #[serde(skip)]
+    logs: HashMap<String, Addr>,
+}
+
+impl RunState {
+    /// Creates an empty state keeping the last `tail` output lines of each
+    /// running transform.
+    pub fn new(tail: usize) -> Self {
+        Self {
+            tail,
+            ..Default::default()
+        }
+    }
+
+    /// Updates the state with the next event of the run.
+    pub fn apply(&mut self, event: &Event) {
+        match &event.kind {
+            EventKind::RunStarted { targets } => {
+                self.targets = targets.clone();
+                self.started = Some(event.time);
+            }
+            EventKind::Planned { addr, depends } => {
+                // Dependencies are always planned first
+                let level = depends
+                    .iter()
+                    .filter_map(|x| self.nodes.get(x))
+                    .map(|x| x.level + 1)
+                    .max()
+                    .unwrap_or_default();
+                self.nodes.entry(addr.clone()).or_insert(NodeProgress {
+                    depends: depends.clone(),
+                    level,
+                    state: NodeState::Pending,
+                    started: None,
+                    estimate_ms: None,
+                    duration_ms: None,
+                    log: None,
+                    tail: VecDeque::new(),
+                });
+            }
+            EventKind::Cached { addr } => {
+                if let Some(node) = self.nodes.get_mut(addr)
+                    && node.state == NodeState::Pending
+                {
+                    node.state = NodeState::Cached;
+                    self.finished_order.push(addr.clone());
+                }
+            }
+            EventKind::Started {
+                addr,
+                log,
+                estimate_ms,
+            } => {
+                if let Some(node) = self.nodes.get_mut(addr) {
+                    node.state = NodeState::Running;
+                    node.started = Some(event.time);
+                    node.estimate_ms = *estimate_ms;
+                    node.log = Some(log.clone());
+                    self.logs.insert(log.clone(), addr.clone());
+                }
+            }
+            EventKind::Output { log, line } => {
+                if let Some(node) = self.logs.get(log).and_then(|x| self.nodes.get_mut(x)) {
+                    if node.tail.len() == self.tail {
+                        node.tail.pop_front();
+                    }
+                    if self.tail > 0 {
+                        node.tail.push_back(line.clone());
+                    }
+                }
+            }
+            EventKind::Finished {
+                addr,
+                outcome,
+                duration_ms,
+            } => {
+                if let Some(node) = self.nodes.get_mut(addr) {
+                    node.state = match outcome {
+                        NodeOutcome::Failed => NodeState::Failed,
+                        _ => NodeState::Built,
+                    };
+                    node.duration_ms = Some(*duration_ms);
+                    node.tail.clear();
+                    if let Some(log) = node.log.as_ref() {
+                        self.logs.remove(log);
+                    }
+                    self.finished_order.push(addr.clone());
+                }
+            }
+            EventKind::RunFinished { status } => {
+                self.finished = Some(event.time);
+                self.status = Some(*status);
+            }
+        }
+    }
+
+    /// Number of transforms in `state`.
+    pub fn count(&self, state: NodeState) -> usize {
+        self.nodes.values().filter(|x| x.state == state).count()
+    }
+
+    /// Running transforms, longest running first.
+    pub fn running(&self) -> Vec<(&Addr, &NodeProgress)> {
+        let mut running: Vec<_> = self
+            .nodes
+            .iter()
+            .filter(|x| x.1.state == NodeState::Running)
+            .collect();
+        running.sort_by_key(|x| x.1.started);
+        running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn subscribers_receive_later_events() {
+        let bus = EventBus::default();
+        bus.publish(EventKind::RunFinished {
+            status: RunStatus::Failed,
+        });
+        let mut events = bus.clone().subscribe();
+        assert!(bus.is_observed());
+        bus.publish(EventKind::Cached {
+            addr: Addr::parse("//a/b").unwrap(),
+        });
+        let event = events.recv().await.unwrap();
+        assert_eq!(
+            event.kind,
+            EventKind::Cached {
+                addr: Addr::parse("//a/b").unwrap()
+            }
+        );
+        assert!(events.try_recv().is_err());
+    }
+
+    fn event(kind: EventKind) -> Event {
+        Event {
+            time: Utc::now(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn run_state_follows_events() {
+        let a = Addr::parse("//pkg/a").unwrap();
+        let b = Addr::parse("//pkg/b").unwrap();
+        let mut state = RunState::new(2);
+        for kind in [
+            EventKind::RunStarted {
+                targets: vec![b.clone()],
+            },
+            EventKind::Planned {
+                addr: a.clone(),
+                depends: Vec::new(),
+            },
+            EventKind::Planned {
+                addr: b.clone(),
+                depends: vec![a.clone()],
+            },
+            EventKind::Cached { addr: a.clone() },
+            EventKind::Started {
+                addr: b.clone(),
+                log: "b.log".to_string(),
+                estimate_ms: Some(4),
+            },
+        ] {
+            state.apply(&event(kind));
+        }
+        for line in ["one", "two", "three"] {
+            state.apply(&event(EventKind::Output {
+                log: "b.log".to_string(),
+                line: line.to_string(),
+            }));
+        }
+        assert_eq!(state.nodes[&b].level, 1);
+        assert_eq!(state.nodes[&b].estimate_ms, Some(4));
+        assert_eq!(state.running().len(), 1);
+        assert_eq!(state.nodes[&b].tail, ["two", "three"]);
+
+        state.apply(&event(EventKind::Finished {
+            addr: b.clone(),
+            outcome: NodeOutcome::Failed,
+            duration_ms: 5,
+        }));
+        state.apply(&event(EventKind::RunFinished {
+            status: RunStatus::Failed,
+        }));
+        assert_eq!(state.count(NodeState::Cached), 1);
+        assert_eq!(state.count(NodeState::Failed), 1);
+        assert!(state.nodes[&b].tail.is_empty());
+        assert_eq!(state.finished_order, vec![a, b]);
+        assert_eq!(state.status, Some(RunStatus::Failed));
+    }
+
+    #[test]
+    fn events_serialize_flat() {
+        let event = Event {
+            time: Utc::now(),
+            kind: EventKind::Output {
+                log: "a.log".to_string(),
+                line: "hello".to_string(),
+            },
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "output");
+        assert_eq!(json["line"], "hello");
+        assert!(json["time"].is_string());
+    }
+}
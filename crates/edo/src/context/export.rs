@@ -0,0 +1,247 @@
+//! Transform definitions described for tools outside edo.
+//!
+//! Organizations already run dependency hygiene checks and license scanners
+//! against Bazel and Buck graphs. [`Context::export_targets`] describes each
+//! transform's inputs, output and dependencies, and [`query_xml`] renders
+//! them the way `bazel query --output=xml` does, so those tools can read an
+//! edo graph too. Transforms become rules of their kind, and the sources they
+//! use become rules of the source kind carrying the definition's string
+//! fields, such as `url` or `ref`.
+
+use super::{Addr, Context, ContextResult, error};
+use serde::Serialize;
+use snafu::OptionExt;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+
+/// A transform, see [`Context::export_targets`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TargetDescription {
+    /// Address of the transform.
+    pub addr: Addr,
+    /// Kind of its definition, such as `script`.
+    pub kind: Option<String>,
+    /// Environment farm it runs in.
+    pub environment: Option<Addr>,
+    /// Transforms it depends on.
+    pub depends: Vec<Addr>,
+    /// Sources it stages.
+    pub sources: Vec<SourceDescription>,
+    /// Id of the artifact it produces, when it can be computed.
+    pub output: Option<String>,
+    /// Capabilities its artifact provides.
+    pub provides: Vec<String>,
+}
+
+/// A source used by a transform.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SourceDescription {
+    /// Address of the source definition.
+    pub addr: Addr,
+    /// Kind of the source, such as `git` or `remote`.
+    pub kind: Option<String>,
+    /// String fields of the definition, such as `url` and `ref`.
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Context {
+    /// Describes the transforms at `targets` and every transform they depend
+    /// on, in address order.
+    ///
+    /// Transforms whose artifact id cannot be computed, such as those of a
+    /// source that cannot be resolved, are described without an output.
+    pub async fn export_targets(&self, targets: &[Addr]) -> ContextResult<Vec<TargetDescription>> {
+        let handle = self.get_handle();
+        let mut described = BTreeMap::new();
+        let mut queue: VecDeque<Addr> = targets.iter().map(|x| self.resolve_alias(x)).collect();
+        while let Some(addr) = queue.pop_front() {
+            if described.contains_key(&addr) {
+                continue;
+            }
+            let transform = self
+                .get_transform(&addr)
+                .context(error::NoTransformFoundSnafu { addr: addr.clone() })?;
+            let depends: Vec<Addr> = transform
+                .depends()
+                .await?
+                .iter()
+                .map(|x| self.resolve_alias(x))
+                .collect();
+            queue.extend(depends.iter().cloned());
+            let sources = self
+                .get_element_sources(&addr)
+                .into_iter()
+                .map(|x| self.describe_source(x))
+                .collect();
+            let mut provides: Vec<String> = self
+                .provides
+                .get(&addr)
+                .map(|x| x.value().iter().cloned().collect())
+                .unwrap_or_default();
+            provides.sort();
+            let description = TargetDescription {
+                kind: self.kinds.get(&addr).map(|x| x.value().clone()),
+                environment: transform.environment().await.ok(),
+                depends,
+                sources,
+                output: handle
+                    .unique_id(&addr, &transform)
+                    .await
+                    .ok()
+                    .map(|x| x.to_string()),
+                provides,
+                addr: addr.clone(),
+            };
+            described.insert(addr, description);
+        }
+        Ok(described.into_values().collect())
+    }
+
+    fn describe_source(&self, addr: Addr) -> SourceDescription {
+        let node = self.sources.get(&addr).map(|x| x.value().clone());
+        let fields = node
+            .as_ref()
+            .and_then(|x| x.get_table())
+            .map(|table| {
+                table
+                    .into_iter()
+                    .filter_map(|(key, value)| Some((key, value.as_string()?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        SourceDescription {
+            kind: node.and_then(|x| x.get_kind()),
+            addr,
+            fields,
+        }
+    }
+}
+
+/// Renders `targets` in the format of `bazel query --output=xml`.
+pub fn query_xml(targets: &[TargetDescription]) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.1\" encoding=\"UTF-8\" standalone=\"no\"?>\n<query version=\"2\">\n",
+    );
+    let mut sources = BTreeMap::new();
+    for target in targets {
+        let _ = writeln!(
+            out,
+            "    <rule class=\"{}\" name=\"{}\">",
+            escape(target.kind.as_deref().unwrap_or("transform")),
+            escape(&target.addr.to_string())
+        );
+        let srcs: Vec<String> = target.sources.iter().map(|x| x.addr.to_string()).collect();
+        let deps: Vec<String> = target.depends.iter().map(|x| x.to_string()).collect();
+        labels(&mut out, "srcs", &srcs);
+        labels(&mut out, "deps", &deps);
+        if let Some(environment) = target.environment.as_ref() {
+            let _ = writeln!(
+                out,
+                "        <label name=\"environment\" value=\"{}\"/>",
+                escape(&environment.to_string())
+            );
+        }
+        if !target.provides.is_empty() {
+            let _ = writeln!(out, "        <list name=\"provides\">");
+            for capability in target.provides.iter() {
+                let _ = writeln!(
+                    out,
+                    "            <string value=\"{}\"/>",
+                    escape(capability)
+                );
+            }
+            let _ = writeln!(out, "        </list>");
+        }
+        let mut inputs: Vec<&String> = srcs.iter().chain(deps.iter()).collect();
+        inputs.sort();
+        for input in inputs {
+            let _ = writeln!(out, "        <rule-input name=\"{}\"/>", escape(input));
+        }
+        if let Some(output) = target.output.as_ref() {
+            let _ = writeln!(out, "        <rule-output name=\"{}\"/>", escape(output));
+        }
+        let _ = writeln!(out, "    </rule>");
+        for source in target.sources.iter() {
+            sources.insert(source.addr.to_string(), source);
+        }
+    }
+    for (addr, source) in sources {
+        let _ = writeln!(
+            out,
+            "    <rule class=\"{}\" name=\"{}\">",
+            escape(source.kind.as_deref().unwrap_or("source")),
+            escape(&addr)
+        );
+        for (key, value) in source.fields.iter() {
+            let _ = writeln!(
+                out,
+                "        <string name=\"{}\" value=\"{}\"/>",
+                escape(key),
+                escape(value)
+            );
+        }
+        let _ = writeln!(out, "    </rule>");
+    }
+    out.push_str("</query>\n");
+    out
+}
+
+fn labels(out: &mut String, name: &str, values: &[String]) {
+    if values.is_empty() {
+        return;
+    }
+    let _ = writeln!(out, "        <list name=\"{name}\">");
+    for value in values {
+        let _ = writeln!(out, "            <label value=\"{}\"/>", escape(value));
+    }
+    let _ = writeln!(out, "        </list>");
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(value: &str) -> Addr {
+        Addr::parse(value).unwrap()
+    }
+
+    #[test]
+    fn targets_render_as_bazel_query_rules() {
+        let source = SourceDescription {
+            addr: addr("//app/src"),
+            kind: Some("git".to_string()),
+            fields: BTreeMap::from([(
+                "url".to_string(),
+                "https://example.com/a.git?x=1&y=2".to_string(),
+            )]),
+        };
+        let targets = [TargetDescription {
+            addr: addr("//app/build"),
+            kind: Some("script".to_string()),
+            environment: None,
+            depends: vec![addr("//app/lib")],
+            sources: vec![source.clone(), source],
+            output: Some("app-build-blake3:abc".to_string()),
+            provides: Vec::new(),
+        }];
+        let xml = query_xml(&targets);
+        assert!(
+            xml.contains("<rule class=\"script\" name=\"//app/build\">"),
+            "{xml}"
+        );
+        assert!(xml.contains("<list name=\"deps\">\n            <label value=\"//app/lib\"/>"));
+        assert!(xml.contains("<rule-input name=\"//app/src\"/>"));
+        assert!(xml.contains("<rule-output name=\"app-build-blake3:abc\"/>"));
+        // Sources used twice are described once
+        assert_eq!(xml.matches("<rule class=\"git\"").count(), 1);
+        assert!(xml.contains("value=\"https://example.com/a.git?x=1&amp;y=2\""));
+    }
+}
@@ -0,0 +1,414 @@
+//! Canonical formatting of build files for `edo fmt`.
+//!
+//! [`format_toml`] rewrites a TOML build file line by line, keeping its
+//! comments and the order it declares things in. Headers and keys lose stray
+//! whitespace, `schema-version` and the `kind` of each definition move first,
+//! the `=` of a table line up, arrays are wrapped one item per line once they
+//! no longer fit in [`MAX_WIDTH`] columns (or when written wrapped already),
+//! and tables are separated by a single blank line. The formatted file is parsed again and rejected if it
+//! declares anything different.
+
+use super::schema::BuildFormat;
+use super::{ContextResult, error};
+use snafu::{ResultExt, ensure};
+use std::path::Path;
+
+/// Width arrays are wrapped at.
+pub const MAX_WIDTH: usize = 100;
+
+/// Indentation of wrapped array items.
+const INDENT: &str = "  ";
+
+/// A key and its value, with the comments written above it.
+struct Entry {
+    /// Comment lines above the key, blank lines as empty strings.
+    lead: Vec<String>,
+    key: String,
+    value: String,
+    comment: Option<String>,
+}
+
+/// A table, or the keys before the first table when `header` is `None`.
+#[derive(Default)]
+struct Table {
+    lead: Vec<String>,
+    header: Option<String>,
+    comment: Option<String>,
+    entries: Vec<Entry>,
+}
+
+/// Index just past the string starting at byte `start` of `text`.
+fn skip_string(text: &[u8], start: usize) -> usize {
+    let quote = text[start];
+    let delimiter = [quote; 3];
+    if text[start..].starts_with(&delimiter) {
+        let mut index = start + 3;
+        while index < text.len() {
+            if quote == b'"' && text[index] == b'\\' {
+                index += 2;
+            } else if text[index..].starts_with(&delimiter) {
+                // Up to two quotes may end the content right before the delimiter
+                let mut end = index + 3;
+                while end < text.len() && end < index + 5 && text[end] == quote {
+                    end += 1;
+                }
+                return end;
+            } else {
+                index += 1;
+            }
+        }
+        return text.len();
+    }
+    let mut index = start + 1;
+    while index < text.len() {
+        match text[index] {
+            b'\\' if quote == b'"' => index += 2,
+            b'\n' => return index,
+            x if x == quote => return index + 1,
+            _ => index += 1,
+        }
+    }
+    text.len()
+}
+
+/// Where the value at the start of `text` ends, before any comment trailing
+/// it on its last line.
+fn value_end(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let (mut index, mut depth) = (0, 0usize);
+    while index < bytes.len() {
+        match bytes[index] {
+            b'"' | b'\'' => {
+                index = skip_string(bytes, index);
+                continue;
+            }
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            b'#' | b'\n' if depth == 0 => break,
+            b'#' => {
+                while index < bytes.len() && bytes[index] != b'\n' {
+                    index += 1;
+                }
+                continue;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    index
+}
+
+/// The items of the array `value`, each written inline, or `None` when it
+/// holds comments or multi-line strings, whose layout is kept.
+fn array_items(value: &str) -> Option<Vec<String>> {
+    let inner = value.strip_prefix('[')?.strip_suffix(']')?;
+    let bytes = inner.as_bytes();
+    let (mut items, mut start, mut depth, mut index) = (Vec::new(), 0, 0usize, 0);
+    while index < bytes.len() {
+        match bytes[index] {
+            b'"' | b'\'' => {
+                let end = skip_string(bytes, index);
+                if inner[index..end].contains('\n') {
+                    return None;
+                }
+                index = end;
+                continue;
+            }
+            b'#' => return None,
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            b',' if depth == 0 => {
+                items.push(inline(inner[start..index].trim())?);
+                start = index + 1;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    let last = inner[start..].trim();
+    if !last.is_empty() {
+        items.push(inline(last)?);
+    }
+    Some(items)
+}
+
+/// `value` written on a single line.
+fn inline(value: &str) -> Option<String> {
+    if value.starts_with('[') {
+        return Some(format!("[{}]", array_items(value)?.join(", ")));
+    }
+    (!value.contains('\n')).then(|| value.to_string())
+}
+
+/// `value`, written after `width` columns of key.
+fn format_value(width: usize, value: &str) -> String {
+    let Some(items) = value.starts_with('[').then(|| array_items(value)).flatten() else {
+        return value.to_string();
+    };
+    let inline = format!("[{}]", items.join(", "));
+    // Arrays written one item per line stay that way
+    if items.is_empty() || !value.contains('\n') && width + inline.len() <= MAX_WIDTH {
+        return inline;
+    }
+    let mut wrapped = "[\n".to_string();
+    for item in items {
+        wrapped.push_str(INDENT);
+        wrapped.push_str(&item);
+        wrapped.push_str(",\n");
+    }
+    wrapped.push(']');
+    wrapped
+}
+
+/// `key` without whitespace around its dots, unless parts of it are quoted.
+fn normalize_key(key: &str) -> String {
+    let key = key.trim();
+    if key.contains(['"', '\'']) {
+        return key.to_string();
+    }
+    key.split('.').map(str::trim).collect::<Vec<_>>().join(".")
+}
+
+/// Index of the `=` separating the key at the start of `line` from its value.
+fn key_end(line: &str) -> Option<usize> {
+    let bytes = line.as_bytes();
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'"' | b'\'' => index = skip_string(bytes, index),
+            b'=' => return Some(index),
+            b'\n' => return None,
+            _ => index += 1,
+        }
+    }
+    None
+}
+
+/// Pushes a blank line onto `lines`, unless it ends with one already.
+fn push_blank(lines: &mut Vec<String>) {
+    if lines.last().is_some_and(|x| !x.is_empty()) {
+        lines.push(String::new());
+    }
+}
+
+/// The comment trailing a header or value ending at byte `end` of `text`, and
+/// where the next line starts.
+fn trailing(text: &str, end: usize) -> (Option<String>, usize) {
+    let rest = &text[end..];
+    let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+    let comment = Some(line.trim().to_string()).filter(|x| !x.is_empty());
+    (comment, end + line.len() + 1)
+}
+
+fn parse(text: &str) -> ContextResult<(Vec<Table>, Vec<String>)> {
+    let malformed = || {
+        error::FormatSnafu {
+            reason: "a line is neither a table, a key or a comment",
+        }
+        .build()
+    };
+    let mut tables = vec![Table::default()];
+    let mut pending = Vec::new();
+    let mut position = 0;
+    while position < text.len() {
+        let rest = &text[position..];
+        let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        let start = position + line.len() - line.trim_start().len();
+        if line.trim().is_empty() {
+            if pending.last().is_none_or(|x: &String| !x.is_empty()) {
+                pending.push(String::new());
+            }
+            position += line.len() + 1;
+            continue;
+        }
+        if line.trim_start().starts_with('#') {
+            pending.push(line.trim().to_string());
+            position += line.len() + 1;
+            continue;
+        }
+        if line.trim_start().starts_with('[') {
+            let end = start + value_end(&text[start..]);
+            let header = text[start..end].trim_end();
+            let (open, close) = if header.starts_with("[[") {
+                ("[[", "]]")
+            } else {
+                ("[", "]")
+            };
+            let key = header
+                .strip_prefix(open)
+                .and_then(|x| x.strip_suffix(close))
+                .ok_or_else(malformed)?;
+            let (comment, next) = trailing(text, end);
+            tables.push(Table {
+                lead: std::mem::take(&mut pending),
+                header: Some(format!("{open}{}{close}", normalize_key(key))),
+                comment,
+                entries: Vec::new(),
+            });
+            position = next;
+        } else {
+            let equals = start + key_end(&text[start..]).ok_or_else(malformed)?;
+            let value_start = equals + 1 + text[equals + 1..].len()
+                - text[equals + 1..].trim_start_matches([' ', '\t']).len();
+            let end = value_start + value_end(&text[value_start..]);
+            let (comment, next) = trailing(text, end);
+            let table = tables.last_mut().ok_or_else(malformed)?;
+            table.entries.push(Entry {
+                lead: std::mem::take(&mut pending),
+                key: normalize_key(&text[start..equals]),
+                value: text[value_start..end].trim_end().to_string(),
+                comment,
+            });
+            position = next;
+        }
+    }
+    Ok((tables, pending))
+}
+
+fn render(tables: Vec<Table>, tail: Vec<String>) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for table in tables {
+        let headed = table.header.is_some();
+        let first = if headed { "kind" } else { "schema-version" };
+        let mut entries = table.entries;
+        entries.sort_by_key(|x| x.key != first);
+        let width = entries.iter().map(|x| x.key.len()).max().unwrap_or(0);
+        if let Some(header) = table.header {
+            push_blank(&mut lines);
+            lines.extend(table.lead.into_iter().skip_while(String::is_empty));
+            lines.push(match table.comment {
+                Some(comment) => format!("{header} {comment}"),
+                None => header,
+            });
+        }
+        for (index, entry) in entries.into_iter().enumerate() {
+            for line in entry.lead {
+                if !line.is_empty() {
+                    lines.push(line);
+                } else if index > 0 || !headed {
+                    // The first key of a table follows its header directly
+                    push_blank(&mut lines);
+                }
+            }
+            let key = format!("{:<width$} = ", entry.key);
+            let mut line = format!("{key}{}", format_value(key.len(), &entry.value));
+            if let Some(comment) = entry.comment {
+                line.push(' ');
+                line.push_str(&comment);
+            }
+            lines.push(line);
+        }
+    }
+    let tail: Vec<String> = tail.into_iter().skip_while(String::is_empty).collect();
+    if !tail.is_empty() {
+        push_blank(&mut lines);
+        lines.extend(tail);
+    }
+    while lines.first().is_some_and(String::is_empty) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Formats the contents of a TOML build file.
+pub fn format_toml(text: &str) -> ContextResult<String> {
+    let declared: toml::Table = toml::from_str(text).context(error::DeserializeSnafu)?;
+    let (tables, tail) = parse(text)?;
+    let formatted = render(tables, tail);
+    ensure!(
+        toml::from_str::<toml::Table>(&formatted).ok() == Some(declared),
+        error::FormatSnafu {
+            reason: "the formatted file would declare something else",
+        }
+    );
+    Ok(formatted)
+}
+
+/// Formats the contents of the build file at `path`, or returns `None` when
+/// it is not written in TOML: YAML files are left as written, as their
+/// parser does not keep comments.
+pub fn format_build_file(path: &Path, text: &str) -> ContextResult<Option<String>> {
+    match BuildFormat::detect(path) {
+        Some(BuildFormat::Toml) => format_toml(text).map(Some),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_files_are_formatted_canonically() {
+        let messy = r#"# Build of the hello program
+
+
+schema-version="1"
+[ transform . build ]   # the main build
+commands = [ "make",  "make install" ]
+  kind = "script"
+source=[
+"code" ]
+
+# Tests are slow
+[transform.test]
+kind = "script"
+commands = ["make check", "cp build/test-results.xml {{install-root}}/results.xml", "cp build/coverage.json {{install-root}}/coverage.json"]
+depends = [
+    "//app/build", # built first
+]
+script = """
+set -e
+make   check
+"""
+"#;
+        let expected = r#"# Build of the hello program
+
+schema-version = "1"
+
+[transform.build] # the main build
+kind     = "script"
+commands = ["make", "make install"]
+source   = [
+  "code",
+]
+
+# Tests are slow
+[transform.test]
+kind     = "script"
+commands = [
+  "make check",
+  "cp build/test-results.xml {{install-root}}/results.xml",
+  "cp build/coverage.json {{install-root}}/coverage.json",
+]
+depends  = [
+    "//app/build", # built first
+]
+script   = """
+set -e
+make   check
+"""
+"#;
+        let formatted = format_toml(messy).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format_toml(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn only_parseable_toml_is_formatted() {
+        assert!(format_toml("[source\n").is_err());
+        assert_eq!(
+            format_build_file(Path::new("app/edo.yaml"), "source: {}\n").unwrap(),
+            None
+        );
+        assert_eq!(
+            format_build_file(Path::new("app/edo.toml"), "a=1").unwrap(),
+            Some("a = 1\n".to_string())
+        );
+    }
+}
@@ -5,16 +5,19 @@
 //! command-line arguments without holding a reference to the full
 //! [`Context`](super::Context).
 
-use super::{Addr, ContextResult, Log, LogManager, error};
+use super::{Addr, Aliases, ArcMap, ContextResult, Log, LogManager, error};
 use crate::{
     context::Config,
-    environment::{Environment, Farm},
-    storage::Storage,
-    transform::Transform,
+    environment::{Environment, Farm, HostRequirements, supports_arch},
+    storage::{Id, Storage},
+    transform::{CacheKey, Contract, Transform, TransformResult, contract_of},
 };
+use dashmap::DashMap;
+use serde_json::Value;
 use snafu::OptionExt;
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio_util::sync::CancellationToken;
 
 /// A handle is passed to transforms where it needs to look up
@@ -27,7 +30,12 @@ pub struct Handle {
     transforms: HashMap<Addr, Transform>,
     farms: HashMap<Addr, Farm>,
     args: HashMap<String, String>,
+    aliases: Aliases,
+    provides: HashMap<Addr, BTreeSet<String>>,
+    local_cache: PathBuf,
     cancellation: CancellationToken,
+    /// Keys computed through [`Handle::cache_key`], shared by every clone
+    keys: ArcMap<Addr, CacheKey>,
 }
 
 unsafe impl Send for Handle {}
@@ -50,15 +58,50 @@ impl Handle {
             transforms,
             farms,
             args,
+            aliases: Aliases::default(),
+            provides: HashMap::new(),
+            local_cache: PathBuf::new(),
             cancellation: CancellationToken::new(),
+            keys: Arc::new(DashMap::new()),
         }
     }
 
+    /// Resolves deprecated aliases through `aliases` in every lookup.
+    pub fn with_aliases(mut self, aliases: &Aliases) -> Self {
+        self.aliases = aliases.clone();
+        self
+    }
+
+    /// Indexes the capabilities the artifacts of each transform provide.
+    pub fn with_provides(mut self, provides: HashMap<Addr, BTreeSet<String>>) -> Self {
+        self.provides = provides;
+        self
+    }
+
+    /// Records the directory of the local cache, for transforms running
+    /// edo themselves, see [`NESTED_CACHE_ENV`](super::NESTED_CACHE_ENV).
+    pub fn with_local_cache(mut self, path: &Path) -> Self {
+        self.local_cache = path.to_path_buf();
+        self
+    }
+
+    /// Returns the directory of the local cache
+    pub fn local_cache(&self) -> &Path {
+        &self.local_cache
+    }
+
     /// Returns the project wide configuration nodes
     pub fn config(&self) -> Config {
         self.config.clone()
     }
 
+    /// Uses `token` as the cancellation token, so whoever holds it can
+    /// cancel the work done through this handle.
+    pub fn with_cancellation(mut self, token: &CancellationToken) -> Self {
+        self.cancellation = token.clone();
+        self
+    }
+
     /// Returns the cancellation token
     pub fn cancellation(&self) -> CancellationToken {
         self.cancellation.clone()
@@ -76,7 +119,67 @@ impl Handle {
 
     /// Looks up a transform by address, returning a clone if found.
     pub fn get(&self, addr: &Addr) -> Option<Transform> {
-        self.transforms.get(addr).cloned()
+        self.transforms.get(&self.aliases.resolve(addr)).cloned()
+    }
+
+    /// Returns the unique id of `transform`, registered at `addr`.
+    ///
+    /// Ids are computed once per handle and shared by its clones, so a
+    /// transform asking for the ids of its dependencies does not hash their
+    /// whole subgraphs again. Every call to
+    /// [`Context::get_handle`](super::Context::get_handle) makes a handle
+    /// with nothing computed yet; the scheduler makes one per run and
+    /// shares it between probing, fetching and building.
+    pub async fn unique_id(&self, addr: &Addr, transform: &Transform) -> TransformResult<Id> {
+        Ok(self.cache_key(addr, transform).await?.id())
+    }
+
+    /// Returns the cache key of `transform`, registered at `addr`, which
+    /// its [`unique_id`](Self::unique_id) is derived from. Keys are
+    /// computed once per handle like ids.
+    pub async fn cache_key(&self, addr: &Addr, transform: &Transform) -> TransformResult<CacheKey> {
+        let addr = self.aliases.resolve(addr);
+        if let Some(key) = self.keys.get(&addr) {
+            return Ok(key.clone());
+        }
+        let key = transform.cache_key(self).await?;
+        self.keys.insert(addr, key.clone());
+        Ok(key)
+    }
+
+    /// Reads the metadata the transform at `addr` declared on its artifact,
+    /// which must be built already, as the artifacts of dependencies are.
+    pub async fn metadata(&self, addr: &Addr) -> ContextResult<Contract> {
+        let transform = self
+            .get(addr)
+            .context(error::NoTransformFoundSnafu { addr: addr.clone() })?;
+        let id = self.unique_id(addr, &transform).await?;
+        let artifact = self.storage.safe_open(&id).await?;
+        Ok(contract_of(&artifact))
+    }
+
+    /// Reads the metadata `key` declared by the transform at `addr`, failing
+    /// when it is not declared.
+    pub async fn metadata_value(&self, addr: &Addr, key: &str) -> ContextResult<Value> {
+        let mut contract = self.metadata(addr).await?;
+        contract.remove(key).context(error::MissingMetadataSnafu {
+            addr: addr.clone(),
+            key,
+        })
+    }
+
+    /// Returns the transforms whose artifacts provide `capability`, in
+    /// address order, so a transform can locate a toolchain or runtime
+    /// dependency without hard-coding its address.
+    pub fn find_providing(&self, capability: &str) -> Vec<Addr> {
+        let mut addrs: Vec<Addr> = self
+            .provides
+            .iter()
+            .filter(|(_, x)| x.contains(capability))
+            .map(|(addr, _)| addr.clone())
+            .collect();
+        addrs.sort();
+        addrs
     }
 
     /// Returns a reference to the full transforms map.
@@ -89,6 +192,77 @@ impl Handle {
         &self.args
     }
 
+    /// Follows `addr` through any aliases to the address of its definition.
+    pub fn resolve_alias(&self, addr: &Addr) -> Addr {
+        self.aliases.resolve(addr)
+    }
+
+    /// Returns the environment farm registered at `addr`, if any.
+    pub fn get_farm(&self, addr: &Addr) -> Option<Farm> {
+        self.farms.get(&self.aliases.resolve(addr)).cloned()
+    }
+
+    /// Returns the farm a transform asking for `farm` runs in when it builds
+    /// for `arch` and needs `requires` of its host: `farm` itself when it
+    /// can execute `arch` and its host meets both `requires` and the farm's
+    /// own requirements, otherwise the first other farm, in address order,
+    /// that does. Farms are only switched for unmet host requirements when
+    /// they allow routing.
+    ///
+    /// A farm that is not registered is returned as is, creating its
+    /// environment reports it missing.
+    pub fn platform_farm(
+        &self,
+        farm: &Addr,
+        arch: Option<&str>,
+        requires: &HostRequirements,
+    ) -> ContextResult<Addr> {
+        let farm = self.aliases.resolve(farm);
+        let Some(requested) = self.farms.get(&farm) else {
+            return Ok(farm);
+        };
+        let executes = |x: &Farm| arch.is_none_or(|arch| supports_arch(&x.platforms(), arch));
+        let unmet = |x: &Farm| x.host_requires().and(requires).unmet(&x.host());
+        let platforms = requested.platforms();
+        let missing = unmet(requested);
+        if executes(requested) && missing.is_empty() {
+            return Ok(farm);
+        }
+        let routed = requested.host_requires().and(requires).route;
+        if !missing.is_empty() && !routed {
+            return error::UnmetHostRequirementsSnafu {
+                farm,
+                unmet: missing.join("; "),
+                routed,
+            }
+            .fail();
+        }
+        let mut alternates: Vec<&Addr> = self
+            .farms
+            .iter()
+            .filter(|(_, x)| executes(x) && unmet(x).is_empty())
+            .map(|(addr, _)| addr)
+            .collect();
+        alternates.sort();
+        if let Some(alternate) = alternates.first() {
+            return Ok((*alternate).clone());
+        }
+        match arch {
+            Some(arch) if !executes(requested) => error::UnsupportedPlatformSnafu {
+                farm,
+                arch,
+                platforms: platforms.join(", "),
+            }
+            .fail(),
+            _ => error::UnmetHostRequirementsSnafu {
+                farm,
+                unmet: missing.join("; "),
+                routed,
+            }
+            .fail(),
+        }
+    }
+
     /// Creates a new build environment from the farm registered at `addr`.
     pub async fn create_environment(
         &self,
@@ -96,10 +270,11 @@ impl Handle {
         addr: &Addr,
         path: &Path,
     ) -> ContextResult<Environment> {
+        let addr = self.aliases.resolve(addr);
         let farm = self
             .farms
-            .get(addr)
-            .context(error::NoEnvironmentFoundSnafu { addr: addr.clone() })?;
+            .get(&addr)
+            .context(error::NoEnvironmentFoundSnafu { addr })?;
         let env = farm.create(log, path).await?;
         Ok(env)
     }
@@ -109,9 +284,13 @@ impl Handle {
 mod tests {
     use super::Handle;
     use crate::context::logmgr::test_support::shared_log_manager;
-    use crate::context::{Addr, Config, Node, error::ContextError};
-    use crate::storage::{Backend, LocalBackend, Storage};
-    use std::collections::{BTreeMap, HashMap};
+    use crate::context::{Addr, Config, Log, Node, error::ContextError};
+    use crate::environment::Environment;
+    use crate::storage::{Backend, Id, LocalBackend, Storage};
+    use crate::transform::{Transform, TransformImpl, TransformResult, TransformStatus};
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use tempfile::TempDir;
 
     /// Build a minimal `Storage` backed by a temporary local directory.
@@ -156,6 +335,42 @@ mod tests {
         assert!(handle.get(&addr).is_none());
     }
 
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn handle_finds_transforms_by_capability() {
+        let dir = TempDir::new().unwrap();
+        let log_mgr = shared_log_manager().await;
+        let storage = tmp_storage(dir.path()).await;
+
+        let rustc = Addr::parse("//rustc").unwrap();
+        let cargo = Addr::parse("//cargo").unwrap();
+        let handle = Handle::new(
+            log_mgr,
+            Config::default(),
+            storage,
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+        )
+        .with_provides(HashMap::from([
+            (
+                rustc.clone(),
+                BTreeSet::from(["toolchain:rust".to_string(), "rustc".to_string()]),
+            ),
+            (
+                cargo.clone(),
+                BTreeSet::from(["toolchain:rust".to_string()]),
+            ),
+        ]));
+
+        assert_eq!(
+            handle.find_providing("toolchain:rust"),
+            vec![cargo, rustc.clone()]
+        );
+        assert_eq!(handle.find_providing("rustc"), vec![rustc]);
+        assert!(handle.find_providing("toolchain:go").is_empty());
+    }
+
     #[tokio::test]
     #[serial_test::serial(log_manager)]
     async fn handle_create_environment_no_farm_errors() {
@@ -187,4 +402,106 @@ mod tests {
             Ok(_) => panic!("expected Err, got Ok"),
         }
     }
+
+    /// Hashes the ids of its dependencies into its own, counting how often
+    /// it is asked for its id.
+    struct Counted {
+        depends: Vec<Addr>,
+        count: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl TransformImpl for Counted {
+        async fn environment(&self) -> TransformResult<Addr> {
+            Ok(Addr::parse("//default").unwrap())
+        }
+
+        async fn get_unique_id(&self, ctx: &Handle) -> TransformResult<Id> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            let mut digest = String::new();
+            for depend in self.depends.iter() {
+                let transform = ctx.get(depend).unwrap();
+                digest.push_str(ctx.unique_id(depend, &transform).await?.digest());
+            }
+            Ok(Id::builder()
+                .name("counted".to_string())
+                .digest(blake3::hash(digest.as_bytes()).to_hex().to_string())
+                .build())
+        }
+
+        async fn depends(&self) -> TransformResult<Vec<Addr>> {
+            Ok(self.depends.clone())
+        }
+
+        async fn prepare(&self, _log: &Log, _ctx: &Handle) -> TransformResult<()> {
+            Ok(())
+        }
+
+        async fn stage(
+            &self,
+            _log: &Log,
+            _ctx: &Handle,
+            _env: &Environment,
+        ) -> TransformResult<()> {
+            Ok(())
+        }
+
+        async fn transform(
+            &self,
+            _log: &Log,
+            _ctx: &Handle,
+            _env: &Environment,
+        ) -> TransformStatus {
+            unreachable!()
+        }
+
+        fn can_shell(&self) -> bool {
+            false
+        }
+
+        fn shell(&self, _env: &Environment) -> TransformResult<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn handle_computes_each_unique_id_once() {
+        let dir = TempDir::new().unwrap();
+        let log_mgr = shared_log_manager().await;
+        let storage = tmp_storage(dir.path()).await;
+
+        // A diamond, where `//bottom` is reached through both sides
+        let count = Arc::new(AtomicUsize::new(0));
+        let counted = |depends: &[&str]| {
+            Transform::new(Counted {
+                depends: depends.iter().map(|x| Addr::parse(x).unwrap()).collect(),
+                count: count.clone(),
+            })
+        };
+        let transforms = HashMap::from([
+            (
+                Addr::parse("//top").unwrap(),
+                counted(&["//left", "//right"]),
+            ),
+            (Addr::parse("//left").unwrap(), counted(&["//bottom"])),
+            (Addr::parse("//right").unwrap(), counted(&["//bottom"])),
+            (Addr::parse("//bottom").unwrap(), counted(&[])),
+        ]);
+        let handle = Handle::new(
+            log_mgr,
+            Config::default(),
+            storage,
+            transforms,
+            HashMap::new(),
+            HashMap::new(),
+        );
+
+        let top = Addr::parse("//top").unwrap();
+        let transform = handle.get(&top).unwrap();
+        let id = handle.unique_id(&top, &transform).await.unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 4);
+        assert_eq!(handle.unique_id(&top, &transform).await.unwrap(), id);
+        assert_eq!(count.load(Ordering::SeqCst), 4);
+    }
 }
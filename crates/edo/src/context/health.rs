@@ -0,0 +1,179 @@
+//! Health checks of the things a build relies on, reported by `edo doctor`.
+//!
+//! Storage backends, environment farms and vendors check themselves through
+//! a `health` method that returns no checks unless implemented, and
+//! [`Context::health`](super::Context::health) gathers them into one
+//! [`HealthReport`] per component.
+
+use crate::util::{format_size, free_space};
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+/// How a [`HealthCheck`] turned out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// Works as expected.
+    Ok,
+    /// Works, but is likely to cause trouble.
+    Warning,
+    /// Does not work, builds relying on it will fail.
+    Error,
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ok => "ok",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// One thing checked, with what to do about it when it is not ok.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct HealthCheck {
+    /// What was checked, such as `bucket access`.
+    pub name: String,
+    /// How the check turned out.
+    pub status: HealthStatus,
+    /// What was found.
+    pub detail: String,
+    /// How to fix a failed check.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remedy: Option<String>,
+}
+
+impl HealthCheck {
+    /// A check that passed.
+    pub fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: HealthStatus::Ok,
+            detail: detail.into(),
+            remedy: None,
+        }
+    }
+
+    /// A check that passed with a problem, fixed by `remedy`.
+    pub fn warning(
+        name: impl Into<String>,
+        detail: impl Into<String>,
+        remedy: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            status: HealthStatus::Warning,
+            detail: detail.into(),
+            remedy: Some(remedy.into()),
+        }
+    }
+
+    /// A check that failed, fixed by `remedy`.
+    pub fn error(
+        name: impl Into<String>,
+        detail: impl Into<String>,
+        remedy: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            status: HealthStatus::Error,
+            detail: detail.into(),
+            remedy: Some(remedy.into()),
+        }
+    }
+}
+
+/// The checks of one component, such as a cache or a farm.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct HealthReport {
+    /// The component checked, such as `build cache` or `environment //default`.
+    pub subject: String,
+    /// Its checks, in the order they ran.
+    pub checks: Vec<HealthCheck>,
+}
+
+impl HealthReport {
+    /// The worst status of the checks, `Ok` when there are none.
+    pub fn status(&self) -> HealthStatus {
+        self.checks
+            .iter()
+            .map(|x| x.status)
+            .max()
+            .unwrap_or(HealthStatus::Ok)
+    }
+}
+
+/// Free space under which a cache directory is reported, in bytes.
+pub const LOW_DISK_SPACE: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Checks that `path` can be written to and how much space its file system
+/// has left.
+pub fn check_directory(path: &Path) -> Vec<HealthCheck> {
+    let mut checks = Vec::new();
+    let probe = path.join(format!(".edo-doctor-{}", uuid::Uuid::now_v7()));
+    match std::fs::create_dir_all(path).and_then(|_| std::fs::write(&probe, b"edo")) {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            checks.push(HealthCheck::ok(
+                "writable",
+                format!("{} is writable", path.display()),
+            ));
+        }
+        Err(e) => checks.push(HealthCheck::error(
+            "writable",
+            format!("cannot write to {}: {e}", path.display()),
+            "fix the permissions of the directory, or point `--storage` somewhere writable",
+        )),
+    }
+    match free_space(path) {
+        Some(free) if free < LOW_DISK_SPACE => checks.push(HealthCheck::warning(
+            "disk space",
+            format!("{} free at {}", format_size(free), path.display()),
+            "free some space, for example with `edo prune`",
+        )),
+        Some(free) => checks.push(HealthCheck::ok(
+            "disk space",
+            format!("{} free at {}", format_size(free), path.display()),
+        )),
+        None => {}
+    }
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_status_is_the_worst_check() {
+        let mut report = HealthReport {
+            subject: "build cache".to_string(),
+            checks: vec![HealthCheck::ok("a", "fine")],
+        };
+        assert_eq!(report.status(), HealthStatus::Ok);
+        report
+            .checks
+            .push(HealthCheck::error("b", "broken", "fix it"));
+        report
+            .checks
+            .push(HealthCheck::warning("c", "meh", "look at it"));
+        assert_eq!(report.status(), HealthStatus::Error);
+    }
+
+    #[test]
+    fn directory_is_checked_for_writes_and_space() {
+        let dir = tempfile::tempdir().unwrap();
+        let checks = check_directory(&dir.path().join("storage"));
+        assert_eq!(checks[0].status, HealthStatus::Ok);
+        assert!(dir.path().join("storage").is_dir());
+        assert_eq!(
+            std::fs::read_dir(dir.path().join("storage"))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+}
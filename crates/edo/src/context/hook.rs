@@ -0,0 +1,52 @@
+//! Project-load hooks.
+//!
+//! A [`ProjectHook`] registered with the [`Registry`](super::Registry) takes
+//! part in [`Project::build`](super::Project::build): it can inject or rewrite
+//! definitions before dependencies are resolved, and inspect the resolved
+//! [`Lock`] before the project is used, e.g. to enforce that every remote
+//! source uses an approved mirror.
+
+use super::{Addr, Context, ContextResult, Lock, Node};
+use arc_handle::arc_handle;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+
+/// The definitions of a project handed to [`ProjectHook::before_resolve`].
+///
+/// Nodes are shared with the project, so rewriting a node in place changes
+/// the definition that gets resolved and registered. Definitions inserted
+/// into a map are registered like ones read from `edo.toml`; injected
+/// sources are not considered for dependency resolution.
+#[derive(Clone, Debug, Default)]
+pub struct ProjectDefinitions {
+    /// Source definitions by address.
+    pub sources: BTreeMap<Addr, Node>,
+    /// Environment farm definitions by address.
+    pub environments: BTreeMap<Addr, Node>,
+    /// Transform definitions by address.
+    pub transforms: BTreeMap<Addr, Node>,
+    /// Vendor definitions by address.
+    pub vendors: BTreeMap<Addr, Node>,
+}
+
+/// Callbacks invoked while a project is loaded. Hooks run in the order of the
+/// names they were registered under.
+#[arc_handle]
+#[async_trait]
+pub trait ProjectHook {
+    /// Called once every `edo.toml` is parsed, before dependencies are
+    /// resolved or anything is registered with the context.
+    async fn before_resolve(
+        &self,
+        _ctx: &Context,
+        _definitions: &mut ProjectDefinitions,
+    ) -> ContextResult<()> {
+        Ok(())
+    }
+    /// Called with the lock once dependencies are resolved, whether freshly or
+    /// from an up to date `edo.lock.json`. Returning an error rejects the
+    /// project before any lock file is written.
+    async fn after_lock(&self, _ctx: &Context, _lock: &Lock) -> ContextResult<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+//! A cached list of the addresses a project defines.
+//!
+//! Shell completion asks for addresses on every key press, which is too
+//! often to evaluate the whole project. A [`ProjectIndex`] records the
+//! addresses once, together with a fingerprint of the files the project is
+//! loaded from, and is reused until one of those files changes.
+
+use super::schema::BuildFormat;
+use super::{Addr, Context, ContextResult, error};
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+use std::fs::{read, read_dir};
+use std::path::Path;
+use tokio::fs::{read_to_string, write};
+
+/// Name of the lock file folded into the fingerprint.
+const LOCK_FILE: &str = "edo.lock.json";
+
+/// The addresses defined by a loaded project.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectIndex {
+    /// Fingerprint of the project files the index was built from, see
+    /// [`ProjectIndex::fingerprint`].
+    pub digest: String,
+    /// Transform addresses, in address order.
+    pub transforms: Vec<Addr>,
+    /// Matrix group addresses, in address order.
+    pub matrices: Vec<Addr>,
+    /// Deprecated alias addresses, in address order.
+    pub aliases: Vec<Addr>,
+}
+
+impl ProjectIndex {
+    /// Describes the project loaded into `ctx`, built from files with the
+    /// fingerprint `digest`.
+    pub fn from_context(ctx: &Context, digest: String) -> Self {
+        let mut matrices: Vec<Addr> = ctx.matrices.iter().map(|x| x.key().clone()).collect();
+        matrices.sort();
+        Self {
+            digest,
+            transforms: ctx.transform_addrs(),
+            matrices,
+            aliases: ctx.aliases().list().into_iter().map(|x| x.0).collect(),
+        }
+    }
+
+    /// Hashes the path and content of every build file under `project_dir`,
+    /// walked the same way [`Project`](super::Project) finds them, and of
+    /// the lock file.
+    pub fn fingerprint(project_dir: &Path) -> ContextResult<String> {
+        let mut hasher = blake3::Hasher::new();
+        hash_manifests(&mut hasher, project_dir, project_dir)?;
+        let lock = project_dir.join(LOCK_FILE);
+        if lock.is_file() {
+            hasher.update(LOCK_FILE.as_bytes());
+            hasher.update(&read(&lock).context(error::IoSnafu)?);
+        }
+        Ok(base16::encode_lower(hasher.finalize().as_bytes()))
+    }
+
+    /// Reads the index stored at `path`, returning `None` when there is none
+    /// or it cannot be parsed.
+    pub async fn load(path: &Path) -> ContextResult<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = read_to_string(path).await.context(error::IoSnafu)?;
+        Ok(serde_json::from_str(&content).ok())
+    }
+
+    /// Writes the index to `path`.
+    pub async fn save(&self, path: &Path) -> ContextResult<()> {
+        let content = serde_json::to_string(self).context(error::SerializeSnafu)?;
+        write(path, content).await.context(error::IoSnafu)
+    }
+
+    /// Returns every address starting with `prefix`, in address order.
+    pub fn complete(&self, prefix: &str) -> Vec<String> {
+        let mut addrs: Vec<String> = self
+            .transforms
+            .iter()
+            .chain(self.matrices.iter())
+            .chain(self.aliases.iter())
+            .map(|x| x.to_string())
+            .filter(|x| x.starts_with(prefix))
+            .collect();
+        addrs.sort();
+        addrs.dedup();
+        addrs
+    }
+}
+
+fn hash_manifests(hasher: &mut blake3::Hasher, root: &Path, directory: &Path) -> ContextResult<()> {
+    let mut entries = read_dir(directory)
+        .context(error::IoSnafu)?
+        .map(|x| x.map(|x| x.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .context(error::IoSnafu)?;
+    // Directory order is not stable, the fingerprint must be
+    entries.sort();
+    for path in entries {
+        if path.is_file() && BuildFormat::detect(&path).is_some() {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update(&read(&path).context(error::IoSnafu)?);
+        } else if path.is_dir() {
+            hash_manifests(hasher, root, &path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn fingerprint_follows_manifests() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("pkg")).unwrap();
+        std::fs::write(dir.path().join("pkg/edo.toml"), "a").unwrap();
+        std::fs::write(dir.path().join("pkg/notes.txt"), "a").unwrap();
+        let first = ProjectIndex::fingerprint(dir.path()).unwrap();
+
+        std::fs::write(dir.path().join("pkg/notes.txt"), "b").unwrap();
+        assert_eq!(ProjectIndex::fingerprint(dir.path()).unwrap(), first);
+
+        std::fs::write(dir.path().join("pkg/edo.toml"), "b").unwrap();
+        let second = ProjectIndex::fingerprint(dir.path()).unwrap();
+        assert_ne!(second, first);
+
+        std::fs::write(dir.path().join(LOCK_FILE), "{}").unwrap();
+        assert_ne!(ProjectIndex::fingerprint(dir.path()).unwrap(), second);
+    }
+
+    #[tokio::test]
+    async fn saved_indexes_complete_prefixes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index.json");
+        assert_eq!(ProjectIndex::load(&path).await.unwrap(), None);
+
+        let index = ProjectIndex {
+            digest: "abc".to_string(),
+            transforms: vec![
+                Addr::parse("//pkg/build").unwrap(),
+                Addr::parse("//other/build").unwrap(),
+            ],
+            matrices: vec![Addr::parse("//pkg/matrix").unwrap()],
+            aliases: Vec::new(),
+        };
+        index.save(&path).await.unwrap();
+        let loaded = ProjectIndex::load(&path).await.unwrap().unwrap();
+        assert_eq!(loaded, index);
+        assert_eq!(loaded.complete("//pk"), vec!["//pkg/build", "//pkg/matrix"]);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(ProjectIndex::load(&path).await.unwrap(), None);
+    }
+}
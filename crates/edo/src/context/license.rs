@@ -0,0 +1,174 @@
+//! License reports of built artifacts.
+//!
+//! An artifact carries the code of every source staged into it or into the
+//! artifacts it depends on, so its licenses are theirs, see
+//! [`crate::source`] for how the licenses of a source are found.
+//! [`Context::license_report`] lists them per source and checks each one
+//! against the `licenses` rule of the [`Policy`](super::Policy), which
+//! [`Context::check_licenses`] enforces before an artifact is published.
+
+use super::{Addr, Context, ContextResult, error};
+use crate::source::{LicenseOrigin, NOASSERTION, declared_licenses, detect_licenses};
+use serde::Serialize;
+use snafu::{OptionExt, ensure};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+/// The licenses of one source, see [`LicenseReport`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SourceLicense {
+    /// Address of the source definition.
+    pub addr: Addr,
+    /// How its licenses were found.
+    pub origin: LicenseOrigin,
+    /// SPDX identifiers of its licenses, [`NOASSERTION`] when unknown.
+    pub licenses: BTreeSet<String>,
+    /// Transforms staging it.
+    pub used_by: Vec<Addr>,
+}
+
+/// A license the policy does not allow.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LicenseViolation {
+    /// Address of the source under the license.
+    pub source: Addr,
+    /// SPDX identifier of the license.
+    pub license: String,
+    /// Which rule rejected it.
+    pub reason: String,
+}
+
+impl fmt::Display for LicenseViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "license '{}' of {} is {}",
+            self.license, self.source, self.reason
+        )
+    }
+}
+
+/// The licenses of the artifact of a transform, see
+/// [`Context::license_report`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LicenseReport {
+    /// Address of the transform.
+    pub addr: Addr,
+    /// Id of its artifact.
+    pub id: String,
+    /// Every license of the artifact.
+    pub licenses: BTreeSet<String>,
+    /// The sources it is built from, in address order.
+    pub sources: Vec<SourceLicense>,
+    /// Licenses the policy does not allow.
+    pub violations: Vec<LicenseViolation>,
+}
+
+impl Context {
+    /// Lists the licenses of the sources the artifact of the transform at
+    /// `addr` is built from, including through its dependencies.
+    ///
+    /// Sources that do not declare a license are fetched, if they are not
+    /// cached yet, to be scanned.
+    pub async fn license_report(&self, addr: &Addr) -> ContextResult<LicenseReport> {
+        let addr = self.resolve_alias(addr);
+        let handle = self.get_handle();
+        let transform = handle
+            .get(&addr)
+            .context(error::NoTransformFoundSnafu { addr: addr.clone() })?;
+        let id = handle.unique_id(&addr, &transform).await?;
+
+        // Sources are found through the transforms staging them, not the
+        // environments they run in
+        let mut used: BTreeMap<Addr, Vec<Addr>> = BTreeMap::new();
+        let mut seen = BTreeSet::new();
+        let mut queue = VecDeque::from([addr.clone()]);
+        while let Some(next) = queue.pop_front() {
+            if !seen.insert(next.clone()) {
+                continue;
+            }
+            let transform = handle
+                .get(&next)
+                .context(error::NoTransformFoundSnafu { addr: next.clone() })?;
+            for source in self.get_element_sources(&next) {
+                used.entry(source).or_default().push(next.clone());
+            }
+            queue.extend(
+                transform
+                    .depends()
+                    .await?
+                    .iter()
+                    .map(|x| self.resolve_alias(x)),
+            );
+        }
+
+        let log = self.log.create("licenses").await?;
+        let mut report = LicenseReport {
+            addr,
+            id: id.to_string(),
+            licenses: BTreeSet::new(),
+            sources: Vec::new(),
+            violations: Vec::new(),
+        };
+        for (source, used_by) in used {
+            let node = self.sources.get(&source).map(|x| x.value().clone());
+            let declared = match node.as_ref() {
+                Some(node) => declared_licenses(node)?,
+                None => None,
+            };
+            let (origin, licenses) = match declared {
+                Some(licenses) => (LicenseOrigin::Declared, licenses),
+                None => {
+                    let mut detected = BTreeSet::new();
+                    if let Some(fetched) = self.get_source(&source).await? {
+                        let artifact = fetched.cache(&log, self.storage()).await?;
+                        detected = detect_licenses(self.storage(), &artifact).await?;
+                    }
+                    if detected.is_empty() {
+                        let unknown = BTreeSet::from([NOASSERTION.to_string()]);
+                        (LicenseOrigin::Unknown, unknown)
+                    } else {
+                        (LicenseOrigin::Detected, detected)
+                    }
+                }
+            };
+            for license in licenses.iter() {
+                if let Some(reason) = self.policy().license_violation(license) {
+                    report.violations.push(LicenseViolation {
+                        source: source.clone(),
+                        license: license.clone(),
+                        reason,
+                    });
+                }
+            }
+            report.licenses.extend(licenses.iter().cloned());
+            report.sources.push(SourceLicense {
+                addr: source,
+                origin,
+                licenses,
+                used_by,
+            });
+        }
+        Ok(report)
+    }
+
+    /// Reports the licenses of the artifact of the transform at `addr` like
+    /// [`Context::license_report`], failing when the policy does not allow
+    /// one of them.
+    pub async fn check_licenses(&self, addr: &Addr) -> ContextResult<LicenseReport> {
+        let report = self.license_report(addr).await?;
+        ensure!(
+            report.violations.is_empty(),
+            error::LicensePolicySnafu {
+                addr: report.addr.clone(),
+                violations: report
+                    .violations
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            }
+        );
+        Ok(report)
+    }
+}
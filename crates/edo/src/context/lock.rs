@@ -3,33 +3,82 @@
 //! A [`Lock`] captures the resolved dependency graph (digest + content map)
 //! so that subsequent builds can skip resolution when the project
 //! configuration has not changed. It is serialized as `edo.lock.json`.
+//!
+//! The lock also pins what every source resolved to the first time it was
+//! fetched, such as the commit a git branch pointed at. The [`SourceLock`]
+//! checks later fetches against those pins, so a source cannot change under
+//! the project until `edo update` drops them.
 
 use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use snafu::{ResultExt, ensure};
 
 use super::Node;
+use super::{Addr, ContextResult, error};
+use crate::util::{SchemaExtra, schema_version};
 
-use super::Addr;
+/// Schema version of the lock files this edo writes.
+pub const LOCK_VERSION: u64 = 1;
 
 /// A serializable lock file that records the digest of the project
 /// configuration and the resolved dependency nodes.
-#[derive(Default, Serialize, Deserialize)]
+///
+/// Lock files are read with [`Lock::from_slice`], which refuses those
+/// written by a newer edo in a format this one cannot read.
+#[derive(Serialize, Deserialize)]
 pub struct Lock {
+    #[serde(default)]
+    version: u64,
     digest: String,
     #[serde(rename = "refs")]
     content: BTreeMap<Addr, Node>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    sources: BTreeMap<Addr, SourcePin>,
+    // Fields a newer edo added without bumping the version, kept as they are
+    #[serde(flatten)]
+    extra: SchemaExtra,
+}
+
+impl Default for Lock {
+    fn default() -> Self {
+        Self::new(String::new())
+    }
 }
 
 impl Lock {
     /// Creates a new `Lock` with the given digest and empty content.
     pub fn new(digest: String) -> Self {
         Self {
+            version: LOCK_VERSION,
             digest,
             content: BTreeMap::new(),
+            sources: BTreeMap::new(),
+            extra: SchemaExtra::new(),
         }
     }
 
+    /// Parses a lock file, upgrading those an older edo wrote to
+    /// [`LOCK_VERSION`].
+    pub fn from_slice(bytes: &[u8]) -> ContextResult<Self> {
+        let document: Value = serde_json::from_slice(bytes).context(error::LockFormatSnafu)?;
+        let version = schema_version(&document);
+        ensure!(
+            version <= LOCK_VERSION,
+            error::LockVersionSnafu {
+                version,
+                supported: LOCK_VERSION,
+            }
+        );
+        let mut lock: Self = serde_json::from_value(document).context(error::LockFormatSnafu)?;
+        lock.version = LOCK_VERSION;
+        Ok(lock)
+    }
+
     /// Returns the digest string.
     pub fn digest(&self) -> &str {
         &self.digest
@@ -44,6 +93,95 @@ impl Lock {
     pub fn content_mut(&mut self) -> &mut BTreeMap<Addr, Node> {
         &mut self.content
     }
+
+    /// Returns the source pins, see [`SourceLock`].
+    pub fn sources(&self) -> &BTreeMap<Addr, SourcePin> {
+        &self.sources
+    }
+
+    /// Returns a mutable reference to the source pins.
+    pub fn sources_mut(&mut self) -> &mut BTreeMap<Addr, SourcePin> {
+        &mut self.sources
+    }
+}
+
+/// What a source resolved to when it was first fetched.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourcePin {
+    /// The unique id of the source, a changed definition changes it and
+    /// replaces the pin.
+    pub id: String,
+    /// The resolved digest, e.g. a git commit or a file hash.
+    pub digest: String,
+}
+
+/// The source pins of the loaded project, shared by every source of a
+/// context.
+///
+/// Pins missing from the lock file are added to it as sources are fetched.
+#[derive(Clone, Default)]
+pub struct SourceLock {
+    inner: Arc<Mutex<SourceLockInner>>,
+}
+
+#[derive(Default)]
+struct SourceLockInner {
+    path: Option<PathBuf>,
+    pins: BTreeMap<Addr, SourcePin>,
+}
+
+impl SourceLock {
+    /// Uses `pins`, recording new ones in the lock file at `path`.
+    pub fn load(&self, path: &Path, pins: &BTreeMap<Addr, SourcePin>) {
+        let mut inner = self.inner.lock();
+        inner.path = Some(path.to_path_buf());
+        inner.pins = pins.clone();
+    }
+
+    /// The current pins.
+    pub fn pins(&self) -> BTreeMap<Addr, SourcePin> {
+        self.inner.lock().pins.clone()
+    }
+
+    /// Checks that the source at `addr` with unique id `id` resolved to
+    /// `digest`, returning the pinned digest when it did not.
+    ///
+    /// A source without a pin for `id` is pinned to `digest`.
+    pub fn check(&self, addr: &Addr, id: &str, digest: &str) -> Result<(), String> {
+        let mut inner = self.inner.lock();
+        if let Some(pin) = inner.pins.get(addr)
+            && pin.id == id
+        {
+            if pin.digest == digest {
+                return Ok(());
+            }
+            return Err(pin.digest.clone());
+        }
+        inner.pins.insert(
+            addr.clone(),
+            SourcePin {
+                id: id.to_string(),
+                digest: digest.to_string(),
+            },
+        );
+        if let Some(path) = inner.path.as_ref()
+            && let Err(e) = Self::save(path, &inner.pins)
+        {
+            warn!(target: "project", "failed to pin {addr} in {}: {e}", path.display());
+        }
+        Ok(())
+    }
+
+    /// Rewrites the pins of the lock file at `path`, keeping the rest.
+    fn save(path: &Path, pins: &BTreeMap<Addr, SourcePin>) -> std::io::Result<()> {
+        let mut lock = match std::fs::read(path) {
+            Ok(bytes) => Lock::from_slice(&bytes).map_err(std::io::Error::other)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Lock::default(),
+            Err(e) => return Err(e),
+        };
+        lock.sources = pins.clone();
+        std::fs::write(path, serde_json::to_vec_pretty(&lock)?)
+    }
 }
 
 #[cfg(test)]
@@ -85,6 +223,34 @@ mod tests {
         assert!(restored.content().contains_key(&a));
     }
 
+    #[test]
+    fn locks_without_pins_still_parse() {
+        let lock: Lock = serde_json::from_str(r#"{"digest":"d","refs":{}}"#).unwrap();
+        assert!(lock.sources().is_empty());
+        let json = serde_json::to_string(&lock).unwrap();
+        assert!(!json.contains("sources"), "unexpected sources in {json}");
+    }
+
+    #[test]
+    fn source_lock_pins_then_detects_drift() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("edo.lock.json");
+        std::fs::write(&path, r#"{"digest":"d","refs":{}}"#).unwrap();
+        let lock = SourceLock::default();
+        lock.load(&path, &BTreeMap::new());
+        let a = addr("//p/src");
+
+        assert_eq!(lock.check(&a, "repo@main", "c1"), Ok(()));
+        assert_eq!(lock.check(&a, "repo@main", "c1"), Ok(()));
+        assert_eq!(lock.check(&a, "repo@main", "c2"), Err("c1".to_string()));
+        // A changed definition replaces the pin
+        assert_eq!(lock.check(&a, "repo@v2", "c2"), Ok(()));
+
+        let saved: Lock = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(saved.digest(), "d");
+        assert_eq!(saved.sources()[&a].digest, "c2");
+    }
+
     #[test]
     fn serde_rename_refs_not_content() {
         let mut lock = Lock::new("x".to_string());
@@ -98,4 +264,20 @@ mod tests {
             "unexpected \"content:\" in {json}"
         );
     }
+
+    #[test]
+    fn locks_of_a_newer_edo_are_refused_and_new_fields_kept() {
+        let err = Lock::from_slice(br#"{"version":2,"digest":"d","refs":{}}"#)
+            .err()
+            .unwrap();
+        assert!(
+            matches!(err, error::ContextError::LockVersion { version: 2, .. }),
+            "{err}"
+        );
+
+        let lock = Lock::from_slice(br#"{"digest":"d","refs":{},"plugins":{"a":1}}"#).unwrap();
+        let json = serde_json::to_string(&lock).unwrap();
+        assert!(json.contains("\"version\":1"), "{json}");
+        assert!(json.contains("\"plugins\":{\"a\":1}"), "{json}");
+    }
 }
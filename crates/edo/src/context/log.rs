@@ -3,18 +3,105 @@
 //! [`Log`] wraps a file handle that captures output for a single build task.
 //! It implements [`std::io::Write`] and [`IntoRawFd`](std::os::fd::IntoRawFd)
 //! so it can be used as both a Rust writer and a raw file descriptor for
-//! child processes.
+//! child processes. Command output is [`stream`](Log::stream)ed into it line
+//! by line, keeping the most recent lines in memory for failure reports and
+//! rotating the file once it grows past the configured [`LogSettings`].
+//! Each line is also published as an [`EventKind::Output`] event.
 
-use super::LogManager;
-use super::{ContextResult as Result, error};
+use super::{Config, ContextResult as Result, error};
+use super::{EventKind, LogManager};
+use crate::util::parse_size;
+use chrono::Local;
 use parking_lot::Mutex;
-use snafu::ResultExt;
-use std::fs::{File, OpenOptions};
-use std::io::Write;
+use snafu::{OptionExt, ResultExt};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions, remove_file, rename};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::os::fd::IntoRawFd;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+/// Number of streamed lines a [`Log`] keeps for [`Log::tail`].
+pub const TAIL_LINES: usize = 100;
+
+/// Prefix of control lines a process prints to report its progress to edo,
+/// e.g. `::edo::step 2`. They are recorded as [`markers`](Log::marker)
+/// instead of being written to the log.
+pub const MARKER_PREFIX: &str = "::edo::";
+
+/// How streamed command output is written to task logs.
+///
+/// Read from the `[log]` table of the configuration:
+///
+/// ```toml
+/// [log]
+/// timestamps = true      # prefix every streamed line with the time
+/// strip_ansi = false     # drop terminal escape sequences
+/// max_size   = "64MiB"   # rotate a log once it grows past this size
+/// keep       = 3         # rotated files kept as <name>.log.1 .. .3
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogSettings {
+    /// Prefix streamed lines with a wall clock timestamp.
+    pub timestamps: bool,
+    /// Remove ANSI escape sequences from streamed lines.
+    pub strip_ansi: bool,
+    /// Size in bytes after which a log is rotated, unlimited when unset.
+    pub max_size: Option<u64>,
+    /// Number of rotated files kept next to the live log.
+    pub keep: usize,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            timestamps: true,
+            strip_ansi: false,
+            max_size: None,
+            keep: 3,
+        }
+    }
+}
+
+impl LogSettings {
+    /// Reads the settings from the `[log]` table of `config`, if present.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut settings = Self::default();
+        let Some(node) = config.get("log") else {
+            return Ok(settings);
+        };
+        let field = |field: &str, type_: &str| error::FieldSnafu {
+            field: format!("log.{field}"),
+            type_: type_.to_string(),
+        };
+        if let Some(value) = node.get("timestamps") {
+            settings.timestamps = value.as_bool().context(field("timestamps", "boolean"))?;
+        }
+        if let Some(value) = node.get("strip_ansi") {
+            settings.strip_ansi = value.as_bool().context(field("strip_ansi", "boolean"))?;
+        }
+        if let Some(value) = node.get("max_size") {
+            let size = match (value.as_int(), value.as_string()) {
+                (Some(size), _) => u64::try_from(size).ok(),
+                (_, Some(size)) => parse_size(&size),
+                _ => None,
+            }
+            .filter(|x| *x > 0);
+            settings.max_size = Some(size.context(field(
+                "max_size",
+                "positive number of bytes, e.g. 67108864 or \"64MiB\"",
+            ))?);
+        }
+        if let Some(value) = node.get("keep") {
+            settings.keep = value
+                .as_int()
+                .and_then(|x| usize::try_from(x).ok())
+                .context(field("keep", "non-negative integer"))?;
+        }
+        Ok(settings)
+    }
+}
+
 /// A cloneable, thread-safe log file for a single build task.
 #[derive(Clone)]
 pub struct Log {
@@ -27,21 +114,31 @@ pub struct Inner {
     path: PathBuf,
     subject: String,
     file: File,
+    settings: LogSettings,
+    size: u64,
+    tail: VecDeque<String>,
+    markers: HashMap<String, String>,
 }
 
 impl Log {
     /// Creates a new log file at `path`, opening it in append mode.
     pub fn new<P: AsRef<Path>>(manager: &LogManager, path: P) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.as_ref())
+            .context(error::IoSnafu)?;
+        let size = file.metadata().context(error::IoSnafu)?.len();
         Ok(Self {
             manager: manager.clone(),
             inner: Arc::new(Mutex::new(Inner {
                 path: path.as_ref().to_path_buf(),
                 subject: "general".to_string(),
-                file: OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(path.as_ref())
-                    .context(error::IoSnafu)?,
+                file,
+                settings: manager.settings(),
+                size,
+                tail: VecDeque::with_capacity(TAIL_LINES),
+                markers: HashMap::new(),
             })),
         })
     }
@@ -69,21 +166,145 @@ impl Log {
 
     /// Writes a section header line to the log file.
     pub fn set_subject(&self, subject: &str) {
-        let _ = self
-            .inner
-            .lock()
-            .file
-            .write_fmt(format_args!("\n=== [{subject}] ===\n"));
-        self.inner.lock().subject = subject.to_string();
+        let mut lock = self.inner.lock();
+        let _ = lock.append(format!("\n=== [{subject}] ===\n").as_bytes());
+        lock.subject = subject.to_string();
     }
 
     /// Writes a dedicated action to the log file
     pub fn record(&self, action: &str, message: &str) -> Result<()> {
         let mut lock = self.inner.lock();
         let line = format!("\n> [{}]({action}): {message}\n", lock.subject.clone());
-        lock.file
-            .write_all(line.as_bytes())
-            .context(error::IoSnafu)?;
+        lock.append(line.as_bytes()).context(error::IoSnafu)?;
+        Ok(())
+    }
+
+    /// Copies `reader` into the log line by line until it is exhausted.
+    ///
+    /// Each line is timestamped and stripped of escape sequences according
+    /// to the [`LogSettings`], and remembered for [`tail`](Self::tail).
+    pub fn stream<R: Read>(&self, reader: R) -> std::io::Result<()> {
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                return Ok(());
+            }
+            let line = String::from_utf8_lossy(&buf);
+            self.write_line(line.trim_end_matches(['\n', '\r']))?;
+        }
+    }
+
+    /// Writes a single line of command output to the log.
+    ///
+    /// A [`MARKER_PREFIX`] control line, possibly following output that did
+    /// not end in a newline, is recorded as a marker instead.
+    pub fn write_line(&self, line: &str) -> std::io::Result<()> {
+        if let Some(position) = line.find(MARKER_PREFIX) {
+            let (line, control) = line.split_at(position);
+            let control = &control[MARKER_PREFIX.len()..];
+            let (key, value) = control.split_once(' ').unwrap_or((control, ""));
+            self.inner
+                .lock()
+                .markers
+                .insert(key.to_string(), value.trim().to_string());
+            if line.is_empty() {
+                return Ok(());
+            }
+            return self.write_line(line);
+        }
+        let mut lock = self.inner.lock();
+        let stripped = strip_ansi(line);
+        let text = if lock.settings.strip_ansi {
+            stripped.as_str()
+        } else {
+            line
+        };
+        let entry = if lock.settings.timestamps {
+            format!("[{}] {text}\n", Local::now().format("%H:%M:%S%.3f"))
+        } else {
+            format!("{text}\n")
+        };
+        lock.append(entry.as_bytes())?;
+        if lock.tail.len() == TAIL_LINES {
+            lock.tail.pop_front();
+        }
+        let events = self.manager.events();
+        if events.is_observed() {
+            let log = lock
+                .path
+                .file_name()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default();
+            lock.tail.push_back(stripped.clone());
+            drop(lock);
+            events.publish(EventKind::Output {
+                log,
+                line: stripped,
+            });
+            return Ok(());
+        }
+        lock.tail.push_back(stripped);
+        Ok(())
+    }
+
+    /// Returns up to the last [`TAIL_LINES`] streamed lines, oldest first,
+    /// without escape sequences.
+    pub fn tail(&self) -> Vec<String> {
+        self.inner.lock().tail.iter().cloned().collect()
+    }
+
+    /// Returns the value of the last `::edo::<key>` control line streamed.
+    pub fn marker(&self, key: &str) -> Option<String> {
+        self.inner.lock().markers.get(key).cloned()
+    }
+
+    /// Forgets every marker recorded so far.
+    pub fn clear_markers(&self) {
+        self.inner.lock().markers.clear();
+    }
+}
+
+impl Inner {
+    /// Appends `buf` to the live file, rotating it first when it would grow
+    /// past the configured maximum size.
+    fn append(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        if let Some(max_size) = self.settings.max_size
+            && self.size > 0
+            && self.size + buf.len() as u64 > max_size
+        {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(())
+    }
+
+    /// Shifts `<name>.1 .. <name>.<keep - 1>` up by one, moves the live file
+    /// to `<name>.1` and starts a new, empty live file.
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let rotated = |index: usize| {
+            let mut name = self.path.as_os_str().to_os_string();
+            name.push(format!(".{index}"));
+            PathBuf::from(name)
+        };
+        if self.settings.keep == 0 {
+            remove_file(&self.path)?;
+        } else {
+            for index in (1..self.settings.keep).rev() {
+                let from = rotated(index);
+                if from.exists() {
+                    rename(&from, rotated(index + 1))?;
+                }
+            }
+            rename(&self.path, rotated(1))?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
         Ok(())
     }
 }
@@ -92,7 +313,8 @@ impl Write for Log {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         // A log should always be receiving text data so we can operate on it as such
         let mut lock = self.inner.lock();
-        lock.file.write(buf)
+        lock.append(buf)?;
+        Ok(buf.len())
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
@@ -109,6 +331,43 @@ impl IntoRawFd for &Log {
     }
 }
 
+/// Removes ANSI escape sequences (colours, cursor movement, terminal titles)
+/// from `line`.
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters up to a final byte in `@`..=`~`
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: terminated by BEL or `ESC \`
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
 #[macro_export]
 macro_rules! record {
     ($log: ident, $action: literal, $($arg: tt)*) => {
@@ -118,7 +377,7 @@ macro_rules! record {
 
 #[cfg(test)]
 mod tests {
-    use super::Log;
+    use super::{Log, LogSettings, TAIL_LINES, strip_ansi};
     use crate::context::logmgr::test_support::shared_log_manager;
     use std::io::Write;
     use std::os::fd::{FromRawFd, IntoRawFd};
@@ -197,4 +456,67 @@ mod tests {
         // Wrap in a File so the descriptor is properly closed.
         let _ = unsafe { std::fs::File::from_raw_fd(fd) };
     }
+
+    #[test]
+    fn strip_ansi_removes_escape_sequences() {
+        assert_eq!(strip_ansi("\x1b[1;31merror\x1b[0m: boom"), "error: boom");
+        assert_eq!(strip_ansi("\x1b]0;title\x07plain"), "plain");
+        assert_eq!(strip_ansi("no escapes"), "no escapes");
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn stream_writes_lines_and_keeps_a_tail() {
+        let dir = TempDir::new().unwrap();
+        let log = make_log(&dir, "stream").await;
+        let output: String = (0..TAIL_LINES + 5)
+            .map(|x| format!("\x1b[32mline {x}\x1b[0m\n"))
+            .collect();
+        log.stream(output.as_bytes()).unwrap();
+        let tail = log.tail();
+        assert_eq!(tail.len(), TAIL_LINES);
+        assert_eq!(tail.first().unwrap(), "line 5");
+        assert_eq!(tail.last().unwrap(), &format!("line {}", TAIL_LINES + 4));
+        let contents = std::fs::read_to_string(log.path()).unwrap();
+        assert!(contents.contains("] \x1b[32mline 0"), "{contents:?}");
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn control_lines_become_markers() {
+        let dir = TempDir::new().unwrap();
+        let log = make_log(&dir, "markers").await;
+        log.stream("::edo::step 0\nbuilding\n::edo::step 1\nno newline::edo::exit 2\n".as_bytes())
+            .unwrap();
+        assert_eq!(log.marker("step").as_deref(), Some("1"));
+        assert_eq!(log.marker("exit").as_deref(), Some("2"));
+        assert_eq!(log.tail(), vec!["building", "no newline"]);
+        let contents = std::fs::read_to_string(log.path()).unwrap();
+        assert!(!contents.contains("::edo::"), "{contents:?}");
+        log.clear_markers();
+        assert!(log.marker("step").is_none());
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn oversized_logs_are_rotated() {
+        let dir = TempDir::new().unwrap();
+        let log = make_log(&dir, "rotate").await;
+        log.inner.lock().settings = LogSettings {
+            timestamps: false,
+            strip_ansi: false,
+            max_size: Some(16),
+            keep: 2,
+        };
+        for line in ["first line", "second line", "third line", "fourth line"] {
+            log.write_line(line).unwrap();
+        }
+        let read = |suffix: &str| {
+            std::fs::read_to_string(dir.path().join(format!("rotate.log{suffix}"))).unwrap()
+        };
+        assert_eq!(read(""), "fourth line\n");
+        assert_eq!(read(".1"), "third line\n");
+        assert_eq!(read(".2"), "second line\n");
+        assert!(!dir.path().join("rotate.log.3").exists());
+    }
 }
@@ -2,7 +2,8 @@
 //!
 //! [`LogManager`] owns the log directory, initializes the `tracing` subscriber
 //! with an indicatif progress layer, and creates per-task [`Log`] files.
-//! [`LogVerbosity`] controls the tracing filter level.
+//! [`LogVerbosity`] controls the tracing filter level. The manager also
+//! carries the run's [`EventBus`].
 //!
 //! The [`elapsed_subsec`], [`build_sub_unit`], and [`build`] free functions
 //! are progress-bar helpers and demo instrumented tasks used during
@@ -14,6 +15,7 @@ use std::{
     time::Duration,
 };
 
+use super::EventBus;
 use chrono::Local;
 use indicatif::ProgressState;
 use owo_colors::{OwoColorize, Stream};
@@ -39,7 +41,7 @@ use tracing_subscriber::{
 };
 
 pub use super::Log;
-use super::{ContextResult as Result, error};
+use super::{Config, ContextResult as Result, LogSettings, error};
 
 const DEBUG_ONLY: &[&str] = &[];
 const TRACE_ONLY: &[&str] = &[
@@ -57,7 +59,7 @@ const TRACE_ONLY: &[&str] = &[
 ];
 
 /// Controls the tracing verbosity level for the log manager.
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum LogVerbosity {
     /// Emit trace-level and above.
     Trace,
@@ -65,6 +67,11 @@ pub enum LogVerbosity {
     Debug,
     /// Emit info-level and above (default).
     Info,
+    /// Emit warnings and errors only, for commands whose output is parsed.
+    Quiet,
+    /// Write nothing to the console and never prompt, for frontends that
+    /// draw the whole screen from [`EventBus`] events.
+    Silent,
 }
 
 /// Manages the log directory and tracing subscriber for a build session.
@@ -81,11 +88,33 @@ impl LogManager {
         })
     }
 
+    /// Initializes the log directory at `path` without installing a tracing
+    /// subscriber, leaving the process's console output alone.
+    ///
+    /// For tests and embedders that set up tracing themselves or run several
+    /// contexts in one process.
+    pub async fn detached<P: AsRef<Path>>(path: P, verbosity: LogVerbosity) -> Result<Self> {
+        Ok(Self {
+            inner: Arc::new(Inner::open(path, verbosity).await?),
+        })
+    }
+
     /// Creates a new [`Log`] file for the given task `id`.
     pub async fn create(&self, id: &str) -> Result<Log> {
         self.inner.create(self, id).await
     }
 
+    /// Applies the `[log]` settings of `config` to every log created afterwards.
+    pub fn configure(&self, config: &Config) -> Result<()> {
+        *self.inner.settings.lock() = LogSettings::from_config(config)?;
+        Ok(())
+    }
+
+    /// Returns the settings new logs are created with.
+    pub fn settings(&self) -> LogSettings {
+        self.inner.settings.lock().clone()
+    }
+
     /// Acquires the global output lock, preventing interleaved console output.
     pub fn acquire(&self) -> MutexGuard<'_, ()> {
         self.inner.acquire()
@@ -95,11 +124,29 @@ impl LogManager {
     pub async fn clear(&self) -> Result<()> {
         self.inner.clear().await
     }
+
+    /// Returns the directory log files are written to.
+    pub fn path(&self) -> &Path {
+        &self.inner.path
+    }
+
+    /// Returns the verbosity the console output was set up with.
+    pub fn verbosity(&self) -> LogVerbosity {
+        self.inner.verbosity
+    }
+
+    /// Returns the bus build events are published on.
+    pub fn events(&self) -> &EventBus {
+        &self.inner.events
+    }
 }
 
 struct Inner {
     path: PathBuf,
     lock: Mutex<()>,
+    settings: Mutex<LogSettings>,
+    verbosity: LogVerbosity,
+    events: EventBus,
 }
 
 /// Formats the elapsed time as `<seconds>.<tenths>s` for progress bar display.
@@ -139,12 +186,7 @@ pub async fn build(unit: u64) {
 
 impl Inner {
     pub async fn init<P: AsRef<Path>>(path: P, verbosity: LogVerbosity) -> Result<Self> {
-        let logdir = path.as_ref();
-        if logdir.exists() {
-            // If the logdir already exists we want to clean it up, it should only be used for a single run
-            remove_dir_all(&logdir).await.context(error::IoSnafu)?;
-        }
-        create_dir_all(&logdir).await.context(error::IoSnafu)?;
+        let inner = Self::open(path, verbosity).await?;
         let indicatif_layer = IndicatifLayer::new()
             .with_progress_style(
             ProgressStyle::with_template(
@@ -182,6 +224,8 @@ impl Inner {
             LogVerbosity::Trace => LevelFilter::TRACE,
             LogVerbosity::Debug => LevelFilter::DEBUG,
             LogVerbosity::Info => LevelFilter::INFO,
+            LogVerbosity::Quiet => LevelFilter::WARN,
+            LogVerbosity::Silent => LevelFilter::OFF,
         };
         let mut filter = Targets::new().with_default(level);
         for entry in DEBUG_ONLY {
@@ -215,9 +259,22 @@ impl Inner {
             .with(indicatif_layer.with_filter(filter.clone()))
             .try_init()
             .context(error::LogSnafu)?;
+        Ok(inner)
+    }
+
+    pub async fn open<P: AsRef<Path>>(path: P, verbosity: LogVerbosity) -> Result<Self> {
+        let logdir = path.as_ref();
+        if logdir.exists() {
+            // If the logdir already exists we want to clean it up, it should only be used for a single run
+            remove_dir_all(&logdir).await.context(error::IoSnafu)?;
+        }
+        create_dir_all(&logdir).await.context(error::IoSnafu)?;
         Ok(Self {
             path: logdir.to_path_buf(),
             lock: Mutex::new(()),
+            settings: Mutex::new(LogSettings::default()),
+            verbosity,
+            events: EventBus::default(),
         })
     }
 
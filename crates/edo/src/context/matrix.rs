@@ -0,0 +1,215 @@
+//! Transform matrix expansion.
+//!
+//! A transform definition may carry a `matrix` table mapping axis names to a
+//! list of values (e.g. `matrix = { arch = ["x86_64", "aarch64"], profile =
+//! ["debug", "release"] }`). [`expand`] turns such a definition into one
+//! concrete transform per combination, each with its own address of the form
+//! `//pkg/build[arch=aarch64,profile=release]` and a `variant` table holding
+//! the selected values for that combination.
+
+use super::{Addr, ContextResult as Result, Node, error};
+use snafu::OptionExt;
+use std::collections::BTreeMap;
+
+/// Key holding the matrix axes on a transform definition.
+pub const MATRIX_KEY: &str = "matrix";
+/// Key holding the selected axis values on an expanded transform definition.
+pub const VARIANT_KEY: &str = "variant";
+
+/// Formats the address of a single matrix variant of `addr`.
+///
+/// Axis values are listed in key order so the same combination always yields
+/// the same address.
+pub fn variant_addr(addr: &Addr, variant: &BTreeMap<String, String>) -> Addr {
+    let id = addr.to_id();
+    let name = variant_name(id.rsplit('/').next().unwrap_or_default(), variant);
+    match addr.parent() {
+        Some(parent) => parent.join(&name),
+        None => Addr::default().join(&name),
+    }
+}
+
+fn variant_name(name: &str, variant: &BTreeMap<String, String>) -> String {
+    let selectors = variant
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{name}[{selectors}]")
+}
+
+fn matrix_axes(node: &Node) -> Result<Option<BTreeMap<String, Vec<String>>>> {
+    let Some(matrix) = node.get(MATRIX_KEY) else {
+        return Ok(None);
+    };
+    let table = matrix.as_table().context(error::FieldSnafu {
+        field: MATRIX_KEY,
+        type_: "table of string lists",
+    })?;
+    let mut axes = BTreeMap::new();
+    for (key, values) in table.iter() {
+        let list = values.as_list().context(error::FieldSnafu {
+            field: format!("{MATRIX_KEY}.{key}"),
+            type_: "list of strings",
+        })?;
+        let mut entries = Vec::new();
+        for value in list.iter() {
+            entries.push(value.as_string().context(error::FieldSnafu {
+                field: format!("{MATRIX_KEY}.{key}"),
+                type_: "list of strings",
+            })?);
+        }
+        if entries.is_empty() {
+            return error::FieldSnafu {
+                field: format!("{MATRIX_KEY}.{key}"),
+                type_: "non-empty list of strings",
+            }
+            .fail();
+        }
+        axes.insert(key.clone(), entries);
+    }
+    Ok(Some(axes))
+}
+
+fn combinations(axes: &BTreeMap<String, Vec<String>>) -> Vec<BTreeMap<String, String>> {
+    let mut result = vec![BTreeMap::new()];
+    for (key, values) in axes.iter() {
+        let mut next = Vec::with_capacity(result.len() * values.len());
+        for partial in result.iter() {
+            for value in values.iter() {
+                let mut entry = partial.clone();
+                entry.insert(key.clone(), value.clone());
+                next.push(entry);
+            }
+        }
+        result = next;
+    }
+    result
+}
+
+/// Expands a transform definition carrying a `matrix` table into its concrete
+/// variants.
+///
+/// Returns `None` when the definition has no matrix, otherwise the address and
+/// definition of every combination in a stable order.
+pub fn expand(addr: &Addr, node: &Node) -> Result<Option<Vec<(Addr, Node)>>> {
+    let Some(axes) = matrix_axes(node)? else {
+        return Ok(None);
+    };
+    let id = node.get_id().context(error::NodeSnafu)?;
+    let kind = node.get_kind().context(error::NodeSnafu)?;
+    let name = node.get_name().context(error::NodeSnafu)?;
+    let mut table = node.get_table().context(error::NodeSnafu)?;
+    table.remove(MATRIX_KEY);
+
+    let mut variants = Vec::new();
+    for variant in combinations(&axes) {
+        let mut vtable = table.clone();
+        vtable.insert(
+            VARIANT_KEY.to_string(),
+            Node::new_table(
+                variant
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Node::new_string(value.clone())))
+                    .collect(),
+            ),
+        );
+        variants.push((
+            variant_addr(addr, &variant),
+            Node::new_definition(&id, &kind, &variant_name(&name, &variant), vtable),
+        ));
+    }
+    Ok(Some(variants))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> Addr {
+        Addr::parse(s).expect("parse addr")
+    }
+
+    fn matrix_node(axes: &[(&str, &[&str])]) -> Node {
+        let matrix = axes
+            .iter()
+            .map(|(key, values)| {
+                (
+                    key.to_string(),
+                    Node::new_list(
+                        values
+                            .iter()
+                            .map(|v| Node::new_string(v.to_string()))
+                            .collect(),
+                    ),
+                )
+            })
+            .collect();
+        Node::new_definition(
+            "transform",
+            "script",
+            "build",
+            BTreeMap::from([
+                ("matrix".to_string(), Node::new_table(matrix)),
+                ("interpreter".to_string(), Node::new_string("bash".into())),
+            ]),
+        )
+    }
+
+    #[test]
+    fn expand_without_matrix_is_none() {
+        let node = Node::new_definition("transform", "script", "build", BTreeMap::new());
+        assert!(expand(&addr("//pkg/build"), &node).unwrap().is_none());
+    }
+
+    #[test]
+    fn expand_produces_cartesian_product() {
+        let node = matrix_node(&[
+            ("arch", &["x86_64", "aarch64"]),
+            ("profile", &["debug", "release"]),
+        ]);
+        let variants = expand(&addr("//pkg/build"), &node).unwrap().unwrap();
+        let addrs: Vec<String> = variants.iter().map(|(a, _)| a.to_string()).collect();
+        assert_eq!(
+            addrs,
+            vec![
+                "//pkg/build[arch=x86_64,profile=debug]",
+                "//pkg/build[arch=x86_64,profile=release]",
+                "//pkg/build[arch=aarch64,profile=debug]",
+                "//pkg/build[arch=aarch64,profile=release]",
+            ]
+        );
+    }
+
+    #[test]
+    fn expand_replaces_matrix_with_variant_table() {
+        let node = matrix_node(&[("arch", &["aarch64"])]);
+        let variants = expand(&addr("//pkg/build"), &node).unwrap().unwrap();
+        let (_, vnode) = &variants[0];
+        assert!(vnode.get("matrix").is_none());
+        assert!(vnode.get("interpreter").is_some());
+        let variant = vnode.get("variant").unwrap().as_table().unwrap();
+        assert_eq!(
+            variant.get("arch").unwrap().as_string().as_deref(),
+            Some("aarch64")
+        );
+        assert_eq!(vnode.get_name().as_deref(), Some("build[arch=aarch64]"));
+    }
+
+    #[test]
+    fn expand_rejects_empty_axis() {
+        let node = matrix_node(&[("arch", &[])]);
+        assert!(expand(&addr("//pkg/build"), &node).is_err());
+    }
+
+    #[test]
+    fn expand_rejects_non_table_matrix() {
+        let node = Node::new_definition(
+            "transform",
+            "script",
+            "build",
+            BTreeMap::from([("matrix".to_string(), Node::new_string("x".into()))]),
+        );
+        assert!(expand(&addr("//pkg/build"), &node).is_err());
+    }
+}
@@ -7,26 +7,46 @@
 //!
 //! Sub-modules provide supporting types:
 //! - Addressing — hierarchical [`Addr`] identifiers
+//! - Affected — change-based target selection ([`OwnershipIndex`])
+//! - Aliases — deprecated forwarding addresses ([`Alias`], [`Aliases`])
 //! - Configuration — user-level [`Config`] and the [`Definable`] traits
+//! - Credentials — helpers asked for the credentials of a host ([`Credentials`])
+//! - Describe — field descriptions of component kinds ([`KindSchema`])
 //! - Errors — [`ContextError`] and the [`ContextResult`] alias
+//! - Events — build progress published to subscribers ([`EventBus`], [`RunState`])
+//! - Format — canonical formatting of build files ([`format_toml`])
 //! - Handle — read-only [`Handle`] passed to transforms
+//! - Health — checks of caches, farms and vendors for `edo doctor` ([`HealthReport`])
+//! - Index — cached project addresses for shell completion ([`ProjectIndex`])
 //! - Lock — dependency lock file ([`Lock`])
 //! - Logging — per-task [`Log`] files and [`LogManager`] tracing setup
 //! - Node — generic data tree ([`Node`], [`Data`], [`Component`])
+//! - Notify — notifications on run completion or first failure ([`Notifications`])
+//! - Outline — positions of definitions and references in build files ([`ProjectOutline`])
+//! - Policy — allowed kinds, hosts and urls ([`Policy`])
+//! - Progress — progress bars for long operations ([`Progress`])
+//! - Runs — per-run summaries and their history ([`RunSummary`], [`RunHistory`])
 //! - Schema — TOML schema deserialization
+//! - Status — what a run would rebuild and why ([`WorkspaceStatus`])
+//! - Visibility — which packages may depend on a transform ([`Visibility`])
+//! - Why — which cache key inputs changed since the last run ([`RebuildCause`])
 //! - Builder — project loading and dependency resolution ([`Project`])
 
 use super::{
-    environment::Farm,
+    environment::{Farm, FaultyFarm},
     scheduler::Scheduler,
-    source::{Source, Vendor},
-    transform::Transform,
+    source::{Explanation, Source, Vendor},
+    transform::{OverrideTransform, Transform, declared_provides},
 };
 use crate::context::registry::Registry;
-use crate::storage::{Backend, LocalBackend, Storage};
+use crate::storage::{
+    Backend, FaultyBackend, LocalBackend, PROXY_KIND, ProxyBackend, STALE_TEMPORARY, SourceOrigin,
+    SourceRoute, Storage,
+};
+use crate::util::FaultPlan;
 use dashmap::DashMap;
-use snafu::ResultExt;
-use std::collections::{BTreeMap, HashMap};
+use snafu::{OptionExt, ResultExt, ensure};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::env::current_dir;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -34,41 +54,116 @@ use tokio::fs::create_dir_all;
 use tracing::Instrument;
 
 mod address;
+mod affected;
+mod alias;
 mod builder;
+mod bundle;
 mod config;
+mod credentials;
+mod describe;
+mod durations;
 pub mod error;
+mod events;
+mod export;
+mod format;
 mod handle;
+mod health;
+mod hook;
+mod index;
+mod license;
 mod lock;
 mod log;
 mod logmgr;
+mod matrix;
+mod nested;
 mod node;
+mod notify;
+mod outline;
+mod policy;
+mod progress;
 mod registry;
+mod runs;
+mod scaffold;
 mod schema;
+mod status;
+mod visibility;
+mod why;
 
 /// Re-exports [`Addr`] and [`Addressable`].
 pub use address::*;
+/// Re-exports [`OwnershipIndex`].
+pub use affected::*;
+/// Re-exports [`Alias`] and [`Aliases`].
+pub use alias::*;
 /// Re-exports [`Project`] and the `non_configurable` macros.
 pub use builder::*;
+/// Re-exports [`SourceBundle`] and [`BundledSource`].
+pub use bundle::*;
 /// Re-exports [`Config`], [`Definable`], [`DefinableNoContext`], and [`NonConfigurable`].
 pub use config::*;
+/// Re-exports [`Credentials`], [`Credential`] and [`CredentialHelper`].
+pub use credentials::*;
+/// Re-exports [`Describe`], [`KindSchema`], [`FieldSchema`], and [`FieldType`].
+pub use describe::*;
+/// Re-exports [`Durations`] and [`format_eta`].
+pub use durations::*;
 /// Re-exports [`ContextError`] at the module level.
 pub use error::ContextError;
+/// Re-exports [`Event`], [`EventKind`], [`EventBus`], and [`RunState`].
+pub use events::*;
+/// Re-exports [`TargetDescription`], [`SourceDescription`] and [`query_xml`].
+pub use export::*;
+/// Re-exports [`format_toml`] and [`format_build_file`].
+pub use format::*;
 /// Re-exports [`Handle`].
 pub use handle::*;
-/// Re-exports [`Lock`].
+/// Re-exports [`HealthCheck`], [`HealthReport`] and [`HealthStatus`].
+pub use health::*;
+/// Re-exports [`ProjectHook`] and [`ProjectDefinitions`].
+pub use hook::*;
+/// Re-exports [`ProjectIndex`].
+pub use index::*;
+/// Re-exports [`LicenseReport`], [`SourceLicense`] and [`LicenseViolation`].
+pub use license::*;
+/// Re-exports [`Lock`], [`SourceLock`] and [`SourcePin`].
 pub use lock::*;
 /// Re-exports [`Log`].
 pub use log::*;
 /// Re-exports [`LogManager`], [`LogVerbosity`], and logging helpers.
 pub use logmgr::*;
+/// Re-exports the transform matrix expansion helpers.
+pub use matrix::*;
+/// Re-exports [`NESTED_CACHE_ENV`] and [`parent_cache`].
+pub use nested::*;
 /// Re-exports [`Node`], [`Data`], [`Component`], [`FromNode`], and [`FromNodeNoContext`].
 pub use node::*;
+/// Re-exports [`Notifications`], [`Notifier`], [`Channel`], and [`Trigger`].
+pub use notify::*;
+/// Re-exports [`Outline`], [`ProjectOutline`], and [`Diagnostic`].
+pub use outline::*;
+/// Re-exports [`Policy`] and [`Rule`].
+pub use policy::*;
+/// Re-exports [`Progress`] and [`ProgressReader`].
+pub use progress::*;
+/// Re-exports [`RunSummary`], [`NodeSummary`], and [`RunHistory`].
+pub use runs::*;
+/// Re-exports [`TemplateProvider`], [`TemplateInfo`], and [`ScaffoldFile`].
+pub use scaffold::*;
+/// Re-exports [`WorkspaceStatus`] and [`StaleTransform`].
+pub use status::*;
+/// Re-exports [`Visibility`].
+pub use visibility::*;
+/// Re-exports [`RebuildCause`] and [`KeyChange`].
+pub use why::*;
 
 /// Convenience alias for `Result<T, ContextError>`.
 pub type ContextResult<T> = std::result::Result<T, error::ContextError>;
 
 type ArcMap<K, V> = Arc<DashMap<K, V>>;
 
+/// Address of the source cache a [`SourceBundle`] is registered as.
+const BUNDLE_ADDR: &str = "//edo-source-bundle";
+
 /// Default subdirectory name for edo's working data (`.edo`).
 const DEFAULT_PATH: &str = ".edo";
 
@@ -81,6 +176,8 @@ const DEFAULT_PATH: &str = ".edo";
 pub struct Context {
     /// Project directory
     project_dir: PathBuf,
+    /// Directory edo keeps its data in
+    data_dir: PathBuf,
     /// Loaded Shared Configuration
     config: Config,
     /// Storage Manager
@@ -95,6 +192,36 @@ pub struct Context {
     transforms: ArcMap<Addr, Transform>,
     /// Registered Farms
     farms: ArcMap<Addr, Farm>,
+    /// Faults injected into storage and environments, from `EDO_FAULTS`
+    faults: FaultPlan,
+    /// Summaries of previous runs
+    runs: RunHistory,
+    /// Where the [`ProjectIndex`] is cached
+    index: PathBuf,
+    /// Matrix groups mapped to their variant addresses
+    matrices: ArcMap<Addr, Vec<Addr>>,
+    /// Source definitions, instantiated on demand by [`Context::get_source`]
+    sources: ArcMap<Addr, Node>,
+    /// Vendor definitions, instantiated again by [`Context::health`]
+    vendors: ArcMap<Addr, Node>,
+    /// Transforms and environments mapped to the addresses of their sources
+    element_sources: ArcMap<Addr, Vec<Addr>>,
+    /// Deprecated addresses forwarding to their new definitions
+    aliases: Aliases,
+    /// Transforms mapped to the packages allowed to depend on them
+    visibility: ArcMap<Addr, Visibility>,
+    /// Transforms mapped to the capabilities their artifacts provide
+    provides: ArcMap<Addr, BTreeSet<String>>,
+    /// Transforms mapped to the kind of their definition
+    kinds: ArcMap<Addr, String>,
+    /// Explanations recorded by the last dependency resolution
+    explanations: ArcMap<Addr, Explanation>,
+    /// Restrictions from the user config, read before any project config is merged
+    policy: Policy,
+    /// Credential helpers from the user config, read before any project config is merged
+    credentials: Credentials,
+    /// What the project's sources are pinned to in `edo.lock.json`
+    source_lock: SourceLock,
     /// Command Line Arguments
     args: HashMap<String, String>,
 }
@@ -115,23 +242,33 @@ impl Context {
         ProjectPath: AsRef<Path>,
         ConfigPath: AsRef<Path>,
     {
-        let project_dir = current_dir().context(error::IoSnafu)?;
-        let path = if let Some(path) = path.as_ref() {
-            path.as_ref().to_path_buf()
-        } else {
-            project_dir.join(DEFAULT_PATH)
-        };
-        if !path.exists() {
-            create_dir_all(&path).await.context(error::IoSnafu)?;
-        }
+        let path = Self::data_path(path.as_ref().map(|x| x.as_ref())).await?;
         // Logs should be in a project specific folder, so they
         // do not clash with other project workspaces.
-        let log_path = path.join("logs");
-        let log = LogManager::init(&log_path, verbosity).await?;
-        // Load the configuration
+        let log = LogManager::init(path.join("logs"), verbosity).await?;
+        Self::init_with_log(Some(path), config, args, log).await
+    }
+
+    /// Initializes a new build context like [`Context::init`], logging
+    /// through the `log` of an earlier context.
+    ///
+    /// A process installs its log subscriber once, so this is how a long
+    /// running process such as `edo daemon` starts over with a fresh context.
+    pub async fn init_with_log<ProjectPath, ConfigPath>(
+        path: Option<ProjectPath>,
+        config: Option<ConfigPath>,
+        args: HashMap<String, String>,
+        log: LogManager,
+    ) -> ContextResult<Self>
+    where
+        ProjectPath: AsRef<Path>,
+        ConfigPath: AsRef<Path>,
+    {
+        let project_dir = current_dir().context(error::IoSnafu)?;
+        let path = Self::data_path(path.as_ref().map(|x| x.as_ref())).await?;
         let config = Config::load(config).await?;
         // Initialize the storage with the default local cache
-        let storage = Storage::init(&Backend::new(
+        let local = Backend::new(
             LocalBackend::new(
                 &Addr::parse("//edo-local-cache")?,
                 &Node::new_definition(
@@ -146,24 +283,90 @@ impl Context {
                 &config,
             )
             .await?,
-        ))
-        .await?;
+        );
+        Self::init_with_backend(&project_dir, &path, config, args, log, local).await
+    }
+
+    /// Initializes a new build context like [`Context::init_with_log`], for
+    /// the project in `project_dir` with its data in `path` and `local` as
+    /// its local cache.
+    ///
+    /// Lets tests and embedders run a context against an
+    /// [`InMemoryBackend`](crate::storage::InMemoryBackend) instead of the
+    /// cache on disk.
+    pub async fn init_with_backend(
+        project_dir: &Path,
+        path: &Path,
+        config: Config,
+        args: HashMap<String, String>,
+        log: LogManager,
+        local: Backend,
+    ) -> ContextResult<Self> {
+        let project_dir = project_dir.to_path_buf();
+        let path = Self::data_path(Some(path)).await?;
+        log.configure(&config)?;
+        let policy = Policy::from_config(&config)?;
+        let credentials = Credentials::from_config(&config)?;
+        let faults = FaultPlan::from_env()?;
+        let storage = Storage::init(&FaultyBackend::wrap("local", local, &faults)).await?;
+        // Inside a transform, read what the parent built without writing to it
+        if let Some(parent) = parent_cache() {
+            let backend = nested::open_parent(&parent, &path.join("storage"), &config).await?;
+            storage.set_parent(&backend).await;
+        }
+        // Layers left half written by processes that died are never finished
+        match storage.sweep_stale(STALE_TEMPORARY).await {
+            Ok(0) => {}
+            Ok(count) => info!(target: "context", "removed {count} stale temporary blobs"),
+            Err(e) => warn!(target: "context", "failed to remove stale temporary blobs: {e}"),
+        }
 
         // Create the initial context
         let ctx = Context {
             project_dir: project_dir.clone(),
+            data_dir: path.clone(),
             config: config.clone(),
             args,
             log: log.clone(),
             storage,
-            registry: Registry::default(),
+            registry: Registry::builtin(),
             scheduler: Scheduler::new(&path.join("env"), &config).await?,
             farms: Arc::new(DashMap::new()),
+            faults,
+            runs: RunHistory::new(path.join("runs")),
+            index: path.join("index.json"),
             transforms: Arc::new(DashMap::new()),
+            matrices: Arc::new(DashMap::new()),
+            sources: Arc::new(DashMap::new()),
+            vendors: Arc::new(DashMap::new()),
+            element_sources: Arc::new(DashMap::new()),
+            aliases: Aliases::default(),
+            visibility: Arc::new(DashMap::new()),
+            provides: Arc::new(DashMap::new()),
+            kinds: Arc::new(DashMap::new()),
+            explanations: Arc::new(DashMap::new()),
+            policy,
+            credentials,
+            source_lock: SourceLock::default(),
         };
+        // Layers are written to the local cache, keep an eye on its space
+        ctx.scheduler.watch_disk(&path.join("storage"));
         Ok(ctx.clone())
     }
 
+    /// The directory edo keeps its data in, `path` or `.edo` in the current
+    /// directory, created if missing.
+    async fn data_path(path: Option<&Path>) -> ContextResult<PathBuf> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => current_dir().context(error::IoSnafu)?.join(DEFAULT_PATH),
+        };
+        if !path.exists() {
+            create_dir_all(&path).await.context(error::IoSnafu)?;
+        }
+        Ok(path)
+    }
+
     /// Adds any project found config nodes to the config
     pub fn add_config(&self, config: &BTreeMap<String, Node>) {
         self.config.merge(config);
@@ -176,6 +379,31 @@ impl Context {
         Ok(())
     }
 
+    /// Loads the project like [`Context::load_project`], but always re-resolves
+    /// dependencies so that [`Context::explanations`] is populated.
+    pub async fn refresh_project(&self) -> ContextResult<()> {
+        Project::refresh(&self.project_dir, self).await?;
+        Ok(())
+    }
+
+    /// Records how a dependency was resolved.
+    pub fn add_explanation(&self, explanation: Explanation) {
+        self.explanations
+            .insert(explanation.addr.clone(), explanation);
+    }
+
+    /// Returns the explanations recorded by the last dependency resolution,
+    /// ordered by address.
+    pub fn explanations(&self) -> Vec<Explanation> {
+        let mut explanations: Vec<Explanation> = self
+            .explanations
+            .iter()
+            .map(|x| x.value().clone())
+            .collect();
+        explanations.sort_by(|x, y| x.addr.cmp(&y.addr));
+        explanations
+    }
+
     /// Returns the registry you can add new implementations to
     pub fn registry(&self) -> &Registry {
         &self.registry
@@ -197,6 +425,24 @@ impl Context {
                 .collect(),
             self.args.clone(),
         )
+        .with_aliases(&self.aliases)
+        .with_local_cache(&self.data_dir.join("storage"))
+        .with_provides(
+            self.provides
+                .iter()
+                .map(|x| (x.key().clone(), x.value().clone()))
+                .collect(),
+        )
+    }
+
+    /// Returns the directory the project was loaded from.
+    pub fn project_dir(&self) -> &Path {
+        &self.project_dir
+    }
+
+    /// Returns the directory edo keeps its data in, `.edo` or `--storage`.
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
     }
 
     /// Returns a reference to the loaded configuration.
@@ -204,6 +450,21 @@ impl Context {
         &self.config
     }
 
+    /// Returns the policy sources, vendors and caches are checked against.
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    /// Returns the credential helpers sources, vendors and caches ask for secrets.
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    /// Returns the source pins sources are checked against when fetched.
+    pub fn source_lock(&self) -> &SourceLock {
+        &self.source_lock
+    }
+
     /// Returns a reference to the storage manager.
     pub fn storage(&self) -> &Storage {
         &self.storage
@@ -214,6 +475,16 @@ impl Context {
         &self.log
     }
 
+    /// Returns the bus build events are published on, see [`EventBus`].
+    pub fn events(&self) -> &EventBus {
+        self.log.events()
+    }
+
+    /// Returns the history of recorded run summaries.
+    pub fn runs(&self) -> &RunHistory {
+        &self.runs
+    }
+
     /// Returns a reference to the execution scheduler.
     pub fn scheduler(&self) -> &Scheduler {
         &self.scheduler
@@ -221,14 +492,187 @@ impl Context {
 
     /// Prints all registered transform addresses to stdout.
     pub fn print_transforms(&self) {
-        for addr in self.transforms.iter() {
-            println!("{}", addr.key());
+        for line in self.transform_listing() {
+            println!("{line}");
+        }
+    }
+
+    /// The lines [`Context::print_transforms`] prints: transforms, then
+    /// matrix groups with their variant count, then deprecated aliases.
+    pub fn transform_listing(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .transforms
+            .iter()
+            .map(|x| x.key().to_string())
+            .collect();
+        for group in self.matrices.iter() {
+            lines.push(format!(
+                "{} ({} variants)",
+                group.key(),
+                group.value().len()
+            ));
         }
+        for (addr, alias) in self.aliases.list() {
+            lines.push(format!("{addr} -> {} (deprecated)", alias.target()));
+        }
+        lines
+    }
+
+    /// Returns the addresses of every transform, in address order.
+    pub fn transform_addrs(&self) -> Vec<Addr> {
+        let mut addrs: Vec<Addr> = self.transforms.iter().map(|x| x.key().clone()).collect();
+        addrs.sort();
+        addrs
+    }
+
+    /// Returns the addresses the project defines, loading the project only
+    /// when its `edo.toml` files or lock file changed since the index was
+    /// last cached.
+    pub async fn project_index(&self) -> ContextResult<ProjectIndex> {
+        let digest = ProjectIndex::fingerprint(&self.project_dir)?;
+        if let Some(index) = ProjectIndex::load(&self.index).await?
+            && index.digest == digest
+        {
+            return Ok(index);
+        }
+        self.load_project(true).await?;
+        let index = ProjectIndex::from_context(self, digest);
+        if let Err(e) = index.save(&self.index).await {
+            warn!(target: "context", "failed to cache the project index: {e}");
+        }
+        Ok(index)
+    }
+
+    /// Records that `addr` is a deprecated alias for another address.
+    pub fn add_alias(&self, addr: &Addr, alias: Alias) {
+        self.aliases.insert(addr, alias);
+    }
+
+    /// Returns the aliases declared by the project.
+    pub fn aliases(&self) -> &Aliases {
+        &self.aliases
+    }
+
+    /// Follows `addr` through any aliases to the address of its definition,
+    /// warning once per deprecated alias used.
+    pub fn resolve_alias(&self, addr: &Addr) -> Addr {
+        self.aliases.resolve(addr)
+    }
+
+    /// Records a matrix group so that building `addr` builds every variant in `members`.
+    pub fn add_matrix(&self, addr: &Addr, members: &[Addr]) {
+        self.matrices.insert(addr.clone(), members.to_vec());
+    }
+
+    /// Returns the variant addresses of the matrix group at `addr`, if any.
+    pub fn get_matrix(&self, addr: &Addr) -> Option<Vec<Addr>> {
+        self.matrices
+            .get(&self.resolve_alias(addr))
+            .map(|x| x.value().clone())
+    }
+
+    /// Restricts which packages may depend on the transform at `addr`.
+    pub fn add_visibility(&self, addr: &Addr, visibility: &Visibility) {
+        self.visibility.insert(addr.clone(), visibility.clone());
+    }
+
+    /// Returns the visibility of the transform at `addr`, public unless restricted.
+    pub fn get_visibility(&self, addr: &Addr) -> Visibility {
+        self.visibility
+            .get(&self.resolve_alias(addr))
+            .map(|x| x.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// Records the definition of the source at `addr` so it can be looked up
+    /// outside of the transforms that use it.
+    pub fn add_source_definition(&self, addr: &Addr, node: &Node) {
+        self.sources.insert(addr.clone(), node.clone());
+    }
+
+    /// Records the definition of the vendor at `addr`, vendors themselves
+    /// only live while dependencies are resolved.
+    pub fn add_vendor_definition(&self, addr: &Addr, node: &Node) {
+        self.vendors.insert(addr.clone(), node.clone());
+    }
+
+    /// Checks every cache, environment farm and vendor the project uses,
+    /// leaving out those with nothing to check.
+    ///
+    /// Vendors are created again for the check, a vendor that cannot be
+    /// created is reported rather than failing.
+    pub async fn health(&self) -> Vec<HealthReport> {
+        let mut reports = self.storage.health().await;
+        let mut farms: Vec<(Addr, Farm)> = self
+            .farms
+            .iter()
+            .map(|x| (x.key().clone(), x.value().clone()))
+            .collect();
+        farms.sort_by(|a, b| a.0.cmp(&b.0));
+        for (addr, farm) in farms {
+            reports.push(HealthReport {
+                subject: format!("environment {addr}"),
+                checks: farm.health().await,
+            });
+        }
+        let mut vendors: Vec<(Addr, Node)> = self
+            .vendors
+            .iter()
+            .map(|x| (x.key().clone(), x.value().clone()))
+            .collect();
+        vendors.sort_by(|a, b| a.0.cmp(&b.0));
+        for (addr, node) in vendors {
+            let checks = match self.add_vendor(&addr, &node).await {
+                Ok(vendor) => vendor.health().await,
+                Err(e) => vec![HealthCheck::error(
+                    "connection",
+                    e.to_string(),
+                    "check the vendor's url and that you are logged in to its registry",
+                )],
+            };
+            reports.push(HealthReport {
+                subject: format!("vendor {addr}"),
+                checks,
+            });
+        }
+        reports.retain(|x| !x.checks.is_empty());
+        reports
+    }
+
+    /// Returns the addresses of every source definition, in address order.
+    pub fn source_addrs(&self) -> Vec<Addr> {
+        let mut addrs: Vec<Addr> = self.sources.iter().map(|x| x.key().clone()).collect();
+        addrs.sort();
+        addrs
+    }
+
+    /// Creates the source defined at `addr`, if any.
+    pub async fn get_source(&self, addr: &Addr) -> ContextResult<Option<Source>> {
+        let addr = self.resolve_alias(addr);
+        let Some(node) = self.sources.get(&addr).map(|x| x.value().clone()) else {
+            return Ok(None);
+        };
+        Ok(Some(self.add_source(&addr, &node).await?))
+    }
+
+    /// Records the addresses of the sources used by the transform or environment at `addr`.
+    pub fn add_element_sources(&self, addr: &Addr, sources: &[Addr]) {
+        self.element_sources.insert(addr.clone(), sources.to_vec());
+    }
+
+    /// Returns the addresses of the sources used by the transform or environment at `addr`.
+    pub fn get_element_sources(&self, addr: &Addr) -> Vec<Addr> {
+        self.element_sources
+            .get(&self.resolve_alias(addr))
+            .map(|x| x.value().clone())
+            .unwrap_or_default()
     }
 
     /// Returns the transform registered at the given address, if any.
     pub fn get_transform(&self, addr: &Addr) -> Option<Transform> {
-        self.transforms.get(addr).map(|x| x.value().clone())
+        self.transforms
+            .get(&self.resolve_alias(addr))
+            .map(|x| x.value().clone())
     }
 
     /// Registers a storage cache backend, routing it to build, output, or source cache
@@ -239,28 +683,75 @@ impl Context {
             component = "context",
             "adding a storage backend {addr}"
         );
-        let kind = node.get_kind().unwrap();
-        let backend = if kind == "local" || kind == "edo:local" {
-            Backend::new(LocalBackend::new(addr, node, self.config()).await?)
+        let backend = self.create_cache(addr, node).await?;
+        self.register_cache(addr, node, backend).await
+    }
+
+    // Create the backend of a cache definition without registering it
+    async fn create_cache(&self, addr: &Addr, node: &Node) -> ContextResult<Backend> {
+        self.policy.check_backend(addr, node)?;
+        let addr_s = addr.to_string();
+        if node.get_kind().as_deref() == Some(PROXY_KIND) {
+            ensure!(
+                addr_s != "//edo-build-cache" && addr_s != "//edo-output-cache",
+                crate::storage::error::SettingSnafu {
+                    key: "kind",
+                    reason: "a proxy can only be a source cache",
+                }
+            );
+            let cache = ProxyBackend::cache_node(node)?;
+            self.policy.check_backend(addr, &cache)?;
+            Ok(ProxyBackend::wrap(self.create_backend(addr, &cache).await?))
         } else {
-            self.registry().backend(addr, node, self).await?
-        };
+            self.create_backend(addr, node).await
+        }
+    }
+
+    // Register a backend created by `create_cache` in the storage slot its
+    // address names, source caches take priority in the order they are
+    // registered
+    async fn register_cache(
+        &self,
+        addr: &Addr,
+        node: &Node,
+        backend: Backend,
+    ) -> ContextResult<()> {
         let addr_s = addr.to_string();
         if addr_s == "//edo-build-cache" {
             // This is a build cache so add it
+            let backend = FaultyBackend::wrap("build", backend, &self.faults);
             self.storage().set_build(&backend).await;
         } else if addr_s == "//edo-output-cache" {
             // This is an output cache so add it
+            let backend = FaultyBackend::wrap("output", backend, &self.faults);
             self.storage().set_output(&backend).await;
         } else {
             // This is a source cache
+            let route = SourceRoute::from_node(node)?;
+            let backend = FaultyBackend::wrap("source", backend, &self.faults);
             self.storage()
                 .add_source_cache(addr_s.as_str(), &backend)
                 .await;
+            if let Some(route) = route {
+                self.storage()
+                    .route_source_cache(addr_s.as_str(), &route)
+                    .await;
+            }
         }
         Ok(())
     }
 
+    async fn create_backend(&self, addr: &Addr, node: &Node) -> ContextResult<Backend> {
+        let kind = node.get_kind().unwrap();
+        if kind == "local" || kind == "edo:local" {
+            Ok(Backend::new(
+                LocalBackend::new(addr, node, self.config()).await?,
+            ))
+        } else {
+            Ok(self.registry().backend(addr, node, self).await?)
+        }
+    }
+
     /// Creates and registers a transform from the given node using the appropriate plugin.
     pub async fn add_transform(&self, addr: &Addr, node: &Node) -> ContextResult<()> {
         debug!(
@@ -268,18 +759,60 @@ impl Context {
             component = "context",
             "adding a transform {addr}"
         );
+        let provides = declared_provides(node)?;
         self.transforms.insert(
             addr.clone(),
             self.registry().transform(addr, node, self).await?,
         );
+        if !provides.is_empty() {
+            self.provides.insert(addr.clone(), provides);
+        }
+        if let Some(kind) = node.get_kind() {
+            self.kinds.insert(addr.clone(), kind);
+        }
         Ok(())
     }
 
+    /// Replaces the transform at `addr` with the directory or tarball at
+    /// `path` for the rest of this context's life, so dependents stage it
+    /// instead of building the transform. Nothing is uploaded to the build
+    /// cache afterwards, see [`Storage::inject`].
+    pub async fn add_override(&self, addr: &Addr, path: &Path) -> ContextResult<()> {
+        let addr = self.resolve_alias(addr);
+        let transform = self
+            .get_transform(&addr)
+            .context(error::NoTransformFoundSnafu { addr: addr.clone() })?;
+        let artifact = self.storage.inject(&addr.to_id(), path).await?;
+        warn!(
+            target: "context",
+            "{addr} is overridden by {}, artifacts built in this run will not be uploaded",
+            path.display()
+        );
+        self.transforms.insert(
+            addr,
+            Transform::new(OverrideTransform::new(&transform, &artifact)),
+        );
+        Ok(())
+    }
+
+    /// Returns the transforms whose artifacts provide `capability`, in
+    /// address order.
+    pub fn find_providing(&self, capability: &str) -> Vec<Addr> {
+        let mut addrs: Vec<Addr> = self
+            .provides
+            .iter()
+            .filter(|x| x.value().contains(capability))
+            .map(|x| x.key().clone())
+            .collect();
+        addrs.sort();
+        addrs
+    }
+
     /// Removes stale local storage entries for all registered transforms.
     pub async fn prune(&self) -> ContextResult<()> {
         let handle = self.get_handle();
         for transform in self.transforms.iter() {
-            let id = transform.get_unique_id(&handle).await?;
+            let id = handle.unique_id(transform.key(), transform.value()).await?;
             self.storage().prune_local(&id).await?;
         }
         Ok(())
@@ -287,7 +820,9 @@ impl Context {
 
     /// Returns the environment farm registered at the given address, if any.
     pub fn get_farm(&self, addr: &Addr) -> Option<Farm> {
-        self.farms.get(addr).map(|x| x.value().clone())
+        self.farms
+            .get(&self.resolve_alias(addr))
+            .map(|x| x.value().clone())
     }
 
     /// Creates and registers an environment farm from the given node using the appropriate plugin.
@@ -298,8 +833,9 @@ impl Context {
             "adding a farm {addr}"
         );
         // If we get here use the core plugin
+        let farm = self.registry().farm(addr, node, self).await?;
         self.farms
-            .insert(addr.clone(), self.registry().farm(addr, node, self).await?);
+            .insert(addr.clone(), FaultyFarm::wrap(farm, &self.faults));
         Ok(())
     }
 
@@ -310,12 +846,101 @@ impl Context {
             component = "context",
             "adding a source {addr}"
         );
+        self.policy.check_source(addr, node)?;
         let result = self.registry().source(addr, node, self).await?;
-        Ok(result)
+        // Sources are created for the element using them, pin them under
+        // their own address
+        let pin = self
+            .sources
+            .iter()
+            .find(|x| x.value().same(node))
+            .map(|x| x.key().clone())
+            .unwrap_or_else(|| addr.clone());
+        Ok(result
+            .with_origin(SourceOrigin::from_node(addr, node))
+            .pinned(&pin, &self.source_lock))
+    }
+
+    /// Copies every source of the project fetched from elsewhere into the
+    /// bundle in `dir` and writes its index, see [`SourceBundle`].
+    ///
+    /// Sources read from the project tree are left out, as are artifacts of
+    /// sources no longer defined.
+    pub async fn bundle_sources(&self, dir: &Path) -> ContextResult<SourceBundle> {
+        let backend = self.bundle_backend(dir).await?;
+        let log = self.log.create("vendor").await?;
+        let mut definitions: Vec<(Addr, Node)> = self
+            .sources
+            .iter()
+            .map(|x| (x.key().clone(), x.value().clone()))
+            .collect();
+        definitions.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut bundle = SourceBundle::default();
+        for (addr, node) in definitions {
+            let source = self.add_source(&addr, &node).await?;
+            if !source.needs_network() {
+                continue;
+            }
+            let artifact = source.cache(&log, self.storage()).await?;
+            let id = artifact.config().id();
+            self.storage().export(id, &backend).await?;
+            let origin = SourceOrigin::from_node(&addr, &node);
+            bundle.sources.insert(
+                addr,
+                BundledSource {
+                    id: id.to_string(),
+                    kind: origin.kind,
+                    url: origin.url,
+                },
+            );
+        }
+        let kept: Vec<String> = bundle.sources.values().map(|x| x.id.clone()).collect();
+        for id in backend.list().await? {
+            if !kept.contains(&id.to_string()) {
+                backend.del(&id).await?;
+            }
+        }
+        bundle.save(dir).await?;
+        Ok(bundle)
+    }
+
+    /// Consults the source bundle of the project at `project`, if it has
+    /// one, before any other source cache.
+    pub async fn use_bundle(&self, project: &Path) -> ContextResult<()> {
+        let dir = SourceBundle::path(self.config(), project);
+        if SourceBundle::load(&dir).await?.is_none() {
+            return Ok(());
+        }
+        debug!(target: "project", "using the source bundle in {}", dir.display());
+        let backend = self.bundle_backend(&dir).await?;
+        self.storage()
+            .add_source_cache_front(
+                BUNDLE_ADDR,
+                &FaultyBackend::wrap("source", backend, &self.faults),
+            )
+            .await;
+        Ok(())
+    }
+
+    async fn bundle_backend(&self, dir: &Path) -> ContextResult<Backend> {
+        self.create_backend(
+            &Addr::parse(BUNDLE_ADDR)?,
+            &Node::new_definition(
+                "storage",
+                "local",
+                "edo-source-bundle",
+                BTreeMap::from([(
+                    "path".to_string(),
+                    Node::new_string(dir.to_string_lossy().to_string()),
+                )]),
+            ),
+        )
+        .await
     }
 
     /// Creates a dependency vendor from the given node using the appropriate plugin.
     pub async fn add_vendor(&self, addr: &Addr, node: &Node) -> ContextResult<Vendor> {
+        self.policy.check_vendor(addr, node)?;
         let result = self.registry().vendor(addr, node, self).await?;
         Ok(result)
     }
@@ -343,9 +968,142 @@ impl Context {
     }
 
     /// Sets up environments and executes the build for the given transform address.
+    ///
+    /// When `addr` names a matrix group rather than a single transform, every
+    /// variant in the group is built in turn.
+    ///
+    /// A [`RunSummary`] is written to the run history whether or not the
+    /// build succeeds, along with the external accesses recorded since the
+    /// context was created, see [`RunHistory::save_audit`]. The durations of
+    /// the transforms it built are recorded to estimate later runs, see
+    /// [`Durations`].
     pub async fn run(&self, addr: &Addr) -> ContextResult<()> {
+        let addr = self.resolve_alias(addr);
+        let targets = if !self.transforms.contains_key(&addr)
+            && let Some(members) = self.get_matrix(&addr)
+        {
+            members
+        } else {
+            vec![addr]
+        };
+        self.run_all(&targets).await
+    }
+
+    /// Builds every transform in `targets` in turn, recording them as a single run.
+    pub async fn run_all(&self, targets: &[Addr]) -> ContextResult<()> {
+        let targets: Vec<Addr> = targets.iter().map(|x| self.resolve_alias(x)).collect();
+        let notifications = Notifications::from_config(&self.config)?;
+        let mut summary = RunSummary::start(&targets);
+        let watch = notifications.watch(self.events(), &summary);
+        self.events().publish(EventKind::RunStarted {
+            targets: targets.clone(),
+        });
+        let result = self.run_targets(&targets, &mut summary).await;
+        self.sweep_temporary().await;
+        summary.finish(result.as_ref().err().map(|e| e.to_string()));
+        self.events().publish(EventKind::RunFinished {
+            status: summary.status,
+        });
+        if let Some(watch) = watch {
+            let _ = watch.await;
+        }
+        let cancelled = matches!(
+            &result,
+            Err(ContextError::Scheduler { source })
+                if matches!(**source, crate::scheduler::error::SchedulerError::Cancelled)
+        );
+        match self.runs.save(&summary).await {
+            Ok(path) if cancelled => {
+                let count = |outcome| {
+                    summary
+                        .nodes
+                        .iter()
+                        .filter(|n| n.outcome == outcome)
+                        .count()
+                };
+                warn!(
+                    target: "context",
+                    "run cancelled: {} built, {} cached, {} not run, partial summary written to {}",
+                    count(NodeOutcome::Built),
+                    count(NodeOutcome::Cached),
+                    count(NodeOutcome::Skipped),
+                    path.display()
+                );
+            }
+            Ok(path) => debug!(
+                target: "context",
+                "run summary written to {}",
+                path.display()
+            ),
+            Err(e) => warn!(target: "context", "failed to write run summary: {e}"),
+        }
+        if let Err(e) = self.runs.record_durations(&summary).await {
+            warn!(target: "context", "failed to record transform durations: {e}");
+        }
+        let accesses = self.storage.audit().take();
+        if let Err(e) = self.runs.save_audit(&summary.id, &accesses).await {
+            warn!(target: "context", "failed to write run audit: {e}");
+        }
+        notifications.finished(&summary).await;
+        result
+    }
+
+    /// Removes the temporary blobs of layers the run abandoned, and those of
+    /// the remote caches it used that other processes left behind.
+    async fn sweep_temporary(&self) {
+        match self.storage.sweep_unfinished().await {
+            Ok(0) => {}
+            Ok(count) => debug!(target: "context", "removed {count} unfinished layers"),
+            Err(e) => warn!(target: "context", "failed to remove unfinished layers: {e}"),
+        }
+        match self.storage.sweep_stale(STALE_TEMPORARY).await {
+            Ok(0) => {}
+            Ok(count) => info!(target: "context", "removed {count} stale temporary blobs"),
+            Err(e) => warn!(target: "context", "failed to remove stale temporary blobs: {e}"),
+        }
+    }
+
+    /// Populates the local cache with everything needed to build `targets`
+    /// without executing any transform.
+    ///
+    /// Environment images, sources and already built artifacts are pulled
+    /// from their remote locations so a later offline run can succeed.
+    /// Matrix groups fetch every variant.
+    pub async fn fetch(&self, targets: &[Addr]) -> ContextResult<()> {
+        self.setup_environments().await?;
+        for target in targets.iter() {
+            let target = self.resolve_alias(target);
+            let members = if !self.transforms.contains_key(&target)
+                && let Some(members) = self.get_matrix(&target)
+            {
+                members
+            } else {
+                vec![target]
+            };
+            for member in members.iter() {
+                self.scheduler().fetch(self, member).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rebuilds `addr` ignoring the build cache, without recording a run.
+    ///
+    /// The fresh artifact replaces the one in the local cache and is not
+    /// uploaded. See [`Scheduler::rebuild`].
+    pub async fn rebuild(&self, addr: &Addr) -> ContextResult<()> {
         self.setup_environments().await?;
-        self.scheduler().run(self, addr).await?;
+        self.scheduler()
+            .rebuild(self, &self.resolve_alias(addr))
+            .await?;
+        Ok(())
+    }
+
+    async fn run_targets(&self, targets: &[Addr], summary: &mut RunSummary) -> ContextResult<()> {
+        self.setup_environments().await?;
+        for target in targets.iter() {
+            self.scheduler().run_recorded(self, target, summary).await?;
+        }
         Ok(())
     }
 }
@@ -510,7 +1268,7 @@ mod tests {
         assert!(
             matches!(
                 err,
-                error::ContextError::NoProvider { ref component, ref kind }
+                error::ContextError::NoProvider { ref component, ref kind, .. }
                 if component == "transform" && kind == "script"
             ),
             "unexpected error: {err:?}",
@@ -540,6 +1298,28 @@ mod tests {
         assert!(ctx.get_farm(&Addr::parse("//f").unwrap()).is_none());
     }
 
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn context_source_definitions_are_looked_up_by_address() {
+        let ctx = ctx_or_skip!();
+        let transform = Addr::parse("//p/build").unwrap();
+        let source = Addr::parse("//p/src").unwrap();
+        assert!(ctx.get_element_sources(&transform).is_empty());
+        assert!(ctx.get_source(&source).await.unwrap().is_none());
+
+        ctx.add_element_sources(&transform, std::slice::from_ref(&source));
+        assert_eq!(ctx.get_element_sources(&transform), vec![source.clone()]);
+        // A recorded definition is instantiated through the registry, which
+        // has no `local` source provider in this crate.
+        let node = Node::new_definition("source", "local", "src", BTreeMap::new());
+        ctx.add_source_definition(&source, &node);
+        let err = ctx.get_source(&source).await.err().expect("no provider");
+        assert!(
+            matches!(err, error::ContextError::NoProvider { ref component, .. } if component == "source"),
+            "unexpected error: {err:?}",
+        );
+    }
+
     #[tokio::test]
     #[serial_test::serial(log_manager)]
     async fn context_print_transforms_smoke() {
@@ -562,4 +1342,114 @@ mod tests {
         // presence of a lockfile since `Project::load` may write one
         // unconditionally. Reaching this point is the assertion.
     }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn source_caches_created_at_once_keep_their_priority() {
+        let ctx = ctx_or_skip!();
+        let tmp = TempDir::new().unwrap();
+        let mut manifest = String::from("schema-version = \"1\"\n");
+        for name in ["c", "a", "b"] {
+            manifest += &format!(
+                "\n[cache.source.{name}]\nkind = \"local\"\npath = \"{}\"\n",
+                tmp.path().join(name).display()
+            );
+        }
+        std::fs::write(tmp.path().join("edo.toml"), manifest).unwrap();
+        Project::load(tmp.path(), &ctx, false).await.unwrap();
+        // Health reports list the caches in priority order
+        let names: Vec<String> = ctx
+            .storage()
+            .health()
+            .await
+            .into_iter()
+            .filter_map(|x| {
+                Some(
+                    x.subject
+                        .split(' ')
+                        .next()?
+                        .strip_prefix("source:")?
+                        .to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+
+    use snafu::ensure;
+
+    /// Injects a source before resolution and records the lock it is shown,
+    /// or rejects the project once the lock is resolved.
+    struct RecordingHook {
+        reject: bool,
+        locked: Arc<parking_lot::Mutex<Option<String>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ProjectHookImpl for RecordingHook {
+        async fn before_resolve(
+            &self,
+            _ctx: &Context,
+            definitions: &mut ProjectDefinitions,
+        ) -> ContextResult<()> {
+            let node = Node::new_definition("source", "local", "injected", BTreeMap::new());
+            definitions
+                .sources
+                .insert(Addr::parse("//hooked/injected").unwrap(), node);
+            Ok(())
+        }
+
+        async fn after_lock(&self, _ctx: &Context, lock: &Lock) -> ContextResult<()> {
+            *self.locked.lock() = Some(lock.digest().to_string());
+            ensure!(
+                !self.reject,
+                error::HookSnafu {
+                    hook: "recording",
+                    reason: "rejected for testing",
+                }
+            );
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn project_hooks_run_around_resolution() {
+        let ctx = ctx_or_skip!();
+        let locked = Arc::new(parking_lot::Mutex::new(None));
+        ctx.registry().register_hook(
+            "recording",
+            ProjectHook::new(RecordingHook {
+                reject: false,
+                locked: locked.clone(),
+            }),
+        );
+        let tmp = TempDir::new().unwrap();
+        Project::load(tmp.path(), &ctx, false).await.unwrap();
+        assert!(
+            ctx.source_addrs()
+                .contains(&Addr::parse("//hooked/injected").unwrap())
+        );
+        assert!(locked.lock().is_some(), "after_lock must see the lock");
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn project_hooks_can_reject_a_project() {
+        let ctx = ctx_or_skip!();
+        ctx.registry().register_hook(
+            "recording",
+            ProjectHook::new(RecordingHook {
+                reject: true,
+                locked: Arc::default(),
+            }),
+        );
+        let tmp = TempDir::new().unwrap();
+        let err = Project::load(tmp.path(), &ctx, false).await.unwrap_err();
+        assert!(
+            matches!(err, error::ContextError::Hook { ref hook, .. } if hook == "recording"),
+            "unexpected error: {err:?}",
+        );
+        assert!(!tmp.path().join("edo.lock.json").exists());
+    }
 }
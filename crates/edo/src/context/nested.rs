@@ -0,0 +1,59 @@
+//! Running edo from inside a transform.
+//!
+//! A transform declaring `nested = true` may run `edo` itself, for example to
+//! build a sub-project. Two processes writing one local cache would race on
+//! its catalog, so the nested edo keeps its own storage, `.edo` in the
+//! directory it runs from unless `--storage` says otherwise, and reads the
+//! parent's local cache through [`Storage::set_parent`](crate::storage::Storage::set_parent)
+//! without ever writing to it. The scheduler mounts that cache read-only in
+//! the transform's environment and names it in [`NESTED_CACHE_ENV`].
+
+use super::{Addr, Config, ContextResult, DefinableNoContext, Node, NonConfigurable, error};
+use crate::storage::{Backend, LocalBackend, StorageError};
+use snafu::{ResultExt, ensure};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the local cache of the edo process running
+/// the transform, set for transforms that declare `nested = true`.
+pub const NESTED_CACHE_ENV: &str = "EDO_NESTED_CACHE";
+
+/// The local cache of the edo process this one runs under, when it runs
+/// inside a transform.
+pub fn parent_cache() -> Option<PathBuf> {
+    std::env::var_os(NESTED_CACHE_ENV)
+        .filter(|x| !x.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Opens the parent's local cache at `parent` for reading, refusing to run
+/// when `storage`, the local cache of this process, is the same directory.
+pub(super) async fn open_parent(
+    parent: &Path,
+    storage: &Path,
+    config: &Config,
+) -> ContextResult<Backend> {
+    let resolved = parent.canonicalize().context(error::IoSnafu)?;
+    ensure!(
+        storage.canonicalize().ok().as_ref() != Some(&resolved),
+        error::NestedStorageSnafu {
+            path: parent.display().to_string(),
+        }
+    );
+    let local =
+        <LocalBackend as DefinableNoContext<StorageError, NonConfigurable<StorageError>>>::new(
+            &Addr::parse("//edo-parent-cache")?,
+            &Node::new_definition(
+                "storage",
+                "local",
+                "edo-parent-cache",
+                BTreeMap::from([(
+                    "path".to_string(),
+                    Node::new_string(resolved.to_string_lossy().to_string()),
+                )]),
+            ),
+            config,
+        )
+        .await?;
+    Ok(Backend::new(local))
+}
@@ -18,7 +18,7 @@ use serde_json::Value as JsonValue;
 use snafu::ensure;
 
 /// Identifies the type of plugin component.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Component {
     /// A storage backend component.
     StorageBackend,
@@ -32,6 +32,22 @@ pub enum Component {
     Vendor,
 }
 
+impl Component {
+    /// Every component type.
+    pub const ALL: [Self; 5] = [
+        Self::StorageBackend,
+        Self::Environment,
+        Self::Source,
+        Self::Transform,
+        Self::Vendor,
+    ];
+
+    /// Parses the name a component is displayed with, e.g. `transform`.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|x| x.to_string() == name)
+    }
+}
+
 impl fmt::Display for Component {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -504,6 +520,12 @@ impl Node {
         Ok(())
     }
 
+    /// Returns `true` if both nodes share the same data, i.e. one is a
+    /// clone of the other.
+    pub fn same(&self, other: &Node) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+
     /// Returns a clone of the underlying [`Data`] value.
     pub fn data(&self) -> Data {
         self.data.read().clone()
@@ -0,0 +1,440 @@
+//! Notifications sent when a run finishes or first fails.
+//!
+//! Read from the `[notify]` table of the configuration, one entry per
+//! notifier:
+//!
+//! ```toml
+//! [notify.team]
+//! kind = "slack"                      # slack, webhook or desktop
+//! url  = "https://hooks.slack.com/services/..."
+//! on   = ["failure", "first-failure"] # finish (default), success, failure, first-failure
+//!
+//! [notify.ci]
+//! kind     = "webhook"
+//! url      = "https://ci.example.com/hooks/edo"
+//! headers  = { Authorization = "Bearer ..." }
+//! template = '{"run": "{{summary.id}}", "summary": {{summary_json}}}'
+//! ```
+//!
+//! A `template` is rendered with handlebars, without escaping, against
+//! `trigger`, `text` (a one line description), `summary` (the
+//! [`RunSummary`]), `summary_json` (the summary as JSON) and, for
+//! `first-failure`, `transform`. Without one, Slack is sent `{"text": ...}`,
+//! a webhook the trigger, text and summary as JSON, and a desktop
+//! notification the text. Delivery failures are logged and never fail the
+//! run.
+
+use super::{Addr, Config, ContextResult as Result, EventBus, EventKind, Node, error};
+use super::{NodeOutcome, RunStatus, RunSummary};
+use handlebars::{Handlebars, no_escape};
+use serde_json::{Value, json};
+use snafu::OptionExt;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+/// Key holding the notifiers in the configuration.
+pub const NOTIFY_KEY: &str = "notify";
+
+/// How long a webhook may take to answer.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a notification is delivered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Channel {
+    /// A Slack incoming webhook.
+    Slack(String),
+    /// An HTTP POST to any url.
+    Webhook(String),
+    /// The desktop notification service, through `notify-send` or `osascript`.
+    Desktop,
+}
+
+/// When a notification is sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Trigger {
+    /// The run finished, whatever its result.
+    Finish,
+    /// The run finished successfully.
+    Success,
+    /// The run finished with an error.
+    Failure,
+    /// The first transform of the run failed, while the run goes on.
+    FirstFailure,
+}
+
+impl Trigger {
+    const ALL: [Self; 4] = [
+        Self::Finish,
+        Self::Success,
+        Self::Failure,
+        Self::FirstFailure,
+    ];
+
+    /// The name used in the configuration.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Finish => "finish",
+            Self::Success => "success",
+            Self::Failure => "failure",
+            Self::FirstFailure => "first-failure",
+        }
+    }
+
+    /// Returns `true` if a notifier listening for `self` fires for a run
+    /// finishing with `status`.
+    fn matches(&self, status: RunStatus) -> bool {
+        match self {
+            Self::Finish => true,
+            Self::Success => status == RunStatus::Success,
+            Self::Failure => status == RunStatus::Failed,
+            Self::FirstFailure => false,
+        }
+    }
+}
+
+/// One configured notification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notifier {
+    /// Name of the entry in the `[notify]` table.
+    pub name: String,
+    /// Where it is delivered.
+    pub channel: Channel,
+    /// When it is sent.
+    pub on: Vec<Trigger>,
+    /// Handlebars template of the payload.
+    pub template: Option<String>,
+    /// Extra HTTP headers sent with webhooks.
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Notifier {
+    fn from_node(name: &str, node: &Node) -> Result<Self> {
+        let field = |key: &str, type_: &str| error::FieldSnafu {
+            field: format!("{NOTIFY_KEY}.{name}.{key}"),
+            type_: type_.to_string(),
+        };
+        let string = |key: &str| -> Result<Option<String>> {
+            node.get(key)
+                .map(|x| x.as_string().context(field(key, "string")))
+                .transpose()
+        };
+        let url = || string("url")?.context(field("url", "string"));
+        let channel = match string("kind")?.as_deref() {
+            Some("slack") => Channel::Slack(url()?),
+            Some("webhook") => Channel::Webhook(url()?),
+            Some("desktop") => Channel::Desktop,
+            _ => {
+                return field("kind", "one of \"slack\", \"webhook\" or \"desktop\"").fail();
+            }
+        };
+        let triggers = "list of \"finish\", \"success\", \"failure\" or \"first-failure\"";
+        let on = match node.get("on") {
+            Some(value) => {
+                let names = match (value.as_string(), value.as_list()) {
+                    (Some(name), _) => vec![Node::new_string(name)],
+                    (_, Some(list)) => list,
+                    _ => return field("on", triggers).fail(),
+                };
+                let mut on = names
+                    .iter()
+                    .map(|x| {
+                        x.as_string()
+                            .and_then(|x| Trigger::ALL.into_iter().find(|t| t.as_str() == x))
+                            .context(field("on", triggers))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                on.sort();
+                on.dedup();
+                on
+            }
+            None => vec![Trigger::Finish],
+        };
+        let mut headers = BTreeMap::new();
+        if let Some(value) = node.get("headers") {
+            let table = value
+                .as_table()
+                .context(field("headers", "table of strings"))?;
+            for (key, value) in table {
+                let value = value
+                    .as_string()
+                    .context(field("headers", "table of strings"))?;
+                headers.insert(key, value);
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            channel,
+            on,
+            template: string("template")?,
+            headers,
+        })
+    }
+
+    /// Renders the payload for `data`.
+    fn payload(&self, data: &Value) -> std::result::Result<String, String> {
+        if let Some(template) = self.template.as_ref() {
+            let mut hb = Handlebars::new();
+            hb.register_escape_fn(no_escape);
+            return hb
+                .render_template(template, data)
+                .map_err(|e| e.to_string());
+        }
+        let text = data["text"].as_str().unwrap_or_default();
+        Ok(match self.channel {
+            Channel::Slack(_) => json!({ "text": text }).to_string(),
+            Channel::Webhook(_) => json!({
+                "trigger": data["trigger"],
+                "text": text,
+                "transform": data["transform"],
+                "summary": data["summary"],
+            })
+            .to_string(),
+            Channel::Desktop => text.to_string(),
+        })
+    }
+
+    async fn send(&self, data: &Value) -> std::result::Result<(), String> {
+        let payload = self.payload(data)?;
+        match &self.channel {
+            Channel::Slack(url) | Channel::Webhook(url) => {
+                let mut request = reqwest::Client::new()
+                    .post(url)
+                    .timeout(TIMEOUT)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json");
+                for (key, value) in self.headers.iter() {
+                    request = request.header(key, value);
+                }
+                let response = request
+                    .body(payload)
+                    .send()
+                    .await
+                    .map_err(|e| e.to_string())?;
+                response.error_for_status().map_err(|e| e.to_string())?;
+            }
+            Channel::Desktop => {
+                let mut command = if cfg!(target_os = "macos") {
+                    let mut command = tokio::process::Command::new("osascript");
+                    command.arg("-e").arg(format!(
+                        "display notification {} with title \"edo\"",
+                        json!(payload)
+                    ));
+                    command
+                } else {
+                    let mut command = tokio::process::Command::new("notify-send");
+                    command.arg("edo").arg(payload);
+                    command
+                };
+                let status = command.status().await.map_err(|e| e.to_string())?;
+                if !status.success() {
+                    return Err(format!("exited with {status}"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Every notifier configured for a run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Notifications {
+    /// The notifiers, in name order.
+    pub notifiers: Vec<Notifier>,
+}
+
+impl Notifications {
+    /// Reads the notifiers from the `[notify]` table of `config`, if present.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let Some(node) = config.get(NOTIFY_KEY) else {
+            return Ok(Self::default());
+        };
+        let table = node.as_table().context(error::FieldSnafu {
+            field: NOTIFY_KEY,
+            type_: "table",
+        })?;
+        Ok(Self {
+            notifiers: table
+                .iter()
+                .map(|(name, node)| Notifier::from_node(name, node))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Sends the notifications for the finished run `summary`.
+    pub async fn finished(&self, summary: &RunSummary) {
+        let trigger = match summary.status {
+            RunStatus::Success => Trigger::Success,
+            _ => Trigger::Failure,
+        };
+        let notifiers = self
+            .notifiers
+            .iter()
+            .filter(|x| x.on.iter().any(|t| t.matches(summary.status)));
+        let data = data(trigger, summary, None);
+        for notifier in notifiers {
+            send(notifier, &data).await;
+        }
+    }
+
+    /// Follows `events` until the run finishes, sending the `first-failure`
+    /// notifications when the first transform fails. Returns `None` when no
+    /// notifier listens for it.
+    pub fn watch(&self, events: &EventBus, summary: &RunSummary) -> Option<JoinHandle<()>> {
+        let notifiers: Vec<Notifier> = self
+            .notifiers
+            .iter()
+            .filter(|x| x.on.contains(&Trigger::FirstFailure))
+            .cloned()
+            .collect();
+        if notifiers.is_empty() {
+            return None;
+        }
+        let mut events = events.subscribe();
+        let summary = summary.clone();
+        Some(tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => match event.kind {
+                        EventKind::Finished {
+                            addr,
+                            outcome: NodeOutcome::Failed,
+                            ..
+                        } => {
+                            let data = data(Trigger::FirstFailure, &summary, Some(&addr));
+                            for notifier in notifiers.iter() {
+                                send(notifier, &data).await;
+                            }
+                            break;
+                        }
+                        EventKind::RunFinished { .. } => break,
+                        _ => {}
+                    },
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }))
+    }
+}
+
+async fn send(notifier: &Notifier, data: &Value) {
+    match notifier.send(data).await {
+        Ok(()) => debug!(target: "notify", "sent the {} notification", notifier.name),
+        Err(e) => warn!(target: "notify", "failed to send the {} notification: {e}", notifier.name),
+    }
+}
+
+/// What templates are rendered against.
+fn data(trigger: Trigger, summary: &RunSummary, transform: Option<&Addr>) -> Value {
+    let targets = summary
+        .targets
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let text = match transform {
+        Some(addr) => format!("{addr} failed in edo run {} of {targets}", summary.id),
+        None => {
+            let count = |outcome| {
+                summary
+                    .nodes
+                    .iter()
+                    .filter(|x| x.outcome == outcome)
+                    .count()
+            };
+            format!(
+                "edo run {} of {targets} {}: {} built, {} cached, {} failed",
+                summary.id,
+                if summary.status == RunStatus::Success {
+                    "succeeded"
+                } else {
+                    "failed"
+                },
+                count(NodeOutcome::Built),
+                count(NodeOutcome::Cached),
+                count(NodeOutcome::Failed),
+            )
+        }
+    };
+    json!({
+        "trigger": trigger.as_str(),
+        "text": text,
+        "transform": transform,
+        "summary": summary,
+        "summary_json": serde_json::to_string(summary).unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> Config {
+        let config = Config::default();
+        config.merge(&toml::from_str(toml).unwrap());
+        config
+    }
+
+    #[test]
+    fn notifiers_are_read_from_config() {
+        let notifications = Notifications::from_config(&config(
+            r#"
+            [notify.ci]
+            kind = "webhook"
+            url = "http://localhost/hook"
+            on = ["first-failure", "failure", "failure"]
+            headers = { Authorization = "Bearer x" }
+
+            [notify.me]
+            kind = "desktop"
+            "#,
+        ))
+        .unwrap();
+        assert_eq!(notifications.notifiers.len(), 2);
+        let ci = &notifications.notifiers[0];
+        assert_eq!(
+            ci.channel,
+            Channel::Webhook("http://localhost/hook".to_string())
+        );
+        assert_eq!(ci.on, vec![Trigger::Failure, Trigger::FirstFailure]);
+        assert_eq!(ci.headers["Authorization"], "Bearer x");
+        assert_eq!(notifications.notifiers[1].on, vec![Trigger::Finish]);
+
+        for bad in [
+            "[notify.x]\nkind = \"pager\"",
+            "[notify.x]\nkind = \"slack\"",
+            "[notify.x]\nkind = \"desktop\"\non = [\"sometimes\"]",
+        ] {
+            assert!(Notifications::from_config(&config(bad)).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn payloads_render_the_summary() {
+        let mut summary = RunSummary::start(&[Addr::parse("//pkg/build").unwrap()]);
+        summary.finish(None);
+        let data = data(Trigger::Success, &summary, None);
+        assert!(
+            data["text"]
+                .as_str()
+                .unwrap()
+                .ends_with("//pkg/build succeeded: 0 built, 0 cached, 0 failed")
+        );
+
+        let mut notifier = Notifier {
+            name: "ci".to_string(),
+            channel: Channel::Slack("http://localhost/hook".to_string()),
+            on: vec![Trigger::Finish],
+            template: None,
+            headers: BTreeMap::new(),
+        };
+        let slack: Value = serde_json::from_str(&notifier.payload(&data).unwrap()).unwrap();
+        assert_eq!(slack["text"], data["text"]);
+
+        notifier.template = Some(r#"{"id": "{{summary.id}}", "run": {{summary_json}}}"#.into());
+        let custom: Value = serde_json::from_str(&notifier.payload(&data).unwrap()).unwrap();
+        assert_eq!(custom["id"], summary.id.as_str());
+        assert_eq!(custom["run"]["status"], "success");
+    }
+}
@@ -0,0 +1,754 @@
+//! Where definitions and address references sit in build files.
+//!
+//! The project loader keeps the contents of build files but not where they
+//! were written, which editors need. An [`Outline`] records, for one build
+//! file, the byte ranges of its definitions, their `kind` and fields, and of
+//! the addresses their `source`, `environment` and `depends` fields refer to.
+//! A [`ProjectOutline`] holds the outline of every build file of a project,
+//! re-outlining a single file as it is edited, and checks them against the
+//! kinds of a [`Registry`]. `edo lsp` serves it to editors.
+
+use super::builder::build_files;
+use super::describe::common_fields;
+use super::schema::BuildFormat;
+use super::{Addr, Component, ContextResult, FieldSchema, FieldType, Registry, error};
+use serde_yaml::Value as Yaml;
+use snafu::ResultExt;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml::Spanned;
+use toml::de::{DeTable, DeValue};
+
+/// A byte range of a build file.
+pub type Span = Range<usize>;
+
+/// Environment the CLI provides to every project.
+const DEFAULT_ENVIRONMENT: &str = "//default";
+
+/// A definition of a build file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Definition {
+    /// Address of the definition.
+    pub addr: Addr,
+    /// Section it is declared in, e.g. `transform` or `cache.source`.
+    pub section: String,
+    /// Component the definition creates, `None` for aliases.
+    pub component: Option<Component>,
+    /// Where its name is written.
+    pub span: Span,
+    /// Its `kind`, and where the value is written.
+    pub kind: Option<(String, Span)>,
+    /// Its other keys, and where each is written.
+    pub fields: Vec<(String, Span)>,
+}
+
+/// An address a definition refers to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reference {
+    /// Field holding the address.
+    pub field: String,
+    /// The address, resolved against the package of the file.
+    pub target: Addr,
+    /// Where the address is written.
+    pub span: Span,
+}
+
+/// The definitions and references of a build file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Outline {
+    /// Definitions, in file order.
+    pub definitions: Vec<Definition>,
+    /// Address references, in file order.
+    pub references: Vec<Reference>,
+    /// Reasons the file cannot be parsed, and where.
+    pub errors: Vec<(String, Span)>,
+}
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The project will not load.
+    Error,
+    /// The project loads, but likely not as intended.
+    Warning,
+}
+
+/// A problem found in a build file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Where the problem is.
+    pub span: Span,
+    /// How serious it is.
+    pub severity: Severity,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(span: &Span, message: String) -> Self {
+        Self {
+            span: span.clone(),
+            severity: Severity::Error,
+            message,
+        }
+    }
+
+    fn warning(span: &Span, message: String) -> Self {
+        Self {
+            span: span.clone(),
+            severity: Severity::Warning,
+            message,
+        }
+    }
+}
+
+/// What can be completed at a position of a build file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Completion {
+    /// The `kind` of a definition of the component.
+    Kind(Component),
+    /// The address of a definition of the component.
+    Address(Component),
+}
+
+/// A value read from a build file, with where it is written. Values read
+/// from YAML have empty spans, as `serde_yaml` does not report positions.
+struct Item {
+    span: Span,
+    value: Value,
+}
+
+enum Value {
+    String(String),
+    List(Vec<Item>),
+    Table(Vec<(String, Span, Item)>),
+    Other,
+}
+
+impl Value {
+    fn from_toml(table: &DeTable<'_>) -> Self {
+        Self::Table(
+            table
+                .iter()
+                .map(|(key, value)| {
+                    (
+                        key.get_ref().to_string(),
+                        key.span(),
+                        Item::from_toml(value),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Item {
+    fn from_toml(item: &Spanned<DeValue<'_>>) -> Self {
+        let value = match item.get_ref() {
+            DeValue::String(value) => Value::String(value.to_string()),
+            DeValue::Array(list) => Value::List(list.iter().map(Self::from_toml).collect()),
+            DeValue::Table(table) => Value::from_toml(table),
+            _ => Value::Other,
+        };
+        Self {
+            span: item.span(),
+            value,
+        }
+    }
+
+    fn from_yaml(item: &Yaml) -> Self {
+        let value = match item {
+            Yaml::String(value) => Value::String(value.clone()),
+            Yaml::Sequence(list) => Value::List(list.iter().map(Self::from_yaml).collect()),
+            Yaml::Mapping(table) => Value::Table(
+                table
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        Some((key.as_str()?.to_string(), 0..0, Self::from_yaml(value)))
+                    })
+                    .collect(),
+            ),
+            Yaml::Tagged(tagged) => return Self::from_yaml(&tagged.value),
+            _ => Value::Other,
+        };
+        Self { span: 0..0, value }
+    }
+
+    fn entries(&self) -> &[(String, Span, Item)] {
+        match &self.value {
+            Value::Table(entries) => entries,
+            _ => &[],
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<(&Span, &Item)> {
+        self.entries()
+            .iter()
+            .find(|x| x.0 == key)
+            .map(|(_, span, item)| (span, item))
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The strings of a string or a list of strings, with their spans.
+    fn strings(&self) -> Vec<(&str, &Span)> {
+        match &self.value {
+            Value::String(value) => vec![(value.as_str(), &self.span)],
+            Value::List(items) => items
+                .iter()
+                .filter_map(|x| Some((x.as_str()?, &x.span)))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Component created by the definitions of a build file section.
+fn section_component(section: &str) -> Option<Component> {
+    match section {
+        "cache" | "cache.source" | "cache.build" | "cache.output" => {
+            Some(Component::StorageBackend)
+        }
+        "environment" => Some(Component::Environment),
+        "source" | "requires" => Some(Component::Source),
+        "transform" => Some(Component::Transform),
+        "vendor" => Some(Component::Vendor),
+        _ => None,
+    }
+}
+
+/// Resolves `target` the way the project loader does: `source` and alias
+/// targets are relative to the package, other addresses are absolute.
+fn resolve(namespace: &Addr, field: &str, target: &str) -> ContextResult<Addr> {
+    if target.starts_with("//") || !matches!(field, "source" | "target") {
+        Addr::parse(target)
+    } else {
+        Ok(namespace.join(target))
+    }
+}
+
+impl Outline {
+    /// Outlines the contents of a build file of the package `namespace`.
+    pub fn parse(namespace: &Addr, format: BuildFormat, text: &str) -> Self {
+        let mut outline = Self::default();
+        let root = match format {
+            BuildFormat::Toml => match DeTable::parse(text) {
+                Ok(table) => Item {
+                    span: table.span(),
+                    value: Value::from_toml(table.get_ref()),
+                },
+                Err(err) => {
+                    let span = err.span().unwrap_or(0..0);
+                    outline.errors.push((err.message().to_string(), span));
+                    return outline;
+                }
+            },
+            BuildFormat::Yaml => match serde_yaml::from_str::<Yaml>(text) {
+                Ok(value) => Item::from_yaml(&value),
+                Err(err) => {
+                    let at = err.location().map(|x| x.index()).unwrap_or(0);
+                    outline.errors.push((err.to_string(), at..at));
+                    return outline;
+                }
+            },
+        };
+        // Deserializing reports what the outline does not look at, like a
+        // missing `schema-version`
+        if let Err(err) = format.parse(text.as_bytes()) {
+            outline.errors.push((err.to_string(), 0..0));
+        }
+        for (section, _, item) in root.entries() {
+            match section.as_str() {
+                "cache" => {
+                    for (name, span, item) in item.entries() {
+                        if name == "source" {
+                            for (name, span, item) in item.entries() {
+                                outline.define(namespace.join(name), "cache.source", span, item);
+                            }
+                        } else {
+                            let addr =
+                                Addr::parse(&format!("//edo-{name}-cache")).unwrap_or_default();
+                            outline.define(addr, &format!("cache.{name}"), span, item);
+                        }
+                    }
+                }
+                "alias" => {
+                    for (name, span, item) in item.entries() {
+                        outline.definitions.push(Definition {
+                            addr: namespace.join(name),
+                            section: section.clone(),
+                            component: None,
+                            span: span.clone(),
+                            kind: None,
+                            fields: Vec::new(),
+                        });
+                        let target = item.as_str().map(|x| (x, &item.span)).or_else(|| {
+                            let (_, target) = item.get("target")?;
+                            Some((target.as_str()?, &target.span))
+                        });
+                        if let Some((target, span)) = target {
+                            outline.refer(namespace, "target", target, span);
+                        }
+                    }
+                }
+                section if section_component(section).is_some() => {
+                    for (name, span, item) in item.entries() {
+                        outline.define(namespace.join(name), section, span, item);
+                        for field in ["source", "environment", "depends"] {
+                            if let Some((_, value)) = item.get(field) {
+                                for (target, span) in value.strings() {
+                                    outline.refer(namespace, field, target, span);
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Tables are read in key order, editors want them in file order
+        outline.definitions.sort_by_key(|x| x.span.start);
+        outline.references.sort_by_key(|x| x.span.start);
+        outline
+    }
+
+    fn define(&mut self, addr: Addr, section: &str, span: &Span, item: &Item) {
+        let mut kind = None;
+        let mut fields = Vec::new();
+        for (key, key_span, value) in item.entries() {
+            if key == "kind" {
+                kind = value.as_str().map(|x| (x.to_string(), value.span.clone()));
+            } else {
+                fields.push((key.clone(), key_span.clone()));
+            }
+        }
+        fields.sort_by_key(|x| x.1.start);
+        self.definitions.push(Definition {
+            addr,
+            section: section.to_string(),
+            component: section_component(section),
+            span: span.clone(),
+            kind,
+            fields,
+        });
+    }
+
+    fn refer(&mut self, namespace: &Addr, field: &str, target: &str, span: &Span) {
+        if let Ok(target) = resolve(namespace, field, target) {
+            self.references.push(Reference {
+                field: field.to_string(),
+                target,
+                span: span.clone(),
+            });
+        }
+    }
+}
+
+/// What to complete at byte `offset` of a build file: kinds after `kind`,
+/// addresses after `source`, `environment` and `depends`.
+pub fn completion_at(text: &str, offset: usize) -> Option<Completion> {
+    let before = text.get(..offset)?;
+    let line = &before[before.rfind('\n').map(|x| x + 1).unwrap_or(0)..];
+    let (key, separator) = match (line.split_once('='), line.split_once(':')) {
+        (Some((key, _)), _) => (key, '='),
+        (None, Some((key, _))) => (key, ':'),
+        _ => return None,
+    };
+    // A YAML key at the start of a line names a section
+    if separator == ':' && !key.starts_with([' ', '-']) {
+        return None;
+    }
+    let key = key.trim().trim_start_matches('-').trim().trim_matches('"');
+    match key {
+        "source" => Some(Completion::Address(Component::Source)),
+        "environment" => Some(Completion::Address(Component::Environment)),
+        "depends" => Some(Completion::Address(Component::Transform)),
+        "kind" => {
+            // The closest section above the line
+            let section = before.lines().rev().skip(1).find_map(|line| {
+                if let Some(header) = line.trim().strip_prefix('[') {
+                    return Some(header.trim_start_matches('[').trim().to_string());
+                }
+                let key = line.strip_suffix(':')?;
+                (!key.starts_with([' ', '-', '#'])).then(|| key.to_string())
+            })?;
+            let section = section.split(['.', ']']).next()?;
+            section_component(section)
+                .filter(|_| section != "requires")
+                .map(Completion::Kind)
+        }
+        _ => None,
+    }
+}
+
+/// The outlines of every build file of a project.
+#[derive(Clone, Debug, Default)]
+pub struct ProjectOutline {
+    root: PathBuf,
+    files: BTreeMap<PathBuf, Outline>,
+}
+
+impl ProjectOutline {
+    /// An outline of the project at `root` without any file.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Outlines every build file of the project at `root`, found the way
+    /// [`Project`](super::Project) finds them.
+    pub fn scan(root: &Path) -> ContextResult<Self> {
+        let mut project = Self::new(root);
+        for (namespace, path, format) in build_files(&Addr::default(), root)? {
+            let text = std::fs::read_to_string(&path).context(error::IoSnafu)?;
+            project
+                .files
+                .insert(path, Outline::parse(&namespace, format, &text));
+        }
+        Ok(project)
+    }
+
+    /// Directory of the project.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Re-outlines the file at `path` from its current `text`, returning
+    /// `false` when `path` is not a build file of the project.
+    pub fn update(&mut self, path: &Path, text: &str) -> bool {
+        let Some(format) = BuildFormat::detect(path) else {
+            return false;
+        };
+        let Some(directory) = path.parent().and_then(|x| x.strip_prefix(&self.root).ok()) else {
+            return false;
+        };
+        let namespace = directory.components().fold(Addr::default(), |addr, x| {
+            addr.join(&x.as_os_str().to_string_lossy())
+        });
+        self.files
+            .insert(path.to_path_buf(), Outline::parse(&namespace, format, text));
+        true
+    }
+
+    /// Forgets the file at `path`.
+    pub fn remove(&mut self, path: &Path) {
+        self.files.remove(path);
+    }
+
+    /// The outlined files, in path order.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    /// The outline of the file at `path`.
+    pub fn outline(&self, path: &Path) -> Option<&Outline> {
+        self.files.get(path)
+    }
+
+    /// The file and definition `addr` refers to. A matrix variant, like
+    /// `//pkg/build[arch=x86_64]`, refers to its transform.
+    pub fn definition(&self, addr: &Addr) -> Option<(&Path, &Definition)> {
+        let name = addr.to_id();
+        let base = match name.split_once('[') {
+            Some((base, _)) => Addr::parse(base).ok()?,
+            None => addr.clone(),
+        };
+        self.files.iter().find_map(|(path, outline)| {
+            outline
+                .definitions
+                .iter()
+                .find(|x| x.addr == base)
+                .map(|x| (path.as_path(), x))
+        })
+    }
+
+    /// The reference written at byte `offset` of the file at `path`.
+    pub fn reference_at(&self, path: &Path, offset: usize) -> Option<&Reference> {
+        self.files
+            .get(path)?
+            .references
+            .iter()
+            .find(|x| x.span.start <= offset && offset <= x.span.end)
+    }
+
+    /// Addresses of the definitions of `component`, in address order.
+    pub fn addresses(&self, component: &Component) -> Vec<Addr> {
+        let mut addrs: Vec<Addr> = self
+            .files
+            .values()
+            .flat_map(|x| x.definitions.iter())
+            .filter(|x| x.component.as_ref() == Some(component))
+            .map(|x| x.addr.clone())
+            .collect();
+        if *component == Component::Environment {
+            addrs.extend(Addr::parse(DEFAULT_ENVIRONMENT));
+        }
+        addrs.sort();
+        addrs.dedup();
+        addrs
+    }
+
+    /// Checks the file at `path`: that it parses, that its definitions use
+    /// kinds of `registry` with the fields they describe, and that the
+    /// addresses it refers to are defined.
+    pub fn check(&self, path: &Path, registry: &Registry) -> Vec<Diagnostic> {
+        let Some(outline) = self.files.get(path) else {
+            return Vec::new();
+        };
+        let mut diagnostics: Vec<Diagnostic> = outline
+            .errors
+            .iter()
+            .map(|(message, span)| Diagnostic::error(span, message.clone()))
+            .collect();
+        let kinds = registry.kinds();
+        for definition in outline.definitions.iter() {
+            let Some(component) = definition.component.as_ref() else {
+                continue;
+            };
+            let Some((kind, kind_span)) = definition.kind.as_ref() else {
+                diagnostics.push(Diagnostic::error(
+                    &definition.span,
+                    format!("{} has no `kind`", definition.addr),
+                ));
+                continue;
+            };
+            // Requirements name the kind of the source a vendor resolves
+            if definition.section == "requires" {
+                continue;
+            }
+            if !kinds.iter().any(|x| x.0 == *component && x.1 == *kind) {
+                diagnostics.push(Diagnostic::error(
+                    kind_span,
+                    format!("no {component} kind named '{kind}'"),
+                ));
+                continue;
+            }
+            let Some(schema) = registry.schema(component, kind) else {
+                continue;
+            };
+            let mut fields = schema.fields;
+            fields.extend(common_fields(component));
+            if definition.section == "cache.source" {
+                fields.push(FieldSchema::new("route", FieldType::Any, ""));
+            }
+            for (field, span) in definition.fields.iter() {
+                if !fields.iter().any(|x| x.name == *field) {
+                    diagnostics.push(Diagnostic::warning(
+                        span,
+                        format!("{component} kind '{kind}' does not describe a `{field}` field"),
+                    ));
+                }
+            }
+            for field in fields.iter().filter(|x| x.required) {
+                if !definition.fields.iter().any(|x| x.0 == field.name) {
+                    diagnostics.push(Diagnostic::error(
+                        &definition.span,
+                        format!(
+                            "{} is missing the required field `{}`",
+                            definition.addr, field.name
+                        ),
+                    ));
+                }
+            }
+        }
+        for reference in outline.references.iter() {
+            if reference.target.to_string() != DEFAULT_ENVIRONMENT
+                && self.definition(&reference.target).is_none()
+            {
+                diagnostics.push(Diagnostic::error(
+                    &reference.span,
+                    format!("nothing is defined at {}", reference.target),
+                ));
+            }
+        }
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::KindSchema;
+
+    const BUILD: &str = r#"schema-version = "1"
+
+[source.code]
+kind = "local"
+path = "src"
+
+[transform.build]
+kind        = "script"
+source      = ["code", "//lib/missing"]
+environment = "//default"
+depends     = ["//app/test[arch=x86_64]"]
+commands    = ["make"]
+jobs        = 4
+
+[transform.test]
+kind = "nope"
+
+[alias]
+old = "build"
+"#;
+
+    fn span_of(text: &str, needle: &str) -> Span {
+        let start = text.find(needle).unwrap();
+        start..start + needle.len()
+    }
+
+    fn project() -> ProjectOutline {
+        let mut project = ProjectOutline::new(Path::new("/work"));
+        assert!(project.update(Path::new("/work/app/edo.toml"), BUILD));
+        assert!(project.update(
+            Path::new("/work/lib/edo.yaml"),
+            "schema-version: \"1\"\nsource:\n  vendored:\n    kind: local\n    path: v\n",
+        ));
+        assert!(!project.update(Path::new("/work/app/Cargo.toml"), ""));
+        project
+    }
+
+    #[test]
+    fn outlines_record_where_definitions_and_references_are() {
+        let outline = Outline::parse(&Addr::parse("//app").unwrap(), BuildFormat::Toml, BUILD);
+        assert!(outline.errors.is_empty(), "{:?}", outline.errors);
+        let addrs: Vec<String> = outline
+            .definitions
+            .iter()
+            .map(|x| x.addr.to_string())
+            .collect();
+        assert_eq!(
+            addrs,
+            ["//app/code", "//app/build", "//app/test", "//app/old"]
+        );
+        let build = &outline.definitions[1];
+        assert_eq!(build.span, span_of(BUILD, "build"));
+        assert_eq!(
+            build.kind,
+            Some(("script".to_string(), span_of(BUILD, "\"script\"")))
+        );
+        assert_eq!(build.fields[4].0, "jobs");
+        let targets: Vec<(&str, String)> = outline
+            .references
+            .iter()
+            .map(|x| (x.field.as_str(), x.target.to_string()))
+            .collect();
+        assert_eq!(
+            targets,
+            [
+                ("source", "//app/code".to_string()),
+                ("source", "//lib/missing".to_string()),
+                ("environment", "//default".to_string()),
+                ("depends", "//app/test[arch=x86_64]".to_string()),
+                ("target", "//app/build".to_string()),
+            ]
+        );
+        assert_eq!(
+            outline.references[1].span,
+            span_of(BUILD, "\"//lib/missing\"")
+        );
+
+        let broken = Outline::parse(&Addr::default(), BuildFormat::Toml, "[source\n");
+        assert_eq!(broken.errors.len(), 1);
+    }
+
+    #[test]
+    fn checks_report_unknown_kinds_fields_and_addresses() {
+        let registry = Registry::default();
+        registry.describe(
+            Component::Transform,
+            "script",
+            KindSchema::new("")
+                .required("commands", FieldType::list(FieldType::String), "")
+                .field("source", FieldType::Any, "")
+                .field("environment", FieldType::String, "")
+                .field("depends", FieldType::Any, ""),
+        );
+        registry.describe(Component::Source, "local", KindSchema::new(""));
+        let project = project();
+        let diagnostics = project.check(Path::new("/work/app/edo.toml"), &registry);
+        let messages: Vec<(&str, Severity)> = diagnostics
+            .iter()
+            .map(|x| (x.message.as_str(), x.severity))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    "source kind 'local' does not describe a `path` field",
+                    Severity::Warning
+                ),
+                (
+                    "transform kind 'script' does not describe a `jobs` field",
+                    Severity::Warning
+                ),
+                ("no transform kind named 'nope'", Severity::Error),
+                ("nothing is defined at //lib/missing", Severity::Error),
+            ]
+        );
+        assert_eq!(diagnostics[2].span, span_of(BUILD, "\"nope\""));
+
+        let (path, definition) = project
+            .definition(&Addr::parse("//lib/vendored").unwrap())
+            .unwrap();
+        assert_eq!(path, Path::new("/work/lib/edo.yaml"));
+        assert_eq!(definition.kind.as_ref().unwrap().0, "local");
+        let offset = BUILD.find("//app/test").unwrap();
+        let reference = project
+            .reference_at(Path::new("/work/app/edo.toml"), offset)
+            .unwrap();
+        let (_, test) = project.definition(&reference.target).unwrap();
+        let start = BUILD.find("test]").unwrap();
+        assert_eq!(test.span, start..start + 4);
+        assert_eq!(
+            project.addresses(&Component::Source),
+            [
+                Addr::parse("//app/code").unwrap(),
+                Addr::parse("//lib/vendored").unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn completions_follow_the_key_being_written() {
+        let toml = "[transform.build]\nkind = \"";
+        assert_eq!(
+            completion_at(toml, toml.len()),
+            Some(Completion::Kind(Component::Transform))
+        );
+        let toml = "[cache.build]\nkind = \"";
+        assert_eq!(
+            completion_at(toml, toml.len()),
+            Some(Completion::Kind(Component::StorageBackend))
+        );
+        let yaml = "environment:\n  gcc:\n    kind: ";
+        assert_eq!(
+            completion_at(yaml, yaml.len()),
+            Some(Completion::Kind(Component::Environment))
+        );
+        let toml = "[transform.build]\ndepends = [\"//";
+        assert_eq!(
+            completion_at(toml, toml.len()),
+            Some(Completion::Address(Component::Transform))
+        );
+        let yaml = "transform:\n  build:\n    environment: ";
+        assert_eq!(
+            completion_at(yaml, yaml.len()),
+            Some(Completion::Address(Component::Environment))
+        );
+        for text in [
+            "environment:",
+            "[transform.build]\ncommands = [\"",
+            "[requires.zlib]\nkind = \"",
+        ] {
+            assert_eq!(completion_at(text, text.len()), None, "{text}");
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! Restrictions on what a project may use.
+//!
+//! A [`Policy`] is read from the `[policy]` table of the user config (the
+//! file passed with `--config`, or `~/.config/edo.toml`) when the context is
+//! created, so a project cannot relax it from its own `[config]`:
+//!
+//! ```toml
+//! [policy]
+//! sources  = { deny = ["local"] }                         # source kinds
+//! vendors  = { allow = ["image"] }                        # vendor kinds
+//! backends = { allow = ["local", "s3"] }                  # storage backend kinds
+//! hosts    = { allow = ["mirror.example.com", "*.corp"] } # hosts of url/uri fields
+//! urls     = { deny = ["http://**"] }                     # url/uri fields
+//! licenses = { deny = ["GPL-*", "AGPL-*"] }               # SPDX ids of sources
+//! ```
+//!
+//! Entries are [`glob_match`] patterns, matched against `/` separated
+//! segments. A value is rejected when it matches a `deny` entry, or when an
+//! `allow` list is given and it matches none of its entries.
+//!
+//! Licenses are not checked as definitions are added but when an artifact
+//! is published, see [`Context::license_report`](super::Context::license_report).
+//! Sources of unknown license are checked as `NOASSERTION`.
+
+use super::{Addr, Config, ContextResult as Result, Node, error};
+use crate::util::glob_match;
+use snafu::{OptionExt, ensure};
+
+/// Key holding the policy in the user config.
+pub const POLICY_KEY: &str = "policy";
+
+/// Fields of a definition checked against the `hosts` and `urls` rules.
+const URL_FIELDS: [&str; 2] = ["url", "uri"];
+
+/// Allow and deny patterns for one kind of value.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rule {
+    /// Patterns a value must match one of, or `None` to allow any value.
+    pub allow: Option<Vec<String>>,
+    /// Patterns no value may match.
+    pub deny: Vec<String>,
+}
+
+impl Rule {
+    fn from_node(name: &str, node: &Node) -> Result<Self> {
+        let patterns = |key: &str| -> Result<Option<Vec<String>>> {
+            let Some(value) = node.get(key) else {
+                return Ok(None);
+            };
+            let field = error::FieldSnafu {
+                field: format!("{POLICY_KEY}.{name}.{key}"),
+                type_: "list of strings",
+            };
+            value
+                .as_list()
+                .context(field.clone())?
+                .iter()
+                .map(|x| x.as_string().context(field.clone()))
+                .collect::<Result<Vec<_>>>()
+                .map(Some)
+        };
+        Ok(Self {
+            allow: patterns("allow")?,
+            deny: patterns("deny")?.unwrap_or_default(),
+        })
+    }
+
+    /// Returns why `value` is rejected, or `None` if it is allowed.
+    fn violation(&self, name: &str, value: &str) -> Option<String> {
+        if let Some(pattern) = self.deny.iter().find(|x| glob_match(x, value)) {
+            return Some(format!(
+                "denied by {POLICY_KEY}.{name}.deny entry '{pattern}'"
+            ));
+        }
+        match self.allow.as_ref() {
+            Some(allow) if !allow.iter().any(|x| glob_match(x, value)) => {
+                Some(format!("not in {POLICY_KEY}.{name}.allow"))
+            }
+            _ => None,
+        }
+    }
+
+    fn check(&self, addr: &Addr, name: &str, subject: &str, value: &str) -> Result<()> {
+        match self.violation(name, value) {
+            Some(reason) => error::PolicySnafu {
+                addr: addr.clone(),
+                subject,
+                value,
+                reason,
+            }
+            .fail(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// What a project is allowed to use, enforced as sources, vendors and caches
+/// are added to the context.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Source kinds.
+    pub sources: Rule,
+    /// Vendor kinds.
+    pub vendors: Rule,
+    /// Storage backend kinds, for both the build/output caches and source caches.
+    pub backends: Rule,
+    /// Hosts named by the `url` or `uri` field of a definition.
+    pub hosts: Rule,
+    /// The `url` or `uri` field of a definition.
+    pub urls: Rule,
+    /// SPDX license identifiers of the sources a published artifact is
+    /// built from.
+    pub licenses: Rule,
+}
+
+impl Policy {
+    /// Reads the policy from the `[policy]` table of `config`, if present.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let Some(node) = config.get(POLICY_KEY) else {
+            return Ok(Self::default());
+        };
+        ensure!(
+            node.as_table().is_some(),
+            error::FieldSnafu {
+                field: POLICY_KEY,
+                type_: "table",
+            }
+        );
+        let rule = |name: &str| match node.get(name) {
+            Some(rule) if rule.as_table().is_some() => Rule::from_node(name, &rule),
+            Some(_) => error::FieldSnafu {
+                field: format!("{POLICY_KEY}.{name}"),
+                type_: "table with allow and deny lists",
+            }
+            .fail(),
+            None => Ok(Rule::default()),
+        };
+        Ok(Self {
+            sources: rule("sources")?,
+            vendors: rule("vendors")?,
+            backends: rule("backends")?,
+            hosts: rule("hosts")?,
+            urls: rule("urls")?,
+            licenses: rule("licenses")?,
+        })
+    }
+
+    /// Returns why `license` is rejected, or `None` if it is allowed.
+    pub fn license_violation(&self, license: &str) -> Option<String> {
+        self.licenses.violation("licenses", license)
+    }
+
+    /// Checks a source definition.
+    pub fn check_source(&self, addr: &Addr, node: &Node) -> Result<()> {
+        if let Some(kind) = node.get_kind() {
+            self.sources.check(addr, "sources", "source kind", &kind)?;
+        }
+        self.check_urls(addr, node)
+    }
+
+    /// Checks a vendor definition.
+    pub fn check_vendor(&self, addr: &Addr, node: &Node) -> Result<()> {
+        if let Some(kind) = node.get_kind() {
+            self.vendors.check(addr, "vendors", "vendor kind", &kind)?;
+        }
+        self.check_urls(addr, node)
+    }
+
+    /// Checks a storage backend definition.
+    pub fn check_backend(&self, addr: &Addr, node: &Node) -> Result<()> {
+        if let Some(kind) = node.get_kind() {
+            self.backends
+                .check(addr, "backends", "storage backend kind", &kind)?;
+        }
+        self.check_urls(addr, node)
+    }
+
+    fn check_urls(&self, addr: &Addr, node: &Node) -> Result<()> {
+        for url in URL_FIELDS
+            .iter()
+            .filter_map(|x| node.get(x).and_then(|x| x.as_string()))
+        {
+            self.urls.check(addr, "urls", "url", &url)?;
+            if let Some(host) = host(&url) {
+                self.hosts.check(addr, "hosts", "host", &host)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Extracts the host from a url, an scp-like `user@host:path` git remote or
+/// a scheme-less registry reference such as `public.ecr.aws/docker/library`.
+pub(super) fn host(value: &str) -> Option<String> {
+    if value.contains("://") {
+        return url::Url::parse(value)
+            .ok()
+            .and_then(|x| x.host_str().map(str::to_string));
+    }
+    let authority = value.split('/').next()?;
+    let authority = authority.rsplit('@').next()?;
+    let host = authority.split(':').next()?;
+    (!host.is_empty()).then(|| host.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn config(toml: &str) -> Config {
+        let nodes: BTreeMap<String, Node> = toml::from_str(toml).unwrap();
+        let config = Config::default();
+        config.merge(&nodes);
+        config
+    }
+
+    fn source(kind: &str, url: &str) -> Node {
+        Node::new_definition(
+            "source",
+            kind,
+            "src",
+            BTreeMap::from([("url".to_string(), Node::new_string(url.to_string()))]),
+        )
+    }
+
+    fn addr() -> Addr {
+        Addr::parse("//p/src").unwrap()
+    }
+
+    #[test]
+    fn missing_table_allows_everything() {
+        let policy = Policy::from_config(&Config::default()).unwrap();
+        assert_eq!(policy, Policy::default());
+        policy
+            .check_source(&addr(), &source("git", "http://example.com/x.git"))
+            .unwrap();
+    }
+
+    #[test]
+    fn denied_kinds_are_rejected() {
+        let policy =
+            Policy::from_config(&config("[policy]\nsources = { deny = [\"local\"] }")).unwrap();
+        let err = policy
+            .check_source(&addr(), &source("local", "files"))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'//p/src' violates the policy: source kind 'local' is denied by policy.sources.deny entry 'local'"
+        );
+        policy
+            .check_source(&addr(), &source("git", "https://example.com/x.git"))
+            .unwrap();
+    }
+
+    #[test]
+    fn hosts_must_be_allowed() {
+        let policy = Policy::from_config(&config(
+            "[policy]\nhosts = { allow = [\"mirror.example.com\", \"*.corp\"] }",
+        ))
+        .unwrap();
+        for url in [
+            "https://mirror.example.com/a.tar.gz",
+            "git@git.corp:team/repo.git",
+            "registry.corp/library",
+        ] {
+            policy
+                .check_source(&addr(), &source("remote", url))
+                .unwrap();
+        }
+        let err = policy
+            .check_vendor(&addr(), &source("image", "public.ecr.aws/docker/library"))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'//p/src' violates the policy: host 'public.ecr.aws' is not in policy.hosts.allow"
+        );
+    }
+
+    #[test]
+    fn urls_are_matched_by_segment() {
+        let policy =
+            Policy::from_config(&config("[policy]\nurls = { deny = [\"http://**\"] }")).unwrap();
+        assert!(
+            policy
+                .check_backend(&addr(), &source("s3", "http://cache.example.com/x"))
+                .is_err()
+        );
+        policy
+            .check_backend(&addr(), &source("s3", "https://cache.example.com/x"))
+            .unwrap();
+    }
+
+    #[test]
+    fn licenses_are_matched_by_id() {
+        let policy = Policy::from_config(&config(
+            "[policy]\nlicenses = { allow = [\"MIT\", \"Apache-2.0\", \"GPL-*\"], deny = [\"GPL-3.0-*\"] }",
+        ))
+        .unwrap();
+        assert_eq!(policy.license_violation("MIT"), None);
+        assert_eq!(policy.license_violation("GPL-2.0-only"), None);
+        assert_eq!(
+            policy.license_violation("GPL-3.0-only").unwrap(),
+            "denied by policy.licenses.deny entry 'GPL-3.0-*'"
+        );
+        assert_eq!(
+            policy.license_violation("NOASSERTION").unwrap(),
+            "not in policy.licenses.allow"
+        );
+    }
+
+    #[test]
+    fn malformed_rules_are_rejected() {
+        let err = Policy::from_config(&config("[policy]\nhosts = { allow = \"x\" }")).unwrap_err();
+        assert!(
+            matches!(err, error::ContextError::Field { ref field, .. } if field == "policy.hosts.allow"),
+            "unexpected error: {err:?}"
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Progress reporting for long running operations.
+//!
+//! [`Progress`] attaches to the current tracing span and drives the progress
+//! bar the indicatif layer renders for it. Sources and other long operations
+//! call [`Progress::set_total`] (or [`Progress::set_total_bytes`]) once the
+//! amount of work is known, then [`Progress::advance`] as it completes, and
+//! [`Progress::message`] to describe the current step, or [`Progress::tick`]
+//! for a message that changes with time, such as the time left. When no
+//! progress layer is installed every call is a cheap no-op beyond the
+//! bookkeeping.
+
+use parking_lot::Mutex;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::Poll;
+use std::time::Duration;
+use tokio::io::AsyncRead;
+use tokio::task::JoinHandle;
+use tracing::Span;
+use tracing_indicatif::span_ext::IndicatifSpanExt;
+use tracing_indicatif::style::ProgressStyle;
+
+const ITEMS_TEMPLATE: &str = "[{elapsed_precise}] {span_child_prefix} {span_fields} {span_name} {msg} {wide_bar:.green/white} {human_pos}/{human_len}";
+/// How often [`Progress::tick`] refreshes the message.
+const TICK: Duration = Duration::from_secs(1);
+const BYTES_TEMPLATE: &str = "[{elapsed_precise}] {span_child_prefix} {span_fields} {span_name} {msg} {wide_bar:.green/white} {bytes}/{total_bytes} ({binary_bytes_per_sec})";
+
+/// Reports progress for the span it was created in.
+///
+/// Clones share the same counters, so a handle can be passed into spawned
+/// tasks or wrapped readers.
+#[derive(Clone)]
+pub struct Progress {
+    span: Span,
+    state: Arc<State>,
+}
+
+#[derive(Default)]
+struct State {
+    total: AtomicU64,
+    position: AtomicU64,
+    message: Mutex<Option<String>>,
+}
+
+impl Progress {
+    /// Creates a progress handle for the current span.
+    pub fn current() -> Self {
+        Self::for_span(Span::current())
+    }
+
+    /// Creates a progress handle for `span`.
+    pub fn for_span(span: Span) -> Self {
+        Self {
+            span,
+            state: Arc::default(),
+        }
+    }
+
+    /// Sets the number of work items and switches the span to a progress bar.
+    pub fn set_total(&self, total: u64) {
+        self.start(total, ITEMS_TEMPLATE);
+    }
+
+    /// Sets the number of bytes to transfer and switches the span to a progress bar.
+    pub fn set_total_bytes(&self, total: u64) {
+        self.start(total, BYTES_TEMPLATE);
+    }
+
+    /// Records `delta` more units of completed work.
+    pub fn advance(&self, delta: u64) {
+        self.state.position.fetch_add(delta, Ordering::Relaxed);
+        self.span.pb_inc(delta);
+    }
+
+    /// Describes the step currently in progress.
+    pub fn message(&self, message: &str) {
+        *self.state.message.lock() = Some(message.to_string());
+        self.span.pb_set_message(message);
+    }
+
+    /// Sets the message to what `describe` returns, now and every second
+    /// until the returned [`Ticker`] is dropped.
+    pub fn tick<F>(&self, describe: F) -> Ticker
+    where
+        F: Fn() -> String + Send + 'static,
+    {
+        self.message(&describe());
+        let progress = self.clone();
+        Ticker(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TICK);
+            // The first tick completes at once, the message is already set
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                progress.message(&describe());
+            }
+        }))
+    }
+
+    /// Returns the total set by [`Progress::set_total`], or zero if unknown.
+    pub fn total(&self) -> u64 {
+        self.state.total.load(Ordering::Relaxed)
+    }
+
+    /// Returns the amount of work completed so far.
+    pub fn position(&self) -> u64 {
+        self.state.position.load(Ordering::Relaxed)
+    }
+
+    /// Returns the last message set, if any.
+    pub fn last_message(&self) -> Option<String> {
+        self.state.message.lock().clone()
+    }
+
+    /// Wraps `reader` so every byte read advances this progress.
+    pub fn wrap_read<R: AsyncRead + Unpin>(&self, reader: R) -> ProgressReader<R> {
+        ProgressReader {
+            inner: reader,
+            progress: self.clone(),
+        }
+    }
+
+    fn start(&self, total: u64, template: &str) {
+        self.state.total.store(total, Ordering::Relaxed);
+        if let Ok(style) = ProgressStyle::with_template(template) {
+            self.span.pb_set_style(&style);
+        }
+        self.span.pb_set_length(total);
+        self.span.pb_set_position(self.position());
+    }
+}
+
+/// Refreshes a [`Progress`] message until dropped, see [`Progress::tick`].
+pub struct Ticker(JoinHandle<()>);
+
+impl Drop for Ticker {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// An [`AsyncRead`] adapter that advances a [`Progress`] by the bytes read.
+pub struct ProgressReader<R> {
+    inner: R,
+    progress: Progress,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ProgressReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = result {
+            let read = buf.filled().len() - before;
+            if read > 0 {
+                this.progress.advance(read as u64);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn counters_track_total_and_position() {
+        let progress = Progress::for_span(Span::none());
+        progress.set_total(10);
+        progress.advance(3);
+        progress.clone().advance(4);
+        progress.message("halfway");
+        assert_eq!(progress.total(), 10);
+        assert_eq!(progress.position(), 7);
+        assert_eq!(progress.last_message().as_deref(), Some("halfway"));
+    }
+
+    #[tokio::test]
+    async fn ticking_messages_refresh_until_dropped() {
+        let progress = Progress::for_span(Span::none());
+        let count = Arc::new(AtomicU64::new(0));
+        let ticks = count.clone();
+        let ticker =
+            progress.tick(move || format!("tick {}", ticks.fetch_add(1, Ordering::Relaxed)));
+        assert_eq!(progress.last_message().as_deref(), Some("tick 0"));
+        tokio::time::sleep(TICK + TICK / 2).await;
+        drop(ticker);
+        let last = progress.last_message();
+        assert_eq!(last.as_deref(), Some("tick 1"));
+        tokio::time::sleep(TICK + TICK / 2).await;
+        assert_eq!(progress.last_message(), last);
+    }
+
+    #[tokio::test]
+    async fn wrapped_reader_advances_by_bytes_read() {
+        let progress = Progress::for_span(Span::none());
+        progress.set_total_bytes(11);
+        let mut reader = progress.wrap_read(std::io::Cursor::new(b"hello world".to_vec()));
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).await.unwrap();
+        assert_eq!(out, b"hello world");
+        assert_eq!(progress.position(), 11);
+    }
+}
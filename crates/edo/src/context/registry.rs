@@ -1,13 +1,20 @@
 use crate::{
-    context::{Addr, Context, Node, error},
+    context::{
+        Addr, Component, Context, DEFAULT_TEMPLATE_PROVIDER, Describe, FieldSchema, FieldType,
+        JSON_SCHEMA_DIALECT, KindSchema, Node, ProjectHook, ScaffoldFile, TemplateInfo,
+        TemplateProvider, common_fields, error,
+    },
     environment::Farm,
     source::{Source, Vendor},
-    storage::Backend,
+    storage::{Backend, LocalBackend, PROXY_KIND, ProxyBackend},
     transform::Transform,
+    util::did_you_mean,
 };
 use dashmap::DashMap;
 use futures::future::BoxFuture;
-use snafu::OptionExt;
+use serde_json::{Value, json};
+use snafu::{OptionExt, ensure};
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 
 use super::ContextResult;
@@ -43,9 +50,24 @@ pub struct Registry {
     pub sources: DashMap<String, Arc<dyn Handler<Source>>>,
     pub transforms: DashMap<String, Arc<dyn Handler<Transform>>>,
     pub vendors: DashMap<String, Arc<dyn Handler<Vendor>>>,
+    pub hooks: DashMap<String, ProjectHook>,
+    pub templates: DashMap<String, TemplateProvider>,
+    pub schemas: DashMap<(Component, String), KindSchema>,
 }
 
 impl Registry {
+    /// A registry describing the storage backends edo implements itself.
+    pub fn builtin() -> Self {
+        let registry = Self::default();
+        registry.describe(Component::StorageBackend, "local", LocalBackend::describe());
+        registry.describe(
+            Component::StorageBackend,
+            PROXY_KIND,
+            ProxyBackend::describe(),
+        );
+        registry
+    }
+
     pub fn register_backend(&self, name: &str, handler: Arc<dyn Handler<Backend>>) {
         self.backends.insert(name.to_string(), handler);
     }
@@ -55,10 +77,10 @@ impl Registry {
             field: "kind",
             type_: "string",
         })?;
-        let constructor = self.backends.get(&kind).context(error::NoProviderSnafu {
-            component: "backend",
-            kind,
-        })?;
+        let constructor = self
+            .backends
+            .get(&kind)
+            .ok_or_else(|| self.no_provider(Component::StorageBackend, "backend", kind))?;
         constructor
             .call(addr.clone(), node.clone(), ctx.clone())
             .await
@@ -73,10 +95,10 @@ impl Registry {
             field: "kind",
             type_: "string",
         })?;
-        let constructor = self.farms.get(&kind).context(error::NoProviderSnafu {
-            component: "environment",
-            kind,
-        })?;
+        let constructor = self
+            .farms
+            .get(&kind)
+            .ok_or_else(|| self.no_provider(Component::Environment, "environment", kind))?;
         constructor
             .call(addr.clone(), node.clone(), ctx.clone())
             .await
@@ -91,10 +113,10 @@ impl Registry {
             field: "kind",
             type_: "string",
         })?;
-        let constructor = self.sources.get(&kind).context(error::NoProviderSnafu {
-            component: "source",
-            kind,
-        })?;
+        let constructor = self
+            .sources
+            .get(&kind)
+            .ok_or_else(|| self.no_provider(Component::Source, "source", kind))?;
         constructor
             .call(addr.clone(), node.clone(), ctx.clone())
             .await
@@ -114,15 +136,233 @@ impl Registry {
             field: "kind",
             type_: "string",
         })?;
-        let constructor = self.transforms.get(&kind).context(error::NoProviderSnafu {
-            component: "transform",
-            kind,
-        })?;
+        let constructor = self
+            .transforms
+            .get(&kind)
+            .ok_or_else(|| self.no_provider(Component::Transform, "transform", kind))?;
         constructor
             .call(addr.clone(), node.clone(), ctx.clone())
             .await
     }
 
+    /// Registers a hook invoked whenever a project is loaded. Registering
+    /// another hook under the same name replaces it.
+    pub fn register_hook(&self, name: &str, hook: ProjectHook) {
+        self.hooks.insert(name.to_string(), hook);
+    }
+
+    /// The registered hooks, ordered by name.
+    pub fn hooks(&self) -> Vec<ProjectHook> {
+        let mut hooks: Vec<(String, ProjectHook)> = self
+            .hooks
+            .iter()
+            .map(|x| (x.key().clone(), x.value().clone()))
+            .collect();
+        hooks.sort_by(|a, b| a.0.cmp(&b.0));
+        hooks.into_iter().map(|(_, hook)| hook).collect()
+    }
+
+    /// Registers a provider of project templates, addressed as
+    /// `<name>:<template>`. Registering another provider under the same name
+    /// replaces it.
+    pub fn register_templates(&self, name: &str, provider: TemplateProvider) {
+        self.templates.insert(name.to_string(), provider);
+    }
+
+    /// Every registered template as `<provider>:<template>`, ordered by name.
+    pub fn template_list(&self) -> Vec<(String, TemplateInfo)> {
+        let mut templates: Vec<_> = self
+            .templates
+            .iter()
+            .flat_map(|x| {
+                let provider = x.key().clone();
+                x.value()
+                    .templates()
+                    .into_iter()
+                    .map(move |info| (format!("{provider}:{}", info.name), info))
+            })
+            .collect();
+        templates.sort_by(|a, b| a.0.cmp(&b.0));
+        templates
+    }
+
+    /// Renders `template`, given as `<provider>:<template>` or as a template
+    /// of the [default provider](DEFAULT_TEMPLATE_PROVIDER).
+    pub fn render_template(
+        &self,
+        template: &str,
+        params: &BTreeMap<String, String>,
+    ) -> ContextResult<Vec<ScaffoldFile>> {
+        let (provider, name) = template
+            .split_once(':')
+            .unwrap_or((DEFAULT_TEMPLATE_PROVIDER, template));
+        let provider = self
+            .templates
+            .get(provider)
+            .map(|x| x.value().clone())
+            .ok_or_else(|| {
+                let names: Vec<String> = self
+                    .template_list()
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .collect();
+                error::ContextError::NoProvider {
+                    component: "template".into(),
+                    kind: template.into(),
+                    hint: provider_hint(template, "template", &names, &[]),
+                }
+            })?;
+        ensure!(
+            provider.templates().iter().any(|x| x.name == name),
+            error::TemplateSnafu {
+                template,
+                reason: "the provider has no template with that name",
+            }
+        );
+        let files = provider.render(name, params)?;
+        for file in files.iter() {
+            file.validate()?;
+        }
+        Ok(files)
+    }
+
+    /// Describes the definitions of `kind`, for `edo schema`. Describing a
+    /// kind again replaces its description.
+    pub fn describe(&self, component: Component, kind: &str, schema: KindSchema) {
+        self.schemas.insert((component, kind.to_string()), schema);
+    }
+
+    /// The description of `kind`, if it was described.
+    pub fn schema(&self, component: &Component, kind: &str) -> Option<KindSchema> {
+        self.schemas
+            .get(&(component.clone(), kind.to_string()))
+            .map(|x| x.value().clone())
+    }
+
+    /// Every registered or described kind, ordered by component then kind.
+    pub fn kinds(&self) -> Vec<(Component, String)> {
+        let mut kinds: BTreeSet<(Component, String)> =
+            self.schemas.iter().map(|x| x.key().clone()).collect();
+        let mut add = |component: Component, names: Vec<String>| {
+            kinds.extend(names.into_iter().map(|x| (component.clone(), x)));
+        };
+        add(
+            Component::StorageBackend,
+            self.backends.iter().map(|x| x.key().clone()).collect(),
+        );
+        add(
+            Component::Environment,
+            self.farms.iter().map(|x| x.key().clone()).collect(),
+        );
+        add(
+            Component::Source,
+            self.sources.iter().map(|x| x.key().clone()).collect(),
+        );
+        add(
+            Component::Transform,
+            self.transforms.iter().map(|x| x.key().clone()).collect(),
+        );
+        add(
+            Component::Vendor,
+            self.vendors.iter().map(|x| x.key().clone()).collect(),
+        );
+        kinds.into_iter().collect()
+    }
+
+    /// The error for a `kind` of `component` nothing is registered for,
+    /// suggesting the registered kinds it is closest to and pointing out when
+    /// `kind` belongs to another component.
+    fn no_provider(&self, component: Component, label: &str, kind: String) -> error::ContextError {
+        let kinds = self.kinds();
+        let supported: Vec<String> = kinds
+            .iter()
+            .filter(|x| x.0 == component)
+            .map(|x| x.1.clone())
+            .collect();
+        let elsewhere: Vec<String> = kinds
+            .iter()
+            .filter(|x| x.0 != component && x.1 == kind)
+            .map(|x| x.0.to_string())
+            .collect();
+        error::ContextError::NoProvider {
+            component: label.into(),
+            hint: provider_hint(&kind, label, &supported, &elsewhere),
+            kind,
+        }
+    }
+
+    /// The JSON Schema of a definition of `kind`. Kinds without a
+    /// description only have their `kind` checked.
+    pub fn kind_json_schema(&self, component: &Component, kind: &str) -> Value {
+        self.schema(component, kind)
+            .unwrap_or_default()
+            .fields(common_fields(component))
+            .to_json(kind)
+    }
+
+    /// The JSON Schema of a build file, accepting definitions of every
+    /// registered kind.
+    pub fn json_schema(&self) -> Value {
+        let kinds = self.kinds();
+        let definition = |component: Component, extra: Vec<FieldSchema>| {
+            let any_of: Vec<Value> = kinds
+                .iter()
+                .filter(|x| x.0 == component)
+                .map(|(component, kind)| {
+                    self.schema(component, kind)
+                        .unwrap_or_default()
+                        .fields(common_fields(component))
+                        .fields(extra.clone())
+                        .to_json(kind)
+                })
+                .collect();
+            json!({ "anyOf": any_of })
+        };
+        let section =
+            |definition: Value| json!({ "type": "object", "additionalProperties": definition });
+        let visibility = FieldType::one_of([FieldType::String, FieldType::list(FieldType::String)]);
+        let route = FieldSchema::new(
+            "route",
+            FieldType::table(FieldType::list(FieldType::String)),
+            "Sources the cache is consulted for, by kinds, prefixes and urls",
+        );
+        json!({
+            "$schema": JSON_SCHEMA_DIALECT,
+            "title": "edo build file",
+            "type": "object",
+            "required": ["schema-version"],
+            "properties": {
+                "schema-version": { "const": "1" },
+                "config": { "type": "object" },
+                "package": {
+                    "type": "object",
+                    "properties": { "visibility": visibility.to_json() },
+                },
+                "cache": {
+                    "type": "object",
+                    "properties": {
+                        "source": section(definition(Component::StorageBackend, vec![route])),
+                        "build": definition(Component::StorageBackend, Vec::new()),
+                        "output": definition(Component::StorageBackend, Vec::new()),
+                    },
+                },
+                "source": section(definition(Component::Source, Vec::new())),
+                "requires": section(json!({
+                    "type": "object",
+                    "properties": {
+                        "kind": { "type": "string" },
+                        "at": { "type": "string", "description": "Version requirement resolved by a vendor" },
+                    },
+                    "required": ["kind", "at"],
+                })),
+                "environment": section(definition(Component::Environment, Vec::new())),
+                "transform": section(definition(Component::Transform, Vec::new())),
+                "vendor": section(definition(Component::Vendor, Vec::new())),
+                "alias": section(FieldType::one_of([FieldType::String, FieldType::table(FieldType::String)]).to_json()),
+            },
+        })
+    }
+
     pub fn register_vendor(&self, name: &str, handler: Arc<dyn Handler<Vendor>>) {
         self.vendors.insert(name.to_string(), handler);
     }
@@ -132,16 +372,36 @@ impl Registry {
             field: "kind",
             type_: "string",
         })?;
-        let constructor = self.vendors.get(&kind).context(error::NoProviderSnafu {
-            component: "vendor",
-            kind,
-        })?;
+        let constructor = self
+            .vendors
+            .get(&kind)
+            .ok_or_else(|| self.no_provider(Component::Vendor, "vendor", kind))?;
         constructor
             .call(addr.clone(), node.clone(), ctx.clone())
             .await
     }
 }
 
+/// Explains a missing `kind` of `label`: the `supported` kinds closest to
+/// it, the components it is a kind of instead and the full list.
+fn provider_hint(kind: &str, label: &str, supported: &[String], elsewhere: &[String]) -> String {
+    let mut notes = Vec::new();
+    let close = did_you_mean(kind, supported.iter().map(String::as_str));
+    if !close.is_empty() {
+        let close: Vec<String> = close.iter().map(|x| format!("`{x}`")).collect();
+        notes.push(format!("did you mean {}?", close.join(" or ")));
+    }
+    for component in elsewhere {
+        notes.push(format!("`{kind}` is a {component} kind"));
+    }
+    if supported.is_empty() {
+        notes.push(format!("no {label} kinds are registered"));
+    } else {
+        notes.push(format!("supported {label} kinds: {}", supported.join(", ")));
+    }
+    format!(" ({})", notes.join("; "))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -218,6 +478,71 @@ mod tests {
         assert!(r.transforms.contains_key("kind-transform"));
     }
 
+    #[test]
+    fn json_schema_accepts_every_registered_kind() {
+        let r = Registry::builtin();
+        r.register_transform("script", dummy_transform_handler());
+        r.register_transform("custom", dummy_transform_handler());
+        r.describe(
+            Component::Transform,
+            "script",
+            KindSchema::new("Runs commands").required(
+                "commands",
+                FieldType::list(FieldType::String),
+                "",
+            ),
+        );
+        assert!(
+            r.kinds()
+                .contains(&(Component::StorageBackend, "local".to_string()))
+        );
+        assert!(
+            r.kinds()
+                .contains(&(Component::Transform, "custom".to_string()))
+        );
+
+        let schema = r.json_schema();
+        let transforms = &schema["properties"]["transform"]["additionalProperties"]["anyOf"];
+        assert_eq!(transforms.as_array().unwrap().len(), 2);
+        let script = &transforms[1];
+        assert_eq!(script["properties"]["kind"]["const"], "script");
+        assert_eq!(script["required"], json!(["kind", "commands"]));
+        // Undescribed kinds still validate their kind and loader fields
+        let custom = r.kind_json_schema(&Component::Transform, "custom");
+        assert_eq!(custom, transforms[0]);
+        assert!(custom["properties"]["visibility"].is_object());
+        let caches = &schema["properties"]["cache"]["properties"];
+        assert!(
+            caches["source"]["additionalProperties"]["anyOf"][0]["properties"]["route"].is_object()
+        );
+        assert!(caches["build"]["anyOf"][0]["properties"]["route"].is_null());
+    }
+
+    #[test]
+    fn missing_kinds_suggest_registered_ones() {
+        let r = Registry::default();
+        r.register_source("git", dummy_source_handler());
+        r.register_source("local", dummy_source_handler());
+        r.register_transform("script", dummy_transform_handler());
+        let err = r.no_provider(Component::Source, "source", "gti".into());
+        assert_eq!(
+            err.to_string(),
+            "no implementation is loaded that supports a source of kind gti \
+             (did you mean `git`?; supported source kinds: git, local)"
+        );
+        let err = r.no_provider(Component::Transform, "transform", "git".into());
+        assert_eq!(
+            err.to_string(),
+            "no implementation is loaded that supports a transform of kind git \
+             (`git` is a source kind; supported transform kinds: script)"
+        );
+        let err = r.no_provider(Component::Vendor, "vendor", "image".into());
+        assert!(
+            err.to_string()
+                .ends_with("(no vendor kinds are registered)")
+        );
+    }
+
     #[test]
     fn register_vendor_inserts_into_map() {
         let r = Registry::default();
@@ -0,0 +1,497 @@
+use super::{Addr, ContextResult, Durations, error};
+use crate::environment::CommandFailure;
+use crate::storage::Access;
+use crate::transform::KeyComponent;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt, ResultExt};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::fs::{create_dir_all, read_dir, read_to_string, remove_dir_all, write};
+use uuid::Uuid;
+
+/// Final state of a whole run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    /// The run is still in progress, or exited without recording a result.
+    Running,
+    /// Every requested target was built or found in the cache.
+    Success,
+    /// At least one transform failed or the run could not start.
+    Failed,
+}
+
+impl fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Running => "running",
+            Self::Success => "success",
+            Self::Failed => "failed",
+        })
+    }
+}
+
+/// What happened to a single transform during a run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeOutcome {
+    /// The build cache already held the artifact, nothing was executed.
+    Cached,
+    /// The transform executed and produced an artifact.
+    Built,
+    /// The transform executed and failed.
+    Failed,
+    /// The transform was never dispatched, usually because another failed.
+    Skipped,
+}
+
+impl fmt::Display for NodeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cached => "cached",
+            Self::Built => "built",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        })
+    }
+}
+
+/// The recorded result of one transform in a run.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeSummary {
+    /// Address of the transform.
+    pub addr: Addr,
+    /// Content-addressed id of the transform's artifact, if it was computed.
+    pub id: Option<String>,
+    /// What happened to the transform.
+    pub outcome: NodeOutcome,
+    /// Wall time spent executing the transform, in milliseconds.
+    pub duration_ms: u64,
+    /// `true` when the artifact was pushed to the build cache.
+    pub uploaded: bool,
+    /// The error the transform failed with.
+    pub error: Option<String>,
+    /// Path to the transform's log file.
+    pub log: Option<PathBuf>,
+    /// The last lines of command output of a failed transform.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tail: Vec<String>,
+    /// The script command a failed transform stopped at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<CommandFailure>,
+    /// Where the transform's workspace was kept with `--keep-workspace`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<PathBuf>,
+    /// The components of the cache key the id was derived from.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key: Vec<KeyComponent>,
+    /// How long earlier runs took to execute the transform, in milliseconds,
+    /// see [`Durations`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate_ms: Option<u64>,
+}
+
+/// A machine readable record of a single `edo run`.
+///
+/// Written to `<storage>/runs/<id>.json` when the run finishes, whether it
+/// succeeded or not, so CI systems can archive results without scraping
+/// console output.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunSummary {
+    /// Unique id of the run. Ids are time ordered.
+    pub id: String,
+    /// The targets requested on the command line, after matrix expansion.
+    pub targets: Vec<Addr>,
+    /// When the run started.
+    pub started: DateTime<Utc>,
+    /// When the run finished.
+    pub finished: Option<DateTime<Utc>>,
+    /// Overall result of the run.
+    pub status: RunStatus,
+    /// The error the run failed with.
+    pub error: Option<String>,
+    /// Every transform the run considered, in address order.
+    pub nodes: Vec<NodeSummary>,
+    /// How long the run was expected to take when it started, in
+    /// milliseconds, if earlier runs executed its transforms.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate_ms: Option<u64>,
+}
+
+impl RunSummary {
+    /// Starts a new summary for `targets`.
+    pub fn start(targets: &[Addr]) -> Self {
+        Self {
+            id: Uuid::now_v7().to_string(),
+            targets: targets.to_vec(),
+            started: Utc::now(),
+            finished: None,
+            status: RunStatus::Running,
+            error: None,
+            nodes: Vec::new(),
+            estimate_ms: None,
+        }
+    }
+
+    /// Records the outcome of the run and stamps the finish time.
+    pub fn finish(&mut self, error: Option<String>) {
+        self.finished = Some(Utc::now());
+        self.status = if error.is_some() {
+            RunStatus::Failed
+        } else {
+            RunStatus::Success
+        };
+        self.error = error;
+    }
+
+    /// Returns the nodes that did not complete successfully.
+    pub fn failures(&self) -> impl Iterator<Item = &NodeSummary> {
+        self.nodes
+            .iter()
+            .filter(|x| x.outcome == NodeOutcome::Failed)
+    }
+
+    /// Returns the artifact id of every transform that was built or found in
+    /// the cache, i.e. what a later run of the same targets would need.
+    pub fn artifacts(&self) -> impl Iterator<Item = (&Addr, &str)> {
+        self.nodes.iter().filter_map(|x| match x.outcome {
+            NodeOutcome::Built | NodeOutcome::Cached => Some((&x.addr, x.id.as_deref()?)),
+            NodeOutcome::Failed | NodeOutcome::Skipped => None,
+        })
+    }
+
+    /// Reads a summary from `path`, such as one copied from another machine.
+    pub async fn read(path: &Path) -> ContextResult<Self> {
+        let content = read_to_string(path).await.context(error::IoSnafu)?;
+        serde_json::from_str(&content).context(error::RunSummarySnafu { path })
+    }
+
+    /// Returns the total wall time of the run, if it has finished.
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.finished
+            .map(|x| (x - self.started).num_milliseconds().max(0) as u64)
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "run {}", self.id)?;
+        writeln!(f, "  status: {}", self.status)?;
+        writeln!(
+            f,
+            "  targets: {}",
+            self.targets
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+        writeln!(f, "  started: {}", self.started.to_rfc3339())?;
+        if let Some(duration) = self.duration_ms() {
+            write!(f, "  duration: {duration}ms")?;
+            if let Some(estimate) = self.estimate_ms {
+                write!(f, " (estimated {estimate}ms)")?;
+            }
+            writeln!(f)?;
+        }
+        if let Some(error) = self.error.as_ref() {
+            writeln!(f, "  error: {error}")?;
+        }
+        for node in self.nodes.iter() {
+            write!(
+                f,
+                "  {} {} ({}ms",
+                node.outcome, node.addr, node.duration_ms
+            )?;
+            if let Some(estimate) = node.estimate_ms {
+                write!(f, ", estimated {estimate}ms")?;
+            }
+            f.write_str(")")?;
+            if node.uploaded {
+                f.write_str(" uploaded")?;
+            }
+            writeln!(f)?;
+            if let Some(error) = node.error.as_ref() {
+                writeln!(f, "    error: {error}")?;
+            }
+            if let Some(failure) = node.failure.as_ref() {
+                write!(
+                    f,
+                    "    failed at command {}: {}",
+                    failure.index, failure.command
+                )?;
+                if let Some(code) = failure.code {
+                    write!(f, " (exit code {code})")?;
+                }
+                writeln!(f)?;
+            }
+            if let Some(log) = node.log.as_ref() {
+                writeln!(f, "    log: {}", log.display())?;
+            }
+            if let Some(workspace) = node.workspace.as_ref() {
+                writeln!(f, "    workspace: {}", workspace.display())?;
+            }
+            if !node.tail.is_empty() {
+                writeln!(f, "    last output:")?;
+                for line in node.tail.iter() {
+                    writeln!(f, "      {line}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The directory of run summaries kept under the storage path.
+#[derive(Clone, Debug)]
+pub struct RunHistory {
+    path: PathBuf,
+}
+
+impl RunHistory {
+    /// Opens the run history stored in `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the directory summaries are written to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes `summary` as `<id>.json`, replacing any earlier copy.
+    pub async fn save(&self, summary: &RunSummary) -> ContextResult<PathBuf> {
+        create_dir_all(&self.path).await.context(error::IoSnafu)?;
+        let target = self.path.join(format!("{}.json", summary.id));
+        let content = serde_json::to_string_pretty(summary).context(error::SerializeSnafu)?;
+        write(&target, content).await.context(error::IoSnafu)?;
+        Ok(target)
+    }
+
+    /// Returns the file the external accesses of run `id` are written to.
+    pub fn audit_path(&self, id: &str) -> PathBuf {
+        self.path.join("audit").join(format!("{id}.json"))
+    }
+
+    /// Writes the external `accesses` made during run `id` to [`RunHistory::audit_path`].
+    pub async fn save_audit(&self, id: &str, accesses: &[Access]) -> ContextResult<PathBuf> {
+        let target = self.audit_path(id);
+        create_dir_all(self.path.join("audit"))
+            .await
+            .context(error::IoSnafu)?;
+        let content = serde_json::to_string_pretty(accesses).context(error::SerializeSnafu)?;
+        write(&target, content).await.context(error::IoSnafu)?;
+        Ok(target)
+    }
+
+    /// Loads the external accesses recorded for run `id`, empty if none were.
+    pub async fn load_audit(&self, id: &str) -> ContextResult<Vec<Access>> {
+        let target = self.audit_path(id);
+        if !target.exists() {
+            return Ok(Vec::new());
+        }
+        let content = read_to_string(&target).await.context(error::IoSnafu)?;
+        serde_json::from_str(&content).context(error::RunSummarySnafu { path: target })
+    }
+
+    /// Returns the file the [`Durations`] of executed transforms are kept in.
+    pub fn durations_path(&self) -> PathBuf {
+        self.path.join("durations").join("transforms.json")
+    }
+
+    /// Loads the recorded durations of executed transforms.
+    pub async fn durations(&self) -> Durations {
+        Durations::load(&self.durations_path()).await
+    }
+
+    /// Adds the durations of the transforms `summary` built to those
+    /// recorded, see [`Durations::record_run`].
+    pub async fn record_durations(&self, summary: &RunSummary) -> ContextResult<()> {
+        let mut durations = self.durations().await;
+        durations.record_run(summary);
+        durations.save(&self.durations_path()).await
+    }
+
+    /// Removes every recorded summary.
+    pub async fn clear(&self) -> ContextResult<()> {
+        if self.path.exists() {
+            remove_dir_all(&self.path).await.context(error::IoSnafu)?;
+        }
+        Ok(())
+    }
+
+    /// Loads the summary for run `id`.
+    pub async fn load(&self, id: &str) -> ContextResult<RunSummary> {
+        let target = self.path.join(format!("{id}.json"));
+        if !target.exists() {
+            return error::NoRunSnafu { id }.fail();
+        }
+        RunSummary::read(&target).await
+    }
+
+    /// Loads the most recent summary, if any run has been recorded.
+    pub async fn latest(&self) -> ContextResult<Option<RunSummary>> {
+        Ok(self.list().await?.pop())
+    }
+
+    /// Loads every recorded summary, oldest first.
+    ///
+    /// Files that are not valid summaries are skipped with a warning.
+    pub async fn list(&self) -> ContextResult<Vec<RunSummary>> {
+        let mut runs = Vec::new();
+        if !self.path.exists() {
+            return Ok(runs);
+        }
+        let mut entries = read_dir(&self.path).await.context(error::IoSnafu)?;
+        while let Some(entry) = entries.next_entry().await.context(error::IoSnafu)? {
+            let path = entry.path();
+            if path.extension().is_none_or(|x| x != "json") {
+                continue;
+            }
+            let content = read_to_string(&path).await.context(error::IoSnafu)?;
+            match serde_json::from_str::<RunSummary>(&content) {
+                Ok(summary) => runs.push(summary),
+                Err(e) => warn!(
+                    component = "context",
+                    "ignoring unreadable run summary {}: {e}",
+                    path.display()
+                ),
+            }
+        }
+        runs.sort_by(|a, b| a.started.cmp(&b.started).then(a.id.cmp(&b.id)));
+        Ok(runs)
+    }
+
+    /// Resolves `id` to a summary, accepting `latest` or any unique prefix.
+    pub async fn find(&self, id: &str) -> ContextResult<RunSummary> {
+        if id == "latest" {
+            return self.latest().await?.context(error::NoRunSnafu { id });
+        }
+        let mut matches = self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|x| x.id.starts_with(id))
+            .collect::<Vec<_>>();
+        match matches.len() {
+            0 => error::NoRunSnafu { id }.fail(),
+            1 => Ok(matches.remove(0)),
+            _ => error::AmbiguousRunSnafu { id }.fail(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn node(addr: &str, outcome: NodeOutcome) -> NodeSummary {
+        NodeSummary {
+            addr: Addr::parse(addr).unwrap(),
+            id: Some(format!("{}-abc", addr.trim_start_matches("//"))),
+            outcome,
+            duration_ms: 12,
+            uploaded: false,
+            error: None,
+            log: None,
+            tail: Vec::new(),
+            failure: None,
+            workspace: None,
+            key: Vec::new(),
+            estimate_ms: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn saved_summaries_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let history = RunHistory::new(dir.path().join("runs"));
+        let mut summary = RunSummary::start(&[Addr::parse("//a").unwrap()]);
+        summary.nodes.push(node("//a", NodeOutcome::Built));
+        summary.finish(None);
+
+        let path = history.save(&summary).await.unwrap();
+        assert_eq!(path, dir.path().join(format!("runs/{}.json", summary.id)));
+        assert_eq!(history.load(&summary.id).await.unwrap(), summary);
+        assert_eq!(history.find(&summary.id[..8]).await.unwrap(), summary);
+    }
+
+    #[tokio::test]
+    async fn list_orders_runs_and_skips_garbage() {
+        let dir = TempDir::new().unwrap();
+        let history = RunHistory::new(dir.path());
+        let first = RunSummary::start(&[Addr::parse("//a").unwrap()]);
+        let mut second = RunSummary::start(&[Addr::parse("//b").unwrap()]);
+        second.finish(Some("boom".to_string()));
+        history.save(&second).await.unwrap();
+        history.save(&first).await.unwrap();
+        tokio::fs::write(dir.path().join("junk.json"), "{")
+            .await
+            .unwrap();
+
+        let runs = history.list().await.unwrap();
+        assert_eq!(runs, vec![first, second.clone()]);
+        assert_eq!(history.find("latest").await.unwrap(), second);
+        assert_eq!(second.status, RunStatus::Failed);
+        assert_eq!(second.error.as_deref(), Some("boom"));
+    }
+
+    #[tokio::test]
+    async fn audits_are_kept_beside_summaries() {
+        use crate::storage::AccessKind;
+
+        let dir = TempDir::new().unwrap();
+        let history = RunHistory::new(dir.path());
+        let summary = RunSummary::start(&[Addr::parse("//a").unwrap()]);
+        history.save(&summary).await.unwrap();
+        assert!(history.load_audit(&summary.id).await.unwrap().is_empty());
+
+        let accesses =
+            vec![Access::new(AccessKind::Fetch, "https://example.com/a.tar.gz").digest("abc")];
+        let path = history.save_audit(&summary.id, &accesses).await.unwrap();
+        assert_eq!(path, dir.path().join(format!("audit/{}.json", summary.id)));
+        assert_eq!(history.load_audit(&summary.id).await.unwrap(), accesses);
+        // Audits are not mistaken for summaries
+        assert_eq!(history.list().await.unwrap(), vec![summary]);
+    }
+
+    #[tokio::test]
+    async fn missing_history_is_empty() {
+        let dir = TempDir::new().unwrap();
+        let history = RunHistory::new(dir.path().join("runs"));
+        assert!(history.list().await.unwrap().is_empty());
+        assert!(history.find("latest").await.is_err());
+        history.clear().await.unwrap();
+    }
+
+    #[test]
+    fn display_lists_failures_with_logs() {
+        let mut summary = RunSummary::start(&[Addr::parse("//a").unwrap()]);
+        let mut failed = node("//a", NodeOutcome::Failed);
+        failed.error = Some("exit status 1".to_string());
+        failed.log = Some(PathBuf::from("/tmp/a.log"));
+        failed.workspace = Some(PathBuf::from("/p/.edo/debug/a-20260101T000000Z"));
+        summary.nodes.push(node("//b", NodeOutcome::Cached));
+        summary.nodes.push(failed);
+        summary.estimate_ms = Some(30);
+        summary.finish(Some("a failed".to_string()));
+
+        let text = summary.to_string();
+        assert!(text.contains("status: failed"), "{text}");
+        assert!(text.contains("cached //b (12ms)"), "{text}");
+        assert!(text.contains("ms (estimated 30ms)"), "{text}");
+        assert!(text.contains("error: exit status 1"), "{text}");
+        assert!(text.contains("log: /tmp/a.log"), "{text}");
+        assert!(
+            text.contains("workspace: /p/.edo/debug/a-20260101T000000Z"),
+            "{text}"
+        );
+        assert_eq!(summary.failures().count(), 1);
+        let artifacts = summary.artifacts().collect::<Vec<_>>();
+        assert_eq!(artifacts, vec![(&Addr::parse("//b").unwrap(), "b-abc")]);
+    }
+}
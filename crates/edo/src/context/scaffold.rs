@@ -0,0 +1,135 @@
+//! Project templates for `edo init`.
+//!
+//! A [`TemplateProvider`] registered with the [`Registry`](super::Registry)
+//! under a provider name offers named project layouts, addressed as
+//! `<provider>:<template>` (e.g. `core:rust`). Organizations can register a
+//! provider next to their components to ship standardized layouts.
+
+use super::{ContextResult, error};
+use arc_handle::arc_handle;
+use snafu::ensure;
+use std::collections::BTreeMap;
+use std::path::{Component, PathBuf};
+
+/// Provider templates are looked up in when `edo init --template` names no
+/// provider.
+pub const DEFAULT_TEMPLATE_PROVIDER: &str = "core";
+
+/// A template offered by a [`TemplateProvider`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TemplateInfo {
+    /// Name of the template within its provider.
+    pub name: String,
+    /// One line describing the layout it creates.
+    pub description: String,
+}
+
+/// A file rendered from a template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScaffoldFile {
+    /// Path relative to the project directory.
+    pub path: PathBuf,
+    /// Contents of the file.
+    pub contents: String,
+}
+
+impl ScaffoldFile {
+    /// A file at `path` holding `contents`.
+    pub fn new(path: impl Into<PathBuf>, contents: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            contents: contents.into(),
+        }
+    }
+
+    /// Checks the file stays inside the project directory.
+    pub fn validate(&self) -> ContextResult<()> {
+        ensure!(
+            self.path.components().next().is_some()
+                && self
+                    .path
+                    .components()
+                    .all(|x| matches!(x, Component::Normal(_) | Component::CurDir)),
+            error::TemplateSnafu {
+                template: self.path.display().to_string(),
+                reason: "template files must be relative paths inside the project",
+            }
+        );
+        Ok(())
+    }
+}
+
+/// Supplies project templates.
+#[arc_handle]
+pub trait TemplateProvider {
+    /// The templates this provider offers.
+    fn templates(&self) -> Vec<TemplateInfo>;
+    /// Renders the template `name` with `params`, e.g. `name` for the package
+    /// name. Only called with names listed by [`templates`](Self::templates).
+    fn render(
+        &self,
+        name: &str,
+        params: &BTreeMap<String, String>,
+    ) -> ContextResult<Vec<ScaffoldFile>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Registry;
+
+    struct Layouts;
+
+    impl TemplateProviderImpl for Layouts {
+        fn templates(&self) -> Vec<TemplateInfo> {
+            ["lib", "escape"]
+                .map(|name| TemplateInfo {
+                    name: name.to_string(),
+                    description: String::new(),
+                })
+                .to_vec()
+        }
+
+        fn render(
+            &self,
+            name: &str,
+            params: &BTreeMap<String, String>,
+        ) -> ContextResult<Vec<ScaffoldFile>> {
+            let path = match name {
+                "lib" => format!("{}/edo.toml", params["name"]),
+                _ => "../edo.toml".to_string(),
+            };
+            Ok(vec![ScaffoldFile::new(path, "schema-version = \"1\"\n")])
+        }
+    }
+
+    #[test]
+    fn templates_render_through_their_provider() {
+        let registry = Registry::default();
+        registry.register_templates("acme", TemplateProvider::new(Layouts));
+        registry.register_templates(DEFAULT_TEMPLATE_PROVIDER, TemplateProvider::new(Layouts));
+        let names: Vec<_> = registry.template_list().into_iter().map(|x| x.0).collect();
+        assert_eq!(
+            names,
+            ["acme:escape", "acme:lib", "core:escape", "core:lib"]
+        );
+
+        let params = BTreeMap::from([("name".to_string(), "fw".to_string())]);
+        let files = registry.render_template("acme:lib", &params).unwrap();
+        assert_eq!(files[0].path, PathBuf::from("fw/edo.toml"));
+        assert_eq!(registry.render_template("lib", &params).unwrap(), files);
+        for template in ["acme:escape", "acme:app", "other:lib"] {
+            assert!(registry.render_template(template, &params).is_err());
+        }
+    }
+
+    #[test]
+    fn files_must_stay_inside_the_project() {
+        for path in ["edo.toml", "app/code/main.c", "./app/edo.toml"] {
+            ScaffoldFile::new(path, "").validate().unwrap();
+        }
+        for path in ["", "/etc/passwd", "../outside", "app/../../outside"] {
+            assert!(ScaffoldFile::new(path, "").validate().is_err(), "{path}");
+        }
+    }
+}
@@ -1,17 +1,64 @@
-//! TOML schema deserialization for `edo.toml` project files.
+//! Schema deserialization for `edo.toml` project files.
 //!
-//! [`Schema`] is the top-level enum dispatching on `schema-version`.
+//! [`BuildFormat`] tells build files apart and parses them, TOML or YAML, into
+//! the same [`Schema`]. [`Schema`] is the top-level enum dispatching on `schema-version`.
 //! [`SchemaV1`] holds the v1 layout: config, cache, plugins, environments,
-//! sources, transforms, vendors, and requires sections. [`Cache`] groups the
+//! sources, transforms, vendors, requires, alias, and package sections. [`Cache`] groups the
 //! three cache categories (source, build, output). The [`toml_def_item`]
 //! helper converts a raw TOML table entry into a [`Node`] definition.
 
 use crate::context::{ContextResult, Node, error};
 use serde::{Deserialize, Serialize};
-use snafu::OptionExt;
+use snafu::{OptionExt, ResultExt};
 use std::collections::BTreeMap;
+use std::path::Path;
 use toml::map::Map;
 
+/// Stem shared by every build file name.
+const BUILD_FILE_STEM: &str = "edo";
+
+/// The languages a build file can be written in.
+///
+/// A directory may hold any number of build files: `edo.toml`, `edo.yaml`
+/// and `<name>.edo.toml` / `<name>.edo.yaml` (`.yml` works too). They all
+/// declare into the directory's package.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildFormat {
+    /// `edo.toml` and `*.edo.toml`.
+    Toml,
+    /// `edo.yaml` and `*.edo.yaml`, or `.yml`.
+    Yaml,
+}
+
+impl BuildFormat {
+    /// Returns the format of the build file at `path`, or `None` when `path`
+    /// is not named like a build file.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let (stem, extension) = name.rsplit_once('.')?;
+        let named = stem
+            .strip_suffix(BUILD_FILE_STEM)
+            .and_then(|x| x.strip_suffix('.'))
+            .is_some_and(|x| !x.is_empty());
+        if stem != BUILD_FILE_STEM && !named {
+            return None;
+        }
+        match extension {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Parses the contents of a build file.
+    pub fn parse(&self, bytes: &[u8]) -> ContextResult<Schema> {
+        match self {
+            Self::Toml => toml::from_slice(bytes).context(error::DeserializeSnafu),
+            Self::Yaml => serde_yaml::from_slice(bytes).context(error::DeserializeYamlSnafu),
+        }
+    }
+}
+
 /// Top-level schema envelope, dispatching on the `schema-version` field.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "schema-version")]
@@ -49,6 +96,10 @@ pub struct SchemaV1 {
     vendor: BTreeMap<String, toml::Value>,
     #[serde(default)]
     requires: BTreeMap<String, toml::Value>,
+    #[serde(default)]
+    alias: BTreeMap<String, toml::Value>,
+    #[serde(default)]
+    package: Map<String, toml::Value>,
 }
 
 fn toml_map(table: &toml::map::Map<String, toml::Value>) -> ContextResult<BTreeMap<String, Node>> {
@@ -125,6 +176,11 @@ impl SchemaV1 {
         toml_map(&self.config)
     }
 
+    /// Returns the `[package]` defaults for this file's definitions as nodes.
+    pub fn get_package(&self) -> ContextResult<BTreeMap<String, Node>> {
+        toml_map(&self.package)
+    }
+
     /// Returns the environment definitions as nodes.
     pub fn get_environments(&self) -> ContextResult<BTreeMap<String, Node>> {
         toml_def(&self.environment, "environment")
@@ -149,12 +205,56 @@ impl SchemaV1 {
     pub fn get_requires(&self) -> ContextResult<BTreeMap<String, Node>> {
         toml_def(&self.requires, "requires")
     }
+
+    /// Returns the declared aliases as `(target, message)` pairs.
+    ///
+    /// An alias is either a bare target string or a table with a `target`
+    /// and an optional deprecation `message`.
+    pub fn get_aliases(&self) -> ContextResult<BTreeMap<String, (String, Option<String>)>> {
+        let mut aliases = BTreeMap::new();
+        for (name, value) in self.alias.iter() {
+            let alias = match value {
+                toml::Value::String(target) => (target.clone(), None),
+                toml::Value::Table(table) => {
+                    let target = table.get("target").and_then(|x| x.as_str()).context(
+                        error::FieldSnafu {
+                            field: "target",
+                            type_: "string",
+                        },
+                    )?;
+                    let message = match table.get("message") {
+                        Some(message) => Some(
+                            message
+                                .as_str()
+                                .context(error::FieldSnafu {
+                                    field: "message",
+                                    type_: "string",
+                                })?
+                                .to_string(),
+                        ),
+                        None => None,
+                    };
+                    (target.to_string(), message)
+                }
+                _ => {
+                    return error::FieldSnafu {
+                        field: name.as_str(),
+                        type_: "string / table",
+                    }
+                    .fail();
+                }
+            };
+            aliases.insert(name.clone(), alias);
+        }
+        Ok(aliases)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::context::ContextError;
-    use crate::context::schema::Schema;
+    use crate::context::schema::{BuildFormat, Schema};
+    use std::path::Path;
 
     #[test]
     fn test_deserialize() {
@@ -190,6 +290,69 @@ commands    = [
         let _: Schema = toml::from_str(toml_str).expect("failed to parse");
     }
 
+    #[test]
+    fn build_files_are_detected_by_name() {
+        for (name, format) in [
+            ("edo.toml", Some(BuildFormat::Toml)),
+            ("tools.edo.toml", Some(BuildFormat::Toml)),
+            ("edo.yaml", Some(BuildFormat::Yaml)),
+            ("tools.edo.yml", Some(BuildFormat::Yaml)),
+            ("Cargo.toml", None),
+            ("toedo.yaml", None),
+            (".edo.toml", None),
+            ("edo.json", None),
+            ("edo", None),
+        ] {
+            assert_eq!(BuildFormat::detect(Path::new(name)), format, "{name}");
+        }
+    }
+
+    #[test]
+    fn yaml_maps_onto_the_same_nodes_as_toml() {
+        let toml_str = r#"schema-version = "1"
+[source.code]
+kind = "local"
+path = "src"
+[transform.build]
+kind     = "script"
+source   = "//pkg/code"
+commands = ["make", ["cp", "out", "{{install-root}}"]]
+jobs     = 4
+"#;
+        let yaml_str = r#"
+schema-version: "1"
+source:
+  code:
+    kind: local
+    path: src
+transform:
+  build:
+    kind: script
+    source: //pkg/code
+    commands:
+      - make
+      - [cp, out, "{{install-root}}"]
+    jobs: 4
+"#;
+        let Schema::V1(from_toml) = BuildFormat::Toml.parse(toml_str.as_bytes()).unwrap();
+        let Schema::V1(from_yaml) = BuildFormat::Yaml.parse(yaml_str.as_bytes()).unwrap();
+        assert_eq!(
+            serde_json::to_value(from_yaml.get_sources().unwrap()).unwrap(),
+            serde_json::to_value(from_toml.get_sources().unwrap()).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(from_yaml.get_transforms().unwrap()).unwrap(),
+            serde_json::to_value(from_toml.get_transforms().unwrap()).unwrap()
+        );
+        let err = BuildFormat::Yaml
+            .parse(b"schema-version: \"1\"\nsource: [")
+            .unwrap_err();
+        assert!(
+            matches!(err, ContextError::DeserializeYaml { .. }),
+            "{err:?}"
+        );
+    }
+
     #[test]
     fn deserialize_empty_v1_defaults_all_fields() {
         let s: Schema = toml::from_str("schema-version = \"1\"").expect("parse");
@@ -202,6 +365,8 @@ commands    = [
         assert!(v1.get_transforms().unwrap().is_empty());
         assert!(v1.get_vendors().unwrap().is_empty());
         assert!(v1.get_requires().unwrap().is_empty());
+        assert!(v1.get_aliases().unwrap().is_empty());
+        assert!(v1.get_package().unwrap().is_empty());
     }
 
     #[test]
@@ -351,4 +516,37 @@ source = "x"
             "got: {err:?}"
         );
     }
+
+    #[test]
+    fn aliases_accept_strings_and_tables() {
+        let toml_str = r#"schema-version = "1"
+[alias]
+short = "//new/short"
+
+[alias.long]
+target  = "emit"
+message = "renamed in 2.0"
+"#;
+        let Schema::V1(v1) = toml::from_str(toml_str).unwrap();
+        let aliases = v1.get_aliases().unwrap();
+        assert_eq!(aliases["short"], ("//new/short".to_string(), None));
+        assert_eq!(
+            aliases["long"],
+            ("emit".to_string(), Some("renamed in 2.0".to_string()))
+        );
+    }
+
+    #[test]
+    fn alias_without_target_returns_field_error() {
+        let toml_str = r#"schema-version = "1"
+[alias.old]
+message = "gone"
+"#;
+        let Schema::V1(v1) = toml::from_str(toml_str).unwrap();
+        let err = v1.get_aliases().expect_err("expected Field error");
+        assert!(
+            matches!(err, ContextError::Field { ref field, .. } if field == "target"),
+            "got: {err:?}"
+        );
+    }
 }
@@ -0,0 +1,117 @@
+//! What a run would rebuild, and why, without running the scheduler.
+//!
+//! Local sources are content addressed, so one whose current id is missing
+//! from the local cache changed since a run last cached it. Transforms are
+//! stale when no cache holds the artifact of their current id, and each is
+//! explained by the dirty sources it or its environment reads, the stale
+//! transforms it depends on, or the id the last run recorded for it.
+
+use super::{Addr, Context, ContextResult};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// A transform whose current artifact is in no cache.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StaleTransform {
+    /// Address of the transform.
+    pub addr: Addr,
+    /// Id of the artifact a run would build.
+    pub id: String,
+    /// Why the artifact has to be built, most specific first.
+    pub reasons: Vec<String>,
+}
+
+/// The local sources that changed and the transforms a run would rebuild,
+/// see [`Context::status`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct WorkspaceStatus {
+    /// Local sources whose content is not in the local cache, in address order.
+    pub dirty: Vec<Addr>,
+    /// Transforms that are not built, in address order.
+    pub stale: Vec<StaleTransform>,
+}
+
+impl Context {
+    /// Reports which local sources changed since they were last cached and
+    /// which transforms of the loaded project are not built, without
+    /// fetching or building anything.
+    pub async fn status(&self) -> ContextResult<WorkspaceStatus> {
+        let mut dirty = BTreeSet::new();
+        for addr in self.source_addrs() {
+            let Some(source) = self.get_source(&addr).await? else {
+                continue;
+            };
+            // Only sources read from the project tree change under the user
+            if source.owned_paths().is_empty() {
+                continue;
+            }
+            if !self
+                .storage()
+                .safe_has(&source.get_unique_id().await?)
+                .await?
+            {
+                dirty.insert(addr);
+            }
+        }
+
+        let handle = self.get_handle();
+        let mut transforms = Vec::new();
+        for addr in self.transform_addrs() {
+            let Some(transform) = handle.get(&addr) else {
+                continue;
+            };
+            let id = handle.unique_id(&addr, &transform).await?;
+            transforms.push((addr, transform, id));
+        }
+        let ids: Vec<_> = transforms.iter().map(|(_, _, id)| id.clone()).collect();
+        let built = self.storage().find_builds(&ids).await?;
+        let stale: BTreeSet<Addr> = transforms
+            .iter()
+            .filter(|(_, _, id)| !built.contains(id))
+            .map(|(addr, _, _)| addr.clone())
+            .collect();
+        // The id each transform had in the latest run that recorded it
+        let mut previous = HashMap::new();
+        for run in self.runs().list().await? {
+            for (addr, id) in run.artifacts() {
+                previous.insert(addr.clone(), id.to_string());
+            }
+        }
+
+        let mut status = WorkspaceStatus {
+            dirty: dirty.iter().cloned().collect(),
+            stale: Vec::new(),
+        };
+        for (addr, transform, id) in transforms {
+            if !stale.contains(&addr) {
+                continue;
+            }
+            let environment = self.resolve_alias(&transform.environment().await?);
+            let mut reasons: Vec<String> = self
+                .get_element_sources(&addr)
+                .into_iter()
+                .chain(self.get_element_sources(&environment))
+                .filter(|x| dirty.contains(x))
+                .map(|x| format!("source {x} changed"))
+                .collect();
+            for dep in transform.depends().await? {
+                let dep = self.resolve_alias(&dep);
+                if stale.contains(&dep) {
+                    reasons.push(format!("depends on {dep}, which is stale"));
+                }
+            }
+            if reasons.is_empty() {
+                reasons.push(match previous.get(&addr) {
+                    Some(last) => format!("its inputs changed since it was built as {last}"),
+                    None => "it was never built".to_string(),
+                });
+            }
+            status.stale.push(StaleTransform {
+                addr,
+                id: id.to_string(),
+                reasons,
+            });
+        }
+        Ok(status)
+    }
+}
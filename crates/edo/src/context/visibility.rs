@@ -0,0 +1,203 @@
+//! Per-package visibility of transforms.
+//!
+//! A transform definition may carry a `visibility` key listing which packages
+//! are allowed to depend on it, and an `edo.toml` may set the default for its
+//! transforms with a `[package]` table:
+//!
+//! ```toml
+//! [package]
+//! visibility = "private"
+//!
+//! [transform.build]
+//! kind       = "script"
+//! visibility = ["//app/...", "//tools/release"]
+//! ```
+//!
+//! Each entry is one of `public` (or `//...`), `private` (the declaring
+//! package only), `//pkg` (exactly that package) or `//pkg/...` (that package
+//! and every package below it). Entries not starting with `//` are relative
+//! to the declaring package. Transforms without a rule are public.
+
+use super::{Addr, ContextResult as Result, Node, error};
+use snafu::OptionExt;
+use std::fmt;
+
+/// Key holding the visibility rules on a transform definition or `[package]` table.
+pub const VISIBILITY_KEY: &str = "visibility";
+
+/// A single visibility rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Rule {
+    Public,
+    Private,
+    Package(Addr),
+    Subtree(Addr),
+}
+
+/// The packages allowed to depend on a transform.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Visibility {
+    rules: Vec<Rule>,
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Self {
+            rules: vec![Rule::Public],
+        }
+    }
+}
+
+/// Returns the package (directory namespace) an address belongs to.
+fn package(addr: &Addr) -> Addr {
+    addr.parent().unwrap_or_default()
+}
+
+impl Visibility {
+    /// Parses the `visibility` value of a definition declared in `namespace`.
+    ///
+    /// Accepts a single rule string or a list of them.
+    pub fn from_node(namespace: &Addr, node: &Node) -> Result<Self> {
+        let entries = match node.as_string() {
+            Some(entry) => vec![entry],
+            None => node
+                .as_list()
+                .context(error::FieldSnafu {
+                    field: VISIBILITY_KEY,
+                    type_: "string / array of string",
+                })?
+                .iter()
+                .map(|x| {
+                    x.as_string().context(error::FieldSnafu {
+                        field: VISIBILITY_KEY,
+                        type_: "string / array of string",
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+        };
+        let rules = entries
+            .iter()
+            .map(|entry| Self::rule(namespace, entry))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    fn rule(namespace: &Addr, entry: &str) -> Result<Rule> {
+        let rule = match entry {
+            "public" | "//..." => Rule::Public,
+            "private" => Rule::Private,
+            "..." => Rule::Subtree(namespace.clone()),
+            entry => {
+                let (path, subtree) = match entry.strip_suffix("/...") {
+                    Some(path) => (path, true),
+                    None => (entry, false),
+                };
+                let addr = if path.starts_with("//") {
+                    Addr::parse(path)?
+                } else {
+                    path.split('/').fold(namespace.clone(), |x, y| x.join(y))
+                };
+                if addr.to_id().split('/').any(|x| x.is_empty() || x == "...") {
+                    return error::VisibilitySnafu { value: entry }.fail();
+                }
+                if subtree {
+                    Rule::Subtree(addr)
+                } else {
+                    Rule::Package(addr)
+                }
+            }
+        };
+        Ok(rule)
+    }
+
+    /// Returns `true` if the transform at `owner` may be depended on from `from`.
+    ///
+    /// A package can always see its own transforms.
+    pub fn allows(&self, owner: &Addr, from: &Addr) -> bool {
+        let from = package(from);
+        let owner = package(owner);
+        if from == owner {
+            return true;
+        }
+        self.rules.iter().any(|rule| match rule {
+            Rule::Public => true,
+            Rule::Private => false,
+            Rule::Package(addr) => *addr == from,
+            Rule::Subtree(addr) => from.starts_with(addr),
+        })
+    }
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rules = self
+            .rules
+            .iter()
+            .map(|rule| match rule {
+                Rule::Public => "public".to_string(),
+                Rule::Private => "private".to_string(),
+                Rule::Package(addr) => addr.to_string(),
+                Rule::Subtree(addr) if addr.to_id().is_empty() => "//...".to_string(),
+                Rule::Subtree(addr) => format!("{addr}/..."),
+            })
+            .collect::<Vec<_>>();
+        f.write_str(&rules.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(value: &str) -> Addr {
+        Addr::parse(value).unwrap()
+    }
+
+    fn visibility(entries: &[&str]) -> Visibility {
+        let node = Node::new_list(
+            entries
+                .iter()
+                .map(|x| Node::new_string(x.to_string()))
+                .collect(),
+        );
+        Visibility::from_node(&addr("//lib"), &node).unwrap()
+    }
+
+    #[test]
+    fn default_is_public() {
+        let public = Visibility::default();
+        assert!(public.allows(&addr("//lib/util"), &addr("//app/main")));
+    }
+
+    #[test]
+    fn private_is_limited_to_the_package() {
+        let private = visibility(&["private"]);
+        assert!(private.allows(&addr("//lib/util"), &addr("//lib/other")));
+        assert!(!private.allows(&addr("//lib/util"), &addr("//lib/sub/other")));
+        assert!(!private.allows(&addr("//lib/util"), &addr("//app/main")));
+    }
+
+    #[test]
+    fn packages_and_subtrees_are_matched() {
+        let rules = visibility(&["//app", "//tools/...", "sub/..."]);
+        let owner = addr("//lib/util");
+        assert!(rules.allows(&owner, &addr("//app/main")));
+        assert!(!rules.allows(&owner, &addr("//app/cli/main")));
+        assert!(rules.allows(&owner, &addr("//tools/main")));
+        assert!(rules.allows(&owner, &addr("//tools/release/main")));
+        assert!(rules.allows(&owner, &addr("//lib/sub/deep/main")));
+        assert!(!rules.allows(&owner, &addr("//other/main")));
+        assert_eq!(rules.to_string(), "//app, //tools/..., //lib/sub/...");
+    }
+
+    #[test]
+    fn single_strings_and_invalid_rules() {
+        let node = Node::new_string("public".to_string());
+        let public = Visibility::from_node(&addr("//lib"), &node).unwrap();
+        assert_eq!(public, Visibility::default());
+
+        let node = Node::new_string("//a//b".to_string());
+        assert!(Visibility::from_node(&addr("//lib"), &node).is_err());
+        assert!(Visibility::from_node(&addr("//lib"), &Node::new_int(1)).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Why a transform is not a cache hit.
+//!
+//! Every run records the components of each transform's cache key in its
+//! summary. Comparing the current key against the one recorded by the latest
+//! run that computed it names the input whose digest changed: a source, a
+//! dependency that was rebuilt, the commands, and so on.
+
+use super::{Addr, Context, ContextResult, error};
+use crate::transform::{KeyComponent, KeyKind};
+use serde::Serialize;
+use snafu::OptionExt;
+use std::fmt;
+
+/// A difference between two cache keys of the same transform.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum KeyChange {
+    /// The input is in both keys with another digest, or another inclusion.
+    Changed {
+        /// The component as the previous run recorded it.
+        before: KeyComponent,
+        /// The component as it is now.
+        after: KeyComponent,
+    },
+    /// The input is new.
+    Added {
+        /// The component as it is now.
+        after: KeyComponent,
+    },
+    /// The input is gone.
+    Removed {
+        /// The component as the previous run recorded it.
+        before: KeyComponent,
+    },
+}
+
+impl fmt::Display for KeyChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Changed { before, after } if before.included != after.included => write!(
+                f,
+                "{} {} is now {} the key",
+                after.kind,
+                after.name,
+                if after.included {
+                    "included in"
+                } else {
+                    "excluded from"
+                }
+            ),
+            Self::Changed { before, after } => {
+                let digests = format!("{} -> {}", short(&before.digest), short(&after.digest));
+                match after.kind {
+                    KeyKind::Depend => write!(f, "dependency {} rebuilt ({digests})", after.name),
+                    KeyKind::Source => {
+                        write!(f, "source {} digest changed ({digests})", after.name)
+                    }
+                    KeyKind::Command => write!(f, "commands {} changed", after.name),
+                    KeyKind::Platform => write!(f, "platform changed ({digests})"),
+                    kind => write!(f, "{kind} {} changed ({digests})", after.name),
+                }
+            }
+            Self::Added { after } => write!(f, "{} {} added", after.kind, after.name),
+            Self::Removed { before } => write!(f, "{} {} removed", before.kind, before.name),
+        }
+    }
+}
+
+/// Why the transform at an address is or is not a cache hit, see
+/// [`Context::why`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RebuildCause {
+    /// Address of the transform.
+    pub addr: Addr,
+    /// Id of the artifact a run would use.
+    pub id: String,
+    /// Whether a cache holds that artifact.
+    pub built: bool,
+    /// The latest run that recorded the transform's key.
+    pub run: Option<String>,
+    /// The id that run derived from the key.
+    pub previous: Option<String>,
+    /// How the key changed since that run, in key order.
+    pub changes: Vec<KeyChange>,
+}
+
+impl Context {
+    /// Explains whether the transform at `addr` is a cache hit and, when it
+    /// is not, which inputs of its cache key changed since the latest run
+    /// that recorded it.
+    pub async fn why(&self, addr: &Addr) -> ContextResult<RebuildCause> {
+        let addr = self.resolve_alias(addr);
+        let handle = self.get_handle();
+        let transform = handle
+            .get(&addr)
+            .context(error::NoTransformFoundSnafu { addr: addr.clone() })?;
+        let key = handle.cache_key(&addr, &transform).await?;
+        let id = key.id();
+        let built = self
+            .storage()
+            .find_builds(std::slice::from_ref(&id))
+            .await?
+            .contains(&id);
+
+        let mut cause = RebuildCause {
+            addr: addr.clone(),
+            id: id.to_string(),
+            built,
+            run: None,
+            previous: None,
+            changes: Vec::new(),
+        };
+        // Runs from before keys were recorded cannot be compared against
+        let recorded = self.runs().list().await?.into_iter().rev().find_map(|run| {
+            let node = run
+                .nodes
+                .into_iter()
+                .find(|x| x.addr == addr && !x.key.is_empty())?;
+            Some((run.id, node))
+        });
+        let Some((run, node)) = recorded else {
+            return Ok(cause);
+        };
+        cause.run = Some(run);
+        cause.previous = node.id;
+        cause.changes = compare(&node.key, &key.breakdown());
+        Ok(cause)
+    }
+}
+
+/// Lists how `after` differs from `before`, matching components by kind
+/// and name.
+fn compare(before: &[KeyComponent], after: &[KeyComponent]) -> Vec<KeyChange> {
+    let find = |list: &[KeyComponent], x: &KeyComponent| {
+        list.iter()
+            .find(|y| y.kind == x.kind && y.name == x.name)
+            .cloned()
+    };
+    let mut changes: Vec<KeyChange> = after
+        .iter()
+        .filter_map(|x| match find(before, x) {
+            Some(y) if y.digest == x.digest && y.included == x.included => None,
+            Some(y) => Some(KeyChange::Changed {
+                before: y,
+                after: x.clone(),
+            }),
+            None => Some(KeyChange::Added { after: x.clone() }),
+        })
+        .collect();
+    changes.extend(
+        before
+            .iter()
+            .filter(|x| find(after, x).is_none())
+            .map(|x| KeyChange::Removed { before: x.clone() }),
+    );
+    changes
+}
+
+fn short(digest: &str) -> &str {
+    digest.get(..12).unwrap_or(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::{CacheKey, KeyPolicy};
+
+    fn key(source: &str, script: &str) -> Vec<KeyComponent> {
+        let mut key = CacheKey::new("build", &KeyPolicy::default());
+        key.add_digest(KeyKind::Depend, "//pkg/dep", "d1");
+        key.add_digest(KeyKind::Source, "src", source);
+        key.add_commands(&[script.to_string()]);
+        key.breakdown()
+    }
+
+    #[test]
+    fn changes_name_the_inputs_whose_digest_changed() {
+        let before = key("aaaa", "make");
+        assert!(compare(&before, &before).is_empty());
+
+        let changes = compare(&before, &key("bbbb", "make install"));
+        let lines: Vec<String> = changes.iter().map(|x| x.to_string()).collect();
+        assert_eq!(
+            lines,
+            [
+                "source src digest changed (aaaa -> bbbb)",
+                "commands script changed"
+            ]
+        );
+
+        let mut after = key("aaaa", "make");
+        after.remove(0);
+        assert_eq!(
+            compare(&before, &after)
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>(),
+            ["deps //pkg/dep removed"]
+        );
+    }
+}
@@ -1,13 +1,156 @@
 use super::Environment;
-use super::{EnvResult, error};
-use crate::context::Log;
+use super::{EnvResult, Placeholders, error, quote, template};
+use crate::context::{Log, MARKER_PREFIX};
 use crate::storage::Id;
-use handlebars::Handlebars;
-use snafu::{ResultExt, ensure};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use snafu::ensure;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::path::Path;
 
+/// Which step of a traced [`Command`] failed and how.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandFailure {
+    /// Zero based index of the failing step among those added with
+    /// [`Command::run`].
+    pub index: usize,
+    /// The failing step after template substitution.
+    pub command: String,
+    /// Exit status of the script, if the shell reported one.
+    pub code: Option<i32>,
+    /// The last lines of output before the failure. Not serialized, run
+    /// summaries record them for the whole transform instead.
+    #[serde(skip)]
+    pub tail: Vec<String>,
+    /// The log section of the failing command, if it was started with
+    /// [`Command::section`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+}
+
+impl fmt::Display for CommandFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(section) = self.section.as_ref() {
+            write!(f, "{section} ")?;
+        }
+        write!(f, "command {} `{}` failed", self.index, self.command)?;
+        if let Some(code) = self.code {
+            write!(f, " with exit code {code}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What a [`Step`] runs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Program {
+    /// Script text, run by the step's interpreter or else by the command's
+    /// shell.
+    Script(String),
+    /// An argument vector. Every argument is quoted, so none of them is
+    /// split or expanded by the shell.
+    Argv(Vec<String>),
+}
+
+/// A step of a [`Command`] that needs more than a raw command line, added
+/// with [`Command::run_step`].
+///
+/// Every field is substituted against the command's template variables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    /// What the step runs.
+    pub program: Program,
+    /// Program a [`Program::Script`] is fed to on its standard input, e.g.
+    /// `python3`, instead of the command's shell.
+    pub interpreter: Option<String>,
+    /// Environment variables set for this step only.
+    pub env: BTreeMap<String, String>,
+    /// Directory the step runs in, instead of the command's.
+    pub dir: Option<String>,
+}
+
+impl Step {
+    /// A step running `script` in the command's shell.
+    pub fn script(script: &str) -> Self {
+        Self::new(Program::Script(script.to_string()))
+    }
+
+    /// A step running the argument vector `argv`.
+    pub fn argv(argv: &[String]) -> Self {
+        Self::new(Program::Argv(argv.to_vec()))
+    }
+
+    fn new(program: Program) -> Self {
+        Self {
+            program,
+            interpreter: None,
+            env: BTreeMap::new(),
+            dir: None,
+        }
+    }
+
+    /// Renders the step as shell script, passing every template through
+    /// `sub` first.
+    ///
+    /// A plain script step renders as its text, so it reads the same as a
+    /// step added with [`Command::run`]. Steps with an environment or a
+    /// directory run in a subshell so neither leaks into later steps.
+    pub fn render(&self, sub: impl Fn(&str) -> EnvResult<String>) -> EnvResult<String> {
+        let body = match &self.program {
+            Program::Argv(argv) => argv
+                .iter()
+                .map(|x| sub(x).map(|x| quote(&x)))
+                .collect::<EnvResult<Vec<_>>>()?
+                .join(" "),
+            Program::Script(script) => {
+                let script = sub(script)?;
+                match self.interpreter.as_ref() {
+                    Some(interpreter) => {
+                        let interpreter = sub(interpreter)?
+                            .split_whitespace()
+                            .map(quote)
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        // The quoted delimiter keeps the shell from expanding the script
+                        let mut delimiter = "EDO_SCRIPT".to_string();
+                        while script.lines().any(|x| x == delimiter) {
+                            delimiter.push('_');
+                        }
+                        format!("{interpreter} <<'{delimiter}'\n{script}\n{delimiter}")
+                    }
+                    None => script,
+                }
+            }
+        };
+        if self.env.is_empty() && self.dir.is_none() {
+            return Ok(body);
+        }
+        let mut lines = vec!["(".to_string()];
+        if let Some(dir) = self.dir.as_ref() {
+            lines.push(format!("cd {} || exit", quote(&sub(dir)?)));
+        }
+        for (key, value) in self.env.iter() {
+            ensure!(
+                !key.is_empty()
+                    && !key.starts_with(|x: char| x.is_ascii_digit())
+                    && key.chars().all(|x| x.is_ascii_alphanumeric() || x == '_'),
+                error::VariableSnafu { key }
+            );
+            lines.push(format!("export {key}={}", quote(&sub(value)?)));
+        }
+        lines.push(body);
+        lines.push(")".to_string());
+        Ok(lines.join("\n"))
+    }
+
+    /// Checks every template of the step against `placeholders`, along with
+    /// the names of its environment variables.
+    pub fn validate(&self, placeholders: &Placeholders) -> EnvResult<()> {
+        self.render(|x| placeholders.validate(x).map(|_| x.to_string()))
+            .map(|_| ())
+    }
+}
+
 /// A Command represents a delayed series of commands to run inside of an environment.
 ///
 /// All transforms should use this to define how they work.
@@ -19,21 +162,61 @@ pub struct Command {
     interpreter: String,
     commands: Vec<String>,
     variables: HashMap<String, String>,
+    traced: bool,
+    steps: Vec<usize>,
+    section: Option<String>,
 }
 
 impl Command {
     /// Create a new empty command bound to `env` with `bash` as the default interpreter.
+    ///
+    /// The `artifact-id`, `version` and `arch` placeholders are bound from
+    /// `id`, with `arch` falling back to the host architecture.
     pub fn new(log: &Log, id: &Id, env: &Environment) -> Self {
+        let variables = HashMap::from([
+            ("artifact-id".to_string(), id.to_string()),
+            (
+                "version".to_string(),
+                id.version().map(|x| x.to_string()).unwrap_or_default(),
+            ),
+            (
+                "arch".to_string(),
+                id.arch()
+                    .unwrap_or_else(|| std::env::consts::ARCH.to_string()),
+            ),
+        ]);
         Self {
             id: id.clone(),
             env: env.clone(),
             log: log.clone(),
             interpreter: "bash".into(),
             commands: Vec::new(),
-            variables: HashMap::new(),
+            variables,
+            traced: false,
+            steps: Vec::new(),
+            section: None,
         }
     }
 
+    /// A new empty command sharing this command's variables, interpreter
+    /// and tracing, whose script is logged as its own `name` section when
+    /// sent and whose failures are reported as failures of `name`.
+    pub fn section(&self, name: &str) -> Self {
+        Self {
+            commands: Vec::new(),
+            steps: Vec::new(),
+            section: Some(name.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// Report which [`run`](Self::run) step the script reached and its exit
+    /// status through the log, so a failed [`send`](Self::send) returns a
+    /// [`CommandFailure`] instead of a bare error.
+    pub fn trace_steps(&mut self) {
+        self.traced = true;
+    }
+
     /// Override the shebang interpreter used when the script is rendered (defaults to `bash`).
     pub fn set_interpreter(&mut self, interpreter: &str) {
         self.interpreter = interpreter.to_string();
@@ -47,11 +230,7 @@ impl Command {
     }
 
     fn sub(&self, line: &str) -> EnvResult<String> {
-        let current = line.to_string();
-        let hg = Handlebars::new();
-
-        hg.render_template(current.as_str(), &self.variables)
-            .context(error::TemplateSnafu)
+        template::render(line, &self.variables)
     }
 
     /// Append a `cd <path>` step to the script, substituting variables in `path`.
@@ -121,6 +300,15 @@ impl Command {
     /// Append a raw command line to the script, substituting variables first.
     pub async fn run(&mut self, cmd: &str) -> EnvResult<()> {
         let cmd = self.sub(cmd)?;
+        self.steps.push(self.commands.len());
+        self.commands.push(cmd);
+        Ok(())
+    }
+
+    /// Append a [`Step`] to the script, substituting variables in each of its fields.
+    pub async fn run_step(&mut self, step: &Step) -> EnvResult<()> {
+        let cmd = step.render(|x| self.sub(x))?;
+        self.steps.push(self.commands.len());
         self.commands.push(cmd);
         Ok(())
     }
@@ -131,20 +319,54 @@ impl Command {
     pub async fn send(&self, path: &str) -> EnvResult<()> {
         let path = self.sub(path)?;
         let dir = self.env.expand(Path::new(path.as_str())).await?;
+        if let Some(section) = self.section.as_ref() {
+            self.log.set_subject(section);
+        }
+        self.log.clear_markers();
         let status = self.env.run(&self.log, &self.id, &dir, self).await?;
+        if !status && let Some(failure) = self.failure() {
+            return error::CommandSnafu { failure }.fail();
+        }
         ensure!(status, error::RunSnafu);
         Ok(())
     }
+
+    /// Reads the step a traced script failed at from the log markers.
+    fn failure(&self) -> Option<Box<CommandFailure>> {
+        if !self.traced {
+            return None;
+        }
+        let index: usize = self.log.marker("step")?.parse().ok()?;
+        Some(Box::new(CommandFailure {
+            index,
+            command: self.commands.get(*self.steps.get(index)?)?.clone(),
+            code: self.log.marker("exit").and_then(|x| x.parse().ok()),
+            tail: self.log.tail(),
+            section: self.section.clone(),
+        }))
+    }
 }
 
 impl fmt::Display for Command {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_fmt(format_args!(
-            r#"#!/usr/bin/env {}
+        if !self.traced {
+            return f.write_fmt(format_args!(
+                r#"#!/usr/bin/env {}
 {}"#,
-            self.interpreter,
-            self.commands.join("\n")
-        ))
+                self.interpreter,
+                self.commands.join("\n")
+            ));
+        }
+        writeln!(f, "#!/usr/bin/env {}", self.interpreter)?;
+        writeln!(f, "trap 'printf \"{MARKER_PREFIX}exit %d\\n\" \"$?\"' EXIT")?;
+        let mut steps = self.steps.iter().enumerate().peekable();
+        for (position, command) in self.commands.iter().enumerate() {
+            if let Some((index, _)) = steps.next_if(|(_, x)| **x == position) {
+                writeln!(f, "printf '{MARKER_PREFIX}step %d\\n' {index}")?;
+            }
+            writeln!(f, "{command}")?;
+        }
+        Ok(())
     }
 }
 
@@ -160,8 +382,8 @@ mod tests {
     //! to the public contract.
     use super::*;
     use crate::context::test_support::shared_log_manager;
-    use crate::environment::error::EnvironmentError;
     use crate::environment::EnvironmentImpl;
+    use crate::environment::error::EnvironmentError;
     use crate::storage::{Id, Storage};
     use crate::util::{Reader, Writer};
     use async_trait::async_trait;
@@ -169,6 +391,9 @@ mod tests {
     use std::sync::{Arc, Mutex};
     use tempfile::TempDir;
 
+    /// Shared log of `(path, command_display)` tuples recorded by the mock.
+    type CmdLog = Arc<Mutex<Vec<(PathBuf, String)>>>;
+
     /// Configurable `EnvironmentImpl` used by the command tests.
     ///
     /// * `expand_prefix` — when `Some`, `expand(p)` returns `prefix.join(p)`
@@ -183,17 +408,19 @@ mod tests {
         expand_prefix: Option<PathBuf>,
         expand_fail: bool,
         run_status: bool,
-        runs: Arc<Mutex<Vec<(PathBuf, String)>>>,
+        output: &'static str,
+        runs: CmdLog,
     }
 
     impl MockEnvImpl {
-        fn new() -> (Self, Arc<Mutex<Vec<(PathBuf, String)>>>) {
+        fn new() -> (Self, CmdLog) {
             let runs = Arc::new(Mutex::new(Vec::new()));
             (
                 Self {
                     expand_prefix: None,
                     expand_fail: false,
                     run_status: true,
+                    output: "",
                     runs: runs.clone(),
                 },
                 runs,
@@ -210,6 +437,11 @@ mod tests {
             self
         }
 
+        fn with_output(mut self, output: &'static str) -> Self {
+            self.output = output;
+            self
+        }
+
         fn with_expand_fail(mut self) -> Self {
             self.expand_fail = true;
             self
@@ -266,11 +498,12 @@ mod tests {
         }
         async fn run(
             &self,
-            _log: &Log,
+            log: &Log,
             _id: &Id,
             path: &Path,
             command: &Command,
         ) -> EnvResult<bool> {
+            log.stream(self.output.as_bytes()).unwrap();
             self.runs
                 .lock()
                 .unwrap()
@@ -297,7 +530,7 @@ mod tests {
             .build()
     }
 
-    fn make_env() -> (Environment, Arc<Mutex<Vec<(PathBuf, String)>>>) {
+    fn make_env() -> (Environment, CmdLog) {
         let (mock, runs) = MockEnvImpl::new();
         (Environment::new(mock), runs)
     }
@@ -343,6 +576,28 @@ mod tests {
         assert_eq!(cmd.to_string(), "#!/usr/bin/env bash\necho /root/.rc");
     }
 
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn new_binds_id_placeholders() {
+        let dir = TempDir::new().unwrap();
+        let log = make_log(&dir, "id-vars").await;
+        let id = Id::builder()
+            .name("pkg".to_string())
+            .version(semver::Version::new(1, 2, 0))
+            .arch("aarch64".to_string())
+            .digest("deadbeef".to_string())
+            .build();
+        let (env, _) = make_env();
+        let mut cmd = Command::new(&log, &id, &env);
+        cmd.run("echo {{artifact-id}} {{version}} {{arch}}")
+            .await
+            .unwrap();
+        assert_eq!(
+            cmd.to_string(),
+            format!("#!/usr/bin/env bash\necho {id} 1.2.0 aarch64")
+        );
+    }
+
     #[tokio::test]
     #[serial_test::serial(log_manager)]
     async fn set_undefined_variable_renders_blank() {
@@ -544,6 +799,92 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn traced_display_marks_each_step() {
+        let dir = TempDir::new().unwrap();
+        let log = make_log(&dir, "traced").await;
+        let id = make_id();
+        let (env, _) = make_env();
+        let mut cmd = Command::new(&log, &id, &env);
+        cmd.set_interpreter("sh");
+        cmd.trace_steps();
+        cmd.create_dir("/work").await.unwrap();
+        cmd.run("make").await.unwrap();
+        cmd.run("make install").await.unwrap();
+        assert_eq!(
+            cmd.to_string(),
+            "#!/usr/bin/env sh\n\
+             trap 'printf \"::edo::exit %d\\n\" \"$?\"' EXIT\n\
+             mkdir -p /work\n\
+             printf '::edo::step %d\\n' 0\nmake\n\
+             printf '::edo::step %d\\n' 1\nmake install\n"
+        );
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn traced_send_reports_the_failing_step() {
+        let dir = TempDir::new().unwrap();
+        let log = make_log(&dir, "traced-fail").await;
+        let id = make_id();
+        let (mock, _runs) = MockEnvImpl::new();
+        let mock = mock
+            .with_run_status(false)
+            .with_output("::edo::step 1\nerror: no rule\n::edo::exit 2\n");
+        let env = Environment::new(mock);
+        let mut cmd = Command::new(&log, &id, &env);
+        cmd.trace_steps();
+        cmd.run("configure").await.unwrap();
+        cmd.run("make").await.unwrap();
+        match cmd.send("/s").await.unwrap_err() {
+            EnvironmentError::Command { failure } => {
+                assert_eq!(failure.index, 1);
+                assert_eq!(failure.command, "make");
+                assert_eq!(failure.code, Some(2));
+                assert_eq!(failure.tail.last().unwrap(), "error: no rule");
+            }
+            other => panic!("expected Command error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn sections_share_variables_and_name_their_failures() {
+        let dir = TempDir::new().unwrap();
+        let log = make_log(&dir, "section").await;
+        let id = make_id();
+        let (mock, runs) = MockEnvImpl::new();
+        let mock = mock.with_run_status(false).with_output(
+            "::edo::step 0
+::edo::exit 1
+",
+        );
+        let env = Environment::new(mock);
+        let mut cmd = Command::new(&log, &id, &env);
+        cmd.set_interpreter("sh");
+        cmd.trace_steps();
+        cmd.set("out", "/o").unwrap();
+        cmd.run("make").await.unwrap();
+        let mut post = cmd.section("post");
+        post.run("ls {{out}}").await.unwrap();
+        assert!(!post.to_string().contains("make"));
+        match post.send("/s").await.unwrap_err() {
+            EnvironmentError::Command { failure } => {
+                assert_eq!(failure.command, "ls /o");
+                assert_eq!(failure.section.as_deref(), Some("post"));
+                assert_eq!(
+                    failure.to_string(),
+                    "post command 0 `ls /o` failed with exit code 1"
+                );
+            }
+            other => panic!("expected Command error, got {other:?}"),
+        }
+        assert!(runs.lock().unwrap()[0].1.starts_with("#!/usr/bin/env sh\n"));
+        let text = std::fs::read_to_string(log.path()).unwrap();
+        assert!(text.contains("=== [post] ==="), "{text}");
+    }
+
     #[tokio::test]
     #[serial_test::serial(log_manager)]
     async fn send_propagates_expand_error() {
@@ -576,4 +917,71 @@ mod tests {
         assert_eq!(log.len(), 1);
         assert_eq!(log[0].0, PathBuf::from("/sandbox/out"));
     }
+
+    // ── Steps ───────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn run_step_quotes_argv_and_scopes_env_and_dir() {
+        let dir = TempDir::new().unwrap();
+        let log = make_log(&dir, "step").await;
+        let id = make_id();
+        let (env, _) = make_env();
+        let mut cmd = Command::new(&log, &id, &env);
+        cmd.set("out", "/o").unwrap();
+        cmd.run_step(&Step::argv(&[
+            "printf".to_string(),
+            "%s it's {{out}}".to_string(),
+        ]))
+        .await
+        .unwrap();
+        let mut step = Step::script("print('$HOME')");
+        step.interpreter = Some("python3 -u".to_string());
+        step.env.insert("MODE".to_string(), "a b".to_string());
+        step.dir = Some("{{out}}/src".to_string());
+        cmd.run_step(&step).await.unwrap();
+        cmd.run_step(&Step::script("echo {{out}}")).await.unwrap();
+        assert_eq!(
+            cmd.to_string(),
+            "#!/usr/bin/env bash\n\
+             'printf' '%s it'\\''s /o'\n\
+             (\n\
+             cd '/o/src' || exit\n\
+             export MODE='a b'\n\
+             'python3' '-u' <<'EDO_SCRIPT'\n\
+             print('$HOME')\n\
+             EDO_SCRIPT\n\
+             )\n\
+             echo /o"
+        );
+
+        let mut step = Step::argv(&["true".to_string()]);
+        step.env.insert("1BAD".to_string(), String::new());
+        assert!(matches!(
+            cmd.run_step(&step).await,
+            Err(EnvironmentError::Variable { .. })
+        ));
+    }
+
+    #[test]
+    fn validate_checks_every_field_of_a_step() {
+        let placeholders = Placeholders::new();
+        let mut step = Step::argv(&["ls".to_string(), "{{build-root}}".to_string()]);
+        step.validate(&placeholders).unwrap();
+        step.dir = Some("{{source-root}}".to_string());
+        assert!(matches!(
+            step.validate(&placeholders),
+            Err(EnvironmentError::Placeholder { .. })
+        ));
+    }
+
+    #[test]
+    fn heredoc_delimiter_avoids_script_lines() {
+        let mut step = Step::script("EDO_SCRIPT\necho");
+        step.interpreter = Some("sh".to_string());
+        assert_eq!(
+            step.render(|x| Ok(x.to_string())).unwrap(),
+            "'sh' <<'EDO_SCRIPT_'\nEDO_SCRIPT\necho\nEDO_SCRIPT_"
+        );
+    }
 }
@@ -18,11 +18,20 @@ pub enum EnvironmentError {
     Implementation {
         source: Box<dyn snafu::Error + Send + Sync>,
     },
+    /// Host mounts or devices were declared incorrectly or cannot be exposed.
+    #[snafu(display("invalid host access: {reason}"))]
+    HostAccess { reason: String },
+    /// Host requirements were declared incorrectly.
+    #[snafu(display("invalid host_requires: {reason}"))]
+    HostRequirements { reason: String },
     #[snafu(display("IO error occured inside environment: {source}"))]
     Io { source: std::io::Error },
     /// A command executed inside the environment returned a non-zero exit status.
     #[snafu(display("command execution failed"))]
     Run,
+    /// A traced command script failed at a known step.
+    #[snafu(display("{failure}"))]
+    Command { failure: Box<super::CommandFailure> },
     /// A propagated storage-layer error encountered during environment setup or I/O.
     #[snafu(transparent)]
     Storage {
@@ -32,6 +41,12 @@ pub enum EnvironmentError {
     /// Handlebars template rendering failed while substituting command variables.
     #[snafu(display("failed to render substitution: {source}"))]
     Template { source: handlebars::RenderError },
+    /// A command template does not parse or uses an undefined placeholder.
+    #[snafu(display("invalid template `{template}`: {reason}"))]
+    Placeholder { template: String, reason: String },
+    /// A command step sets an environment variable the shell cannot name.
+    #[snafu(display("'{key}' is not a valid environment variable name"))]
+    Variable { key: String },
     /// Failure during VFS operation
     #[snafu(display("vfs operation failed: {action}"))]
     Vfs { action: String },
@@ -1,6 +1,8 @@
 use super::EnvResult;
 use super::Environment;
-use crate::context::Log;
+use super::HostAccess;
+use super::{HostInfo, HostRequirements};
+use crate::context::{HealthCheck, Log};
 use crate::storage::Storage;
 use arc_handle::arc_handle;
 use async_trait::async_trait;
@@ -15,6 +17,51 @@ pub trait Farm {
     async fn setup(&self, log: &Log, storage: &Storage) -> EnvResult<()>;
     /// Create a new environment using this farm
     async fn create(&self, log: &Log, path: &Path) -> EnvResult<Environment>;
+    /// Host mounts and devices every environment of this farm exposes.
+    fn host_access(&self) -> HostAccess {
+        HostAccess::default()
+    }
+    /// Identifies the toolchain environments of this farm build with, such as
+    /// the digest of a container image. Transforms hash it into their unique
+    /// id so changing the environment does not reuse stale artifacts.
+    async fn identity(&self) -> EnvResult<Option<String>> {
+        Ok(None)
+    }
+    /// Checks that environments of this farm can be created, such as that
+    /// its container engine answers, for `edo doctor`.
+    async fn health(&self) -> Vec<HealthCheck> {
+        Vec::new()
+    }
+    /// Architectures environments of this farm can execute, the host's
+    /// unless the farm can emulate others.
+    fn platforms(&self) -> Vec<String> {
+        vec![std::env::consts::ARCH.to_string()]
+    }
+    /// The machine environments of this farm run on, the host's unless
+    /// the farm runs them elsewhere.
+    fn host(&self) -> HostInfo {
+        HostInfo::local()
+    }
+    /// What every transform running in this farm needs of its host.
+    fn host_requires(&self) -> HostRequirements {
+        HostRequirements::default()
+    }
+}
+
+/// Returns the name edo uses for `arch`, mapping the names container
+/// runtimes use (`amd64`, `arm64`) to the ones Rust and uname report.
+pub fn canonical_arch(arch: &str) -> &str {
+    match arch {
+        "amd64" | "x64" => "x86_64",
+        "arm64" => "aarch64",
+        other => other,
+    }
+}
+
+/// Returns `true` when a farm executing `platforms` can run `arch`.
+pub fn supports_arch(platforms: &[String], arch: &str) -> bool {
+    let arch = canonical_arch(arch);
+    platforms.iter().any(|x| canonical_arch(x) == arch)
 }
 
 #[cfg(test)]
@@ -33,9 +80,9 @@ mod tests {
     use super::*;
     use crate::context::test_support::shared_log_manager;
     use crate::context::{Addr, Config, Log, Node};
+    use crate::environment::Command;
     use crate::environment::EnvironmentImpl;
     use crate::environment::error::EnvironmentError;
-    use crate::environment::Command;
     use crate::storage::{Backend, Id, LocalBackend, Storage};
     use crate::util::{Reader, Writer};
     use async_trait::async_trait;
@@ -84,22 +131,10 @@ mod tests {
         async fn read(&self, _p: &Path, _w: Writer) -> EnvResult<()> {
             Ok(())
         }
-        async fn cmd(
-            &self,
-            _log: &Log,
-            _id: &Id,
-            _p: &Path,
-            _c: &str,
-        ) -> EnvResult<bool> {
+        async fn cmd(&self, _log: &Log, _id: &Id, _p: &Path, _c: &str) -> EnvResult<bool> {
             Ok(true)
         }
-        async fn run(
-            &self,
-            _log: &Log,
-            _id: &Id,
-            _p: &Path,
-            _c: &Command,
-        ) -> EnvResult<bool> {
+        async fn run(&self, _log: &Log, _id: &Id, _p: &Path, _c: &Command) -> EnvResult<bool> {
             Ok(true)
         }
         fn shell(&self, _p: &Path) -> EnvResult<()> {
@@ -287,4 +322,16 @@ mod tests {
             "expected Implementation error, got {err:?}"
         );
     }
+
+    #[test]
+    fn arch_names_of_runtimes_match_the_host_names() {
+        let platforms = vec!["x86_64".to_string(), "arm64".to_string()];
+        assert!(supports_arch(&platforms, "amd64"));
+        assert!(supports_arch(&platforms, "aarch64"));
+        assert!(!supports_arch(&platforms, "riscv64"));
+        assert!(supports_arch(
+            &[std::env::consts::ARCH.to_string()],
+            std::env::consts::ARCH
+        ));
+    }
 }
@@ -0,0 +1,298 @@
+use super::{
+    Command, EnvResult, Environment, EnvironmentImpl, Farm, FarmImpl, HostAccess, HostInfo,
+    HostRequirements,
+};
+use crate::context::{HealthCheck, Log};
+use crate::storage::{Id, Storage};
+use crate::util::{FaultPlan, Reader, Writer};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+/// Farm wrapper that applies a [`FaultPlan`] to the farm and every
+/// environment it creates.
+///
+/// The farm checks the points `farm.setup` and `farm.create`; environments
+/// check `env.<operation>` (e.g. `env.up`, `env.run`, `env.unpack`) before
+/// delegating. `get_env`, `shell` and `expose` are never faulted.
+pub struct FaultyFarm {
+    inner: Farm,
+    plan: FaultPlan,
+}
+
+impl FaultyFarm {
+    /// Wraps `farm` when `plan` has faults to inject, otherwise returns it unchanged.
+    pub fn wrap(farm: Farm, plan: &FaultPlan) -> Farm {
+        if plan.is_empty() {
+            return farm;
+        }
+        Farm::new(Self {
+            inner: farm,
+            plan: plan.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl FarmImpl for FaultyFarm {
+    async fn setup(&self, log: &Log, storage: &Storage) -> EnvResult<()> {
+        self.plan.inject(&["farm.setup"]).await?;
+        self.inner.setup(log, storage).await
+    }
+
+    async fn create(&self, log: &Log, path: &Path) -> EnvResult<Environment> {
+        self.plan.inject(&["farm.create"]).await?;
+        let inner = self.inner.create(log, path).await?;
+        Ok(Environment::new(FaultyEnvironment {
+            inner,
+            plan: self.plan.clone(),
+        }))
+    }
+
+    fn host_access(&self) -> HostAccess {
+        self.inner.host_access()
+    }
+
+    async fn identity(&self) -> EnvResult<Option<String>> {
+        self.inner.identity().await
+    }
+
+    async fn health(&self) -> Vec<HealthCheck> {
+        self.inner.health().await
+    }
+
+    fn platforms(&self) -> Vec<String> {
+        self.inner.platforms()
+    }
+
+    fn host(&self) -> HostInfo {
+        self.inner.host()
+    }
+
+    fn host_requires(&self) -> HostRequirements {
+        self.inner.host_requires()
+    }
+}
+
+struct FaultyEnvironment {
+    inner: Environment,
+    plan: FaultPlan,
+}
+
+impl FaultyEnvironment {
+    async fn inject(&self, operation: &str) -> EnvResult<()> {
+        self.plan.inject(&[&format!("env.{operation}")]).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EnvironmentImpl for FaultyEnvironment {
+    async fn expand(&self, path: &Path) -> EnvResult<PathBuf> {
+        self.inject("expand").await?;
+        self.inner.expand(path).await
+    }
+
+    async fn create_dir(&self, path: &Path) -> EnvResult<()> {
+        self.inject("create_dir").await?;
+        self.inner.create_dir(path).await
+    }
+
+    async fn set_env(&self, key: &str, value: &str) -> EnvResult<()> {
+        self.inject("set_env").await?;
+        self.inner.set_env(key, value).await
+    }
+
+    async fn get_env(&self, key: &str) -> Option<String> {
+        self.inner.get_env(key).await
+    }
+
+    async fn setup(&self, log: &Log, storage: &Storage) -> EnvResult<()> {
+        self.inject("setup").await?;
+        self.inner.setup(log, storage).await
+    }
+
+    async fn up(&self, log: &Log) -> EnvResult<()> {
+        self.inject("up").await?;
+        self.inner.up(log).await
+    }
+
+    async fn down(&self, log: &Log) -> EnvResult<()> {
+        self.inject("down").await?;
+        self.inner.down(log).await
+    }
+
+    async fn clean(&self, log: &Log) -> EnvResult<()> {
+        self.inject("clean").await?;
+        self.inner.clean(log).await
+    }
+
+    async fn write(&self, path: &Path, reader: Reader) -> EnvResult<()> {
+        self.inject("write").await?;
+        self.inner.write(path, reader).await
+    }
+
+    async fn unpack(&self, path: &Path, reader: Reader) -> EnvResult<()> {
+        self.inject("unpack").await?;
+        self.inner.unpack(path, reader).await
+    }
+
+    async fn read(&self, path: &Path, writer: Writer) -> EnvResult<()> {
+        self.inject("read").await?;
+        self.inner.read(path, writer).await
+    }
+
+    async fn cmd(&self, log: &Log, id: &Id, path: &Path, command: &str) -> EnvResult<bool> {
+        self.inject("cmd").await?;
+        self.inner.cmd(log, id, path, command).await
+    }
+
+    async fn run(&self, log: &Log, id: &Id, path: &Path, command: &Command) -> EnvResult<bool> {
+        self.inject("run").await?;
+        self.inner.run(log, id, path, command).await
+    }
+
+    fn shell(&self, path: &Path) -> EnvResult<()> {
+        self.inner.shell(path)
+    }
+
+    fn expose(&self, access: &HostAccess) -> EnvResult<()> {
+        self.inner.expose(access)
+    }
+
+    async fn snapshot(&self, log: &Log, dir: &Path) -> EnvResult<bool> {
+        self.inject("snapshot").await?;
+        self.inner.snapshot(log, dir).await
+    }
+
+    async fn restore(&self, log: &Log, dir: &Path) -> EnvResult<bool> {
+        self.inject("restore").await?;
+        self.inner.restore(log, dir).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Mock farm/environment impls are duplicated inline, following the
+    //! convention in `environment/farm.rs`.
+    use super::*;
+    use crate::context::test_support::shared_log_manager;
+    use crate::util::FaultAction;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    struct MockEnvImpl {
+        ups: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl EnvironmentImpl for MockEnvImpl {
+        async fn expand(&self, path: &Path) -> EnvResult<PathBuf> {
+            Ok(path.to_path_buf())
+        }
+        async fn create_dir(&self, _p: &Path) -> EnvResult<()> {
+            Ok(())
+        }
+        async fn set_env(&self, _k: &str, _v: &str) -> EnvResult<()> {
+            Ok(())
+        }
+        async fn get_env(&self, _k: &str) -> Option<String> {
+            None
+        }
+        async fn setup(&self, _log: &Log, _storage: &Storage) -> EnvResult<()> {
+            Ok(())
+        }
+        async fn up(&self, _log: &Log) -> EnvResult<()> {
+            self.ups.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+        async fn down(&self, _log: &Log) -> EnvResult<()> {
+            Ok(())
+        }
+        async fn clean(&self, _log: &Log) -> EnvResult<()> {
+            Ok(())
+        }
+        async fn write(&self, _p: &Path, _r: Reader) -> EnvResult<()> {
+            Ok(())
+        }
+        async fn unpack(&self, _p: &Path, _r: Reader) -> EnvResult<()> {
+            Ok(())
+        }
+        async fn read(&self, _p: &Path, _w: Writer) -> EnvResult<()> {
+            Ok(())
+        }
+        async fn cmd(&self, _log: &Log, _id: &Id, _p: &Path, _c: &str) -> EnvResult<bool> {
+            Ok(true)
+        }
+        async fn run(&self, _log: &Log, _id: &Id, _p: &Path, _c: &Command) -> EnvResult<bool> {
+            Ok(true)
+        }
+        fn shell(&self, _p: &Path) -> EnvResult<()> {
+            Ok(())
+        }
+    }
+
+    struct MockFarmImpl {
+        ups: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl FarmImpl for MockFarmImpl {
+        async fn setup(&self, _log: &Log, _storage: &Storage) -> EnvResult<()> {
+            Ok(())
+        }
+        async fn create(&self, _log: &Log, _path: &Path) -> EnvResult<Environment> {
+            Ok(Environment::new(MockEnvImpl {
+                ups: self.ups.clone(),
+            }))
+        }
+    }
+
+    async fn make_log(dir: &TempDir) -> Log {
+        let mgr = shared_log_manager().await;
+        Log::new(&mgr, dir.path().join("fault.log")).expect("Log::new")
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn environment_faults_fail_before_delegating() {
+        let dir = TempDir::new().unwrap();
+        let log = make_log(&dir).await;
+        let ups = Arc::new(AtomicUsize::new(0));
+        let plan = FaultPlan::default();
+        plan.add("env.up", FaultAction::Fail, Some(1));
+        let farm = FaultyFarm::wrap(Farm::new(MockFarmImpl { ups: ups.clone() }), &plan);
+
+        let env = farm.create(&log, dir.path()).await.unwrap();
+        let err = env.up(&log).await.unwrap_err();
+        assert_eq!(err.to_string(), "injected failure at env.up");
+        assert_eq!(ups.load(Ordering::SeqCst), 0);
+
+        env.up(&log).await.unwrap();
+        assert_eq!(ups.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn farm_create_can_be_delayed() {
+        let dir = TempDir::new().unwrap();
+        let log = make_log(&dir).await;
+        let plan = FaultPlan::default();
+        plan.add(
+            "farm.create",
+            FaultAction::Delay(Duration::from_millis(20)),
+            None,
+        );
+        let farm = FaultyFarm::wrap(
+            Farm::new(MockFarmImpl {
+                ups: Arc::new(AtomicUsize::new(0)),
+            }),
+            &plan,
+        );
+
+        let start = Instant::now();
+        farm.create(&log, dir.path()).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,190 @@
+use super::{EnvResult, error};
+use crate::context::{FieldSchema, FieldType, Node};
+use snafu::{OptionExt, ensure};
+use std::path::PathBuf;
+
+/// A host path bind mounted into an environment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostMount {
+    /// Path on the host.
+    pub source: PathBuf,
+    /// Path inside the environment.
+    pub target: PathBuf,
+    /// Mount the path read-only.
+    pub readonly: bool,
+}
+
+impl HostMount {
+    /// Parses `source[:target][:ro]`. The target defaults to the source path.
+    pub fn parse(value: &str) -> EnvResult<Self> {
+        let mut parts: Vec<&str> = value.split(':').collect();
+        let readonly = parts.len() > 1 && parts.last() == Some(&"ro");
+        if readonly {
+            parts.pop();
+        }
+        ensure!(
+            (1..=2).contains(&parts.len()) && parts.iter().all(|x| x.starts_with('/')),
+            error::HostAccessSnafu {
+                reason: format!(
+                    "mount '{value}' should be an absolute 'source[:target][:ro]' path"
+                ),
+            }
+        );
+        let source = PathBuf::from(parts[0]);
+        Ok(Self {
+            target: parts.get(1).map(PathBuf::from).unwrap_or(source.clone()),
+            source,
+            readonly,
+        })
+    }
+}
+
+/// Host resources an environment or transform node asks to be exposed in the
+/// build environment: extra bind mounts and devices.
+///
+/// Builds depending on them are not reproducible from their inputs alone, so
+/// nodes must acknowledge this with `unsafe = true` and the artifacts they
+/// produce are never uploaded to shared build caches.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HostAccess {
+    /// Extra bind mounts, declared with `mounts = [...]`.
+    pub mounts: Vec<HostMount>,
+    /// Device nodes passed through, declared with `devices = [...]`.
+    pub devices: Vec<PathBuf>,
+}
+
+impl HostAccess {
+    /// Reads `mounts`, `devices` and the `unsafe` acknowledgement from `node`.
+    pub fn from_node(node: &Node) -> EnvResult<Self> {
+        let mut access = Self::default();
+        for value in Self::strings(node, "mounts")? {
+            access.mounts.push(HostMount::parse(&value)?);
+        }
+        for value in Self::strings(node, "devices")? {
+            ensure!(
+                value.starts_with('/'),
+                error::HostAccessSnafu {
+                    reason: format!("device '{value}' should be an absolute path"),
+                }
+            );
+            access.devices.push(PathBuf::from(value));
+        }
+        ensure!(
+            access.is_empty() || node.get("unsafe").and_then(|x| x.as_bool()) == Some(true),
+            error::HostAccessSnafu {
+                reason: "mounts and devices require `unsafe = true`, their builds are never uploaded to shared caches",
+            }
+        );
+        Ok(access)
+    }
+
+    /// Describes the keys read by [`from_node`](Self::from_node).
+    pub fn fields() -> Vec<FieldSchema> {
+        vec![
+            FieldSchema::new(
+                "mounts",
+                FieldType::list(FieldType::String),
+                "Host paths to bind mount, as source[:target][:ro]",
+            ),
+            FieldSchema::new(
+                "devices",
+                FieldType::list(FieldType::String),
+                "Host devices to pass through",
+            ),
+            FieldSchema::new(
+                "unsafe",
+                FieldType::Boolean,
+                "Acknowledges that mounts and devices make builds unreproducible",
+            ),
+        ]
+    }
+
+    /// Returns `true` when no host resource is requested.
+    pub fn is_empty(&self) -> bool {
+        self.mounts.is_empty() && self.devices.is_empty()
+    }
+
+    /// A stable description of the requested resources, suitable for hashing
+    /// into an artifact id.
+    pub fn fingerprint(&self) -> String {
+        let mut parts: Vec<String> = self
+            .mounts
+            .iter()
+            .map(|x| {
+                format!(
+                    "mount={}:{}:{}",
+                    x.source.display(),
+                    x.target.display(),
+                    x.readonly
+                )
+            })
+            .collect();
+        parts.extend(
+            self.devices
+                .iter()
+                .map(|x| format!("device={}", x.display())),
+        );
+        parts.join("\n")
+    }
+
+    fn strings(node: &Node, field: &str) -> EnvResult<Vec<String>> {
+        let Some(list) = node.get(field) else {
+            return Ok(Vec::new());
+        };
+        list.as_list()
+            .and_then(|x| x.iter().map(|x| x.as_string()).collect())
+            .context(error::HostAccessSnafu {
+                reason: format!("'{field}' should be a list of strings"),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn node(entries: &[(&str, Node)]) -> Node {
+        let table: BTreeMap<String, Node> = entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect();
+        Node::new_definition("environment", "container", "test", table)
+    }
+
+    fn list(values: &[&str]) -> Node {
+        Node::new_list(
+            values
+                .iter()
+                .map(|x| Node::new_string(x.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn mounts_parse_target_and_readonly() {
+        let mount = HostMount::parse("/run/license.sock").unwrap();
+        assert_eq!(mount.target, PathBuf::from("/run/license.sock"));
+        assert!(!mount.readonly);
+        let mount = HostMount::parse("/opt/sdk:/sdk:ro").unwrap();
+        assert_eq!(mount.source, PathBuf::from("/opt/sdk"));
+        assert_eq!(mount.target, PathBuf::from("/sdk"));
+        assert!(mount.readonly);
+        assert!(HostMount::parse("relative:/x").is_err());
+        assert!(HostMount::parse("/a:/b:/c").is_err());
+    }
+
+    #[test]
+    fn access_requires_unsafe_acknowledgement() {
+        let devices = list(&["/dev/kvm"]);
+        let err = HostAccess::from_node(&node(&[("devices", devices.clone())])).unwrap_err();
+        assert!(err.to_string().contains("unsafe = true"), "{err}");
+        let access = HostAccess::from_node(&node(&[
+            ("devices", devices),
+            ("unsafe", Node::new_bool(true)),
+        ]))
+        .unwrap();
+        assert_eq!(access.devices, vec![PathBuf::from("/dev/kvm")]);
+        assert!(HostAccess::from_node(&node(&[])).unwrap().is_empty());
+    }
+}
@@ -20,11 +20,19 @@ use std::path::{Path, PathBuf};
 mod command;
 pub mod error;
 mod farm;
+mod fault;
+mod host;
+mod requirements;
+mod template;
 mod vfs;
 
 pub use command::*;
 pub use error::EnvironmentError;
 pub use farm::*;
+pub use fault::*;
+pub use host::*;
+pub use requirements::*;
+pub use template::*;
 pub use vfs::*;
 
 /// Convenience result alias for fallible environment operations.
@@ -63,6 +71,33 @@ pub trait Environment {
     async fn run(&self, log: &Log, id: &Id, path: &Path, command: &Command) -> EnvResult<bool>;
     /// Open a shell in the environment
     fn shell(&self, path: &Path) -> EnvResult<()>;
+    /// Expose the host mounts and devices a transform requested, called
+    /// before [`up`](Environment::up). Environments that cannot refuse.
+    fn expose(&self, access: &HostAccess) -> EnvResult<()> {
+        let _ = access;
+        error::HostAccessSnafu {
+            reason: "this environment cannot expose host mounts or devices",
+        }
+        .fail()
+    }
+
+    /// Save what setup and staging left in the environment into `dir`, so a
+    /// later environment of the same farm can resume from it with
+    /// [`restore`](Environment::restore). Returns `false` if the environment
+    /// cannot take snapshots, the default.
+    async fn snapshot(&self, log: &Log, dir: &Path) -> EnvResult<bool> {
+        let _ = (log, dir);
+        Ok(false)
+    }
+
+    /// Replace the staged state of a freshly set up environment with the
+    /// [`snapshot`](Environment::snapshot) in `dir`. Returns `false` if the
+    /// environment cannot restore snapshots, the default, in which case the
+    /// caller stages as usual.
+    async fn restore(&self, log: &Log, dir: &Path) -> EnvResult<bool> {
+        let _ = (log, dir);
+        Ok(false)
+    }
 }
 
 impl Environment {
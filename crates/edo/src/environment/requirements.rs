@@ -0,0 +1,217 @@
+use super::{EnvResult, canonical_arch, error};
+use crate::context::{FieldSchema, FieldType, Node};
+use crate::util::{format_size, parse_size, total_memory};
+use snafu::{OptionExt, ensure};
+
+const KEYS: [&str; 4] = ["os", "arch", "min_memory", "route"];
+
+/// The machine environments of a farm run on, as checked against
+/// [`HostRequirements`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostInfo {
+    /// Operating system, as [`std::env::consts::OS`] names it.
+    pub os: String,
+    /// Native architecture, as [`std::env::consts::ARCH`] names it.
+    pub arch: String,
+    /// Bytes of physical memory, when known.
+    pub memory: Option<u64>,
+}
+
+impl HostInfo {
+    /// The machine edo runs on.
+    pub fn local() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            memory: total_memory(),
+        }
+    }
+}
+
+/// What a transform or environment node needs of the machine it builds on,
+/// declared with `host_requires = { os = "linux", arch = "x86_64",
+/// min_memory = "8GiB" }`.
+///
+/// The scheduler checks them before anything runs. When the requested farm
+/// falls short, the transform moves to another farm that meets them unless
+/// `route = false`, and the run fails naming what is missing otherwise.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostRequirements {
+    /// Operating system the host must run.
+    pub os: Option<String>,
+    /// Native architecture the host must have.
+    pub arch: Option<String>,
+    /// Bytes of physical memory the host must have at least.
+    pub min_memory: Option<u64>,
+    /// Whether another farm may be used when the requested one falls short.
+    pub route: bool,
+}
+
+impl Default for HostRequirements {
+    fn default() -> Self {
+        Self {
+            os: None,
+            arch: None,
+            min_memory: None,
+            route: true,
+        }
+    }
+}
+
+impl HostRequirements {
+    /// Reads the `host_requires` table of `node`.
+    pub fn from_node(node: &Node) -> EnvResult<Self> {
+        let Some(table) = node.get("host_requires") else {
+            return Ok(Self::default());
+        };
+        let table = table.as_table().context(error::HostRequirementsSnafu {
+            reason: "expected a table",
+        })?;
+        if let Some(key) = table.keys().find(|x| !KEYS.contains(&x.as_str())) {
+            return error::HostRequirementsSnafu {
+                reason: format!("unknown key '{key}', expected one of {}", KEYS.join(", ")),
+            }
+            .fail();
+        }
+        let string = |key: &str| -> EnvResult<Option<String>> {
+            table
+                .get(key)
+                .map(|x| {
+                    x.as_string().context(error::HostRequirementsSnafu {
+                        reason: format!("'{key}' should be a string"),
+                    })
+                })
+                .transpose()
+        };
+        let min_memory = match string("min_memory")? {
+            Some(value) => Some(parse_size(&value).context(error::HostRequirementsSnafu {
+                reason: format!("min_memory '{value}' should be a size such as \"8GiB\""),
+            })?),
+            None => None,
+        };
+        let route = match table.get("route") {
+            Some(value) => value.as_bool().context(error::HostRequirementsSnafu {
+                reason: "'route' should be a boolean",
+            })?,
+            None => true,
+        };
+        let requirements = Self {
+            os: string("os")?,
+            arch: string("arch")?,
+            min_memory,
+            route,
+        };
+        ensure!(
+            requirements.os.as_deref() != Some("") && requirements.arch.as_deref() != Some(""),
+            error::HostRequirementsSnafu {
+                reason: "os and arch cannot be empty",
+            }
+        );
+        Ok(requirements)
+    }
+
+    /// Describes the key read by [`from_node`](Self::from_node).
+    pub fn fields() -> Vec<FieldSchema> {
+        vec![FieldSchema::new(
+            "host_requires",
+            FieldType::table(FieldType::one_of([FieldType::String, FieldType::Boolean])),
+            "Operating system, architecture and min_memory the build host must have, and whether to route to another farm that has them",
+        )]
+    }
+
+    /// Returns `true` when nothing is required.
+    pub fn is_empty(&self) -> bool {
+        self.os.is_none() && self.arch.is_none() && self.min_memory.is_none()
+    }
+
+    /// Requires what both `self` and `other` do. Routing is allowed only
+    /// when both allow it.
+    pub fn and(&self, other: &HostRequirements) -> Self {
+        Self {
+            os: self.os.clone().or(other.os.clone()),
+            arch: self.arch.clone().or(other.arch.clone()),
+            min_memory: self.min_memory.max(other.min_memory),
+            route: self.route && other.route,
+        }
+    }
+
+    /// Describes every requirement `host` does not meet. Memory that
+    /// cannot be measured is assumed to be enough.
+    pub fn unmet(&self, host: &HostInfo) -> Vec<String> {
+        let mut unmet = Vec::new();
+        if let Some(os) = self.os.as_ref()
+            && *os != host.os
+        {
+            unmet.push(format!("needs os {os}, runs {}", host.os));
+        }
+        if let Some(arch) = self.arch.as_ref()
+            && canonical_arch(arch) != canonical_arch(&host.arch)
+        {
+            unmet.push(format!("needs arch {arch}, runs {}", host.arch));
+        }
+        if let (Some(needed), Some(memory)) = (self.min_memory, host.memory)
+            && memory < needed
+        {
+            unmet.push(format!(
+                "needs {} of memory, has {}",
+                format_size(needed),
+                format_size(memory)
+            ));
+        }
+        unmet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn node(entries: &[(&str, Node)]) -> Node {
+        let table: BTreeMap<String, Node> = entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect();
+        let mut outer = BTreeMap::new();
+        outer.insert("host_requires".to_string(), Node::new_table(table));
+        Node::new_table(outer)
+    }
+
+    fn host(memory: u64) -> HostInfo {
+        HostInfo {
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            memory: Some(memory),
+        }
+    }
+
+    #[test]
+    fn requirements_are_read_from_the_host_requires_table() {
+        let requirements = HostRequirements::from_node(&node(&[
+            ("os", Node::new_string("linux".to_string())),
+            ("arch", Node::new_string("amd64".to_string())),
+            ("min_memory", Node::new_string("8GiB".to_string())),
+        ]))
+        .unwrap();
+        assert_eq!(requirements.min_memory, Some(8 << 30));
+        assert!(requirements.route);
+        assert!(requirements.unmet(&host(8 << 30)).is_empty());
+        assert_eq!(
+            requirements.unmet(&host(4 << 30)),
+            ["needs 8.0 GiB of memory, has 4.0 GiB"]
+        );
+        assert!(HostRequirements::from_node(&node(&[])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_requirements_are_rejected() {
+        let error = HostRequirements::from_node(&node(&[(
+            "min_memory",
+            Node::new_string("lots".to_string()),
+        )]))
+        .unwrap_err();
+        assert!(error.to_string().contains("min_memory 'lots'"), "{error}");
+        let error = HostRequirements::from_node(&node(&[("cpus", Node::new_int(4))])).unwrap_err();
+        assert!(error.to_string().contains("unknown key 'cpus'"), "{error}");
+    }
+}
@@ -0,0 +1,185 @@
+//! Command templates.
+//!
+//! Every string a [`Command`](super::Command) runs is a handlebars template
+//! over the command's variables:
+//!
+//! - `{{name}}` inserts the value of `name` as is. Values are never HTML
+//!   escaped, so `{{{name}}}` means the same.
+//! - `{{quote name}}` inserts the value quoted for the shell, so it is passed
+//!   on as a single word however many spaces or quotes it holds.
+//! - `\{{` is a literal `{{`.
+//!
+//! No other helpers, blocks or partials are supported. Transforms check their
+//! templates with [`Placeholders::validate`] when they are loaded, so a typo
+//! is a configuration error rather than an empty string in a running build.
+
+use super::{EnvResult, error};
+use handlebars::template::{Parameter, TemplateElement};
+use handlebars::{Handlebars, handlebars_helper, no_escape};
+use snafu::{ResultExt, ensure};
+use std::collections::{BTreeSet, HashMap};
+
+/// The helper that quotes a value for the shell.
+const QUOTE: &str = "quote";
+
+/// Placeholders every command may use. `build-root` and `install-root` are
+/// bound by [`Command::create_named_dir`](super::Command::create_named_dir),
+/// the others by [`Command::new`](super::Command::new) from the artifact id
+/// the command builds. `version` is empty for unversioned ids.
+pub const BUILTIN_PLACEHOLDERS: [&str; 5] = [
+    "build-root",
+    "install-root",
+    "artifact-id",
+    "arch",
+    "version",
+];
+
+/// Quotes `arg` for the shell, so it is passed on as a single word.
+pub fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+handlebars_helper!(quote_helper: |arg: str| quote(arg));
+
+/// Renders `template` against `variables` using the rules of this module.
+/// Undefined variables render as empty strings.
+pub fn render(template: &str, variables: &HashMap<String, String>) -> EnvResult<String> {
+    let mut registry = Handlebars::new();
+    registry.register_escape_fn(no_escape);
+    registry.register_helper(QUOTE, Box::new(quote_helper));
+    registry
+        .render_template(template, variables)
+        .context(error::TemplateSnafu)
+}
+
+/// The placeholders a transform's templates may use.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Placeholders {
+    names: BTreeSet<String>,
+}
+
+impl Default for Placeholders {
+    fn default() -> Self {
+        Self {
+            names: BUILTIN_PLACEHOLDERS.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+}
+
+impl Placeholders {
+    /// The [builtin placeholders](BUILTIN_PLACEHOLDERS).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `name`, e.g. a variant axis or a user `--arg`.
+    pub fn define(&mut self, name: &str) {
+        self.names.insert(name.to_string());
+    }
+
+    /// Returns `true` if templates may use `name`.
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    /// Checks that `template` parses and only uses defined placeholders.
+    pub fn validate(&self, template: &str) -> EnvResult<()> {
+        let invalid = |reason: String| error::PlaceholderSnafu {
+            template: template.to_string(),
+            reason,
+        };
+        let parsed = handlebars::Template::compile(template)
+            .map_err(|e| invalid(e.reason().to_string()).build())?;
+        for element in parsed.elements.iter() {
+            let expression = match element {
+                TemplateElement::RawString(_) | TemplateElement::Comment(_) => continue,
+                TemplateElement::Expression(x) | TemplateElement::HtmlExpression(x) => x,
+                _ => {
+                    return invalid("blocks, partials and decorators are not supported".into())
+                        .fail();
+                }
+            };
+            let name = expression.name.as_name().unwrap_or_default();
+            let used = match expression.params.as_slice() {
+                [] => Some(name),
+                [param] if name == QUOTE => match param {
+                    Parameter::Literal(_) => None,
+                    param => param.as_name(),
+                },
+                _ => {
+                    return invalid(format!(
+                        "'{name}' is not a helper, only {{{{quote <name>}}}} is"
+                    ))
+                    .fail();
+                }
+            };
+            ensure!(
+                expression.hash.is_empty(),
+                invalid("helpers take no named arguments".into())
+            );
+            if let Some(used) = used {
+                ensure!(
+                    self.is_defined(used),
+                    invalid(format!("'{used}' is not defined"))
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::EnvironmentError;
+
+    #[test]
+    fn validate_accepts_defined_placeholders_only() {
+        let mut placeholders = Placeholders::new();
+        placeholders.define("profile");
+        for template in [
+            "make -C {{build-root}} PROFILE={{profile}}",
+            "cp out {{{install-root}}}/{{artifact-id}}",
+            "echo {{quote version}} {{quote \"x y\"}} \\{{literal}}",
+        ] {
+            placeholders.validate(template).unwrap();
+        }
+        for template in [
+            "echo {{profle}}",
+            "echo {{quote missing}}",
+            "{{#if arch}}x{{/if}}",
+            "{{lookup arch 0}}",
+            "{{unterminated",
+        ] {
+            let err = placeholders.validate(template).unwrap_err();
+            assert!(
+                matches!(err, EnvironmentError::Placeholder { .. }),
+                "{template}: {err:?}"
+            );
+        }
+        assert!(
+            placeholders
+                .validate("echo {{profle}}")
+                .unwrap_err()
+                .to_string()
+                .contains("'profle' is not defined")
+        );
+    }
+
+    #[test]
+    fn render_quotes_on_request_and_never_html_escapes() {
+        let variables = HashMap::from([
+            ("flags".to_string(), "-O2 -g".to_string()),
+            ("url".to_string(), "a?b=1&c='2'".to_string()),
+        ]);
+        assert_eq!(
+            render("cc {{flags}} {{quote flags}}", &variables).unwrap(),
+            "cc -O2 -g '-O2 -g'"
+        );
+        assert_eq!(
+            render("curl {{url}} {{quote url}}", &variables).unwrap(),
+            r"curl a?b=1&c='2' 'a?b=1&c='\''2'\'''"
+        );
+        assert_eq!(render(r"\{{url}}", &variables).unwrap(), "{{url}}");
+    }
+}
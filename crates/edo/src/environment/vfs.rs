@@ -92,7 +92,7 @@ impl Vfs {
     pub async fn try_exists(&self, path: impl AsRef<Path>) -> EnvResult<bool> {
         let path = self.canonicalize(path).await?;
         self.env
-            .cmd(&self.log, &self.id, &self.path(), &format!("stat {path:?}"))
+            .cmd(&self.log, &self.id, self.path(), &format!("stat {path:?}"))
             .await
     }
 
@@ -283,8 +283,8 @@ mod tests {
     //! toggling the exit status and the `expand` prefix.
     use super::*;
     use crate::context::test_support::shared_log_manager;
-    use crate::environment::{Command, EnvironmentImpl};
     use crate::environment::error::EnvironmentError;
+    use crate::environment::{Command, EnvironmentImpl};
     use crate::storage::{Id, Storage};
     use crate::util::{Reader, Writer};
     use async_trait::async_trait;
@@ -293,6 +293,9 @@ mod tests {
     use std::sync::{Arc, Mutex};
     use tempfile::TempDir;
 
+    /// Shared log of `(path, command_string)` tuples recorded by the mock.
+    type CmdLog = Arc<Mutex<Vec<(PathBuf, String)>>>;
+
     /// Configurable `EnvironmentImpl` used by the VFS tests.
     ///
     /// * `expand_prefix` — when `Some`, `expand(p)` returns `prefix.join(p)`
@@ -304,16 +307,12 @@ mod tests {
     struct MockEnvImpl {
         expand_prefix: Option<PathBuf>,
         cmd_status: bool,
-        cmds: Arc<Mutex<Vec<(PathBuf, String)>>>,
+        cmds: CmdLog,
         env_vars: Arc<Mutex<HashMap<String, String>>>,
     }
 
     impl MockEnvImpl {
-        fn new() -> (
-            Self,
-            Arc<Mutex<Vec<(PathBuf, String)>>>,
-            Arc<Mutex<HashMap<String, String>>>,
-        ) {
+        fn new() -> (Self, CmdLog, Arc<Mutex<HashMap<String, String>>>) {
             let cmds = Arc::new(Mutex::new(Vec::new()));
             let env_vars = Arc::new(Mutex::new(HashMap::new()));
             (
@@ -390,13 +389,7 @@ mod tests {
                 .push((path.to_path_buf(), command.to_string()));
             Ok(self.cmd_status)
         }
-        async fn run(
-            &self,
-            _log: &Log,
-            _id: &Id,
-            _p: &Path,
-            _c: &Command,
-        ) -> EnvResult<bool> {
+        async fn run(&self, _log: &Log, _id: &Id, _p: &Path, _c: &Command) -> EnvResult<bool> {
             unimplemented!()
         }
         fn shell(&self, _p: &Path) -> EnvResult<()> {
@@ -420,11 +413,7 @@ mod tests {
     }
 
     /// Default mock: succeeds, echoes paths.
-    fn make_env() -> (
-        Environment,
-        Arc<Mutex<Vec<(PathBuf, String)>>>,
-        Arc<Mutex<HashMap<String, String>>>,
-    ) {
+    fn make_env() -> (Environment, CmdLog, Arc<Mutex<HashMap<String, String>>>) {
         let (mock, cmds, env_vars) = MockEnvImpl::new();
         (Environment::new(mock), cmds, env_vars)
     }
@@ -700,10 +689,7 @@ mod tests {
         let (mock, _, _) = MockEnvImpl::new();
         let env = Environment::new(mock.with_cmd_status(false));
         let vfs = new_vfs(&log, &env).await;
-        let err = vfs
-            .command("lint", "cargo", ["fmt"])
-            .await
-            .unwrap_err();
+        let err = vfs.command("lint", "cargo", ["fmt"]).await.unwrap_err();
         assert!(
             matches!(&err, EnvironmentError::Vfs { action } if action == "lint"),
             "expected Vfs{{action=lint}}, got {err:?}"
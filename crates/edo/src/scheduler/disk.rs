@@ -0,0 +1,116 @@
+//! Free disk space guardrails for a run.
+//!
+//! Running out of space halfway through writing a layer leaves a broken
+//! artifact behind and a confusing I/O error in front of the user, so the
+//! scheduler checks the file systems it writes to before the run and again
+//! before every transform it starts. When one falls below the configured
+//! minimum the local cache is pruned once; if that does not help, new
+//! transforms wait for the running ones, whose workspaces are cleaned up
+//! when they finish, and the run fails once nothing is left to wait for.
+
+use super::Result;
+use super::error;
+use crate::context::{Config, Context};
+use crate::util::{format_size, free_space, parse_size};
+use parking_lot::Mutex;
+use std::path::{Path, PathBuf};
+
+/// Free space kept by default on the watched file systems, in bytes.
+pub const DEFAULT_MIN_FREE_SPACE: u64 = 1 << 30;
+
+/// Reads `[scheduler] min_free_space`, a byte count such as `2GiB`, where
+/// `0` turns the guardrails off.
+///
+/// Like the other scheduler keys a malformed value falls back to the
+/// default rather than failing the build.
+pub(crate) fn min_free_space(config: &Config) -> u64 {
+    let Some(value) = config
+        .get("scheduler")
+        .and_then(|x| x.get("min_free_space"))
+    else {
+        return DEFAULT_MIN_FREE_SPACE;
+    };
+    match (value.as_int(), value.as_string()) {
+        (Some(size), _) => u64::try_from(size).ok(),
+        (_, Some(size)) => parse_size(&size),
+        _ => None,
+    }
+    .unwrap_or_else(|| {
+        warn!("ignoring scheduler.min_free_space, expected a number of bytes such as \"2GiB\"");
+        DEFAULT_MIN_FREE_SPACE
+    })
+}
+
+/// Watches the free space of the directories a run writes to.
+pub(crate) struct DiskGuard {
+    paths: Vec<PathBuf>,
+    min_free: u64,
+    pruned: Mutex<bool>,
+}
+
+impl DiskGuard {
+    /// Watches `paths`, which must keep at least `min_free` bytes free.
+    pub(crate) fn new(paths: Vec<PathBuf>, min_free: u64) -> Self {
+        Self {
+            paths,
+            min_free,
+            pruned: Mutex::new(false),
+        }
+    }
+
+    /// The first watched directory below the minimum, with its free space.
+    fn low(&self) -> Option<(&Path, u64)> {
+        if self.min_free == 0 {
+            return None;
+        }
+        self.paths.iter().find_map(|path| {
+            // Directories are created lazily, measure the closest existing one
+            let existing = path.ancestors().find(|x| x.exists())?;
+            let free = free_space(existing)?;
+            (free < self.min_free).then_some((path.as_path(), free))
+        })
+    }
+
+    /// Fails unless every watched directory has enough free space, pruning
+    /// stale entries from the local cache the first time one does not.
+    pub(crate) async fn ensure(&self, ctx: &Context) -> Result<()> {
+        let Some((path, free)) = self.low() else {
+            return Ok(());
+        };
+        let prune = !std::mem::replace(&mut *self.pruned.lock(), true);
+        if prune {
+            warn!(
+                "only {} free at {}, pruning stale entries from the local cache",
+                format_size(free),
+                path.display()
+            );
+            ctx.prune().await?;
+        }
+        match self.low() {
+            Some((path, free)) => error::DiskSpaceSnafu {
+                path: path.to_path_buf(),
+                free,
+                required: self.min_free,
+            }
+            .fail(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn low_reports_directories_under_the_minimum() {
+        let dir = TempDir::new().unwrap();
+        let missing = dir.path().join("not/yet/created");
+        assert!(DiskGuard::new(vec![missing.clone()], 1).low().is_none());
+        assert!(DiskGuard::new(vec![missing.clone()], 0).low().is_none());
+        let full = DiskGuard::new(vec![dir.path().into(), missing], u64::MAX);
+        let (path, _) = full.low().expect("no file system has u64::MAX bytes free");
+        assert_eq!(path, dir.path());
+    }
+}
@@ -17,12 +17,27 @@ pub enum SchedulerError {
     Cancelled,
     #[snafu(display("errors occured during execution: {}", children.iter().map(|x| x.to_string()).collect::<Vec<_>>().join("\n")))]
     Child { children: Vec<SchedulerError> },
+    #[snafu(display("{failure}"))]
+    Command {
+        failure: Box<crate::environment::CommandFailure>,
+    },
     #[snafu(display("dependency does not exist in execution graph: {addr}"))]
     Depend { addr: Addr },
     #[snafu(transparent)]
     Environment {
         source: crate::environment::EnvironmentError,
     },
+    #[snafu(display(
+        "only {} free at {}, at least {} is needed: free some space, for example with `edo prune --all`, or lower `[scheduler] min_free_space`",
+        crate::util::format_size(*free),
+        path.display(),
+        crate::util::format_size(*required)
+    ))]
+    DiskSpace {
+        path: std::path::PathBuf,
+        free: u64,
+        required: u64,
+    },
     #[snafu(display("failed to build execution graph: {source}"))]
     Graph { source: daggy::WouldCycle<String> },
     #[snafu(display("failed to prompt user: {source}"))]
@@ -37,8 +52,20 @@ pub enum SchedulerError {
     Node { addr: Addr },
     #[snafu(display("transformation didn't run"))]
     NoRun,
+    #[snafu(display("{dep} is not visible to {addr} (visibility: {visibility})"))]
+    NotVisible {
+        addr: Addr,
+        dep: Addr,
+        visibility: String,
+    },
     #[snafu(display("{message}"))]
     Passthrough { message: String },
+    #[snafu(display("{addr} cannot run: {source}"))]
+    Platform {
+        addr: Addr,
+        #[snafu(source(from(crate::context::ContextError, Box::new)))]
+        source: Box<crate::context::ContextError>,
+    },
     #[snafu(transparent)]
     Context {
         source: crate::context::ContextError,
@@ -84,6 +111,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn display_disk_space() {
+        let e = SchedulerError::DiskSpace {
+            path: "/work/.edo/env".into(),
+            free: 200 << 20,
+            required: 1 << 30,
+        };
+        assert_eq!(
+            e.to_string(),
+            "only 200.0 MiB free at /work/.edo/env, at least 1.0 GiB is needed: free some space, for example with `edo prune --all`, or lower `[scheduler] min_free_space`",
+        );
+    }
+
     #[test]
     fn display_node() {
         let e = SchedulerError::Node { addr: addr() };
@@ -99,6 +139,35 @@ mod tests {
         assert_eq!(e.to_string(), "transformation didn't run");
     }
 
+    #[test]
+    fn display_not_visible() {
+        let e = SchedulerError::NotVisible {
+            addr: Addr::parse("//app/main").unwrap(),
+            dep: addr(),
+            visibility: "private".into(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "//proj/name is not visible to //app/main (visibility: private)",
+        );
+    }
+
+    #[test]
+    fn display_platform() {
+        let e = SchedulerError::Platform {
+            addr: addr(),
+            source: Box::new(crate::context::ContextError::UnsupportedPlatform {
+                farm: Addr::parse("//default").unwrap(),
+                arch: "riscv64".into(),
+                platforms: "x86_64".into(),
+            }),
+        };
+        assert_eq!(
+            e.to_string(),
+            "//proj/name cannot run: //default cannot execute riscv64 (it runs x86_64) and no other environment farm can",
+        );
+    }
+
     #[test]
     fn display_passthrough() {
         let e = SchedulerError::Passthrough {
@@ -0,0 +1,185 @@
+//! Time left in a run, estimated from the durations of earlier runs.
+//!
+//! [`BuildEstimate`] follows the transforms a run still has to execute,
+//! each expected to take as long as it did before, see
+//! [`Durations`](crate::context::Durations). A run cannot finish before its
+//! longest chain of dependent transforms, nor before its workers got through
+//! all of the work left, so the larger of the two is reported. Transforms
+//! that never ran before are expected to take as long as the average of
+//! those that did.
+
+use daggy::NodeIndex;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::context::format_eta;
+
+/// A transform left to execute.
+struct Entry {
+    expected: Duration,
+    depends: Vec<NodeIndex>,
+    started: Option<Instant>,
+    finished: bool,
+}
+
+impl Entry {
+    fn left(&self, now: Instant) -> Duration {
+        match (self.finished, self.started) {
+            (true, _) => Duration::ZERO,
+            (false, Some(started)) => self.expected.saturating_sub(now - started),
+            (false, None) => self.expected,
+        }
+    }
+}
+
+/// Estimates the time left in a run as its transforms start and finish.
+pub(crate) struct BuildEstimate {
+    workers: u64,
+    nodes: Mutex<HashMap<NodeIndex, Entry>>,
+}
+
+impl BuildEstimate {
+    /// Follows the transforms of `nodes`, each listed with how long it is
+    /// expected to take, if it ran before, and the transforms among `nodes`
+    /// it depends on. Returns `None` when none of them ran before.
+    pub fn new(
+        workers: u64,
+        nodes: Vec<(NodeIndex, Option<Duration>, Vec<NodeIndex>)>,
+    ) -> Option<Self> {
+        let known: Vec<Duration> = nodes.iter().filter_map(|x| x.1).collect();
+        let average = known
+            .iter()
+            .sum::<Duration>()
+            .checked_div(known.len() as u32)?;
+        let nodes = nodes
+            .into_iter()
+            .map(|(n, expected, depends)| {
+                let entry = Entry {
+                    expected: expected.unwrap_or(average),
+                    depends,
+                    started: None,
+                    finished: false,
+                };
+                (n, entry)
+            })
+            .collect();
+        Some(Self {
+            workers: workers.max(1),
+            nodes: Mutex::new(nodes),
+        })
+    }
+
+    /// Records that `n` started executing.
+    pub fn started(&self, n: NodeIndex) {
+        if let Some(entry) = self.nodes.lock().get_mut(&n) {
+            entry.started = Some(Instant::now());
+        }
+    }
+
+    /// Records that `n` finished executing.
+    pub fn finished(&self, n: NodeIndex) {
+        if let Some(entry) = self.nodes.lock().get_mut(&n) {
+            entry.finished = true;
+        }
+    }
+
+    /// How long the rest of the run is expected to take.
+    pub fn remaining(&self) -> Duration {
+        self.remaining_at(Instant::now())
+    }
+
+    fn remaining_at(&self, now: Instant) -> Duration {
+        let nodes = self.nodes.lock();
+        let mut paths = HashMap::new();
+        let critical = nodes
+            .keys()
+            .map(|n| path(&nodes, *n, now, &mut paths))
+            .max()
+            .unwrap_or_default();
+        let total: Duration = nodes.values().map(|x| x.left(now)).sum();
+        critical.max(total / self.workers as u32)
+    }
+}
+
+/// Time left until `n` and every transform it depends on finished.
+fn path(
+    nodes: &HashMap<NodeIndex, Entry>,
+    n: NodeIndex,
+    now: Instant,
+    paths: &mut HashMap<NodeIndex, Duration>,
+) -> Duration {
+    if let Some(known) = paths.get(&n) {
+        return *known;
+    }
+    let Some(entry) = nodes.get(&n) else {
+        return Duration::ZERO;
+    };
+    let before = entry
+        .depends
+        .iter()
+        .map(|x| path(nodes, *x, now, paths))
+        .max()
+        .unwrap_or_default();
+    let total = before + entry.left(now);
+    paths.insert(n, total);
+    total
+}
+
+/// Describes the time left of a transform expected to take `expected` that
+/// has been running for `elapsed`.
+pub(crate) fn countdown(expected: Duration, elapsed: Duration) -> String {
+    match expected.checked_sub(elapsed) {
+        Some(left) => format!("eta {}", format_eta(left)),
+        None => format!("{} over estimate", format_eta(elapsed - expected)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(value: u64) -> Duration {
+        Duration::from_secs(value)
+    }
+
+    #[test]
+    fn remaining_time_follows_the_longest_chain_or_the_total_work() {
+        let (a, b, c, d) = (
+            NodeIndex::new(0),
+            NodeIndex::new(1),
+            NodeIndex::new(2),
+            NodeIndex::new(3),
+        );
+        let nodes = || {
+            vec![
+                (a, Some(secs(60)), Vec::new()),
+                (b, Some(secs(120)), vec![a]),
+                (c, Some(secs(30)), Vec::new()),
+                // Never ran, expected to take the 70s average
+                (d, None, vec![c]),
+            ]
+        };
+        assert!(BuildEstimate::new(2, vec![(a, None, Vec::new())]).is_none());
+
+        // Two workers get through the chain a, b while c, d run beside it
+        let estimate = BuildEstimate::new(2, nodes()).unwrap();
+        let now = Instant::now();
+        assert_eq!(estimate.remaining_at(now), secs(180));
+        // A single worker has to execute everything in turn
+        let serial = BuildEstimate::new(1, nodes()).unwrap();
+        assert_eq!(serial.remaining_at(now), secs(280));
+
+        estimate.started(a);
+        estimate.nodes.lock().get_mut(&a).unwrap().started = Some(now);
+        assert_eq!(estimate.remaining_at(now + secs(20)), secs(160));
+        estimate.finished(a);
+        assert_eq!(estimate.remaining_at(now + secs(20)), secs(120));
+    }
+
+    #[test]
+    fn countdowns_report_overruns() {
+        assert_eq!(countdown(secs(300), secs(55)), "eta 4m05s");
+        assert_eq!(countdown(secs(60), secs(90)), "30s over estimate");
+    }
+}
@@ -5,7 +5,7 @@
 
 use super::{Result, error};
 use crate::{
-    context::{Handle, Log},
+    context::{Handle, Log, LogVerbosity},
     environment::Environment,
     storage::Artifact,
     transform::{Transform, TransformStatus},
@@ -13,18 +13,21 @@ use crate::{
 use dialoguer::{Editor, Select};
 use snafu::ResultExt;
 use std::fs::read_to_string;
+use std::io::IsTerminal;
 use tracing_indicatif::suspend_tracing_indicatif;
 
 /// Executes a transform with interactive error recovery.
 ///
 /// Runs the given transform within the provided environment. On failure,
 /// prompts the user with options to view logs, retry, open a shell, or quit.
-/// On success, uploads the resulting artifact to the build cache.
+/// On success, uploads the resulting artifact to the build cache when
+/// `upload` is set.
 pub async fn execute(
     log: &Log,
     ctx: &Handle,
     transform: &Transform,
     env: &Environment,
+    upload: bool,
 ) -> Result<Artifact> {
     #[allow(unused_assignments)]
     let mut result: Result<Artifact> = error::NoRunSnafu {}.fail();
@@ -40,7 +43,32 @@ pub async fn execute(
             // If the attempt failed for any reason we need to prompt the user what
             // we should do about it.
             TransformStatus::Retryable(log_file, e) | TransformStatus::Failed(log_file, e) => {
+                // Commands killed by a Ctrl-C fail too, there is nothing to
+                // ask the user about them
+                if ctx.cancellation().is_cancelled() {
+                    result = error::CancelledSnafu.fail();
+                    break 'transform;
+                }
                 error!(target: "transform", "transformation failed: {}", e.to_string());
+                // Without a terminal there is nobody to ask, so fail with
+                // whatever we know about the failure. The same goes for a
+                // silenced console, whose screen belongs to a dashboard.
+                if !std::io::stdin().is_terminal()
+                    || !std::io::stderr().is_terminal()
+                    || ctx.log().verbosity() == LogVerbosity::Silent
+                {
+                    result = match attempt_result.command_failure() {
+                        Some(failure) => error::CommandSnafu {
+                            failure: Box::new(failure.clone()),
+                        }
+                        .fail(),
+                        None => error::PassthroughSnafu {
+                            message: e.to_string(),
+                        }
+                        .fail(),
+                    };
+                    break 'transform;
+                }
                 // Collect the valid options to present the user with
                 let mut options = Vec::new();
                 if log_file.is_some() {
@@ -108,7 +136,13 @@ pub async fn execute(
     // Upload the result if we have a build cache setup
     match result {
         Ok(artifact) => {
-            ctx.storage().upload_build(artifact.config().id()).await?;
+            if upload {
+                let token = ctx.cancellation();
+                tokio::select! {
+                    uploaded = ctx.storage().upload_build(artifact.config().id()) => uploaded?,
+                    _ = token.cancelled() => return error::CancelledSnafu.fail(),
+                }
+            }
             Ok(artifact)
         }
         Err(e) => Err(e),
@@ -311,7 +345,7 @@ mod tests {
             digest: "deadbeef".to_string(),
         });
 
-        let artifact = execute(&log, &handle, &transform, &env)
+        let artifact = execute(&log, &handle, &transform, &env, true)
             .await
             .expect("execute success");
         assert_eq!(artifact.config().id().digest(), "deadbeef");
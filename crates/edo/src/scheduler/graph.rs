@@ -18,8 +18,8 @@
 //! ```ignore
 //! let mut g = Graph::new(workers);
 //! g.add(ctx, &target).await?;        // build the DAG
-//! g.fetch(ctx).await?;               // hash + cache-check + prepare
-//! Arc::new(g).run(path, ctx, &target).await?; // execute
+//! g.probe(ctx).await?;               // hash + cache-check
+//! Arc::new(g).run(path, ctx, &target).await?; // prepare just in time + execute
 //! ```
 //!
 //! Each phase is independent and can fail without leaving the graph in a
@@ -38,12 +38,13 @@ use async_recursion::async_recursion;
 use bimap::BiHashMap;
 use daggy::{Dag, NodeIndex, Walker, petgraph::visit::IntoNodeReferences};
 use futures::future::try_join_all;
-use snafu::{OptionExt, ResultExt};
+use snafu::{OptionExt, ResultExt, ensure};
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     ops::Index,
-    path::Path,
-    sync::Arc,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
 };
 use tempfile::TempDir;
 use tokio::sync::{Mutex, Semaphore, mpsc::channel};
@@ -51,12 +52,19 @@ use tokio::task::{JoinError, JoinHandle};
 use tokio_util::sync::CancellationToken;
 use tracing::Instrument;
 
-use crate::context::{Addr, Context, Handle};
-use crate::storage::{Artifact, Id};
+use crate::context::{
+    Addr, Context, Durations, EventKind, Handle, NESTED_CACHE_ENV, NodeOutcome, NodeSummary,
+    Progress, format_eta,
+};
+use crate::environment::{HostAccess, HostMount};
+use crate::storage::{Artifact, CacheSelector, Id};
 use crate::transform::Transform;
 
+use super::disk::DiskGuard;
+use super::estimate::{BuildEstimate, countdown};
 use super::node::Node;
-use super::{Result, error};
+use super::snapshot::Snapshots;
+use super::{KeepWorkspace, Result, error};
 
 /// Execution graph: the DAG plus per-root metadata required to dispatch
 /// transforms in topological order with bounded concurrency.
@@ -100,6 +108,62 @@ pub struct Graph {
     /// `run` clones the inner map at start and decrements it as nodes
     /// complete; that's why this is a "template" rather than mutable state.
     indegrees: HashMap<Addr, HashMap<NodeIndex, u32>>,
+    /// When set, `probe` ignores the build cache so every node is rebuilt,
+    /// and `run` does not upload the results. Used to verify that a
+    /// transform reproduces the artifact already in the cache.
+    fresh: bool,
+    /// Which transform workspaces `run` keeps instead of removing.
+    keep: KeepWorkspace,
+    /// Directory kept workspaces are moved to.
+    debug: PathBuf,
+    /// Most transforms in flight at once per environment farm, on top of
+    /// `batch_size`. Mirrors the `[scheduler] farms` config table.
+    farm_limits: HashMap<Addr, u64>,
+    /// Cancels `run` when fired, such as by Ctrl-C.
+    cancellation: CancellationToken,
+    /// Holds back transforms while disk space is low, see `set_disk_guard`.
+    disk: Option<Arc<DiskGuard>>,
+    /// Snapshots of staged environments, see `set_snapshots`.
+    snapshots: Option<Arc<Snapshots>>,
+    /// Cache the logs of built transforms are uploaded to, see `set_log_cache`.
+    log_cache: Option<CacheSelector>,
+    /// Durations of earlier runs, see `set_durations`.
+    durations: Durations,
+    /// Time the last `run` was expected to take when it started.
+    estimate: OnceLock<Duration>,
+    /// The handle `probe` computed ids with, reused by `fetch` and `run` so
+    /// the ids and keys it memoized are not hashed again while staging.
+    handle: OnceLock<Handle>,
+}
+
+/// Where a transform's environment is created, and what happens to it after.
+struct Workspace {
+    /// Parent of the per-transform temp directories.
+    path: PathBuf,
+    /// Whether the temp directory is kept once the transform finishes.
+    keep: KeepWorkspace,
+    /// Directory kept temp directories are moved to.
+    debug: PathBuf,
+    /// Snapshots restored in place of staging, when enabled.
+    snapshots: Option<Arc<Snapshots>>,
+    /// Cache the log is uploaded to along with the artifact, when set.
+    logs: Option<CacheSelector>,
+}
+
+impl Workspace {
+    /// Moves `temp` to `<debug>/<addr>-<timestamp>`, returning the new path.
+    fn retain(&self, addr: &Addr, temp: TempDir) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.debug).context(error::IoSnafu)?;
+        let addr = addr.to_string();
+        let target = self.debug.join(format!(
+            "{}-{}",
+            addr.strip_prefix("//").unwrap_or(&addr).replace('/', "-"),
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%3fZ")
+        ));
+        let path = temp.keep();
+        std::fs::rename(&path, &target).context(error::IoSnafu)?;
+        Ok(target)
+    }
 }
 
 impl Graph {
@@ -116,9 +180,85 @@ impl Graph {
             index: BiHashMap::new(),
             subgraphs: HashMap::new(),
             indegrees: HashMap::new(),
+            fresh: false,
+            keep: KeepWorkspace::default(),
+            debug: PathBuf::new(),
+            farm_limits: HashMap::new(),
+            cancellation: CancellationToken::new(),
+            disk: None,
+            snapshots: None,
+            log_cache: None,
+            durations: Durations::default(),
+            estimate: OnceLock::new(),
+            handle: OnceLock::new(),
         }
     }
 
+    /// Returns the handle every phase of a run shares, made from `ctx` the
+    /// first time it is asked for.
+    fn handle(&self, ctx: &Context) -> Handle {
+        self.handle.get_or_init(|| ctx.get_handle()).clone()
+    }
+
+    /// Rebuilds every node regardless of the build cache, without uploading
+    /// the results. See [`Scheduler::rebuild`](super::Scheduler::rebuild).
+    pub fn set_fresh(&mut self, fresh: bool) {
+        self.fresh = fresh;
+    }
+
+    /// Keeps the workspaces `keep` selects in `debug` rather than removing
+    /// them. See [`KeepWorkspace`].
+    pub fn set_keep_workspace(&mut self, keep: KeepWorkspace, debug: &Path) {
+        self.keep = keep;
+        self.debug = debug.to_path_buf();
+    }
+
+    /// Cancels `run` when `token` fires. Transforms in flight are stopped
+    /// and their environments torn down, transforms not yet started are
+    /// skipped.
+    pub fn set_cancellation(&mut self, token: &CancellationToken) {
+        self.cancellation = token.clone();
+    }
+
+    /// Checks free disk space before `run` starts each transform. While it
+    /// is low no new transform starts until a running one finishes, and the
+    /// run fails once nothing is running.
+    pub(crate) fn set_disk_guard(&mut self, disk: &Arc<DiskGuard>) {
+        self.disk = Some(disk.clone());
+    }
+
+    /// Snapshots each environment once staged, and restores the snapshot
+    /// instead of staging when a transform runs again with the same inputs.
+    pub(crate) fn set_snapshots(&mut self, snapshots: &Arc<Snapshots>) {
+        self.snapshots = Some(snapshots.clone());
+    }
+
+    /// Uploads the log of each transform that built to `cache` whenever its
+    /// artifact is uploaded. See [`log_cache`](super::log_cache).
+    pub fn set_log_cache(&mut self, cache: Option<CacheSelector>) {
+        self.log_cache = cache;
+    }
+
+    /// Expects each transform `run` executes to take as long as it did in
+    /// the earlier runs of `durations`. The time left is shown next to each
+    /// running transform and the whole build.
+    pub fn set_durations(&mut self, durations: Durations) {
+        self.durations = durations;
+    }
+
+    /// How long `run` expected the build to take when it started, if any of
+    /// its transforms ran before.
+    pub fn estimate(&self) -> Option<Duration> {
+        self.estimate.get().copied()
+    }
+
+    /// Caps how many transforms `run` has in flight at once in each farm of
+    /// `limits`, keyed by resolved farm address. Farms left out are only
+    /// bound by the worker count.
+    pub fn set_farm_limits(&mut self, limits: HashMap<Addr, u64>) {
+        self.farm_limits = limits;
+    }
+
     /// Recursively adds a transform and its dependencies to the graph.
     ///
     /// Returns the `NodeIndex` of the added (or existing) node. Edges are
@@ -130,6 +270,9 @@ impl Graph {
     /// `NodeIndex` without re-walking its dependencies.
     #[async_recursion]
     async fn add_recursive(&mut self, ctx: &Context, addr: &Addr) -> Result<NodeIndex> {
+        // Key nodes by the aliased definition so a transform reached through
+        // an old address is not built twice.
+        let addr = &ctx.resolve_alias(addr);
         // Fast path: node already registered. Without this, a diamond DAG
         // would infinite-loop on the shared dependency.
         if let Some(index) = self.index.get_by_left(addr) {
@@ -146,6 +289,16 @@ impl Graph {
         // (or finds it via the fast path) and we wire an edge dep -> self.
         // `add_edge` is what catches cycles — daggy returns `WouldCycle`.
         for dep in transform.depends().await? {
+            let dep = ctx.resolve_alias(&dep);
+            let visibility = ctx.get_visibility(&dep);
+            ensure!(
+                visibility.allows(&dep, addr),
+                error::NotVisibleSnafu {
+                    addr: addr.clone(),
+                    dep: dep.clone(),
+                    visibility: visibility.to_string(),
+                }
+            );
             let child = self.add_recursive(ctx, &dep).await?;
             trace!(component = "execution", "adding edge for {dep} -> {addr}");
             self.graph
@@ -206,58 +359,116 @@ impl Graph {
         Ok(idx)
     }
 
-    /// Computes content-addressed ids, checks the build cache, and prepares
-    /// (downloads sources for) every node in the graph.
+    /// Computes content-addressed ids and checks the build cache for every
+    /// node in the graph, without preparing any of them.
     ///
     /// For each node:
-    /// 1. Compute its [`Id`] via [`Transform::get_unique_id`] and stash it
-    ///    on the node.
-    /// 2. Probe the build cache. If a fully-built artifact exists for that
-    ///    id we mark the node as a cache hit and skip preparation entirely
-    ///    — `run` will short-circuit dispatch for cache-hit subtrees in its
-    ///    pre-pass cascade.
-    /// 3. Otherwise spawn a task that calls [`Transform::prepare`] (typically
-    ///    a network fetch of sources and ancillary artifacts).
+    /// 1. Compute its [`Id`] via
+    ///    [`Handle::unique_id`](crate::context::Handle::unique_id) and stash
+    ///    it on the node. The handle is shared by every node and kept for
+    ///    `fetch` and `run`, so each id in the graph is hashed once however
+    ///    many dependents ask for it.
+    /// 2. Probe the build cache, asking each cache once for every id. If a
+    ///    fully-built artifact exists for that id we mark the node as a cache
+    ///    hit — `run` will short-circuit dispatch for cache-hit subtrees in
+    ///    its pre-pass cascade, and the node is never prepared.
     ///
-    /// Concurrency is bounded by a [`Semaphore`] sized to `batch_size`.
-    /// Fetch parallelism is order-independent (unlike `run`'s topological
-    /// dispatch), so we don't need ready/ready-not state — just a permit
-    /// pool that throttles the network.
-    pub async fn fetch(&self, ctx: &Context) -> Result<()> {
-        let mut tasks = Vec::new();
-        let ctx = ctx.get_handle();
-        let max_concurrent = self.batch_size;
-
-        // Fetching is network-bound. We don't want to issue thousands of
-        // requests in parallel, but unlike execution we also don't need to
-        // respect topological order — sources for a child can pull at the
-        // same time as sources for its parent. A semaphore is the simplest
-        // way to cap in-flight fetches at `batch_size`.
-        let semaphore = Arc::new(Semaphore::new(max_concurrent as usize));
+    /// `run` prepares the remaining nodes as their dependencies approach
+    /// readiness, so a probed graph can be run straight away.
+    pub async fn probe(&self, ctx: &Context) -> Result<()> {
+        let ctx = self.handle(ctx);
+        // Caches may keep their catalog for a while, a run starts from the
+        // current state of every cache
+        ctx.storage().refresh().await?;
+        let mut nodes = Vec::new();
         for node_ref in self.graph.node_references() {
+            ensure!(!self.cancellation.is_cancelled(), error::CancelledSnafu);
             let node: Arc<Node> = node_ref.1.clone();
             let transform = ctx.get(&node.addr).context(error::ProjectTransformSnafu {
                 addr: node.addr.clone(),
             })?;
+            // Check the transform can execute where it asked to before
+            // anything runs, moving it to a farm that can execute its
+            // architecture and meets its host requirements when the
+            // requested one cannot.
+            let requested = ctx.resolve_alias(&transform.environment().await?);
+            let farm = ctx
+                .platform_farm(
+                    &requested,
+                    transform.platform().as_deref(),
+                    &transform.host_requires(),
+                )
+                .context(error::PlatformSnafu {
+                    addr: node.addr.clone(),
+                })?;
+            if farm != requested {
+                info!(
+                    "running {} in {farm}, {requested} cannot execute its platform or does not meet its host_requires",
+                    node.addr
+                );
+            }
+            node.set_farm(&farm);
+
             // Compute the content-addressed id and stash it on the node so
             // workers in `run` can index into the build cache without
-            // recomputing it.
-            let id = transform.get_unique_id(&ctx).await?;
+            // recomputing it. The key it came from goes in the run summary.
+            let key = ctx.cache_key(&node.addr, &transform).await?;
+            let id = key.id();
             node.set_id(&id);
+            node.set_key(key.breakdown());
+            nodes.push((node, id));
+        }
+        if self.fresh {
+            return Ok(());
+        }
 
-            // Build cache probe. `find_build(.., true)` requires a *full*
-            // artifact (all layers present) — partial hits do not count.
-            // `cache_hit = true` will let `run`'s pre-pass cascade promote
-            // this node and any cache-hit ancestors to Success without
-            // ever spawning an environment.
-            if ctx.storage().find_build(&id, true).await?.is_some() {
+        // Build cache probe. Every cache is asked about all the ids at once,
+        // then `find_build(.., true)` downloads the hits and requires a *full*
+        // artifact (all layers present) — partial hits do not count.
+        // `cache_hit = true` will let `run`'s pre-pass cascade promote
+        // this node and any cache-hit ancestors to Success without
+        // ever spawning an environment.
+        let ids: Vec<Id> = nodes.iter().map(|(_, id)| id.clone()).collect();
+        let built = ctx.storage().find_builds(&ids).await?;
+        for (node, id) in nodes {
+            if built.contains(&id) && ctx.storage().find_build(&id, true).await?.is_some() {
                 info!("skipped fetch for built entry {}", node.addr);
                 node.set_cache_hit(true);
-                continue;
             }
+        }
+        Ok(())
+    }
+
+    /// Probes every node like [`Graph::probe`], then prepares (downloads
+    /// sources for) every node that is not already built.
+    ///
+    /// Preparation calls [`Transform::prepare`] (typically a network fetch
+    /// of sources and ancillary artifacts) in parallel tasks, bounded by a
+    /// [`Semaphore`] sized to `batch_size`. Fetch parallelism is
+    /// order-independent (unlike `run`'s topological dispatch), so we don't
+    /// need ready/ready-not state — just a permit pool that throttles the
+    /// network. Used when nothing is going to run, by `edo fetch`.
+    pub async fn fetch(&self, ctx: &Context) -> Result<()> {
+        self.probe(ctx).await?;
+        let mut tasks = Vec::new();
+        let ctx = self.handle(ctx);
 
+        // Fetching is network-bound. We don't want to issue thousands of
+        // requests in parallel, but unlike execution we also don't need to
+        // respect topological order — sources for a child can pull at the
+        // same time as sources for its parent. A semaphore is the simplest
+        // way to cap in-flight fetches at `batch_size`.
+        let semaphore = Arc::new(Semaphore::new(self.batch_size as usize));
+        for node_ref in self.graph.node_references() {
+            let node: Arc<Node> = node_ref.1.clone();
+            if node.is_cache_hit() {
+                continue;
+            }
+            let transform = ctx.get(&node.addr).context(error::ProjectTransformSnafu {
+                addr: node.addr.clone(),
+            })?;
+            let id = node.id().context(error::InfallableSnafu)?.clone();
             let ctx = ctx.clone();
-            let node_for_task = node.clone();
             // Acquire the permit *outside* the spawn so the loop blocks
             // here when we're already at capacity. Owned permits are moved
             // into the task and released on drop.
@@ -268,11 +479,7 @@ impl Graph {
                 .ok()
                 .context(error::InfallableSnafu)?;
             tasks.push(tokio::spawn(async move {
-                let logf = ctx.log().create(format!("{id}").as_str()).await?;
-                logf.set_subject("fetch");
-                transform.prepare(&logf, &ctx).await?;
-                info!("pulled sources and artifacts for {}", node_for_task.addr);
-                drop(logf);
+                prepare(&ctx, &node, &transform, &id).await?;
                 // Explicit drop is documentation: the permit returns to
                 // the pool exactly when this task ends.
                 drop(permit);
@@ -293,7 +500,7 @@ impl Graph {
     /// ## High-level shape
     ///
     /// 1. **Resolve start.** Look up `addr` in the index and bail early if
-    ///    its node was already cache-hit by `fetch` — there's nothing to
+    ///    its node was already cache-hit by `probe` — there's nothing to
     ///    run.
     /// 2. **Snapshot dispatch state.** Clone the per-root indegree template
     ///    so we can mutate it without affecting future runs of the same
@@ -321,7 +528,7 @@ impl Graph {
     /// post the result. That keeps the scheduling logic single-threaded and
     /// lock-free without giving up parallelism on the actual work.
     pub async fn run(&self, path: &Path, ctx: &Context, addr: &Addr) -> Result<()> {
-        let ctx_handle = ctx.get_handle();
+        let ctx_handle = self.handle(ctx).with_cancellation(&self.cancellation);
         let token = ctx_handle.cancellation();
 
         // ── Step 1: resolve the target node. ──────────────────────────────
@@ -330,12 +537,18 @@ impl Graph {
             .get_by_left(addr)
             .context(error::NodeSnafu { addr: addr.clone() })?;
         let root_node = self.graph.index(*start);
+        let events = ctx_handle.log().events();
 
-        // Early exit: the target itself is already built. `fetch` populates
+        // Early exit: the target itself is already built. `probe` populates
         // `cache_hit`; if the root is one we don't even need to walk its
         // dependencies — they only matter if we have to rebuild.
         if root_node.is_cache_hit() {
             info!("{addr} is already built, skipping...");
+            events.publish(EventKind::Planned {
+                addr: addr.clone(),
+                depends: Vec::new(),
+            });
+            events.publish(EventKind::Cached { addr: addr.clone() });
             return Ok(());
         }
 
@@ -347,6 +560,9 @@ impl Graph {
             .subgraphs
             .get(addr)
             .context(error::NodeSnafu { addr: addr.clone() })?;
+        if events.is_observed() {
+            self.publish_plan(subgraph, events);
+        }
         let mut indegree = self
             .indegrees
             .get(addr)
@@ -360,7 +576,16 @@ impl Graph {
         // may themselves be cache hits, and so on. The cascade can promote
         // entire subtrees to Success without ever spawning a worker.
         //
-        // Non-hit frontier nodes drop into `ready` for the dispatcher.
+        // Non-hit frontier nodes drop into `ready` for the dispatcher, and
+        // start fetching right away. Promoted nodes count as dispatched for
+        // the look-ahead, so their children start fetching too.
+        let mut lookahead = Lookahead {
+            ctx: ctx_handle.clone(),
+            permits: Arc::new(Semaphore::new(self.batch_size as usize)),
+            waiting: indegree.clone(),
+            started: HashSet::new(),
+            tasks: Vec::new(),
+        };
         let mut cascade: VecDeque<NodeIndex> = indegree
             .iter()
             .filter_map(|(n, d)| if *d == 0 { Some(*n) } else { None })
@@ -370,6 +595,10 @@ impl Graph {
             let node = self.graph.index(n);
             if node.is_cache_hit() {
                 node.set_success();
+                events.publish(EventKind::Cached {
+                    addr: node.addr.clone(),
+                });
+                lookahead.dispatched(&self.graph, subgraph, n)?;
                 // Decrement each in-subgraph child's indegree; any that
                 // hit zero join the cascade so we can keep promoting.
                 for (_, c) in self.graph.children(n).iter(&self.graph) {
@@ -384,10 +613,70 @@ impl Graph {
                 }
             } else {
                 // Not a cache hit — this is real work for the worker pool.
+                lookahead.start(&self.graph, n);
                 ready.push_back(n);
             }
         }
 
+        // ── Step 3a: estimates. ───────────────────────────────────────────
+        // Every node left to execute is expected to take as long as it did
+        // in earlier runs. The progress of the span `run` executes in counts
+        // finished transforms, and its message the time left in the build.
+        let mut work = Vec::new();
+        for n in subgraph.iter() {
+            let node = self.graph.index(*n);
+            if node.is_cache_hit() {
+                continue;
+            }
+            if let Some(expected) = node.id().and_then(|x| self.durations.estimate(&x.prefix())) {
+                let _ = node.estimate.set(expected);
+            }
+            let depends = self
+                .graph
+                .parents(*n)
+                .iter(&self.graph)
+                .map(|(_, p)| p)
+                .filter(|p| subgraph.contains(p) && !self.graph.index(*p).is_cache_hit())
+                .collect();
+            work.push((*n, node.estimate.get().copied(), depends));
+        }
+        let progress = Progress::current();
+        progress.set_total(work.len() as u64);
+        let estimate = BuildEstimate::new(self.batch_size, work).map(Arc::new);
+        let _ticker = estimate.as_ref().map(|estimate| {
+            let _ = self.estimate.set(estimate.remaining());
+            let estimate = estimate.clone();
+            progress.tick(move || format!("eta {}", format_eta(estimate.remaining())))
+        });
+
+        // ── Step 3b: per-farm tokens. ─────────────────────────────────────
+        // A node whose environment farm has a concurrency limit holds one
+        // of the farm's tokens while in flight, so e.g. a VM farm can be
+        // kept to a couple of environments while local ones use every
+        // worker. Only nodes that may be dispatched need a farm.
+        let mut farms: HashMap<NodeIndex, Addr> = HashMap::new();
+        if !self.farm_limits.is_empty() {
+            for n in subgraph.iter() {
+                let node = self.graph.index(*n);
+                if node.is_cache_hit() {
+                    continue;
+                }
+                let transform =
+                    ctx.get_transform(&node.addr)
+                        .context(error::ProjectTransformSnafu {
+                            addr: node.addr.clone(),
+                        })?;
+                let farm = match node.farm.get() {
+                    Some(farm) => farm.clone(),
+                    None => ctx.resolve_alias(&transform.environment().await?),
+                };
+                if self.farm_limits.contains_key(&farm) {
+                    farms.insert(*n, farm);
+                }
+            }
+        }
+        let mut tokens = self.farm_limits.clone();
+
         // ── Step 4: spawn the worker pool. ────────────────────────────────
         // Two MPSC channels:
         //   - work_tx/work_rx: driver -> workers, carries NodeIndex.
@@ -408,9 +697,16 @@ impl Graph {
             let work_rx = work_rx.clone();
             let done_tx = done_tx.clone();
             let ctx_clone = ctx_handle.clone();
-            let path_buf = path.to_path_buf();
+            let workspace = Workspace {
+                path: path.to_path_buf(),
+                keep: self.keep,
+                debug: self.debug.clone(),
+                snapshots: self.snapshots.clone(),
+                logs: self.log_cache.clone(),
+            };
             let graph = self.graph.clone();
             let token = token.clone();
+            let upload = !self.fresh;
             worker_handles.push(tokio::spawn(async move {
                 loop {
                     // Briefly hold the receive lock just long enough to
@@ -442,14 +738,39 @@ impl Graph {
                             continue;
                         }
                     };
-                    // `fetch` is required to have run before `run`, so the
+                    // `probe` is required to have run before `run`, so the
                     // id is always populated by this point.
                     let id = node.id().context(error::InfallableSnafu)?.clone();
-                    let result = run_transform_lifecycle(
-                        &ctx_clone, &path_buf, &node, &transform, &id, &token,
-                    )
-                    .instrument(info_span!("transforming", addr = node.addr.to_string()))
+                    let started = Instant::now();
+                    let span = info_span!("transforming", addr = node.addr.to_string());
+                    let _ticker = node.estimate.get().copied().map(|expected| {
+                        Progress::for_span(span.clone())
+                            .tick(move || countdown(expected, started.elapsed()))
+                    });
+                    let result = async {
+                        // Usually done by a look-ahead fetch already, in
+                        // which case this returns at once or waits for it
+                        tokio::select! {
+                            prepared = prepare(&ctx_clone, &node, &transform, &id) => prepared?,
+                            _ = token.cancelled() => return error::CancelledSnafu.fail(),
+                        }
+                        run_transform_lifecycle(
+                            &ctx_clone, &workspace, &node, &transform, &id, &token, upload,
+                        )
+                        .await
+                    }
+                    .instrument(span)
                     .await;
+                    node.set_elapsed(started.elapsed());
+                    ctx_clone.log().events().publish(EventKind::Finished {
+                        addr: node.addr.clone(),
+                        outcome: match &result {
+                            Ok(_) => NodeOutcome::Built,
+                            Err(error::SchedulerError::Cancelled) => NodeOutcome::Skipped,
+                            Err(_) => NodeOutcome::Failed,
+                        },
+                        duration_ms: started.elapsed().as_millis() as u64,
+                    });
                     // If the driver has gone away (done_rx dropped) there's
                     // nobody left to report to — exit quietly.
                     if done_tx.send((idx, result)).await.is_err() {
@@ -470,22 +791,63 @@ impl Graph {
         //     which are not already cache hits.
         //   - Once `failed` is set or the cancellation token fires, no new
         //     work is dispatched; we drain in-flight tasks then exit.
+        //   - Farm limits are at least 1, so with nothing in flight every
+        //     farm has a token and the first ready node can be dispatched.
         let mut inflight: usize = 0;
         let mut failed = false;
         let mut first_error: Option<error::SchedulerError> = None;
+        let mut paused = false;
 
         loop {
-            // Saturate the pool: push ready work into `work_tx` until
-            // either we run out of ready nodes or hit the concurrency cap.
-            // We pause dispatching on failure or cancellation so the
-            // remaining in-flight tasks can drain naturally.
-            while !ready.is_empty()
-                && inflight < self.batch_size as usize
+            // Disk space guardrail: with space low, hold dispatch until an
+            // in-flight transform finishes and frees its workspace, or fail
+            // when there is nothing left to wait for.
+            let mut hold = false;
+            if let Some(disk) = &self.disk
+                && !ready.is_empty()
                 && !failed
                 && !token.is_cancelled()
             {
-                let n = ready.pop_front().context(error::InfallableSnafu)?;
+                match disk.ensure(ctx).await {
+                    Ok(()) => paused = false,
+                    Err(e) if inflight > 0 => {
+                        if !paused {
+                            warn!("{e}, waiting for running transforms before starting more");
+                        }
+                        paused = true;
+                        hold = true;
+                    }
+                    Err(e) => {
+                        error!("{e}");
+                        first_error.get_or_insert(e);
+                        failed = true;
+                        ready.clear();
+                    }
+                }
+            }
+
+            // Saturate the pool: push ready work into `work_tx` until
+            // either we run out of ready nodes or hit the concurrency cap.
+            // Nodes whose farm has no token left wait in `ready` while
+            // later ones go ahead. We pause dispatching on failure or
+            // cancellation so the remaining in-flight tasks can drain
+            // naturally.
+            while !hold && inflight < self.batch_size as usize && !failed && !token.is_cancelled() {
+                let Some(position) = ready
+                    .iter()
+                    .position(|n| farms.get(n).is_none_or(|farm| tokens[farm] > 0))
+                else {
+                    break;
+                };
+                let n = ready.remove(position).context(error::InfallableSnafu)?;
+                if let Some(farm) = farms.get(&n) {
+                    *tokens.get_mut(farm).context(error::InfallableSnafu)? -= 1;
+                }
                 self.graph.index(n).set_running();
+                if let Some(estimate) = estimate.as_ref() {
+                    estimate.started(n);
+                }
+                lookahead.dispatched(&self.graph, subgraph, n)?;
                 // `try_send` is infallible here: channel capacity is
                 // `batch_size` and `inflight < batch_size` guarantees space.
                 work_tx.try_send(n).ok().context(error::InfallableSnafu)?;
@@ -505,6 +867,13 @@ impl Graph {
             // workers stay alive while there's anything to wait for.
             let (idx, res) = done_rx.recv().await.context(error::InfallableSnafu)?;
             inflight -= 1;
+            progress.advance(1);
+            if let Some(estimate) = estimate.as_ref() {
+                estimate.finished(idx);
+            }
+            if let Some(farm) = farms.get(&idx) {
+                *tokens.get_mut(farm).context(error::InfallableSnafu)? += 1;
+            }
             let node = self.graph.index(idx);
             match res {
                 Ok(_) => {
@@ -525,12 +894,16 @@ impl Graph {
                         }
                     }
                 }
+                // A transform stopped by cancellation did not fail, it is
+                // reported as skipped like the ones never started
+                Err(error::SchedulerError::Cancelled) => {}
                 Err(e) => {
                     // Fail-fast: latch the first error, mark the node
                     // failed, and clear `ready` so no further dispatch
                     // happens. We still need to drain `inflight` tasks
                     // so workers don't leak.
                     error!("{} failed: {e}", node.addr);
+                    node.set_error(&e);
                     node.set_failed();
                     if first_error.is_none() {
                         first_error = Some(e);
@@ -566,6 +939,144 @@ impl Graph {
     }
 }
 
+impl Graph {
+    /// Publishes an [`EventKind::Planned`] event for every node of
+    /// `subgraph`, dependencies first.
+    fn publish_plan(&self, subgraph: &HashSet<NodeIndex>, events: &crate::context::EventBus) {
+        let mut nodes: Vec<NodeIndex> = subgraph.iter().copied().collect();
+        nodes.sort_by(|a, b| self.graph.index(*a).addr.cmp(&self.graph.index(*b).addr));
+        let mut published = HashSet::new();
+        while published.len() < nodes.len() {
+            for n in nodes.iter() {
+                if published.contains(n) {
+                    continue;
+                }
+                let parents: Vec<NodeIndex> = self
+                    .graph
+                    .parents(*n)
+                    .iter(&self.graph)
+                    .map(|(_, p)| p)
+                    .filter(|p| subgraph.contains(p))
+                    .collect();
+                if parents.iter().all(|p| published.contains(p)) {
+                    let mut depends: Vec<Addr> = parents
+                        .iter()
+                        .map(|p| self.graph.index(*p).addr.clone())
+                        .collect();
+                    depends.sort();
+                    events.publish(EventKind::Planned {
+                        addr: self.graph.index(*n).addr.clone(),
+                        depends,
+                    });
+                    published.insert(*n);
+                }
+            }
+        }
+    }
+
+    /// Describes every node reachable from `addr` for a run summary, in
+    /// address order. Returns nothing if `addr` was never added.
+    ///
+    /// `uploads` states whether a build cache was registered; see
+    /// [`Node::summary`].
+    pub fn summarize(&self, addr: &Addr, uploads: bool) -> Vec<NodeSummary> {
+        let Some(subgraph) = self.subgraphs.get(addr) else {
+            return Vec::new();
+        };
+        let mut nodes = subgraph
+            .iter()
+            .map(|n| self.graph.index(*n).summary(uploads))
+            .collect::<Vec<_>>();
+        nodes.sort_by(|a, b| a.addr.cmp(&b.addr));
+        nodes
+    }
+}
+
+/// Prepares `node` unless it already was, see [`Node::prepared`].
+async fn prepare(ctx: &Handle, node: &Node, transform: &Transform, id: &Id) -> Result<()> {
+    node.prepared
+        .get_or_try_init(|| async {
+            let logf = ctx.log().create(format!("{id}").as_str()).await?;
+            logf.set_subject("fetch");
+            transform.prepare(&logf, ctx).await?;
+            info!("pulled sources and artifacts for {}", node.addr);
+            Ok::<(), error::SchedulerError>(())
+        })
+        .await?;
+    Ok(())
+}
+
+/// Fetches the nodes of a [`Graph::run`] ahead of their dispatch.
+///
+/// A node starts preparing once every dependency it waits on has been
+/// handed to a worker (or promoted as a cache hit), so its downloads
+/// overlap with those builds instead of delaying the first one. Fetches are
+/// throttled to `batch_size` like [`Graph::fetch`], and the ones still
+/// running are aborted when the run ends.
+struct Lookahead {
+    ctx: Handle,
+    permits: Arc<Semaphore>,
+    /// Per node of the subgraph, dependencies not dispatched yet.
+    waiting: HashMap<NodeIndex, u32>,
+    /// Nodes a fetch was started for.
+    started: HashSet<NodeIndex>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Lookahead {
+    /// Starts preparing node `n` in the background, once.
+    fn start(&mut self, graph: &Dag<Arc<Node>, String>, n: NodeIndex) {
+        let node = graph.index(n).clone();
+        if node.is_cache_hit() || !self.started.insert(n) {
+            return;
+        }
+        // Without a transform or an id the worker reports the problem
+        let (Some(transform), Some(id)) = (self.ctx.get(&node.addr), node.id().cloned()) else {
+            return;
+        };
+        let ctx = self.ctx.clone();
+        let permits = self.permits.clone();
+        self.tasks.push(tokio::spawn(async move {
+            let Ok(_permit) = permits.acquire_owned().await else {
+                return;
+            };
+            // The worker running the node tries again and reports the error
+            if let Err(e) = prepare(&ctx, &node, &transform, &id).await {
+                warn!("fetching ahead for {} failed: {e}", node.addr);
+            }
+        }));
+    }
+
+    /// Records that `n` left the ready queue, starting the fetches of the
+    /// children in `subgraph` that no longer wait on anything undispatched.
+    fn dispatched(
+        &mut self,
+        graph: &Dag<Arc<Node>, String>,
+        subgraph: &HashSet<NodeIndex>,
+        n: NodeIndex,
+    ) -> Result<()> {
+        for (_, c) in graph.children(n).iter(graph) {
+            if !subgraph.contains(&c) {
+                continue;
+            }
+            let d = self.waiting.get_mut(&c).context(error::SubgraphSnafu)?;
+            *d = d.saturating_sub(1);
+            if *d == 0 {
+                self.start(graph, c);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Lookahead {
+    fn drop(&mut self) {
+        for task in self.tasks.iter() {
+            task.abort();
+        }
+    }
+}
+
 /// Runs the full per-transform lifecycle for one node.
 ///
 /// The lifecycle has five user-visible stages, each guarded by a
@@ -573,35 +1084,51 @@ impl Graph {
 ///
 /// 1. **create-environment** — ask the [`Handle`] to materialize an
 ///    [`Environment`] from the transform's farm address. The temp dir
-///    backing it lives under `workspace` and is dropped at function exit.
+///    backing it lives under `workspace` and is dropped at function exit,
+///    unless the workspace asks to keep it.
 /// 2. **setup-environment** — populate the environment with anything the
 ///    farm needs (e.g. base layers from storage).
 /// 3. **spinup environment** — start the environment (e.g. boot a
 ///    container). After this point, `down` and `clean` are best-effort
 ///    invoked unconditionally so we never leak a running environment.
-/// 4. **staging + execution** — ask the transform to stage its inputs and
-///    then run via [`execute::execute`](super::execute::execute), which
+/// 4. **staging + execution** — ask the transform to stage its inputs, or
+///    restore the snapshot of an identical earlier staging when snapshots
+///    are enabled, and then run via [`execute::execute`](super::execute::execute), which
 ///    handles interactive retry/quit prompts on failure.
 /// 5. **spindown + clean** — best-effort teardown. Errors here are
-///    swallowed so a clean-up failure doesn't mask the real outcome.
+///    swallowed so a clean-up failure doesn't mask the real outcome. A
+///    workspace that is kept skips `clean` and is moved to its debug
+///    directory instead, which is recorded on the node.
 ///
 /// The function returns the staging+execution outcome — environment
-/// teardown errors are intentionally not propagated.
+/// teardown errors are intentionally not propagated. The artifact is only
+/// uploaded to the build cache when `upload` is set and neither the farm nor
+/// the transform exposes host mounts or devices.
 async fn run_transform_lifecycle(
     ctx: &Handle,
-    workspace: &Path,
+    workspace: &Workspace,
     node: &Arc<Node>,
     transform: &Transform,
     id: &Id,
     token: &CancellationToken,
+    upload: bool,
 ) -> Result<Artifact> {
     // Per-transform scratch directory; dropped (and removed) when this
-    // function returns regardless of success/failure path.
-    let temp = TempDir::new_in(workspace).context(error::TemporaryDirectorySnafu)?;
+    // function returns unless the workspace keeps it for debugging.
+    let temp = TempDir::new_in(&workspace.path).context(error::TemporaryDirectorySnafu)?;
     let logf = ctx.log().create(format!("{id}").as_str()).await?;
+    node.set_log(&logf.path());
+    ctx.log().events().publish(EventKind::Started {
+        addr: node.addr.clone(),
+        log: logf.log_name(),
+        estimate_ms: node.estimate.get().map(|x| x.as_millis() as u64),
+    });
 
     logf.set_subject("create-environment");
-    let env_addr = transform.environment().await?;
+    let env_addr = match node.farm.get() {
+        Some(farm) => farm.clone(),
+        None => transform.environment().await?,
+    };
     let environment = ctx
         .create_environment(&logf, &env_addr, temp.path())
         .instrument(info_span!(
@@ -610,6 +1137,36 @@ async fn run_transform_lifecycle(
         ))
         .await?;
 
+    // Host mounts and devices make the build depend on more than its
+    // inputs, so such artifacts never reach the shared build cache
+    let access = transform.host_access();
+    if !access.is_empty() {
+        environment.expose(&access)?;
+    }
+    let farm_access = ctx
+        .get_farm(&env_addr)
+        .map(|x| x.host_access())
+        .unwrap_or_default();
+    // Nested edo invocations read the cache they are built from, which does
+    // not make the build depend on the host
+    if transform.nested() {
+        let cache = ctx.local_cache().to_path_buf();
+        environment.expose(&HostAccess {
+            mounts: vec![HostMount {
+                source: cache.clone(),
+                target: cache.clone(),
+                readonly: true,
+            }],
+            devices: Vec::new(),
+        })?;
+        environment
+            .set_env(NESTED_CACHE_ENV, &cache.to_string_lossy())
+            .await?;
+    }
+    let uses_host = !access.is_empty() || !farm_access.is_empty();
+    node.set_host_access(uses_host);
+    let upload = upload && !uses_host;
+
     if token.is_cancelled() {
         return error::CancelledSnafu.fail();
     }
@@ -634,38 +1191,91 @@ async fn run_transform_lifecycle(
     // Compute the outcome inside an inner async block so the `down` /
     // `clean` calls below run on every exit path — including the
     // cancellation early-returns inside the block.
-    let outcome: Result<Artifact> = async {
+    let outcome = async {
         if token.is_cancelled() {
             return error::CancelledSnafu.fail();
         }
         logf.set_subject("staging");
-        transform
-            .stage(&logf, ctx, &environment)
-            .instrument(info_span!(
-                "staging into environment",
-                addr = node.addr.to_string()
-            ))
-            .await?;
+        let snapshot = match workspace.snapshots.as_ref() {
+            Some(snapshots) => Snapshots::stage_key(ctx, transform, &env_addr)
+                .await?
+                .map(|key| (snapshots, key)),
+            None => None,
+        };
+        let restored = match snapshot.as_ref() {
+            Some((snapshots, key)) => {
+                snapshots
+                    .restore(&logf, &environment, &node.addr, key)
+                    .await?
+            }
+            None => false,
+        };
+        if restored {
+            info!(
+                "restored the staged environment of {} from a snapshot",
+                node.addr
+            );
+        } else {
+            transform
+                .stage(&logf, ctx, &environment)
+                .instrument(info_span!(
+                    "staging into environment",
+                    addr = node.addr.to_string()
+                ))
+                .await?;
+            if let Some((snapshots, key)) = snapshot.as_ref() {
+                snapshots.save(&logf, &environment, &node.addr, key).await;
+            }
+        }
 
         if token.is_cancelled() {
             return error::CancelledSnafu.fail();
         }
         logf.set_subject("execution");
-        super::execute::execute(&logf, ctx, transform, &environment).await
-    }
-    .await;
+        super::execute::execute(&logf, ctx, transform, &environment, upload).await
+    };
+    // Cancellation stops whatever the transform awaits, commands it runs
+    // are terminated by whoever cancelled, see `Interrupt`
+    let outcome: Result<Artifact> = tokio::select! {
+        outcome = outcome => outcome,
+        _ = token.cancelled() => error::CancelledSnafu.fail(),
+    };
 
     // Best-effort teardown: errors are logged-and-swallowed so a clean-up
     // failure never overrides a successful build (or vice versa).
     logf.set_subject("spindown environment");
     let _ = environment.down(&logf).await;
-    logf.set_subject("clean environment");
-    let _ = environment
-        .clean(&logf)
-        .instrument(info_span!("cleaning up", addr = node.addr.to_string()))
-        .await;
+    if workspace.keep.keeps(outcome.is_err()) {
+        match workspace.retain(&node.addr, temp) {
+            Ok(path) => {
+                warn!("workspace of {} kept at {}", node.addr, path.display());
+                node.set_workspace(&path);
+            }
+            Err(e) => warn!("failed to keep workspace of {}: {e}", node.addr),
+        }
+    } else {
+        logf.set_subject("clean environment");
+        let _ = environment
+            .clean(&logf)
+            .instrument(info_span!("cleaning up", addr = node.addr.to_string()))
+            .await;
+    }
 
+    // Keep the end of the output around for the run summary of a failure
+    if outcome.is_err() {
+        node.set_tail(logf.tail());
+    }
+    if let Err(error::SchedulerError::Command { failure }) = &outcome {
+        node.set_failure(failure);
+    }
+    let log = logf.path();
     drop(logf);
+    // The log is complete once closed. Losing it never fails the build.
+    if let (Ok(_), true, Some(cache)) = (&outcome, upload, workspace.logs.as_ref())
+        && let Err(e) = ctx.storage().upload_log(id, &log, cache).await
+    {
+        warn!("failed to upload the log of {}: {e}", node.addr);
+    }
     match outcome {
         Ok(artifact) => {
             info!("transformation complete");
@@ -720,7 +1330,10 @@ pub(crate) mod tests {
 
     use super::*;
     use crate::context::{Addr, Context, Handle, LogVerbosity};
-    use crate::environment::{Command, EnvResult, Environment, EnvironmentImpl, Farm, FarmImpl};
+    use crate::environment::{
+        Command, EnvResult, Environment, EnvironmentImpl, Farm, FarmImpl, HostInfo,
+        HostRequirements,
+    };
     use crate::storage::{Artifact as StorageArtifact, Config as ArtifactConfig, Id, MediaType};
     use crate::transform::{Transform, TransformImpl, TransformResult, TransformStatus};
     use crate::util::{Reader, Writer};
@@ -868,6 +1481,50 @@ pub(crate) mod tests {
         Farm::new(MockFarmImpl)
     }
 
+    /// A mock farm executing `platforms`.
+    struct PlatformFarmImpl(Vec<String>);
+
+    #[async_trait]
+    impl FarmImpl for PlatformFarmImpl {
+        async fn setup(
+            &self,
+            _log: &crate::context::Log,
+            _storage: &crate::storage::Storage,
+        ) -> EnvResult<()> {
+            Ok(())
+        }
+        async fn create(&self, _log: &crate::context::Log, _path: &Path) -> EnvResult<Environment> {
+            Ok(Environment::new(MockEnvironmentImpl))
+        }
+        fn platforms(&self) -> Vec<String> {
+            self.0.clone()
+        }
+    }
+
+    /// A mock farm whose environments run on a host with all the memory
+    /// there is.
+    struct LargeHostFarmImpl;
+
+    #[async_trait]
+    impl FarmImpl for LargeHostFarmImpl {
+        async fn setup(
+            &self,
+            _log: &crate::context::Log,
+            _storage: &crate::storage::Storage,
+        ) -> EnvResult<()> {
+            Ok(())
+        }
+        async fn create(&self, _log: &crate::context::Log, _path: &Path) -> EnvResult<Environment> {
+            Ok(Environment::new(MockEnvironmentImpl))
+        }
+        fn host(&self) -> HostInfo {
+            HostInfo {
+                memory: Some(u64::MAX),
+                ..HostInfo::local()
+            }
+        }
+    }
+
     // ── mock Transform ───────────────────────────────────────────────────────
 
     /// Outcome the mock's `transform` method should return.
@@ -894,6 +1551,7 @@ pub(crate) mod tests {
         pub deps: Vec<Addr>,
         pub env_addr: Addr,
         pub digest: String,
+        pub id_called: Arc<AtomicUsize>,
         pub prepare_called: Arc<AtomicUsize>,
         pub stage_called: Arc<AtomicUsize>,
         pub transform_called: Arc<AtomicUsize>,
@@ -906,6 +1564,10 @@ pub(crate) mod tests {
         /// can reliably observe ordering bugs that real-world long-running
         /// transforms would expose.
         pub delay: Option<std::time::Duration>,
+        /// Architecture the mock asks to execute on.
+        pub platform: Option<String>,
+        /// What the mock needs of its host.
+        pub requires: HostRequirements,
     }
 
     impl Default for MockTransformImpl {
@@ -915,6 +1577,7 @@ pub(crate) mod tests {
                 deps: Vec::new(),
                 env_addr: Addr::parse("//default").unwrap(),
                 digest: "0000".into(),
+                id_called: Arc::new(AtomicUsize::new(0)),
                 prepare_called: Arc::new(AtomicUsize::new(0)),
                 stage_called: Arc::new(AtomicUsize::new(0)),
                 transform_called: Arc::new(AtomicUsize::new(0)),
@@ -923,6 +1586,8 @@ pub(crate) mod tests {
                 order_log: Arc::new(TokioMutex::new(Vec::new())),
                 outcome: MockOutcome::Success,
                 delay: None,
+                platform: None,
+                requires: HostRequirements::default(),
             }
         }
     }
@@ -945,6 +1610,7 @@ pub(crate) mod tests {
         }
 
         async fn get_unique_id(&self, _ctx: &Handle) -> TransformResult<Id> {
+            self.id_called.fetch_add(1, AtomicOrdering::SeqCst);
             Ok(Id::builder()
                 .name(self.addr.to_string())
                 .digest(self.digest.clone())
@@ -963,10 +1629,17 @@ pub(crate) mod tests {
         async fn stage(
             &self,
             _log: &crate::context::Log,
-            _ctx: &Handle,
+            ctx: &Handle,
             _env: &Environment,
         ) -> TransformResult<()> {
             self.stage_called.fetch_add(1, AtomicOrdering::SeqCst);
+            // Staging looks up the artifacts of dependencies by id, as real
+            // transforms do
+            for dep in self.deps.iter() {
+                if let Some(t) = ctx.get(dep) {
+                    ctx.unique_id(dep, &t).await?;
+                }
+            }
             if matches!(self.outcome, MockOutcome::FailInStage) {
                 return Err(crate::transform::TransformError::Implementation {
                     source: Box::new(std::io::Error::other("mock stage failure")),
@@ -1012,6 +1685,14 @@ pub(crate) mod tests {
         fn shell(&self, _env: &Environment) -> TransformResult<()> {
             Ok(())
         }
+
+        fn platform(&self) -> Option<String> {
+            self.platform.clone()
+        }
+
+        fn host_requires(&self) -> HostRequirements {
+            self.requires.clone()
+        }
     }
 
     /// Handle bundle returned to tests so they can observe counters after
@@ -1019,6 +1700,7 @@ pub(crate) mod tests {
     #[allow(dead_code)] // fields consumed by sibling test modules
     pub(crate) struct MockHandles {
         pub addr: Addr,
+        pub id_called: Arc<AtomicUsize>,
         pub prepare_called: Arc<AtomicUsize>,
         pub stage_called: Arc<AtomicUsize>,
         pub transform_called: Arc<AtomicUsize>,
@@ -1046,6 +1728,7 @@ pub(crate) mod tests {
             .collect();
         let env_addr = Addr::parse("//default").unwrap();
         let digest = format!("{:064x}", fxhash(addr_str));
+        let id_called = Arc::new(AtomicUsize::new(0));
         let prepare_called = Arc::new(AtomicUsize::new(0));
         let stage_called = Arc::new(AtomicUsize::new(0));
         let transform_called = Arc::new(AtomicUsize::new(0));
@@ -1056,6 +1739,7 @@ pub(crate) mod tests {
             deps: deps_vec,
             env_addr: env_addr.clone(),
             digest,
+            id_called: id_called.clone(),
             prepare_called: prepare_called.clone(),
             stage_called: stage_called.clone(),
             transform_called: transform_called.clone(),
@@ -1064,11 +1748,14 @@ pub(crate) mod tests {
             order_log: shared_order.clone(),
             outcome: MockOutcome::Success,
             delay: None,
+            platform: None,
+            requires: HostRequirements::default(),
         };
         let t = Transform::new(mock);
         ctx.insert_transform_for_test(&addr, t);
         MockHandles {
             addr,
+            id_called,
             prepare_called,
             stage_called,
             transform_called,
@@ -1100,6 +1787,7 @@ pub(crate) mod tests {
             .collect();
         let env_addr = Addr::parse("//default").unwrap();
         let digest = format!("{:064x}", fxhash(addr_str));
+        let id_called = Arc::new(AtomicUsize::new(0));
         let prepare_called = Arc::new(AtomicUsize::new(0));
         let stage_called = Arc::new(AtomicUsize::new(0));
         let transform_called = Arc::new(AtomicUsize::new(0));
@@ -1110,6 +1798,7 @@ pub(crate) mod tests {
             deps: deps_vec,
             env_addr: env_addr.clone(),
             digest,
+            id_called: id_called.clone(),
             prepare_called: prepare_called.clone(),
             stage_called: stage_called.clone(),
             transform_called: transform_called.clone(),
@@ -1118,11 +1807,14 @@ pub(crate) mod tests {
             order_log: shared_order.clone(),
             outcome,
             delay,
+            platform: None,
+            requires: HostRequirements::default(),
         };
         let t = Transform::new(mock);
         ctx.insert_transform_for_test(&addr, t);
         MockHandles {
             addr,
+            id_called,
             prepare_called,
             stage_called,
             transform_called,
@@ -1271,6 +1963,198 @@ pub(crate) mod tests {
         assert_eq!(h_c.prepare_called.load(AtomicOrdering::SeqCst), 1);
     }
 
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn graph_run_prepares_probed_nodes_once() {
+        let ctx = ctx_or_skip!();
+        ensure_default_farm(&ctx);
+        let order = Arc::new(TokioMutex::new(Vec::new()));
+        let mi = Arc::new(AtomicUsize::new(0));
+        let h_c = register_mock(&ctx, "//gjit/c", &[], order.clone(), mi.clone());
+        let h_b = register_mock(&ctx, "//gjit/b", &["//gjit/c"], order.clone(), mi.clone());
+        let h_a = register_mock(&ctx, "//gjit/a", &["//gjit/b"], order, mi);
+
+        let mut g = Graph::new(4);
+        let root = Addr::parse("//gjit/a").unwrap();
+        g.add(&ctx, &root).await.unwrap();
+        g.probe(&ctx).await.unwrap();
+        assert_eq!(h_a.prepare_called.load(AtomicOrdering::SeqCst), 0);
+        let ws = TempDir::new().unwrap();
+        g.run(ws.path(), &ctx, &root).await.expect("run");
+
+        // Look-ahead fetches and workers share one preparation per node
+        assert_eq!(h_a.prepare_called.load(AtomicOrdering::SeqCst), 1);
+        assert_eq!(h_b.prepare_called.load(AtomicOrdering::SeqCst), 1);
+        assert_eq!(h_c.prepare_called.load(AtomicOrdering::SeqCst), 1);
+        assert_eq!(h_a.transform_called.load(AtomicOrdering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn graph_run_hashes_each_node_once() {
+        let ctx = ctx_or_skip!();
+        ensure_default_farm(&ctx);
+        let order = Arc::new(TokioMutex::new(Vec::new()));
+        let mi = Arc::new(AtomicUsize::new(0));
+        let h_c = register_mock(&ctx, "//gid/c", &[], order.clone(), mi.clone());
+        let h_b = register_mock(&ctx, "//gid/b", &["//gid/c"], order.clone(), mi.clone());
+        let h_a = register_mock(&ctx, "//gid/a", &["//gid/b"], order, mi);
+
+        let mut g = Graph::new(4);
+        let root = Addr::parse("//gid/a").unwrap();
+        g.add(&ctx, &root).await.unwrap();
+        g.probe(&ctx).await.unwrap();
+        let ws = TempDir::new().unwrap();
+        g.run(ws.path(), &ctx, &root).await.expect("run");
+
+        // Staging asks for the ids of dependencies probing already computed
+        assert_eq!(h_b.stage_called.load(AtomicOrdering::SeqCst), 1);
+        for h in [&h_a, &h_b, &h_c] {
+            assert_eq!(h.id_called.load(AtomicOrdering::SeqCst), 1, "{}", h.addr);
+        }
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn graph_run_does_not_prepare_nodes_past_a_failure() {
+        let ctx = ctx_or_skip!();
+        ensure_default_farm(&ctx);
+        let order = Arc::new(TokioMutex::new(Vec::new()));
+        let mi = Arc::new(AtomicUsize::new(0));
+        let h_leaf = register_mock_with(
+            &ctx,
+            "//gjf/leaf",
+            &[],
+            order.clone(),
+            mi.clone(),
+            MockOutcome::FailInStage,
+            None,
+        );
+        register_mock(
+            &ctx,
+            "//gjf/mid",
+            &["//gjf/leaf"],
+            order.clone(),
+            mi.clone(),
+        );
+        let h_root = register_mock(&ctx, "//gjf/root", &["//gjf/mid"], order, mi);
+
+        let mut g = Graph::new(2);
+        let root = Addr::parse("//gjf/root").unwrap();
+        g.add(&ctx, &root).await.unwrap();
+        g.probe(&ctx).await.unwrap();
+        let ws = TempDir::new().unwrap();
+        let _ = g.run(ws.path(), &ctx, &root).await;
+
+        // `mid` may have been fetched ahead of the leaf's failure, but
+        // `root` waited on `mid`, which was never dispatched
+        assert_eq!(h_leaf.prepare_called.load(AtomicOrdering::SeqCst), 1);
+        assert_eq!(h_root.prepare_called.load(AtomicOrdering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn graph_probe_rejects_platforms_no_farm_executes() {
+        let ctx = ctx_or_skip!();
+        ensure_default_farm(&ctx);
+        let addr = Addr::parse("//gplat/missing").unwrap();
+        let mock = MockTransformImpl {
+            addr: addr.clone(),
+            digest: format!("{:064x}", fxhash("//gplat/missing")),
+            platform: Some("edo-missing-arch".to_string()),
+            ..Default::default()
+        };
+        ctx.insert_transform_for_test(&addr, Transform::new(mock));
+
+        let mut g = Graph::new(2);
+        g.add(&ctx, &addr).await.unwrap();
+        let err = g.probe(&ctx).await.unwrap_err();
+        assert!(
+            matches!(err, error::SchedulerError::Platform { .. }),
+            "expected Platform error, got {err:?}"
+        );
+        assert!(err.to_string().contains("cannot execute edo-missing-arch"));
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn graph_runs_transforms_in_a_farm_executing_their_platform() {
+        let ctx = ctx_or_skip!();
+        ensure_default_farm(&ctx);
+        let emulated = Addr::parse("//gplat/emulated").unwrap();
+        ctx.insert_farm_for_test(
+            &emulated,
+            Farm::new(PlatformFarmImpl(vec![
+                std::env::consts::ARCH.to_string(),
+                "edo-emulated-arch".to_string(),
+            ])),
+        );
+        let addr = Addr::parse("//gplat/cross").unwrap();
+        let transform_called = Arc::new(AtomicUsize::new(0));
+        let mock = MockTransformImpl {
+            addr: addr.clone(),
+            digest: format!("{:064x}", fxhash("//gplat/cross")),
+            transform_called: transform_called.clone(),
+            platform: Some("edo-emulated-arch".to_string()),
+            ..Default::default()
+        };
+        ctx.insert_transform_for_test(&addr, Transform::new(mock));
+
+        let mut g = Graph::new(2);
+        let idx = g.add(&ctx, &addr).await.unwrap();
+        g.probe(&ctx).await.unwrap();
+        assert_eq!(g.graph.index(idx).farm.get(), Some(&emulated));
+        let ws = TempDir::new().unwrap();
+        g.run(ws.path(), &ctx, &addr).await.expect("run");
+        assert_eq!(transform_called.load(AtomicOrdering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn graph_probe_routes_transforms_to_a_farm_meeting_their_host_requirements() {
+        let ctx = ctx_or_skip!();
+        ensure_default_farm(&ctx);
+        let large = Addr::parse("//ghost/large").unwrap();
+        ctx.insert_farm_for_test(&large, Farm::new(LargeHostFarmImpl));
+        let requires = HostRequirements {
+            min_memory: Some(1 << 62),
+            ..Default::default()
+        };
+        let addr = Addr::parse("//ghost/heavy").unwrap();
+        let mock = MockTransformImpl {
+            addr: addr.clone(),
+            digest: format!("{:064x}", fxhash("//ghost/heavy")),
+            requires: requires.clone(),
+            ..Default::default()
+        };
+        ctx.insert_transform_for_test(&addr, Transform::new(mock));
+        let mut g = Graph::new(2);
+        let idx = g.add(&ctx, &addr).await.unwrap();
+        g.probe(&ctx).await.unwrap();
+        assert_eq!(g.graph.index(idx).farm.get(), Some(&large));
+
+        // Without routing the shortfall of the requested farm is reported
+        let addr = Addr::parse("//ghost/pinned").unwrap();
+        let mock = MockTransformImpl {
+            addr: addr.clone(),
+            digest: format!("{:064x}", fxhash("//ghost/pinned")),
+            requires: HostRequirements {
+                route: false,
+                ..requires
+            },
+            ..Default::default()
+        };
+        ctx.insert_transform_for_test(&addr, Transform::new(mock));
+        let mut g = Graph::new(2);
+        g.add(&ctx, &addr).await.unwrap();
+        let err = g.probe(&ctx).await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("//default does not meet host_requires: needs 4194304.0 TiB of memory"),
+            "{err}"
+        );
+    }
+
     #[tokio::test]
     #[serial_test::serial(log_manager)]
     async fn graph_run_linear_chain_in_topological_order() {
@@ -1309,6 +2193,64 @@ pub(crate) mod tests {
         );
     }
 
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn graph_run_publishes_events() {
+        let ctx = ctx_or_skip!();
+        ensure_default_farm(&ctx);
+        let order = Arc::new(TokioMutex::new(Vec::new()));
+        let mi = Arc::new(AtomicUsize::new(0));
+        register_mock(&ctx, "//gev/b", &[], order.clone(), mi.clone());
+        register_mock(&ctx, "//gev/a", &["//gev/b"], order.clone(), mi);
+
+        let mut g = Graph::new(2);
+        let root = Addr::parse("//gev/a").unwrap();
+        g.add(&ctx, &root).await.unwrap();
+        g.fetch(&ctx).await.unwrap();
+        let ws = TempDir::new().unwrap();
+        let mut events = ctx.events().subscribe();
+        g.run(ws.path(), &ctx, &root).await.expect("run");
+
+        let mut kinds = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if !matches!(event.kind, EventKind::Output { .. }) {
+                kinds.push(event.kind);
+            }
+        }
+        let a = Addr::parse("//gev/a").unwrap();
+        let b = Addr::parse("//gev/b").unwrap();
+        assert_eq!(
+            kinds[..2],
+            [
+                EventKind::Planned {
+                    addr: b.clone(),
+                    depends: Vec::new()
+                },
+                EventKind::Planned {
+                    addr: a.clone(),
+                    depends: vec![b.clone()]
+                },
+            ]
+        );
+        let finished: Vec<&Addr> = kinds
+            .iter()
+            .filter_map(|x| match x {
+                EventKind::Finished {
+                    addr,
+                    outcome: NodeOutcome::Built,
+                    ..
+                } => Some(addr),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(finished, vec![&b, &a]);
+        assert!(
+            kinds
+                .iter()
+                .any(|x| matches!(x, EventKind::Started { addr, .. } if addr == &a))
+        );
+    }
+
     #[tokio::test]
     #[serial_test::serial(log_manager)]
     async fn graph_run_diamond_dependency_respected() {
@@ -1385,6 +2327,46 @@ pub(crate) mod tests {
         );
     }
 
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn graph_run_farm_limit_caps_concurrency() {
+        // The worker pool could run all three leaves at once, but their
+        // farm only hands out one token. The leaves share an in-flight
+        // counter so overlap between them is observable.
+        let ctx = ctx_or_skip!();
+        ensure_default_farm(&ctx);
+        let order = Arc::new(TokioMutex::new(Vec::new()));
+        let mi = Arc::new(AtomicUsize::new(0));
+        let inflight = Arc::new(AtomicUsize::new(0));
+        let leaves = ["//gf/l1", "//gf/l2", "//gf/l3"];
+        for leaf in leaves {
+            let addr = Addr::parse(leaf).unwrap();
+            let mock = MockTransformImpl {
+                addr: addr.clone(),
+                digest: format!("{:064x}", fxhash(leaf)),
+                inflight: inflight.clone(),
+                max_inflight: mi.clone(),
+                order_log: order.clone(),
+                delay: Some(std::time::Duration::from_millis(20)),
+                ..Default::default()
+            };
+            ctx.insert_transform_for_test(&addr, Transform::new(mock));
+        }
+        register_mock(&ctx, "//gf/root", &leaves, order.clone(), mi.clone());
+
+        let mut g = Graph::new(8);
+        g.set_farm_limits(HashMap::from([(Addr::parse("//default").unwrap(), 1)]));
+        let root = Addr::parse("//gf/root").unwrap();
+        g.add(&ctx, &root).await.unwrap();
+        g.fetch(&ctx).await.unwrap();
+        let ws = TempDir::new().unwrap();
+        let g = Arc::new(g);
+        g.run(ws.path(), &ctx, &root).await.expect("run");
+
+        assert_eq!(order.lock().await.len(), 4);
+        assert_eq!(mi.load(AtomicOrdering::SeqCst), 1);
+    }
+
     /// Regression test for the race between the parent task's inflight
     /// decrement and its child-enqueue walk.
     ///
@@ -1467,6 +2449,87 @@ pub(crate) mod tests {
         );
     }
 
+    /// Cancelling the run's token, as Ctrl-C does, stops the transform in
+    /// flight without waiting for it and skips the rest.
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn graph_run_stops_on_cancellation() {
+        let ctx = ctx_or_skip!();
+        ensure_default_farm(&ctx);
+        let order = Arc::new(TokioMutex::new(Vec::new()));
+        let mi = Arc::new(AtomicUsize::new(0));
+        register_mock_with(
+            &ctx,
+            "//cancel/leaf",
+            &[],
+            order.clone(),
+            mi.clone(),
+            MockOutcome::Success,
+            Some(std::time::Duration::from_secs(30)),
+        );
+        let h_root = register_mock_with(
+            &ctx,
+            "//cancel/root",
+            &["//cancel/leaf"],
+            order.clone(),
+            mi.clone(),
+            MockOutcome::Success,
+            None,
+        );
+
+        let token = CancellationToken::new();
+        let mut g = Graph::new(2);
+        g.set_cancellation(&token);
+        let root = Addr::parse("//cancel/root").unwrap();
+        g.add(&ctx, &root).await.unwrap();
+        g.fetch(&ctx).await.unwrap();
+        let ws = TempDir::new().unwrap();
+        let cancel = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            cancel.cancel();
+        });
+        let started = std::time::Instant::now();
+        let result = g.run(ws.path(), &ctx, &root).await;
+        assert!(matches!(result, Err(error::SchedulerError::Cancelled)));
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+        assert_eq!(h_root.transform_called.load(AtomicOrdering::SeqCst), 0);
+        let outcomes: Vec<_> = g
+            .summarize(&root, false)
+            .into_iter()
+            .map(|n| n.outcome)
+            .collect();
+        assert_eq!(outcomes, vec![NodeOutcome::Skipped, NodeOutcome::Skipped]);
+    }
+
+    /// With too little disk space, and nothing running that could free
+    /// some, the run fails before starting a transform.
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn graph_run_fails_on_low_disk_space() {
+        let ctx = ctx_or_skip!();
+        ensure_default_farm(&ctx);
+        let order = Arc::new(TokioMutex::new(Vec::new()));
+        let mi = Arc::new(AtomicUsize::new(0));
+        let h = register_mock(&ctx, "//disk/leaf", &[], order, mi);
+
+        let ws = TempDir::new().unwrap();
+        let mut g = Graph::new(2);
+        g.set_disk_guard(&Arc::new(DiskGuard::new(
+            vec![ws.path().to_path_buf()],
+            u64::MAX,
+        )));
+        let addr = Addr::parse("//disk/leaf").unwrap();
+        g.add(&ctx, &addr).await.unwrap();
+        g.fetch(&ctx).await.unwrap();
+        let result = g.run(ws.path(), &ctx, &addr).await;
+        assert!(matches!(
+            result,
+            Err(error::SchedulerError::DiskSpace { .. })
+        ));
+        assert_eq!(h.transform_called.load(AtomicOrdering::SeqCst), 0);
+    }
+
     /// Regression test for the parent-status check in the child-enqueue
     /// walk. With the previous predicate `is_queued() || is_pending()`, a
     /// parent in `Running` state was treated as "done enough" and the child
@@ -1657,6 +2720,44 @@ pub(crate) mod tests {
         );
     }
 
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn graph_run_keeps_failed_workspace() {
+        let ctx = ctx_or_skip!();
+        ensure_default_farm(&ctx);
+        let order = Arc::new(TokioMutex::new(Vec::new()));
+        let mi = Arc::new(AtomicUsize::new(0));
+        register_mock_with(
+            &ctx,
+            "//rkw/leaf",
+            &[],
+            order.clone(),
+            mi.clone(),
+            MockOutcome::FailInStage,
+            None,
+        );
+
+        let mut g = Graph::new(1);
+        let leaf = Addr::parse("//rkw/leaf").unwrap();
+        g.add(&ctx, &leaf).await.unwrap();
+        g.fetch(&ctx).await.unwrap();
+        let ws = TempDir::new().unwrap();
+        let debug = ws.path().join("debug");
+        g.set_keep_workspace(KeepWorkspace::Failed, &debug);
+        let _ = g.run(ws.path(), &ctx, &leaf).await;
+
+        let nodes = g.summarize(&leaf, false);
+        let kept = nodes[0].workspace.as_ref().expect("workspace kept");
+        assert!(kept.is_dir(), "{}", kept.display());
+        assert_eq!(kept.parent(), Some(debug.as_path()));
+        assert!(
+            kept.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("rkw-leaf-")
+        );
+    }
+
     /// Regression test for the bottlerocket-buildstream ordering inversion:
     /// a wide compose root with many leaf packages plus a handful of
     /// mid-tier packages that share a deep-leaf dependency (`glibc`).
@@ -0,0 +1,68 @@
+//! Ctrl-C handling for a run.
+//!
+//! The first interrupt cancels the run's token and terminates the commands
+//! transforms are running, so every worker winds down through the usual
+//! teardown: environments go down, workspaces are removed and the partial
+//! run summary is written. A second interrupt kills the commands and exits
+//! at once.
+
+use crate::util::{kill_commands, terminate_commands};
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+
+/// Exit code of a process stopped by SIGINT.
+const INTERRUPTED: i32 = 130;
+
+/// Watches for Ctrl-C until dropped.
+///
+/// The watch runs on a thread of its own, since workers blocked on build
+/// commands may hold every thread of the runtime.
+pub(crate) struct Interrupt {
+    done: Option<oneshot::Sender<()>>,
+}
+
+impl Interrupt {
+    /// Cancels `token` on the first Ctrl-C.
+    pub(crate) fn watch(token: &CancellationToken) -> Self {
+        let (done, mut finished) = oneshot::channel();
+        let token = token.clone();
+        let watch = std::thread::Builder::new()
+            .name("edo-interrupt".into())
+            .spawn(move || {
+                let Ok(runtime) = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                else {
+                    return;
+                };
+                runtime.block_on(async move {
+                    tokio::select! {
+                        signal = tokio::signal::ctrl_c() => if signal.is_err() { return },
+                        _ = &mut finished => return,
+                    }
+                    warn!("interrupted, stopping the running transforms (press Ctrl-C again to stop at once)");
+                    token.cancel();
+                    terminate_commands();
+                    tokio::select! {
+                        signal = tokio::signal::ctrl_c() => if signal.is_ok() {
+                            kill_commands();
+                            std::process::exit(INTERRUPTED);
+                        },
+                        _ = finished => {}
+                    }
+                });
+            });
+        if let Err(e) = watch {
+            warn!("failed to watch for interrupts: {e}");
+        }
+        Self { done: Some(done) }
+    }
+}
+
+impl Drop for Interrupt {
+    fn drop(&mut self) {
+        if let Some(done) = self.done.take() {
+            let _ = done.send(());
+        }
+    }
+}
@@ -13,26 +13,49 @@
 //!    `addr` and its transitive dependencies, constructing a DAG of
 //!    [`Node`](node::Node)s, performing transitive reduction, and
 //!    pre-computing per-root indegree templates.
-//! 2. **Fetch** — [`Graph::fetch`](graph::Graph::fetch) computes a
-//!    content-addressed [`Id`](crate::storage::Id) for every node,
-//!    queries the build cache, and downloads sources only for nodes that
-//!    aren't already built. This is bounded by a [`Semaphore`] sized to
-//!    `workers` to avoid hammering remote storage.
+//! 2. **Probe** — [`Graph::probe`](graph::Graph::probe) computes a
+//!    content-addressed [`Id`](crate::storage::Id) for every node and
+//!    queries the build cache.
 //! 3. **Run** — [`Graph::run`](graph::Graph::run) drives the actual
 //!    DAG execution: ready nodes are pushed into a worker pool, completed
 //!    nodes unblock their children, and cache hits are cascaded so that
-//!    fully-built subtrees never spin up an environment.
+//!    fully-built subtrees never spin up an environment. Sources are
+//!    fetched just in time: a node starts downloading once every
+//!    dependency it waits on is dispatched, so fetches overlap with the
+//!    builds ahead of them. [`Graph::fetch`](graph::Graph::fetch) instead
+//!    downloads everything up front, for `edo fetch`.
 //!
 //! ## Concurrency model
 //!
 //! Worker count comes from the `[scheduler] workers` TOML key (default
-//! `8`). The same number bounds:
+//! `8`), or `edo run --jobs` for a single invocation. The same number
+//! bounds:
 //!
 //! - the worker tasks in [`Graph::run`](graph::Graph::run),
-//! - the in-flight fetch permits in [`Graph::fetch`](graph::Graph::fetch),
+//! - the in-flight fetch permits in [`Graph::fetch`](graph::Graph::fetch)
+//!   and of the look-ahead fetches in [`Graph::run`](graph::Graph::run),
 //! - the work/done channel capacities (so dispatch never blocks while
 //!   the pool has free slots).
 //!
+//! The `[scheduler] farms` table additionally caps how many transforms run
+//! at once in an environment farm, keyed by farm address:
+//!
+//! ```toml
+//! [scheduler]
+//! workers = 16
+//!
+//! [scheduler.farms]
+//! "//project/vm" = 2
+//! ```
+//!
+//! `upload_logs` ships the log of every transform that built to a cache,
+//! next to its artifact, see [`log_cache`]:
+//!
+//! ```toml
+//! [scheduler]
+//! upload_logs = "build"   # or true, "output", or a cache address
+//! ```
+//!
 //! All shared state ([`Node`](node::Node), the [`Graph`](graph::Graph)
 //! itself) is `Arc`-wrapped and uses atomics rather than locks on the hot
 //! path.
@@ -45,28 +68,74 @@
 //! to enqueue new work once cancellation is observed. A first failure or
 //! a user-driven `quit` from [`execute::execute`] both flip the same
 //! switch.
+//!
+//! Ctrl-C cancels the token of the run too. Transforms in flight stop at
+//! once: their commands are sent `SIGTERM` (and `SIGKILL` after a grace
+//! period), pending storage transfers are dropped, and their environments
+//! still go through `down` and `clean`. A second Ctrl-C kills the commands
+//! and exits without any cleanup. Nodes stopped or never started are
+//! reported as skipped in the run summary.
 
 use super::context::Context;
-use crate::context::{Addr, Config};
+use crate::context::{Addr, Config, RunSummary};
+use crate::storage::CacheSelector;
+pub use disk::DEFAULT_MIN_FREE_SPACE;
+use disk::DiskGuard;
 use graph::Graph;
+use interrupt::Interrupt;
+use parking_lot::Mutex;
 use snafu::ResultExt;
+use snapshot::Snapshots;
 use std::{
+    collections::HashMap,
     path::{Path, PathBuf},
     sync::Arc,
 };
 use tokio::fs::create_dir_all;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, info_span};
 
+mod disk;
 /// Error types for the scheduler subsystem.
 pub mod error;
+mod estimate;
 /// Interactive transform executor with error recovery.
 pub mod execute;
 /// DAG-based execution graph for parallel transform orchestration.
 pub mod graph;
+mod interrupt;
 /// Node representation within the scheduler execution graph.
 pub mod node;
+mod snapshot;
 
 type Result<T> = std::result::Result<T, error::SchedulerError>;
 
+/// Which transform workspaces survive a run for debugging.
+///
+/// Kept workspaces are moved to `debug/<addr>-<timestamp>` beside the
+/// scheduler workspace, i.e. `.edo/debug`, instead of being removed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeepWorkspace {
+    /// Remove every workspace once its transform finishes.
+    #[default]
+    Never,
+    /// Keep the workspaces of failed transforms.
+    Failed,
+    /// Keep every workspace.
+    Always,
+}
+
+impl KeepWorkspace {
+    /// Returns `true` if the workspace of a transform that `failed` is kept.
+    pub fn keeps(&self, failed: bool) -> bool {
+        match self {
+            Self::Never => false,
+            Self::Failed => failed,
+            Self::Always => true,
+        }
+    }
+}
+
 /// Parallel task scheduler that builds a dependency graph and executes
 /// transforms concurrently.
 ///
@@ -108,20 +177,125 @@ impl Scheduler {
                 } else {
                     8
                 },
+                jobs: Mutex::new(None),
                 path: path.to_path_buf(),
+                keep: Mutex::new(KeepWorkspace::default()),
+                disks: Mutex::new(Vec::new()),
             }),
         })
     }
 }
 
+/// Reads the `[scheduler] farms` table, mapping environment farm addresses to
+/// the most transforms run in them at once.
+///
+/// Like a malformed `workers` key, limits that are not a positive integer are
+/// skipped rather than failing the build.
+fn farm_limits(config: &Config) -> HashMap<Addr, u64> {
+    let Some(farms) = config
+        .get("scheduler")
+        .and_then(|x| x.get("farms"))
+        .and_then(|x| x.as_table())
+    else {
+        return HashMap::new();
+    };
+    farms
+        .iter()
+        .filter_map(|(farm, limit)| {
+            let Some(limit) = limit.as_int().filter(|x| *x > 0) else {
+                warn!("ignoring scheduler.farms limit of {farm}, expected a positive integer");
+                return None;
+            };
+            Some((Addr::parse(farm).ok()?, limit as u64))
+        })
+        .collect()
+}
+
+/// Reads `[scheduler] upload_logs`, the cache the logs of transforms that
+/// built are uploaded to as [`log_id`](crate::storage::log_id) artifacts.
+///
+/// `true` selects the build cache, a string any cache `edo cache` accepts
+/// such as `"output"` or the address of a source cache. A malformed value
+/// is ignored with a warning, like the other scheduler settings.
+pub fn log_cache(config: &Config) -> Option<CacheSelector> {
+    let value = config.get("scheduler").and_then(|x| x.get("upload_logs"))?;
+    if let Some(enabled) = value.as_bool() {
+        return enabled.then_some(CacheSelector::Build);
+    }
+    match value.as_string().map(|x| x.parse::<CacheSelector>()) {
+        Some(Ok(cache)) => Some(cache),
+        _ => {
+            warn!(
+                "ignoring scheduler.upload_logs, expected a boolean or a cache such as \"build\""
+            );
+            None
+        }
+    }
+}
+
 impl Scheduler {
+    /// Sets which transform workspaces later runs keep for debugging.
+    pub fn set_keep_workspace(&self, keep: KeepWorkspace) {
+        *self.inner.keep.lock() = keep;
+    }
+
+    /// Watches the free space of `path` during runs, as is always done for
+    /// the workspace directory.
+    ///
+    /// Runs refuse to start transforms while a watched directory has less
+    /// than `[scheduler] min_free_space` left, see [`DEFAULT_MIN_FREE_SPACE`].
+    pub fn watch_disk(&self, path: &Path) {
+        self.inner.disks.lock().push(path.to_path_buf());
+    }
+
+    /// Overrides the configured worker count for later runs, or restores it
+    /// with `None`.
+    pub fn set_jobs(&self, jobs: Option<u64>) {
+        *self.inner.jobs.lock() = jobs;
+    }
+
     /// Builds the dependency graph for `addr` and executes every reachable
     /// transform.
     ///
     /// Convenience wrapper around [`Inner::run`]; see that method for the
     /// phase-by-phase walk-through.
     pub async fn run(&self, ctx: &Context, addr: &Addr) -> Result<()> {
-        self.inner.run(ctx, addr).await
+        let mut summary = RunSummary::start(std::slice::from_ref(addr));
+        self.inner.run(ctx, addr, false, &mut summary).await
+    }
+
+    /// Like [`Scheduler::run`], but appends the outcome of every node
+    /// reachable from `addr` to `summary`, whether or not the run succeeds,
+    /// and adds the time it was expected to take to the summary's estimate.
+    pub async fn run_recorded(
+        &self,
+        ctx: &Context,
+        addr: &Addr,
+        summary: &mut RunSummary,
+    ) -> Result<()> {
+        self.inner.run(ctx, addr, false, summary).await
+    }
+
+    /// Rebuilds `addr` and its dependencies from scratch.
+    ///
+    /// The build cache is neither consulted nor updated, so every reachable
+    /// transform executes again and overwrites its artifact in the local
+    /// cache only. Used to check that a build is reproducible.
+    pub async fn rebuild(&self, ctx: &Context, addr: &Addr) -> Result<()> {
+        let mut summary = RunSummary::start(std::slice::from_ref(addr));
+        self.inner.run(ctx, addr, true, &mut summary).await
+    }
+
+    /// Runs only the build and fetch phases for `addr`.
+    ///
+    /// Every source reachable from `addr` ends up in the local cache, as do
+    /// the artifacts of transforms found in the build cache, so a later run
+    /// does not need the network. Nothing is executed.
+    pub async fn fetch(&self, ctx: &Context, addr: &Addr) -> Result<()> {
+        let addr = &ctx.resolve_alias(addr);
+        let mut graph = Graph::new(self.inner.workers());
+        graph.add(ctx, addr).await?;
+        graph.fetch(ctx).await
     }
 }
 
@@ -133,9 +307,31 @@ struct Inner {
     /// Number of concurrent worker tasks. Also bounds fetch concurrency
     /// and the work/done channel capacities.
     workers: u64,
+    /// Worker count overriding `workers`, see [`Scheduler::set_jobs`].
+    jobs: Mutex<Option<u64>>,
+    /// Which workspaces survive a run, see [`KeepWorkspace`].
+    keep: Mutex<KeepWorkspace>,
+    /// Directories whose free space runs watch besides `path`, see
+    /// [`Scheduler::watch_disk`].
+    disks: Mutex<Vec<PathBuf>>,
 }
 
 impl Inner {
+    /// The worker count of the next run.
+    fn workers(&self) -> u64 {
+        self.jobs.lock().unwrap_or(self.workers)
+    }
+
+    /// The directory kept workspaces are moved to.
+    fn debug_path(&self) -> PathBuf {
+        self.path.parent().unwrap_or(&self.path).join("debug")
+    }
+
+    /// The directory snapshots of staged environments are kept in.
+    fn snapshot_path(&self) -> PathBuf {
+        self.path.parent().unwrap_or(&self.path).join("snapshots")
+    }
+
     /// Drives a single end-to-end build for `addr`.
     ///
     /// Sequencing matters here:
@@ -145,19 +341,65 @@ impl Inner {
     /// 2. `Graph::add` recursively pulls in `addr` and its transitive
     ///    dependencies, computes the active subgraph, and pre-builds an
     ///    indegree template for the dispatcher.
-    /// 3. `Graph::fetch` populates each node's [`Id`](crate::storage::Id),
-    ///    consults the build cache, and prepares (downloads sources for)
-    ///    every node that isn't already built.
-    /// 4. The graph is wrapped in an `Arc` (cheap; `Graph` is `Clone` but
-    ///    we want shared ownership across worker tasks) and `Graph::run`
-    ///    spawns the worker pool and drives the topological dispatch.
-    pub async fn run(&self, ctx: &Context, addr: &Addr) -> Result<()> {
-        let mut graph = Graph::new(self.workers);
-        graph.add(ctx, addr).await?;
-        graph.fetch(ctx).await?;
-        let graph_ref = Arc::new(graph);
-        graph_ref.run(&self.path, ctx, addr).await?;
-        Ok(())
+    /// 3. `Graph::probe` populates each node's [`Id`](crate::storage::Id)
+    ///    and consults the build cache.
+    /// 4. `Graph::run` spawns the worker pool and drives the topological
+    ///    dispatch, preparing (downloading sources for) each node that
+    ///    isn't already built as its dependencies get dispatched.
+    ///
+    /// With `fresh` set the build cache is ignored; see [`Scheduler::rebuild`].
+    /// Whatever the outcome, every node that made it into the graph is
+    /// described in `summary` afterwards. Transforms are expected to take as
+    /// long as they did in earlier runs, see [`Durations`](crate::context::Durations),
+    /// and the time the whole build was expected to take is added to
+    /// [`RunSummary::estimate_ms`].
+    pub async fn run(
+        &self,
+        ctx: &Context,
+        addr: &Addr,
+        fresh: bool,
+        summary: &mut RunSummary,
+    ) -> Result<()> {
+        let addr = &ctx.resolve_alias(addr);
+        let mut graph = Graph::new(self.workers());
+        graph.set_fresh(fresh);
+        graph.set_keep_workspace(*self.keep.lock(), &self.debug_path());
+        // Read from the context so project `[config.scheduler]` tables apply
+        graph.set_farm_limits(
+            farm_limits(ctx.config())
+                .into_iter()
+                .map(|(farm, limit)| (ctx.resolve_alias(&farm), limit))
+                .collect(),
+        );
+        let mut disks = vec![self.path.clone()];
+        disks.extend(self.disks.lock().iter().cloned());
+        let disk = Arc::new(DiskGuard::new(disks, disk::min_free_space(ctx.config())));
+        disk.ensure(ctx).await?;
+        graph.set_disk_guard(&disk);
+        if snapshot::enabled(ctx.config()) {
+            graph.set_snapshots(&Arc::new(Snapshots::new(&self.snapshot_path())));
+        }
+        graph.set_log_cache(log_cache(ctx.config()));
+        graph.set_durations(ctx.runs().durations().await);
+        let token = CancellationToken::new();
+        graph.set_cancellation(&token);
+        let _interrupt = Interrupt::watch(&token);
+        let result: Result<()> = async {
+            graph.add(ctx, addr).await?;
+            graph.probe(ctx).await?;
+            graph
+                .run(&self.path, ctx, addr)
+                .instrument(info_span!("building", addr = addr.to_string()))
+                .await
+        }
+        .await;
+        let uploads = ctx.storage().has_build_cache().await;
+        summary.nodes.extend(graph.summarize(addr, uploads));
+        if let Some(estimate) = graph.estimate() {
+            let estimate = estimate.as_millis() as u64;
+            summary.estimate_ms = Some(summary.estimate_ms.unwrap_or_default() + estimate);
+        }
+        result
     }
 }
 
@@ -233,6 +475,89 @@ mod tests {
         assert_eq!(s.inner.workers, 8);
     }
 
+    #[tokio::test]
+    async fn jobs_override_the_configured_workers() {
+        let dir = TempDir::new().unwrap();
+        let cfg = config_from_toml(&dir, "[scheduler]\nworkers = 3\n").await;
+        let s = Scheduler::new(dir.path(), &cfg).await.unwrap();
+        s.set_jobs(Some(12));
+        assert_eq!(s.inner.workers(), 12);
+        s.set_jobs(None);
+        assert_eq!(s.inner.workers(), 3);
+    }
+
+    #[tokio::test]
+    async fn farm_limits_skip_malformed_entries() {
+        let dir = TempDir::new().unwrap();
+        let cfg = config_from_toml(
+            &dir,
+            "[scheduler.farms]\n\"//p/vm\" = 2\n\"//p/none\" = 0\n\"//p/text\" = \"two\"\n",
+        )
+        .await;
+        assert_eq!(
+            farm_limits(&cfg),
+            HashMap::from([(Addr::parse("//p/vm").unwrap(), 2)])
+        );
+        assert!(farm_limits(&empty_config(&dir).await).is_empty());
+    }
+
+    #[tokio::test]
+    async fn log_cache_reads_booleans_and_caches() {
+        let dir = TempDir::new().unwrap();
+        let cache = |body: &'static str| {
+            let dir = &dir;
+            async move { log_cache(&config_from_toml(dir, body).await) }
+        };
+        assert_eq!(
+            cache("[scheduler]\nupload_logs = true\n").await,
+            Some(CacheSelector::Build)
+        );
+        assert_eq!(cache("[scheduler]\nupload_logs = false\n").await, None);
+        assert_eq!(
+            cache("[scheduler]\nupload_logs = \"output\"\n").await,
+            Some(CacheSelector::Output)
+        );
+        assert_eq!(
+            cache("[scheduler]\nupload_logs = \"nowhere\"\n").await,
+            None
+        );
+        assert_eq!(log_cache(&empty_config(&dir).await), None);
+    }
+
+    #[tokio::test]
+    async fn min_free_space_reads_sizes_and_falls_back() {
+        let dir = TempDir::new().unwrap();
+        let min_free = |body: &'static str| {
+            let dir = &dir;
+            async move { disk::min_free_space(&config_from_toml(dir, body).await) }
+        };
+        assert_eq!(
+            min_free("[scheduler]\nmin_free_space = \"2GiB\"\n").await,
+            2 << 30
+        );
+        assert_eq!(min_free("[scheduler]\nmin_free_space = 0\n").await, 0);
+        assert_eq!(
+            min_free("[scheduler]\nmin_free_space = \"lots\"\n").await,
+            DEFAULT_MIN_FREE_SPACE
+        );
+        assert_eq!(
+            disk::min_free_space(&empty_config(&dir).await),
+            DEFAULT_MIN_FREE_SPACE
+        );
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_opt_in() {
+        let dir = TempDir::new().unwrap();
+        let enabled = |body: &'static str| {
+            let dir = &dir;
+            async move { snapshot::enabled(&config_from_toml(dir, body).await) }
+        };
+        assert!(enabled("[scheduler]\nsnapshots = true\n").await);
+        assert!(!enabled("[scheduler]\nsnapshots = \"yes\"\n").await);
+        assert!(!snapshot::enabled(&empty_config(&dir).await));
+    }
+
     #[tokio::test]
     async fn new_preserves_workspace_path() {
         let dir = TempDir::new().unwrap();
@@ -295,6 +620,42 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    #[serial_test::serial(log_manager)]
+    async fn rebuild_ignores_cached_artifact() {
+        let Some(ctx) = try_shared_context().await else {
+            eprintln!("skip");
+            return;
+        };
+        ensure_default_farm(&ctx);
+
+        let order = Arc::new(TokioMutex::new(Vec::new()));
+        let mi = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let h = register_mock(&ctx, "//rb/a", &[], order, mi);
+        let addr = Addr::parse("//rb/a").unwrap();
+        // Seed the local cache so a normal run is a cache hit
+        let id = ctx
+            .get_transform(&addr)
+            .unwrap()
+            .get_unique_id(&ctx.get_handle())
+            .await
+            .unwrap();
+        let cached = crate::storage::Artifact::builder()
+            .media_type(crate::storage::MediaType::Manifest)
+            .config(crate::storage::Config::builder().id(id).build())
+            .build();
+        ctx.storage().safe_save(&cached).await.unwrap();
+
+        let dir = TempDir::new().unwrap();
+        let cfg = empty_config(&dir).await;
+        let s = Scheduler::new(dir.path().join("ws"), &cfg).await.unwrap();
+        s.run(&ctx, &addr).await.expect("run");
+        assert_eq!(h.transform_called.load(AtomicOrdering::SeqCst), 0);
+
+        s.rebuild(&ctx, &addr).await.expect("rebuild");
+        assert_eq!(h.transform_called.load(AtomicOrdering::SeqCst), 1);
+    }
+
     #[tokio::test]
     async fn clone_shares_inner_arc() {
         // `Scheduler::clone` must be cheap — verify the inner Arc is shared
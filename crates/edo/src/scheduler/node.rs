@@ -6,18 +6,33 @@
 //!
 //! - **`addr`** — stable identity (used as the registry key).
 //! - **`status`** — lifecycle state machine (`Pending → Running → Success|Failed`).
-//! - **`id`** — content-addressed [`Id`], populated by [`Graph::fetch`](super::graph::Graph::fetch).
+//! - **`id`** — content-addressed [`Id`], populated by [`Graph::probe`](super::graph::Graph::probe)
+//!   along with the **`key`** components it was derived from.
 //! - **`cache_hit`** — whether the build cache already has an artifact for `id`.
+//! - **`prepared`** — set once the transform's sources and artifacts were
+//!   fetched, whether ahead of dispatch or by the worker running it.
+//! - **`estimate`** — how long earlier runs took to execute the transform.
+//! - **`elapsed`**, **`error`**, **`log`** — execution record used to build
+//!   the [`RunSummary`](crate::context::RunSummary) once the run finishes.
 //!
 //! All mutable fields are atomics / `OnceLock` so that [`Node`] can be wrapped
 //! in `Arc<Node>` and shared across worker tasks without external locking.
 
-use std::sync::{
-    OnceLock,
-    atomic::{AtomicBool, AtomicU8, Ordering},
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering},
+    },
+    time::Duration,
 };
 
-use crate::{context::Addr, storage::Id};
+use crate::{
+    context::{Addr, NodeOutcome, NodeSummary},
+    environment::CommandFailure,
+    storage::Id,
+    transform::KeyComponent,
+};
 
 /// A single vertex in the scheduler's execution graph.
 ///
@@ -30,14 +45,46 @@ pub struct Node {
     /// Lifecycle state encoded as a `u8` matching [`NodeStatus`].
     pub status: AtomicU8,
     /// Content-addressed id for the transform, computed during
-    /// [`Graph::fetch`](super::graph::Graph::fetch). Set exactly once.
+    /// [`Graph::probe`](super::graph::Graph::probe). Set exactly once.
     pub id: OnceLock<Id>,
-    /// `true` when [`Graph::fetch`](super::graph::Graph::fetch) finds a
+    /// Components of the cache key `id` was derived from, recorded in the
+    /// run summary so `edo why` can compare against them. Set at most once.
+    pub key: OnceLock<Vec<KeyComponent>>,
+    /// `true` when [`Graph::probe`](super::graph::Graph::probe) finds a
     /// fully-built artifact for this node in the build cache. Drives the
     /// pre-pass cascade in [`Graph::run`](super::graph::Graph::run) which
     /// short-circuits dispatch for already-built subtrees. Written once,
     /// read many times.
     pub cache_hit: AtomicBool,
+    /// Set once [`Transform::prepare`](crate::transform::Transform::prepare)
+    /// succeeded, by [`Graph::fetch`](super::graph::Graph::fetch), a
+    /// look-ahead fetch in [`Graph::run`](super::graph::Graph::run) or the
+    /// worker itself. Concurrent callers wait on the first attempt, so a
+    /// node is never prepared twice.
+    pub prepared: tokio::sync::OnceCell<()>,
+    /// How long the transform is expected to take, from the
+    /// [`Durations`](crate::context::Durations) of earlier runs. Set at
+    /// most once, by [`Graph::run`](super::graph::Graph::run).
+    pub estimate: OnceLock<Duration>,
+    /// Milliseconds spent in the transform lifecycle, recorded by the
+    /// worker once the node finishes.
+    pub elapsed: AtomicU64,
+    /// Rendered error for a failed node. Set at most once.
+    pub error: OnceLock<String>,
+    /// Log file the transform lifecycle wrote to. Set at most once.
+    pub log: OnceLock<PathBuf>,
+    /// Last lines of command output, recorded when the transform failed.
+    pub tail: OnceLock<Vec<String>>,
+    /// The script command a failed transform stopped at, if known.
+    pub failure: OnceLock<CommandFailure>,
+    /// Where the transform's workspace was kept for debugging, if it was.
+    pub workspace: OnceLock<PathBuf>,
+    /// `true` when the transform ran with host mounts or devices exposed,
+    /// in which case its artifact is kept out of the build cache.
+    pub host_access: AtomicBool,
+    /// Farm the transform runs in, chosen by [`Graph::probe`](super::Graph::probe)
+    /// among those that can execute its architecture. Set at most once.
+    pub farm: OnceLock<Addr>,
 }
 
 /// Lifecycle of a [`Node`].
@@ -79,7 +126,18 @@ impl Node {
             addr: addr.clone(),
             status: AtomicU8::new(NodeStatus::Pending as u8),
             id: OnceLock::new(),
+            key: OnceLock::new(),
             cache_hit: AtomicBool::new(false),
+            prepared: tokio::sync::OnceCell::new(),
+            estimate: OnceLock::new(),
+            elapsed: AtomicU64::new(0),
+            error: OnceLock::new(),
+            log: OnceLock::new(),
+            tail: OnceLock::new(),
+            failure: OnceLock::new(),
+            workspace: OnceLock::new(),
+            host_access: AtomicBool::new(false),
+            farm: OnceLock::new(),
         }
     }
 
@@ -125,8 +183,14 @@ impl Node {
         self.id.get()
     }
 
+    /// Records the components of the cache key the id was derived from. The
+    /// first call wins.
+    pub fn set_key(&self, key: Vec<KeyComponent>) {
+        let _ = self.key.set(key);
+    }
+
     /// Records whether a fully-built artifact exists in the build cache.
-    /// Called by [`Graph::fetch`](super::graph::Graph::fetch).
+    /// Called by [`Graph::probe`](super::graph::Graph::probe).
     pub fn set_cache_hit(&self, v: bool) {
         self.cache_hit.store(v, Ordering::SeqCst);
     }
@@ -136,4 +200,85 @@ impl Node {
     pub fn is_cache_hit(&self) -> bool {
         self.cache_hit.load(Ordering::SeqCst)
     }
+
+    /// Records how long the transform lifecycle took.
+    pub fn set_elapsed(&self, elapsed: Duration) {
+        self.elapsed
+            .store(elapsed.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    /// Records the error a failed node reported. The first error wins.
+    pub fn set_error(&self, error: &impl std::fmt::Display) {
+        let _ = self.error.set(error.to_string());
+    }
+
+    /// Records the log file for this node. The first path wins.
+    pub fn set_log(&self, path: &Path) {
+        let _ = self.log.set(path.to_path_buf());
+    }
+
+    /// Records the last lines of output of a failed transform. The first
+    /// call wins.
+    pub fn set_tail(&self, tail: Vec<String>) {
+        let _ = self.tail.set(tail);
+    }
+
+    /// Records the script command a failed transform stopped at. The first
+    /// call wins.
+    pub fn set_failure(&self, failure: &CommandFailure) {
+        let _ = self.failure.set(failure.clone());
+    }
+
+    /// Records where the transform's workspace was kept. The first path wins.
+    pub fn set_workspace(&self, path: &Path) {
+        let _ = self.workspace.set(path.to_path_buf());
+    }
+
+    /// Records the farm the transform runs in. The first call wins.
+    pub fn set_farm(&self, farm: &Addr) {
+        let _ = self.farm.set(farm.clone());
+    }
+
+    /// Records whether the transform ran with host mounts or devices exposed.
+    pub fn set_host_access(&self, v: bool) {
+        self.host_access.store(v, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if the transform ran with host mounts or devices
+    /// exposed.
+    pub fn uses_host(&self) -> bool {
+        self.host_access.load(Ordering::SeqCst)
+    }
+
+    /// Describes this node for a [`RunSummary`](crate::context::RunSummary).
+    ///
+    /// Cache hits are reported as cached regardless of status since they
+    /// are never dispatched. `uploads` states whether a build cache was
+    /// registered, in which case every built node that did not use host
+    /// mounts or devices was pushed to it.
+    pub fn summary(&self, uploads: bool) -> NodeSummary {
+        let outcome = if self.is_cache_hit() {
+            NodeOutcome::Cached
+        } else {
+            match NodeStatus::from(self.status.load(Ordering::SeqCst)) {
+                NodeStatus::Success => NodeOutcome::Built,
+                NodeStatus::Failed => NodeOutcome::Failed,
+                NodeStatus::Pending | NodeStatus::Running => NodeOutcome::Skipped,
+            }
+        };
+        NodeSummary {
+            addr: self.addr.clone(),
+            id: self.id().map(|x| x.to_string()),
+            outcome,
+            duration_ms: self.elapsed.load(Ordering::SeqCst),
+            uploaded: uploads && outcome == NodeOutcome::Built && !self.uses_host(),
+            error: self.error.get().cloned(),
+            log: self.log.get().cloned(),
+            tail: self.tail.get().cloned().unwrap_or_default(),
+            failure: self.failure.get().cloned(),
+            workspace: self.workspace.get().cloned(),
+            key: self.key.get().cloned().unwrap_or_default(),
+            estimate_ms: self.estimate.get().map(|x| x.as_millis() as u64),
+        }
+    }
 }
@@ -0,0 +1,140 @@
+//! Snapshots of staged transform environments.
+//!
+//! Running a transform again after editing its commands, as in an edit loop
+//! against `edo daemon`, sets up and stages the same inputs every time.
+//! With `[scheduler] snapshots = true` the scheduler asks each environment to
+//! snapshot itself once staging is done, and records the digest of what was
+//! staged next to it. When the transform runs again with the same
+//! dependencies, sources, farm and platform the snapshot is restored in
+//! place of staging, so only the commands run. Any change to those inputs
+//! stages from scratch and replaces the snapshot. Environments that cannot
+//! take snapshots, and transforms whose ids are not built from a
+//! [`CacheKey`](crate::transform::CacheKey), always stage.
+
+use super::Result;
+use super::error;
+use crate::context::{Addr, Config, Handle, Log};
+use crate::environment::Environment;
+use crate::transform::{KeyKind, Transform};
+use snafu::ResultExt;
+use std::path::{Path, PathBuf};
+
+/// Name of the file holding the stage digest a snapshot was taken with.
+const KEY_FILE: &str = "key";
+/// Directory of a snapshot holding the environment's state.
+const STATE_DIR: &str = "state";
+
+/// Reads `[scheduler] snapshots`, off unless set to `true`.
+pub(crate) fn enabled(config: &Config) -> bool {
+    let Some(value) = config.get("scheduler").and_then(|x| x.get("snapshots")) else {
+        return false;
+    };
+    value.as_bool().unwrap_or_else(|| {
+        warn!("ignoring scheduler.snapshots, expected true or false");
+        false
+    })
+}
+
+/// The snapshots of staged environments, one per transform.
+pub(crate) struct Snapshots {
+    path: PathBuf,
+}
+
+impl Snapshots {
+    /// Keeps snapshots in `path`, i.e. `.edo/snapshots`.
+    pub(crate) fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    fn dir(&self, addr: &Addr) -> PathBuf {
+        let addr = addr.to_string();
+        self.path
+            .join(addr.strip_prefix("//").unwrap_or(&addr).replace('/', "-"))
+    }
+
+    /// The digest of everything staging `transform` in `farm` depends on,
+    /// `None` if its inputs are unknown.
+    pub(crate) async fn stage_key(
+        ctx: &Handle,
+        transform: &Transform,
+        farm: &Addr,
+    ) -> Result<Option<String>> {
+        let key = transform.cache_key(ctx).await?;
+        let mut hash = blake3::Hasher::new();
+        hash.update(farm.to_string().as_bytes());
+        for component in key.components() {
+            match component.kind {
+                KeyKind::Opaque => return Ok(None),
+                KeyKind::Depend | KeyKind::Source | KeyKind::Environment | KeyKind::Platform => {
+                    hash.update(
+                        format!(
+                            "\n{}:{}={}",
+                            component.kind.name(),
+                            component.name,
+                            component.digest
+                        )
+                        .as_bytes(),
+                    );
+                }
+                _ => {}
+            }
+        }
+        Ok(Some(hash.finalize().to_hex().to_string()))
+    }
+
+    /// Restores the snapshot of `addr` into `env` if it was taken with
+    /// `key`, returning whether staging can be skipped.
+    pub(crate) async fn restore(
+        &self,
+        log: &Log,
+        env: &Environment,
+        addr: &Addr,
+        key: &str,
+    ) -> Result<bool> {
+        let dir = self.dir(addr);
+        match tokio::fs::read_to_string(dir.join(KEY_FILE)).await {
+            Ok(saved) if saved == key => {}
+            _ => return Ok(false),
+        }
+        let restored = env.restore(log, &dir.join(STATE_DIR)).await;
+        if !matches!(restored, Ok(true)) {
+            // Never try a snapshot that could not be restored again
+            let _ = tokio::fs::remove_dir_all(&dir).await;
+        }
+        Ok(restored?)
+    }
+
+    /// Replaces the snapshot of `addr` with the state of `env`, staged with
+    /// `key`. Failing to take one only loses the speed up, so errors are
+    /// logged rather than returned.
+    pub(crate) async fn save(&self, log: &Log, env: &Environment, addr: &Addr, key: &str) {
+        let dir = self.dir(addr);
+        let result: Result<()> = async {
+            if dir.exists() {
+                tokio::fs::remove_dir_all(&dir)
+                    .await
+                    .context(error::IoSnafu)?;
+            }
+            tokio::fs::create_dir_all(&dir)
+                .await
+                .context(error::IoSnafu)?;
+            if !env.snapshot(log, &dir.join(STATE_DIR)).await? {
+                tokio::fs::remove_dir_all(&dir)
+                    .await
+                    .context(error::IoSnafu)?;
+                return Ok(());
+            }
+            // The key goes last, a snapshot without one is never restored
+            tokio::fs::write(dir.join(KEY_FILE), key)
+                .await
+                .context(error::IoSnafu)
+        }
+        .await;
+        if let Err(e) = result {
+            warn!("failed to snapshot the environment of {addr}: {e}");
+            let _ = tokio::fs::remove_dir_all(&dir).await;
+        }
+    }
+}
@@ -24,12 +24,26 @@ pub enum SourceError {
         #[snafu(source(from(crate::storage::StorageError, Box::new)))]
         source: Box<crate::storage::StorageError>,
     },
+    /// A source resolved to something else than its pin in the lock file.
+    #[snafu(display(
+        "source {addr} resolved to {actual} but edo.lock.json pins {locked}, run `edo update` to accept the change"
+    ))]
+    Drift {
+        addr: crate::context::Addr,
+        locked: String,
+        actual: String,
+    },
     /// A node field was present but had an unexpected type.
     #[snafu(display("field '{field}' should be defined as a {type_}"))]
     Field { field: String, type_: String },
     /// An I/O error occurred during source operations.
     #[snafu(display("io eccor occured: {source}"))]
     Io { source: std::io::Error },
+    /// The source is not in the local cache and fetching it needs the network.
+    #[snafu(display(
+        "source {id} is not in the local cache, run `edo fetch` before building offline"
+    ))]
+    Offline { id: String },
     /// No vendor with the given name is registered in the context.
     #[snafu(display("no vendor registered with name {name}"))]
     NoVendor { name: String },
@@ -46,10 +60,11 @@ pub enum SourceError {
         source: Box<crate::context::ContextError>,
     },
     /// Failed to build a resolver requirement for the named dependency at the given version.
-    #[snafu(display("could not build requirement for {name} at {version}"))]
+    #[snafu(display("could not build requirement for {name} at {version}:\n{explanation}"))]
     Requirement {
         name: String,
         version: semver::VersionReq,
+        explanation: String,
     },
     /// The dependency resolver could not find a satisfying solution.
     #[snafu(display("resolution of vendored dependencies failed: {reason}"))]
@@ -0,0 +1,167 @@
+use crate::context::Addr;
+use semver::Version;
+use std::fmt;
+
+/// The verdict the resolver reached for a single candidate version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    /// Vendor offering this candidate.
+    pub vendor: String,
+    /// The candidate version.
+    pub version: Version,
+    /// Priority of the offering vendor.
+    pub priority: i64,
+    /// Why the candidate was filtered out, or `None` if it satisfied every constraint.
+    pub rejected: Option<String>,
+}
+
+/// A human readable account of how one dependency was resolved.
+///
+/// Lists every candidate offered by every vendor in the order the resolver
+/// would try them, the constraint that filtered out each rejected candidate,
+/// and the final selection if resolution succeeded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    /// Address of the `requires` entry being explained.
+    pub addr: Addr,
+    /// Package name being resolved.
+    pub name: String,
+    /// The constraints declared on the dependency, rendered for display.
+    pub constraints: Vec<String>,
+    /// All candidates, in resolver preference order.
+    pub candidates: Vec<Candidate>,
+    /// The selected vendor and version, if resolution succeeded.
+    pub selected: Option<(String, Version)>,
+}
+
+impl Explanation {
+    /// Returns the candidates that satisfied every constraint.
+    pub fn admitted(&self) -> impl Iterator<Item = &Candidate> {
+        self.candidates.iter().filter(|x| x.rejected.is_none())
+    }
+
+    fn rationale(&self) -> String {
+        let Some((vendor, version)) = self.selected.as_ref() else {
+            return if self.admitted().next().is_none() {
+                "no candidate satisfies the constraints".to_string()
+            } else {
+                "no selection was made, see the resolution conflict".to_string()
+            };
+        };
+        let first = self.admitted().next();
+        if first.is_some_and(|x| x.vendor == *vendor && x.version == *version) {
+            let ties = self
+                .admitted()
+                .filter(|x| x.version == *version && x.vendor != *vendor)
+                .count();
+            if ties == 0 {
+                format!("{version} from {vendor} is the highest admitted version")
+            } else {
+                format!(
+                    "{version} from {vendor} is the highest admitted version, preferred over \
+                     {ties} other vendor(s) offering it by vendor priority then name"
+                )
+            }
+        } else {
+            format!(
+                "{version} from {vendor} was chosen over newer admitted candidates to satisfy \
+                 other requirements"
+            )
+        }
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} ({}): {}",
+            self.name,
+            self.addr,
+            self.constraints.join(", ")
+        )?;
+        if self.candidates.is_empty() {
+            writeln!(f, "  no vendor offers any version of {}", self.name)?;
+        }
+        for candidate in self.candidates.iter() {
+            let marker = match self.selected.as_ref() {
+                Some((vendor, version))
+                    if *vendor == candidate.vendor && *version == candidate.version =>
+                {
+                    "*"
+                }
+                _ if candidate.rejected.is_none() => "+",
+                _ => "-",
+            };
+            write!(
+                f,
+                "  {marker} {} from {} (priority {})",
+                candidate.version, candidate.vendor, candidate.priority
+            )?;
+            if let Some(reason) = candidate.rejected.as_ref() {
+                write!(f, ": {reason}")?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "  => {}", self.rationale())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(vendor: &str, version: &str, rejected: Option<&str>) -> Candidate {
+        Candidate {
+            vendor: vendor.into(),
+            version: Version::parse(version).unwrap(),
+            priority: 0,
+            rejected: rejected.map(|x| x.to_string()),
+        }
+    }
+
+    fn explanation(candidates: Vec<Candidate>, selected: Option<(&str, &str)>) -> Explanation {
+        Explanation {
+            addr: Addr::parse("//p/dep").unwrap(),
+            name: "dep".into(),
+            constraints: vec!["at ^1".into()],
+            candidates,
+            selected: selected.map(|(v, ver)| (v.to_string(), Version::parse(ver).unwrap())),
+        }
+    }
+
+    #[test]
+    fn display_marks_selected_admitted_and_rejected() {
+        let e = explanation(
+            vec![
+                candidate("a", "2.0.0", Some("does not satisfy ^1")),
+                candidate("a", "1.2.0", None),
+                candidate("a", "1.0.0", None),
+            ],
+            Some(("a", "1.2.0")),
+        );
+        let text = e.to_string();
+        assert!(text.contains("- 2.0.0 from a (priority 0): does not satisfy ^1"));
+        assert!(text.contains("* 1.2.0 from a"));
+        assert!(text.contains("+ 1.0.0 from a"));
+        assert!(text.contains("highest admitted version"));
+    }
+
+    #[test]
+    fn rationale_mentions_tie_break() {
+        let e = explanation(
+            vec![candidate("a", "1.0.0", None), candidate("b", "1.0.0", None)],
+            Some(("a", "1.0.0")),
+        );
+        assert!(e.to_string().contains("1 other vendor(s)"));
+    }
+
+    #[test]
+    fn rationale_without_admitted_candidates() {
+        let e = explanation(vec![candidate("a", "2.0.0", Some("no"))], None);
+        assert!(
+            e.to_string()
+                .contains("no candidate satisfies the constraints")
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! License metadata of sources.
+//!
+//! A source definition, or the node a vendor resolves a dependency to,
+//! declares what its code is licensed under with an SPDX expression in its
+//! `license` field, such as `license = "MIT OR Apache-2.0"`. Sources without
+//! one are scanned once fetched: `SPDX-License-Identifier:` tags near the
+//! top of each file, and the text of `LICENSE`, `LICENCE` and `COPYING`
+//! files, which are told apart by phrases of the common licenses. A source
+//! whose license can be found neither way is reported as
+//! [`NOASSERTION`], the SPDX value for an unknown license.
+
+use super::SourceResult;
+use crate::context::{FieldSchema, FieldType, Node};
+use crate::storage::{Artifact, MediaType, Storage, decode};
+use futures::StreamExt;
+use serde::Serialize;
+use snafu::{OptionExt, ResultExt};
+use std::collections::BTreeSet;
+use std::fmt;
+use tokio::io::AsyncReadExt;
+use tokio_tar::Archive;
+
+use super::error;
+
+/// Field of source definitions declaring their license as an SPDX expression.
+pub const LICENSE_FIELD: &str = "license";
+/// License of sources whose license is not known.
+pub const NOASSERTION: &str = "NOASSERTION";
+
+/// Tag files declare their license with.
+const SPDX_TAG: &str = "SPDX-License-Identifier:";
+/// How much of each file is searched for an [`SPDX_TAG`].
+const HEAD_SIZE: usize = 8 * 1024;
+
+/// Phrases identifying license texts, checked in order so the LGPL and AGPL
+/// are not mistaken for the GPL.
+const TEXTS: &[(&[&str], &str)] = &[
+    (&["Apache License", "Version 2.0"], "Apache-2.0"),
+    (
+        &["GNU LESSER GENERAL PUBLIC LICENSE", "Version 2.1"],
+        "LGPL-2.1-only",
+    ),
+    (
+        &["GNU LESSER GENERAL PUBLIC LICENSE", "Version 3"],
+        "LGPL-3.0-only",
+    ),
+    (
+        &["GNU AFFERO GENERAL PUBLIC LICENSE", "Version 3"],
+        "AGPL-3.0-only",
+    ),
+    (&["GNU GENERAL PUBLIC LICENSE", "Version 2"], "GPL-2.0-only"),
+    (&["GNU GENERAL PUBLIC LICENSE", "Version 3"], "GPL-3.0-only"),
+    (&["Mozilla Public License Version 2.0"], "MPL-2.0"),
+    (&["Permission is hereby granted, free of charge"], "MIT"),
+    (
+        &[
+            "Redistribution and use in source and binary forms",
+            "Neither the name",
+        ],
+        "BSD-3-Clause",
+    ),
+    (
+        &["Redistribution and use in source and binary forms"],
+        "BSD-2-Clause",
+    ),
+    (
+        &["Permission to use, copy, modify, and/or distribute this software for any purpose"],
+        "ISC",
+    ),
+    (
+        &["This is free and unencumbered software released into the public domain"],
+        "Unlicense",
+    ),
+];
+
+/// How the licenses of a source were found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LicenseOrigin {
+    /// The definition declares them in its `license` field.
+    Declared,
+    /// The fetched source was scanned for them.
+    Detected,
+    /// Neither gave any, the source is reported as [`NOASSERTION`].
+    Unknown,
+}
+
+impl fmt::Display for LicenseOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Declared => "declared",
+            Self::Detected => "detected",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+/// The license identifiers in the SPDX expression `expression`.
+///
+/// Every identifier is returned, whether it is joined by `AND` or `OR`, and
+/// `WITH` keeps an exception with its license, such as
+/// `GPL-2.0-only WITH Classpath-exception-2.0`.
+pub fn license_ids(expression: &str) -> BTreeSet<String> {
+    let expression = expression.replace(['(', ')'], " ");
+    let mut ids = BTreeSet::new();
+    let mut current: Vec<&str> = Vec::new();
+    for word in expression.split_whitespace() {
+        if word == "AND" || word == "OR" {
+            if !current.is_empty() {
+                ids.insert(current.join(" "));
+            }
+            current.clear();
+        } else {
+            current.push(word);
+        }
+    }
+    if !current.is_empty() {
+        ids.insert(current.join(" "));
+    }
+    ids
+}
+
+/// The licenses the source defined by `node` declares, if any.
+pub fn declared_licenses(node: &Node) -> SourceResult<Option<BTreeSet<String>>> {
+    let Some(value) = node.get(LICENSE_FIELD) else {
+        return Ok(None);
+    };
+    let expression = value.as_string().context(error::FieldSnafu {
+        field: LICENSE_FIELD,
+        type_: "SPDX license expression",
+    })?;
+    Ok(Some(license_ids(&expression)).filter(|x| !x.is_empty()))
+}
+
+/// Describes the `license` field of a source.
+pub fn license_field() -> FieldSchema {
+    FieldSchema::new(
+        LICENSE_FIELD,
+        FieldType::String,
+        "SPDX expression of the source's license, such as MIT OR Apache-2.0",
+    )
+}
+
+/// Scans the tar layers of the fetched source `artifact` for licenses.
+pub async fn detect_licenses(
+    storage: &Storage,
+    artifact: &Artifact,
+) -> SourceResult<BTreeSet<String>> {
+    let mut licenses = BTreeSet::new();
+    for layer in artifact.layers() {
+        let MediaType::Tar(compression) = layer.media_type() else {
+            continue;
+        };
+        let reader = decode(storage.safe_read(layer).await?, compression);
+        let mut archive = Archive::new(reader);
+        let mut entries = archive.entries().context(error::IoSnafu)?;
+        while let Some(entry) = entries.next().await {
+            let entry = entry.context(error::IoSnafu)?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = entry.path().context(error::IoSnafu)?.to_path_buf();
+            let name = path
+                .file_name()
+                .map(|x| x.to_string_lossy().to_uppercase())
+                .unwrap_or_default();
+            let is_license = ["LICENSE", "LICENCE", "COPYING"]
+                .iter()
+                .any(|x| name.starts_with(x));
+            let limit = if is_license { 64 * 1024 } else { HEAD_SIZE };
+            let mut head = Vec::new();
+            entry
+                .take(limit as u64)
+                .read_to_end(&mut head)
+                .await
+                .context(error::IoSnafu)?;
+            let text = String::from_utf8_lossy(&head);
+            licenses.extend(tagged_licenses(&text));
+            if is_license && let Some(license) = identify_text(&text) {
+                licenses.insert(license.to_string());
+            }
+        }
+    }
+    Ok(licenses)
+}
+
+/// The licenses named by the `SPDX-License-Identifier:` tags in `text`.
+fn tagged_licenses(text: &str) -> BTreeSet<String> {
+    text.lines()
+        .filter_map(|line| line.split_once(SPDX_TAG).map(|x| x.1))
+        .flat_map(|expression| {
+            // Tags usually sit in a comment that may close on the same line
+            let expression = ["*/", "-->", "#}"]
+                .iter()
+                .fold(expression, |x, end| x.split(end).next().unwrap_or(x));
+            license_ids(expression)
+        })
+        .collect()
+}
+
+/// The license whose text `text` is, if it is a common one.
+fn identify_text(text: &str) -> Option<&'static str> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    TEXTS
+        .iter()
+        .find(|(phrases, _)| phrases.iter().all(|x| text.contains(x)))
+        .map(|(_, license)| *license)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Backend, InMemoryBackend, Storage};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn expressions_list_every_license() {
+        assert_eq!(
+            license_ids("(MIT OR Apache-2.0) AND GPL-2.0-only WITH Classpath-exception-2.0"),
+            BTreeSet::from([
+                "Apache-2.0".to_string(),
+                "GPL-2.0-only WITH Classpath-exception-2.0".to_string(),
+                "MIT".to_string(),
+            ])
+        );
+        let node = Node::new_definition(
+            "source",
+            "git",
+            "src",
+            BTreeMap::from([(
+                LICENSE_FIELD.to_string(),
+                Node::new_string("BSD-3-Clause".into()),
+            )]),
+        );
+        assert_eq!(
+            declared_licenses(&node).unwrap(),
+            Some(BTreeSet::from(["BSD-3-Clause".to_string()]))
+        );
+    }
+
+    #[tokio::test]
+    async fn fetched_trees_are_scanned_for_licenses() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("LICENSE"),
+            "Copyright (c) 2024\n\nPermission is hereby granted, free of\ncharge, to any person",
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("src/lib.c"),
+            "/* SPDX-License-Identifier: Apache-2.0 */\nint x;\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("README"), "no license here").unwrap();
+
+        let storage = Storage::init(&Backend::new(InMemoryBackend::new()))
+            .await
+            .unwrap();
+        let artifact = storage.inject("src", dir.path()).await.unwrap();
+        assert_eq!(
+            detect_licenses(&storage, &artifact).await.unwrap(),
+            BTreeSet::from(["Apache-2.0".to_string(), "MIT".to_string()])
+        );
+    }
+}
@@ -9,14 +9,16 @@
 //! All fallible operations return [`SourceResult`], with failures modelled by
 //! [`SourceError`].
 
-use crate::context::Log;
+use crate::context::{Addr, Log, SourceLock};
 use crate::environment::Environment;
-use crate::storage::{Artifact, Id, Storage};
+use crate::storage::{Artifact, Id, SourceOrigin, Storage};
 use arc_handle::arc_handle;
 use async_trait::async_trait;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 mod error;
+mod explain;
+mod license;
 mod require;
 mod resolver;
 mod vendor;
@@ -25,6 +27,8 @@ mod version;
 /// Convenience result alias for fallible source operations.
 pub type SourceResult<T> = std::result::Result<T, error::SourceError>;
 pub use error::SourceError;
+pub use explain::*;
+pub use license::*;
 pub use require::*;
 pub use resolver::*;
 pub use vendor::*;
@@ -50,6 +54,38 @@ pub trait Source {
         env: &Environment,
         path: &Path,
     ) -> SourceResult<()>;
+    /// Paths in the project tree this source is read from, relative to the
+    /// project root.
+    ///
+    /// Used to map changed files back to the sources that own them, sources
+    /// fetched from elsewhere own none.
+    fn owned_paths(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+    /// Whether fetching this source may reach out over the network.
+    ///
+    /// Sources that do are refused in offline mode unless they are already
+    /// in the local cache.
+    fn needs_network(&self) -> bool {
+        true
+    }
+    /// Where this source comes from, used to pick the source caches it is
+    /// looked up in. Sources without one are looked up in every cache.
+    fn origin(&self) -> Option<SourceOrigin> {
+        None
+    }
+    /// What the fetched `artifact` resolved to, such as the commit a git
+    /// branch pointed at, pinned in `edo.lock.json`.
+    ///
+    /// Sources returning `None`, like ones read from the project tree, are
+    /// not pinned.
+    fn resolved(&self, _artifact: &Artifact) -> Option<String> {
+        None
+    }
+    /// Checks a fetched or cached `artifact` before it is used.
+    async fn verify(&self, _artifact: &Artifact) -> SourceResult<()> {
+        Ok(())
+    }
 }
 
 impl Source {
@@ -62,10 +98,147 @@ impl Source {
         // See if our storage can find this source artifact already
         // Note: we use fetch_source because we want to ensure when this is called
         // the artifact is in the local cache.
-        if let Some(artifact) = storage.fetch_source(&id).await? {
+        let origin = self.origin();
+        let cached = match origin.as_ref() {
+            Some(origin) => storage.fetch_source_for(&id, origin).await?,
+            None => storage.fetch_source(&id).await?,
+        };
+        if let Some(artifact) = cached {
+            self.verify(&artifact).await?;
             return Ok(artifact.clone());
         }
-        // Otherwise perform the fetch
-        self.fetch(log, storage).await
+        snafu::ensure!(
+            !self.needs_network() || !storage.is_offline().await,
+            error::OfflineSnafu { id: id.to_string() }
+        );
+        // Otherwise perform the fetch, handing the result to any read-through caches
+        let artifact = self.fetch(log, storage).await?;
+        self.verify(&artifact).await?;
+        storage.populate_source(&artifact, origin.as_ref()).await;
+        Ok(artifact)
+    }
+
+    /// Wraps this source so it reports `origin`, see [`Source::origin`].
+    pub fn with_origin(self, origin: SourceOrigin) -> Source {
+        Source::new(Origin {
+            inner: self,
+            origin,
+        })
+    }
+
+    /// Wraps this source so what it resolves to is checked against the pin
+    /// of `addr` in `lock`, see [`Source::resolved`].
+    pub fn pinned(self, addr: &Addr, lock: &SourceLock) -> Source {
+        Source::new(Pinned {
+            inner: self,
+            addr: addr.clone(),
+            lock: lock.clone(),
+        })
+    }
+}
+
+/// A source reporting where it comes from, see [`Source::with_origin`].
+struct Origin {
+    inner: Source,
+    origin: SourceOrigin,
+}
+
+#[async_trait]
+impl SourceImpl for Origin {
+    async fn get_unique_id(&self) -> SourceResult<Id> {
+        self.inner.get_unique_id().await
+    }
+
+    async fn fetch(&self, log: &Log, storage: &Storage) -> SourceResult<Artifact> {
+        self.inner.fetch(log, storage).await
+    }
+
+    async fn stage(
+        &self,
+        log: &Log,
+        storage: &Storage,
+        env: &Environment,
+        path: &Path,
+    ) -> SourceResult<()> {
+        self.inner.stage(log, storage, env, path).await
+    }
+
+    fn owned_paths(&self) -> Vec<PathBuf> {
+        self.inner.owned_paths()
+    }
+
+    fn needs_network(&self) -> bool {
+        self.inner.needs_network()
+    }
+
+    fn origin(&self) -> Option<SourceOrigin> {
+        Some(self.origin.clone())
+    }
+
+    fn resolved(&self, artifact: &Artifact) -> Option<String> {
+        self.inner.resolved(artifact)
+    }
+
+    async fn verify(&self, artifact: &Artifact) -> SourceResult<()> {
+        self.inner.verify(artifact).await
+    }
+}
+
+/// A source checked against its pin, see [`Source::pinned`].
+struct Pinned {
+    inner: Source,
+    addr: Addr,
+    lock: SourceLock,
+}
+
+#[async_trait]
+impl SourceImpl for Pinned {
+    async fn get_unique_id(&self) -> SourceResult<Id> {
+        self.inner.get_unique_id().await
+    }
+
+    async fn fetch(&self, log: &Log, storage: &Storage) -> SourceResult<Artifact> {
+        self.inner.fetch(log, storage).await
+    }
+
+    async fn stage(
+        &self,
+        log: &Log,
+        storage: &Storage,
+        env: &Environment,
+        path: &Path,
+    ) -> SourceResult<()> {
+        self.inner.stage(log, storage, env, path).await
+    }
+
+    fn owned_paths(&self) -> Vec<PathBuf> {
+        self.inner.owned_paths()
+    }
+
+    fn needs_network(&self) -> bool {
+        self.inner.needs_network()
+    }
+
+    fn origin(&self) -> Option<SourceOrigin> {
+        self.inner.origin()
+    }
+
+    fn resolved(&self, artifact: &Artifact) -> Option<String> {
+        self.inner.resolved(artifact)
+    }
+
+    async fn verify(&self, artifact: &Artifact) -> SourceResult<()> {
+        self.inner.verify(artifact).await?;
+        let Some(actual) = self.inner.resolved(artifact) else {
+            return Ok(());
+        };
+        let id = self.get_unique_id().await?.to_string();
+        self.lock
+            .check(&self.addr, &id, &actual)
+            .map_err(|locked| SourceError::Drift {
+                addr: self.addr.clone(),
+                locked,
+                actual,
+            })
     }
 }
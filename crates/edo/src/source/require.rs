@@ -1,14 +1,24 @@
 use super::error;
+use super::version::EdoVersion;
 use async_trait::async_trait;
-use semver::VersionReq;
+use semver::{Comparator, Op, Prerelease, Version, VersionReq};
 use snafu::{OptionExt, ensure};
 
 use crate::context::{Addr, Context, FromNode, Node};
 
+/// The channel name that only admits releases without a pre-release tag.
+pub const STABLE_CHANNEL: &str = "stable";
+
 /// A resolved dependency requirement parsed from an `edo.toml` `requires` node.
 ///
 /// Contains the package name, its semver version requirement, the optional
 /// vendor hint, and the originating address in the project graph.
+///
+/// Besides the `at` requirement a dependency may opt into pre-releases with
+/// `prerelease = true`, or select a release `channel`: `"stable"` excludes
+/// every pre-release, while any other channel name (e.g. `"beta"`) admits
+/// releases plus pre-releases whose first identifier matches the channel.
+/// Writing `at` as a bare version (`at = "v1.2.3"`) pins that exact version.
 #[derive(Clone, Hash, PartialEq, Eq)]
 pub struct Dependency {
     /// The address of the node that declared this dependency.
@@ -21,6 +31,79 @@ pub struct Dependency {
     pub version: VersionReq,
     /// Optional vendor name constraining which registry to resolve from.
     pub vendor: Option<String>,
+    /// Whether pre-release versions may satisfy the requirement.
+    pub prerelease: bool,
+    /// Optional release channel restricting which pre-releases are considered.
+    pub channel: Option<String>,
+}
+
+/// Builds a requirement matching exactly `version`, including its pre-release tag.
+fn exact(version: &Version) -> VersionReq {
+    VersionReq {
+        comparators: vec![Comparator {
+            op: Op::Exact,
+            major: version.major,
+            minor: Some(version.minor),
+            patch: Some(version.patch),
+            pre: version.pre.clone(),
+        }],
+    }
+}
+
+impl Dependency {
+    /// Returns `true` if `candidate` satisfies this dependency's vendor,
+    /// version, pre-release, and channel constraints.
+    pub fn admits(&self, candidate: &EdoVersion) -> bool {
+        self.rejection(candidate).is_none()
+    }
+
+    /// Returns why `candidate` does not satisfy this dependency, or `None` if it does.
+    pub fn rejection(&self, candidate: &EdoVersion) -> Option<String> {
+        if let Some(vendor) = self.vendor.as_ref()
+            && *vendor != candidate.vendor()
+        {
+            return Some(format!("dependency is pinned to vendor {vendor}"));
+        }
+        let version = candidate.version();
+        if !version.pre.is_empty() && !candidate.matches(&self.version) {
+            // semver only lets a pre-release satisfy a requirement that names a
+            // pre-release of the same version, so once the user opts in we match
+            // the pre-release as if it were its release.
+            if !self.prerelease && self.channel.as_deref().is_none_or(|c| c == STABLE_CHANNEL) {
+                return Some("pre-releases are not enabled".to_string());
+            }
+            let mut release = version.clone();
+            release.pre = Prerelease::EMPTY;
+            if !self.version.matches(&release) {
+                return Some(format!("does not satisfy {}", self.version));
+            }
+        } else if !candidate.matches(&self.version) {
+            return Some(format!("does not satisfy {}", self.version));
+        }
+        match self.channel.as_deref() {
+            Some(_) if version.pre.is_empty() => None,
+            Some(STABLE_CHANNEL) => Some("not on the stable channel".to_string()),
+            Some(channel) if version.pre.as_str().split('.').next() != Some(channel) => {
+                Some(format!("not on the {channel} channel"))
+            }
+            _ => None,
+        }
+    }
+
+    /// Describes the declared constraints, for use in resolution explanations.
+    pub fn constraints(&self) -> Vec<String> {
+        let mut constraints = vec![format!("at {}", self.version)];
+        if let Some(vendor) = self.vendor.as_ref() {
+            constraints.push(format!("vendor {vendor}"));
+        }
+        if self.prerelease {
+            constraints.push("pre-releases enabled".to_string());
+        }
+        if let Some(channel) = self.channel.as_ref() {
+            constraints.push(format!("channel {channel}"));
+        }
+        constraints
+    }
 }
 
 #[async_trait]
@@ -37,14 +120,15 @@ impl FromNode for Dependency {
         node.validate_keys(&["at"])?;
         let kind = node.get_kind().context(error::UndefinedSnafu)?;
         let name = node.get_name().context(error::UndefinedSnafu)?;
-        let version = node
-            .get("at")
-            .context(error::NoRequireSnafu)?
-            .as_require()
-            .context(error::FieldSnafu {
+        let at = node.get("at").context(error::NoRequireSnafu)?;
+        let version = if let Some(pin) = at.as_version() {
+            exact(&pin)
+        } else {
+            at.as_require().context(error::FieldSnafu {
                 field: "at",
                 type_: "version requirement",
-            })?;
+            })?
+        };
         let vendor = if let Some(value) = node.get("vendor") {
             Some(
                 value
@@ -58,12 +142,119 @@ impl FromNode for Dependency {
         } else {
             None
         };
+        let prerelease = if let Some(value) = node.get("prerelease") {
+            value.as_bool().context(error::FieldSnafu {
+                field: "prerelease",
+                type_: "boolean",
+            })?
+        } else {
+            false
+        };
+        let channel = if let Some(value) = node.get("channel") {
+            Some(value.as_string().context(error::FieldSnafu {
+                field: "channel",
+                type_: "string",
+            })?)
+        } else {
+            None
+        };
         Ok(Self {
             addr: addr.clone(),
             kind: kind.clone(),
             name: name.clone(),
             version: version.clone(),
             vendor,
+            prerelease,
+            channel,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dep(at: &str) -> Dependency {
+        Dependency {
+            addr: Addr::parse("//p/dep").unwrap(),
+            kind: "image".into(),
+            name: "dep".into(),
+            version: VersionReq::parse(at).unwrap(),
+            vendor: None,
+            prerelease: false,
+            channel: None,
+        }
+    }
+
+    fn candidate(vendor: &str, version: &str) -> EdoVersion {
+        EdoVersion::new(vendor, &Version::parse(version).unwrap())
+    }
+
+    #[test]
+    fn admits_release_matching_requirement() {
+        let d = dep("^1.2");
+        assert!(d.admits(&candidate("a", "1.4.0")));
+        assert!(!d.admits(&candidate("a", "2.0.0")));
+    }
+
+    #[test]
+    fn rejects_prerelease_without_opt_in() {
+        let d = dep("^1.2");
+        assert!(!d.admits(&candidate("a", "1.5.0-beta.1")));
+    }
+
+    #[test]
+    fn admits_prerelease_with_opt_in() {
+        let mut d = dep("^1.2");
+        d.prerelease = true;
+        assert!(d.admits(&candidate("a", "1.5.0-beta.1")));
+        assert!(!d.admits(&candidate("a", "2.0.0-beta.1")));
+    }
+
+    #[test]
+    fn stable_channel_excludes_prereleases() {
+        let mut d = dep("^1.2");
+        d.prerelease = true;
+        d.channel = Some(STABLE_CHANNEL.into());
+        assert!(!d.admits(&candidate("a", "1.5.0-beta.1")));
+        assert!(d.admits(&candidate("a", "1.5.0")));
+    }
+
+    #[test]
+    fn named_channel_admits_only_its_prereleases() {
+        let mut d = dep("^1.2");
+        d.channel = Some("beta".into());
+        assert!(d.admits(&candidate("a", "1.5.0-beta.1")));
+        assert!(!d.admits(&candidate("a", "1.5.0-alpha.1")));
+        assert!(d.admits(&candidate("a", "1.4.0")));
+    }
+
+    #[test]
+    fn exact_pin_matches_prerelease_version_only() {
+        let mut d = dep("*");
+        d.version = exact(&Version::parse("1.5.0-rc.1").unwrap());
+        assert!(d.admits(&candidate("a", "1.5.0-rc.1")));
+        assert!(!d.admits(&candidate("a", "1.5.0")));
+    }
+
+    #[test]
+    fn rejection_names_the_failing_constraint() {
+        let d = dep("^1.2");
+        assert_eq!(
+            d.rejection(&candidate("a", "2.0.0")).as_deref(),
+            Some("does not satisfy ^1.2")
+        );
+        assert_eq!(
+            d.rejection(&candidate("a", "1.5.0-beta.1")).as_deref(),
+            Some("pre-releases are not enabled")
+        );
+    }
+
+    #[test]
+    fn vendor_constraint_filters_candidates() {
+        let mut d = dep("^1");
+        d.vendor = Some("b".into());
+        assert!(!d.admits(&candidate("a", "1.0.0")));
+        assert!(d.admits(&candidate("b", "1.0.0")));
+    }
+}
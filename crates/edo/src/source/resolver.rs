@@ -12,6 +12,7 @@ use tokio::runtime::Handle;
 
 use crate::context::Addr;
 
+use super::explain::{Candidate, Explanation};
 use super::require::Dependency;
 use super::version::EdoVersion;
 use super::version::EdoVersionSet;
@@ -29,6 +30,7 @@ pub struct Resolver {
     pool: Arc<Pool<EdoVersionSet>>,
     name_to_vs: DashMap<NameId, Set>,
     vendors: DashMap<String, Vendor>,
+    priorities: DashMap<String, i64>,
 }
 
 #[derive(Clone)]
@@ -67,10 +69,13 @@ impl Resolver {
         let problem = Problem::new().requirements(requirements);
         let resolution = match solver.solve(problem) {
             Ok(result) => Ok(result),
-            Err(UnsolvableOrCancelled::Unsolvable(conflict)) => error::ResolutionSnafu {
-                reason: conflict.display_user_friendly(&solver).to_string(),
+            Err(UnsolvableOrCancelled::Unsolvable(conflict)) => {
+                let mut reason = conflict.display_user_friendly(&solver).to_string();
+                for entry in requires.iter() {
+                    reason.push_str(&format!("\n{}", self.explain(entry, None)));
+                }
+                error::ResolutionSnafu { reason }.fail()
             }
-            .fail(),
             Err(UnsolvableOrCancelled::Cancelled(_)) => error::ResolutionSnafu {
                 reason: "resolution was cancelled",
             }
@@ -128,15 +133,17 @@ impl Resolver {
             let vsid = self
                 .pool
                 .intern_version_set(name_id, EdoVersionSet::new(edo_versions.as_slice()));
-            if let Some(entry) = self.name_to_vs.get(&name_id) {
-                let union_id = match entry.value() {
+            // Clone the existing set out so the map guard is released before we insert
+            let existing = self.name_to_vs.get(&name_id).map(|x| x.value().clone());
+            if let Some(existing) = existing {
+                let union_id = match existing {
                     Set::Union(union_id) => {
-                        let vs_union = self.pool.resolve_version_set_union(*union_id);
+                        let vs_union = self.pool.resolve_version_set_union(union_id);
                         self.pool.intern_version_set_union(vsid, vs_union)
                     }
                     Set::Single(vs_id) => self
                         .pool
-                        .intern_version_set_union(vsid, [*vs_id].iter().cloned()),
+                        .intern_version_set_union(vsid, [vs_id].iter().cloned()),
                 };
                 self.name_to_vs.insert(name_id, Set::Union(union_id));
             } else {
@@ -151,61 +158,96 @@ impl Resolver {
         self.vendors.insert(name.to_string(), vendor);
     }
 
+    /// Set the priority of a registered vendor.
+    ///
+    /// When several vendors offer the same version of a package the one with
+    /// the highest priority is preferred. Vendors default to a priority of `0`.
+    pub fn set_vendor_priority(&mut self, name: &str, priority: i64) {
+        self.priorities.insert(name.to_string(), priority);
+    }
+
+    fn vendor_priority(&self, name: &str) -> i64 {
+        self.priorities.get(name).map(|x| *x.value()).unwrap_or(0)
+    }
+
+    /// Return every candidate version offered for `name` across all vendors.
+    fn candidates(&self, name: &str) -> Vec<EdoVersion> {
+        let Some(name_id) = self.pool.lookup_package_name(&name.to_string()) else {
+            return Vec::new();
+        };
+        let Some(entry) = self.name_to_vs.get(&name_id).map(|x| x.value().clone()) else {
+            return Vec::new();
+        };
+        let vs_ids = match entry {
+            Set::Union(union_id) => self.pool.resolve_version_set_union(union_id).collect(),
+            Set::Single(vs_id) => vec![vs_id],
+        };
+        let mut candidates = Vec::new();
+        for vs_id in vs_ids {
+            candidates.extend(self.pool.resolve_version_set(vs_id).get().iter().cloned());
+        }
+        candidates
+    }
+
+    /// Order two candidates by resolver preference: newest version first, then
+    /// the highest priority vendor, then the vendor name so the outcome never
+    /// depends on registration order.
+    fn prefer(&self, left: &EdoVersion, right: &EdoVersion) -> std::cmp::Ordering {
+        right
+            .version()
+            .cmp(&left.version())
+            .then_with(|| {
+                self.vendor_priority(&right.vendor())
+                    .cmp(&self.vendor_priority(&left.vendor()))
+            })
+            .then_with(|| left.vendor().cmp(&right.vendor()))
+    }
+
+    /// Explain how `dep` is resolved against the candidates known to this resolver.
+    ///
+    /// `selected` is the (vendor, version) the solver picked, if any; every
+    /// candidate is listed in preference order together with the constraint
+    /// that rejected it.
+    pub fn explain(&self, dep: &Dependency, selected: Option<(String, Version)>) -> Explanation {
+        let mut candidates = self.candidates(&dep.name);
+        candidates.sort_by(|x, y| self.prefer(x, y));
+        Explanation {
+            addr: dep.addr.clone(),
+            name: dep.name.clone(),
+            constraints: dep.constraints(),
+            candidates: candidates
+                .iter()
+                .map(|x| Candidate {
+                    vendor: x.vendor(),
+                    version: x.version(),
+                    priority: self.vendor_priority(&x.vendor()),
+                    rejected: dep.rejection(x),
+                })
+                .collect(),
+            selected,
+        }
+    }
+
     /// Build a [`Requirement`] from a [`Dependency`] node against the current pool state.
     pub fn build_requirement(&self, node: &Dependency) -> Result<Requirement> {
-        let dep_id = if let Some(name_id) = self.pool.lookup_package_name(&node.name) {
-            name_id
-        } else {
-            return error::RequirementSnafu {
-                name: node.name.clone(),
-                version: node.version.clone(),
-            }
-            .fail();
-        };
-        let mut matches = Vec::new();
-        let require = node.version.clone();
-        if let Some(entry) = self.name_to_vs.get(&dep_id) {
-            match entry.value() {
-                Set::Union(union_id) => {
-                    let union = self.pool.resolve_version_set_union(*union_id);
-                    for vs_id in union {
-                        let version_set = self.pool.resolve_version_set(vs_id);
-                        for version in version_set.get() {
-                            let mut flag = version.matches(&require);
-                            if let Some(vendor) = node.vendor.as_ref() {
-                                flag &= *vendor == version.vendor();
-                            }
-                            if flag {
-                                matches.push(version.clone());
-                            }
-                        }
-                    }
-                }
-                Set::Single(vs_id) => {
-                    let version_set = self.pool.resolve_version_set(*vs_id);
-                    for version in version_set.get() {
-                        let mut flag = version.matches(&require);
-                        if let Some(vendor) = node.vendor.as_ref() {
-                            flag &= *vendor == version.vendor();
-                        }
-                        if flag {
-                            matches.push(version.clone());
-                        }
-                    }
-                }
+        let matches: Vec<EdoVersion> = self
+            .candidates(&node.name)
+            .into_iter()
+            .filter(|x| node.admits(x))
+            .collect();
+        match self.pool.lookup_package_name(&node.name) {
+            Some(dep_id) if !matches.is_empty() => {
+                let vs_id = self
+                    .pool
+                    .intern_version_set(dep_id, EdoVersionSet::new(matches.as_slice()));
+                Ok(Requirement::Single(vs_id))
             }
-        }
-        if !matches.is_empty() {
-            let vs_id = self
-                .pool
-                .intern_version_set(dep_id, EdoVersionSet::new(matches.as_slice()));
-            Ok(Requirement::Single(vs_id))
-        } else {
-            error::RequirementSnafu {
+            _ => error::RequirementSnafu {
                 name: node.name.clone(),
                 version: node.version.clone(),
+                explanation: self.explain(node, None).to_string(),
             }
-            .fail()
+            .fail(),
         }
     }
 }
@@ -308,10 +350,14 @@ impl DependencyProvider for Resolver {
         _solver: &resolvo::SolverCache<Self>,
         solvables: &mut [SolvableId],
     ) {
+        // The solver tries candidates in order, so the newest version comes
+        // first, then the highest priority vendor, then the vendor name so the
+        // outcome never depends on registration order.
         solvables.sort_by(|x, y| {
-            let left = self.pool.resolve_solvable(*x);
-            let right = self.pool.resolve_solvable(*y);
-            left.record.version().cmp(&right.record.version())
+            self.prefer(
+                &self.pool.resolve_solvable(*x).record,
+                &self.pool.resolve_solvable(*y).record,
+            )
         });
     }
 
@@ -374,3 +420,127 @@ impl DependencyProvider for Resolver {
         dependencies
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Node;
+    use crate::source::{SourceResult, VendorImpl};
+    use async_trait::async_trait;
+    use semver::VersionReq;
+
+    /// Vendor offering a fixed list of versions for every package name.
+    struct MockVendor(Vec<&'static str>);
+
+    #[async_trait]
+    impl VendorImpl for MockVendor {
+        async fn get_options(&self, _name: &str) -> SourceResult<HashSet<Version>> {
+            Ok(self.0.iter().map(|v| Version::parse(v).unwrap()).collect())
+        }
+
+        async fn resolve(&self, _name: &str, _version: &Version) -> SourceResult<Node> {
+            unimplemented!()
+        }
+
+        async fn get_dependencies(
+            &self,
+            _name: &str,
+            _version: &Version,
+        ) -> SourceResult<Option<HashMap<String, semver::VersionReq>>> {
+            Ok(None)
+        }
+    }
+
+    fn dep(at: &str) -> Dependency {
+        Dependency {
+            addr: Addr::parse("//p/dep").unwrap(),
+            kind: "image".into(),
+            name: "dep".into(),
+            version: VersionReq::parse(at).unwrap(),
+            vendor: None,
+            prerelease: false,
+            channel: None,
+        }
+    }
+
+    async fn solve(resolver: Resolver, dep: Dependency) -> (String, Version) {
+        resolver.build_db(&dep.name).await.unwrap();
+        let addr = dep.addr.clone();
+        let found = tokio::task::spawn_blocking(move || resolver.resolve(vec![dep]))
+            .await
+            .unwrap()
+            .unwrap();
+        let (vendor, _, version) = found.get(&addr).unwrap().clone();
+        (vendor, version)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn prefers_highest_matching_version() {
+        let mut resolver = Resolver::default();
+        resolver.add_vendor(
+            "a",
+            Vendor::new(MockVendor(vec!["1.0.0", "1.2.0", "2.0.0"])),
+        );
+        let (_, version) = solve(resolver, dep("^1")).await;
+        assert_eq!(version, Version::new(1, 2, 0));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn skips_prereleases_unless_opted_in() {
+        let mut resolver = Resolver::default();
+        resolver.add_vendor("a", Vendor::new(MockVendor(vec!["1.0.0", "1.1.0-beta.1"])));
+        let (_, version) = solve(resolver, dep("^1")).await;
+        assert_eq!(version, Version::new(1, 0, 0));
+
+        let mut resolver = Resolver::default();
+        resolver.add_vendor("a", Vendor::new(MockVendor(vec!["1.0.0", "1.1.0-beta.1"])));
+        let mut opted = dep("^1");
+        opted.prerelease = true;
+        let (_, version) = solve(resolver, opted).await;
+        assert_eq!(version, Version::parse("1.1.0-beta.1").unwrap());
+    }
+
+    fn two_vendors() -> Resolver {
+        let mut resolver = Resolver::default();
+        resolver.add_vendor("a", Vendor::new(MockVendor(vec!["1.0.0"])));
+        resolver.add_vendor("b", Vendor::new(MockVendor(vec!["1.0.0"])));
+        resolver
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn vendor_name_breaks_ties_by_default() {
+        let (vendor, _) = solve(two_vendors(), dep("^1")).await;
+        assert_eq!(vendor, "a");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn vendor_priority_breaks_ties() {
+        let mut resolver = two_vendors();
+        resolver.set_vendor_priority("b", 10);
+        let (vendor, _) = solve(resolver, dep("^1")).await;
+        assert_eq!(vendor, "b");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn explain_reports_rejections_and_selection() {
+        let mut resolver = Resolver::default();
+        resolver.add_vendor("a", Vendor::new(MockVendor(vec!["1.0.0", "2.0.0"])));
+        resolver.build_db("dep").await.unwrap();
+        let explanation = resolver.explain(&dep("^1"), Some(("a".into(), Version::new(1, 0, 0))));
+        assert_eq!(explanation.candidates.len(), 2);
+        assert_eq!(
+            explanation.candidates[0].rejected.as_deref(),
+            Some("does not satisfy ^1")
+        );
+        assert!(explanation.to_string().contains("* 1.0.0 from a"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn unmatched_requirement_error_includes_explanation() {
+        let mut resolver = Resolver::default();
+        resolver.add_vendor("a", Vendor::new(MockVendor(vec!["2.0.0"])));
+        resolver.build_db("dep").await.unwrap();
+        let err = resolver.build_requirement(&dep("^1")).unwrap_err();
+        assert!(err.to_string().contains("2.0.0 from a"), "got: {err}");
+    }
+}
@@ -1,5 +1,5 @@
 use super::SourceResult;
-use crate::context::Node;
+use crate::context::{HealthCheck, Node};
 use arc_handle::arc_handle;
 use async_trait::async_trait;
 use semver::{Version, VersionReq};
@@ -23,4 +23,9 @@ pub trait Vendor {
         name: &str,
         version: &Version,
     ) -> SourceResult<Option<HashMap<String, VersionReq>>>;
+    /// Checks that the registry behind this vendor can be reached, for
+    /// `edo doctor`.
+    async fn health(&self) -> Vec<HealthCheck> {
+        Vec::new()
+    }
 }
@@ -1,10 +1,10 @@
-use super::{StorageResult, error, id::Id};
+use super::{DigestAlgorithm, LayerEncryption, StorageResult, error, id::Id};
 use bon::Builder;
 use ocilot::models::Platform;
 use regex::Regex;
 use semver::VersionReq;
 use serde::{Deserialize, Serialize};
-use snafu::ResultExt;
+use snafu::{ResultExt, ensure};
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::str::FromStr;
@@ -66,6 +66,23 @@ impl Compression {
     }
 }
 
+impl FromStr for Compression {
+    type Err = error::StorageError;
+
+    /// Parse a compression algorithm by name, as written in backend configuration.
+    fn from_str(s: &str) -> StorageResult<Self> {
+        match s {
+            "zstd" | "zst" => Ok(Self::Zstd),
+            "gzip" | "gz" => Ok(Self::Gzip),
+            "bzip2" | "bz2" => Ok(Self::Bzip2),
+            "lzma" | "lz4" => Ok(Self::Lz),
+            "xz" => Ok(Self::Xz),
+            "none" => Ok(Self::None),
+            value => error::UnknownCompressionSnafu { value }.fail(),
+        }
+    }
+}
+
 impl fmt::Display for Compression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str(match self {
@@ -106,6 +123,35 @@ impl MediaType {
         }
     }
 
+    /// Returns the compression applied to this media type.
+    pub fn compression(&self) -> Compression {
+        match self {
+            Self::Manifest => Compression::None,
+            Self::File(comp)
+            | Self::Tar(comp)
+            | Self::Oci(comp)
+            | Self::Image(comp)
+            | Self::Zip(comp)
+            | Self::Custom(_, comp) => comp.clone(),
+        }
+    }
+
+    /// Parse a layer media type written without its `vnd.edo.artifact.v1.`
+    /// prefix, such as `tar`, `tar+gz` or `file`, as build files do. Full
+    /// media types are accepted as well.
+    pub fn from_short_name(name: &str) -> StorageResult<Self> {
+        let media_type = if name.starts_with("vnd.edo.artifact") {
+            name.parse()?
+        } else {
+            format!("vnd.edo.artifact.{ARTIFACT_SCHEMA_VERSION}.{name}").parse()?
+        };
+        ensure!(
+            media_type != Self::Manifest,
+            error::InvalidMediaTypeSnafu { value: name }
+        );
+        Ok(media_type)
+    }
+
     /// Override the compression variant for this media type.
     pub fn set_compression(&mut self, compression: Compression) {
         match self {
@@ -238,20 +284,51 @@ impl Config {
     handle!(provides, provides_mut, provides, BTreeSet<String>);
 }
 
-/// A BLAKE3 content digest identifying a layer's blob.
-#[derive(Debug, Clone)]
-pub struct LayerDigest(String);
+/// A content digest identifying a layer's blob.
+///
+/// Serialized as `<algorithm>:<hex>`. Digests without a known algorithm
+/// prefix are BLAKE3.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LayerDigest {
+    algorithm: DigestAlgorithm,
+    hex: String,
+}
 
 impl LayerDigest {
-    /// Return the raw hex digest string (without the `blake3:` prefix).
+    /// Create a digest from a hex string computed with `algorithm`.
+    pub fn new(algorithm: DigestAlgorithm, hex: impl Into<String>) -> Self {
+        Self {
+            algorithm,
+            hex: hex.into(),
+        }
+    }
+
+    /// Return the raw hex digest string (without the `<algorithm>:` prefix).
     pub fn digest(&self) -> String {
-        self.0.clone()
+        self.hex.clone()
+    }
+
+    /// Return the algorithm the digest was computed with.
+    pub fn algorithm(&self) -> DigestAlgorithm {
+        self.algorithm
+    }
+
+    /// The blob's path relative to a backend's blob root, `<algorithm>/<hex>`.
+    pub fn path(&self) -> String {
+        format!("{}/{}", self.algorithm, self.hex)
+    }
+}
+
+impl fmt::Display for LayerDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.hex)
     }
 }
 
 impl<'a> From<&'a str> for LayerDigest {
     fn from(value: &'a str) -> Self {
-        Self(value.strip_prefix("blake3:").unwrap_or(value).to_string())
+        let (algorithm, hex) = DigestAlgorithm::split(value);
+        Self::new(algorithm, hex)
     }
 }
 
@@ -266,7 +343,7 @@ impl Serialize for LayerDigest {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&format!("blake3:{}", self.0))
+        serializer.serialize_str(&self.to_string())
     }
 }
 
@@ -276,20 +353,21 @@ impl<'de> Deserialize<'de> for LayerDigest {
         D: serde::Deserializer<'de>,
     {
         let str = String::deserialize(deserializer)?;
-        if str.starts_with("blake3:") {
-            Ok(Self(str.strip_prefix("blake3:").unwrap().to_string()))
-        } else {
-            Err(serde::de::Error::custom(
-                "not a valid artifact layer digest",
-            ))
-        }
+        let (name, hex) = str
+            .split_once(':')
+            .ok_or_else(|| serde::de::Error::custom("not a valid artifact layer digest"))?;
+        let algorithm = name.parse().map_err(serde::de::Error::custom)?;
+        Ok(Self::new(algorithm, hex))
     }
 }
 
 /// A single content-addressed blob within an [`Artifact`].
 ///
-/// Each layer has a media type describing its content format, a BLAKE3 digest,
-/// a byte size, and an optional platform constraint.
+/// Each layer has a media type describing its content format, a content digest,
+/// a byte size, and an optional platform constraint. Layers that
+/// [`Storage`](super::Storage) compressed on upload also record the digest of
+/// the uncompressed blob they were produced from, and layers a backend
+/// encrypted record how in [`LayerEncryption`].
 #[derive(Serialize, Deserialize, Debug, Clone, Builder)]
 pub struct Layer {
     #[builder(into)]
@@ -300,6 +378,12 @@ pub struct Layer {
     size: usize,
     #[builder(into)]
     platform: Option<Platform>,
+    #[builder(into)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uncompressed: Option<LayerDigest>,
+    #[builder(into)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encryption: Option<LayerEncryption>,
 }
 
 impl Layer {
@@ -307,6 +391,18 @@ impl Layer {
     handle!(digest, digest_mut, digest, LayerDigest);
     handle!(size, size_mut, size, usize);
     handle!(platform, platform_mut, platform, Option<Platform>);
+    handle!(
+        uncompressed,
+        uncompressed_mut,
+        uncompressed,
+        Option<LayerDigest>
+    );
+    handle!(
+        encryption,
+        encryption_mut,
+        encryption,
+        Option<LayerEncryption>
+    );
 }
 
 /// An artifact is used to store any data in-flight or final. All artifacts are stored and represented
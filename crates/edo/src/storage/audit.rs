@@ -0,0 +1,141 @@
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+/// How edo reached out to an external location.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessKind {
+    /// A source was fetched from its origin.
+    Fetch,
+    /// A registry or index was queried while resolving dependencies.
+    Query,
+    /// An artifact was downloaded from a remote cache.
+    Download,
+    /// An artifact was uploaded to a remote cache.
+    Upload,
+}
+
+impl fmt::Display for AccessKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Fetch => "fetch",
+            Self::Query => "query",
+            Self::Download => "download",
+            Self::Upload => "upload",
+        })
+    }
+}
+
+/// A single external access.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Access {
+    /// When the access completed.
+    pub time: DateTime<Utc>,
+    /// What kind of access it was.
+    pub kind: AccessKind,
+    /// The location accessed, e.g. a source url, registry reference or `s3://` bucket.
+    pub url: String,
+    /// The artifact id or reference the access was for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// Digests of the content transferred.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub digests: Vec<String>,
+}
+
+impl Access {
+    /// Describes an access of `kind` to `url` happening now.
+    pub fn new(kind: AccessKind, url: impl Into<String>) -> Self {
+        Self {
+            time: Utc::now(),
+            kind,
+            url: url.into(),
+            subject: None,
+            digests: Vec::new(),
+        }
+    }
+
+    /// Names the artifact or reference the access was for.
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    /// Adds the digest of content that was transferred.
+    pub fn digest(mut self, digest: impl Into<String>) -> Self {
+        self.digests.push(digest.into());
+        self
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.time.to_rfc3339(), self.kind, self.url)?;
+        if let Some(subject) = self.subject.as_ref() {
+            write!(f, " ({subject})")?;
+        }
+        for digest in self.digests.iter() {
+            write!(f, " {digest}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The external accesses made through a [`Storage`](super::Storage).
+///
+/// Clones share the same record. Remote cache transfers are recorded by
+/// [`Storage`](super::Storage) itself; sources and vendors record what they
+/// fetch from their origin with [`Audit::record`].
+#[derive(Clone, Default)]
+pub struct Audit {
+    accesses: Arc<Mutex<Vec<Access>>>,
+}
+
+impl Audit {
+    /// Records an access.
+    pub fn record(&self, access: Access) {
+        trace!(component = "storage", "audit: {access}");
+        self.accesses.lock().push(access);
+    }
+
+    /// Returns the accesses recorded so far, oldest first.
+    pub fn accesses(&self) -> Vec<Access> {
+        self.accesses.lock().clone()
+    }
+
+    /// Removes and returns the accesses recorded so far, oldest first.
+    pub fn take(&self) -> Vec<Access> {
+        std::mem::take(&mut *self.accesses.lock())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_the_record() {
+        let audit = Audit::default();
+        audit.clone().record(
+            Access::new(AccessKind::Fetch, "https://example.com/a.tar.gz")
+                .subject("a-123")
+                .digest("abc"),
+        );
+        let accesses = audit.take();
+        assert_eq!(accesses.len(), 1);
+        assert_eq!(accesses[0].digests, vec!["abc".to_string()]);
+        assert!(audit.accesses().is_empty());
+    }
+
+    #[test]
+    fn accesses_serialize_without_empty_fields() {
+        let access = Access::new(AccessKind::Query, "public.ecr.aws/docker/library");
+        let json = serde_json::to_value(&access).unwrap();
+        assert_eq!(json["kind"], "query");
+        assert!(json.get("subject").is_none());
+        assert!(json.get("digests").is_none());
+    }
+}
@@ -3,11 +3,14 @@ use std::collections::BTreeSet;
 use arc_handle::arc_handle;
 use async_trait::async_trait;
 use ocilot::models::Platform;
+use snafu::{OptionExt, ResultExt};
+use tokio::io::AsyncReadExt;
 
+use crate::context::{FieldSchema, FieldType, HealthCheck, Node};
 use crate::util::{Reader, Writer};
 
-use super::StorageResult;
-use super::artifact::MediaType;
+use super::artifact::{Compression, MediaType};
+use super::{DigestAlgorithm, Generation, StorageResult, TemporaryBlob, TransferPolicy, error};
 use super::{
     artifact::{Artifact, Layer},
     id::Id,
@@ -25,20 +28,93 @@ pub trait Backend {
     async fn list(&self) -> StorageResult<BTreeSet<Id>>;
     /// Check if the backend has an artifact by this name
     async fn has(&self, id: &Id) -> StorageResult<bool>;
+    /// Check which of `ids` the backend has artifacts for
+    ///
+    /// The default asks [`has`](Self::has) for each id, backends that can
+    /// answer from one look at their catalog should override it.
+    async fn has_many(&self, ids: &[Id]) -> StorageResult<BTreeSet<Id>> {
+        let mut found = BTreeSet::new();
+        for id in ids {
+            if self.has(id).await? {
+                found.insert(id.clone());
+            }
+        }
+        Ok(found)
+    }
     /// Open an artifact's manifest into memory
     async fn open(&self, id: &Id) -> StorageResult<Artifact>;
+    /// Check that the blob of every layer of `artifact` is stored, so its
+    /// manifest can be trusted
+    ///
+    /// The default looks the layers up in [`blobs`](Self::blobs) and trusts
+    /// the manifest of backends that cannot list their blobs.
+    async fn has_layers(&self, artifact: &Artifact) -> StorageResult<bool> {
+        let Some(blobs) = self.blobs().await? else {
+            return Ok(true);
+        };
+        Ok(artifact
+            .layers()
+            .iter()
+            .all(|x| blobs.contains(&x.digest().to_string())))
+    }
     /// Save an artifact's manifest
     async fn save(&self, artifact: &Artifact) -> StorageResult<()>;
     /// Delete this artifact and all its layers from the backend
     async fn del(&self, id: &Id) -> StorageResult<()>;
     /// Copy an artifact to a new id
     async fn copy(&self, from: &Id, to: &Id) -> StorageResult<()>;
-    /// Prune any other artifact with a different digest from the backend
+    /// Prune the other artifacts with the prefix of `id` and a different
+    /// digest, keeping the most recent generations the backend is set to keep
     async fn prune(&self, id: &Id) -> StorageResult<()>;
     /// Prune any duplicate artifacts from the backend
     async fn prune_all(&self) -> StorageResult<()>;
+    /// Forget what the backend cached of its catalog, so the next lookups
+    /// see artifacts other processes saved since
+    ///
+    /// Backends that read their catalog afresh every time need not override it.
+    async fn refresh(&self) -> StorageResult<()> {
+        Ok(())
+    }
+    /// List the generations of the artifacts sharing `prefix`, newest first
+    ///
+    /// The default lists every matching id in id order, for backends that do
+    /// not record when artifacts were saved.
+    async fn history(&self, prefix: &str) -> StorageResult<Vec<Generation>> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|x| x.prefix() == prefix)
+            .map(|id| Generation { id, saved: None })
+            .collect())
+    }
+    /// Point the alias `tag` at the artifact `id`, replacing what it pointed
+    /// at before
+    ///
+    /// Aliases are how [`Storage::publish`](super::Storage::publish) names
+    /// artifacts in the output cache. Backends without a catalog to record
+    /// them in do not support it.
+    async fn tag(&self, _tag: &str, _id: &Id) -> StorageResult<()> {
+        error::UnsupportedSnafu { operation: "tags" }.fail()
+    }
+    /// Return the id the alias `tag` points at, if any
+    async fn resolve_tag(&self, _tag: &str) -> StorageResult<Option<Id>> {
+        Ok(None)
+    }
     /// Open a reader to a layer
     async fn read(&self, layer: &Layer) -> StorageResult<Reader>;
+    /// Open a reader to a layer starting `offset` bytes in, used to resume interrupted transfers
+    ///
+    /// The default reads and discards the first `offset` bytes; backends that
+    /// can seek or issue range requests should override it.
+    async fn read_from(&self, layer: &Layer, offset: u64) -> StorageResult<Reader> {
+        let mut reader = self.read(layer).await?;
+        let mut skipped = (&mut reader).take(offset);
+        tokio::io::copy(&mut skipped, &mut tokio::io::sink())
+            .await
+            .context(error::IoSnafu)?;
+        Ok(reader)
+    }
     /// Creates a new layer writer for an artifact
     async fn start_layer(&self) -> StorageResult<Writer>;
     /// Saves and adds a layer to an artifact
@@ -48,4 +124,166 @@ pub trait Backend {
         platform: Option<Platform>,
         writer: &Writer,
     ) -> StorageResult<Layer>;
+    /// Discards a layer started with [`start_layer`](Self::start_layer) that
+    /// will not be finished, removing its temporary blob
+    ///
+    /// Backends that keep nothing for unfinished layers need not override it.
+    async fn abort_layer(&self, writer: &Writer) -> StorageResult<()> {
+        self.remove_temporary(&writer.target()).await
+    }
+    /// List the temporary blobs of layers started and not finished yet, or
+    /// never finished
+    ///
+    /// Used by [`Storage::sweep_stale`](super::Storage::sweep_stale) to
+    /// clean up after processes that died mid-write. Backends that keep
+    /// nothing for unfinished layers return `None`.
+    async fn temporary_blobs(&self) -> StorageResult<Option<Vec<TemporaryBlob>>> {
+        Ok(None)
+    }
+    /// Delete the temporary blob a writer named `target` fills
+    async fn remove_temporary(&self, _target: &str) -> StorageResult<()> {
+        Ok(())
+    }
+    /// List the digests of every blob stored, referenced or not
+    ///
+    /// Used by [`fsck`](super::fsck) to find orphaned blobs. Backends that
+    /// cannot enumerate their blobs return `None`.
+    async fn blobs(&self) -> StorageResult<Option<BTreeSet<String>>> {
+        Ok(None)
+    }
+    /// Delete a single blob, whether or not a manifest references it
+    async fn remove_blob(&self, _digest: &str) -> StorageResult<()> {
+        error::UnsupportedSnafu {
+            operation: "remove_blob",
+        }
+        .fail()
+    }
+    /// The compression this backend wants uncompressed tar layers stored with
+    ///
+    /// [`Storage`](super::Storage) compresses such layers on upload and
+    /// restores the raw layer on download. `None` stores layers as they are.
+    fn compression(&self) -> Option<Compression> {
+        None
+    }
+    /// The bandwidth, concurrency and retry limits for copying layers to or from this backend
+    fn transfer_policy(&self) -> TransferPolicy {
+        TransferPolicy::default()
+    }
+    /// The algorithm new layers written to this backend are addressed by
+    ///
+    /// [`Storage`](super::Storage) keeps the algorithm of layers it copies
+    /// between backends, so this only applies to layers created here.
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        DigestAlgorithm::default()
+    }
+    /// Whether layers read from this backend are re-hashed as they stream
+    ///
+    /// [`Storage`](super::Storage) then fails a read whose bytes do not match
+    /// the layer digest instead of passing silently corrupted data on.
+    fn verify_reads(&self) -> bool {
+        false
+    }
+    /// Where this backend keeps its artifacts, if that is outside this machine
+    ///
+    /// Transfers to and from backends with a location are recorded in the
+    /// [`Audit`](super::Audit) of the [`Storage`](super::Storage).
+    fn location(&self) -> Option<String> {
+        None
+    }
+    /// Whether sources fetched from upstream are uploaded to this source cache
+    ///
+    /// See [`ProxyBackend`](super::ProxyBackend).
+    fn read_through(&self) -> bool {
+        false
+    }
+    /// Checks that this backend can be reached and written to, for `edo doctor`
+    ///
+    /// Backends without anything worth checking return no checks.
+    async fn health(&self) -> Vec<HealthCheck> {
+        Vec::new()
+    }
+}
+
+/// Previous generations of an artifact pruning keeps by default.
+pub const DEFAULT_HISTORY: usize = 1;
+
+/// Describes the transfer, `verify`, `digest` and `history` keys a cache definition may
+/// set, for backends reading them.
+pub fn cache_settings() -> Vec<FieldSchema> {
+    vec![
+        FieldSchema::new(
+            "bandwidth",
+            FieldType::one_of([FieldType::Integer, FieldType::String]),
+            "Bytes per second transfers may use, e.g. 5242880 or \"5MiB\"",
+        ),
+        FieldSchema::new(
+            "concurrency",
+            FieldType::Integer,
+            "Layers transferred at once",
+        ),
+        FieldSchema::new(
+            "retries",
+            FieldType::Integer,
+            "Times a failed transfer is retried",
+        ),
+        FieldSchema::new(
+            "retry_delay_ms",
+            FieldType::Integer,
+            "Milliseconds to wait before the first retry",
+        ),
+        FieldSchema::new(
+            "verify",
+            FieldType::Boolean,
+            "Check the digest of every layer read",
+        ),
+        FieldSchema::new(
+            "digest",
+            FieldType::choice(["blake3", "sha256", "sha512"]),
+            "Algorithm layers are addressed by",
+        ),
+        FieldSchema::new(
+            "history",
+            FieldType::Integer,
+            "Previous generations of an artifact kept when pruning",
+        ),
+    ]
+}
+
+/// Reads the optional `digest` key of a cache definition, falling back to `default`.
+pub fn digest_setting(node: &Node, default: DigestAlgorithm) -> StorageResult<DigestAlgorithm> {
+    match node.get("digest") {
+        Some(value) => value
+            .as_string()
+            .context(error::SettingSnafu {
+                key: "digest",
+                reason: "expected 'blake3', 'sha256' or 'sha512'",
+            })?
+            .parse(),
+        None => Ok(default),
+    }
+}
+
+/// Reads the optional `history` key of a cache definition, falling back to `default`.
+pub fn history_setting(node: &Node, default: usize) -> StorageResult<usize> {
+    match node.get("history") {
+        Some(value) => value
+            .as_int()
+            .and_then(|x| usize::try_from(x).ok())
+            .context(error::SettingSnafu {
+                key: "history",
+                reason: "expected a number of generations",
+            }),
+        None => Ok(default),
+    }
+}
+
+/// Reads the optional `verify` key of a cache definition, falling back to `default`.
+pub fn verify_setting(node: &Node, default: bool) -> StorageResult<bool> {
+    match node.get("verify") {
+        Some(value) => value.as_bool().context(error::SettingSnafu {
+            key: "verify",
+            reason: "expected true or false",
+        }),
+        None => Ok(default),
+    }
 }
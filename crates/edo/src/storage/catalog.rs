@@ -1,22 +1,105 @@
 use std::collections::{BTreeMap, BTreeSet};
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use snafu::{ResultExt, ensure};
 
-use crate::storage::{Artifact, Id, Layer};
+use crate::storage::{Artifact, Id, Layer, LayerDigest, StorageResult, error};
+use crate::util::{SchemaExtra, schema_version};
+
+/// Schema version of the catalogs this edo writes.
+///
+/// Version 2 keys blob reference counts by `<algorithm>:<hex>`, version 1
+/// catalogs used the bare BLAKE3 hex.
+pub const CATALOG_VERSION: u64 = 2;
 
 /// In-memory index of stored artifacts and their reference-counted blobs.
 ///
 /// Tracks manifests by [`Id`], groups them by prefix for pruning, and
 /// maintains per-digest reference counts so blobs can be safely deleted
-/// when no manifest references them.
-#[derive(Deserialize, Serialize, Default)]
+/// when no manifest references them. The ids sharing a prefix are kept in
+/// the order they were saved, so earlier generations of an artifact can be
+/// listed and checked out after a newer build regresses.
+///
+/// Catalogs are read with [`Catalog::from_slice`], which migrates those an
+/// older edo wrote and refuses those written by a newer one.
+#[derive(Deserialize, Serialize)]
 pub struct Catalog {
+    #[serde(default)]
+    version: u64,
     catalog: BTreeMap<String, BTreeSet<Id>>,
     manifests: BTreeMap<Id, Artifact>,
     blob_counts: BTreeMap<String, i64>,
+    // Oldest first, missing from catalogs written before generations were kept
+    #[serde(default)]
+    generations: BTreeMap<String, Vec<Generation>>,
+    // Aliases published with the artifacts, missing from older catalogs
+    #[serde(default)]
+    tags: BTreeMap<String, Id>,
+    // Fields a newer edo added without bumping the version, kept as they are
+    #[serde(flatten)]
+    extra: SchemaExtra,
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Self {
+            version: CATALOG_VERSION,
+            catalog: BTreeMap::new(),
+            manifests: BTreeMap::new(),
+            blob_counts: BTreeMap::new(),
+            generations: BTreeMap::new(),
+            tags: BTreeMap::new(),
+            extra: SchemaExtra::new(),
+        }
+    }
+}
+
+/// One saved generation of the artifacts sharing an [`Id::prefix`].
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Generation {
+    /// Id of the artifact.
+    pub id: Id,
+    /// When it was saved, `None` for artifacts saved before it was recorded.
+    pub saved: Option<DateTime<Utc>>,
+}
+
+/// Prefixes the blob reference count keys of a version 1 catalog with
+/// `blake3:`.
+fn migrate_blob_counts(catalog: &mut Value) {
+    if let Some(counts) = catalog
+        .get_mut("blob_counts")
+        .and_then(|x| x.as_object_mut())
+    {
+        *counts = std::mem::take(counts)
+            .into_iter()
+            .map(|(digest, count)| (LayerDigest::from(digest).to_string(), count))
+            .collect();
+    }
 }
 
 impl Catalog {
+    /// Parses a catalog as stored by a backend, migrating catalogs an older
+    /// edo wrote to [`CATALOG_VERSION`].
+    pub fn from_slice(bytes: &[u8]) -> StorageResult<Self> {
+        let mut document: Value = serde_json::from_slice(bytes).context(error::CatalogSnafu)?;
+        let version = schema_version(&document);
+        ensure!(
+            version <= CATALOG_VERSION,
+            error::CatalogVersionSnafu {
+                version,
+                supported: CATALOG_VERSION,
+            }
+        );
+        if version < 2 {
+            migrate_blob_counts(&mut document);
+        }
+        let mut catalog: Self = serde_json::from_value(document).context(error::CatalogSnafu)?;
+        catalog.version = CATALOG_VERSION;
+        Ok(catalog)
+    }
+
     /// List all artifact IDs stored in the catalog.
     pub fn list_all(&self) -> BTreeSet<Id> {
         self.catalog.get("*").cloned().unwrap_or_default()
@@ -37,6 +120,55 @@ impl Catalog {
         self.catalog.get(&id.prefix()).cloned().unwrap_or_default()
     }
 
+    /// Return the generations stored under `prefix`, newest first. Artifacts
+    /// saved before generations were recorded come last, in id order.
+    pub fn history(&self, prefix: &str) -> Vec<Generation> {
+        let mut history: Vec<Generation> = self
+            .generations
+            .get(prefix)
+            .map(|x| x.iter().rev().cloned().collect())
+            .unwrap_or_default();
+        for id in self.catalog.get(prefix).into_iter().flatten() {
+            if !history.iter().any(|x| x.id == *id) {
+                history.push(Generation {
+                    id: id.clone(),
+                    saved: None,
+                });
+            }
+        }
+        history
+    }
+
+    /// Return the ids sharing the prefix of `id` that pruning removes: every
+    /// other generation but the `keep` newest.
+    pub fn stale(&self, id: &Id, keep: usize) -> Vec<Id> {
+        self.history(&id.prefix())
+            .into_iter()
+            .map(|x| x.id)
+            .filter(|x| x != id)
+            .skip(keep)
+            .collect()
+    }
+
+    /// Point the alias `tag` at `id`, replacing what it pointed at before.
+    pub fn tag(&mut self, tag: &str, id: &Id) {
+        self.tags.insert(tag.to_string(), id.clone());
+    }
+
+    /// Return the id the alias `tag` points at, if the catalog still holds it.
+    pub fn resolve(&self, tag: &str) -> Option<&Id> {
+        self.tags.get(tag).filter(|x| self.has(x))
+    }
+
+    /// Return every alias pointing at an artifact the catalog holds.
+    pub fn tags(&self) -> BTreeMap<String, Id> {
+        self.tags
+            .iter()
+            .filter(|(_, id)| self.has(id))
+            .map(|(tag, id)| (tag.clone(), id.clone()))
+            .collect()
+    }
+
     /// Insert an artifact into the catalog, updating prefix indexes and blob counts.
     pub fn add(&mut self, artifact: &Artifact) {
         let id = artifact.config().id();
@@ -48,16 +180,23 @@ impl Catalog {
             .entry(id.prefix())
             .or_default()
             .insert(id.clone());
+        // Saving an id again makes it the newest generation
+        let generations = self.generations.entry(id.prefix()).or_default();
+        generations.retain(|x| x.id != *id);
+        generations.push(Generation {
+            id: id.clone(),
+            saved: Some(Utc::now()),
+        });
         self.manifests.insert(id.clone(), artifact.clone());
         for layer in artifact.layers() {
-            let digest = layer.digest().digest();
+            let digest = layer.digest().to_string();
             *self.blob_counts.entry(digest).or_default() += 1;
         }
     }
 
     /// Return the reference count for the blob backing `layer`.
     pub fn count(&self, layer: &Layer) -> i64 {
-        let digest = layer.digest().digest();
+        let digest = layer.digest().to_string();
         self.blob_counts.get(&digest).cloned().unwrap_or(0)
     }
 
@@ -70,9 +209,15 @@ impl Catalog {
                 self.catalog.remove(&id.prefix());
             }
         }
+        if let Some(generations) = self.generations.get_mut(&id.prefix()) {
+            generations.retain(|x| x.id != *id);
+            if generations.is_empty() {
+                self.generations.remove(&id.prefix());
+            }
+        }
         if let Some(artifact) = self.manifests.remove(id) {
             for layer in artifact.layers() {
-                let digest = layer.digest().digest();
+                let digest = layer.digest().to_string();
                 if let Some(blob_count) = self.blob_counts.get_mut(&digest) {
                     *blob_count -= 1;
                     if *blob_count <= 0 {
@@ -83,3 +228,102 @@ impl Catalog {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogs_without_algorithms_are_migrated_to_blake3() {
+        let catalog = Catalog::from_slice(
+            br#"{"catalog": {}, "manifests": {}, "blob_counts": {"abc": 2, "sha256:def": 1}}"#,
+        )
+        .unwrap();
+        assert_eq!(catalog.blob_counts.get("blake3:abc"), Some(&2));
+        assert_eq!(catalog.blob_counts.get("sha256:def"), Some(&1));
+        let written = serde_json::to_string(&catalog).unwrap();
+        assert!(written.contains("\"blake3:abc\":2"), "{written}");
+    }
+
+    fn artifact(digest: &str) -> Artifact {
+        let id = Id::builder()
+            .name("hello".to_string())
+            .digest(digest.to_string())
+            .build();
+        Artifact::builder()
+            .media_type(crate::storage::MediaType::Manifest)
+            .config(crate::storage::Config::builder().id(id).build())
+            .build()
+    }
+
+    #[test]
+    fn generations_are_listed_newest_first_and_pruned_past_the_limit() {
+        let mut catalog = Catalog::default();
+        for digest in ["1", "2", "3", "4"] {
+            catalog.add(&artifact(digest));
+        }
+        let current = artifact("4").config().id().clone();
+        let digests = |ids: Vec<Id>| ids.iter().map(|x| x.digest().clone()).collect::<Vec<_>>();
+        let history = catalog.history(&current.prefix());
+        assert_eq!(
+            digests(history.iter().map(|x| x.id.clone()).collect()),
+            ["4", "3", "2", "1"]
+        );
+        assert!(history.iter().all(|x| x.saved.is_some()));
+        assert_eq!(digests(catalog.stale(&current, 1)), ["2", "1"]);
+
+        // Saving an older id again brings it back to the front
+        catalog.add(&artifact("2"));
+        assert_eq!(catalog.history(&current.prefix())[0].id.digest(), "2");
+        catalog.del(&current);
+        assert_eq!(catalog.history(&current.prefix()).len(), 3);
+    }
+
+    #[test]
+    fn catalogs_without_generations_list_their_artifacts() {
+        let mut catalog = Catalog::default();
+        catalog.add(&artifact("1"));
+        catalog.generations.clear();
+        let written = serde_json::to_string(&catalog).unwrap();
+        let catalog =
+            Catalog::from_slice(written.replace(",\"generations\":{}", "").as_bytes()).unwrap();
+        let history = catalog.history(&artifact("1").config().id().prefix());
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].saved, None);
+    }
+
+    #[test]
+    fn tags_move_to_the_newest_artifact_and_hide_deleted_ones() {
+        let mut catalog = Catalog::default();
+        let (first, second) = (artifact("1"), artifact("2"));
+        catalog.add(&first);
+        catalog.add(&second);
+        catalog.tag("hello:latest", first.config().id());
+        catalog.tag("hello:latest", second.config().id());
+        catalog.tag("hello:old", first.config().id());
+        assert_eq!(catalog.resolve("hello:latest"), Some(second.config().id()));
+
+        catalog.del(first.config().id());
+        assert_eq!(catalog.resolve("hello:old"), None);
+        assert_eq!(catalog.tags().len(), 1);
+    }
+
+    #[test]
+    fn catalogs_of_a_newer_edo_are_refused_and_new_fields_kept() {
+        let err = Catalog::from_slice(
+            br#"{"version": 3, "catalog": {}, "manifests": {}, "blob_counts": {}}"#,
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("version 3"), "{err}");
+
+        let catalog = Catalog::from_slice(
+            br#"{"version": 2, "catalog": {}, "manifests": {}, "blob_counts": {"sha256:a": 1}, "pins": [1]}"#,
+        )
+        .unwrap();
+        let written = serde_json::to_string(&catalog).unwrap();
+        assert!(written.contains("\"pins\":[1]"), "{written}");
+        assert!(written.contains("\"version\":2"), "{written}");
+        assert!(written.contains("\"sha256:a\":1"), "{written}");
+    }
+}
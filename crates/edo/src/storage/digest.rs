@@ -0,0 +1,188 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+
+use super::{StorageError, StorageResult, error};
+
+/// A hash algorithm layer blobs can be addressed by.
+///
+/// Layers are written with BLAKE3 unless a cache asks for another algorithm,
+/// typically `sha256` for artifacts that are exchanged with OCI registries.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DigestAlgorithm {
+    #[default]
+    Blake3,
+    Sha256,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    /// The name used as the `<algorithm>:` prefix of a digest.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Blake3 => "blake3",
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+        }
+    }
+
+    /// Start a fresh hash with this algorithm.
+    pub fn hasher(&self) -> Hasher {
+        match self {
+            Self::Blake3 => Hasher::Blake3(Box::default()),
+            Self::Sha256 => Hasher::Sha256(Sha256::new()),
+            Self::Sha512 => Hasher::Sha512(Box::new(Sha512::new())),
+        }
+    }
+
+    /// Split an optional `<algorithm>:` prefix off `digest`.
+    ///
+    /// Digests without a known prefix are BLAKE3, which is how every digest
+    /// was written before other algorithms were supported.
+    pub fn split(digest: &str) -> (Self, &str) {
+        match digest.split_once(':') {
+            Some((name, hex)) => match name.parse() {
+                Ok(algorithm) => (algorithm, hex),
+                Err(_) => (Self::Blake3, digest),
+            },
+            None => (Self::Blake3, digest),
+        }
+    }
+}
+
+impl FromStr for DigestAlgorithm {
+    type Err = StorageError;
+
+    fn from_str(s: &str) -> StorageResult<Self> {
+        match s.to_lowercase().as_str() {
+            "blake3" => Ok(Self::Blake3),
+            "sha256" => Ok(Self::Sha256),
+            "sha512" => Ok(Self::Sha512),
+            _ => error::UnknownDigestAlgorithmSnafu { value: s }.fail(),
+        }
+    }
+}
+
+impl fmt::Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for DigestAlgorithm {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for DigestAlgorithm {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// An in-progress hash for one of the [`DigestAlgorithm`]s.
+#[derive(Clone)]
+pub enum Hasher {
+    Blake3(Box<blake3::Hasher>),
+    Sha256(Sha256),
+    Sha512(Box<Sha512>),
+}
+
+impl Hasher {
+    /// The algorithm this hash is computed with.
+    pub fn algorithm(&self) -> DigestAlgorithm {
+        match self {
+            Self::Blake3(_) => DigestAlgorithm::Blake3,
+            Self::Sha256(_) => DigestAlgorithm::Sha256,
+            Self::Sha512(_) => DigestAlgorithm::Sha512,
+        }
+    }
+
+    /// Feed more bytes into the hash.
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Return the hex-encoded digest of all bytes hashed so far.
+    pub fn finalize(&self) -> String {
+        match self {
+            Self::Blake3(hasher) => base16::encode_lower(hasher.finalize().as_bytes()),
+            Self::Sha256(hasher) => base16::encode_lower(&hasher.clone().finalize()),
+            Self::Sha512(hasher) => base16::encode_lower(&hasher.as_ref().clone().finalize()),
+        }
+    }
+}
+
+impl Default for Hasher {
+    fn default() -> Self {
+        DigestAlgorithm::default().hasher()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LayerDigest;
+
+    #[test]
+    fn algorithms_hash_to_their_known_digests() {
+        let digest = |algorithm: DigestAlgorithm| {
+            let mut hasher = algorithm.hasher();
+            hasher.update(b"abc");
+            hasher.finalize()
+        };
+        assert_eq!(
+            digest(DigestAlgorithm::Sha256),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(digest(DigestAlgorithm::Sha512).starts_with("ddaf35a193617aba"));
+        assert_eq!(
+            digest(DigestAlgorithm::Blake3),
+            blake3::hash(b"abc").to_hex().to_string()
+        );
+    }
+
+    #[test]
+    fn prefixes_select_the_algorithm() {
+        assert_eq!(
+            DigestAlgorithm::split("sha256:abc"),
+            (DigestAlgorithm::Sha256, "abc")
+        );
+        assert_eq!(
+            DigestAlgorithm::split("blake3:abc"),
+            (DigestAlgorithm::Blake3, "abc")
+        );
+        assert_eq!(
+            DigestAlgorithm::split("abc"),
+            (DigestAlgorithm::Blake3, "abc")
+        );
+        assert!("md5".parse::<DigestAlgorithm>().is_err());
+    }
+
+    #[test]
+    fn layer_digests_serialize_with_their_algorithm() {
+        let digest = LayerDigest::new(DigestAlgorithm::Sha512, "abc");
+        let json = serde_json::to_string(&digest).unwrap();
+        assert_eq!(json, "\"sha512:abc\"");
+        assert_eq!(serde_json::from_str::<LayerDigest>(&json).unwrap(), digest);
+        assert_eq!(digest.path(), "sha512/abc");
+        assert!(serde_json::from_str::<LayerDigest>("\"md5:abc\"").is_err());
+        assert!(serde_json::from_str::<LayerDigest>("\"abc\"").is_err());
+    }
+}
@@ -0,0 +1,478 @@
+//! Client-side encryption of layers kept in remote backends.
+//!
+//! A cache definition opts in with an `encryption` table naming where its
+//! 32 byte key comes from, either a file or the output of a command (such as
+//! `aws kms decrypt` of a key kept next to the project):
+//!
+//! ```toml
+//! [cache.build]
+//! kind       = "s3"
+//! bucket     = "team-builds"
+//! encryption = { key_file = "/etc/edo/cache.key" }
+//! # encryption = { key_command = "aws kms decrypt --ciphertext-blob fileb://cache.key.enc --query Plaintext --output text" }
+//! ```
+//!
+//! Keys are given raw, hex or base64 encoded. Blobs are split into 64 KiB
+//! chunks sealed with AES-256-GCM under a key derived with HKDF-SHA256 from
+//! the configured key and the layer's digest, the nonce counting chunks and
+//! marking the last one, so chunks can be neither reordered nor dropped. As
+//! every digest gets its own key the same layer always encrypts to the same
+//! blob, and uploading it again never breaks manifests already pointing at
+//! it. Layers record the scheme and a fingerprint of the key in
+//! [`LayerEncryption`], so reading with another key fails clearly.
+
+use super::{Layer, LayerDigest, StorageResult, error};
+use crate::context::{FieldSchema, FieldType, Node};
+use base64::Engine;
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, Nonce, UnboundKey};
+use ring::hkdf::{HKDF_SHA256, Salt};
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt, ResultExt, ensure};
+use std::fmt;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadBuf};
+
+/// Name of the only encryption scheme, recorded in [`LayerEncryption`].
+pub const ENCRYPTION_SCHEME: &str = "aes-256-gcm";
+
+/// Bytes of plaintext sealed together.
+const CHUNK: usize = 64 * 1024;
+/// Bytes the authentication tag adds to every chunk.
+const TAG: usize = 16;
+
+/// How a backend encrypted a layer's blob.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LayerEncryption {
+    /// The encryption scheme, [`ENCRYPTION_SCHEME`].
+    pub scheme: String,
+    /// Fingerprint of the key the blob was encrypted with.
+    pub key: String,
+}
+
+/// A key encrypting the layers a backend stores.
+#[derive(Clone)]
+pub struct LayerKey {
+    key: [u8; 32],
+    id: String,
+}
+
+// Keys must never end up in logs
+impl fmt::Debug for LayerKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayerKey").field("id", &self.id).finish()
+    }
+}
+
+impl LayerKey {
+    /// Reads a 32 byte key given raw, hex or base64 encoded.
+    pub fn parse(bytes: &[u8]) -> StorageResult<Self> {
+        let text = String::from_utf8_lossy(bytes);
+        let text = text.trim();
+        let key = if bytes.len() == 32 {
+            bytes.to_vec()
+        } else if text.len() == 64 && text.bytes().all(|x| x.is_ascii_hexdigit()) {
+            base16::decode(text).map_err(|e| {
+                error::EncryptionKeySnafu {
+                    reason: e.to_string(),
+                }
+                .build()
+            })?
+        } else {
+            base64::engine::general_purpose::STANDARD
+                .decode(text)
+                .map_err(|_| {
+                    error::EncryptionKeySnafu {
+                        reason: "expected 32 bytes, raw, hex or base64 encoded",
+                    }
+                    .build()
+                })?
+        };
+        let key: [u8; 32] = key.try_into().map_err(|key: Vec<u8>| {
+            error::EncryptionKeySnafu {
+                reason: format!("expected 32 bytes, found {}", key.len()),
+            }
+            .build()
+        })?;
+        let id = blake3::derive_key("edo layer encryption key id", &key);
+        Ok(Self {
+            key,
+            id: base16::encode_lower(&id[..8]),
+        })
+    }
+
+    /// Fingerprint of the key, safe to show and store.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// What layers encrypted with this key record.
+    pub fn encryption(&self) -> LayerEncryption {
+        LayerEncryption {
+            scheme: ENCRYPTION_SCHEME.to_string(),
+            key: self.id.clone(),
+        }
+    }
+
+    /// The key sealing the chunks of the layer with `digest`.
+    fn cipher(&self, digest: &LayerDigest) -> LessSafeKey {
+        let prk = Salt::new(HKDF_SHA256, digest.to_string().as_bytes()).extract(&self.key);
+        let okm = prk
+            .expand(&[b"edo layer"], &AES_256_GCM)
+            .expect("an aes-256-gcm key is a valid hkdf output length");
+        LessSafeKey::new(UnboundKey::from(okm))
+    }
+
+    /// Encrypts the plaintext of the layer with `digest` in `from` into `to`.
+    pub async fn encrypt_file(
+        &self,
+        digest: &LayerDigest,
+        from: &Path,
+        to: &Path,
+    ) -> StorageResult<()> {
+        let cipher = self.cipher(digest);
+        let mut input = tokio::fs::File::open(from).await.context(error::IoSnafu)?;
+        let size = input.metadata().await.context(error::IoSnafu)?.len() as usize;
+        let mut output = tokio::fs::File::create(to).await.context(error::IoSnafu)?;
+        let last = last_chunk(size);
+        for index in 0..=last {
+            let length = CHUNK.min(size - index as usize * CHUNK);
+            let mut chunk = vec![0; length];
+            input.read_exact(&mut chunk).await.context(error::IoSnafu)?;
+            cipher
+                .seal_in_place_append_tag(nonce(index, index == last), Aad::empty(), &mut chunk)
+                .map_err(|_| {
+                    error::EncryptionKeySnafu {
+                        reason: "failed to seal a chunk",
+                    }
+                    .build()
+                })?;
+            output.write_all(&chunk).await.context(error::IoSnafu)?;
+        }
+        output.flush().await.context(error::IoSnafu)?;
+        Ok(())
+    }
+
+    /// Checks that `layer` was encrypted with this key.
+    pub fn check(&self, layer: &Layer) -> StorageResult<()> {
+        let encryption = layer
+            .encryption()
+            .as_ref()
+            .context(error::EncryptionStateSnafu {
+                digest: layer.digest().to_string(),
+                state: "unencrypted",
+                configured: format!("to encrypt with key {}", self.id),
+            })?;
+        ensure!(
+            encryption.scheme == ENCRYPTION_SCHEME && encryption.key == self.id,
+            error::EncryptionKeyMismatchSnafu {
+                digest: layer.digest().to_string(),
+                key: format!("{} key {}", encryption.scheme, encryption.key),
+                configured: self.id.clone(),
+            }
+        );
+        Ok(())
+    }
+
+    /// Where in the encrypted blob reading from plaintext `offset` starts.
+    pub fn encrypted_offset(offset: u64) -> u64 {
+        offset / CHUNK as u64 * (CHUNK + TAG) as u64
+    }
+
+    /// Decrypts the blob of `layer`, read by `reader` from
+    /// [`encrypted_offset`](Self::encrypted_offset) of plaintext `offset` on.
+    pub fn decrypt<R>(&self, layer: &Layer, reader: R, offset: u64) -> DecryptReader<R>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let size = *layer.size();
+        let index = offset / CHUNK as u64;
+        let last = last_chunk(size);
+        DecryptReader {
+            reader,
+            cipher: self.cipher(layer.digest()),
+            index,
+            last,
+            size,
+            skip: (offset % CHUNK as u64) as usize,
+            sealed: Vec::new(),
+            plain: Vec::new(),
+            position: 0,
+            done: index > last,
+        }
+    }
+}
+
+/// Index of the last chunk of `size` bytes, empty plaintexts still seal one.
+fn last_chunk(size: usize) -> u64 {
+    (size.saturating_sub(1) / CHUNK) as u64
+}
+
+/// The nonce of chunk `index`, telling the last chunk apart.
+fn nonce(index: u64, last: bool) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[..8].copy_from_slice(&index.to_be_bytes());
+    nonce[11] = last as u8;
+    Nonce::assume_unique_for_key(nonce)
+}
+
+/// Reads the plaintext of an encrypted blob, failing with
+/// [`std::io::ErrorKind::InvalidData`] on any chunk that does not
+/// authenticate.
+pub struct DecryptReader<R> {
+    reader: R,
+    cipher: LessSafeKey,
+    index: u64,
+    last: u64,
+    size: usize,
+    // Plaintext bytes of the first chunk before the requested offset
+    skip: usize,
+    sealed: Vec<u8>,
+    plain: Vec<u8>,
+    position: usize,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for DecryptReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.position < this.plain.len() {
+                let count = buf.remaining().min(this.plain.len() - this.position);
+                buf.put_slice(&this.plain[this.position..this.position + count]);
+                this.position += count;
+                return Poll::Ready(Ok(()));
+            }
+            if this.done {
+                return Poll::Ready(Ok(()));
+            }
+            let plain = CHUNK.min(this.size - this.index as usize * CHUNK);
+            let expected = plain + TAG;
+            while this.sealed.len() < expected {
+                let mut chunk = vec![0; expected - this.sealed.len()];
+                let mut read = ReadBuf::new(&mut chunk);
+                match Pin::new(&mut this.reader).poll_read(cx, &mut read) {
+                    Poll::Ready(Ok(())) if read.filled().is_empty() => {
+                        return Poll::Ready(Err(std::io::Error::new(
+                            std::io::ErrorKind::UnexpectedEof,
+                            format!("encrypted blob ends inside chunk {}", this.index),
+                        )));
+                    }
+                    Poll::Ready(Ok(())) => this.sealed.extend_from_slice(read.filled()),
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+            let mut sealed = std::mem::take(&mut this.sealed);
+            let last = this.index == this.last;
+            let opened = this
+                .cipher
+                .open_in_place(nonce(this.index, last), Aad::empty(), &mut sealed)
+                .map_err(|_| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "chunk {} of the encrypted blob does not authenticate",
+                            this.index
+                        ),
+                    )
+                })?;
+            this.plain = opened[this.skip.min(opened.len())..].to_vec();
+            this.position = 0;
+            this.skip = 0;
+            this.index += 1;
+            this.done = last;
+        }
+    }
+}
+
+/// Describes the `encryption` key a cache definition may set, for backends
+/// reading it.
+pub fn encryption_field() -> FieldSchema {
+    FieldSchema::new(
+        "encryption",
+        FieldType::table(FieldType::String),
+        "Encrypt layers with the key in `key_file` or printed by `key_command`",
+    )
+}
+
+/// Reads the optional `encryption` table of a cache definition.
+pub async fn encryption_setting(node: &Node) -> StorageResult<Option<LayerKey>> {
+    let Some(table) = node.get("encryption") else {
+        return Ok(None);
+    };
+    let table = table.as_table().context(error::SettingSnafu {
+        key: "encryption",
+        reason: "expected a table with a key_file or key_command",
+    })?;
+    let value = |name: &str| {
+        table
+            .get(name)
+            .map(|x| {
+                x.as_string().context(error::SettingSnafu {
+                    key: format!("encryption.{name}"),
+                    reason: "expected a string",
+                })
+            })
+            .transpose()
+    };
+    let bytes = match (value("key_file")?, value("key_command")?) {
+        (Some(path), None) => tokio::fs::read(&path).await.map_err(|e| {
+            error::EncryptionKeySnafu {
+                reason: format!("cannot read {path}: {e}"),
+            }
+            .build()
+        })?,
+        (None, Some(command)) => {
+            let output = tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(&command)
+                .output()
+                .await
+                .context(error::IoSnafu)?;
+            ensure!(
+                output.status.success(),
+                error::EncryptionKeySnafu {
+                    reason: format!(
+                        "`{command}` failed with {}: {}",
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ),
+                }
+            );
+            output.stdout
+        }
+        _ => {
+            return error::SettingSnafu {
+                key: "encryption",
+                reason: "expected exactly one of key_file or key_command",
+            }
+            .fail();
+        }
+    };
+    LayerKey::parse(&bytes).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{DigestAlgorithm, MediaType};
+
+    fn layer(digest: &str, size: usize, key: &LayerKey) -> Layer {
+        let mut layer = Layer::builder()
+            .media_type(MediaType::Manifest)
+            .digest(LayerDigest::new(DigestAlgorithm::Blake3, digest))
+            .size(size)
+            .build();
+        *layer.encryption_mut() = Some(key.encryption());
+        layer
+    }
+
+    async fn encrypt(key: &LayerKey, layer: &Layer, plain: &[u8]) -> Vec<u8> {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("plain"), plain).unwrap();
+        key.encrypt_file(
+            layer.digest(),
+            &dir.path().join("plain"),
+            &dir.path().join("sealed"),
+        )
+        .await
+        .unwrap();
+        std::fs::read(dir.path().join("sealed")).unwrap()
+    }
+
+    async fn decrypt(key: &LayerKey, layer: &Layer, sealed: &[u8], offset: u64) -> Vec<u8> {
+        let start = LayerKey::encrypted_offset(offset) as usize;
+        let mut out = Vec::new();
+        key.decrypt(layer, &sealed[start..], offset)
+            .read_to_end(&mut out)
+            .await
+            .unwrap();
+        out
+    }
+
+    #[tokio::test]
+    async fn layers_round_trip_from_any_offset() {
+        let key = LayerKey::parse(&[7; 32]).unwrap();
+        let plain: Vec<u8> = (0..CHUNK * 2 + 100).map(|x| x as u8).collect();
+        let layer = layer("aa", plain.len(), &key);
+        let sealed = encrypt(&key, &layer, &plain).await;
+        assert_eq!(sealed.len(), plain.len() + 3 * TAG);
+        // The same layer always encrypts to the same blob
+        assert_eq!(encrypt(&key, &layer, &plain).await, sealed);
+        for offset in [0, 5, CHUNK, CHUNK + 17, plain.len()] {
+            assert_eq!(
+                decrypt(&key, &layer, &sealed, offset as u64).await,
+                plain[offset..]
+            );
+        }
+        let empty = self::layer("bb", 0, &key);
+        let sealed = encrypt(&key, &empty, b"").await;
+        assert_eq!(decrypt(&key, &empty, &sealed, 0).await, b"");
+    }
+
+    #[tokio::test]
+    async fn tampered_or_truncated_blobs_fail() {
+        let key = LayerKey::parse(&[7; 32]).unwrap();
+        let plain = vec![1u8; CHUNK + 10];
+        let layer = layer("aa", plain.len(), &key);
+        let sealed = encrypt(&key, &layer, &plain).await;
+        let mut out = Vec::new();
+
+        let mut tampered = sealed.clone();
+        tampered[3] ^= 1;
+        let err = key
+            .decrypt(&layer, tampered.as_slice(), 0)
+            .read_to_end(&mut out)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // Dropping the last chunk makes the first one look like the end
+        let err = key
+            .decrypt(&layer, &sealed[..CHUNK + TAG], 0)
+            .read_to_end(&mut out)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        // Another digest derives another key
+        let other = self::layer("ab", plain.len(), &key);
+        assert!(
+            key.decrypt(&other, sealed.as_slice(), 0)
+                .read_to_end(&mut out)
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn keys_are_read_in_any_encoding_and_checked() {
+        let raw = LayerKey::parse(&[9; 32]).unwrap();
+        let hex = LayerKey::parse(format!("{}\n", "09".repeat(32)).as_bytes()).unwrap();
+        let base64 = LayerKey::parse(
+            base64::engine::general_purpose::STANDARD
+                .encode([9; 32])
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(raw.id(), hex.id());
+        assert_eq!(raw.id(), base64.id());
+        assert_eq!(
+            format!("{raw:?}"),
+            format!("LayerKey {{ id: {:?} }}", raw.id())
+        );
+        assert!(LayerKey::parse(b"short").is_err());
+
+        let other = LayerKey::parse(&[1; 32]).unwrap();
+        let layer = layer("aa", 1, &raw);
+        raw.check(&layer).unwrap();
+        let err = other.check(&layer).unwrap_err();
+        assert!(err.to_string().contains(raw.id()), "{err}");
+    }
+}
@@ -14,9 +14,29 @@ pub enum StorageError {
     /// Failed to resolve a filesystem path to an absolute path.
     #[snafu(display("failed to resolve absolute path: {source}"))]
     Absolute { source: std::io::Error },
+    /// A cache catalog is not valid JSON, or not a catalog.
+    #[snafu(display("invalid cache catalog: {source}"))]
+    Catalog { source: serde_json::Error },
+    /// A cache catalog was written by a newer edo in a format this one cannot read.
+    #[snafu(display(
+        "the cache catalog has schema version {version}, but this edo only reads up to version {supported}; it was written by a newer edo, upgrade edo to use this cache"
+    ))]
+    CatalogVersion { version: u64, supported: u64 },
     /// Multiple storage operations failed concurrently.
     #[snafu(display("multiple errors occured: {}", children.iter().map(|x| x.to_string()).collect::<Vec<_>>().join("\n")))]
     Child { children: Vec<StorageError> },
+    /// A cache's encryption key could not be read.
+    #[snafu(display("invalid layer encryption key: {reason}"))]
+    EncryptionKey { reason: String },
+    /// A layer was encrypted with another key than the cache is configured with.
+    #[snafu(display(
+        "layer {digest} was encrypted with {key}, but the cache is configured with key {configured}"
+    ))]
+    EncryptionKeyMismatch {
+        digest: String,
+        key: String,
+        configured: String,
+    },
     /// An artifact identifier could not be parsed.
     #[snafu(display("invalid artifact id: {reason}"))]
     Id { reason: String },
@@ -28,12 +48,27 @@ pub enum StorageError {
     Implementation {
         source: Box<dyn snafu::Error + Send + Sync>,
     },
+    /// Layers of an upload are not in the cache, so its manifest was not published.
+    #[snafu(display("layers of {id} are missing from the cache after uploading them"))]
+    IncompleteUpload { id: String },
+    /// A directory or tarball cannot stand in for a transform's artifact.
+    #[snafu(display("cannot inject {path} as an artifact: {reason}"))]
+    Inject { path: String, reason: String },
     /// The provided string is not a valid edo artifact media type.
     #[snafu(display("{value} is not a valid edo artifact media type"))]
     InvalidMediaType { value: String },
     /// A spawned async task panicked or was cancelled.
     #[snafu(display("failed to join on task: {source}"))]
     Join { source: JoinError },
+    /// An OCI layout document could not be read or written.
+    #[snafu(display("invalid OCI image layout document: {source}"))]
+    OciDocument { source: serde_json::Error },
+    /// An artifact cannot be exported as an OCI image layout.
+    #[snafu(display("cannot export an OCI image layout: {reason}"))]
+    OciLayout { reason: String },
+    /// No cache of the requested kind is registered.
+    #[snafu(display("no {cache} cache is configured"))]
+    NoSuchCache { cache: String },
     /// A propagated context-layer error (e.g. project/config issues).
     #[snafu(transparent)]
     Project {
@@ -50,4 +85,42 @@ pub enum StorageError {
     /// A semver version string could not be parsed.
     #[snafu(display("invalid semantic version: {source}"))]
     Semver { source: semver::Error },
+    /// A layer decompressed on download does not match the digest recorded at upload.
+    #[snafu(display(
+        "layer restored from a compressed copy has digest {actual}, expected {expected}"
+    ))]
+    Transcode { expected: String, actual: String },
+    /// A cache definition has an invalid bandwidth, concurrency or retry setting.
+    #[snafu(display("invalid cache transfer setting '{key}': {reason}"))]
+    Transfer { key: String, reason: String },
+    /// A layer read with a cache's encryption key was stored unencrypted, or
+    /// the other way round.
+    #[snafu(display("layer {digest} is stored {state}, but the cache is configured {configured}"))]
+    EncryptionState {
+        digest: String,
+        state: String,
+        configured: String,
+    },
+    /// A cache definition has a setting of the wrong type.
+    #[snafu(display("invalid cache setting '{key}': {reason}"))]
+    Setting { key: String, reason: String },
+    /// An artifact was committed while layers it started were still open.
+    #[snafu(display("cannot save {id} with {count} unfinished layers"))]
+    UnfinishedLayers { id: String, count: usize },
+    /// An alias template cannot be rendered into a valid alias.
+    #[snafu(display("invalid tag template '{template}': {reason}"))]
+    Tag { template: String, reason: String },
+    /// A backend was configured with a compression algorithm edo does not know.
+    #[snafu(display(
+        "unknown compression '{value}', expected one of 'zstd', 'gzip', 'bzip2', 'lzma', 'xz' or 'none'"
+    ))]
+    UnknownCompression { value: String },
+    /// A digest or cache definition named a hash algorithm edo does not know.
+    #[snafu(display(
+        "unknown digest algorithm '{value}', expected one of 'blake3', 'sha256' or 'sha512'"
+    ))]
+    UnknownDigestAlgorithm { value: String },
+    /// The backend does not implement an optional operation.
+    #[snafu(display("this storage backend does not support {operation}"))]
+    Unsupported { operation: String },
 }
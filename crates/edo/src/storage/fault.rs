@@ -0,0 +1,262 @@
+use std::collections::BTreeSet;
+
+use crate::context::HealthCheck;
+use crate::storage::{
+    Artifact, Backend, BackendImpl, BackendOperation, Compression, DigestAlgorithm, Generation, Id,
+    Layer, MediaType, StorageResult, TemporaryBlob, TransferPolicy,
+};
+use crate::util::{FaultPlan, Reader, Writer};
+use async_trait::async_trait;
+use ocilot::models::Platform;
+
+/// Backend wrapper that applies a [`FaultPlan`] before delegating each call.
+///
+/// Every operation checks the points `storage.<cache>.<operation>` and then
+/// `storage.<operation>`, where `<cache>` is the name given to
+/// [`FaultyBackend::wrap`] (`local`, `build`, `output` or `source`).
+pub struct FaultyBackend {
+    name: String,
+    inner: Backend,
+    plan: FaultPlan,
+}
+
+impl FaultyBackend {
+    /// Wraps `backend` when `plan` has faults to inject, otherwise returns it unchanged.
+    pub fn wrap(name: &str, backend: Backend, plan: &FaultPlan) -> Backend {
+        if plan.is_empty() {
+            return backend;
+        }
+        Backend::new(Self {
+            name: name.to_string(),
+            inner: backend,
+            plan: plan.clone(),
+        })
+    }
+
+    async fn inject(&self, operation: BackendOperation) -> StorageResult<()> {
+        let scoped = format!("storage.{}.{operation}", self.name);
+        let global = format!("storage.{operation}");
+        self.plan.inject(&[&scoped, &global]).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BackendImpl for FaultyBackend {
+    async fn list(&self) -> StorageResult<BTreeSet<Id>> {
+        self.inject(BackendOperation::List).await?;
+        self.inner.list().await
+    }
+
+    async fn has(&self, id: &Id) -> StorageResult<bool> {
+        self.inject(BackendOperation::Has).await?;
+        self.inner.has(id).await
+    }
+
+    async fn has_many(&self, ids: &[Id]) -> StorageResult<BTreeSet<Id>> {
+        self.inject(BackendOperation::Has).await?;
+        self.inner.has_many(ids).await
+    }
+
+    async fn open(&self, id: &Id) -> StorageResult<Artifact> {
+        self.inject(BackendOperation::Open).await?;
+        self.inner.open(id).await
+    }
+
+    async fn has_layers(&self, artifact: &Artifact) -> StorageResult<bool> {
+        self.inject(BackendOperation::Has).await?;
+        self.inner.has_layers(artifact).await
+    }
+
+    async fn save(&self, artifact: &Artifact) -> StorageResult<()> {
+        self.inject(BackendOperation::Save).await?;
+        self.inner.save(artifact).await
+    }
+
+    async fn del(&self, id: &Id) -> StorageResult<()> {
+        self.inject(BackendOperation::Del).await?;
+        self.inner.del(id).await
+    }
+
+    async fn copy(&self, from: &Id, to: &Id) -> StorageResult<()> {
+        self.inject(BackendOperation::Copy).await?;
+        self.inner.copy(from, to).await
+    }
+
+    async fn prune(&self, id: &Id) -> StorageResult<()> {
+        self.inject(BackendOperation::Prune).await?;
+        self.inner.prune(id).await
+    }
+
+    async fn history(&self, prefix: &str) -> StorageResult<Vec<Generation>> {
+        self.inject(BackendOperation::History).await?;
+        self.inner.history(prefix).await
+    }
+
+    async fn tag(&self, tag: &str, id: &Id) -> StorageResult<()> {
+        self.inject(BackendOperation::Tag).await?;
+        self.inner.tag(tag, id).await
+    }
+
+    async fn resolve_tag(&self, tag: &str) -> StorageResult<Option<Id>> {
+        self.inject(BackendOperation::ResolveTag).await?;
+        self.inner.resolve_tag(tag).await
+    }
+
+    async fn prune_all(&self) -> StorageResult<()> {
+        self.inject(BackendOperation::PruneAll).await?;
+        self.inner.prune_all().await
+    }
+
+    async fn refresh(&self) -> StorageResult<()> {
+        self.inner.refresh().await
+    }
+
+    async fn read(&self, layer: &Layer) -> StorageResult<Reader> {
+        self.inject(BackendOperation::Read).await?;
+        self.inner.read(layer).await
+    }
+
+    async fn read_from(&self, layer: &Layer, offset: u64) -> StorageResult<Reader> {
+        self.inject(BackendOperation::Read).await?;
+        self.inner.read_from(layer, offset).await
+    }
+
+    async fn start_layer(&self) -> StorageResult<Writer> {
+        self.inject(BackendOperation::StartLayer).await?;
+        self.inner.start_layer().await
+    }
+
+    async fn finish_layer(
+        &self,
+        media_type: &MediaType,
+        platform: Option<Platform>,
+        writer: &Writer,
+    ) -> StorageResult<Layer> {
+        self.inject(BackendOperation::FinishLayer).await?;
+        self.inner.finish_layer(media_type, platform, writer).await
+    }
+
+    async fn abort_layer(&self, writer: &Writer) -> StorageResult<()> {
+        self.inner.abort_layer(writer).await
+    }
+
+    async fn temporary_blobs(&self) -> StorageResult<Option<Vec<TemporaryBlob>>> {
+        self.inner.temporary_blobs().await
+    }
+
+    async fn remove_temporary(&self, target: &str) -> StorageResult<()> {
+        self.inner.remove_temporary(target).await
+    }
+
+    async fn blobs(&self) -> StorageResult<Option<BTreeSet<String>>> {
+        self.inject(BackendOperation::List).await?;
+        self.inner.blobs().await
+    }
+
+    async fn remove_blob(&self, digest: &str) -> StorageResult<()> {
+        self.inject(BackendOperation::Del).await?;
+        self.inner.remove_blob(digest).await
+    }
+
+    fn compression(&self) -> Option<Compression> {
+        self.inner.compression()
+    }
+
+    fn transfer_policy(&self) -> TransferPolicy {
+        self.inner.transfer_policy()
+    }
+
+    fn verify_reads(&self) -> bool {
+        self.inner.verify_reads()
+    }
+
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        self.inner.digest_algorithm()
+    }
+
+    fn location(&self) -> Option<String> {
+        self.inner.location()
+    }
+
+    fn read_through(&self) -> bool {
+        self.inner.read_through()
+    }
+
+    async fn health(&self) -> Vec<HealthCheck> {
+        self.inner.health().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Compression, Config, InMemoryBackend, Storage};
+    use crate::util::FaultAction;
+    use tokio::io::AsyncWriteExt;
+
+    async fn local_artifact(backend: &InMemoryBackend) -> Artifact {
+        let mut writer = backend.start_layer().await.unwrap();
+        writer.write_all(b"built").await.unwrap();
+        let layer = backend
+            .finish_layer(&MediaType::File(Compression::None), None, &writer)
+            .await
+            .unwrap();
+        let id = Id::builder()
+            .name("a".to_string())
+            .digest("1".to_string())
+            .build();
+        let artifact = Artifact::builder()
+            .media_type(MediaType::Manifest)
+            .config(Config::builder().id(id).build())
+            .layers(vec![layer])
+            .build();
+        backend.save(&artifact).await.unwrap();
+        artifact
+    }
+
+    #[tokio::test]
+    async fn partial_upload_leaves_build_cache_without_manifest() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new();
+        let artifact = local_artifact(&local).await;
+
+        let plan = FaultPlan::default();
+        plan.add("storage.build.save", FaultAction::Fail, Some(1));
+        let storage = Storage::init(&FaultyBackend::wrap(
+            "local",
+            Backend::new(local.clone()),
+            &plan,
+        ))
+        .await
+        .unwrap();
+        storage
+            .set_build(&FaultyBackend::wrap(
+                "build",
+                Backend::new(build.clone()),
+                &plan,
+            ))
+            .await;
+
+        let id = artifact.config().id();
+        let err = storage.upload_build(id).await.unwrap_err();
+        assert_eq!(err.to_string(), "injected failure at storage.build.save");
+        // The layer made it across but the manifest did not
+        assert_eq!(build.blob_count(), 1);
+        assert!(!build.has(id).await.unwrap());
+
+        // The rule was counted, so a retry succeeds
+        storage.upload_build(id).await.unwrap();
+        assert!(build.has(id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn unscoped_points_apply_to_every_cache() {
+        let plan = FaultPlan::default();
+        plan.add("storage.list", FaultAction::Fail, None);
+        let local = FaultyBackend::wrap("local", Backend::new(InMemoryBackend::new()), &plan);
+        let build = FaultyBackend::wrap("build", Backend::new(InMemoryBackend::new()), &plan);
+        assert!(local.list().await.is_err());
+        assert!(build.list().await.is_err());
+    }
+}
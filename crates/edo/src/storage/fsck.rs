@@ -0,0 +1,273 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+use bon::Builder;
+
+use super::{Backend, Id, Layer, LayerDigest, StorageError, StorageResult, error};
+
+/// Selects one of the caches managed by [`Storage`](super::Storage).
+///
+/// Parses from `local`, `build`, `output` or `source:<name>`, or from the
+/// address a cache is defined at, such as `//edo-build-cache`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CacheSelector {
+    Local,
+    Build,
+    Output,
+    /// A source cache, by the name of its `[cache.source.<name>]` table.
+    Source(String),
+}
+
+impl FromStr for CacheSelector {
+    type Err = StorageError;
+
+    fn from_str(s: &str) -> StorageResult<Self> {
+        match s {
+            "local" => Ok(Self::Local),
+            "build" | "//edo-build-cache" => Ok(Self::Build),
+            "output" | "//edo-output-cache" => Ok(Self::Output),
+            value if value.starts_with("//") && value.len() > 2 => {
+                Ok(Self::Source(value.to_string()))
+            }
+            value => match value.strip_prefix("source:") {
+                Some(name) if !name.is_empty() => Ok(Self::Source(name.to_string())),
+                _ => error::NoSuchCacheSnafu { cache: value }.fail(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for CacheSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Local => f.write_str("local"),
+            Self::Build => f.write_str("build"),
+            Self::Output => f.write_str("output"),
+            Self::Source(name) => write!(f, "source:{name}"),
+        }
+    }
+}
+
+/// What [`fsck`] checks and which repairs it makes.
+#[derive(Clone, Debug, Default, Builder)]
+pub struct FsckOptions {
+    /// Re-hash every referenced blob and compare it against its recorded digest.
+    ///
+    /// Without it only the existence of each blob is checked.
+    #[builder(default)]
+    pub verify: bool,
+    /// Delete manifests that reference missing, unreadable or corrupt blobs.
+    #[builder(default)]
+    pub drop_broken: bool,
+    /// Delete blobs that no manifest references.
+    #[builder(default)]
+    pub delete_orphans: bool,
+}
+
+/// A single inconsistency found by [`fsck`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FsckIssue {
+    /// A manifest references a blob the backend does not have.
+    MissingLayer { id: Id, digest: String },
+    /// A blob could not be read.
+    UnreadableLayer {
+        id: Id,
+        digest: String,
+        reason: String,
+    },
+    /// A blob's content does not match the digest or size it is stored under.
+    CorruptLayer {
+        id: Id,
+        digest: String,
+        actual: String,
+        size: usize,
+    },
+    /// A blob no manifest references, usually left by an interrupted write.
+    OrphanedBlob { digest: String },
+}
+
+impl FsckIssue {
+    /// Returns the artifact whose manifest is broken by this issue, if any.
+    pub fn id(&self) -> Option<&Id> {
+        match self {
+            Self::MissingLayer { id, .. }
+            | Self::UnreadableLayer { id, .. }
+            | Self::CorruptLayer { id, .. } => Some(id),
+            Self::OrphanedBlob { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for FsckIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingLayer { id, digest } => {
+                write!(f, "missing layer {digest} of {id}")
+            }
+            Self::UnreadableLayer { id, digest, reason } => {
+                write!(f, "unreadable layer {digest} of {id}: {reason}")
+            }
+            Self::CorruptLayer {
+                id,
+                digest,
+                actual,
+                size,
+            } => write!(
+                f,
+                "corrupt layer {digest} of {id}: content hashes to {actual} ({size} bytes)"
+            ),
+            Self::OrphanedBlob { digest } => write!(f, "orphaned blob {digest}"),
+        }
+    }
+}
+
+/// The outcome of [`fsck`].
+#[derive(Clone, Debug, Default)]
+pub struct FsckReport {
+    /// Number of manifests checked.
+    pub artifacts: usize,
+    /// Number of distinct blobs referenced by those manifests.
+    pub layers: usize,
+    /// `false` when the backend cannot list its blobs, so orphans were not looked for.
+    pub orphans_checked: bool,
+    /// Every inconsistency found, in the order it was found.
+    pub issues: Vec<FsckIssue>,
+    /// Manifests deleted by [`FsckOptions::drop_broken`].
+    pub dropped: Vec<Id>,
+    /// Blobs deleted by [`FsckOptions::delete_orphans`].
+    pub removed: Vec<String>,
+}
+
+impl FsckReport {
+    /// Returns `true` if every issue found has been repaired.
+    pub fn is_clean(&self) -> bool {
+        self.issues.iter().all(|issue| match issue {
+            FsckIssue::OrphanedBlob { digest } => self.removed.contains(digest),
+            issue => issue.id().is_some_and(|id| self.dropped.contains(id)),
+        })
+    }
+}
+
+/// Check that every manifest in `backend` points at intact blobs and that no
+/// blob is left unreferenced, repairing what `options` asks for.
+///
+/// Each distinct blob is checked once, however many manifests share it.
+pub async fn fsck(backend: &Backend, options: &FsckOptions) -> StorageResult<FsckReport> {
+    let mut report = FsckReport::default();
+    let stored = backend.blobs().await?;
+    report.orphans_checked = stored.is_some();
+
+    // Group manifests by the blobs they reference
+    let mut users: BTreeMap<String, (Layer, Vec<Id>)> = BTreeMap::new();
+    for id in backend.list().await? {
+        let artifact = backend.open(&id).await?;
+        report.artifacts += 1;
+        for layer in artifact.layers() {
+            users
+                .entry(layer.digest().to_string())
+                .or_insert_with(|| (layer.clone(), Vec::new()))
+                .1
+                .push(id.clone());
+        }
+    }
+    report.layers = users.len();
+
+    for (digest, (layer, ids)) in users.iter() {
+        let problem = check_layer(backend, layer, stored.as_ref(), options.verify).await?;
+        let Some(problem) = problem else {
+            continue;
+        };
+        for id in ids {
+            report.issues.push(match &problem {
+                Problem::Missing => FsckIssue::MissingLayer {
+                    id: id.clone(),
+                    digest: digest.clone(),
+                },
+                Problem::Unreadable(reason) => FsckIssue::UnreadableLayer {
+                    id: id.clone(),
+                    digest: digest.clone(),
+                    reason: reason.clone(),
+                },
+                Problem::Corrupt(actual, size) => FsckIssue::CorruptLayer {
+                    id: id.clone(),
+                    digest: digest.clone(),
+                    actual: actual.clone(),
+                    size: *size,
+                },
+            });
+        }
+    }
+    if let Some(stored) = stored.as_ref() {
+        for digest in stored.iter().filter(|x| !users.contains_key(*x)) {
+            report.issues.push(FsckIssue::OrphanedBlob {
+                digest: digest.clone(),
+            });
+        }
+    }
+
+    if options.drop_broken {
+        let broken = report
+            .issues
+            .iter()
+            .filter_map(|x| x.id())
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        for id in broken {
+            info!(component = "storage", "dropping broken artifact {id}");
+            backend.del(&id).await?;
+            report.dropped.push(id);
+        }
+    }
+    if options.delete_orphans {
+        // Dropping manifests above may already have removed some of these
+        let remaining = backend.blobs().await?.unwrap_or_default();
+        for issue in report.issues.iter() {
+            if let FsckIssue::OrphanedBlob { digest } = issue {
+                if remaining.contains(digest) {
+                    info!(component = "storage", "deleting orphaned blob {digest}");
+                    backend.remove_blob(digest).await?;
+                }
+                report.removed.push(digest.clone());
+            }
+        }
+    }
+    Ok(report)
+}
+
+enum Problem {
+    Missing,
+    Unreadable(String),
+    Corrupt(String, usize),
+}
+
+async fn check_layer(
+    backend: &Backend,
+    layer: &Layer,
+    stored: Option<&BTreeSet<String>>,
+    verify: bool,
+) -> StorageResult<Option<Problem>> {
+    let digest = layer.digest().to_string();
+    if stored.is_some_and(|x| !x.contains(&digest)) {
+        return Ok(Some(Problem::Missing));
+    }
+    if !verify && stored.is_some() {
+        return Ok(None);
+    }
+    let mut reader = match backend.read(layer).await {
+        Ok(reader) => reader.with_algorithm(layer.digest().algorithm()),
+        Err(e) => return Ok(Some(Problem::Unreadable(e.to_string()))),
+    };
+    if !verify {
+        return Ok(None);
+    }
+    let size = match tokio::io::copy(&mut reader, &mut tokio::io::sink()).await {
+        Ok(size) => size as usize,
+        Err(e) => return Ok(Some(Problem::Unreadable(e.to_string()))),
+    };
+    let actual = LayerDigest::new(layer.digest().algorithm(), reader.finish()).to_string();
+    if actual != digest || size != *layer.size() {
+        return Ok(Some(Problem::Corrupt(actual, size)));
+    }
+    Ok(None)
+}
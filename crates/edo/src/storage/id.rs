@@ -1,8 +1,8 @@
-use super::error;
+use super::{DigestAlgorithm, error};
 use bon::Builder;
 use semver::Version;
 use serde::{Deserialize, Serialize};
-use snafu::ResultExt;
+use snafu::{OptionExt, ResultExt, ensure};
 use std::{fmt, str::FromStr};
 
 const UNSUPPORTED_CHARS: &[char] = &['@', ':', '.', '-', '/'];
@@ -54,7 +54,8 @@ impl fmt::Display for Name {
 /// The unique identifier for an artifact in storage.
 ///
 /// Composed of a [`Name`], an optional package name, an optional semver
-/// version, an optional architecture tag, and a BLAKE3 content digest.
+/// version, an optional architecture tag, and a content digest. The digest is
+/// BLAKE3 unless it carries an `<algorithm>:` prefix such as `sha256:`.
 /// Serializes to the format `[<package>+]<name>[-<version>][.<arch>]-<digest>`.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Builder)]
 pub struct Id {
@@ -80,11 +81,16 @@ impl Id {
         self.package.clone().map(|x| x.to_string())
     }
 
-    /// Return a reference to the BLAKE3 hex digest.
+    /// Return a reference to the digest, including any `<algorithm>:` prefix.
     pub fn digest(&self) -> &String {
         &self.digest
     }
 
+    /// Return the algorithm the digest was computed with.
+    pub fn algorithm(&self) -> DigestAlgorithm {
+        DigestAlgorithm::split(&self.digest).0
+    }
+
     /// Return the optional architecture tag.
     pub fn arch(&self) -> Option<String> {
         self.arch.clone()
@@ -95,6 +101,11 @@ impl Id {
         self.version.clone()
     }
 
+    /// Replace the name, sanitizing it like [`Name`] does.
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.into();
+    }
+
     /// Replace the digest with a new value.
     pub fn set_digest(&mut self, digest: &str) {
         self.digest = digest.to_string();
@@ -129,12 +140,73 @@ impl Id {
         }
         prefix
     }
+
+    /// Format this id as a reference, `[<package>+]<name>[@<version>]#<digest>[/<arch>]`.
+    ///
+    /// Unlike the [`Display`](fmt::Display) form every component has its own
+    /// separator, so references always parse back to the same id.
+    pub fn reference(&self) -> String {
+        let mut reference = String::default();
+        if let Some(package) = self.package() {
+            reference += package.as_str();
+            reference += "+";
+        }
+        reference += self.name().as_str();
+        if let Some(version) = self.version() {
+            reference += "@";
+            reference += version.to_string().as_str();
+        }
+        reference += "#";
+        reference += self.digest();
+        if let Some(arch) = self.arch() {
+            reference += "/";
+            reference += arch.as_str();
+        }
+        reference
+    }
+
+    /// Parse a reference produced by [`Id::reference`].
+    pub fn parse_reference(s: &str) -> std::result::Result<Self, super::error::StorageError> {
+        let invalid = || error::IdSnafu {
+            reason: format!("'{s}' is not a valid artifact reference"),
+        };
+        let (package, rest) = match s.split_once('+') {
+            Some((package, rest)) => (Some(package.into()), rest),
+            None => (None, s),
+        };
+        let (head, tail) = rest.split_once('#').context(invalid())?;
+        let (digest, arch) = match tail.split_once('/') {
+            Some((digest, arch)) => (digest, Some(arch.to_string())),
+            None => (tail, None),
+        };
+        let (name, version) = match head.split_once('@') {
+            Some((name, version)) => (
+                name,
+                Some(Version::parse(version).context(error::SemverSnafu)?),
+            ),
+            None => (head, None),
+        };
+        ensure!(
+            !name.is_empty() && !digest.is_empty() && arch.as_ref().is_none_or(|x| !x.is_empty()),
+            invalid()
+        );
+        Ok(Self {
+            name: name.into(),
+            package,
+            version,
+            arch,
+            digest: digest.into(),
+        })
+    }
 }
 
 impl FromStr for Id {
     type Err = super::error::StorageError;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if s.contains('#') {
+            return Self::parse_reference(s);
+        }
         let (package, s) = if s.contains('+') {
             let (left, right) = s.split_once('+').unwrap();
             (Some(left.into()), right)
@@ -160,20 +232,24 @@ impl FromStr for Id {
                 digest: segments[1].into(),
             })
         } else if segments.len() == 3 {
-            let (version, arch) = if segments[1].contains(".") {
-                segments[0]
-                    .split_once(".")
-                    .map(|(x, y)| (x, Some(y)))
-                    .unwrap()
-            } else {
-                (segments[0], None)
+            // Versions contain dots themselves, so an arch suffix is only
+            // split off when the whole segment is not a valid version
+            let (version, arch) = match Version::parse(segments[1]) {
+                Ok(version) => (version, None),
+                Err(e) => match segments[1].rsplit_once(".") {
+                    Some((version, arch)) => (
+                        Version::parse(version).context(error::SemverSnafu)?,
+                        Some(arch),
+                    ),
+                    None => return Err(e).context(error::SemverSnafu),
+                },
             };
             Ok(Self {
                 name: segments[0].into(),
                 package,
-                version: Some(Version::parse(version).context(error::SemverSnafu)?),
+                version: Some(version),
                 arch: arch.map(|x| x.into()),
-                digest: segments[1].into(),
+                digest: segments[2].into(),
             })
         } else {
             error::IdSnafu {
@@ -222,3 +298,59 @@ impl<'de> Deserialize<'de> for Id {
         Self::from_str(string.as_str()).map_err(serde::de::Error::custom)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full() -> Id {
+        Id::builder()
+            .name("hello")
+            .package("tools")
+            .version(Version::new(1, 2, 3))
+            .arch("x86_64")
+            .digest("abc123".to_string())
+            .build()
+    }
+
+    #[test]
+    fn reference_round_trips() {
+        let id = full();
+        assert_eq!(id.reference(), "tools+hello@1.2.3#abc123/x86_64");
+        assert_eq!(Id::from_str(&id.reference()).unwrap(), id);
+
+        let bare = Id::builder()
+            .name("hello")
+            .digest("abc123".to_string())
+            .build();
+        assert_eq!(bare.reference(), "hello#abc123");
+        assert_eq!(Id::from_str("hello#abc123").unwrap(), bare);
+    }
+
+    #[test]
+    fn display_form_parses_with_version() {
+        let id = full();
+        assert_eq!(id.to_string(), "tools+hello-1.2.3.x86_64-abc123");
+        assert_eq!(Id::from_str(&id.to_string()).unwrap(), id);
+
+        let mut plain = full();
+        plain.arch = None;
+        assert_eq!(Id::from_str(&plain.to_string()).unwrap(), plain);
+    }
+
+    #[test]
+    fn digests_may_name_their_algorithm() {
+        let id = Id::from_str("hello-sha256:abc123").unwrap();
+        assert_eq!(id.digest(), "sha256:abc123");
+        assert_eq!(id.algorithm(), DigestAlgorithm::Sha256);
+        assert_eq!(Id::from_str(&id.to_string()).unwrap(), id);
+        assert_eq!(full().algorithm(), DigestAlgorithm::Blake3);
+    }
+
+    #[test]
+    fn invalid_references_are_rejected() {
+        for input in ["hello#", "#abc", "hello@1.x#abc", "hello#abc/"] {
+            assert!(Id::parse_reference(input).is_err(), "{input}");
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Artifacts injected from a directory or tarball on the host.
+//!
+//! `edo run --override //dep=path` stands a locally built tree in for the
+//! artifact of `//dep` so its dependents can be iterated on without building
+//! it. The injected artifact holds a single uncompressed tar layer without a
+//! platform, so every dependent stages it, and its id is derived from that
+//! layer's digest so dependents built against it never share ids with builds
+//! against the real artifact.
+
+use std::path::Path;
+
+use snafu::{ResultExt, ensure};
+use tokio::io::AsyncWriteExt;
+
+use super::transcode::Transcode;
+use super::{Artifact, Compression, Id, MediaType, Storage, StorageResult, error};
+
+/// Saves the directory or tarball at `path` as an artifact named `name` in
+/// the local cache.
+pub(super) async fn inject(storage: &Storage, name: &str, path: &Path) -> StorageResult<Artifact> {
+    let metadata = tokio::fs::metadata(path)
+        .await
+        .map_err(|e| invalid(path, &e.to_string()))?;
+    // The id depends on the layer, so it is only known once it is written
+    let mut tx = storage.begin_artifact(
+        &Id::builder()
+            .name(name.to_string())
+            .digest(String::new())
+            .build(),
+    );
+    let mut writer = tx.start_layer().await?;
+    if metadata.is_dir() {
+        let mut archive = tokio_tar::Builder::new(writer.clone());
+        archive
+            .append_dir_all(".", path)
+            .await
+            .context(error::IoSnafu)?;
+        archive.finish().await.context(error::IoSnafu)?;
+    } else {
+        let file_name = path
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let (stem, compression) = match file_name.strip_suffix(".tgz") {
+            Some(stem) => (format!("{stem}.tar"), Compression::Gzip),
+            None => Compression::detect(&file_name)?,
+        };
+        ensure!(
+            stem.ends_with(".tar"),
+            error::InjectSnafu {
+                path: path.display().to_string(),
+                reason: "expected a directory or a tarball",
+            }
+        );
+        let file = tokio::fs::File::open(path).await.context(error::IoSnafu)?;
+        let mut reader = Transcode::Decode(compression).apply(file);
+        tokio::io::copy(&mut reader, &mut writer)
+            .await
+            .context(error::IoSnafu)?;
+    }
+    writer.flush().await.context(error::IoSnafu)?;
+    let layer = tx
+        .finish_layer(&MediaType::Tar(Compression::None), None, &writer)
+        .await?;
+    let digest = blake3::hash(format!("override:{}", layer.digest().digest()).as_bytes())
+        .to_hex()
+        .to_string();
+    *tx.artifact_mut().config_mut().id_mut() =
+        Id::builder().name(name.to_string()).digest(digest).build();
+    tx.commit().await
+}
+
+fn invalid(path: &Path, reason: &str) -> error::StorageError {
+    error::InjectSnafu {
+        path: path.display().to_string(),
+        reason,
+    }
+    .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::{Backend, InMemoryBackend, Storage};
+    use futures::StreamExt;
+    use tokio::io::AsyncReadExt;
+
+    async fn storage() -> Storage {
+        Storage::init(&Backend::new(InMemoryBackend::new()))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn directories_are_injected_as_one_tar_layer() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("hello.txt"), "hello").unwrap();
+        let storage = storage().await;
+        let artifact = storage.inject("dep", dir.path()).await.unwrap();
+        assert!(storage.has_injected().await);
+        assert_eq!(artifact.config().id().name(), "dep");
+        assert_eq!(artifact.layers().len(), 1);
+        assert!(artifact.layers()[0].platform().is_none());
+
+        let mut reader = storage.safe_read(&artifact.layers()[0]).await.unwrap();
+        let mut tar = Vec::new();
+        reader.read_to_end(&mut tar).await.unwrap();
+        let mut archive = tokio_tar::Archive::new(tar.as_slice());
+        let mut entries = archive.entries().unwrap();
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next().await {
+            names.push(entry.unwrap().path().unwrap().display().to_string());
+        }
+        assert!(names.iter().any(|x| x.ends_with("hello.txt")), "{names:?}");
+    }
+
+    #[tokio::test]
+    async fn other_files_are_rejected() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("hello.zip");
+        std::fs::write(&file, "hello").unwrap();
+        let error = storage().await.inject("dep", &file).await.unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("expected a directory or a tarball"),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! Export of artifacts as OCI image layouts.
+//!
+//! An OCI image layout is a directory holding an `oci-layout` marker, an
+//! `index.json` and every blob under `blobs/sha256/`, which tools such as
+//! `skopeo copy oci:<dir>:<name>` or `crane` read without a registry. Every
+//! tar layer of an artifact becomes a layer of a single platform image whose
+//! config records the uncompressed digest of each layer. Layouts are
+//! written without timestamps, so exporting the same artifact twice gives
+//! byte for byte the same image.
+
+use super::{
+    Artifact, Compression, DigestAlgorithm, Id, MediaType, Storage, StorageResult, error,
+    transcode::Transcode,
+};
+use crate::util::Reader;
+use serde_json::{Value, json};
+use snafu::ResultExt;
+use std::path::Path;
+use tokio::fs::File;
+
+/// Name of the file marking a directory as an OCI image layout.
+pub const OCI_LAYOUT_FILE: &str = "oci-layout";
+/// Name of the image index of an OCI image layout.
+pub const OCI_INDEX_FILE: &str = "index.json";
+/// Annotation naming an image in the index, `<name>` in `oci:<dir>:<name>`.
+pub const OCI_REF_NAME: &str = "org.opencontainers.image.ref.name";
+
+const INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.image.config.v1+json";
+
+/// A blob written to the layout.
+struct Blob {
+    digest: String,
+    size: u64,
+}
+
+impl Blob {
+    fn descriptor(&self, media_type: &str) -> Value {
+        json!({
+            "mediaType": media_type,
+            "digest": self.digest,
+            "size": self.size,
+        })
+    }
+}
+
+/// The name an artifact is listed under in an index, its id's name with
+/// anything a reference may not contain replaced by `-`.
+pub fn oci_ref_name(id: &Id) -> String {
+    id.name()
+        .chars()
+        .map(|x| {
+            if x.is_ascii_alphanumeric() || "-.:@/+".contains(x) {
+                x
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Writes `artifact` as an image to the OCI image layout at `dir`, creating
+/// the layout if needed, and returns the digest of its manifest.
+///
+/// An image already listed under the same name is replaced, other images of
+/// an existing layout are kept. Layers that are not tarballs are skipped,
+/// as are tarballs compressed with anything but gzip or zstd, which the
+/// image specification has no media type for.
+pub async fn export_oci_layout(
+    storage: &Storage,
+    artifact: &Artifact,
+    dir: &Path,
+) -> StorageResult<String> {
+    let blobs = dir.join("blobs").join(DigestAlgorithm::Sha256.name());
+    tokio::fs::create_dir_all(&blobs)
+        .await
+        .context(error::IoSnafu)?;
+
+    let mut layers = Vec::new();
+    let mut diff_ids = Vec::new();
+    let mut platform = None;
+    for layer in artifact.layers() {
+        let (MediaType::Tar(compression), Some(media_type)) =
+            (layer.media_type(), oci_layer_media_type(layer.media_type()))
+        else {
+            warn!(
+                component = "storage",
+                "skipping layer {} with media type {}, it is not an OCI image layer",
+                layer.digest(),
+                layer.media_type()
+            );
+            continue;
+        };
+        let reader =
+            Reader::new(storage.safe_read(layer).await?).with_algorithm(DigestAlgorithm::Sha256);
+        let blob = write_blob(&blobs, reader).await?;
+        let diff_id = match compression {
+            Compression::None => blob.digest.clone(),
+            compression => {
+                let path = blobs.join(blob_hex(&blob));
+                let file = File::open(&path).await.context(error::IoSnafu)?;
+                let mut reader = Reader::new(Transcode::Decode(compression.clone()).apply(file))
+                    .with_algorithm(DigestAlgorithm::Sha256);
+                tokio::io::copy(&mut reader, &mut tokio::io::sink())
+                    .await
+                    .context(error::IoSnafu)?;
+                format!("sha256:{}", reader.finish())
+            }
+        };
+        if platform.is_none() {
+            platform.clone_from(layer.platform());
+        }
+        layers.push(blob.descriptor(media_type));
+        diff_ids.push(Value::String(diff_id));
+    }
+
+    let platform = platform.unwrap_or_default();
+    let config = json!({
+        "architecture": platform.architecture,
+        "os": platform.os,
+        "config": {},
+        "rootfs": {
+            "type": "layers",
+            "diff_ids": diff_ids,
+        },
+    });
+    let config = write_json_blob(&blobs, &config).await?;
+    let manifest = json!({
+        "schemaVersion": 2,
+        "mediaType": MANIFEST_MEDIA_TYPE,
+        "config": config.descriptor(CONFIG_MEDIA_TYPE),
+        "layers": layers,
+    });
+    let manifest = write_json_blob(&blobs, &manifest).await?;
+
+    let name = oci_ref_name(artifact.config().id());
+    let mut descriptor = manifest.descriptor(MANIFEST_MEDIA_TYPE);
+    descriptor["platform"] = json!({
+        "architecture": platform.architecture,
+        "os": platform.os,
+    });
+    descriptor["annotations"] = json!({ OCI_REF_NAME: name });
+    let mut manifests = read_index(dir).await?;
+    manifests.retain(|x| x["annotations"][OCI_REF_NAME].as_str() != Some(name.as_str()));
+    manifests.push(descriptor);
+    let index = json!({
+        "schemaVersion": 2,
+        "mediaType": INDEX_MEDIA_TYPE,
+        "manifests": manifests,
+    });
+    write_json(&dir.join(OCI_INDEX_FILE), &index).await?;
+    write_json(
+        &dir.join(OCI_LAYOUT_FILE),
+        &json!({ "imageLayoutVersion": "1.0.0" }),
+    )
+    .await?;
+    Ok(manifest.digest)
+}
+
+/// The OCI media type of a tar layer, `None` for anything else.
+fn oci_layer_media_type(media_type: &MediaType) -> Option<&'static str> {
+    match media_type {
+        MediaType::Tar(Compression::None) => Some("application/vnd.oci.image.layer.v1.tar"),
+        MediaType::Tar(Compression::Gzip) => Some("application/vnd.oci.image.layer.v1.tar+gzip"),
+        MediaType::Tar(Compression::Zstd) => Some("application/vnd.oci.image.layer.v1.tar+zstd"),
+        _ => None,
+    }
+}
+
+fn blob_hex(blob: &Blob) -> &str {
+    DigestAlgorithm::split(&blob.digest).1
+}
+
+/// The image descriptors of the index at `dir`, empty when there is none.
+async fn read_index(dir: &Path) -> StorageResult<Vec<Value>> {
+    let path = dir.join(OCI_INDEX_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let bytes = tokio::fs::read(&path).await.context(error::IoSnafu)?;
+    let index: Value = serde_json::from_slice(&bytes).context(error::OciDocumentSnafu)?;
+    match index.get("manifests") {
+        Some(Value::Array(manifests)) => Ok(manifests.clone()),
+        None => Ok(Vec::new()),
+        Some(_) => error::OciLayoutSnafu {
+            reason: format!("{} has a malformed manifests list", path.display()),
+        }
+        .fail(),
+    }
+}
+
+/// Copies everything `reader` hashes with sha256 into `blobs`.
+async fn write_blob(blobs: &Path, mut reader: Reader) -> StorageResult<Blob> {
+    // Stream into a temporary file as the digest is only known at the end
+    let temp = blobs.join(format!(".tmp-{}", uuid::Uuid::now_v7()));
+    let mut file = File::create(&temp).await.context(error::IoSnafu)?;
+    let size = tokio::io::copy(&mut reader, &mut file)
+        .await
+        .context(error::IoSnafu)?;
+    drop(file);
+    let hex = reader.finish();
+    tokio::fs::rename(&temp, blobs.join(&hex))
+        .await
+        .context(error::IoSnafu)?;
+    Ok(Blob {
+        digest: format!("sha256:{hex}"),
+        size,
+    })
+}
+
+async fn write_json_blob(blobs: &Path, value: &Value) -> StorageResult<Blob> {
+    let bytes = serde_json::to_vec(value).context(error::OciDocumentSnafu)?;
+    let mut hasher = DigestAlgorithm::Sha256.hasher();
+    hasher.update(&bytes);
+    let hex = hasher.finalize();
+    tokio::fs::write(blobs.join(&hex), &bytes)
+        .await
+        .context(error::IoSnafu)?;
+    Ok(Blob {
+        digest: format!("sha256:{hex}"),
+        size: bytes.len() as u64,
+    })
+}
+
+async fn write_json(path: &Path, value: &Value) -> StorageResult<()> {
+    let bytes = serde_json::to_vec(value).context(error::OciDocumentSnafu)?;
+    tokio::fs::write(path, bytes).await.context(error::IoSnafu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Backend, Config, InMemoryBackend, Layer};
+    use tempfile::TempDir;
+    use tokio::io::AsyncWriteExt;
+
+    async fn write_layer(storage: &Storage, media_type: MediaType, data: &[u8]) -> Layer {
+        let mut writer = storage.safe_start_layer().await.unwrap();
+        writer.write_all(data).await.unwrap();
+        storage
+            .safe_finish_layer(&media_type, None, &writer)
+            .await
+            .unwrap()
+    }
+
+    async fn read_json(path: &Path) -> Value {
+        serde_json::from_slice(&tokio::fs::read(path).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn exports_tar_layers_as_an_image() {
+        let storage = Storage::init(&Backend::new(InMemoryBackend::new()))
+            .await
+            .unwrap();
+        let tar = write_layer(&storage, MediaType::Tar(Compression::None), b"tarball").await;
+        let file = write_layer(&storage, MediaType::File(Compression::None), b"notes").await;
+        let id = Id::builder()
+            .name("hello_world".to_string())
+            .digest("1".to_string())
+            .build();
+        let artifact = Artifact::builder()
+            .media_type(MediaType::Manifest)
+            .config(Config::builder().id(id).build())
+            .layers(vec![tar, file])
+            .build();
+
+        let dir = TempDir::new().unwrap();
+        let digest = export_oci_layout(&storage, &artifact, dir.path())
+            .await
+            .unwrap();
+        // Exporting again replaces the image instead of adding another one
+        assert_eq!(
+            export_oci_layout(&storage, &artifact, dir.path())
+                .await
+                .unwrap(),
+            digest
+        );
+
+        let layout = read_json(&dir.path().join(OCI_LAYOUT_FILE)).await;
+        assert_eq!(layout["imageLayoutVersion"], "1.0.0");
+        let index = read_json(&dir.path().join(OCI_INDEX_FILE)).await;
+        let manifests = index["manifests"].as_array().unwrap();
+        assert_eq!(manifests.len(), 1);
+        assert_eq!(manifests[0]["digest"], digest);
+        assert_eq!(manifests[0]["annotations"][OCI_REF_NAME], "hello-world");
+
+        let blobs = dir.path().join("blobs/sha256");
+        let manifest = read_json(&blobs.join(digest.trim_start_matches("sha256:"))).await;
+        let layers = manifest["layers"].as_array().unwrap();
+        assert_eq!(layers.len(), 1);
+        let mut hasher = DigestAlgorithm::Sha256.hasher();
+        hasher.update(b"tarball");
+        let expected = format!("sha256:{}", hasher.finalize());
+        assert_eq!(layers[0]["digest"], expected);
+        assert_eq!(layers[0]["size"], 7);
+        let config = manifest["config"]["digest"].as_str().unwrap();
+        let config = read_json(&blobs.join(config.trim_start_matches("sha256:"))).await;
+        assert_eq!(config["rootfs"]["diff_ids"], json!([expected]));
+    }
+}
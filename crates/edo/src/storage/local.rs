@@ -1,28 +1,43 @@
 use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
 
-use crate::context::{Addr, Config, FromNodeNoContext, Node};
+use crate::context::{
+    Addr, Config, Describe, FieldType, FromNodeNoContext, HealthCheck, KindSchema, Node,
+    check_directory,
+};
 use crate::non_configurable_no_context;
-use crate::storage::{Artifact, BackendImpl, Id, Layer, MediaType, StorageResult};
+use crate::storage::{
+    Artifact, BackendImpl, DEFAULT_HISTORY, DigestAlgorithm, Generation, Id, Layer, LayerDigest,
+    MediaType, StorageResult, TransferPolicy, digest_setting, history_setting, verify_setting,
+};
 use crate::util::{Reader, Writer};
 use async_trait::async_trait;
 use ocilot::models::Platform;
 use parking_lot::RwLock;
 use snafu::{OptionExt, ResultExt, ensure};
 use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncSeekExt;
 use uuid::Uuid;
 
+use super::TemporaryBlob;
 use super::catalog::Catalog;
 
+/// Suffix of the files layers are written to until they are finished.
+const TEMPORARY_SUFFIX: &str = ".tmp";
+
 /// Local filesystem storage backend.
 ///
-/// Layers are stored as individual blobs under `blobs/blake3/<digest>` and
-/// manifests are tracked in a JSON catalog file. The shared blob layout means
-/// copy operations are metadata-only.
+/// Layers are stored as individual blobs under `blobs/<algorithm>/<digest>`
+/// and manifests are tracked in a JSON catalog file. The shared blob layout
+/// means copy operations are metadata-only.
 #[derive(Debug)]
 pub struct LocalBackend {
-    layer_dir: PathBuf,
+    blob_dir: PathBuf,
     catalog_file: RwLock<PathBuf>,
+    transfer: TransferPolicy,
+    verify: bool,
+    algorithm: DigestAlgorithm,
+    history: usize,
 }
 
 #[async_trait]
@@ -32,19 +47,41 @@ impl FromNodeNoContext for LocalBackend {
     async fn from_node(
         _addr: &Addr,
         node: &Node,
-        _config: &Config,
+        config: &Config,
     ) -> std::result::Result<Self, Self::Error> {
         node.validate_keys(&["path"])?;
+        // The `[storage]` table of the user configuration picks the default
+        // algorithm and history
+        let (default, history) = match config.get("storage") {
+            Some(storage) => (
+                digest_setting(&storage, DigestAlgorithm::default())?,
+                history_setting(&storage, DEFAULT_HISTORY)?,
+            ),
+            None => (DigestAlgorithm::default(), DEFAULT_HISTORY),
+        };
         let path = node
             .get("path")
             .and_then(|x| x.as_string())
             .context(error::PathNotSpecifiedSnafu)?;
-        Self::new_(path).await
+        let mut backend = Self::new_(path).await?;
+        backend.transfer = TransferPolicy::from_node(node)?;
+        backend.verify = verify_setting(node, false)?;
+        backend.algorithm = digest_setting(node, default)?;
+        backend.history = history_setting(node, history)?;
+        Ok(backend)
     }
 }
 
 non_configurable_no_context!(LocalBackend, crate::storage::StorageError);
 
+impl Describe for LocalBackend {
+    fn describe() -> KindSchema {
+        KindSchema::new("A cache in a directory on this machine")
+            .required("path", FieldType::String, "Directory holding the cache")
+            .fields(super::cache_settings())
+    }
+}
+
 unsafe impl Send for LocalBackend {}
 unsafe impl Sync for LocalBackend {}
 
@@ -64,15 +101,20 @@ impl LocalBackend {
                 .context(error::NewSnafu)?;
         }
         let catalog_file = path.join("catalog.json");
-        let layer_dir = path.join("blobs/blake3");
-        if !layer_dir.exists() {
-            tokio::fs::create_dir_all(&layer_dir)
+        let blob_dir = path.join("blobs");
+        let default_dir = blob_dir.join(DigestAlgorithm::default().name());
+        if !default_dir.exists() {
+            tokio::fs::create_dir_all(&default_dir)
                 .await
                 .context(error::NewSnafu)?;
         }
         Ok(Self {
-            layer_dir,
+            blob_dir,
             catalog_file: RwLock::new(catalog_file),
+            transfer: TransferPolicy::default(),
+            verify: false,
+            algorithm: DigestAlgorithm::default(),
+            history: DEFAULT_HISTORY,
         })
     }
 }
@@ -82,20 +124,22 @@ impl LocalBackend {
         if !path.exists() {
             return Ok(Catalog::default());
         }
-        let mut reader = std::fs::File::open(path).context(error::ReadCatalogSnafu)?;
-        let catalog: Catalog =
-            serde_json::from_reader(&mut reader).context(error::DeserializeSnafu)?;
-        Ok(catalog)
+        let bytes = std::fs::read(path).context(error::ReadCatalogSnafu)?;
+        Catalog::from_slice(&bytes)
     }
 
     fn flush_at(path: &Path, catalog: &Catalog) -> StorageResult<()> {
+        // Written aside and renamed over the catalog, so an edo process
+        // reading this cache as its parent never sees half a catalog
+        let partial = path.with_file_name(format!(".catalog.json.{}", std::process::id()));
         let mut writer = std::fs::OpenOptions::new()
             .create(true)
             .write(true)
             .truncate(true)
-            .open(path)
+            .open(&partial)
             .context(error::WriteCatalogSnafu)?;
         serde_json::to_writer(&mut writer, catalog).context(error::SerializeSnafu)?;
+        std::fs::rename(&partial, path).context(error::WriteCatalogSnafu)?;
         Ok(())
     }
 
@@ -117,6 +161,11 @@ impl BackendImpl for LocalBackend {
         Ok(catalog.has(id))
     }
 
+    async fn has_many(&self, ids: &[Id]) -> StorageResult<BTreeSet<Id>> {
+        let catalog = self.load()?;
+        Ok(ids.iter().filter(|x| catalog.has(x)).cloned().collect())
+    }
+
     async fn open(&self, id: &Id) -> StorageResult<Artifact> {
         let catalog = self.load()?;
         let artifact = catalog
@@ -125,14 +174,21 @@ impl BackendImpl for LocalBackend {
         Ok(artifact.clone())
     }
 
+    async fn has_layers(&self, artifact: &Artifact) -> StorageResult<bool> {
+        Ok(artifact
+            .layers()
+            .iter()
+            .all(|x| self.blob_dir.join(x.digest().path()).exists()))
+    }
+
     async fn save(&self, artifact: &Artifact) -> StorageResult<()> {
         // Before we allow the save we should validate that all layers exist
         for layer in artifact.layers() {
-            let blob_path = self.layer_dir.join(layer.digest().digest());
+            let blob_path = self.blob_dir.join(layer.digest().path());
             ensure!(
                 blob_path.exists(),
                 error::LayerMissingSnafu {
-                    digest: layer.digest().digest()
+                    digest: layer.digest().to_string()
                 }
             );
         }
@@ -163,11 +219,12 @@ impl BackendImpl for LocalBackend {
             artifact
         };
         for layer in artifact.layers() {
-            let digest = layer.digest().digest();
-            let blob_path = self.layer_dir.join(digest.clone());
-            let lock = self.catalog_file.read();
-            let catalog = Self::load_at(lock.as_path())?;
-            if catalog.count(layer) <= 0 && blob_path.exists() {
+            let blob_path = self.blob_dir.join(layer.digest().path());
+            let count = {
+                let lock = self.catalog_file.read();
+                Self::load_at(lock.as_path())?.count(layer)
+            };
+            if count <= 0 && blob_path.exists() {
                 tokio::fs::remove_file(&blob_path)
                     .await
                     .context(error::RemoveSnafu)?;
@@ -198,10 +255,7 @@ impl BackendImpl for LocalBackend {
         // To prune historical artifacts we want to load our catalog for the id prefix
         let catalog = self.load()?;
 
-        for entry in catalog.matching(id) {
-            if entry == *id {
-                continue;
-            }
+        for entry in catalog.stale(id, self.history) {
             info!(
                 section = "storage",
                 component = "backend",
@@ -213,13 +267,30 @@ impl BackendImpl for LocalBackend {
         Ok(())
     }
 
+    async fn history(&self, prefix: &str) -> StorageResult<Vec<Generation>> {
+        Ok(self.load()?.history(prefix))
+    }
+
+    async fn tag(&self, tag: &str, id: &Id) -> StorageResult<()> {
+        let lock = self.catalog_file.write();
+        let mut catalog = Self::load_at(lock.as_path())?;
+        ensure!(catalog.has(id), error::NotFoundSnafu { id: id.clone() });
+        catalog.tag(tag, id);
+        Self::flush_at(lock.as_path(), &catalog)?;
+        Ok(())
+    }
+
+    async fn resolve_tag(&self, tag: &str) -> StorageResult<Option<Id>> {
+        Ok(self.load()?.resolve(tag).cloned())
+    }
+
     #[allow(clippy::await_holding_lock)]
     async fn prune_all(&self) -> StorageResult<()> {
         let lock = self.catalog_file.write();
         tokio::fs::remove_file(lock.as_path())
             .await
             .context(error::RemoveSnafu)?;
-        tokio::fs::remove_dir_all(&self.layer_dir)
+        tokio::fs::remove_dir_all(&self.blob_dir)
             .await
             .context(error::RemoveSnafu)?;
         Ok(())
@@ -227,18 +298,30 @@ impl BackendImpl for LocalBackend {
 
     async fn read(&self, layer: &Layer) -> StorageResult<Reader> {
         // A Read is a pretty simple operation, we just want to load the correct blob file
-        let blob_digest = layer.digest().digest();
-        let blob_file = self.layer_dir.join(blob_digest);
+        let blob_file = self.blob_dir.join(layer.digest().path());
         Ok(Reader::new(
             File::open(&blob_file).await.context(error::ReadSnafu)?,
         ))
     }
 
+    async fn read_from(&self, layer: &Layer, offset: u64) -> StorageResult<Reader> {
+        let blob_file = self.blob_dir.join(layer.digest().path());
+        let mut file = File::open(&blob_file).await.context(error::ReadSnafu)?;
+        file.seek(std::io::SeekFrom::Start(offset))
+            .await
+            .context(error::ReadSnafu)?;
+        Ok(Reader::new(file))
+    }
+
     async fn start_layer(&self) -> StorageResult<Writer> {
         // A new layer starts its life as a temporary file
-        let tmp_name = format!("{}.tmp", Uuid::now_v7());
-        let file_path = self.layer_dir.join(tmp_name.clone());
-        Ok(Writer::new(
+        let dir = self.blob_dir.join(self.algorithm.name());
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .context(error::CreateSnafu)?;
+        let tmp_name = format!("{}/{}{TEMPORARY_SUFFIX}", self.algorithm, Uuid::now_v7());
+        let file_path = self.blob_dir.join(tmp_name.clone());
+        let writer = Writer::new(
             tmp_name.clone(),
             OpenOptions::new()
                 .create(true)
@@ -247,7 +330,9 @@ impl BackendImpl for LocalBackend {
                 .open(&file_path)
                 .await
                 .context(error::CreateSnafu)?,
-        ))
+        );
+        writer.set_algorithm(self.algorithm);
+        Ok(writer)
     }
 
     async fn finish_layer(
@@ -257,12 +342,17 @@ impl BackendImpl for LocalBackend {
         writer: &Writer,
     ) -> StorageResult<Layer> {
         // The writer will contain the temporary file name to use
-        let tmp_path = self.layer_dir.join(writer.target());
+        let tmp_path = self.blob_dir.join(writer.target());
         // Now we want to calculate the digest
-        let digest = writer.finish().await;
-        let target_path = self.layer_dir.join(digest.clone());
+        let digest = LayerDigest::new(writer.algorithm(), writer.finish().await);
+        let target_path = self.blob_dir.join(digest.path());
+        if let Some(parent) = target_path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .context(error::CreateSnafu)?;
+        }
         let layer = Layer::builder()
-            .digest(digest.clone())
+            .digest(digest)
             .media_type(media_type.clone())
             .size(writer.size())
             .maybe_platform(platform)
@@ -279,6 +369,124 @@ impl BackendImpl for LocalBackend {
         }
         Ok(layer)
     }
+
+    async fn temporary_blobs(&self) -> StorageResult<Option<Vec<TemporaryBlob>>> {
+        // Layers are written to `<algorithm>/<uuid>.tmp` until finished
+        let mut temporary = Vec::new();
+        if !self.blob_dir.exists() {
+            return Ok(Some(temporary));
+        }
+        let mut dirs = tokio::fs::read_dir(&self.blob_dir)
+            .await
+            .context(error::ReadSnafu)?;
+        while let Some(dir) = dirs.next_entry().await.context(error::ReadSnafu)? {
+            let Some(algorithm) = dir.file_name().to_str().map(|x| x.to_string()) else {
+                continue;
+            };
+            let mut entries = tokio::fs::read_dir(dir.path())
+                .await
+                .context(error::ReadSnafu)?;
+            while let Some(entry) = entries.next_entry().await.context(error::ReadSnafu)? {
+                let Some(name) = entry.file_name().to_str().map(|x| x.to_string()) else {
+                    continue;
+                };
+                if !name.ends_with(TEMPORARY_SUFFIX) {
+                    continue;
+                }
+                // Finished or aborted since the directory was read
+                let Ok(metadata) = entry.metadata().await else {
+                    continue;
+                };
+                temporary.push(TemporaryBlob {
+                    target: format!("{algorithm}/{name}"),
+                    size: metadata.len(),
+                    modified: metadata.modified().context(error::ReadSnafu)?,
+                });
+            }
+        }
+        Ok(Some(temporary))
+    }
+
+    async fn remove_temporary(&self, target: &str) -> StorageResult<()> {
+        // Only ever delete temporary blobs, whatever the target says
+        if !target.ends_with(TEMPORARY_SUFFIX) {
+            return Ok(());
+        }
+        let tmp_path = self.blob_dir.join(target);
+        if tmp_path.exists() {
+            tokio::fs::remove_file(&tmp_path)
+                .await
+                .context(error::RemoveSnafu)?;
+        }
+        Ok(())
+    }
+
+    async fn blobs(&self) -> StorageResult<Option<BTreeSet<String>>> {
+        // Blobs are named `<algorithm>:<file>` after the directory they are in
+        let mut blobs = BTreeSet::new();
+        let mut dirs = tokio::fs::read_dir(&self.blob_dir)
+            .await
+            .context(error::ReadSnafu)?;
+        while let Some(dir) = dirs.next_entry().await.context(error::ReadSnafu)? {
+            let Some(algorithm) = dir.file_name().to_str().map(|x| x.to_string()) else {
+                continue;
+            };
+            let mut entries = tokio::fs::read_dir(dir.path())
+                .await
+                .context(error::ReadSnafu)?;
+            while let Some(entry) = entries.next_entry().await.context(error::ReadSnafu)? {
+                // Layers still being written are not blobs yet
+                if let Some(name) = entry.file_name().to_str()
+                    && !name.ends_with(TEMPORARY_SUFFIX)
+                {
+                    blobs.insert(format!("{algorithm}:{name}"));
+                }
+            }
+        }
+        Ok(Some(blobs))
+    }
+
+    async fn remove_blob(&self, digest: &str) -> StorageResult<()> {
+        let blob_path = self.blob_dir.join(LayerDigest::from(digest).path());
+        if blob_path.exists() {
+            tokio::fs::remove_file(&blob_path)
+                .await
+                .context(error::RemoveSnafu)?;
+        }
+        Ok(())
+    }
+
+    fn transfer_policy(&self) -> TransferPolicy {
+        self.transfer.clone()
+    }
+
+    fn verify_reads(&self) -> bool {
+        self.verify
+    }
+
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        self.algorithm
+    }
+
+    async fn health(&self) -> Vec<HealthCheck> {
+        let root = self.blob_dir.parent().unwrap_or(&self.blob_dir);
+        let mut checks = check_directory(root);
+        let catalog = self.catalog_file.read().clone();
+        checks.push(match self.load() {
+            Ok(loaded) => {
+                HealthCheck::ok("catalog", format!("{} artifacts", loaded.list_all().len()))
+            }
+            Err(e) => HealthCheck::error(
+                "catalog",
+                e.to_string(),
+                format!(
+                    "move {} aside, its artifacts are then fetched or built again",
+                    catalog.display()
+                ),
+            ),
+        });
+        checks
+    }
 }
 
 pub(crate) mod error {
@@ -289,8 +497,6 @@ pub(crate) mod error {
     #[derive(Snafu, Debug)]
     #[snafu(visibility(pub(crate)))]
     pub(crate) enum Error {
-        #[snafu(display("failed to deserialize manifest: {source}"))]
-        Deserialize { source: serde_json::Error },
         #[snafu(display("failed to copy blob: {source}"))]
         Copy { source: std::io::Error },
         #[snafu(display("failed to create temporary file for new layer: {source}"))]
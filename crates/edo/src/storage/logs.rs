@@ -0,0 +1,65 @@
+//! Transform logs shipped next to the artifacts they built.
+//!
+//! A cached artifact may have been built on another machine, such as a CI
+//! runner, whose log files are gone. When `[scheduler] upload_logs` names a
+//! cache, the log of every transform that built is saved as a secondary
+//! artifact next to its output and uploaded there, so `edo logs --remote`
+//! can show how the artifact was produced. It has the id of the output
+//! renamed with a `_log` suffix and a distinct [`log_media_type`].
+
+use std::path::Path;
+
+use snafu::ResultExt;
+use tokio::io::AsyncWriteExt;
+
+use super::{Artifact, Compression, Id, MediaType, Storage, StorageResult, error};
+
+/// Name of the media type of shipped log artifacts.
+pub const LOG_MEDIA_TYPE: &str = "log";
+
+/// The media type of shipped log artifacts.
+pub fn log_media_type() -> MediaType {
+    MediaType::Custom(LOG_MEDIA_TYPE.to_string(), Compression::None)
+}
+
+/// The id of the log written while building the artifact `id`.
+pub fn log_id(id: &Id) -> Id {
+    let mut log = id.clone();
+    log.set_name(&format!("{}_log", id.name()));
+    log
+}
+
+/// Saves the log file at `path` in the local cache as the log artifact of
+/// the build `id`, a single uncompressed file layer.
+pub(super) async fn save_log(storage: &Storage, id: &Id, path: &Path) -> StorageResult<Artifact> {
+    let mut tx = storage.begin_artifact(&log_id(id));
+    *tx.artifact_mut().media_type_mut() = log_media_type();
+    let mut writer = tx.start_layer().await?;
+    let mut file = tokio::fs::File::open(path).await.context(error::IoSnafu)?;
+    tokio::io::copy(&mut file, &mut writer)
+        .await
+        .context(error::IoSnafu)?;
+    writer.flush().await.context(error::IoSnafu)?;
+    tx.finish_layer(&MediaType::File(Compression::None), None, &writer)
+        .await?;
+    tx.commit().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_are_named_after_the_output() {
+        let id = Id::builder()
+            .name("app".to_string())
+            .digest("abc".to_string())
+            .arch("x86_64".to_string())
+            .build();
+        let log = log_id(&id);
+        assert_eq!(log.name(), "app_log");
+        assert_eq!(log.digest(), id.digest());
+        assert_eq!(log.arch(), id.arch());
+        assert_eq!(log_media_type().to_string(), "vnd.edo.artifact.v1.log");
+    }
+}
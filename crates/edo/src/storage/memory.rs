@@ -0,0 +1,1226 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Poll;
+
+use crate::storage::{
+    Artifact, BackendImpl, Compression, DigestAlgorithm, Generation, Id, Layer, LayerDigest,
+    MediaType, StorageResult, TransferPolicy,
+};
+use crate::util::{Reader, Writer};
+use async_trait::async_trait;
+use ocilot::models::Platform;
+use parking_lot::{Mutex, RwLock};
+use snafu::{OptionExt, ensure};
+use tokio::io::{AsyncRead, AsyncWrite};
+use uuid::Uuid;
+
+use super::catalog::Catalog;
+
+/// A [`Backend`](super::Backend) operation, used to name the operation
+/// when injecting failures into an [`InMemoryBackend`] or a
+/// [`FaultyBackend`](super::FaultyBackend).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BackendOperation {
+    List,
+    Has,
+    Open,
+    Save,
+    Del,
+    Copy,
+    Prune,
+    History,
+    Tag,
+    ResolveTag,
+    PruneAll,
+    Read,
+    StartLayer,
+    FinishLayer,
+}
+
+impl fmt::Display for BackendOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::List => "list",
+            Self::Has => "has",
+            Self::Open => "open",
+            Self::Save => "save",
+            Self::Del => "del",
+            Self::Copy => "copy",
+            Self::Prune => "prune",
+            Self::History => "history",
+            Self::Tag => "tag",
+            Self::ResolveTag => "resolve_tag",
+            Self::PruneAll => "prune_all",
+            Self::Read => "read",
+            Self::StartLayer => "start_layer",
+            Self::FinishLayer => "finish_layer",
+        })
+    }
+}
+
+/// Storage backend that keeps blobs and the catalog in memory.
+///
+/// Intended for tests of code built on top of [`Storage`](super::Storage):
+/// nothing touches the filesystem, and individual operations can be told to
+/// fail with [`InMemoryBackend::fail`] or [`InMemoryBackend::fail_next`] to
+/// exercise error paths. Clones share the same contents, so a test can keep
+/// a clone around after handing another to [`Backend::new`](super::Backend::new).
+#[derive(Clone, Default)]
+pub struct InMemoryBackend {
+    inner: Arc<Inner>,
+    compression: Option<Compression>,
+    transfer: TransferPolicy,
+    verify: bool,
+    algorithm: DigestAlgorithm,
+    location: Option<String>,
+    history: usize,
+}
+
+#[derive(Default)]
+struct Inner {
+    catalog: RwLock<Catalog>,
+    blobs: RwLock<BTreeMap<String, Arc<[u8]>>>,
+    pending: Mutex<BTreeMap<String, Arc<Mutex<Vec<u8>>>>>,
+    // Remaining failures per operation, `None` fails every call
+    failures: Mutex<BTreeMap<BackendOperation, Option<usize>>>,
+    // Byte counts after which upcoming layer reads break off
+    interruptions: Mutex<Vec<usize>>,
+}
+
+impl InMemoryBackend {
+    /// Create an empty backend.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prefer uncompressed tar layers to be stored compressed with `compression`.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Copy layers to and from this backend following `policy`.
+    pub fn with_transfer_policy(mut self, policy: TransferPolicy) -> Self {
+        self.transfer = policy;
+        self
+    }
+
+    /// Re-hash layers read from this backend.
+    pub fn with_verify_reads(mut self) -> Self {
+        self.verify = true;
+        self
+    }
+
+    /// Address new layers by `algorithm` instead of BLAKE3.
+    pub fn with_digest_algorithm(mut self, algorithm: DigestAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Keep the `history` newest previous generations of an artifact when
+    /// pruning, instead of none.
+    pub fn with_history(mut self, history: usize) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Report `location` as where this backend lives, as a remote cache would.
+    pub fn with_location(mut self, location: impl Into<String>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+
+    /// Flip the first byte of a stored blob, as silent corruption would.
+    pub fn corrupt_blob(&self, digest: &str) {
+        let mut blobs = self.inner.blobs.write();
+        if let Some(blob) = blobs.get_mut(&LayerDigest::from(digest).to_string()) {
+            let mut data = blob.to_vec();
+            if let Some(first) = data.first_mut() {
+                *first ^= 0xff;
+            }
+            *blob = data.into();
+        }
+    }
+
+    /// Make the next layer read fail with an I/O error after `bytes` bytes.
+    pub fn interrupt_next_read(&self, bytes: usize) {
+        self.inner.interruptions.lock().push(bytes);
+    }
+
+    /// Make every subsequent call of `operation` fail until [`InMemoryBackend::recover`].
+    pub fn fail(&self, operation: BackendOperation) {
+        self.inner.failures.lock().insert(operation, None);
+    }
+
+    /// Make only the next call of `operation` fail.
+    pub fn fail_next(&self, operation: BackendOperation) {
+        let mut failures = self.inner.failures.lock();
+        match failures.get_mut(&operation) {
+            Some(None) => {}
+            Some(Some(count)) => *count += 1,
+            None => {
+                failures.insert(operation, Some(1));
+            }
+        }
+    }
+
+    /// Stop injecting failures into `operation`.
+    pub fn recover(&self, operation: BackendOperation) {
+        self.inner.failures.lock().remove(&operation);
+    }
+
+    /// Number of distinct blobs currently stored.
+    pub fn blob_count(&self) -> usize {
+        self.inner.blobs.read().len()
+    }
+
+    fn check(&self, operation: BackendOperation) -> StorageResult<()> {
+        let mut failures = self.inner.failures.lock();
+        let fail = match failures.get_mut(&operation) {
+            Some(None) => true,
+            Some(Some(count)) => {
+                *count -= 1;
+                if *count == 0 {
+                    failures.remove(&operation);
+                }
+                true
+            }
+            None => false,
+        };
+        ensure!(!fail, error::InjectedSnafu { operation });
+        Ok(())
+    }
+
+    fn remove(&self, id: &Id) -> StorageResult<()> {
+        let mut catalog = self.inner.catalog.write();
+        let Some(artifact) = catalog.get(id).cloned() else {
+            return Ok(());
+        };
+        catalog.del(id);
+        let mut blobs = self.inner.blobs.write();
+        for layer in artifact.layers() {
+            if catalog.count(layer) <= 0 {
+                blobs.remove(&layer.digest().to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BackendImpl for InMemoryBackend {
+    async fn list(&self) -> StorageResult<BTreeSet<Id>> {
+        self.check(BackendOperation::List)?;
+        Ok(self.inner.catalog.read().list_all())
+    }
+
+    async fn has(&self, id: &Id) -> StorageResult<bool> {
+        self.check(BackendOperation::Has)?;
+        Ok(self.inner.catalog.read().has(id))
+    }
+
+    async fn open(&self, id: &Id) -> StorageResult<Artifact> {
+        self.check(BackendOperation::Open)?;
+        let catalog = self.inner.catalog.read();
+        let artifact = catalog
+            .get(id)
+            .context(error::NotFoundSnafu { id: id.clone() })?;
+        Ok(artifact.clone())
+    }
+
+    async fn save(&self, artifact: &Artifact) -> StorageResult<()> {
+        self.check(BackendOperation::Save)?;
+        // Lock the catalog before the blobs, as `remove` does
+        let mut catalog = self.inner.catalog.write();
+        let blobs = self.inner.blobs.read();
+        for layer in artifact.layers() {
+            let digest = layer.digest().to_string();
+            ensure!(
+                blobs.contains_key(&digest),
+                error::LayerMissingSnafu { digest }
+            );
+        }
+        drop(blobs);
+        // Replacing a manifest must not leave its old blob references counted twice
+        catalog.del(artifact.config().id());
+        catalog.add(artifact);
+        Ok(())
+    }
+
+    async fn del(&self, id: &Id) -> StorageResult<()> {
+        self.check(BackendOperation::Del)?;
+        self.remove(id)
+    }
+
+    async fn copy(&self, from: &Id, to: &Id) -> StorageResult<()> {
+        self.check(BackendOperation::Copy)?;
+        let mut artifact = self
+            .inner
+            .catalog
+            .read()
+            .get(from)
+            .cloned()
+            .context(error::NotFoundSnafu { id: from.clone() })?;
+        *artifact.config_mut().id_mut() = to.clone();
+        self.inner.catalog.write().add(&artifact);
+        Ok(())
+    }
+
+    async fn prune(&self, id: &Id) -> StorageResult<()> {
+        self.check(BackendOperation::Prune)?;
+        let stale = self.inner.catalog.read().stale(id, self.history);
+        for entry in stale {
+            self.remove(&entry)?;
+        }
+        Ok(())
+    }
+
+    async fn history(&self, prefix: &str) -> StorageResult<Vec<Generation>> {
+        self.check(BackendOperation::History)?;
+        Ok(self.inner.catalog.read().history(prefix))
+    }
+
+    async fn tag(&self, tag: &str, id: &Id) -> StorageResult<()> {
+        self.check(BackendOperation::Tag)?;
+        let mut catalog = self.inner.catalog.write();
+        ensure!(catalog.has(id), error::NotFoundSnafu { id: id.clone() });
+        catalog.tag(tag, id);
+        Ok(())
+    }
+
+    async fn resolve_tag(&self, tag: &str) -> StorageResult<Option<Id>> {
+        self.check(BackendOperation::ResolveTag)?;
+        Ok(self.inner.catalog.read().resolve(tag).cloned())
+    }
+
+    async fn prune_all(&self) -> StorageResult<()> {
+        self.check(BackendOperation::PruneAll)?;
+        *self.inner.catalog.write() = Catalog::default();
+        self.inner.blobs.write().clear();
+        Ok(())
+    }
+
+    async fn read(&self, layer: &Layer) -> StorageResult<Reader> {
+        self.read_from(layer, 0).await
+    }
+
+    async fn read_from(&self, layer: &Layer, offset: u64) -> StorageResult<Reader> {
+        self.check(BackendOperation::Read)?;
+        let digest = layer.digest().to_string();
+        let blob = self
+            .inner
+            .blobs
+            .read()
+            .get(&digest)
+            .cloned()
+            .context(error::BlobNotFoundSnafu { digest })?;
+        let start = (offset as usize).min(blob.len());
+        let interruption = {
+            let mut interruptions = self.inner.interruptions.lock();
+            (!interruptions.is_empty()).then(|| interruptions.remove(0))
+        };
+        Ok(Reader::new(InterruptedReader {
+            data: blob,
+            position: start,
+            end: interruption.map(|x| start + x),
+        }))
+    }
+
+    async fn start_layer(&self) -> StorageResult<Writer> {
+        self.check(BackendOperation::StartLayer)?;
+        let target = Uuid::now_v7().to_string();
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        self.inner
+            .pending
+            .lock()
+            .insert(target.clone(), buffer.clone());
+        let writer = Writer::new(target, BlobWriter(buffer));
+        writer.set_algorithm(self.algorithm);
+        Ok(writer)
+    }
+
+    async fn finish_layer(
+        &self,
+        media_type: &MediaType,
+        platform: Option<Platform>,
+        writer: &Writer,
+    ) -> StorageResult<Layer> {
+        self.check(BackendOperation::FinishLayer)?;
+        let target = writer.target();
+        let buffer = self
+            .inner
+            .pending
+            .lock()
+            .remove(&target)
+            .context(error::UnknownWriterSnafu { target })?;
+        let digest = LayerDigest::new(writer.algorithm(), writer.finish().await);
+        let data: Arc<[u8]> = std::mem::take(&mut *buffer.lock()).into();
+        self.inner.blobs.write().insert(digest.to_string(), data);
+        Ok(Layer::builder()
+            .digest(digest)
+            .media_type(media_type.clone())
+            .size(writer.size())
+            .maybe_platform(platform)
+            .build())
+    }
+
+    fn compression(&self) -> Option<Compression> {
+        self.compression.clone()
+    }
+
+    async fn abort_layer(&self, writer: &Writer) -> StorageResult<()> {
+        self.inner.pending.lock().remove(&writer.target());
+        Ok(())
+    }
+
+    async fn blobs(&self) -> StorageResult<Option<BTreeSet<String>>> {
+        self.check(BackendOperation::List)?;
+        Ok(Some(self.inner.blobs.read().keys().cloned().collect()))
+    }
+
+    async fn remove_blob(&self, digest: &str) -> StorageResult<()> {
+        self.check(BackendOperation::Del)?;
+        self.inner
+            .blobs
+            .write()
+            .remove(&LayerDigest::from(digest).to_string());
+        Ok(())
+    }
+
+    fn transfer_policy(&self) -> TransferPolicy {
+        self.transfer.clone()
+    }
+
+    fn verify_reads(&self) -> bool {
+        self.verify
+    }
+
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        self.algorithm
+    }
+
+    fn location(&self) -> Option<String> {
+        self.location.clone()
+    }
+}
+
+// Serves a blob, failing with an I/O error once `end` is reached
+struct InterruptedReader {
+    data: Arc<[u8]>,
+    position: usize,
+    end: Option<usize>,
+}
+
+impl AsyncRead for InterruptedReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let limit = this.end.unwrap_or(this.data.len()).min(this.data.len());
+        if this.position >= limit && limit < this.data.len() {
+            return Poll::Ready(Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "injected interruption",
+            )));
+        }
+        let count = buf.remaining().min(limit - this.position);
+        buf.put_slice(&this.data[this.position..this.position + count]);
+        this.position += count;
+        Poll::Ready(Ok(()))
+    }
+}
+
+// Collects a layer's bytes until the backend moves them into the blob map
+struct BlobWriter(Arc<Mutex<Vec<u8>>>);
+
+impl AsyncWrite for BlobWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        self.0.lock().extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+pub(crate) mod error {
+    use snafu::Snafu;
+
+    use super::BackendOperation;
+    use crate::storage::StorageError;
+
+    #[derive(Snafu, Debug)]
+    #[snafu(visibility(pub(crate)))]
+    pub(crate) enum Error {
+        #[snafu(display(
+            "in-memory storage backend does not contain a blob with digest '{digest}'"
+        ))]
+        BlobNotFound { digest: String },
+        #[snafu(display("injected failure during {operation}"))]
+        Injected { operation: BackendOperation },
+        #[snafu(display("cannot save an artifact that is missing a layer with digest '{digest}'"))]
+        LayerMissing { digest: String },
+        #[snafu(display("storage backend does not contain an artifact with id: {id}"))]
+        NotFound { id: crate::storage::Id },
+        #[snafu(display("layer writer '{target}' was not started by this backend"))]
+        UnknownWriter { target: String },
+    }
+
+    impl From<Error> for StorageError {
+        fn from(value: Error) -> Self {
+            Self::Implementation {
+                source: Box::new(value),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{
+        AccessKind, Backend, CacheSelector, Config, FsckIssue, FsckOptions, LayerDigest,
+        LayerEncryption, Storage, fsck,
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn write_layer(backend: &InMemoryBackend, data: &[u8]) -> Layer {
+        let mut writer = backend.start_layer().await.unwrap();
+        writer.write_all(data).await.unwrap();
+        backend
+            .finish_layer(&MediaType::File(Compression::None), None, &writer)
+            .await
+            .unwrap()
+    }
+
+    fn artifact(name: &str, digest: &str, layers: Vec<Layer>) -> Artifact {
+        let id = Id::builder()
+            .name(name.to_string())
+            .digest(digest.to_string())
+            .build();
+        Artifact::builder()
+            .media_type(MediaType::Manifest)
+            .config(Config::builder().id(id).build())
+            .layers(layers)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn round_trips_layers_and_manifests() {
+        let backend = InMemoryBackend::new();
+        let layer = write_layer(&backend, b"hello").await;
+        let artifact = artifact("a", "1", vec![layer.clone()]);
+        backend.save(&artifact).await.unwrap();
+
+        let id = artifact.config().id();
+        assert!(backend.has(id).await.unwrap());
+        assert_eq!(backend.list().await.unwrap().len(), 1);
+        let opened = backend.open(id).await.unwrap();
+        assert_eq!(opened.layers()[0].size(), &5);
+
+        let mut reader = backend.read(&layer).await.unwrap();
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).await.unwrap();
+        assert_eq!(out, b"hello");
+    }
+
+    #[tokio::test]
+    async fn save_rejects_missing_layers() {
+        let backend = InMemoryBackend::new();
+        let other = InMemoryBackend::new();
+        let layer = write_layer(&other, b"elsewhere").await;
+        assert!(
+            backend
+                .save(&artifact("a", "1", vec![layer]))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn del_keeps_blobs_shared_with_copies() {
+        let backend = InMemoryBackend::new();
+        let layer = write_layer(&backend, b"shared").await;
+        let original = artifact("a", "1", vec![layer]);
+        backend.save(&original).await.unwrap();
+        let copy = Id::builder()
+            .name("a".to_string())
+            .digest("2".to_string())
+            .build();
+        backend.copy(original.config().id(), &copy).await.unwrap();
+
+        backend.del(original.config().id()).await.unwrap();
+        assert_eq!(backend.blob_count(), 1);
+        backend.del(&copy).await.unwrap();
+        assert_eq!(backend.blob_count(), 0);
+        assert!(backend.list().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn prune_removes_other_digests_with_the_same_prefix() {
+        let backend = InMemoryBackend::new();
+        let old = artifact("a", "1", vec![write_layer(&backend, b"old").await]);
+        let new = artifact("a", "2", vec![write_layer(&backend, b"new").await]);
+        backend.save(&old).await.unwrap();
+        backend.save(&new).await.unwrap();
+
+        backend.prune(new.config().id()).await.unwrap();
+        assert!(!backend.has(old.config().id()).await.unwrap());
+        assert!(backend.has(new.config().id()).await.unwrap());
+        assert_eq!(backend.blob_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn prune_keeps_the_newest_previous_generations() {
+        let backend = InMemoryBackend::new().with_history(1);
+        let mut saved = Vec::new();
+        for digest in ["1", "2", "3"] {
+            let layer = write_layer(&backend, digest.as_bytes()).await;
+            let artifact = artifact("a", digest, vec![layer]);
+            backend.save(&artifact).await.unwrap();
+            saved.push(artifact.config().id().clone());
+        }
+
+        backend.prune(&saved[2]).await.unwrap();
+        let history = backend.history(&saved[2].prefix()).await.unwrap();
+        let ids: Vec<&Id> = history.iter().map(|x| &x.id).collect();
+        assert_eq!(ids, [&saved[2], &saved[1]]);
+        assert_eq!(backend.blob_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn injected_failures_fail_until_recovered() {
+        let backend = InMemoryBackend::new();
+        let id = Id::builder()
+            .name("a".to_string())
+            .digest("1".to_string())
+            .build();
+
+        backend.fail_next(BackendOperation::Has);
+        let err = backend.has(&id).await.unwrap_err();
+        assert_eq!(err.to_string(), "injected failure during has");
+        assert!(backend.has(&id).await.is_ok());
+
+        backend.fail(BackendOperation::List);
+        assert!(backend.list().await.is_err());
+        assert!(backend.list().await.is_err());
+        backend.recover(BackendOperation::List);
+        assert!(backend.list().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn injected_failures_reach_history_and_tags() {
+        let backend = InMemoryBackend::new();
+        let saved = artifact("a", "1", vec![write_layer(&backend, b"tagged").await]);
+        backend.save(&saved).await.unwrap();
+        let id = saved.config().id();
+
+        backend.fail_next(BackendOperation::History);
+        assert!(backend.history(&id.prefix()).await.is_err());
+        backend.fail_next(BackendOperation::Tag);
+        assert!(backend.tag("latest", id).await.is_err());
+        backend.tag("latest", id).await.unwrap();
+        backend.fail_next(BackendOperation::ResolveTag);
+        assert!(backend.resolve_tag("latest").await.is_err());
+        assert_eq!(
+            backend.resolve_tag("latest").await.unwrap(),
+            Some(id.clone())
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn concurrent_saves_and_deletes_do_not_deadlock() {
+        let backend = InMemoryBackend::new();
+        let saved = artifact("a", "1", vec![write_layer(&backend, b"racy").await]);
+        let saver = {
+            let backend = backend.clone();
+            let saved = saved.clone();
+            tokio::spawn(async move {
+                for _ in 0..500 {
+                    backend.save(&saved).await.unwrap();
+                }
+            })
+        };
+        backend.save(&saved).await.unwrap();
+        let copy = Id::builder()
+            .name("a".to_string())
+            .digest("2".to_string())
+            .build();
+        for _ in 0..500 {
+            backend.copy(saved.config().id(), &copy).await.unwrap();
+            backend.del(&copy).await.unwrap();
+        }
+        saver.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn storage_downloads_from_in_memory_build_cache() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new();
+        let artifact = artifact("a", "1", vec![write_layer(&build, b"built").await]);
+        build.save(&artifact).await.unwrap();
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build.clone())).await;
+        let id = artifact.config().id();
+        assert!(storage.find_build(id, true).await.unwrap().is_some());
+        assert!(local.has(id).await.unwrap());
+        assert_eq!(local.blob_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn storage_imports_from_a_selected_cache() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new();
+        let artifact = artifact("a", "1", vec![write_layer(&build, b"built").await]);
+        build.save(&artifact).await.unwrap();
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        let cache: CacheSelector = "//edo-build-cache".parse().unwrap();
+        assert_eq!(cache, CacheSelector::Build);
+        let id = artifact.config().id();
+        assert!(storage.import(id, &cache).await.is_err());
+        storage.set_build(&Backend::new(build)).await;
+        assert!(storage.import(id, &cache).await.unwrap());
+        assert!(local.has(id).await.unwrap());
+        let missing = Id::builder()
+            .name("b".to_string())
+            .digest("2".to_string())
+            .build();
+        assert!(!storage.import(&missing, &cache).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn storage_publishes_aliases_to_the_output_cache() {
+        let local = InMemoryBackend::new();
+        let output = InMemoryBackend::new();
+        let first = artifact("a", "1", vec![write_layer(&local, b"first").await]);
+        let second = artifact("a", "2", vec![write_layer(&local, b"second").await]);
+        local.save(&first).await.unwrap();
+        local.save(&second).await.unwrap();
+
+        let storage = Storage::init(&Backend::new(local)).await.unwrap();
+        let (first, second) = (first.config().id(), second.config().id());
+        assert!(storage.publish(first, &["latest".into()]).await.is_err());
+        storage.set_output(&Backend::new(output.clone())).await;
+        let tags = storage
+            .publish(first, &["latest".into(), "a:{digest}".into()])
+            .await
+            .unwrap();
+        assert_eq!(tags, ["a:latest", "a:1"]);
+        assert!(output.has(first).await.unwrap());
+        // Publishing again moves the alias, nothing is left half published
+        assert!(storage.publish(second, &["{arch}".into()]).await.is_err());
+        assert!(!output.has(second).await.unwrap());
+        storage.publish(second, &["latest".into()]).await.unwrap();
+        assert_eq!(
+            storage.resolve_tag("a:latest").await.unwrap().as_ref(),
+            Some(second)
+        );
+        assert_eq!(
+            storage.resolve_tag("a:1").await.unwrap().as_ref(),
+            Some(first)
+        );
+        assert_eq!(storage.resolve_tag("a:2").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn storage_drops_encryption_of_downloaded_layers() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new();
+        let mut layer = write_layer(&build, b"built").await;
+        *layer.encryption_mut() = Some(LayerEncryption {
+            scheme: "aes-256-gcm".to_string(),
+            key: "0011223344556677".to_string(),
+        });
+        let artifact = artifact("a", "1", vec![layer]);
+        build.save(&artifact).await.unwrap();
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build)).await;
+        let id = artifact.config().id();
+        storage.find_build(id, true).await.unwrap();
+        let downloaded = local.open(id).await.unwrap();
+        assert!(downloaded.layers()[0].encryption().is_none());
+    }
+
+    #[tokio::test]
+    async fn storage_reads_built_artifacts_from_the_parent_cache() {
+        let local = InMemoryBackend::new();
+        let parent = InMemoryBackend::new();
+        let build = InMemoryBackend::new();
+        let built = artifact("a", "1", vec![write_layer(&parent, b"built").await]);
+        parent.save(&built).await.unwrap();
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_parent(&Backend::new(parent.clone())).await;
+        storage.set_build(&Backend::new(build.clone())).await;
+        let id = built.config().id();
+        assert!(storage.find_build(id, true).await.unwrap().is_some());
+        assert!(local.has(id).await.unwrap());
+        // Uploads go to the build cache, never to the parent
+        let other = artifact("b", "2", vec![write_layer(&local, b"other").await]);
+        local.save(&other).await.unwrap();
+        storage.upload_build(other.config().id()).await.unwrap();
+        assert!(build.has(other.config().id()).await.unwrap());
+        assert!(!parent.has(other.config().id()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn storage_finds_builds_across_caches_at_once() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new();
+        let here = artifact("a", "1", vec![write_layer(&local, b"here").await]);
+        local.save(&here).await.unwrap();
+        let remote = artifact("b", "2", vec![write_layer(&build, b"remote").await]);
+        build.save(&remote).await.unwrap();
+        let missing = artifact("c", "3", Vec::new());
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build.clone())).await;
+        let ids = [
+            here.config().id().clone(),
+            remote.config().id().clone(),
+            missing.config().id().clone(),
+        ];
+        let found = storage.find_builds(&ids).await.unwrap();
+        assert_eq!(found, BTreeSet::from([ids[0].clone(), ids[1].clone()]));
+        // Nothing is downloaded
+        assert!(!local.has(&ids[1]).await.unwrap());
+
+        storage.set_offline(true).await;
+        let found = storage.find_builds(&ids).await.unwrap();
+        assert_eq!(found, BTreeSet::from([ids[0].clone()]));
+    }
+
+    #[tokio::test]
+    async fn storage_rebuilds_artifacts_missing_remote_layers() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new();
+        let lost = write_layer(&build, b"lost").await;
+        let partial = artifact("a", "1", vec![lost.clone()]);
+        build.save(&partial).await.unwrap();
+        build.remove_blob(&lost.digest().digest()).await.unwrap();
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build)).await;
+        let id = partial.config().id();
+        assert!(storage.find_build(id, true).await.unwrap().is_none());
+        assert!(!local.has(id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn storage_streams_builds_without_downloading_them() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new();
+        let remote = artifact("a", "1", vec![write_layer(&build, b"remote").await]);
+        build.save(&remote).await.unwrap();
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build)).await;
+        let id = remote.config().id();
+        let stream = storage.stream_build(id).await.unwrap().unwrap();
+        assert!(stream.is_remote());
+        let mut content = Vec::new();
+        stream
+            .read(&stream.artifact().layers()[0])
+            .await
+            .unwrap()
+            .read_to_end(&mut content)
+            .await
+            .unwrap();
+        assert_eq!(content, b"remote");
+        assert!(!local.has(id).await.unwrap());
+
+        storage.find_build(id, true).await.unwrap();
+        assert!(!storage.stream_build(id).await.unwrap().unwrap().is_remote());
+    }
+
+    #[tokio::test]
+    async fn storage_audits_remote_cache_transfers() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new().with_location("s3://cache/builds");
+        let layer = write_layer(&build, b"built").await;
+        let artifact = artifact("a", "1", vec![layer.clone()]);
+        build.save(&artifact).await.unwrap();
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build)).await;
+        let id = artifact.config().id();
+        storage.find_build(id, true).await.unwrap();
+        storage.upload_build(id).await.unwrap();
+
+        let accesses = storage.audit().take();
+        assert_eq!(accesses.len(), 2);
+        assert_eq!(accesses[0].kind, AccessKind::Download);
+        assert_eq!(accesses[0].url, "s3://cache/builds");
+        assert_eq!(accesses[0].subject, Some(id.to_string()));
+        assert_eq!(accesses[0].digests, vec![layer.digest().to_string()]);
+        assert_eq!(accesses[1].kind, AccessKind::Upload);
+    }
+
+    #[tokio::test]
+    async fn storage_does_not_audit_local_caches() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new();
+        let artifact = artifact("a", "1", vec![write_layer(&build, b"built").await]);
+        build.save(&artifact).await.unwrap();
+
+        let storage = Storage::init(&Backend::new(local)).await.unwrap();
+        storage.set_build(&Backend::new(build)).await;
+        storage
+            .find_build(artifact.config().id(), true)
+            .await
+            .unwrap();
+        assert!(storage.audit().accesses().is_empty());
+    }
+
+    #[tokio::test]
+    async fn storage_surfaces_backend_failures() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new();
+        let artifact = artifact("a", "1", vec![write_layer(&build, b"built").await]);
+        build.save(&artifact).await.unwrap();
+        build.fail(BackendOperation::Read);
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build)).await;
+        let id = artifact.config().id();
+        assert!(storage.find_build(id, true).await.is_err());
+        assert!(!local.has(id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn storage_compresses_tar_layers_for_preferring_backends() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new().with_compression(Compression::Zstd);
+        let data = b"pretend this is a tarball ".repeat(64);
+        let mut writer = local.start_layer().await.unwrap();
+        writer.write_all(&data).await.unwrap();
+        let raw = local
+            .finish_layer(&MediaType::Tar(Compression::None), None, &writer)
+            .await
+            .unwrap();
+        let file = write_layer(&local, b"left alone").await;
+        let artifact = artifact("a", "1", vec![raw.clone(), file.clone()]);
+        local.save(&artifact).await.unwrap();
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build.clone())).await;
+        let id = artifact.config().id();
+        storage.upload_build(id).await.unwrap();
+
+        let uploaded = build.open(id).await.unwrap();
+        let compressed = &uploaded.layers()[0];
+        assert_eq!(compressed.media_type(), &MediaType::Tar(Compression::Zstd));
+        assert_eq!(
+            compressed.uncompressed().as_ref().map(|x| x.digest()),
+            Some(raw.digest().digest())
+        );
+        assert!(compressed.size() < raw.size());
+        assert_eq!(
+            uploaded.layers()[1].digest().digest(),
+            file.digest().digest()
+        );
+        assert!(uploaded.layers()[1].uncompressed().is_none());
+
+        // A fresh local cache gets the raw layer back with its original digest
+        let fresh = InMemoryBackend::new();
+        let storage = Storage::init(&Backend::new(fresh.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build)).await;
+        storage.find_build(id, true).await.unwrap();
+        let restored = fresh.open(id).await.unwrap();
+        let layer = &restored.layers()[0];
+        assert_eq!(layer.media_type(), &MediaType::Tar(Compression::None));
+        assert_eq!(layer.digest().digest(), raw.digest().digest());
+        assert!(layer.uncompressed().is_none());
+        let mut out = Vec::new();
+        fresh
+            .read(layer)
+            .await
+            .unwrap()
+            .read_to_end(&mut out)
+            .await
+            .unwrap();
+        assert_eq!(out, data);
+    }
+
+    #[tokio::test]
+    async fn storage_rejects_restored_layers_with_the_wrong_digest() {
+        let build = InMemoryBackend::new();
+        let mut writer = build.start_layer().await.unwrap();
+        writer.write_all(b"not even zstd").await.unwrap();
+        let mut layer = build
+            .finish_layer(&MediaType::Tar(Compression::None), None, &writer)
+            .await
+            .unwrap();
+        *layer.uncompressed_mut() = Some(LayerDigest::from("0".repeat(64)));
+        let artifact = artifact("a", "1", vec![layer]);
+        build.save(&artifact).await.unwrap();
+
+        let local = InMemoryBackend::new();
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build)).await;
+        let err = storage
+            .find_build(artifact.config().id(), true)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("expected blake3:0000"), "{err}");
+        assert!(!local.has(artifact.config().id()).await.unwrap());
+    }
+
+    fn retrying(retries: u32) -> TransferPolicy {
+        TransferPolicy::default().with_retries(retries, std::time::Duration::ZERO)
+    }
+
+    #[tokio::test]
+    async fn interrupted_downloads_resume_when_retries_are_allowed() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new().with_transfer_policy(retrying(1));
+        let artifact = artifact("a", "1", vec![write_layer(&build, b"hello world").await]);
+        build.save(&artifact).await.unwrap();
+        build.interrupt_next_read(5);
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build)).await;
+        let id = artifact.config().id();
+        storage.find_build(id, true).await.unwrap();
+        let restored = local.open(id).await.unwrap();
+        let mut out = Vec::new();
+        local
+            .read(&restored.layers()[0])
+            .await
+            .unwrap()
+            .read_to_end(&mut out)
+            .await
+            .unwrap();
+        assert_eq!(out, b"hello world");
+    }
+
+    #[tokio::test]
+    async fn interrupted_downloads_fail_without_retries() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new();
+        let artifact = artifact("a", "1", vec![write_layer(&build, b"hello world").await]);
+        build.save(&artifact).await.unwrap();
+        build.interrupt_next_read(5);
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build)).await;
+        let err = storage
+            .find_build(artifact.config().id(), true)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("injected interruption"), "{err}");
+        assert!(!local.has(artifact.config().id()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn interrupted_compressed_uploads_start_over() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new()
+            .with_compression(Compression::Gzip)
+            .with_transfer_policy(retrying(2));
+        let mut writer = local.start_layer().await.unwrap();
+        writer.write_all(&b"tarball ".repeat(128)).await.unwrap();
+        let raw = local
+            .finish_layer(&MediaType::Tar(Compression::None), None, &writer)
+            .await
+            .unwrap();
+        let artifact = artifact("a", "1", vec![raw.clone()]);
+        local.save(&artifact).await.unwrap();
+        local.interrupt_next_read(100);
+        build.fail_next(BackendOperation::FinishLayer);
+
+        let storage = Storage::init(&Backend::new(local)).await.unwrap();
+        storage.set_build(&Backend::new(build.clone())).await;
+        let id = artifact.config().id();
+        storage.upload_build(id).await.unwrap();
+
+        // The compressed copy still decompresses to the original layer
+        let fresh = InMemoryBackend::new();
+        let storage = Storage::init(&Backend::new(fresh.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build)).await;
+        storage.find_build(id, true).await.unwrap();
+        let restored = fresh.open(id).await.unwrap();
+        assert_eq!(
+            restored.layers()[0].digest().digest(),
+            raw.digest().digest()
+        );
+    }
+
+    #[tokio::test]
+    async fn fsck_reports_and_repairs_missing_and_orphaned_blobs() {
+        let backend = InMemoryBackend::new();
+        let kept = write_layer(&backend, b"kept").await;
+        let lost = write_layer(&backend, b"lost").await;
+        let orphan = write_layer(&backend, b"orphan").await;
+        backend
+            .save(&artifact("a", "1", vec![kept.clone()]))
+            .await
+            .unwrap();
+        let broken = artifact("b", "2", vec![kept, lost.clone()]);
+        backend.save(&broken).await.unwrap();
+        backend.remove_blob(&lost.digest().digest()).await.unwrap();
+
+        let handle = Backend::new(backend.clone());
+        let report = fsck(&handle, &FsckOptions::default()).await.unwrap();
+        assert_eq!(report.artifacts, 2);
+        assert_eq!(report.layers, 2);
+        assert_eq!(
+            report.issues,
+            vec![
+                FsckIssue::MissingLayer {
+                    id: broken.config().id().clone(),
+                    digest: lost.digest().to_string(),
+                },
+                FsckIssue::OrphanedBlob {
+                    digest: orphan.digest().to_string(),
+                },
+            ]
+        );
+        assert!(!report.is_clean());
+
+        let repair = FsckOptions::builder()
+            .drop_broken(true)
+            .delete_orphans(true)
+            .build();
+        let report = fsck(&handle, &repair).await.unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.dropped, vec![broken.config().id().clone()]);
+        assert!(!backend.has(broken.config().id()).await.unwrap());
+        assert_eq!(backend.blob_count(), 1);
+        assert!(
+            fsck(&handle, &FsckOptions::default())
+                .await
+                .unwrap()
+                .issues
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn storage_fsck_requires_a_configured_cache() {
+        let storage = Storage::init(&Backend::new(InMemoryBackend::new()))
+            .await
+            .unwrap();
+        let options = FsckOptions::default();
+        assert!(storage.fsck(&CacheSelector::Local, &options).await.is_ok());
+        let err = storage
+            .fsck(&CacheSelector::Build, &options)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no build cache"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn verified_downloads_reject_corrupt_blobs() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new().with_verify_reads();
+        let layer = write_layer(&build, b"built").await;
+        let artifact = artifact("a", "1", vec![layer.clone()]);
+        build.save(&artifact).await.unwrap();
+        build.corrupt_blob(&layer.digest().digest());
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build)).await;
+        let err = storage
+            .find_build(artifact.config().id(), true)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("failed verification"), "{err}");
+        assert!(!local.has(artifact.config().id()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn verified_downloads_start_over_after_interruptions() {
+        let local = InMemoryBackend::new();
+        let build = InMemoryBackend::new()
+            .with_verify_reads()
+            .with_transfer_policy(retrying(1));
+        let artifact = artifact("a", "1", vec![write_layer(&build, b"hello world").await]);
+        build.save(&artifact).await.unwrap();
+        build.interrupt_next_read(5);
+
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        storage.set_build(&Backend::new(build)).await;
+        let id = artifact.config().id();
+        storage.find_build(id, true).await.unwrap();
+        assert_eq!(local.blob_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn verified_local_reads_reject_corrupt_blobs() {
+        let local = InMemoryBackend::new().with_verify_reads();
+        let layer = write_layer(&local, b"kept").await;
+        let storage = Storage::init(&Backend::new(local.clone())).await.unwrap();
+        let mut out = Vec::new();
+        storage
+            .safe_read(&layer)
+            .await
+            .unwrap()
+            .read_to_end(&mut out)
+            .await
+            .unwrap();
+        assert_eq!(out, b"kept");
+
+        local.corrupt_blob(&layer.digest().digest());
+        let err = storage
+            .safe_read(&layer)
+            .await
+            .unwrap()
+            .read_to_end(&mut Vec::new())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn copies_keep_the_digest_algorithm_of_the_source() {
+        let local = InMemoryBackend::new().with_digest_algorithm(DigestAlgorithm::Sha256);
+        let build = InMemoryBackend::new().with_verify_reads();
+        let layer = write_layer(&local, b"abc").await;
+        assert_eq!(layer.digest().algorithm(), DigestAlgorithm::Sha256);
+        assert_eq!(
+            layer.digest().digest(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let artifact = artifact("a", "1", vec![layer.clone()]);
+        local.save(&artifact).await.unwrap();
+
+        let storage = Storage::init(&Backend::new(local)).await.unwrap();
+        storage.set_build(&Backend::new(build.clone())).await;
+        storage.upload_build(artifact.config().id()).await.unwrap();
+        let uploaded = build.open(artifact.config().id()).await.unwrap();
+        assert_eq!(uploaded.layers()[0].digest(), layer.digest());
+        let mut out = Vec::new();
+        build
+            .read(&uploaded.layers()[0])
+            .await
+            .unwrap()
+            .verify(layer.digest(), 3)
+            .read_to_end(&mut out)
+            .await
+            .unwrap();
+        assert_eq!(out, b"abc");
+    }
+}
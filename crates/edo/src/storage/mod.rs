@@ -4,33 +4,73 @@
 //! image layouts. [`Storage`] orchestrates multiple [`Backend`] caches (local,
 //! source, build, output) while [`Artifact`], [`Layer`], and [`Id`] describe
 //! the data model. The default [`LocalBackend`] persists blobs on the
-//! filesystem using BLAKE3 digests.
+//! filesystem using BLAKE3 digests, while [`InMemoryBackend`] keeps them in
+//! memory for tests. Transfers to and from remote caches are recorded in an
+//! [`Audit`] alongside the fetches sources report.
 //!
 //! All fallible operations return [`StorageResult`], with failures modelled by
 //! [`StorageError`].
 
 mod artifact;
+mod audit;
 mod backend;
 mod catalog;
+mod digest;
+mod encryption;
 pub mod error;
+mod fault;
+mod fsck;
 mod id;
+mod inject;
+mod layout;
 mod local;
+mod logs;
+mod memory;
+mod proxy;
+mod route;
+mod stream;
+mod sweep;
+mod tag;
+mod transaction;
+mod transcode;
+mod transfer;
 
 pub use artifact::*;
+pub use audit::*;
 pub use backend::*;
 pub use catalog::*;
+pub use digest::*;
+pub use encryption::*;
 pub use error::StorageError;
 pub use error::StorageResult;
+pub use fault::*;
+pub use fsck::*;
 use futures::future::try_join_all;
 pub use id::*;
+pub use layout::*;
 pub use local::*;
+pub use logs::*;
+pub use memory::*;
 use ocilot::models::Platform;
+pub use proxy::*;
+pub use route::*;
+pub use stream::*;
+pub use sweep::*;
+pub use tag::*;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::task::JoinError;
+pub use transaction::*;
+use transcode::Transcode;
+pub(crate) use transcode::decode;
+pub use transfer::*;
 
+use crate::context::{HealthReport, Progress};
 use crate::util::{Reader, Writer};
 use indexmap::IndexMap;
-use snafu::ResultExt;
+use snafu::{OptionExt, ResultExt, ensure};
+use std::collections::{BTreeSet, HashMap};
 use std::future::Future;
+use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::Instrument;
@@ -45,6 +85,10 @@ pub struct Storage {
     // We protect the implementation inside an arced rwlock as we do
     // operate with same storage over multiple tokio routines/threads
     inner: Arc<RwLock<Inner>>,
+    audit: Audit,
+    // Writers of local layers started and not finished or aborted yet, by
+    // target. Whatever is left once a run ends was abandoned
+    started: Arc<parking_lot::Mutex<BTreeSet<String>>>,
 }
 
 struct Inner {
@@ -56,6 +100,9 @@ struct Inner {
     // that are used as sources, these are configurable at addr pattern
     // //edo-source-cache/<name>
     source: IndexMap<String, Backend>,
+    // Source caches restricted to some sources, by name. Caches without a
+    // route serve every source
+    routes: HashMap<String, SourceRoute>,
     // A remote build cache allows you to re-use built artifacts from
     // a transform should they exist here.
     // This is configurable at //edo-build-cache
@@ -64,6 +111,18 @@ struct Inner {
     // generally this cache should only ever be pushed to. It is configurable at addr
     // pattern //edo-output-cache
     output: Option<Backend>,
+    // In offline mode only caches on this machine are consulted, remote
+    // caches are neither read from nor uploaded to.
+    offline: bool,
+    // The local cache of the edo process that started this one, when edo
+    // runs inside a transform. It is only ever read, see Storage::set_parent
+    parent: Option<Backend>,
+    // Set once an artifact was injected in place of a build, after which
+    // nothing is uploaded to the build cache, see Storage::inject
+    injected: bool,
+    // Every transfer to or from a remote cache is recorded here, shared with
+    // the handle returned by Storage::audit
+    audit: Audit,
 }
 
 // All methods inside inner are actual implementation methods and should return
@@ -71,12 +130,17 @@ struct Inner {
 impl Inner {
     // Initialize a storage handler, the path here can override where the storage
     // will handle files locally. If provided it willb e turned into an absolute path
-    async fn init(backend: Backend) -> StorageResult<Self> {
+    async fn init(backend: Backend, audit: Audit) -> StorageResult<Self> {
         Ok(Self {
             local: backend,
             source: IndexMap::new(),
+            routes: HashMap::new(),
             build: None,
             output: None,
+            offline: false,
+            parent: None,
+            injected: false,
+            audit,
         })
     }
 
@@ -103,9 +167,18 @@ impl Inner {
             component = "storage",
             "deregistering source cache with name {name}"
         );
+        self.routes.remove(name);
         self.source.shift_remove(name)
     }
 
+    fn route_source_cache(&mut self, name: &str, route: &SourceRoute) {
+        debug!(
+            component = "storage",
+            "routing source cache {name} to {route:?}"
+        );
+        self.routes.insert(name.to_string(), route.clone());
+    }
+
     // Set a build cache
     fn set_build_cache(&mut self, cache: &Backend) {
         debug!(component = "storage", "registering a build cache");
@@ -131,7 +204,7 @@ impl Inner {
             "opening local layer ({})",
             layer.digest().digest()
         );
-        self.local.read(layer).await
+        Ok(verified(&self.local, layer, self.local.read(layer).await?))
     }
 
     // Create an artifact in the local cache
@@ -150,6 +223,15 @@ impl Inner {
         self.local.finish_layer(media_type, platform, writer).await
     }
 
+    // Discard a layer that will not be finished
+    async fn safe_abort_layer(&self, writer: &Writer) -> StorageResult<()> {
+        debug!(
+            component = "storage",
+            "discarding an unfinished local layer"
+        );
+        self.local.abort_layer(writer).await
+    }
+
     // Save the artifact in the local cache
     async fn safe_save(&self, artifact: &Artifact) -> StorageResult<()> {
         debug!(
@@ -162,51 +244,92 @@ impl Inner {
 
     async fn download(&self, artifact: &Artifact, backend: &Backend) -> StorageResult<()> {
         // Now we want to in parallel copy all layers
+        let policy = backend.transfer_policy();
         let mut handles = Vec::new();
         for layer in artifact.layers() {
             let backend = backend.clone();
             let local = self.local.clone();
             let layer = layer.clone();
+            let policy = policy.clone();
             let digest = layer.digest().digest();
             handles.push(tokio::spawn(async move {
-                let layer = layer.clone();
-                let mut reader = backend.read(&layer).await?;
-                let mut writer = local.start_layer().await?;
-                tokio::io::copy(&mut reader, &mut writer).await.context(error::IoSnafu)?;
-                local.finish_layer(layer.media_type(), layer.platform().clone(), &writer).await?;
-                Ok(())
+                // Layers compressed on upload are restored to the raw layer they came from
+                let Some(media_type) = transcode::restored(&layer, local.compression()) else {
+                    let copied = copy_layer(&backend, &local, &layer, layer.media_type(), &Transcode::Copy, &policy).await?;
+                    return Ok(encrypted_as(layer, &copied));
+                };
+                debug!(component = "storage", "decompressing layer {} on download", layer.digest().digest());
+                let transcode = Transcode::Decode(layer.media_type().compression());
+                let restored = copy_layer(&backend, &local, &layer, &media_type, &transcode, &policy).await?;
+                let expected = layer.uncompressed().as_ref().map(|x| x.to_string()).unwrap_or_default();
+                ensure!(restored.digest().to_string() == expected, error::TranscodeSnafu { expected, actual: restored.digest().to_string() });
+                Ok(restored)
             }.instrument(info_span!(target: "storage", "downloading", id = artifact.config().id().to_string(), digest = digest))));
         }
-        wait(handles).await?;
-        self.local.save(artifact).await?;
+        let mut artifact = artifact.clone();
+        *artifact.layers_mut() = wait(handles).await?;
+        self.local.save(&artifact).await?;
+        self.record(AccessKind::Download, &artifact, backend);
         Ok(())
     }
 
     async fn upload(&self, artifact: &Artifact, backend: &Backend) -> StorageResult<()> {
         // Now we want to in parallel copy all layers
+        let policy = backend.transfer_policy();
         let mut handles = Vec::new();
         for layer in artifact.layers() {
             let backend = backend.clone();
             let local = self.local.clone();
             let layer = layer.clone();
+            let policy = policy.clone();
             let digest = layer.digest().digest();
             handles.push(tokio::spawn(async move {
-                let layer = layer.clone();
-                let mut reader = local.read(&layer).await?;
-                let mut writer = backend.start_layer().await?;
-                tokio::io::copy(&mut reader, &mut writer).await.context(error::IoSnafu)?;
-                backend.finish_layer(layer.media_type(), layer.platform().clone(), &writer).await?;
-                Ok(())
+                // Compress raw tarballs for backends that prefer to store them compressed
+                let Some(media_type) = transcode::compressed(&layer, backend.compression()) else {
+                    let copied = copy_layer(&local, &backend, &layer, layer.media_type(), &Transcode::Copy, &policy).await?;
+                    return Ok(encrypted_as(layer, &copied));
+                };
+                debug!(component = "storage", "compressing layer {} as {media_type} on upload", layer.digest().digest());
+                let transcode = Transcode::Encode(media_type.compression());
+                let mut compressed = copy_layer(&local, &backend, &layer, &media_type, &transcode, &policy).await?;
+                *compressed.uncompressed_mut() = Some(layer.digest().clone());
+                Ok(compressed)
             }.instrument(info_span!(target: "storage", "uploading", id = artifact.config().id().to_string(), digest = digest))));
         }
-        wait(handles).await?;
-        backend.save(artifact).await?;
+        let mut artifact = artifact.clone();
+        *artifact.layers_mut() = wait(handles).await?;
+        // The manifest is published last, and only once every layer is
+        // there, so a process dying mid upload never leaves a manifest
+        // pointing at missing blobs. Its blobs are orphans for `fsck`.
+        ensure!(
+            backend.has_layers(&artifact).await?,
+            error::IncompleteUploadSnafu {
+                id: artifact.config().id().to_string()
+            }
+        );
+        backend.save(&artifact).await?;
+        self.record(AccessKind::Upload, &artifact, backend);
         Ok(())
     }
 
+    // Record a transfer of artifact if backend lives outside this machine
+    fn record(&self, kind: AccessKind, artifact: &Artifact, backend: &Backend) {
+        let Some(location) = backend.location() else {
+            return;
+        };
+        self.audit.record(artifact.layers().iter().fold(
+            Access::new(kind, location).subject(artifact.config().id().to_string()),
+            |access, layer| access.digest(layer.digest().to_string()),
+        ));
+    }
+
     // Fetch a source artifact to the local cache if it doesn't exist,
     // otherwise open it
-    async fn fetch_source(&self, id: &Id) -> StorageResult<Option<Artifact>> {
+    async fn fetch_source(
+        &self,
+        id: &Id,
+        origin: Option<&SourceOrigin>,
+    ) -> StorageResult<Option<Artifact>> {
         debug!(
             component = "storage",
             "fetching artifact {id} from source caches"
@@ -218,7 +341,7 @@ impl Inner {
             );
             return Ok(Some(self.local.open(id).await?));
         }
-        if let Some((artifact, backend)) = self.find_source(id).await? {
+        if let Some((artifact, backend)) = self.find_source(id, origin).await? {
             self.download(&artifact, &backend).await?;
             Ok(Some(artifact))
         } else {
@@ -227,9 +350,18 @@ impl Inner {
     }
 
     // Find a source artifact in the source caches by the priority of the order of the
-    // source caches
-    async fn find_source(&self, id: &Id) -> StorageResult<Option<(Artifact, Backend)>> {
-        for (_, cache) in self.source.iter() {
+    // source caches, skipping those not routed to origin when it is known
+    async fn find_source(
+        &self,
+        id: &Id,
+        origin: Option<&SourceOrigin>,
+    ) -> StorageResult<Option<(Artifact, Backend)>> {
+        for (name, cache) in self.source.iter() {
+            // Offline, only caches on this machine such as a source bundle
+            // can be used
+            if (self.offline && cache.location().is_some()) || !self.routed(name, origin) {
+                continue;
+            }
             if cache.has(id).await? {
                 return Ok(Some((cache.open(id).await?, cache.clone())));
             }
@@ -237,9 +369,65 @@ impl Inner {
         Ok(None)
     }
 
+    // Whether the source cache name is consulted for origin
+    fn routed(&self, name: &str, origin: Option<&SourceOrigin>) -> bool {
+        if let (Some(origin), Some(route)) = (origin, self.routes.get(name))
+            && !route.matches(origin)
+        {
+            trace!(
+                component = "storage",
+                "source cache {name} is not routed to {}", origin.addr
+            );
+            return false;
+        }
+        true
+    }
+
+    // Upload a source artifact fetched from upstream to the read-through source
+    // caches routed to origin. A cache that cannot be populated only warns, as
+    // the artifact is already in the local cache
+    async fn populate_source(&self, artifact: &Artifact, origin: Option<&SourceOrigin>) {
+        if self.offline {
+            return;
+        }
+        let id = artifact.config().id();
+        for (name, cache) in self.source.iter() {
+            if !cache.read_through() || !self.routed(name, origin) {
+                continue;
+            }
+            debug!(
+                component = "storage",
+                "populating read-through source cache {name} with {id}"
+            );
+            let result = match cache.has(id).await {
+                Ok(true) => Ok(()),
+                Ok(false) => self.upload(artifact, cache).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!(
+                    component = "storage",
+                    "failed to populate source cache {name} with {id}: {e}"
+                );
+            }
+        }
+    }
+
     // Check for a build artifact, if found we will synchronize it to the local cache if
     // asked to
     async fn find_build(&self, id: &Id, sync: bool) -> StorageResult<Option<Artifact>> {
+        let Some(stream) = self.locate_build(id).await? else {
+            return Ok(None);
+        };
+        // No need to sync what is in the local cache already
+        if sync && stream.is_remote() {
+            self.download(stream.artifact(), stream.backend()).await?;
+        }
+        Ok(Some(stream.artifact().clone()))
+    }
+
+    // Find the cache holding a built artifact without downloading it
+    async fn locate_build(&self, id: &Id) -> StorageResult<Option<BuildStream>> {
         debug!(
             component = "storage",
             "fetching artifact {id} from build cache"
@@ -250,29 +438,79 @@ impl Inner {
                 component = "storage",
                 "loading from the local cache as {id} exists already"
             );
-            // No need to sync as its already in the local cache
-            return Ok(Some(self.local.open(id).await?));
+            let artifact = self.local.open(id).await?;
+            return Ok(Some(BuildStream::new(artifact, &self.local, false)));
+        }
+
+        // A parent process may have built it already
+        if let Some(parent) = self.parent.as_ref()
+            && let Some(artifact) = Self::open_complete(id, parent, "parent").await?
+        {
+            return Ok(Some(BuildStream::new(artifact, parent, true)));
         }
 
         // Check if we have registered a build cache and it has this artifact
         if let Some(build) = self.build.as_ref()
-            && build.has(id).await?
+            && !self.offline
+            && let Some(artifact) = Self::open_complete(id, build, "build").await?
         {
-            let artifact = build.open(id).await?;
-            if sync {
-                self.download(&artifact, build).await?;
-            }
-            // Otherwise open it direct from the build cache
-            return Ok(Some(artifact));
+            return Ok(Some(BuildStream::new(artifact, build, true)));
         }
         // None found
         Ok(None)
     }
 
+    // open an artifact of a remote cache if the cache has every one of its
+    // layers, a manifest left by an interrupted upload is rebuilt instead
+    async fn open_complete(
+        id: &Id,
+        backend: &Backend,
+        cache: &str,
+    ) -> StorageResult<Option<Artifact>> {
+        if !backend.has(id).await? {
+            return Ok(None);
+        }
+        let artifact = backend.open(id).await?;
+        if !backend.has_layers(&artifact).await? {
+            warn!(
+                component = "storage",
+                "{id} in the {cache} cache is missing layers, building it again"
+            );
+            return Ok(None);
+        }
+        Ok(Some(artifact))
+    }
+
+    // which of the ids are in the local, parent or build cache, asking each
+    // cache once for all the ids the ones before it did not have
+    async fn find_builds(&self, ids: &[Id]) -> StorageResult<BTreeSet<Id>> {
+        let mut found = self.local.has_many(ids).await?;
+        let mut caches = Vec::new();
+        if let Some(parent) = self.parent.as_ref() {
+            caches.push(parent);
+        }
+        if let Some(build) = self.build.as_ref()
+            && !self.offline
+        {
+            caches.push(build);
+        }
+        for cache in caches {
+            let missing: Vec<Id> = ids.iter().filter(|x| !found.contains(x)).cloned().collect();
+            if missing.is_empty() {
+                break;
+            }
+            found.extend(cache.has_many(&missing).await?);
+        }
+        Ok(found)
+    }
+
     // upload a build artifact if it exists
     async fn upload_build(&self, id: &Id) -> StorageResult<()> {
         // This only occurs if a build cache is registered
-        if let Some(build) = self.build.as_ref() {
+        if let Some(build) = self.build.as_ref()
+            && !self.offline
+            && !self.injected
+        {
             debug!(component = "storage", "build cache detected uploading {id}");
             let artifact = self.local.open(id).await?;
             self.upload(&artifact, build).await?;
@@ -280,16 +518,48 @@ impl Inner {
         Ok(())
     }
 
-    // upload an output artifact if registered to
-    #[allow(dead_code)]
-    async fn upload_output(&self, id: &Id) -> StorageResult<()> {
-        // This only occurs if an output cache is registered
-        if let Some(output) = self.output.as_ref() {
-            debug!(component = "output cache detected, uploading {id}");
+    // upload an artifact to the output cache unless it holds it already
+    async fn upload_output(&self, id: &Id) -> StorageResult<Backend> {
+        let output = self.select(&CacheSelector::Output)?;
+        if !output.has(id).await? {
+            debug!(component = "storage", "publishing {id} to the output cache");
             let artifact = self.local.open(id).await?;
-            self.upload(&artifact, output).await?;
+            self.upload(&artifact, &output).await?;
         }
-        Ok(())
+        Ok(output)
+    }
+
+    // Resolve one of the registered caches
+    fn select(&self, cache: &CacheSelector) -> StorageResult<Backend> {
+        let backend = match cache {
+            CacheSelector::Local => Some(self.local.clone()),
+            CacheSelector::Build => self.build.clone(),
+            CacheSelector::Output => self.output.clone(),
+            CacheSelector::Source(name) => self
+                .source
+                .iter()
+                .find(|(key, _)| *key == name || key.ends_with(&format!("/{name}")))
+                .map(|(_, backend)| backend.clone()),
+        };
+        backend.context(error::NoSuchCacheSnafu {
+            cache: cache.to_string(),
+        })
+    }
+
+    // Every registered cache, named the way `CacheSelector` parses it
+    fn caches(&self) -> Vec<(CacheSelector, Backend)> {
+        let mut caches = vec![(CacheSelector::Local, self.local.clone())];
+        for (name, backend) in self.source.iter() {
+            let name = name.rsplit('/').next().unwrap_or(name);
+            caches.push((CacheSelector::Source(name.to_string()), backend.clone()));
+        }
+        if let Some(build) = self.build.as_ref() {
+            caches.push((CacheSelector::Build, build.clone()));
+        }
+        if let Some(output) = self.output.as_ref() {
+            caches.push((CacheSelector::Output, output.clone()));
+        }
+        caches
     }
 
     pub async fn prune_local(&self, id: &Id) -> StorageResult<()> {
@@ -304,11 +574,21 @@ impl Inner {
 impl Storage {
     /// Initialize storage with the given backend as the local cache.
     pub async fn init(backend: &Backend) -> StorageResult<Self> {
+        let audit = Audit::default();
         Ok(Self {
-            inner: Arc::new(RwLock::new(Inner::init(backend.clone()).await?)),
+            inner: Arc::new(RwLock::new(
+                Inner::init(backend.clone(), audit.clone()).await?,
+            )),
+            audit,
+            started: Arc::default(),
         })
     }
 
+    /// The record of external accesses made during this session.
+    pub fn audit(&self) -> &Audit {
+        &self.audit
+    }
+
     /// Add a new source cache to the end of the priority list
     pub async fn add_source_cache(&self, name: &str, cache: &Backend) {
         self.inner.write().await.add_source_cache(name, cache);
@@ -319,6 +599,11 @@ impl Storage {
         self.inner.write().await.add_source_cache_front(name, cache);
     }
 
+    /// Restrict a source cache to the sources matching `route`
+    pub async fn route_source_cache(&self, name: &str, route: &SourceRoute) {
+        self.inner.write().await.route_source_cache(name, route);
+    }
+
     /// Remove a source cache
     pub async fn remove_source_cache(&self, name: &str) -> Option<Backend> {
         self.inner.write().await.remove_source_cache(name)
@@ -329,6 +614,13 @@ impl Storage {
         self.inner.write().await.set_build_cache(cache);
     }
 
+    /// Reads built artifacts from `cache`, the local cache of the edo
+    /// process this one runs under, when they are not in the local cache.
+    /// Nothing is ever written to it.
+    pub async fn set_parent(&self, cache: &Backend) {
+        self.inner.write().await.parent = Some(cache.clone());
+    }
+
     /// Set the output cache
     pub async fn set_output(&self, cache: &Backend) {
         self.inner.write().await.set_output_cache(cache);
@@ -341,6 +633,13 @@ impl Storage {
         self.inner.read().await.safe_open(id).await
     }
 
+    /// Check the local cache has an artifact
+    /// **safe operation** This operation is safe to call in a networkless environment or in the
+    /// build stages as it will make no network calls
+    pub async fn safe_has(&self, id: &Id) -> StorageResult<bool> {
+        self.inner.read().await.local.has(id).await
+    }
+
     /// Open a layer stored in the local cache
     /// **safe operation** This operation is safe to call in a networkless environment or in the
     /// build stages as it will make no network calls
@@ -350,7 +649,9 @@ impl Storage {
 
     /// All new artifacts should be created first in the local cache with safe_create
     pub async fn safe_start_layer(&self) -> StorageResult<Writer> {
-        self.inner.read().await.safe_start_layer().await
+        let writer = self.inner.read().await.safe_start_layer().await?;
+        self.started.lock().insert(writer.target());
+        Ok(writer)
     }
 
     /// Finish writing of a local layer
@@ -360,11 +661,67 @@ impl Storage {
         platform: Option<Platform>,
         writer: &Writer,
     ) -> StorageResult<Layer> {
-        self.inner
+        let layer = self
+            .inner
             .write()
             .await
             .safe_finish_layer(media_type, platform, writer)
-            .await
+            .await?;
+        self.started.lock().remove(&writer.target());
+        Ok(layer)
+    }
+
+    /// Discard a local layer that will not be finished, removing its temporary blob
+    pub async fn safe_abort_layer(&self, writer: &Writer) -> StorageResult<()> {
+        self.inner.read().await.safe_abort_layer(writer).await?;
+        self.started.lock().remove(&writer.target());
+        Ok(())
+    }
+
+    /// Remove the temporary blobs of local layers started since the last
+    /// sweep and never finished, returning how many were removed
+    ///
+    /// Called once a run ends, when nothing is writing layers anymore.
+    pub async fn sweep_unfinished(&self) -> StorageResult<usize> {
+        let started = std::mem::take(&mut *self.started.lock());
+        let local = self.inner.read().await.local.clone();
+        for target in started.iter() {
+            debug!(
+                component = "storage",
+                "removing the unfinished layer {target}"
+            );
+            local.remove_temporary(target).await?;
+        }
+        Ok(started.len())
+    }
+
+    /// Remove the temporary blobs of every registered cache last written
+    /// more than `age` ago, which other processes left behind, returning
+    /// how many were removed
+    pub async fn sweep_stale(&self, age: std::time::Duration) -> StorageResult<usize> {
+        let caches = self.inner.read().await.caches();
+        let started = self.started.lock().clone();
+        let mut removed = 0;
+        for (_, backend) in caches {
+            removed += sweep::sweep_stale(&backend, &started, age).await?;
+        }
+        Ok(removed)
+    }
+
+    /// Saves the directory or tarball at `path` in the local cache as an
+    /// artifact named `name`, to stand in for a transform's build. Nothing
+    /// is uploaded to the build cache from then on, as artifacts built
+    /// against it only exist on this machine.
+    pub async fn inject(&self, name: &str, path: &Path) -> StorageResult<Artifact> {
+        let artifact = inject::inject(self, name, path).await?;
+        self.inner.write().await.injected = true;
+        Ok(artifact)
+    }
+
+    /// Begin writing a new local artifact whose layers and config are saved
+    /// together by [`ArtifactTransaction::commit`]
+    pub fn begin_artifact(&self, id: &Id) -> ArtifactTransaction {
+        ArtifactTransaction::new(self, id)
     }
 
     /// Finish creation of a new local artifact
@@ -377,14 +734,137 @@ impl Storage {
     /// **unsafe operation** This operation is unsafe because it could reach out to a networked back source
     /// cache.
     pub async fn fetch_source(&self, id: &Id) -> StorageResult<Option<Artifact>> {
-        self.inner.read().await.fetch_source(id).await
+        self.inner.read().await.fetch_source(id, None).await
+    }
+
+    /// Like [`Storage::fetch_source`], only consulting the source caches
+    /// routed to `origin`.
+    /// **unsafe operation** This operation is unsafe because it could reach out to a networked back source
+    /// cache.
+    pub async fn fetch_source_for(
+        &self,
+        id: &Id,
+        origin: &SourceOrigin,
+    ) -> StorageResult<Option<Artifact>> {
+        self.inner.read().await.fetch_source(id, Some(origin)).await
+    }
+
+    /// Upload a source artifact just fetched from upstream to the read-through
+    /// source caches routed to `origin`, see [`ProxyBackend`]. Failures are
+    /// only logged.
+    /// **unsafe operation** This operation is unsafe because it could reach out to a networked back source
+    /// cache.
+    pub async fn populate_source(&self, artifact: &Artifact, origin: Option<&SourceOrigin>) {
+        self.inner
+            .read()
+            .await
+            .populate_source(artifact, origin)
+            .await
     }
 
     /// Find a source in the source caches
     /// **unsafe operation** This operation is unsafe because it could reach out to a networked back source
     /// cache.
     pub async fn find_source(&self, id: &Id) -> StorageResult<Option<(Artifact, Backend)>> {
-        self.inner.read().await.find_source(id).await
+        self.inner.read().await.find_source(id, None).await
+    }
+
+    /// Copies the local artifact `id` to `backend`, unless it has it already.
+    pub async fn export(&self, id: &Id, backend: &Backend) -> StorageResult<()> {
+        let inner = self.inner.read().await;
+        if backend.has(id).await? {
+            return Ok(());
+        }
+        let artifact = inner.local.open(id).await?;
+        inner.upload(&artifact, backend).await
+    }
+
+    /// Copies the artifact `id` from `cache` into the local cache, unless it
+    /// is there already. Returns `false` when neither cache has it.
+    /// **unsafe operation** This operation is unsafe because it could reach out to a remotely backed
+    /// cache.
+    pub async fn import(&self, id: &Id, cache: &CacheSelector) -> StorageResult<bool> {
+        let inner = self.inner.read().await;
+        if inner.local.has(id).await? {
+            return Ok(true);
+        }
+        let backend = inner.select(cache)?;
+        if !backend.has(id).await? {
+            return Ok(false);
+        }
+        let artifact = backend.open(id).await?;
+        inner.download(&artifact, &backend).await?;
+        Ok(true)
+    }
+
+    /// Saves the log file at `path` as the log artifact of the build `id`,
+    /// see [`log_id`], and uploads it to `cache`. Like builds, nothing is
+    /// uploaded while offline or once an artifact was injected.
+    /// **unsafe operation** This operation is unsafe because it could reach out to a remotely backed
+    /// cache.
+    pub async fn upload_log(
+        &self,
+        id: &Id,
+        path: &Path,
+        cache: &CacheSelector,
+    ) -> StorageResult<()> {
+        {
+            let inner = self.inner.read().await;
+            if inner.offline || inner.injected {
+                return Ok(());
+            }
+            inner.select(cache)?;
+        }
+        let artifact = logs::save_log(self, id, path).await?;
+        let inner = self.inner.read().await;
+        let backend = inner.select(cache)?;
+        inner.upload(&artifact, &backend).await
+    }
+
+    /// Opens the log shipped for the build `id`, copying it from `cache`
+    /// into the local cache unless it is there already. Returns `None` when
+    /// neither cache has it.
+    /// **unsafe operation** This operation is unsafe because it could reach out to a remotely backed
+    /// cache.
+    pub async fn find_log(&self, id: &Id, cache: &CacheSelector) -> StorageResult<Option<Reader>> {
+        let log = log_id(id);
+        if !self.import(&log, cache).await? {
+            return Ok(None);
+        }
+        let artifact = self.safe_open(&log).await?;
+        match artifact.layers().first() {
+            Some(layer) => Ok(Some(self.safe_read(layer).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Publishes the artifact `id` from the local cache to the output cache
+    /// and points the aliases rendered from `tags` at it there, see
+    /// [`render_tag`]. Returns the aliases.
+    ///
+    /// Every template is rendered before anything is uploaded, so an invalid
+    /// one publishes nothing.
+    /// **unsafe operation** This operation is unsafe because it could reach out to a remotely backed
+    /// cache.
+    pub async fn publish(&self, id: &Id, tags: &[String]) -> StorageResult<Vec<String>> {
+        let tags = tags
+            .iter()
+            .map(|x| render_tag(x, id))
+            .collect::<StorageResult<Vec<_>>>()?;
+        let output = self.inner.read().await.upload_output(id).await?;
+        for tag in tags.iter() {
+            output.tag(tag, id).await?;
+        }
+        Ok(tags)
+    }
+
+    /// Returns the id the alias `tag` points at in the output cache, see
+    /// [`Storage::publish`].
+    /// **unsafe operation** This operation is unsafe because it could reach out to a remotely backed
+    /// cache.
+    pub async fn resolve_tag(&self, tag: &str) -> StorageResult<Option<Id>> {
+        let output = self.inner.read().await.select(&CacheSelector::Output)?;
+        output.resolve_tag(tag).await
     }
 
     /// Check for a build artifact
@@ -394,6 +874,65 @@ impl Storage {
         self.inner.read().await.find_build(id, sync).await
     }
 
+    /// Finds a build artifact like [`Storage::find_build`] without
+    /// downloading it, to read its layers straight from the cache holding it
+    /// **unsafe operation** This operation is unsafe because it could reach out to a remotely backed
+    /// build cache.
+    pub async fn stream_build(&self, id: &Id) -> StorageResult<Option<BuildStream>> {
+        let inner = self.inner.read().await;
+        let stream = inner.locate_build(id).await?;
+        if let Some(stream) = stream.as_ref().filter(|x| x.is_remote()) {
+            inner.record(AccessKind::Download, stream.artifact(), stream.backend());
+        }
+        Ok(stream)
+    }
+
+    /// Returns which of `ids` are built already, in the local cache, the
+    /// parent cache or the build cache, without downloading any of them.
+    ///
+    /// Each cache is asked once for all the ids, see [`Backend::has_many`],
+    /// instead of once per id as [`Storage::find_build`] does.
+    pub async fn find_builds(&self, ids: &[Id]) -> StorageResult<BTreeSet<Id>> {
+        self.inner.read().await.find_builds(ids).await
+    }
+
+    /// Has every cache forget what it cached of its catalog, see
+    /// [`Backend::refresh`], so lookups see what other processes saved since.
+    pub async fn refresh(&self) -> StorageResult<()> {
+        let inner = self.inner.read().await;
+        for (_, backend) in inner.caches() {
+            backend.refresh().await?;
+        }
+        if let Some(parent) = inner.parent.as_ref() {
+            parent.refresh().await?;
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if a build cache is registered, in which case every
+    /// built artifact is uploaded to it, unless one was injected.
+    pub async fn has_build_cache(&self) -> bool {
+        let inner = self.inner.read().await;
+        inner.build.is_some() && !inner.offline && !inner.injected
+    }
+
+    /// Returns `true` once an artifact was injected with [`Storage::inject`],
+    /// from then on nothing is uploaded to the build cache.
+    pub async fn has_injected(&self) -> bool {
+        self.inner.read().await.injected
+    }
+
+    /// Restricts storage to the local cache, see [`Storage::is_offline`].
+    pub async fn set_offline(&self, offline: bool) {
+        self.inner.write().await.offline = offline;
+    }
+
+    /// Returns `true` when remote source and build caches are ignored, so a
+    /// build only uses what is already in the local cache.
+    pub async fn is_offline(&self) -> bool {
+        self.inner.read().await.offline
+    }
+
     /// Upload a build artifact if we have a build cache
     pub async fn upload_build(&self, id: &Id) -> StorageResult<()> {
         self.inner.read().await.upload_build(id).await
@@ -409,6 +948,168 @@ impl Storage {
     pub async fn prune_local_all(&self) -> StorageResult<()> {
         self.inner.read().await.prune_local_all().await
     }
+
+    /// Checks every registered cache, see [`Backend::health`].
+    pub async fn health(&self) -> Vec<HealthReport> {
+        let caches = self.inner.read().await.caches();
+        let mut reports = Vec::new();
+        for (cache, backend) in caches {
+            let subject = match backend.location() {
+                Some(location) => format!("{cache} cache ({location})"),
+                None => format!("{cache} cache"),
+            };
+            reports.push(HealthReport {
+                subject,
+                checks: backend.health().await,
+            });
+        }
+        reports
+    }
+
+    /// Count what one of the registered caches holds, see [`cache_info`].
+    pub async fn info(&self, cache: &CacheSelector) -> StorageResult<CacheInfo> {
+        let backend = self.inner.read().await.select(cache)?;
+        cache_info(&backend).await
+    }
+
+    /// List the generations of the artifacts sharing `prefix` in one of the
+    /// registered caches, newest first.
+    pub async fn history(
+        &self,
+        cache: &CacheSelector,
+        prefix: &str,
+    ) -> StorageResult<Vec<Generation>> {
+        let backend = self.inner.read().await.select(cache)?;
+        backend.history(prefix).await
+    }
+
+    /// Check the integrity of one of the registered caches, see [`fsck`].
+    pub async fn fsck(
+        &self,
+        cache: &CacheSelector,
+        options: &FsckOptions,
+    ) -> StorageResult<FsckReport> {
+        let backend = self.inner.read().await.select(cache)?;
+        fsck(&backend, options).await
+    }
+}
+
+/// Copy `layer` from `from` into a new layer of `to` stored as `media_type`.
+///
+/// The copy follows `policy`: it waits for a free transfer slot, is throttled
+/// to the policy's bandwidth and is retried when reading, writing or
+/// finishing the layer fails. Plain copies resume from the bytes that already
+/// reached the writer, transcoded copies start over.
+async fn copy_layer(
+    from: &Backend,
+    to: &Backend,
+    layer: &Layer,
+    media_type: &MediaType,
+    transcode: &Transcode,
+    policy: &TransferPolicy,
+) -> StorageResult<Layer> {
+    let _permit = policy.acquire().await;
+    let progress = Progress::current();
+    progress.set_total_bytes(*layer.size() as u64);
+    // Copies keep the algorithm the source layer is addressed by
+    let algorithm = layer.digest().algorithm();
+    let mut writer = to.start_layer().await?;
+    writer.set_algorithm(algorithm);
+    let mut attempt = 0;
+    loop {
+        let result = match pump(from, layer, &writer, transcode, policy, &progress).await {
+            Ok(()) => {
+                to.finish_layer(media_type, layer.platform().clone(), &writer)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(copied) => return Ok(copied),
+            Err(e) if attempt < policy.retries() => {
+                attempt += 1;
+                warn!(
+                    component = "storage",
+                    "transfer of layer {} failed, retrying ({attempt}/{}): {e}",
+                    layer.digest().digest(),
+                    policy.retries()
+                );
+                tokio::time::sleep(policy.retry_delay(attempt)).await;
+                if !resumable(from, transcode) {
+                    to.abort_layer(&writer).await?;
+                    writer = to.start_layer().await?;
+                    writer.set_algorithm(algorithm);
+                }
+            }
+            Err(e) => {
+                if let Err(abort) = to.abort_layer(&writer).await {
+                    warn!(
+                        component = "storage",
+                        "failed to discard the copy of layer {}: {abort}",
+                        layer.digest().digest()
+                    );
+                }
+                return Err(e);
+            }
+        }
+    }
+}
+
+// Move the rest of a layer's bytes into `writer`, throttled by the bytes read from `from`
+async fn pump(
+    from: &Backend,
+    layer: &Layer,
+    writer: &Writer,
+    transcode: &Transcode,
+    policy: &TransferPolicy,
+    progress: &Progress,
+) -> StorageResult<()> {
+    let offset = if resumable(from, transcode) {
+        writer.size() as u64
+    } else {
+        0
+    };
+    let reader = verified(from, layer, from.read_from(layer, offset).await?);
+    let mut reader = transcode.apply(progress.wrap_read(reader));
+    let mut writer = writer.clone();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut accounted = progress.position();
+    loop {
+        let read = reader.read(&mut buffer).await.context(error::IoSnafu)?;
+        if read == 0 {
+            break;
+        }
+        writer
+            .write_all(&buffer[..read])
+            .await
+            .context(error::IoSnafu)?;
+        let position = progress.position();
+        policy.throttle((position - accounted) as usize).await;
+        accounted = position;
+    }
+    writer.flush().await.context(error::IoSnafu)?;
+    Ok(())
+}
+
+// Plain copies pick up where a failed attempt stopped, unless the whole layer
+// has to stream through a transcoder or a verifying reader again
+fn resumable(from: &Backend, transcode: &Transcode) -> bool {
+    matches!(transcode, Transcode::Copy) && !from.verify_reads()
+}
+
+// A copied layer is encrypted only as the backend it was copied to stored it
+fn encrypted_as(mut layer: Layer, copied: &Layer) -> Layer {
+    *layer.encryption_mut() = copied.encryption().clone();
+    layer
+}
+
+// Check the bytes read from `backend` against `layer` if the backend asks for it
+fn verified(backend: &Backend, layer: &Layer, reader: Reader) -> Reader {
+    if backend.verify_reads() {
+        reader.verify(layer.digest(), *layer.size())
+    } else {
+        reader
+    }
 }
 
 async fn wait<I, R>(handles: I) -> StorageResult<Vec<R>>
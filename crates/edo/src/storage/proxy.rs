@@ -0,0 +1,273 @@
+use std::collections::BTreeSet;
+
+use crate::context::{Describe, FieldType, HealthCheck, KindSchema, Node};
+use crate::storage::{
+    Artifact, Backend, BackendImpl, Compression, DigestAlgorithm, Generation, Id, Layer, MediaType,
+    StorageResult, TemporaryBlob, TransferPolicy, error,
+};
+use crate::util::{Reader, Writer};
+use async_trait::async_trait;
+use ocilot::models::Platform;
+use snafu::OptionExt;
+
+/// The kind of a read-through source cache definition.
+pub const PROXY_KIND: &str = "proxy";
+
+/// A read-through source cache, wrapping a shared cache such as an s3 bucket.
+///
+/// Lookups are served by the wrapped cache as usual. When none of the source
+/// caches has a source, it is fetched from upstream by the source itself and
+/// [`Storage`](super::Storage) then uploads it here, so the first machine to
+/// need a source populates the cache for every other:
+///
+/// ```toml
+/// [cache.source.mirror]
+/// kind  = "proxy"
+/// cache = { kind = "s3", bucket = "edo-mirror" }
+/// ```
+pub struct ProxyBackend {
+    inner: Backend,
+}
+
+impl ProxyBackend {
+    /// Wraps `backend` so it is populated with the sources fetched from upstream.
+    pub fn wrap(backend: Backend) -> Backend {
+        Backend::new(Self { inner: backend })
+    }
+
+    /// Returns the definition of the cache a proxy definition wraps, named
+    /// after the proxy.
+    pub fn cache_node(node: &Node) -> StorageResult<Node> {
+        let setting = error::SettingSnafu {
+            key: "cache",
+            reason: "a proxy needs a cache table with a kind to populate",
+        };
+        let mut table = node
+            .get("cache")
+            .and_then(|x| x.as_table())
+            .context(setting)?;
+        let kind = table
+            .remove("kind")
+            .and_then(|x| x.as_string())
+            .context(setting)?;
+        Ok(Node::new_definition(
+            &node.get_id().unwrap_or_default(),
+            &kind,
+            &node.get_name().unwrap_or_default(),
+            table,
+        ))
+    }
+}
+
+impl Describe for ProxyBackend {
+    fn describe() -> KindSchema {
+        KindSchema::new("A source cache populated with the sources fetched from upstream").required(
+            "cache",
+            FieldType::table(FieldType::Any),
+            "Definition of the wrapped cache, with its kind",
+        )
+    }
+}
+
+#[async_trait]
+impl BackendImpl for ProxyBackend {
+    async fn list(&self) -> StorageResult<BTreeSet<Id>> {
+        self.inner.list().await
+    }
+
+    async fn has(&self, id: &Id) -> StorageResult<bool> {
+        self.inner.has(id).await
+    }
+
+    async fn has_many(&self, ids: &[Id]) -> StorageResult<BTreeSet<Id>> {
+        self.inner.has_many(ids).await
+    }
+
+    async fn open(&self, id: &Id) -> StorageResult<Artifact> {
+        self.inner.open(id).await
+    }
+
+    async fn has_layers(&self, artifact: &Artifact) -> StorageResult<bool> {
+        self.inner.has_layers(artifact).await
+    }
+
+    async fn save(&self, artifact: &Artifact) -> StorageResult<()> {
+        self.inner.save(artifact).await
+    }
+
+    async fn del(&self, id: &Id) -> StorageResult<()> {
+        self.inner.del(id).await
+    }
+
+    async fn copy(&self, from: &Id, to: &Id) -> StorageResult<()> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn prune(&self, id: &Id) -> StorageResult<()> {
+        self.inner.prune(id).await
+    }
+
+    async fn history(&self, prefix: &str) -> StorageResult<Vec<Generation>> {
+        self.inner.history(prefix).await
+    }
+
+    async fn tag(&self, tag: &str, id: &Id) -> StorageResult<()> {
+        self.inner.tag(tag, id).await
+    }
+
+    async fn resolve_tag(&self, tag: &str) -> StorageResult<Option<Id>> {
+        self.inner.resolve_tag(tag).await
+    }
+
+    async fn prune_all(&self) -> StorageResult<()> {
+        self.inner.prune_all().await
+    }
+
+    async fn refresh(&self) -> StorageResult<()> {
+        self.inner.refresh().await
+    }
+
+    async fn read(&self, layer: &Layer) -> StorageResult<Reader> {
+        self.inner.read(layer).await
+    }
+
+    async fn read_from(&self, layer: &Layer, offset: u64) -> StorageResult<Reader> {
+        self.inner.read_from(layer, offset).await
+    }
+
+    async fn start_layer(&self) -> StorageResult<Writer> {
+        self.inner.start_layer().await
+    }
+
+    async fn finish_layer(
+        &self,
+        media_type: &MediaType,
+        platform: Option<Platform>,
+        writer: &Writer,
+    ) -> StorageResult<Layer> {
+        self.inner.finish_layer(media_type, platform, writer).await
+    }
+
+    async fn abort_layer(&self, writer: &Writer) -> StorageResult<()> {
+        self.inner.abort_layer(writer).await
+    }
+
+    async fn temporary_blobs(&self) -> StorageResult<Option<Vec<TemporaryBlob>>> {
+        self.inner.temporary_blobs().await
+    }
+
+    async fn remove_temporary(&self, target: &str) -> StorageResult<()> {
+        self.inner.remove_temporary(target).await
+    }
+
+    async fn blobs(&self) -> StorageResult<Option<BTreeSet<String>>> {
+        self.inner.blobs().await
+    }
+
+    async fn remove_blob(&self, digest: &str) -> StorageResult<()> {
+        self.inner.remove_blob(digest).await
+    }
+
+    fn compression(&self) -> Option<Compression> {
+        self.inner.compression()
+    }
+
+    fn transfer_policy(&self) -> TransferPolicy {
+        self.inner.transfer_policy()
+    }
+
+    fn verify_reads(&self) -> bool {
+        self.inner.verify_reads()
+    }
+
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        self.inner.digest_algorithm()
+    }
+
+    fn location(&self) -> Option<String> {
+        self.inner.location()
+    }
+
+    fn read_through(&self) -> bool {
+        true
+    }
+
+    async fn health(&self) -> Vec<HealthCheck> {
+        self.inner.health().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Addr;
+    use crate::storage::{Config, InMemoryBackend, SourceOrigin, SourceRoute, Storage};
+    use tokio::io::AsyncWriteExt;
+
+    async fn local_artifact(backend: &InMemoryBackend) -> Artifact {
+        let mut writer = backend.start_layer().await.unwrap();
+        writer.write_all(b"fetched").await.unwrap();
+        let layer = backend
+            .finish_layer(&MediaType::File(Compression::None), None, &writer)
+            .await
+            .unwrap();
+        let id = Id::builder()
+            .name("src".to_string())
+            .digest("1".to_string())
+            .build();
+        let artifact = Artifact::builder()
+            .media_type(MediaType::Manifest)
+            .config(Config::builder().id(id).build())
+            .layers(vec![layer])
+            .build();
+        backend.save(&artifact).await.unwrap();
+        artifact
+    }
+
+    #[tokio::test]
+    async fn fetched_sources_populate_routed_proxies() {
+        let local = InMemoryBackend::new();
+        let plain = InMemoryBackend::new();
+        let mirror = InMemoryBackend::new();
+        let images = InMemoryBackend::new();
+        let artifact = local_artifact(&local).await;
+        let storage = Storage::init(&Backend::new(local)).await.unwrap();
+        storage
+            .add_source_cache("plain", &Backend::new(plain.clone()))
+            .await;
+        storage
+            .add_source_cache("mirror", &ProxyBackend::wrap(Backend::new(mirror.clone())))
+            .await;
+        storage
+            .add_source_cache("images", &ProxyBackend::wrap(Backend::new(images.clone())))
+            .await;
+        storage
+            .route_source_cache(
+                "images",
+                &SourceRoute {
+                    kinds: Some(vec!["image".to_string()]),
+                    ..Default::default()
+                },
+            )
+            .await;
+        let origin = SourceOrigin {
+            addr: Addr::parse("//pkg/src").unwrap(),
+            kind: "remote".to_string(),
+            url: None,
+        };
+        let id = artifact.config().id();
+
+        storage.set_offline(true).await;
+        storage.populate_source(&artifact, Some(&origin)).await;
+        assert!(!mirror.has(id).await.unwrap());
+
+        storage.set_offline(false).await;
+        storage.populate_source(&artifact, Some(&origin)).await;
+        assert!(mirror.has(id).await.unwrap());
+        assert!(!plain.has(id).await.unwrap());
+        assert!(!images.has(id).await.unwrap());
+        // The next lookup is served by the proxy
+        let (_, backend) = storage.find_source(id).await.unwrap().unwrap();
+        assert!(backend.read_through());
+    }
+}
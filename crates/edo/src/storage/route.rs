@@ -0,0 +1,177 @@
+//! Routing of sources to the source caches that serve them.
+//!
+//! Source caches are consulted in priority order. A cache definition may add
+//! a `route` table so it is only consulted for some sources:
+//!
+//! ```toml
+//! [cache.source.ecr]
+//! kind   = "s3"
+//! bucket = "images"
+//! route  = { kinds = ["image"] }
+//!
+//! [cache.source.mirror]
+//! kind   = "s3"
+//! bucket = "tarballs"
+//! route  = { kinds = ["remote"], urls = ["https://**/*.tar.*"] }
+//! ```
+//!
+//! `kinds` lists source kinds, `prefixes` address prefixes such as
+//! `//third_party/` and `urls` [`glob_match`] patterns matched against the
+//! source's `url`. A source must match every list given. Caches without a
+//! route serve every source.
+
+use super::{StorageResult, error};
+use crate::context::{Addr, Node};
+use crate::util::glob_match;
+use snafu::OptionExt;
+
+/// Fields of a source definition holding where it is fetched from.
+const URL_FIELDS: [&str; 2] = ["url", "uri"];
+
+/// What a source cache route is matched against.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceOrigin {
+    /// Address of the source.
+    pub addr: Addr,
+    /// Kind of the source, e.g. `remote` or `image`.
+    pub kind: String,
+    /// Where the source is fetched from, when it names a url.
+    pub url: Option<String>,
+}
+
+impl SourceOrigin {
+    /// Describes the source defined by `node` at `addr`.
+    pub fn from_node(addr: &Addr, node: &Node) -> Self {
+        Self {
+            addr: addr.clone(),
+            kind: node.get_kind().unwrap_or_default(),
+            url: URL_FIELDS
+                .iter()
+                .find_map(|x| node.get(x).and_then(|x| x.as_string())),
+        }
+    }
+}
+
+/// The sources a source cache is consulted for.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceRoute {
+    /// Source kinds, or `None` for any kind.
+    pub kinds: Option<Vec<String>>,
+    /// Address prefixes, or `None` for any address.
+    pub prefixes: Option<Vec<String>>,
+    /// Url patterns, or `None` for any url.
+    pub urls: Option<Vec<String>>,
+}
+
+impl SourceRoute {
+    /// Reads the optional `route` key of a source cache definition.
+    pub fn from_node(node: &Node) -> StorageResult<Option<Self>> {
+        let Some(route) = node.get("route") else {
+            return Ok(None);
+        };
+        route.as_table().context(error::SettingSnafu {
+            key: "route",
+            reason: "expected a table of kinds, prefixes and urls",
+        })?;
+        let list = |key: &str| -> StorageResult<Option<Vec<String>>> {
+            let Some(value) = route.get(key) else {
+                return Ok(None);
+            };
+            let reason = "expected a list of strings";
+            value
+                .as_list()
+                .context(error::SettingSnafu {
+                    key: format!("route.{key}"),
+                    reason,
+                })?
+                .iter()
+                .map(|x| {
+                    x.as_string().context(error::SettingSnafu {
+                        key: format!("route.{key}"),
+                        reason,
+                    })
+                })
+                .collect::<StorageResult<Vec<_>>>()
+                .map(Some)
+        };
+        Ok(Some(Self {
+            kinds: list("kinds")?,
+            prefixes: list("prefixes")?,
+            urls: list("urls")?,
+        }))
+    }
+
+    /// Returns `true` if the cache should be consulted for `origin`.
+    pub fn matches(&self, origin: &SourceOrigin) -> bool {
+        let addr = origin.addr.to_string();
+        self.kinds.as_ref().is_none_or(|x| x.contains(&origin.kind))
+            && self
+                .prefixes
+                .as_ref()
+                .is_none_or(|x| x.iter().any(|x| addr.starts_with(x.as_str())))
+            && self.urls.as_ref().is_none_or(|x| {
+                origin
+                    .url
+                    .as_ref()
+                    .is_some_and(|url| x.iter().any(|x| glob_match(x, url)))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn origin(addr: &str, kind: &str, url: Option<&str>) -> SourceOrigin {
+        SourceOrigin {
+            addr: Addr::parse(addr).unwrap(),
+            kind: kind.to_string(),
+            url: url.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn routes_match_every_given_predicate() {
+        let node: BTreeMap<String, Node> = toml::from_str(
+            r#"
+            kind = "s3"
+            route = { kinds = ["remote"], urls = ["https://**/*.tar.gz"] }
+            "#,
+        )
+        .unwrap();
+        let route = SourceRoute::from_node(&Node::new_table(node))
+            .unwrap()
+            .unwrap();
+        assert!(route.matches(&origin(
+            "//pkg/src",
+            "remote",
+            Some("https://example.com/dist/pkg-1.0.tar.gz")
+        )));
+        assert!(!route.matches(&origin(
+            "//pkg/src",
+            "remote",
+            Some("https://example.com/dist/pkg-1.0.zip")
+        )));
+        assert!(!route.matches(&origin("//pkg/src", "git", None)));
+
+        let prefixed = SourceRoute {
+            prefixes: Some(vec!["//third_party/".to_string()]),
+            ..Default::default()
+        };
+        assert!(prefixed.matches(&origin("//third_party/zlib/src", "git", None)));
+        assert!(!prefixed.matches(&origin("//app/src", "git", None)));
+    }
+
+    #[test]
+    fn malformed_routes_are_rejected() {
+        for toml in [r#"route = "image""#, r#"route = { kinds = "image" }"#] {
+            let node: BTreeMap<String, Node> = toml::from_str(toml).unwrap();
+            assert!(SourceRoute::from_node(&Node::new_table(node)).is_err());
+        }
+        assert_eq!(
+            SourceRoute::from_node(&Node::new_table(BTreeMap::new())).unwrap(),
+            None
+        );
+    }
+}
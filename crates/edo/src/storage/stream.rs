@@ -0,0 +1,53 @@
+//! Built artifacts read in place from the cache that holds them.
+//!
+//! `edo checkout` unpacks an artifact while its layers are read, instead of
+//! downloading it to the local cache first, so checking out an image of tens
+//! of gigabytes needs neither the disk space nor the time of a full copy.
+
+use super::{Artifact, Backend, Layer, StorageResult, verified};
+use crate::util::Reader;
+
+/// A built artifact and the cache it is read from, see
+/// [`Storage::stream_build`](super::Storage::stream_build).
+#[derive(Clone)]
+pub struct BuildStream {
+    artifact: Artifact,
+    backend: Backend,
+    remote: bool,
+}
+
+impl BuildStream {
+    pub(super) fn new(artifact: Artifact, backend: &Backend, remote: bool) -> Self {
+        Self {
+            artifact,
+            backend: backend.clone(),
+            remote,
+        }
+    }
+
+    /// The artifact's manifest.
+    pub fn artifact(&self) -> &Artifact {
+        &self.artifact
+    }
+
+    /// Whether the artifact is read from the parent or build cache rather
+    /// than the local cache.
+    pub fn is_remote(&self) -> bool {
+        self.remote
+    }
+
+    pub(super) fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    /// Opens a reader to one of the artifact's layers, as stored by the
+    /// cache. Its bytes are checked against the layer's digest when the
+    /// cache asks for it, and nothing is written to the local cache.
+    pub async fn read(&self, layer: &Layer) -> StorageResult<Reader> {
+        Ok(verified(
+            &self.backend,
+            layer,
+            self.backend.read(layer).await?,
+        ))
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::BTreeSet;
+use std::time::{Duration, SystemTime};
+
+use super::{Backend, StorageResult};
+
+/// Temporary blobs untouched for this long belong to a process that died
+/// mid-write and are removed when edo starts.
+pub const STALE_TEMPORARY: Duration = Duration::from_secs(60 * 60);
+
+/// The temporary blob of a layer that was started and not finished.
+#[derive(Clone, Debug)]
+pub struct TemporaryBlob {
+    /// Name of the writer filling it, see [`Writer::target`](crate::util::Writer::target).
+    pub target: String,
+    /// Bytes written so far.
+    pub size: u64,
+    /// When it was last written to.
+    pub modified: SystemTime,
+}
+
+impl TemporaryBlob {
+    /// Whether it was last written to more than `age` ago.
+    pub fn is_older_than(&self, age: Duration) -> bool {
+        self.modified.elapsed().is_ok_and(|elapsed| elapsed > age)
+    }
+}
+
+/// What one cache holds, for `edo cache info`.
+#[derive(Clone, Debug, Default)]
+pub struct CacheInfo {
+    /// Number of artifact manifests.
+    pub artifacts: usize,
+    /// Number of blobs, `None` when the backend cannot list them.
+    pub blobs: Option<usize>,
+    /// Number of blobs no manifest references.
+    pub unreferenced: Option<usize>,
+    /// Number of temporary blobs of unfinished layers, `None` when the
+    /// backend keeps none.
+    pub temporary: Option<usize>,
+    /// Bytes held by those temporary blobs.
+    pub temporary_bytes: u64,
+    /// Number of temporary blobs older than [`STALE_TEMPORARY`].
+    pub stale: usize,
+}
+
+/// Counts the manifests, blobs and temporary blobs of `backend`.
+pub async fn cache_info(backend: &Backend) -> StorageResult<CacheInfo> {
+    let mut info = CacheInfo::default();
+    let mut referenced = BTreeSet::new();
+    for id in backend.list().await? {
+        info.artifacts += 1;
+        for layer in backend.open(&id).await?.layers() {
+            referenced.insert(layer.digest().to_string());
+        }
+    }
+    if let Some(blobs) = backend.blobs().await? {
+        info.blobs = Some(blobs.len());
+        info.unreferenced = Some(blobs.difference(&referenced).count());
+    }
+    if let Some(temporary) = backend.temporary_blobs().await? {
+        info.temporary = Some(temporary.len());
+        info.temporary_bytes = temporary.iter().map(|x| x.size).sum();
+        info.stale = temporary
+            .iter()
+            .filter(|x| x.is_older_than(STALE_TEMPORARY))
+            .count();
+    }
+    Ok(info)
+}
+
+/// Removes the temporary blobs of `backend` older than `age` that are not
+/// in `started`, returning how many were removed.
+pub(super) async fn sweep_stale(
+    backend: &Backend,
+    started: &BTreeSet<String>,
+    age: Duration,
+) -> StorageResult<usize> {
+    let Some(temporary) = backend.temporary_blobs().await? else {
+        return Ok(0);
+    };
+    let mut removed = 0;
+    for blob in temporary {
+        if started.contains(&blob.target) || !blob.is_older_than(age) {
+            continue;
+        }
+        debug!(
+            component = "storage",
+            "removing stale temporary blob {}", blob.target
+        );
+        backend.remove_temporary(&blob.target).await?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::{Addr, Config, DefinableNoContext, Node, NonConfigurable};
+    use crate::storage::{
+        Backend, Compression, LocalBackend, MediaType, STALE_TEMPORARY, Storage, StorageError,
+    };
+    use std::collections::BTreeMap;
+    use std::path::Path;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+
+    async fn local_storage(dir: &Path) -> Storage {
+        let addr = Addr::parse("//edo-local-cache").unwrap();
+        let node = Node::new_definition(
+            "storage",
+            "local",
+            "local",
+            BTreeMap::from([(
+                "path".to_string(),
+                Node::new_string(dir.to_string_lossy().to_string()),
+            )]),
+        );
+        let config = Config::load::<&Path>(None).await.unwrap();
+        let local = <LocalBackend as DefinableNoContext<
+            StorageError,
+            NonConfigurable<StorageError>,
+        >>::new(&addr, &node, &config)
+        .await
+        .unwrap();
+        Storage::init(&Backend::new(local)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn abandoned_layers_are_swept_when_the_run_ends() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let storage = local_storage(dir.path()).await;
+        let mut finished = storage.safe_start_layer().await.unwrap();
+        finished.write_all(b"done").await.unwrap();
+        storage
+            .safe_finish_layer(&MediaType::File(Compression::None), None, &finished)
+            .await
+            .unwrap();
+        let mut abandoned = storage.safe_start_layer().await.unwrap();
+        abandoned.write_all(b"partial").await.unwrap();
+        drop(abandoned);
+
+        let info = storage.info(&"local".parse().unwrap()).await.unwrap();
+        assert_eq!(info.temporary, Some(1));
+        assert_eq!(info.unreferenced, Some(1));
+        // Fresh, so left alone in case another process is writing it
+        assert_eq!(storage.sweep_stale(STALE_TEMPORARY).await.unwrap(), 0);
+        assert_eq!(storage.sweep_unfinished().await.unwrap(), 1);
+        let info = storage.info(&"local".parse().unwrap()).await.unwrap();
+        assert_eq!(info.temporary, Some(0));
+        assert_eq!(storage.sweep_stale(Duration::ZERO).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,129 @@
+//! Human-friendly aliases of published artifacts.
+//!
+//! Artifacts are addressed by content, which release tooling cannot guess.
+//! Publishing an artifact to the output cache, see [`Storage::publish`],
+//! records aliases such as `myapp:1.2.0-x86_64` or `myapp:latest` in that
+//! cache's catalog, pointing at the artifact's [`Id`]. A later publish moves
+//! an alias to the newer artifact, and consumers open artifacts by alias
+//! with [`Storage::resolve_tag`].
+//!
+//! Aliases are rendered from templates: `{name}`, `{package}`, `{version}`,
+//! `{arch}` and `{digest}` are replaced by the components of the id, and a
+//! template without a `:` is a tag of the artifact's name, so `latest`
+//! becomes `<name>:latest`. The templates published by default are read
+//! from `[config.publish] tags`.
+
+use snafu::{OptionExt, ensure};
+use tracing::warn;
+
+use super::{DigestAlgorithm, Id, StorageResult, error};
+use crate::context::Config;
+
+/// Renders the alias template `template` for the artifact `id`.
+pub fn render_tag(template: &str, id: &Id) -> StorageResult<String> {
+    let invalid = |reason: String| error::TagSnafu {
+        template: template.to_string(),
+        reason,
+    };
+    let mut tag = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        tag += &rest[..start];
+        let end = rest[start..]
+            .find('}')
+            .context(invalid("unterminated placeholder".into()))?;
+        let field = &rest[start + 1..start + end];
+        let value = match field {
+            "name" => Some(id.name()),
+            "package" => id.package(),
+            "version" => id.version().map(|x| x.to_string()),
+            "arch" => id.arch(),
+            "digest" => Some(DigestAlgorithm::split(id.digest()).1.to_string()),
+            _ => return invalid(format!("unknown placeholder {{{field}}}")).fail(),
+        };
+        tag += &value.context(invalid(format!("{id} has no {field}")))?;
+        rest = &rest[start + end + 1..];
+    }
+    tag += rest;
+    if !tag.contains(':') {
+        tag = format!("{}:{tag}", id.name());
+    }
+    let (repository, reference) = tag.split_once(':').unwrap_or_default();
+    ensure!(
+        !repository.is_empty() && !reference.is_empty(),
+        invalid(format!("'{tag}' is not of the form <name>:<tag>"))
+    );
+    ensure!(
+        !tag.contains(|x: char| x.is_whitespace() || x == '#' || x == '/'),
+        invalid(format!("'{tag}' contains whitespace, '#' or '/'"))
+    );
+    Ok(tag)
+}
+
+/// Returns `true` when `input` names an alias rather than an artifact id.
+///
+/// Aliases have a `:` that ids only have in the digest of their last `-`
+/// segment, such as `hello-sha256:abc`, and references have a `#`.
+pub fn is_tag(input: &str) -> bool {
+    if input.contains('#') || !input.contains(':') {
+        return false;
+    }
+    let last = input.rsplit('-').next().unwrap_or(input);
+    last.split_once(':')
+        .is_none_or(|(algorithm, _)| algorithm.parse::<DigestAlgorithm>().is_err())
+}
+
+/// The alias templates `[config.publish] tags` lists, published when none
+/// are given.
+pub fn publish_tags(config: &Config) -> Vec<String> {
+    let Some(value) = config.get("publish").and_then(|x| x.get("tags")) else {
+        return Vec::new();
+    };
+    let tags = value.as_list().and_then(|list| {
+        list.iter()
+            .map(|x| x.as_string())
+            .collect::<Option<Vec<String>>>()
+    });
+    tags.unwrap_or_else(|| {
+        warn!("ignoring publish.tags, expected a list of alias templates");
+        Vec::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use semver::Version;
+
+    fn id() -> Id {
+        Id::builder()
+            .name("myapp".to_string())
+            .version(Version::new(1, 2, 0))
+            .arch("x86_64".to_string())
+            .digest("sha256:abc".to_string())
+            .build()
+    }
+
+    #[test]
+    fn templates_render_the_components_of_the_id() {
+        let id = id();
+        assert_eq!(
+            render_tag("myapp:{version}-{arch}", &id).unwrap(),
+            "myapp:1.2.0-x86_64"
+        );
+        assert_eq!(render_tag("latest", &id).unwrap(), "myapp:latest");
+        assert_eq!(render_tag("{name}:{digest}", &id).unwrap(), "myapp:abc");
+        assert!(render_tag("{package}", &id).is_err());
+        assert!(render_tag("{flavour}", &id).is_err());
+        assert!(render_tag("my app", &id).is_err());
+    }
+
+    #[test]
+    fn tags_are_told_apart_from_ids() {
+        assert!(is_tag("myapp:latest"));
+        assert!(is_tag("my-app:1.2.0-x86_64"));
+        assert!(!is_tag("myapp-1.2.0-abc"));
+        assert!(!is_tag("myapp-1.2.0-sha256:abc"));
+        assert!(!is_tag("myapp@1.2.0#sha256:abc"));
+    }
+}
@@ -0,0 +1,244 @@
+//! Transactional creation of artifacts.
+//!
+//! Writing an artifact takes several steps: start a layer, fill it, finish
+//! it, repeat, then save the manifest. A transform failing between those
+//! steps used to leave the temporary blobs of its open layers behind. An
+//! [`ArtifactTransaction`] tracks the layers it started, adds the finished
+//! ones to the artifact, and either commits the manifest or discards every
+//! layer still open, including when it is dropped on an early return.
+
+use super::{Artifact, Config, Id, Layer, MediaType, Storage, StorageResult, error};
+use crate::util::Writer;
+use ocilot::models::Platform;
+use snafu::ensure;
+
+/// An artifact being written to the local cache, see [`Storage::begin_artifact`].
+///
+/// Finished layers are content addressed and may be shared with other
+/// artifacts, so an aborted transaction leaves them for `edo cache fsck` and
+/// only removes the temporary blobs of layers it never finished.
+pub struct ArtifactTransaction {
+    storage: Storage,
+    artifact: Artifact,
+    open: Vec<Writer>,
+}
+
+impl ArtifactTransaction {
+    pub(super) fn new(storage: &Storage, id: &Id) -> Self {
+        Self {
+            storage: storage.clone(),
+            artifact: Artifact::builder()
+                .config(Config::builder().id(id.clone()).build())
+                .media_type(MediaType::Manifest)
+                .build(),
+            open: Vec::new(),
+        }
+    }
+
+    /// The artifact as committed so far, its finished layers included.
+    pub fn artifact(&self) -> &Artifact {
+        &self.artifact
+    }
+
+    /// Gives access to the artifact's media type, config and metadata.
+    pub fn artifact_mut(&mut self) -> &mut Artifact {
+        &mut self.artifact
+    }
+
+    /// Starts a new layer, discarded unless finished before the commit.
+    pub async fn start_layer(&mut self) -> StorageResult<Writer> {
+        let writer = self.storage.safe_start_layer().await?;
+        self.open.push(writer.clone());
+        Ok(writer)
+    }
+
+    /// Finishes a layer started by this transaction and appends it to the
+    /// artifact's layers.
+    pub async fn finish_layer(
+        &mut self,
+        media_type: &MediaType,
+        platform: Option<Platform>,
+        writer: &Writer,
+    ) -> StorageResult<Layer> {
+        let target = writer.target();
+        self.open.retain(|x| x.target() != target);
+        let layer = match self
+            .storage
+            .safe_finish_layer(media_type, platform, writer)
+            .await
+        {
+            Ok(layer) => layer,
+            Err(e) => {
+                // Half finished layers still need their temporary blob removed
+                self.open.push(writer.clone());
+                return Err(e);
+            }
+        };
+        self.artifact.layers_mut().push(layer.clone());
+        Ok(layer)
+    }
+
+    /// Saves the artifact's manifest, failing without saving anything when a
+    /// layer it started is not finished.
+    pub async fn commit(self) -> StorageResult<Artifact> {
+        ensure!(
+            self.open.is_empty(),
+            error::UnfinishedLayersSnafu {
+                id: self.artifact.config().id().to_string(),
+                count: self.open.len(),
+            }
+        );
+        self.storage.safe_save(&self.artifact).await?;
+        Ok(self.artifact.clone())
+    }
+
+    /// Discards the temporary blobs of every layer still open.
+    pub async fn abort(mut self) -> StorageResult<()> {
+        for writer in std::mem::take(&mut self.open) {
+            self.storage.safe_abort_layer(&writer).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ArtifactTransaction {
+    fn drop(&mut self) {
+        if self.open.is_empty() {
+            return;
+        }
+        // A transform returned early, clean up in the background as drop
+        // cannot wait
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let storage = self.storage.clone();
+        let open = std::mem::take(&mut self.open);
+        runtime.spawn(async move {
+            for writer in open {
+                if let Err(e) = storage.safe_abort_layer(&writer).await {
+                    warn!(
+                        component = "storage",
+                        "failed to discard an unfinished layer: {e}"
+                    );
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{Addr, DefinableNoContext, Node, NonConfigurable};
+    use crate::storage::{Backend, Compression, InMemoryBackend, LocalBackend, StorageError};
+    use std::collections::BTreeMap;
+    use std::path::{Path, PathBuf};
+    use tokio::io::AsyncWriteExt;
+
+    fn id(name: &str) -> Id {
+        Id::builder()
+            .name(name.to_string())
+            .digest("1".to_string())
+            .build()
+    }
+
+    async fn local_storage(dir: &Path) -> Storage {
+        let addr = Addr::parse("//edo-local-cache").unwrap();
+        let node = Node::new_definition(
+            "storage",
+            "local",
+            "local",
+            BTreeMap::from([(
+                "path".to_string(),
+                Node::new_string(dir.to_string_lossy().to_string()),
+            )]),
+        );
+        let config = crate::context::Config::load::<&Path>(None).await.unwrap();
+        let local = <LocalBackend as DefinableNoContext<
+            StorageError,
+            NonConfigurable<StorageError>,
+        >>::new(&addr, &node, &config)
+        .await
+        .unwrap();
+        Storage::init(&Backend::new(local)).await.unwrap()
+    }
+
+    /// The temporary blobs of layers started in the cache at `dir`.
+    fn temporary_blobs(dir: &Path) -> Vec<PathBuf> {
+        let mut found = Vec::new();
+        let mut stack = vec![dir.to_path_buf()];
+        while let Some(dir) = stack.pop() {
+            for entry in std::fs::read_dir(&dir).unwrap().flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    stack.push(path);
+                } else if path.extension().is_some_and(|x| x == "tmp") {
+                    found.push(path);
+                }
+            }
+        }
+        found
+    }
+
+    #[tokio::test]
+    async fn commit_saves_the_finished_layers() {
+        let storage = Storage::init(&Backend::new(InMemoryBackend::new()))
+            .await
+            .unwrap();
+        let mut tx = storage.begin_artifact(&id("hello"));
+        for data in [b"one", b"two"] {
+            let mut writer = tx.start_layer().await.unwrap();
+            writer.write_all(data).await.unwrap();
+            tx.finish_layer(&MediaType::File(Compression::None), None, &writer)
+                .await
+                .unwrap();
+        }
+        *tx.artifact_mut().config_mut().metadata_mut() = serde_json::json!({ "key": 1 });
+        let artifact = tx.commit().await.unwrap();
+        let saved = storage.safe_open(&id("hello")).await.unwrap();
+        assert_eq!(saved.layers().len(), 2);
+        assert_eq!(saved.config().metadata()["key"], 1);
+        assert_eq!(artifact.layers().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn commit_refuses_unfinished_layers() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let storage = local_storage(dir.path()).await;
+        let mut tx = storage.begin_artifact(&id("hello"));
+        let mut writer = tx.start_layer().await.unwrap();
+        writer.write_all(b"partial").await.unwrap();
+        assert!(tx.commit().await.is_err());
+        assert!(storage.safe_open(&id("hello")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn abort_removes_temporary_blobs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let storage = local_storage(dir.path()).await;
+        let mut tx = storage.begin_artifact(&id("hello"));
+        let mut writer = tx.start_layer().await.unwrap();
+        writer.write_all(b"partial").await.unwrap();
+        assert_eq!(temporary_blobs(dir.path()).len(), 1);
+        tx.abort().await.unwrap();
+        assert!(temporary_blobs(dir.path()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn dropping_removes_temporary_blobs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let storage = local_storage(dir.path()).await;
+        {
+            let mut tx = storage.begin_artifact(&id("hello"));
+            let mut writer = tx.start_layer().await.unwrap();
+            writer.write_all(b"partial").await.unwrap();
+        }
+        for _ in 0..100 {
+            if temporary_blobs(dir.path()).is_empty() {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("the temporary blob was not removed");
+    }
+}
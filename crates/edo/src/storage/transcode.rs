@@ -0,0 +1,98 @@
+use std::pin::Pin;
+
+use async_compression::tokio::bufread::{
+    BzDecoder, BzEncoder, GzipDecoder, GzipEncoder, LzmaDecoder, LzmaEncoder, XzDecoder, XzEncoder,
+    ZstdDecoder, ZstdEncoder,
+};
+use tokio::io::{AsyncRead, BufReader};
+
+use super::{Compression, Layer, MediaType};
+
+/// A boxed reader producing a layer's bytes in the representation being written.
+pub(crate) type LayerStream = Pin<Box<dyn AsyncRead + Send>>;
+
+/// How a layer's bytes are rewritten while being copied between backends.
+#[derive(Clone, Debug)]
+pub(crate) enum Transcode {
+    /// Copy the bytes unchanged.
+    Copy,
+    /// Compress the bytes with the given algorithm.
+    Encode(Compression),
+    /// Decompress bytes compressed with the given algorithm.
+    Decode(Compression),
+}
+
+impl Transcode {
+    /// Wraps `reader` so it produces the rewritten bytes.
+    pub(crate) fn apply<R>(&self, reader: R) -> LayerStream
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        match self {
+            Self::Copy => Box::pin(reader),
+            Self::Encode(compression) => encode(reader, compression),
+            Self::Decode(compression) => decode(reader, compression),
+        }
+    }
+}
+
+/// Returns the media type `layer` is stored as in a backend preferring `compression`.
+///
+/// Only uncompressed tarballs are transcoded, every other layer is copied as is.
+pub(crate) fn compressed(layer: &Layer, compression: Option<Compression>) -> Option<MediaType> {
+    match (layer.media_type(), compression) {
+        (MediaType::Tar(Compression::None), Some(compression))
+            if compression != Compression::None =>
+        {
+            Some(MediaType::Tar(compression))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the media type `layer` is restored to in a backend preferring `compression`.
+///
+/// Layers compressed by [`compressed`] are decompressed again unless the
+/// consumer keeps them with the same compression.
+pub(crate) fn restored(layer: &Layer, compression: Option<Compression>) -> Option<MediaType> {
+    layer.uncompressed().as_ref()?;
+    let current = layer.media_type().compression();
+    if compression.is_some_and(|x| x == current) {
+        return None;
+    }
+    let mut media_type = layer.media_type().clone();
+    media_type.set_compression(Compression::None);
+    Some(media_type)
+}
+
+/// Compress `reader` with `compression`.
+fn encode<R>(reader: R, compression: &Compression) -> LayerStream
+where
+    R: AsyncRead + Send + 'static,
+{
+    let reader = BufReader::new(reader);
+    match compression {
+        Compression::Bzip2 => Box::pin(BzEncoder::new(reader)),
+        Compression::Lz => Box::pin(LzmaEncoder::new(reader)),
+        Compression::Xz => Box::pin(XzEncoder::new(reader)),
+        Compression::Gzip => Box::pin(GzipEncoder::new(reader)),
+        Compression::Zstd => Box::pin(ZstdEncoder::new(reader)),
+        Compression::None => Box::pin(reader),
+    }
+}
+
+/// Decompress `reader`, which was compressed with `compression`.
+pub(crate) fn decode<R>(reader: R, compression: &Compression) -> LayerStream
+where
+    R: AsyncRead + Send + 'static,
+{
+    let reader = BufReader::new(reader);
+    match compression {
+        Compression::Bzip2 => Box::pin(BzDecoder::new(reader)),
+        Compression::Lz => Box::pin(LzmaDecoder::new(reader)),
+        Compression::Xz => Box::pin(XzDecoder::new(reader)),
+        Compression::Gzip => Box::pin(GzipDecoder::new(reader)),
+        Compression::Zstd => Box::pin(ZstdDecoder::new(reader)),
+        Compression::None => Box::pin(reader),
+    }
+}
@@ -0,0 +1,244 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use snafu::OptionExt;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use super::{StorageResult, error};
+use crate::context::Node;
+use crate::util::parse_size;
+
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Limits and retry behaviour applied when layers are copied to or from a backend.
+///
+/// Read from the optional `bandwidth`, `concurrency`, `retries` and
+/// `retry_delay_ms` keys of a `[cache.*]` table. Clones share the same
+/// limits, so every transfer against one backend draws from one budget.
+/// The default policy is unlimited and never retries.
+#[derive(Clone, Debug)]
+pub struct TransferPolicy {
+    bandwidth: Option<u64>,
+    concurrency: Option<usize>,
+    retries: u32,
+    retry_delay: Duration,
+    permits: Option<Arc<Semaphore>>,
+    // The instant the bandwidth budget is next free
+    next: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Default for TransferPolicy {
+    fn default() -> Self {
+        Self {
+            bandwidth: None,
+            concurrency: None,
+            retries: 0,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            permits: None,
+            next: Arc::default(),
+        }
+    }
+}
+
+impl TransferPolicy {
+    /// Reads a policy from the transfer keys of a cache definition.
+    pub fn from_node(node: &Node) -> StorageResult<Self> {
+        let mut policy = Self::default();
+        if let Some(value) = node.get("bandwidth") {
+            let bandwidth = match (value.as_int(), value.as_string()) {
+                (Some(rate), _) => u64::try_from(rate).ok(),
+                (_, Some(rate)) => parse_rate(&rate),
+                _ => None,
+            }
+            .filter(|x| *x > 0)
+            .context(error::TransferSnafu {
+                key: "bandwidth",
+                reason: "expected a positive number of bytes per second, e.g. 5242880 or \"5MiB\"",
+            })?;
+            policy = policy.with_bandwidth(bandwidth);
+        }
+        if let Some(value) = node.get("concurrency") {
+            let concurrency = value
+                .as_int()
+                .and_then(|x| usize::try_from(x).ok())
+                .filter(|x| *x > 0)
+                .context(error::TransferSnafu {
+                    key: "concurrency",
+                    reason: "expected a positive integer",
+                })?;
+            policy = policy.with_concurrency(concurrency);
+        }
+        let retries = match node.get("retries") {
+            Some(value) => value.as_int().and_then(|x| u32::try_from(x).ok()).context(
+                error::TransferSnafu {
+                    key: "retries",
+                    reason: "expected a non-negative integer",
+                },
+            )?,
+            None => 0,
+        };
+        let retry_delay = match node.get("retry_delay_ms") {
+            Some(value) => value
+                .as_int()
+                .and_then(|x| u64::try_from(x).ok())
+                .map(Duration::from_millis)
+                .context(error::TransferSnafu {
+                    key: "retry_delay_ms",
+                    reason: "expected a non-negative number of milliseconds",
+                })?,
+            None => DEFAULT_RETRY_DELAY,
+        };
+        Ok(policy.with_retries(retries, retry_delay))
+    }
+
+    /// Caps the combined throughput of all transfers at `bytes` per second.
+    pub fn with_bandwidth(mut self, bytes: u64) -> Self {
+        self.bandwidth = Some(bytes);
+        self
+    }
+
+    /// Allows at most `layers` layer transfers at once.
+    pub fn with_concurrency(mut self, layers: usize) -> Self {
+        self.concurrency = Some(layers);
+        self.permits = Some(Arc::new(Semaphore::new(layers)));
+        self
+    }
+
+    /// Retries a failed layer transfer up to `retries` times, doubling `delay` after each attempt.
+    pub fn with_retries(mut self, retries: u32, delay: Duration) -> Self {
+        self.retries = retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Returns the bandwidth cap in bytes per second, if any.
+    pub fn bandwidth(&self) -> Option<u64> {
+        self.bandwidth
+    }
+
+    /// Returns the maximum number of concurrent layer transfers, if limited.
+    pub fn concurrency(&self) -> Option<usize> {
+        self.concurrency
+    }
+
+    /// Returns how many times a failed layer transfer is retried.
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Returns how long to wait before retry number `attempt`, starting at one.
+    pub fn retry_delay(&self, attempt: u32) -> Duration {
+        self.retry_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+    }
+
+    /// Waits for a free transfer slot, held until the returned permit is dropped.
+    pub async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        match self.permits.as_ref() {
+            Some(permits) => permits.clone().acquire_owned().await.ok(),
+            None => None,
+        }
+    }
+
+    /// Accounts for `bytes` transferred, sleeping long enough to stay under the bandwidth cap.
+    pub async fn throttle(&self, bytes: usize) {
+        let Some(bandwidth) = self.bandwidth else {
+            return;
+        };
+        let cost = Duration::from_secs_f64(bytes as f64 / bandwidth as f64);
+        let until = {
+            let mut next = self.next.lock();
+            let now = Instant::now();
+            let start = next.filter(|x| *x > now).unwrap_or(now);
+            *next = Some(start + cost);
+            start + cost
+        };
+        tokio::time::sleep_until(until).await;
+    }
+}
+
+/// Parse a rate such as `512KiB`, `5MiB/s` or `1G` into bytes per second.
+fn parse_rate(value: &str) -> Option<u64> {
+    let value = value.trim();
+    parse_size(value.strip_suffix("/s").unwrap_or(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn node(entries: &[(&str, Node)]) -> Node {
+        Node::new_table(
+            entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn rates_accept_binary_and_decimal_units() {
+        assert_eq!(parse_rate("2048"), Some(2048));
+        assert_eq!(parse_rate("512KiB"), Some(512 * 1024));
+        assert_eq!(parse_rate("5MiB/s"), Some(5 * 1024 * 1024));
+        assert_eq!(parse_rate("1 G"), Some(1_000_000_000));
+        assert_eq!(parse_rate("fast"), None);
+        assert_eq!(parse_rate("5PiB"), None);
+    }
+
+    #[test]
+    fn policy_is_read_from_cache_keys() {
+        let policy = TransferPolicy::from_node(&node(&[
+            ("bandwidth", Node::new_string("10MiB".to_string())),
+            ("concurrency", Node::new_int(2)),
+            ("retries", Node::new_int(3)),
+            ("retry_delay_ms", Node::new_int(100)),
+        ]))
+        .unwrap();
+        assert_eq!(policy.bandwidth(), Some(10 << 20));
+        assert_eq!(policy.concurrency(), Some(2));
+        assert_eq!(policy.retries(), 3);
+        assert_eq!(policy.retry_delay(1), Duration::from_millis(100));
+        assert_eq!(policy.retry_delay(3), Duration::from_millis(400));
+
+        let unlimited = TransferPolicy::from_node(&node(&[])).unwrap();
+        assert_eq!(unlimited.bandwidth(), None);
+        assert_eq!(unlimited.retries(), 0);
+    }
+
+    #[test]
+    fn invalid_keys_are_rejected() {
+        let err =
+            TransferPolicy::from_node(&node(&[("concurrency", Node::new_int(0))])).unwrap_err();
+        assert!(err.to_string().contains("'concurrency'"), "{err}");
+        let err = TransferPolicy::from_node(&node(&[(
+            "bandwidth",
+            Node::new_string("quick".to_string()),
+        )]))
+        .unwrap_err();
+        assert!(err.to_string().contains("'bandwidth'"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn throttle_spreads_transfers_over_time() {
+        let policy = TransferPolicy::default().with_bandwidth(1_000_000);
+        let start = Instant::now();
+        policy.throttle(50_000).await;
+        policy.clone().throttle(50_000).await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn concurrency_limits_outstanding_permits() {
+        let policy = TransferPolicy::default().with_concurrency(1);
+        let permit = policy.acquire().await;
+        assert!(permit.is_some());
+        let permits = policy.permits.clone().unwrap();
+        assert_eq!(permits.available_permits(), 0);
+        drop(permit);
+        assert_eq!(permits.available_permits(), 1);
+    }
+}
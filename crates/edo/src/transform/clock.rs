@@ -0,0 +1,117 @@
+//! Hermetic clocks for reproducible builds.
+//!
+//! Timestamps baked into outputs, such as archive mtimes or generated version
+//! banners, make two builds of the same inputs differ. A transform fixes the
+//! time its commands see with `source_date_epoch`, or inherits the
+//! `[reproducibility] source_date_epoch` of the project. The epoch is
+//! exported as `SOURCE_DATE_EPOCH`, which most toolchains honour, hashed into
+//! the transform's id and recorded in its artifact's metadata. Environments
+//! that can fake the system clock, like container farms with `faketime = true`,
+//! also start it at the epoch.
+
+use crate::context::{Config, ContextResult, FieldSchema, FieldType, Node, error as context_error};
+use chrono::DateTime;
+use snafu::OptionExt;
+
+/// Environment variable carrying the epoch, see <https://reproducible-builds.org/specs/source-date-epoch/>.
+pub const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+/// Field of transforms and the `[reproducibility]` table holding the epoch.
+pub const SOURCE_DATE_EPOCH_FIELD: &str = "source_date_epoch";
+
+/// Reads the epoch a transform defined by `node` runs at, in seconds since
+/// 1970, from its `source_date_epoch` or else from `config`.
+///
+/// A transform can opt out of the project's epoch with
+/// `source_date_epoch = false`.
+pub fn source_date_epoch(node: &Node, config: &Config) -> ContextResult<Option<i64>> {
+    if let Some(value) = node.get(SOURCE_DATE_EPOCH_FIELD) {
+        return parse(&value, SOURCE_DATE_EPOCH_FIELD);
+    }
+    match config
+        .get("reproducibility")
+        .and_then(|x| x.get(SOURCE_DATE_EPOCH_FIELD))
+    {
+        Some(value) => parse(&value, "reproducibility.source_date_epoch"),
+        None => Ok(None),
+    }
+}
+
+fn parse(value: &Node, field: &str) -> ContextResult<Option<i64>> {
+    if value.as_bool() == Some(false) {
+        return Ok(None);
+    }
+    value
+        .as_int()
+        .filter(|x| *x >= 0)
+        .map(Some)
+        .context(context_error::FieldSnafu {
+            field,
+            type_: "non-negative number of seconds since 1970, or false",
+        })
+}
+
+/// Describes the `source_date_epoch` field of a transform.
+pub fn epoch_field() -> FieldSchema {
+    FieldSchema::new(
+        SOURCE_DATE_EPOCH_FIELD,
+        FieldType::one_of([FieldType::Integer, FieldType::Boolean]),
+        "Seconds since 1970 exported as SOURCE_DATE_EPOCH, false ignores [reproducibility]",
+    )
+}
+
+/// The libfaketime `FAKETIME` value starting the clock at `epoch`, as
+/// `@YYYY-MM-DD hh:mm:ss` in UTC. The clock keeps ticking from there, so
+/// builds waiting on time still make progress.
+pub fn faketime(epoch: i64) -> Option<String> {
+    let time = DateTime::from_timestamp(epoch, 0)?;
+    Some(time.format("@%Y-%m-%d %H:%M:%S").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn transform(entries: &[(&str, Node)]) -> Node {
+        let table: BTreeMap<String, Node> = entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect();
+        Node::new_definition("transform", "script", "test", table)
+    }
+
+    #[tokio::test]
+    async fn transforms_override_the_project_epoch() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("edo.toml");
+        tokio::fs::write(&path, "[reproducibility]\nsource_date_epoch = 1700000000\n")
+            .await
+            .unwrap();
+        let config = Config::load(Some(&path)).await.unwrap();
+        let empty = Config::load(Some(&dir.path().join("missing.toml")))
+            .await
+            .unwrap();
+
+        assert_eq!(source_date_epoch(&transform(&[]), &empty).unwrap(), None);
+        assert_eq!(
+            source_date_epoch(&transform(&[]), &config).unwrap(),
+            Some(1700000000)
+        );
+        let own = transform(&[(SOURCE_DATE_EPOCH_FIELD, Node::new_int(86400))]);
+        assert_eq!(source_date_epoch(&own, &config).unwrap(), Some(86400));
+        let off = transform(&[(SOURCE_DATE_EPOCH_FIELD, Node::new_bool(false))]);
+        assert_eq!(source_date_epoch(&off, &config).unwrap(), None);
+        let negative = transform(&[(SOURCE_DATE_EPOCH_FIELD, Node::new_int(-1))]);
+        assert!(source_date_epoch(&negative, &config).is_err());
+    }
+
+    #[test]
+    fn faketime_starts_at_the_epoch_in_utc() {
+        assert_eq!(faketime(0).as_deref(), Some("@1970-01-01 00:00:00"));
+        assert_eq!(
+            faketime(1700000000).as_deref(),
+            Some("@2023-11-14 22:13:20")
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! Metadata contracts between transforms.
+//!
+//! A transform declares facts about its output with a `metadata` table, such
+//! as `metadata = { soname = "libfoo.so.1", abi = "gnu" }`. They are recorded
+//! under `contract` in its artifact's metadata and hashed into its id, and
+//! dependents read them with [`Handle::metadata`](crate::context::Handle::metadata)
+//! instead of passing ad-hoc files between transforms.
+//!
+//! A transform also lists the capabilities its artifact provides, such as
+//! `provides = ["toolchain:rust"]`. They are recorded in the artifact's
+//! [`Config::provides`](crate::storage::Config::provides) and indexed by the
+//! context, so [`Handle::find_providing`](crate::context::Handle::find_providing)
+//! locates a toolchain or runtime dependency without a hard-coded address.
+
+use super::{CacheKey, KeyKind};
+use crate::context::{ContextResult, FieldSchema, FieldType, Node, error as context_error};
+use crate::storage::Artifact;
+use serde_json::Value;
+use snafu::OptionExt;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Field of transforms declaring the metadata of their artifact.
+pub const METADATA_FIELD: &str = "metadata";
+/// Key of an artifact's metadata holding what its transform declared.
+pub const CONTRACT_KEY: &str = "contract";
+/// Field of transforms listing the capabilities of their artifact.
+pub const PROVIDES_FIELD: &str = "provides";
+
+/// Metadata a transform declares on its artifact, by name.
+pub type Contract = BTreeMap<String, Value>;
+
+/// Reads the `metadata` table of a transform defined by `node`, whose values
+/// are strings, numbers or booleans. Empty when the field is missing.
+pub fn declared_metadata(node: &Node) -> ContextResult<Contract> {
+    let Some(table) = node.get(METADATA_FIELD) else {
+        return Ok(Contract::new());
+    };
+    let error = context_error::FieldSnafu {
+        field: METADATA_FIELD,
+        type_: "table of strings, numbers or booleans",
+    };
+    let mut contract = Contract::new();
+    for (name, value) in table.as_table().context(error)? {
+        let value = if let Some(x) = value.as_string() {
+            Value::from(x)
+        } else if let Some(x) = value.as_int() {
+            Value::from(x)
+        } else if let Some(x) = value.as_float() {
+            Value::from(x)
+        } else {
+            Value::from(value.as_bool().context(error)?)
+        };
+        contract.insert(name, value);
+    }
+    Ok(contract)
+}
+
+/// Describes the `metadata` field of a transform.
+pub fn metadata_field() -> FieldSchema {
+    FieldSchema::new(
+        METADATA_FIELD,
+        FieldType::table(FieldType::one_of([
+            FieldType::String,
+            FieldType::Integer,
+            FieldType::Boolean,
+        ])),
+        "Facts about the artifact dependents can read, such as its soname",
+    )
+}
+
+/// Reads the `provides` list of a transform defined by `node`. Empty when the
+/// field is missing.
+pub fn declared_provides(node: &Node) -> ContextResult<BTreeSet<String>> {
+    let Some(list) = node.get(PROVIDES_FIELD) else {
+        return Ok(BTreeSet::new());
+    };
+    list.as_list()
+        .and_then(|x| x.iter().map(|x| x.as_string()).collect())
+        .context(context_error::FieldSnafu {
+            field: PROVIDES_FIELD,
+            type_: "list of strings",
+        })
+}
+
+/// Describes the `provides` field of a transform.
+pub fn provides_field() -> FieldSchema {
+    FieldSchema::new(
+        PROVIDES_FIELD,
+        FieldType::list(FieldType::String),
+        "Capabilities of the artifact transforms can look up, such as toolchain:rust",
+    )
+}
+
+/// Hashes every entry of `contract` into `key`, so changing what a
+/// transform declares gives its artifact a new id.
+pub fn add_contract(key: &mut CacheKey, contract: &Contract) {
+    for (name, value) in contract.iter() {
+        key.add_content(KeyKind::Metadata, name, &format!("{name}={value}"));
+    }
+}
+
+/// Hashes the capabilities in `provides` into `key`, as they are part of the
+/// artifact's config.
+pub fn add_provides(key: &mut CacheKey, provides: &BTreeSet<String>) {
+    for capability in provides.iter() {
+        key.add_content(KeyKind::Metadata, PROVIDES_FIELD, capability);
+    }
+}
+
+/// Adds the capabilities in `provides` to the config of `artifact`.
+pub fn record_provides(artifact: &mut Artifact, provides: &BTreeSet<String>) {
+    artifact
+        .config_mut()
+        .provides_mut()
+        .extend(provides.iter().cloned());
+}
+
+/// Records `contract` in the metadata of `artifact`, keeping anything else
+/// the transform put there.
+pub fn record_contract(artifact: &mut Artifact, contract: &Contract) {
+    if contract.is_empty() {
+        return;
+    }
+    let metadata = artifact.config_mut().metadata_mut();
+    if !metadata.is_object() {
+        *metadata = Value::Object(Default::default());
+    }
+    metadata[CONTRACT_KEY] = Value::Object(contract.clone().into_iter().collect());
+}
+
+/// The metadata the transform building `artifact` declared.
+pub fn contract_of(artifact: &Artifact) -> Contract {
+    match artifact.config().metadata().get(CONTRACT_KEY) {
+        Some(Value::Object(contract)) => contract.clone().into_iter().collect(),
+        _ => Contract::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Config, Id, MediaType};
+
+    #[test]
+    fn declared_metadata_round_trips_through_the_artifact() {
+        let node = Node::new_definition(
+            "transform",
+            "script",
+            "libfoo",
+            BTreeMap::from([(
+                METADATA_FIELD.to_string(),
+                Node::new_table(BTreeMap::from([
+                    ("soname".to_string(), Node::new_string("libfoo.so.1".into())),
+                    ("abi".to_string(), Node::new_int(2)),
+                ])),
+            )]),
+        );
+        let contract = declared_metadata(&node).unwrap();
+        assert_eq!(contract["soname"], "libfoo.so.1");
+        assert_eq!(contract["abi"], 2);
+
+        let id = Id::builder()
+            .name("libfoo".to_string())
+            .digest("1".to_string())
+            .build();
+        let mut artifact = Artifact::builder()
+            .media_type(MediaType::Manifest)
+            .config(
+                Config::builder()
+                    .id(id)
+                    .metadata(serde_json::json!({ "source_date_epoch": 0 }))
+                    .build(),
+            )
+            .build();
+        assert!(contract_of(&artifact).is_empty());
+        record_contract(&mut artifact, &contract);
+        assert_eq!(contract_of(&artifact), contract);
+        assert_eq!(artifact.config().metadata()["source_date_epoch"], 0);
+    }
+
+    #[test]
+    fn declared_provides_are_recorded_on_the_artifact() {
+        let node = Node::new_definition(
+            "transform",
+            "script",
+            "rustc",
+            BTreeMap::from([(
+                PROVIDES_FIELD.to_string(),
+                Node::new_list(vec![Node::new_string("toolchain:rust".into())]),
+            )]),
+        );
+        let provides = declared_provides(&node).unwrap();
+        let id = Id::builder()
+            .name("rustc".to_string())
+            .digest("1".to_string())
+            .build();
+        let mut artifact = Artifact::builder()
+            .media_type(MediaType::Manifest)
+            .config(Config::builder().id(id).build())
+            .build();
+        record_provides(&mut artifact, &provides);
+        assert!(artifact.config().provides().contains("toolchain:rust"));
+
+        let bad = Node::new_definition(
+            "transform",
+            "script",
+            "rustc",
+            BTreeMap::from([(
+                PROVIDES_FIELD.to_string(),
+                Node::new_string("toolchain:rust".into()),
+            )]),
+        );
+        assert!(declared_provides(&bad).is_err());
+    }
+
+    #[test]
+    fn nested_metadata_is_rejected() {
+        let node = Node::new_definition(
+            "transform",
+            "script",
+            "libfoo",
+            BTreeMap::from([(
+                METADATA_FIELD.to_string(),
+                Node::new_table(BTreeMap::from([(
+                    "abi".to_string(),
+                    Node::new_list(vec![Node::new_string("gnu".into())]),
+                )])),
+            )]),
+        );
+        assert!(declared_metadata(&node).is_err());
+    }
+}
@@ -0,0 +1,423 @@
+//! Composable cache keys.
+//!
+//! A [`CacheKey`] records every input a transform's artifact id is derived
+//! from as a named [`KeyComponent`], so the id can be explained component by
+//! component (`edo inspect --key`). A [`KeyPolicy`], read from the `[ids]`
+//! config table, decides which components take part in the hash.
+
+use crate::context::{Config, ContextResult, error as context_error};
+use crate::storage::Id;
+use serde::{Deserialize, Serialize};
+use snafu::OptionExt;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// The kind of input a [`KeyComponent`] covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "&'static str", try_from = "String")]
+pub enum KeyKind {
+    /// The id of a dependency transform.
+    Depend,
+    /// The id of a source.
+    Source,
+    /// A matrix variant `key=value` pair.
+    Variant,
+    /// The commands a transform runs.
+    Command,
+    /// Host mounts and devices exposed to the build.
+    Host,
+    /// The toolchain identity of the environment farm and interpreter.
+    Environment,
+    /// Metadata declared on the artifact for dependents to read.
+    Metadata,
+    /// The target architecture, carried on the id rather than hashed.
+    Platform,
+    /// A user supplied salt mixed into every key.
+    Salt,
+    /// An id computed by a transform that does not describe its inputs.
+    Opaque,
+}
+
+impl KeyKind {
+    /// Every kind, in the order they are listed in the `[ids]` config.
+    pub const ALL: [KeyKind; 10] = [
+        Self::Depend,
+        Self::Source,
+        Self::Variant,
+        Self::Command,
+        Self::Host,
+        Self::Environment,
+        Self::Metadata,
+        Self::Platform,
+        Self::Salt,
+        Self::Opaque,
+    ];
+
+    /// The name used for this kind in config and output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Depend => "deps",
+            Self::Source => "sources",
+            Self::Variant => "variant",
+            Self::Command => "commands",
+            Self::Host => "host",
+            Self::Environment => "env",
+            Self::Metadata => "metadata",
+            Self::Platform => "platform",
+            Self::Salt => "salt",
+            Self::Opaque => "opaque",
+        }
+    }
+
+    /// Looks a kind up by its [`name`](Self::name).
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|x| x.name() == name)
+    }
+}
+
+impl From<KeyKind> for &'static str {
+    fn from(kind: KeyKind) -> Self {
+        kind.name()
+    }
+}
+
+impl TryFrom<String> for KeyKind {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Self::parse(&name).ok_or_else(|| format!("unknown key component kind '{name}'"))
+    }
+}
+
+impl fmt::Display for KeyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Decides which components of a [`CacheKey`] are hashed and how.
+///
+/// Read from the `[ids]` table of the user config or a project's `[config]`:
+///
+/// ```toml
+/// [ids]
+/// exclude     = ["platform"]  # component kinds left out of every key
+/// normalize   = true          # ignore blank lines and comment lines in commands
+/// salt        = "2024-06"     # mixed into every key to invalidate all artifacts
+/// environment = false         # shorthand for excluding "env"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyPolicy {
+    /// Component kinds left out of the hash.
+    pub exclude: BTreeSet<KeyKind>,
+    /// Strip blank lines and `#` comment lines from commands before hashing.
+    pub normalize: bool,
+    /// Extra value mixed into every key.
+    pub salt: Option<String>,
+}
+
+impl KeyPolicy {
+    /// Reads the policy from the `[ids]` table of `config`, if present.
+    pub fn from_config(config: &Config) -> ContextResult<Self> {
+        let mut policy = Self::default();
+        let Some(node) = config.get("ids") else {
+            return Ok(policy);
+        };
+        let field = |field: &str, type_: &str| context_error::FieldSnafu {
+            field: format!("ids.{field}"),
+            type_: type_.to_string(),
+        };
+        if let Some(value) = node.get("environment")
+            && !value.as_bool().context(field("environment", "bool"))?
+        {
+            policy.exclude.insert(KeyKind::Environment);
+        }
+        if let Some(value) = node.get("exclude") {
+            let names: Vec<&str> = KeyKind::ALL.iter().map(|x| x.name()).collect();
+            let type_ = format!("list of {}", names.join(", "));
+            for item in value.as_list().context(field("exclude", &type_))? {
+                let kind = item
+                    .as_string()
+                    .and_then(|x| KeyKind::parse(&x))
+                    .context(field("exclude", &type_))?;
+                policy.exclude.insert(kind);
+            }
+        }
+        if let Some(value) = node.get("normalize") {
+            policy.normalize = value.as_bool().context(field("normalize", "bool"))?;
+        }
+        if let Some(value) = node.get("salt") {
+            policy.salt = Some(value.as_string().context(field("salt", "string"))?);
+        }
+        Ok(policy)
+    }
+
+    /// Returns `true` when components of `kind` are hashed.
+    pub fn includes(&self, kind: KeyKind) -> bool {
+        !self.exclude.contains(&kind)
+    }
+}
+
+/// One input of a [`CacheKey`].
+///
+/// Run summaries record the components of every key without the hashed
+/// value, which is what `edo why` compares a transform's key against.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyComponent {
+    /// What the input is.
+    pub kind: KeyKind,
+    /// Which input of that kind, e.g. a source name or dependency address.
+    pub name: String,
+    /// Digest identifying the input.
+    pub digest: String,
+    /// `false` when the policy leaves the component out of the hash.
+    pub included: bool,
+    #[serde(skip)]
+    value: String,
+}
+
+/// The inputs an artifact id is derived from.
+///
+/// Components are hashed in the order they were added, so transforms must
+/// add them in a stable order.
+#[derive(Clone, Debug)]
+pub struct CacheKey {
+    name: String,
+    policy: KeyPolicy,
+    components: Vec<KeyComponent>,
+    fixed: Option<Id>,
+}
+
+impl CacheKey {
+    /// Starts an empty key for an artifact called `name`.
+    pub fn new(name: impl Into<String>, policy: &KeyPolicy) -> Self {
+        Self {
+            name: name.into(),
+            policy: policy.clone(),
+            components: Vec::new(),
+            fixed: None,
+        }
+    }
+
+    /// Wraps an id computed without a key, for transforms that do not
+    /// describe their inputs.
+    pub fn opaque(id: &Id) -> Self {
+        let mut key = Self::new(id.name(), &KeyPolicy::default());
+        key.push(KeyKind::Opaque, "id", id.digest(), id.digest().clone());
+        key.fixed = Some(id.clone());
+        key
+    }
+
+    /// Adds an input that is already identified by a digest, such as the id
+    /// of a source or dependency.
+    pub fn add_digest(&mut self, kind: KeyKind, name: impl Into<String>, digest: &str) {
+        self.push(kind, name, digest, digest.to_string());
+    }
+
+    /// Adds an input by its content, which is hashed.
+    pub fn add_content(&mut self, kind: KeyKind, name: impl Into<String>, content: &str) {
+        let digest = blake3::hash(content.as_bytes()).to_hex().to_string();
+        self.push(kind, name, &digest, content.to_string());
+    }
+
+    /// Adds the commands a transform runs, normalized if the policy asks.
+    pub fn add_commands(&mut self, commands: &[String]) {
+        self.add_command_section("script", commands);
+    }
+
+    /// Adds a separate group of commands, such as those run before or after
+    /// a script's main commands, under `name`.
+    pub fn add_command_section(&mut self, name: &str, commands: &[String]) {
+        let script = if self.policy.normalize {
+            commands
+                .iter()
+                .map(|x| normalize(x))
+                .filter(|x| !x.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            commands.join("\n")
+        };
+        self.add_content(KeyKind::Command, name, &script);
+    }
+
+    /// Sets the architecture the artifact is built for.
+    pub fn add_platform(&mut self, arch: &str) {
+        self.push(KeyKind::Platform, "arch", arch, arch.to_string());
+    }
+
+    /// The recorded components, including those the policy excludes.
+    pub fn components(&self) -> &[KeyComponent] {
+        &self.components
+    }
+
+    /// The recorded components followed by the salt, as listed by
+    /// `edo inspect --key`.
+    pub fn breakdown(&self) -> Vec<KeyComponent> {
+        self.components.iter().cloned().chain(self.salt()).collect()
+    }
+
+    /// The hash of every included component, with the salt last.
+    pub fn digest(&self) -> String {
+        let mut hash = blake3::Hasher::new();
+        for component in self.hashed() {
+            hash.update(component.value.as_bytes());
+        }
+        if let Some(salt) = self.salt() {
+            hash.update(salt.value.as_bytes());
+        }
+        base16::encode_lower(hash.finalize().as_bytes())
+    }
+
+    /// The artifact id this key resolves to.
+    pub fn id(&self) -> Id {
+        if let Some(id) = self.fixed.as_ref() {
+            return id.clone();
+        }
+        let arch = self
+            .components
+            .iter()
+            .find(|x| x.kind == KeyKind::Platform && x.included)
+            .map(|x| x.value.clone());
+        Id::builder()
+            .name(self.name.clone())
+            .digest(self.digest())
+            .maybe_arch(arch)
+            .build()
+    }
+
+    fn hashed(&self) -> impl Iterator<Item = &KeyComponent> {
+        self.components
+            .iter()
+            .filter(|x| x.included && x.kind != KeyKind::Platform)
+    }
+
+    fn salt(&self) -> Option<KeyComponent> {
+        let salt = self.policy.salt.as_ref()?;
+        Some(KeyComponent {
+            kind: KeyKind::Salt,
+            name: "salt".into(),
+            digest: blake3::hash(salt.as_bytes()).to_hex().to_string(),
+            included: self.policy.includes(KeyKind::Salt),
+            value: salt.clone(),
+        })
+        .filter(|x| x.included)
+    }
+
+    fn push(&mut self, kind: KeyKind, name: impl Into<String>, digest: &str, value: String) {
+        self.components.push(KeyComponent {
+            kind,
+            name: name.into(),
+            digest: digest.to_string(),
+            included: self.policy.includes(kind),
+            value,
+        });
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let components = self.breakdown();
+        let width = components
+            .iter()
+            .map(|x| x.name.len())
+            .max()
+            .unwrap_or_default();
+        for component in components.iter() {
+            write!(
+                f,
+                "{:<9} {:<width$} {}",
+                component.kind.name(),
+                component.name,
+                component.digest
+            )?;
+            if !component.included {
+                f.write_str(" (excluded)")?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "id        {}", self.id())
+    }
+}
+
+/// Drops blank lines and `#` comment lines and trims the rest.
+fn normalize(command: &str) -> String {
+    command
+        .lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(policy: &KeyPolicy, commands: &[&str]) -> CacheKey {
+        let mut key = CacheKey::new("pkg-build", policy);
+        key.add_digest(KeyKind::Source, "src", "abc");
+        key.add_commands(&commands.iter().map(|x| x.to_string()).collect::<Vec<_>>());
+        key.add_content(KeyKind::Environment, "interpreter", "interpreter=sh");
+        key.add_platform("x86_64");
+        key
+    }
+
+    #[test]
+    fn digest_hashes_included_values_in_order() {
+        let key = key(&KeyPolicy::default(), &["make", "make install"]);
+        let mut hash = blake3::Hasher::new();
+        hash.update(b"abc");
+        hash.update(b"make\nmake install");
+        hash.update(b"interpreter=sh");
+        assert_eq!(
+            key.digest(),
+            base16::encode_lower(hash.finalize().as_bytes())
+        );
+        assert_eq!(key.id().arch().as_deref(), Some("x86_64"));
+    }
+
+    #[test]
+    fn policy_excludes_components() {
+        let policy = KeyPolicy {
+            exclude: [KeyKind::Environment, KeyKind::Platform].into(),
+            ..Default::default()
+        };
+        let all = key(&KeyPolicy::default(), &["make"]);
+        let some = key(&policy, &["make"]);
+        assert_ne!(all.digest(), some.digest());
+        assert_eq!(some.id().arch(), None);
+        assert!(some.to_string().contains("(excluded)"));
+    }
+
+    #[test]
+    fn normalization_ignores_comments() {
+        let policy = KeyPolicy {
+            normalize: true,
+            ..Default::default()
+        };
+        let plain = key(&policy, &["make", "make install"]);
+        let commented = key(
+            &policy,
+            &["# build it", "  make\n\n# then install", "make install"],
+        );
+        assert_eq!(plain.digest(), commented.digest());
+        let strict = key(
+            &KeyPolicy::default(),
+            &["# build it", "make", "make install"],
+        );
+        assert_ne!(plain.digest(), strict.digest());
+    }
+
+    #[test]
+    fn salt_changes_every_key() {
+        let policy = KeyPolicy {
+            salt: Some("2024-06".into()),
+            ..Default::default()
+        };
+        assert_ne!(
+            key(&policy, &["make"]).digest(),
+            key(&KeyPolicy::default(), &["make"]).digest()
+        );
+    }
+}
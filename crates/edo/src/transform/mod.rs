@@ -10,12 +10,26 @@
 //! by [`TransformError`].
 
 use crate::context::{Addr, Handle, Log};
-use crate::environment::Environment;
+use crate::environment::{CommandFailure, Environment, HostAccess, HostRequirements};
 use crate::storage::{Artifact, Id};
 use arc_handle::arc_handle;
 use async_trait::async_trait;
 use std::path::PathBuf;
 
+mod clock;
+mod contract;
+mod key;
+mod overrides;
+mod platform;
+mod results;
+
+pub use clock::*;
+pub use contract::*;
+pub use key::*;
+pub use overrides::*;
+pub use platform::*;
+pub use results::*;
+
 /// Convenience result alias for fallible transform operations.
 pub type TransformResult<T> = std::result::Result<T, error::TransformError>;
 pub use error::TransformError;
@@ -32,6 +46,12 @@ pub trait Transform {
     async fn environment(&self) -> TransformResult<Addr>;
     /// Compute the unique artifact [`Id`] that will represent this transform's output.
     async fn get_unique_id(&self, ctx: &Handle) -> TransformResult<Id>;
+    /// Describe the inputs [`get_unique_id`](Self::get_unique_id) is derived
+    /// from. Transforms that build their id from a [`CacheKey`] should return
+    /// it here, the default only reports the id itself.
+    async fn cache_key(&self, ctx: &Handle) -> TransformResult<CacheKey> {
+        Ok(CacheKey::opaque(&self.get_unique_id(ctx).await?))
+    }
     /// Returns addresses of all transforms this one depends on.
     async fn depends(&self) -> TransformResult<Vec<Addr>>;
     /// Prepare the transform by fetching all sources and dependent artifacts into storage.
@@ -44,6 +64,26 @@ pub trait Transform {
     fn can_shell(&self) -> bool;
     /// Open an interactive shell in the environment at the transform's working directory.
     fn shell(&self, env: &Environment) -> TransformResult<()>;
+    /// Host mounts and devices this transform needs exposed in its environment.
+    fn host_access(&self) -> HostAccess {
+        HostAccess::default()
+    }
+    /// Whether this transform runs edo itself. Its environment then gets
+    /// the local cache mounted read-only and named in
+    /// [`NESTED_CACHE_ENV`](crate::context::NESTED_CACHE_ENV).
+    fn nested(&self) -> bool {
+        false
+    }
+    /// Architecture this transform must execute on, when it requests one.
+    /// The scheduler runs it in a farm that can execute it.
+    fn platform(&self) -> Option<String> {
+        None
+    }
+    /// What this transform needs of the machine it builds on. The scheduler
+    /// checks them before anything runs, see [`HostRequirements`].
+    fn host_requires(&self) -> HostRequirements {
+        HostRequirements::default()
+    }
 }
 
 /// The outcome of a transform execution.
@@ -59,6 +99,16 @@ pub enum TransformStatus {
     Failed(Option<PathBuf>, error::TransformError),
 }
 
+impl TransformStatus {
+    /// The failing script command, when the transform failed running one.
+    pub fn command_failure(&self) -> Option<&CommandFailure> {
+        match self {
+            Self::Success(..) => None,
+            Self::Retryable(_, e) | Self::Failed(_, e) => e.command_failure(),
+        }
+    }
+}
+
 /// Errors produced by the transform subsystem.
 pub mod error {
     use snafu::Snafu;
@@ -99,6 +149,28 @@ pub mod error {
             #[snafu(source(from(crate::storage::StorageError, Box::new)))]
             source: Box<crate::storage::StorageError>,
         },
+        /// A dependency holds layers for several platforms, none of them the
+        /// one being built for.
+        #[snafu(display("dependency {id} has no layers for {arch}, only for {available}"))]
+        NoMatchingPlatform {
+            id: String,
+            arch: String,
+            available: String,
+        },
+    }
+
+    impl TransformError {
+        /// The failing script command, when this error came from a traced
+        /// [`Command`](crate::environment::Command).
+        pub fn command_failure(&self) -> Option<&crate::environment::CommandFailure> {
+            match self {
+                Self::Environment { source } => match source.as_ref() {
+                    crate::environment::EnvironmentError::Command { failure } => Some(failure),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
     }
 }
 
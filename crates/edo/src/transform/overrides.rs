@@ -0,0 +1,66 @@
+use super::{Transform, TransformImpl, TransformResult, TransformStatus};
+use crate::context::{Addr, Handle, Log};
+use crate::environment::Environment;
+use crate::storage::{Artifact, Id};
+use async_trait::async_trait;
+
+/// Stands in for a transform whose artifact was injected with
+/// [`Storage::inject`](crate::storage::Storage::inject), see
+/// [`Context::add_override`](crate::context::Context::add_override).
+///
+/// It has no dependencies and its artifact is in the local cache already,
+/// so the scheduler treats it as built and dependents stage the injected
+/// tree in place of the transform's own artifact.
+pub struct OverrideTransform {
+    replaced: Transform,
+    artifact: Artifact,
+}
+
+impl OverrideTransform {
+    /// Overrides `replaced` with `artifact`.
+    pub fn new(replaced: &Transform, artifact: &Artifact) -> Self {
+        Self {
+            replaced: replaced.clone(),
+            artifact: artifact.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl TransformImpl for OverrideTransform {
+    async fn environment(&self) -> TransformResult<Addr> {
+        self.replaced.environment().await
+    }
+
+    async fn get_unique_id(&self, _ctx: &Handle) -> TransformResult<Id> {
+        Ok(self.artifact.config().id().clone())
+    }
+
+    async fn depends(&self) -> TransformResult<Vec<Addr>> {
+        Ok(Vec::new())
+    }
+
+    async fn prepare(&self, _log: &Log, _ctx: &Handle) -> TransformResult<()> {
+        Ok(())
+    }
+
+    async fn stage(&self, _log: &Log, _ctx: &Handle, _env: &Environment) -> TransformResult<()> {
+        Ok(())
+    }
+
+    async fn transform(&self, _log: &Log, ctx: &Handle, _env: &Environment) -> TransformStatus {
+        // Only reached when the cache is bypassed, the artifact is never rebuilt
+        match ctx.storage().safe_open(self.artifact.config().id()).await {
+            Ok(artifact) => TransformStatus::Success(artifact),
+            Err(e) => TransformStatus::Failed(None, e.into()),
+        }
+    }
+
+    fn can_shell(&self) -> bool {
+        false
+    }
+
+    fn shell(&self, _env: &Environment) -> TransformResult<()> {
+        Ok(())
+    }
+}
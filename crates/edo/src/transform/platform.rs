@@ -0,0 +1,99 @@
+use super::{TransformResult, error};
+use crate::environment::canonical_arch;
+use crate::storage::{Artifact, Layer};
+use std::collections::BTreeSet;
+
+/// Picks the layers of a dependency `artifact` to stage for a transform
+/// targeting `arch`.
+///
+/// Layers without a platform are always staged. When the artifact's layers
+/// name a single architecture they are all staged, as artifacts built for
+/// one platform recorded it loosely. Otherwise only the layers of `arch` are
+/// staged, matched as [`canonical_arch`] names, and an artifact with none
+/// for `arch` is an error rather than a build against the wrong platform.
+pub fn select_layers<'a>(artifact: &'a Artifact, arch: &str) -> TransformResult<Vec<&'a Layer>> {
+    let available: BTreeSet<&str> = artifact
+        .layers()
+        .iter()
+        .filter_map(|x| x.platform().as_ref())
+        .map(|x| canonical_arch(&x.architecture))
+        .collect();
+    if available.len() < 2 {
+        return Ok(artifact.layers().iter().collect());
+    }
+    let arch = canonical_arch(arch);
+    snafu::ensure!(
+        available.contains(arch),
+        error::NoMatchingPlatformSnafu {
+            id: artifact.config().id().to_string(),
+            arch,
+            available: available.into_iter().collect::<Vec<_>>().join(", "),
+        }
+    );
+    Ok(artifact
+        .layers()
+        .iter()
+        .filter(|x| {
+            x.platform()
+                .as_ref()
+                .is_none_or(|x| canonical_arch(&x.architecture) == arch)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Compression, Config, DigestAlgorithm, Id, LayerDigest, MediaType};
+    use ocilot::models::Platform;
+
+    fn artifact(platforms: &[Option<&str>]) -> Artifact {
+        let mut artifact = Artifact::builder()
+            .media_type(MediaType::Manifest)
+            .config(
+                Config::builder()
+                    .id(Id::builder()
+                        .name("dep".to_string())
+                        .digest("1".to_string())
+                        .build())
+                    .build(),
+            )
+            .build();
+        for (index, arch) in platforms.iter().enumerate() {
+            artifact.layers_mut().push(
+                Layer::builder()
+                    .media_type(MediaType::Tar(Compression::None))
+                    .digest(LayerDigest::new(DigestAlgorithm::Blake3, index.to_string()))
+                    .size(0usize)
+                    .maybe_platform(
+                        arch.map(|x| Platform::builder().os("linux").architecture(x).build()),
+                    )
+                    .build(),
+            );
+        }
+        artifact
+    }
+
+    fn staged(artifact: &Artifact, arch: &str) -> Vec<String> {
+        select_layers(artifact, arch)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.digest().digest())
+            .collect()
+    }
+
+    #[test]
+    fn single_platform_artifacts_stage_every_layer() {
+        let artifact = artifact(&[Some("linux"), Some("linux"), None]);
+        assert_eq!(staged(&artifact, "aarch64"), ["0", "1", "2"]);
+    }
+
+    #[test]
+    fn multi_platform_artifacts_stage_the_matching_layers() {
+        let artifact = artifact(&[Some("amd64"), Some("arm64"), None]);
+        assert_eq!(staged(&artifact, "x86_64"), ["0", "2"]);
+        assert_eq!(staged(&artifact, "arm64"), ["1", "2"]);
+        let error = select_layers(&artifact, "riscv64").unwrap_err();
+        assert!(error.to_string().contains("aarch64, x86_64"), "{error}");
+    }
+}
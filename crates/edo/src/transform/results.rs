@@ -0,0 +1,88 @@
+//! Result artifacts captured from a transform's workspace.
+//!
+//! Test reports and benchmark numbers are worth keeping whether or not a
+//! build succeeds, so transforms that support a `capture` list save the
+//! matching files as a secondary artifact next to their output. It has the
+//! id of the output renamed with a `_results` suffix, so it can be found
+//! from the transform alone, and a distinct [`results_media_type`].
+
+use crate::environment::quote;
+use crate::storage::{Compression, Id, MediaType};
+
+/// Name of the media type of captured result artifacts.
+pub const RESULTS_MEDIA_TYPE: &str = "results";
+
+/// The media type of captured result artifacts.
+pub fn results_media_type() -> MediaType {
+    MediaType::Custom(RESULTS_MEDIA_TYPE.to_string(), Compression::None)
+}
+
+/// The id of the results captured while building the artifact `id`.
+pub fn results_id(id: &Id) -> Id {
+    let mut results = id.clone();
+    results.set_name(&format!("{}_results", id.name()));
+    results
+}
+
+/// A shell script copying every file matching the shell glob `patterns`,
+/// relative to the working directory, into `dest` under the same relative
+/// path. Patterns that match nothing are skipped.
+pub fn capture_script(patterns: &[String], dest: &str) -> String {
+    let dest = quote(dest);
+    // Patterns are left unquoted so the shell expands them
+    format!(
+        "for f in {}; do\n  [ -f \"$f\" ] || continue\n  mkdir -p {dest}/\"$(dirname \"$f\")\" && cp \"$f\" {dest}/\"$f\" || exit\ndone",
+        patterns.join(" ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_are_named_after_the_output() {
+        let id = Id::builder()
+            .name("app_test".to_string())
+            .arch("x86_64".to_string())
+            .digest("abc".to_string())
+            .build();
+        let results = results_id(&id);
+        assert_eq!(results.name(), "app_test_results");
+        assert_eq!(results.digest(), id.digest());
+        assert_eq!(results.arch(), id.arch());
+        assert_eq!(
+            results_media_type().to_string(),
+            "vnd.edo.artifact.v1.results"
+        );
+    }
+
+    #[test]
+    fn capture_script_copies_matches_under_dest() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let work = dir.path().join("work");
+        std::fs::create_dir_all(work.join("results/unit")).unwrap();
+        std::fs::write(work.join("results/unit/a.xml"), "a").unwrap();
+        std::fs::write(work.join("results/b.xml"), "b").unwrap();
+        std::fs::write(work.join("results/c.txt"), "c").unwrap();
+        let dest = dir.path().join("it's captured");
+        let script = capture_script(
+            &[
+                "results/*.xml".to_string(),
+                "results/*/*.xml".to_string(),
+                "bench/*.json".to_string(),
+            ],
+            dest.to_str().unwrap(),
+        );
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&script)
+            .current_dir(&work)
+            .status()
+            .unwrap();
+        assert!(status.success(), "{script}");
+        assert!(dest.join("results/unit/a.xml").is_file());
+        assert!(dest.join("results/b.xml").is_file());
+        assert!(!dest.join("results/c.txt").exists());
+    }
+}
@@ -5,9 +5,81 @@ use std::os::fd::IntoRawFd;
 use std::path::Path;
 
 use dashmap::DashMap;
-use duct::IntoExecutablePath;
-use std::collections::HashMap;
+use duct::{Expression, IntoExecutablePath};
+use os_pipe::PipeWriter;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
+use std::process::Output;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Process groups of the logged commands still running, see [`terminate_commands`].
+static RUNNING: LazyLock<Mutex<HashSet<u32>>> = LazyLock::new(Default::default);
+
+/// How long [`terminate_commands`] waits before killing what ignored it.
+const TERMINATE_GRACE: Duration = Duration::from_secs(5);
+
+/// Keeps a command's process group in [`RUNNING`] while it runs.
+struct Running(Vec<u32>);
+
+impl Running {
+    fn register(pids: Vec<u32>) -> Self {
+        RUNNING.lock().extend(pids.iter().copied());
+        Self(pids)
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        let mut running = RUNNING.lock();
+        for pid in self.0.iter() {
+            running.remove(pid);
+        }
+    }
+}
+
+/// Sends `signal` to the process group of each of `pids` still running,
+/// returning the ones signalled.
+fn signal_commands(pids: Option<&HashSet<u32>>, signal: i32) -> HashSet<u32> {
+    let running = RUNNING.lock();
+    let targets: HashSet<u32> = match pids {
+        Some(pids) => running.intersection(pids).copied().collect(),
+        None => running.clone(),
+    };
+    for pid in targets.iter() {
+        // SAFETY: killpg only sends a signal, a group that already exited
+        // makes it fail harmlessly
+        unsafe {
+            libc::killpg(*pid as libc::pid_t, signal);
+        }
+    }
+    targets
+}
+
+/// Stops every command started by the functions of this module that stream
+/// into a build log, along with everything it started.
+///
+/// Each such command runs in a process group of its own, so a build's
+/// compilers and test runners stop with the shell running them. They are
+/// asked to terminate first and killed if still running after a grace
+/// period. Returns the number of commands signalled.
+pub fn terminate_commands() -> usize {
+    let signalled = signal_commands(None, libc::SIGTERM);
+    let count = signalled.len();
+    if count > 0 {
+        std::thread::spawn(move || {
+            std::thread::sleep(TERMINATE_GRACE);
+            signal_commands(Some(&signalled), libc::SIGKILL);
+        });
+    }
+    count
+}
+
+/// Kills every command [`terminate_commands`] would stop, at once.
+pub fn kill_commands() -> usize {
+    signal_commands(None, libc::SIGKILL).len()
+}
 
 /// Convert a [`DashMap`] into a standard [`HashMap`] by cloning all entries.
 pub fn from_dash<K, V>(input: &DashMap<K, V>) -> HashMap<K, V>
@@ -21,7 +93,39 @@ where
         .collect()
 }
 
-/// Run a command with piped stdin, capturing stdout+stderr to the build log.
+/// Starts `expr` with the write end of a pipe handed to `attach` and streams
+/// everything written to it into `log` line by line until the process exits.
+///
+/// `feed` runs on the calling thread while the output is streamed, e.g. to
+/// write the process's stdin.
+fn run_streamed(
+    log: &Log,
+    expr: Expression,
+    attach: impl FnOnce(Expression, PipeWriter) -> Expression,
+    feed: impl FnOnce() -> Result<()>,
+) -> Result<Output> {
+    let (reader, writer) = os_pipe::pipe()?;
+    // The expression holding the write end is dropped at the end of this
+    // statement so the stream ends once the process does
+    let handle = attach(expr, writer)
+        .unchecked()
+        .before_spawn(|cmd| {
+            std::os::unix::process::CommandExt::process_group(cmd, 0);
+            Ok(())
+        })
+        .start()?;
+    let _running = Running::register(handle.pids());
+    std::thread::scope(|scope| {
+        let streamer = scope.spawn(|| log.stream(reader));
+        let fed = feed();
+        let output = handle.into_output()?;
+        streamer.join().expect("log streaming thread panicked")?;
+        fed?;
+        Ok(output)
+    })
+}
+
+/// Run a command with piped stdin, streaming stdout+stderr to the build log.
 ///
 /// Returns `true` if the process exits successfully.
 pub fn cmd<P, S, In, A, I>(
@@ -43,21 +147,23 @@ where
     let mut expr = duct::cmd(program, args)
         .dir(path.as_ref())
         .stderr_to_stdout()
-        .stdout_file(log)
         .stdin_file(pipe_reader);
     for (key, value) in env.iter() {
         expr = expr.env(key.clone(), value.clone());
     }
-
-    let handle = expr.unchecked().start()?;
-    std::io::copy(input, &mut pipe_writer)?;
-    pipe_writer.flush()?;
-    drop(pipe_writer);
-    let output = handle.wait()?;
+    let output = run_streamed(
+        log,
+        expr,
+        |expr, out| expr.stdout_file(out),
+        move || {
+            std::io::copy(input, &mut pipe_writer)?;
+            pipe_writer.flush()
+        },
+    )?;
     Ok(output.status.success())
 }
 
-/// Run a command capturing stdout into a byte vector; stderr goes to the log.
+/// Run a command capturing stdout into a byte vector; stderr is streamed to the log.
 pub fn cmd_collect_out<P, S, A, I>(
     path: P,
     log: &Log,
@@ -71,19 +177,15 @@ where
     I: IntoIterator<Item = A>,
     A: Into<OsString>,
 {
-    let mut expr = duct::cmd(program, args)
-        .stdout_capture()
-        .stderr_file(log)
-        .unchecked()
-        .dir(path.as_ref());
+    let mut expr = duct::cmd(program, args).stdout_capture().dir(path.as_ref());
     for (key, value) in env.iter() {
         expr = expr.env(key.clone(), value.clone());
     }
-    let output = expr.run()?;
+    let output = run_streamed(log, expr, |expr, err| expr.stderr_file(err), || Ok(()))?;
     Ok(output.stdout)
 }
 
-/// Run a command piping stdout to a raw file descriptor; stderr goes to the log.
+/// Run a command piping stdout to a raw file descriptor; stderr is streamed to the log.
 ///
 /// Returns `true` if the process exits successfully.
 pub fn cmd_pipeout<P, F, S, A, I>(
@@ -101,19 +203,15 @@ where
     I: IntoIterator<Item = A>,
     A: Into<OsString>,
 {
-    let mut expr = duct::cmd(program, args)
-        .stdout_file(out)
-        .stderr_file(log)
-        .unchecked()
-        .dir(path.as_ref());
+    let mut expr = duct::cmd(program, args).stdout_file(out).dir(path.as_ref());
     for (key, value) in env.iter() {
         expr = expr.env(key.clone(), value.clone());
     }
-    let output = expr.run()?;
+    let output = run_streamed(log, expr, |expr, err| expr.stderr_file(err), || Ok(()))?;
     Ok(output.status.success())
 }
 
-/// Run a command with no stdin, merging stdout+stderr to the build log.
+/// Run a command with no stdin, streaming stdout+stderr to the build log.
 ///
 /// Returns `true` if the process exits successfully.
 pub fn cmd_noinput<P, S, A, I>(
@@ -131,14 +229,11 @@ where
 {
     let mut expr = duct::cmd(program, args)
         .stderr_to_stdout()
-        .stdout_file(log)
-        .unchecked()
         .dir(path.as_ref());
     for (key, value) in env.iter() {
         expr = expr.env(key.clone(), value.clone());
     }
-    let output = expr.run()?;
-
+    let output = run_streamed(log, expr, |expr, out| expr.stdout_file(out), || Ok(()))?;
     Ok(output.status.success())
 }
 
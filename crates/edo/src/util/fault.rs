@@ -0,0 +1,274 @@
+use parking_lot::Mutex;
+use snafu::{OptionExt, Snafu, ensure};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Environment variable holding the fault plan applied by [`FaultPlan::from_env`].
+pub const FAULTS_ENV: &str = "EDO_FAULTS";
+
+/// What happens when execution reaches a faulted point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultAction {
+    /// The operation fails with [`FaultError::Injected`].
+    Fail,
+    /// The operation is held back for the given duration, then proceeds.
+    Delay(Duration),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Rule {
+    action: FaultAction,
+    // `None` applies the rule to every call
+    remaining: Option<usize>,
+}
+
+/// A set of faults to inject into storage and environment operations.
+///
+/// Points are named `<subsystem>.<operation>`, e.g. `storage.read`,
+/// `storage.build.finish_layer`, `farm.create` or `env.run`. A plan is written
+/// as a comma separated list of `<point>=<action>[*<times>]` rules where the
+/// action is `fail` or `delay:<millis>ms`:
+///
+/// ```text
+/// EDO_FAULTS="storage.build.save=fail,env.run=delay:250ms*2"
+/// ```
+///
+/// Without `*<times>` a rule applies to every call. Clones share the same
+/// rules, so counted rules are consumed across every wrapped component.
+#[derive(Clone, Default)]
+pub struct FaultPlan {
+    rules: Arc<Mutex<BTreeMap<String, Rule>>>,
+}
+
+impl FaultPlan {
+    /// Parse a plan from its textual form.
+    pub fn parse(spec: &str) -> Result<Self, FaultError> {
+        let plan = Self::default();
+        for rule in spec.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            let (point, action) = rule.split_once('=').context(ParseSnafu {
+                rule,
+                reason: "expected <point>=<action>",
+            })?;
+            let (action, times) = match action.split_once('*') {
+                Some((action, times)) => {
+                    let times = times.parse::<usize>().ok().context(ParseSnafu {
+                        rule,
+                        reason: "repeat count must be a positive integer",
+                    })?;
+                    ensure!(
+                        times > 0,
+                        ParseSnafu {
+                            rule,
+                            reason: "repeat count must be a positive integer",
+                        }
+                    );
+                    (action, Some(times))
+                }
+                None => (action, None),
+            };
+            let action = if action == "fail" {
+                FaultAction::Fail
+            } else if let Some(delay) = action
+                .strip_prefix("delay:")
+                .and_then(|x| x.strip_suffix("ms"))
+            {
+                FaultAction::Delay(Duration::from_millis(delay.parse().ok().context(
+                    ParseSnafu {
+                        rule,
+                        reason: "delay must be written as delay:<millis>ms",
+                    },
+                )?))
+            } else {
+                return ParseSnafu {
+                    rule,
+                    reason: "action must be fail or delay:<millis>ms",
+                }
+                .fail();
+            };
+            plan.add(point.trim(), action, times);
+        }
+        Ok(plan)
+    }
+
+    /// Load the plan from [`FAULTS_ENV`], returning an empty plan if it is unset.
+    ///
+    /// Only builds with the `fault-injection` feature read the plan; others
+    /// warn that the variable is ignored and inject nothing.
+    pub fn from_env() -> Result<Self, FaultError> {
+        let Ok(spec) = std::env::var(FAULTS_ENV) else {
+            return Ok(Self::default());
+        };
+        if !cfg!(any(test, feature = "fault-injection")) {
+            warn!(
+                component = "fault",
+                "ignoring {FAULTS_ENV}, this build was made without fault injection"
+            );
+            return Ok(Self::default());
+        }
+        let plan = Self::parse(&spec)?;
+        if !plan.is_empty() {
+            warn!(
+                component = "fault",
+                "injecting faults from {FAULTS_ENV}: {spec}"
+            );
+        }
+        Ok(plan)
+    }
+
+    /// Returns `true` if no faults remain to be injected.
+    pub fn is_empty(&self) -> bool {
+        self.rules.lock().is_empty()
+    }
+
+    /// Inject `action` at `point`, for the next `times` calls or for every call.
+    pub fn add(&self, point: &str, action: FaultAction, times: Option<usize>) {
+        self.rules.lock().insert(
+            point.to_string(),
+            Rule {
+                action,
+                remaining: times,
+            },
+        );
+    }
+
+    /// Apply any fault registered for the first matching point.
+    ///
+    /// Points are checked in order so callers can pass the most specific
+    /// name first (e.g. `storage.build.read` before `storage.read`).
+    pub async fn inject(&self, points: &[&str]) -> Result<(), FaultError> {
+        let hit = {
+            let mut rules = self.rules.lock();
+            points.iter().find_map(|point| {
+                let rule = rules.get_mut(*point)?;
+                let action = rule.action;
+                if let Some(remaining) = rule.remaining.as_mut() {
+                    *remaining -= 1;
+                    if *remaining == 0 {
+                        rules.remove(*point);
+                    }
+                }
+                Some((point.to_string(), action))
+            })
+        };
+        match hit {
+            Some((point, FaultAction::Fail)) => {
+                warn!(component = "fault", "injecting failure at {point}");
+                InjectedSnafu { point }.fail()
+            }
+            Some((point, FaultAction::Delay(delay))) => {
+                warn!(
+                    component = "fault",
+                    "injecting {}ms delay at {point}",
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Errors raised by fault injection.
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum FaultError {
+    /// A fault plan requested that this operation fail.
+    #[snafu(display("injected failure at {point}"))]
+    Injected { point: String },
+    /// A rule in the fault plan could not be parsed.
+    #[snafu(display("invalid fault rule '{rule}': {reason}"))]
+    Parse { rule: String, reason: String },
+}
+
+impl From<FaultError> for crate::storage::StorageError {
+    fn from(value: FaultError) -> Self {
+        Self::Implementation {
+            source: Box::new(value),
+        }
+    }
+}
+
+impl From<FaultError> for crate::environment::EnvironmentError {
+    fn from(value: FaultError) -> Self {
+        Self::Implementation {
+            source: Box::new(value),
+        }
+    }
+}
+
+impl From<FaultError> for crate::context::ContextError {
+    fn from(value: FaultError) -> Self {
+        Self::Component {
+            source: Box::new(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_accepts_fail_delay_and_counts() {
+        let plan = FaultPlan::parse("storage.read=fail, env.run=delay:5ms*2").unwrap();
+        let rules = plan.rules.lock();
+        assert_eq!(rules["storage.read"].action, FaultAction::Fail);
+        assert_eq!(rules["storage.read"].remaining, None);
+        assert_eq!(
+            rules["env.run"].action,
+            FaultAction::Delay(Duration::from_millis(5))
+        );
+        assert_eq!(rules["env.run"].remaining, Some(2));
+    }
+
+    #[test]
+    fn parse_rejects_malformed_rules() {
+        for spec in [
+            "storage.read",
+            "env.up=explode",
+            "env.up=fail*0",
+            "env.up=delay:5s",
+        ] {
+            let err = FaultPlan::parse(spec).err().expect(spec);
+            assert!(err.to_string().starts_with("invalid fault rule"), "{err}");
+        }
+    }
+
+    #[test]
+    fn empty_spec_is_empty_plan() {
+        assert!(FaultPlan::parse("").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn counted_rules_are_consumed() {
+        let plan = FaultPlan::default();
+        plan.add("env.up", FaultAction::Fail, Some(1));
+        let err = plan.inject(&["env.up"]).await.unwrap_err();
+        assert_eq!(err.to_string(), "injected failure at env.up");
+        assert!(plan.inject(&["env.up"]).await.is_ok());
+        assert!(plan.is_empty());
+    }
+
+    #[tokio::test]
+    async fn most_specific_point_wins() {
+        let plan = FaultPlan::default();
+        plan.add("storage.read", FaultAction::Fail, None);
+        plan.add(
+            "storage.build.read",
+            FaultAction::Delay(Duration::from_millis(1)),
+            None,
+        );
+        assert!(
+            plan.inject(&["storage.build.read", "storage.read"])
+                .await
+                .is_ok()
+        );
+        assert!(
+            plan.inject(&["storage.local.read", "storage.read"])
+                .await
+                .is_err()
+        );
+    }
+}
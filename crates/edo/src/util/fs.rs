@@ -1,5 +1,8 @@
 use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 use tokio::fs;
+use tokio::io::AsyncWrite;
+use tokio_tar::{Builder, Header, HeaderMode};
 
 /// Recursively copy the directory tree rooted at `from` into `to`.
 ///
@@ -40,3 +43,184 @@ pub async fn copy_r<U: AsRef<Path>, V: AsRef<Path>>(from: U, to: V) -> Result<()
 
     Ok(())
 }
+
+/// Copy the directory tree rooted at `from` into `to` as it is.
+///
+/// Unlike [`copy_r`], symbolic links are recreated rather than followed,
+/// so links that point outside of the tree or nowhere are kept intact.
+pub async fn copy_tree<U: AsRef<Path>, V: AsRef<Path>>(
+    from: U,
+    to: V,
+) -> Result<(), std::io::Error> {
+    let mut stack = vec![(from.as_ref().to_path_buf(), to.as_ref().to_path_buf())];
+    while let Some((src, dest)) = stack.pop() {
+        fs::create_dir_all(&dest).await?;
+        let mut walker = fs::read_dir(&src).await?;
+        while let Some(entry) = walker.next_entry().await? {
+            let path = entry.path();
+            let target = dest.join(entry.file_name());
+            let kind = entry.file_type().await?;
+            if kind.is_symlink() {
+                let link = fs::read_link(&path).await?;
+                #[cfg(unix)]
+                fs::symlink(link, &target).await?;
+                #[cfg(not(unix))]
+                fs::copy(path.parent().unwrap_or(&path).join(link), &target).await?;
+            } else if kind.is_dir() {
+                stack.push((path, target));
+            } else {
+                fs::copy(&path, &target).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Remove everything inside the directory at `path`, keeping the directory
+/// itself so that mounts of it stay valid.
+pub async fn clear_dir<P: AsRef<Path>>(path: P) -> Result<(), std::io::Error> {
+    let mut entries = fs::read_dir(path).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_dir() {
+            fs::remove_dir_all(entry.path()).await?;
+        } else {
+            fs::remove_file(entry.path()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Archive the directory tree rooted at `path` into `archive` under `.`,
+/// with no entry modified later than `clamp` seconds after the epoch.
+///
+/// Entries are added in name order and directories created while staging a
+/// build carry the time they were created, so their modification times are
+/// clamped for the same tree to pack into the same bytes whenever it is
+/// packed. Without a `clamp`, entries are clamped to the newest file in the
+/// tree. Symbolic links are followed as `append_dir_all` does.
+pub async fn archive_dir<W: AsyncWrite + Unpin + Send>(
+    archive: &mut Builder<W>,
+    path: &Path,
+    clamp: Option<u64>,
+) -> Result<(), std::io::Error> {
+    let mut entries = Vec::new();
+    let mut stack = vec![(path.to_path_buf(), PathBuf::from("."))];
+    while let Some((src, dest)) = stack.pop() {
+        let meta = fs::metadata(&src).await?;
+        if meta.is_dir() {
+            let mut walker = fs::read_dir(&src).await?;
+            while let Some(entry) = walker.next_entry().await? {
+                stack.push((entry.path(), dest.join(entry.file_name())));
+            }
+        }
+        entries.push((src, dest, meta));
+    }
+    entries.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let mtime = |meta: &std::fs::Metadata| {
+        meta.modified()
+            .ok()
+            .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+            .map(|x| x.as_secs())
+            .unwrap_or_default()
+    };
+    let clamp = clamp.unwrap_or_else(|| {
+        entries
+            .iter()
+            .filter(|x| !x.2.is_dir())
+            .map(|x| mtime(&x.2))
+            .max()
+            .unwrap_or_default()
+    });
+    for (src, dest, meta) in entries {
+        let mut header = Header::new_gnu();
+        header.set_metadata_in_mode(&meta, HeaderMode::Complete);
+        header.set_mtime(mtime(&meta).min(clamp));
+        if meta.is_dir() {
+            archive
+                .append_data(&mut header, &dest, tokio::io::empty())
+                .await?;
+        } else if meta.is_file() {
+            let file = fs::File::open(&src).await?;
+            archive.append_data(&mut header, &dest, file).await?;
+        } else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("cannot archive special file {}", src.display()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn copy_tree_keeps_symlinks() {
+        let dir = TempDir::new().unwrap();
+        let from = dir.path().join("from");
+        std::fs::create_dir_all(from.join("a/b")).unwrap();
+        std::fs::write(from.join("a/b/file"), "data").unwrap();
+        std::os::unix::fs::symlink("a/b/file", from.join("link")).unwrap();
+        std::os::unix::fs::symlink("missing", from.join("dangling")).unwrap();
+        let to = dir.path().join("to");
+        copy_tree(&from, &to).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(to.join("a/b/file")).unwrap(),
+            "data"
+        );
+        assert_eq!(
+            std::fs::read_link(to.join("link")).unwrap(),
+            Path::new("a/b/file")
+        );
+        assert_eq!(
+            std::fs::read_link(to.join("dangling")).unwrap(),
+            Path::new("missing")
+        );
+    }
+
+    #[tokio::test]
+    async fn archived_directories_are_clamped_to_their_newest_file() {
+        let dir = TempDir::new().unwrap();
+        let pack = |name: &'static str| {
+            let root = dir.path().join(name);
+            async move {
+                std::fs::create_dir_all(root.join("sub")).unwrap();
+                let file = std::fs::File::create(root.join("sub/file")).unwrap();
+                file.set_modified(UNIX_EPOCH + std::time::Duration::from_secs(1_000))
+                    .unwrap();
+                let mut archive = Builder::new(Vec::new());
+                archive_dir(&mut archive, &root, None).await.unwrap();
+                archive.into_inner().await.unwrap()
+            }
+        };
+        // The directories of the second tree are created over a second
+        // later than those of the first
+        let first = pack("first").await;
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        let second = pack("second").await;
+        assert_eq!(first, second);
+
+        let mut entries = tokio_tar::Archive::new(first.as_slice());
+        let mut entries = entries.entries().unwrap();
+        let mut seen = Vec::new();
+        while let Some(entry) = futures::StreamExt::next(&mut entries).await {
+            let entry = entry.unwrap();
+            seen.push((
+                entry.path().unwrap().display().to_string(),
+                entry.header().mtime().unwrap(),
+            ));
+        }
+        assert_eq!(
+            seen,
+            [
+                (".".to_string(), 1_000),
+                ("sub".to_string(), 1_000),
+                ("sub/file".to_string(), 1_000),
+            ]
+        );
+    }
+}
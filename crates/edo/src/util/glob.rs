@@ -0,0 +1,113 @@
+use std::path::{Path, PathBuf};
+
+/// Returns `true` if the `/` separated relative `path` matches `pattern`.
+///
+/// Patterns are matched segment by segment: `**` matches any number of
+/// segments (including none), `*` matches any run of characters within a
+/// segment and `?` matches exactly one character.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').filter(|x| !x.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').filter(|x| !x.is_empty()).collect();
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_segments(rest, &path[skip..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, remaining)) => {
+                match_segment(segment.as_bytes(), name.as_bytes())
+                    && match_segments(rest, remaining)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_segment(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| match_segment(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && match_segment(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && match_segment(rest, &name[1..]),
+    }
+}
+
+/// Lists the files below `root` matching any `include` pattern and no
+/// `exclude` pattern, as paths relative to `root` in sorted order.
+///
+/// An empty `include` matches every file. Symbolic links are listed when
+/// they point at a file and never descended into, so the expansion only
+/// depends on the contents of `root`.
+pub fn glob_files(
+    root: &Path,
+    include: &[String],
+    exclude: &[String],
+) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        for entry in std::fs::read_dir(root.join(&relative))? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if file_type.is_symlink() && !entry.path().is_file() {
+                continue;
+            }
+            let name = path.to_string_lossy().replace('\\', "/");
+            let included = include.is_empty() || include.iter().any(|x| glob_match(x, &name));
+            if included && !exclude.iter().any(|x| glob_match(x, &name)) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_match_within_a_segment() {
+        assert!(glob_match("*.c", "main.c"));
+        assert!(!glob_match("*.c", "src/main.c"));
+        assert!(glob_match("src/?ain.c", "src/main.c"));
+        assert!(!glob_match("src/*.c", "src/main.h"));
+    }
+
+    #[test]
+    fn double_star_matches_any_depth() {
+        assert!(glob_match("src/**/*.c", "src/main.c"));
+        assert!(glob_match("src/**/*.c", "src/a/b/main.c"));
+        assert!(glob_match("**", "anything/at/all"));
+        assert!(!glob_match("src/**/*.c", "lib/main.c"));
+    }
+
+    #[test]
+    fn expansion_is_sorted_and_honours_excludes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for file in ["src/b.c", "src/a.c", "src/gen/x.c", "src/a.h", "README"] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, file).unwrap();
+        }
+        let files = glob_files(
+            dir.path(),
+            &["src/**/*.c".to_string()],
+            &["src/gen/**".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            files,
+            vec![PathBuf::from("src/a.c"), PathBuf::from("src/b.c")]
+        );
+        assert_eq!(glob_files(dir.path(), &[], &[]).unwrap().len(), 5);
+    }
+}
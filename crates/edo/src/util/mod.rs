@@ -2,17 +2,32 @@
 //!
 //! Provides [`Reader`] and [`Writer`] wrappers with integrated BLAKE3 hashing,
 //! synchronous adapters for async I/O ([`SyncReader`], [`sync`], [`sync_fn`]),
-//! filesystem helpers ([`copy_r`]), and subprocess execution functions that
-//! pipe output through the build log.
+//! filesystem helpers ([`copy_r`], [`copy_tree`], [`clear_dir`],
+//! [`glob_files`]), subprocess execution functions that stream output into
+//! the build log, [`parse_size`], [`format_size`] and
+//! [`free_space`] for byte counts and disk space, [`did_you_mean`] for
+//! suggesting names close to a mistyped one, [`schema_version`] for
+//! versioned JSON documents, and the [`FaultPlan`] used to inject failures
+//! and delays in tests.
 
 mod command;
+mod fault;
 mod fs;
+mod glob;
 mod reader;
+mod schema;
+mod size;
+mod suggest;
 mod sync;
 mod writer;
 
 pub use command::*;
+pub use fault::*;
 pub use fs::*;
+pub use glob::*;
 pub use reader::*;
+pub use schema::*;
+pub use size::*;
+pub use suggest::*;
 pub use sync::*;
 pub use writer::*;
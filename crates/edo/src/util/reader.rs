@@ -1,10 +1,11 @@
+use crate::storage::{DigestAlgorithm, Hasher, LayerDigest};
 use parking_lot::Mutex;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::Poll;
 use tokio::io::{AsyncRead, AsyncReadExt};
 
-/// An async reader wrapper that computes a BLAKE3 hash of all bytes read.
+/// An async reader wrapper that computes a hash of all bytes read, BLAKE3 unless told otherwise.
 ///
 /// Implements both [`AsyncRead`] and [`std::io::Read`] (blocking via the
 /// current tokio runtime). Use [`Reader::finish`] after all data has been
@@ -20,18 +21,31 @@ impl Reader {
         Self {
             inner: Rc::new(Mutex::new(Inner {
                 reader: Box::pin(reader),
-                hash: blake3::Hasher::new(),
+                hash: Hasher::default(),
                 pos: 0,
+                expected: None,
             })),
         }
     }
 
-    /// Finalize the hash and return the hex-encoded BLAKE3 digest of all bytes read so far.
-    pub fn finish(&self) -> String {
-        let lock = self.inner.lock();
-        let hash = lock.hash.finalize();
+    /// Hash with `algorithm` instead, call before anything is read.
+    pub fn with_algorithm(self, algorithm: DigestAlgorithm) -> Self {
+        self.inner.lock().hash = algorithm.hasher();
+        self
+    }
 
-        base16::encode_lower(hash.as_bytes())
+    /// Fail the read that reaches the end of the stream with
+    /// [`std::io::ErrorKind::InvalidData`] unless exactly `size` bytes hashing
+    /// to `digest` were read.
+    pub fn verify(self, digest: &LayerDigest, size: usize) -> Self {
+        let this = self.with_algorithm(digest.algorithm());
+        this.inner.lock().expected = Some((digest.clone(), size));
+        this
+    }
+
+    /// Finalize the hash and return the hex-encoded digest of all bytes read so far.
+    pub fn finish(&self) -> String {
+        self.inner.lock().hash.finalize()
     }
 }
 
@@ -40,8 +54,30 @@ unsafe impl Sync for Reader {}
 
 struct Inner {
     reader: Pin<Box<dyn AsyncRead>>,
-    hash: blake3::Hasher,
+    hash: Hasher,
     pos: usize,
+    // Digest and size checked once the end of the stream is reached
+    expected: Option<(LayerDigest, usize)>,
+}
+
+impl Inner {
+    fn check(&mut self) -> std::io::Result<()> {
+        let Some((digest, size)) = self.expected.take() else {
+            return Ok(());
+        };
+        let actual = self.hash.finalize();
+        if actual != digest.digest() || self.pos != size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "layer {digest} failed verification, read {} bytes hashing to {}:{actual} instead of {size} bytes",
+                    self.pos,
+                    digest.algorithm()
+                ),
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl std::io::Read for Reader {
@@ -60,14 +96,16 @@ impl AsyncRead for Reader {
     ) -> Poll<std::io::Result<()>> {
         let this = self.get_mut();
         let mut lock = this.inner.lock();
+        let before = buf.filled().len();
         match lock.reader.as_mut().poll_read(cx, buf) {
             Poll::Ready(Ok(())) => {
-                if !buf.filled().is_empty() {
-                    let segment = buf.filled();
+                let segment = &buf.filled()[before..];
+                if !segment.is_empty() {
                     lock.pos += segment.len();
-                    if !segment.is_empty() {
-                        lock.hash.update(segment);
-                    }
+                    lock.hash.update(segment);
+                } else if buf.remaining() > 0 {
+                    // Nothing was read into a buffer with room left, so this is the end
+                    lock.check()?;
                 }
                 Poll::Ready(Ok(()))
             }
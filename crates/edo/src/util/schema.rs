@@ -0,0 +1,22 @@
+use serde_json::Value;
+
+/// Field versioned JSON documents, such as `edo.lock.json` or a cache
+/// catalog, record their schema version in.
+pub const SCHEMA_VERSION_KEY: &str = "version";
+
+/// The schema version `document` records. Documents written before
+/// versions were recorded are version 1.
+///
+/// A version is only bumped when older releases would misread a document.
+/// Fields added without a bump are kept by older releases that rewrite the
+/// document, see [`SchemaExtra`].
+pub fn schema_version(document: &Value) -> u64 {
+    document
+        .get(SCHEMA_VERSION_KEY)
+        .and_then(|x| x.as_u64())
+        .unwrap_or(1)
+}
+
+/// Type of the field a versioned document keeps the fields this edo does
+/// not know in, `#[serde(flatten)]`ed so they are written back unchanged.
+pub type SchemaExtra = std::collections::BTreeMap<String, Value>;
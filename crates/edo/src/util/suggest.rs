@@ -0,0 +1,73 @@
+/// The edit distance between `a` and `b` in characters, where swapping two
+/// neighbouring characters counts as a single edit like the others.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// The candidates `word` is most likely a typo of, closest first.
+///
+/// A candidate matches when it is within a third of its length in edits of
+/// `word`, or when one starts with the other, so `gti` suggests `git` and
+/// `cargo` suggests `cargo-vendor`.
+pub fn did_you_mean<'a, I>(word: &str, candidates: I) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let word = word.to_lowercase();
+    let mut matches: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let lower = candidate.to_lowercase();
+            let distance = edit_distance(&word, &lower);
+            let close = distance <= (lower.chars().count() / 3).max(1);
+            let prefix = !word.is_empty() && (lower.starts_with(&word) || word.starts_with(&lower));
+            (close || prefix).then_some((distance, candidate))
+        })
+        .collect();
+    matches.sort();
+    matches.dedup();
+    matches.into_iter().map(|(_, x)| x).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("git", "git"), 0);
+        assert_eq!(edit_distance("gti", "git"), 1);
+        assert_eq!(edit_distance("scrpt", "script"), 1);
+        assert_eq!(edit_distance("", "local"), 5);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn did_you_mean_orders_close_candidates() {
+        let kinds = ["compose", "import", "script", "cargo-vendor", "go-vendor"];
+        assert_eq!(did_you_mean("scrpt", kinds), vec!["script"]);
+        assert_eq!(did_you_mean("Import", kinds), vec!["import"]);
+        assert_eq!(did_you_mean("cargo", kinds), vec!["cargo-vendor"]);
+        assert!(did_you_mean("docker", kinds).is_empty());
+    }
+}
@@ -1,10 +1,11 @@
+use crate::storage::{DigestAlgorithm, Hasher};
 use parking_lot::Mutex;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::Poll;
 use tokio::io::AsyncWrite;
 
-/// An async writer wrapper that computes a BLAKE3 hash of all bytes written.
+/// An async writer wrapper that computes a hash of all bytes written, BLAKE3 unless told otherwise.
 ///
 /// Implements [`AsyncWrite`]. After writing is complete call [`Writer::finish`]
 /// to obtain the hex-encoded content digest.
@@ -19,7 +20,7 @@ impl Writer {
         Self {
             inner: Rc::new(Mutex::new(Inner {
                 writer: Box::pin(writer),
-                hash: blake3::Hasher::new(),
+                hash: Hasher::default(),
                 digest: None,
                 size: 0,
                 target,
@@ -27,6 +28,16 @@ impl Writer {
         }
     }
 
+    /// Hash with `algorithm` instead, call before anything is written.
+    pub fn set_algorithm(&self, algorithm: DigestAlgorithm) {
+        self.inner.lock().hash = algorithm.hasher();
+    }
+
+    /// Return the algorithm the content digest is computed with.
+    pub fn algorithm(&self) -> DigestAlgorithm {
+        self.inner.lock().hash.algorithm()
+    }
+
     /// Return the total number of bytes written so far.
     pub fn size(&self) -> usize {
         self.inner.lock().size
@@ -42,14 +53,13 @@ impl Writer {
         self.inner.lock().target.clone()
     }
 
-    /// Finalize the hash and return the hex-encoded BLAKE3 digest.
+    /// Finalize the hash and return the hex-encoded digest.
     ///
     /// If a digest was set manually via [`Writer::set_digest`], that value is
     /// returned instead.
     pub async fn finish(&self) -> String {
         let lock = self.inner.lock();
-        let hash = lock.hash.finalize();
-        let digest = base16::encode_lower(hash.as_bytes());
+        let digest = lock.hash.finalize();
 
         lock.digest.clone().unwrap_or(digest)
     }
@@ -60,7 +70,7 @@ unsafe impl Sync for Writer {}
 
 struct Inner {
     writer: Pin<Box<dyn AsyncWrite + Send + Sync>>,
-    hash: blake3::Hasher,
+    hash: Hasher,
     digest: Option<String>,
     size: usize,
     target: String,
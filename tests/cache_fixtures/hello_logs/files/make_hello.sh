@@ -0,0 +1,4 @@
+#!/bin/sh
+set -eu
+out="$1"
+printf 'script-produced hello\n' > "$out"
@@ -19,6 +19,13 @@ pub fn net_fixtures_root() -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("net_fixtures")
 }
 
+/// Absolute path to the fixture tree of projects declaring caches
+/// (`tests/cache_fixtures/`), kept out of the umbrella so its runs stay
+/// uncached.
+pub fn cache_fixtures_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("cache_fixtures")
+}
+
 /// A copy of a fixture placed in a temporary directory so tests cannot pollute each other.
 ///
 /// `path` is the directory edo is invoked from — it is the parent of a copied
@@ -38,6 +45,53 @@ impl Fixture {
         self.path.join("edo.lock.json")
     }
 
+    /// Returns the path to the manifest of the copied fixture `name`.
+    pub fn manifest(&self, name: &str) -> PathBuf {
+        self.path.join(name).join("edo.toml")
+    }
+
+    /// Rewrites the manifest of the copied fixture `name` with `edit`.
+    pub fn edit_manifest(self, name: &str, edit: impl FnOnce(String) -> String) -> Self {
+        let manifest = self.manifest(name);
+        let content = std::fs::read_to_string(&manifest)
+            .unwrap_or_else(|e| panic!("read {}: {e}", manifest.display()));
+        std::fs::write(&manifest, edit(content))
+            .unwrap_or_else(|e| panic!("write {}: {e}", manifest.display()));
+        self
+    }
+
+    /// Appends `extra` to the manifest of the copied fixture `name`.
+    pub fn append_manifest(self, name: &str, extra: &str) -> Self {
+        self.edit_manifest(name, |content| format!("{content}\n{extra}\n"))
+    }
+
+    /// Declares a local `cache` (such as `build` or `output`) in the manifest
+    /// of the copied fixture `name`, stored in `<cache>-cache` beside it and
+    /// followed by `extra`.
+    pub fn with_local_cache(self, name: &str, cache: &str, extra: &str) -> Self {
+        let path = self.path.join(format!("{cache}-cache"));
+        self.append_manifest(
+            name,
+            &format!(
+                "[cache.{cache}]\nkind = \"local\"\npath = \"{}\"\n{extra}",
+                path.display()
+            ),
+        )
+    }
+
+    /// Declares a local build cache, see [`Fixture::with_local_cache`].
+    pub fn with_local_build_cache(self, name: &str, extra: &str) -> Self {
+        self.with_local_cache(name, "build", extra)
+    }
+
+    /// Writes `content` to the file `name` beside the copied fixture, such
+    /// as a `--config` file, and returns its path.
+    pub fn write_file(&self, name: &str, content: &str) -> String {
+        let path = self.dir.path().join(name);
+        std::fs::write(&path, content).unwrap_or_else(|e| panic!("write {}: {e}", path.display()));
+        path.display().to_string()
+    }
+
     /// Builds a bare `edo` invocation rooted at this fixture.
     ///
     /// Does not inject `--storage` — use [`Fixture::edo`] when you want that
@@ -63,6 +117,34 @@ impl Fixture {
         }
         c.assert()
     }
+
+    /// Returns the artifact id `edo inspect` reports for `addr`.
+    pub fn inspect_id(&self, addr: &str) -> String {
+        let out = self.edo(&["inspect", addr]).success();
+        let stdout = String::from_utf8(out.get_output().stdout.clone()).unwrap();
+        stdout
+            .lines()
+            .last()
+            .expect("inspect prints the id")
+            .to_string()
+    }
+
+    /// Reads the summary of every run recorded in the fixture's storage,
+    /// oldest first.
+    pub fn summaries(&self) -> Vec<serde_json::Value> {
+        let dir = self.storage.join("runs");
+        let mut runs = std::fs::read_dir(&dir)
+            .unwrap_or_else(|e| panic!("read {}: {e}", dir.display()))
+            .flatten()
+            .filter(|x| x.path().is_file())
+            .map(|x| {
+                let content = std::fs::read_to_string(x.path()).expect("read summary");
+                serde_json::from_str::<serde_json::Value>(&content).expect("parse summary")
+            })
+            .collect::<Vec<_>>();
+        runs.sort_by_key(|x| x["id"].as_str().unwrap_or_default().to_string());
+        runs
+    }
 }
 
 /// Copies `fixtures_root()/<name>` into a fresh tempdir (preserving the name
@@ -83,6 +165,14 @@ pub fn copy_from(root: &Path, name: &str) -> Fixture {
     Fixture { dir, path, storage }
 }
 
+/// An empty project directory, for commands that create one.
+pub fn empty_fixture() -> Fixture {
+    let dir = TempDir::new().expect("create tempdir");
+    let path = dir.path().to_path_buf();
+    let storage = path.join(".edo-test-store");
+    Fixture { dir, path, storage }
+}
+
 /// Copies the entire `fixtures/` tree into a tempdir so umbrella / cross-project
 /// tests can walk it.
 pub fn copy_umbrella() -> Fixture {
@@ -94,6 +184,34 @@ pub fn copy_umbrella() -> Fixture {
     Fixture { dir, path, storage }
 }
 
+/// Replaces the only occurrence of `from` in `content` with `to`, panicking
+/// when it is missing or repeated so edits of a drifted fixture fail loudly.
+pub fn replace_once(content: &str, from: &str, to: &str) -> String {
+    let count = content.matches(from).count();
+    assert_eq!(count, 1, "expected {from:?} exactly once in:\n{content}");
+    content.replacen(from, to, 1)
+}
+
+/// Finds a file named `name` anywhere below `root`, such as in a checked out
+/// artifact.
+pub fn find_file(root: &Path, name: &str) -> Option<PathBuf> {
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(read) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in read.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else if path.file_name().and_then(|x| x.to_str()) == Some(name) {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
 fn copy_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
     std::fs::create_dir_all(dst)?;
     for entry in std::fs::read_dir(src)? {
@@ -2,7 +2,7 @@ pub mod fixtures;
 pub mod network;
 
 pub use fixtures::{
-    Fixture, copy_fixture, copy_from, copy_umbrella, error_fixtures_root, fixtures_root,
-    net_fixtures_root,
+    Fixture, cache_fixtures_root, copy_fixture, copy_from, copy_umbrella, empty_fixture,
+    error_fixtures_root, find_file, fixtures_root, net_fixtures_root, replace_once,
 };
 pub use network::container_enabled;
@@ -0,0 +1,78 @@
+use edo_integration_tests::common::*;
+use predicates::prelude::PredicateBooleanExt;
+use predicates::str::contains;
+use std::process::Command;
+
+fn git(fx: &Fixture, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=edo", "-c", "user.email=edo@example.com"])
+        .args(args)
+        .current_dir(&fx.path)
+        .status()
+        .expect("git must be installed");
+    assert!(status.success(), "git {args:?} failed");
+}
+
+/// Commits the umbrella as is, then applies `change` in a second commit.
+fn committed_umbrella(change: impl Fn(&Fixture)) -> Fixture {
+    let fx = copy_umbrella();
+    git(&fx, &["init", "-q"]);
+    git(&fx, &["add", "."]);
+    git(&fx, &["commit", "-q", "-m", "base"]);
+    change(&fx);
+    git(&fx, &["add", "."]);
+    git(&fx, &["commit", "-q", "-m", "change"]);
+    fx
+}
+
+#[test]
+fn changed_source_builds_its_reverse_dependencies() {
+    let fx = committed_umbrella(|fx| {
+        std::fs::write(
+            fx.path.join("hello_local/files/greeting.txt"),
+            "hello again\n",
+        )
+        .unwrap();
+    });
+    fx.edo(&["run", "--affected-by", "HEAD~1..HEAD"])
+        .success()
+        .stdout(contains("affected: //hello_local/emit"))
+        .stdout(contains("affected: //cross_project_consumer/final"))
+        .stdout(contains("//hello_compose/bundle").not());
+}
+
+#[test]
+fn changed_definition_affects_its_package() {
+    let fx = committed_umbrella(|fx| {
+        let path = fx.path.join("hello_compose/edo.toml");
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(path, format!("{content}\n# touched\n")).unwrap();
+    });
+    fx.edo(&["run", "--affected-by", "HEAD~1..HEAD"])
+        .success()
+        .stdout(contains("affected: //hello_compose/bundle"))
+        .stdout(contains("affected: //hello_compose/left"))
+        .stdout(contains("//hello_local/emit").not());
+}
+
+#[test]
+fn unrelated_change_builds_nothing() {
+    let fx = committed_umbrella(|fx| {
+        std::fs::write(fx.path.join("NOTES.md"), "nothing to build\n").unwrap();
+    });
+    fx.edo(&["run", "--affected-by", "HEAD~1..HEAD"])
+        .success()
+        .stdout(contains("no transforms are affected by HEAD~1..HEAD"));
+}
+
+#[test]
+fn invalid_range_is_reported() {
+    let fx = committed_umbrella(|fx| {
+        std::fs::write(fx.path.join("NOTES.md"), "nothing to build\n").unwrap();
+    });
+    fx.edo(&["run", "--affected-by", "no-such-rev..HEAD"])
+        .failure()
+        .stderr(contains(
+            "failed to list the files changed in no-such-rev..HEAD",
+        ));
+}
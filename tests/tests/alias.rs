@@ -0,0 +1,70 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+/// Appends `extra` to the `edo.toml` of `name` inside the fixture.
+fn append(fx: &Fixture, name: &str, extra: &str) {
+    let path = fx.path.join(name).join("edo.toml");
+    let mut content = std::fs::read_to_string(&path).unwrap();
+    content.push_str(extra);
+    std::fs::write(path, content).unwrap();
+}
+
+#[test]
+fn alias_target_runs_with_a_deprecation_warning() {
+    let fx = copy_fixture("hello_local");
+    append(
+        &fx,
+        "hello_local",
+        "\n[alias.old_emit]\ntarget  = \"emit\"\nmessage = \"renamed\"\n",
+    );
+    fx.edo(&["run", "//hello_local/old_emit"])
+        .success()
+        .stdout(contains(
+            "//hello_local/old_emit is deprecated, use //hello_local/emit instead: renamed",
+        ));
+}
+
+#[test]
+fn list_shows_aliases() {
+    let fx = copy_fixture("hello_local");
+    append(&fx, "hello_local", "\n[alias]\nold_emit = \"emit\"\n");
+    fx.edo(&["list"]).success().stdout(contains(
+        "//hello_local/old_emit -> //hello_local/emit (deprecated)",
+    ));
+}
+
+#[test]
+fn dependencies_resolve_through_aliases() {
+    let fx = copy_fixture("hello_compose");
+    let path = fx.path.join("hello_compose/edo.toml");
+    let content = std::fs::read_to_string(&path)
+        .unwrap()
+        .replace("\"//hello_compose/left\"", "\"//hello_compose/lhs\"");
+    std::fs::write(&path, content).unwrap();
+    append(&fx, "hello_compose", "\n[alias]\nlhs = \"left\"\n");
+    fx.edo(&["run", "//hello_compose/bundle"])
+        .success()
+        .stdout(contains("//hello_compose/lhs is deprecated"));
+}
+
+#[test]
+fn alias_cycle_is_rejected() {
+    let fx = copy_fixture("hello_local");
+    append(&fx, "hello_local", "\n[alias]\na = \"b\"\nb = \"a\"\n");
+    fx.edo(&["list"])
+        .failure()
+        .stderr(contains("forms a cycle"));
+}
+
+#[test]
+fn alias_shadowing_a_transform_is_rejected() {
+    let fx = copy_fixture("hello_local");
+    append(
+        &fx,
+        "hello_local",
+        "\n[alias]\nemit = \"//elsewhere/emit\"\n",
+    );
+    fx.edo(&["list"])
+        .failure()
+        .stderr(contains("shadows an existing definition"));
+}
@@ -0,0 +1,119 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+/// Copies `hello_local` and points its build cache at a local directory with
+/// the given transfer settings.
+fn with_build_cache(settings: &str) -> Fixture {
+    copy_fixture("hello_local").with_local_build_cache("hello_local", settings)
+}
+
+/// Forget everything the first run left locally so the next run has to pull
+/// from the build cache.
+fn clear_local(fx: &Fixture) {
+    std::fs::remove_dir_all(&fx.storage).unwrap();
+    std::fs::remove_file(fx.lock_path()).unwrap();
+}
+
+fn run_with_faults(fx: &Fixture, faults: &str) -> assert_cmd::assert::Assert {
+    fx.cmd()
+        .env("EDO_FAULTS", faults)
+        .arg("--storage")
+        .arg(&fx.storage)
+        .args(["run", "//hello_local/emit"])
+        .assert()
+}
+
+#[test]
+fn throttled_build_cache_receives_uploads() {
+    let fx = with_build_cache("bandwidth = \"64MiB\"\nconcurrency = 1");
+    fx.edo(&["run", "//hello_local/emit"]).success();
+    let catalog = std::fs::read_to_string(fx.path.join("build-cache/catalog.json"))
+        .expect("the build cache must hold a catalog after the run");
+    assert!(catalog.contains("emit"), "{catalog}");
+}
+
+#[test]
+fn dropped_download_is_retried() {
+    let fx = with_build_cache("retries = 2\nretry_delay_ms = 10");
+    fx.edo(&["run", "//hello_local/emit"]).success();
+    clear_local(&fx);
+    run_with_faults(&fx, "storage.build.read=fail*1").success();
+}
+
+#[test]
+fn dropped_download_fails_without_retries() {
+    let fx = with_build_cache("");
+    fx.edo(&["run", "//hello_local/emit"]).success();
+    clear_local(&fx);
+    run_with_faults(&fx, "storage.build.read=fail*1")
+        .failure()
+        .stderr(contains("injected failure at storage.build.read"));
+}
+
+#[test]
+fn invalid_transfer_settings_are_rejected() {
+    let fx = with_build_cache("concurrency = 0");
+    fx.edo(&["run", "//hello_local/emit"])
+        .failure()
+        .stderr(contains("invalid cache transfer setting 'concurrency'"));
+}
+
+#[test]
+fn corrupt_download_is_rejected_when_verifying() {
+    let fx = with_build_cache("verify = true");
+    fx.edo(&["run", "//hello_local/emit"]).success();
+    for blob in std::fs::read_dir(fx.path.join("build-cache/blobs/blake3")).unwrap() {
+        std::fs::write(blob.unwrap().path(), b"bit rot").unwrap();
+    }
+    clear_local(&fx);
+    fx.edo(&["run", "//hello_local/emit"])
+        .failure()
+        .stderr(contains("failed verification"));
+}
+
+#[test]
+fn invalid_verify_setting_is_rejected() {
+    let fx = with_build_cache("verify = \"yes\"");
+    fx.edo(&["run", "//hello_local/emit"])
+        .failure()
+        .stderr(contains("invalid cache setting 'verify'"));
+}
+
+#[test]
+fn warm_downloads_artifacts_of_a_previous_run() {
+    let fx = with_build_cache("");
+    fx.edo(&["run", "//hello_local/emit"]).success();
+    let summary = std::fs::read_dir(fx.storage.join("runs"))
+        .unwrap()
+        .map(|x| x.unwrap().path())
+        .find(|x| x.extension().is_some_and(|x| x == "json"))
+        .expect("the run must write a summary");
+    let copied = fx.dir.path().join("summary.json");
+    std::fs::copy(summary, &copied).unwrap();
+    clear_local(&fx);
+
+    fx.edo(&[
+        "warm",
+        "--from",
+        copied.to_str().unwrap(),
+        "--backend",
+        "//edo-build-cache",
+    ])
+    .success()
+    .stdout(contains("warmed 1 of 1 artifacts from the build cache"));
+    let catalog = std::fs::read_to_string(fx.storage.join("storage/catalog.json"))
+        .expect("the local cache must hold a catalog after warming");
+    assert!(catalog.contains("emit"), "{catalog}");
+}
+
+#[test]
+fn warm_reports_artifacts_missing_from_the_cache() {
+    let fx = with_build_cache("");
+    fx.edo(&["run", "//hello_local/emit"]).success();
+    std::fs::remove_dir_all(fx.path.join("build-cache")).unwrap();
+    std::fs::remove_dir_all(fx.storage.join("storage")).unwrap();
+    fx.edo(&["warm"])
+        .success()
+        .stdout(contains("warmed 0 of 1 artifacts"))
+        .stdout(contains("missing //hello_local/emit"));
+}
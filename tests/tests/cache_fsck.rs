@@ -0,0 +1,135 @@
+use edo_integration_tests::common::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+use std::path::PathBuf;
+
+/// Runs `hello_local` so the local cache holds something to check.
+fn populated() -> Fixture {
+    let fx = copy_fixture("hello_local");
+    fx.edo(&["run", "//hello_local/emit"]).success();
+    fx
+}
+
+fn blob_dir(fx: &Fixture) -> PathBuf {
+    fx.storage.join("storage/blobs/blake3")
+}
+
+/// Overwrites the first blob in the local cache with garbage.
+fn corrupt_a_blob(fx: &Fixture) {
+    let blob = std::fs::read_dir(blob_dir(fx))
+        .unwrap()
+        .next()
+        .expect("the run must leave at least one blob")
+        .unwrap()
+        .path();
+    std::fs::write(blob, b"not what was stored").unwrap();
+}
+
+#[test]
+fn fresh_cache_is_clean() {
+    let fx = populated();
+    fx.edo(&["cache", "fsck", "--verify"])
+        .success()
+        .stdout(contains("0 problems"));
+}
+
+#[test]
+fn existence_check_does_not_rehash() {
+    let fx = populated();
+    corrupt_a_blob(&fx);
+    fx.edo(&["cache", "fsck"]).success();
+}
+
+#[test]
+fn corrupt_blob_is_reported() {
+    let fx = populated();
+    corrupt_a_blob(&fx);
+    fx.edo(&["cache", "fsck", "--verify"])
+        .failure()
+        .stdout(contains("corrupt layer"))
+        .stderr(contains("rerun with --repair"));
+}
+
+#[test]
+fn orphaned_blob_is_reported() {
+    let fx = populated();
+    std::fs::write(blob_dir(&fx).join("leftover"), b"partial").unwrap();
+    fx.edo(&["cache", "fsck"])
+        .failure()
+        .stdout(contains("orphaned blob blake3:leftover"));
+}
+
+#[test]
+fn repair_leaves_a_clean_cache() {
+    let fx = populated();
+    corrupt_a_blob(&fx);
+    std::fs::write(blob_dir(&fx).join("leftover"), b"partial").unwrap();
+    fx.edo(&["cache", "fsck", "--verify", "--repair"])
+        .success()
+        .stdout(contains("dropped"))
+        .stdout(contains("removed blake3:leftover"));
+    fx.edo(&["cache", "fsck", "--verify"])
+        .success()
+        .stdout(contains("0 problems"));
+}
+
+#[test]
+fn unknown_cache_is_rejected() {
+    let fx = populated();
+    fx.edo(&["cache", "fsck", "--cache", "build"])
+        .failure()
+        .stderr(contains("no build cache is configured"));
+}
+
+#[test]
+fn configured_digest_algorithm_addresses_local_blobs() {
+    let fx = copy_fixture("hello_local");
+    let config = fx.path.join("edo-config.toml");
+    std::fs::write(&config, "[storage]\ndigest = \"sha256\"\n").unwrap();
+    let config = config.to_string_lossy().to_string();
+    fx.edo(&["--config", &config, "run", "//hello_local/emit"])
+        .success();
+    let blobs = std::fs::read_dir(fx.storage.join("storage/blobs/sha256"))
+        .expect("layers must be stored under their algorithm")
+        .count();
+    assert!(blobs > 0);
+    fx.edo(&["--config", &config, "cache", "fsck", "--verify"])
+        .success()
+        .stdout(contains("0 problems"));
+}
+
+#[test]
+fn info_counts_unreferenced_and_temporary_blobs() {
+    let fx = populated();
+    std::fs::write(blob_dir(&fx).join("leftover"), b"orphan").unwrap();
+    let partial = blob_dir(&fx).join("0190b8a0-7c1e-7000-8000-000000000000.tmp");
+    std::fs::write(&partial, b"partial").unwrap();
+    fx.edo(&["cache", "info"])
+        .success()
+        .stdout(contains("unreferenced  1"))
+        .stdout(contains("temporary     1 (7 B, 0 stale)"));
+    // Not stale yet, so another process may still be writing it
+    assert!(partial.exists());
+    fx.edo(&["cache", "fsck"])
+        .failure()
+        .stdout(contains("orphaned blob blake3:leftover"))
+        .stdout(contains(".tmp").not());
+}
+
+#[test]
+fn stale_temporary_blobs_are_swept_on_startup() {
+    let fx = populated();
+    let partial = blob_dir(&fx).join("0190b8a0-7c1e-7000-8000-000000000000.tmp");
+    std::fs::write(&partial, b"partial").unwrap();
+    let two_hours_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(2 * 60 * 60);
+    std::fs::File::options()
+        .write(true)
+        .open(&partial)
+        .unwrap()
+        .set_modified(two_hours_ago)
+        .unwrap();
+    fx.edo(&["cache", "info"])
+        .success()
+        .stdout(contains("temporary     0"));
+    assert!(!partial.exists());
+}
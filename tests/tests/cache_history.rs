@@ -0,0 +1,76 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+/// Builds `hello_script` once per entry of `versions`, each build writing its
+/// version to `version.txt` so every one is a new generation.
+fn built(versions: &[&str]) -> Fixture {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let original = std::fs::read_to_string(&manifest).unwrap();
+    for version in versions {
+        let content = original.replace(
+            "  \"mkdir -p {{install-root}}\",\n",
+            &format!(
+                "  \"mkdir -p {{{{install-root}}}}\",\n  \"echo {version} > {{{{install-root}}}}/version.txt\",\n"
+            ),
+        );
+        std::fs::write(&manifest, content).unwrap();
+        fx.edo(&["run", "//hello_script/build"]).success();
+    }
+    fx
+}
+
+fn version(fx: &Fixture, generation: Option<&str>) -> String {
+    let out = fx
+        .dir
+        .path()
+        .join(format!("out-{}", generation.unwrap_or("current")));
+    let mut args = vec!["checkout", "//hello_script/build", out.to_str().unwrap()];
+    if let Some(generation) = generation {
+        args.extend(["--generation", generation]);
+    }
+    fx.edo(&args).success();
+    let file = find_file(&out, "version.txt").expect("version.txt must exist");
+    std::fs::read_to_string(file).unwrap()
+}
+
+#[test]
+fn history_lists_generations_newest_first() {
+    let fx = built(&["v1", "v2"]);
+    let out = fx
+        .edo(&["cache", "history", "//hello_script/build"])
+        .success();
+    let stdout = String::from_utf8(out.get_output().stdout.clone()).unwrap();
+    let lines: Vec<&str> = stdout
+        .lines()
+        .filter(|x| x.contains("hello_script"))
+        .collect();
+    assert_eq!(lines.len(), 2, "{stdout}");
+    assert!(lines[0].trim_start().starts_with("0 "), "{stdout}");
+    assert!(lines[0].ends_with("(current)"), "{stdout}");
+    assert!(!lines[1].ends_with("(current)"), "{stdout}");
+}
+
+#[test]
+fn checkout_rolls_back_to_an_earlier_generation() {
+    let fx = built(&["v1", "v2"]);
+    assert_eq!(version(&fx, None), "v2\n");
+    assert_eq!(version(&fx, Some("1")), "v1\n");
+}
+
+#[test]
+fn prune_keeps_the_previous_generation() {
+    let fx = built(&["v1", "v2", "v3"]);
+    fx.edo(&["prune"]).success();
+    assert_eq!(version(&fx, Some("1")), "v2\n");
+    let out = fx.dir.path().join("out-gone");
+    fx.edo(&[
+        "checkout",
+        "//hello_script/build",
+        out.to_str().unwrap(),
+        "--generation",
+        "2",
+    ])
+    .failure()
+    .stderr(contains("there is no generation 2"));
+}
@@ -1,16 +1,13 @@
 use edo_integration_tests::common::*;
+use predicates::str::contains;
 
 #[test]
 fn checkout_extracts_files() {
     let fx = copy_fixture("hello_local");
     fx.edo(&["run", "//hello_local/emit"]).success();
     let out = fx.dir.path().join("out");
-    fx.edo(&[
-        "checkout",
-        "//hello_local/emit",
-        out.to_str().unwrap(),
-    ])
-    .success();
+    fx.edo(&["checkout", "//hello_local/emit", out.to_str().unwrap()])
+        .success();
     let greeting = find_file(&out, "greeting.txt").expect("greeting.txt must exist");
     let content = std::fs::read_to_string(&greeting).unwrap();
     assert!(
@@ -24,12 +21,8 @@ fn checkout_script_produces_hello_txt() {
     let fx = copy_fixture("hello_script");
     fx.edo(&["run", "//hello_script/build"]).success();
     let out = fx.dir.path().join("out");
-    fx.edo(&[
-        "checkout",
-        "//hello_script/build",
-        out.to_str().unwrap(),
-    ])
-    .success();
+    fx.edo(&["checkout", "//hello_script/build", out.to_str().unwrap()])
+        .success();
     let hello = find_file(&out, "hello.txt").expect("hello.txt must exist");
     let content = std::fs::read_to_string(&hello).unwrap();
     assert!(
@@ -38,17 +31,44 @@ fn checkout_script_produces_hello_txt() {
     );
 }
 
+#[test]
+fn checkout_script_sees_source_date_epoch() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let content = std::fs::read_to_string(&manifest).unwrap().replace(
+        "\"mkdir -p {{install-root}}\",\n",
+        "\"mkdir -p {{install-root}}\",\n  \"echo $SOURCE_DATE_EPOCH > {{install-root}}/epoch.txt\",\n",
+    );
+    std::fs::write(&manifest, &content).unwrap();
+    fx.edo(&["run", "//hello_script/build"]).success();
+    let unset = artifact_id(&fx, "//hello_script/build");
+
+    std::fs::write(
+        &manifest,
+        format!("{content}\n[config.reproducibility]\nsource_date_epoch = 1700000000\n"),
+    )
+    .unwrap();
+    fx.edo(&["run", "//hello_script/build"]).success();
+    let set = artifact_id(&fx, "//hello_script/build");
+    assert_ne!(unset, set, "the epoch must be part of the id");
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", "//hello_script/build", out.to_str().unwrap()])
+        .success();
+    let epoch = find_file(&out, "epoch.txt").expect("epoch.txt must exist");
+    assert_eq!(std::fs::read_to_string(epoch).unwrap().trim(), "1700000000");
+    // The epoch is recorded in the artifact's metadata
+    fx.edo(&["diff", &unset, &set])
+        .success()
+        .stdout(contains("source_date_epoch"));
+}
+
 #[test]
 fn checkout_compose_contains_all() {
     let fx = copy_fixture("hello_compose");
     fx.edo(&["run", "//hello_compose/bundle"]).success();
     let out = fx.dir.path().join("out");
-    fx.edo(&[
-        "checkout",
-        "//hello_compose/bundle",
-        out.to_str().unwrap(),
-    ])
-    .success();
+    fx.edo(&["checkout", "//hello_compose/bundle", out.to_str().unwrap()])
+        .success();
     assert!(
         find_file(&out, "left.txt").is_some(),
         "left.txt missing from composed artifact",
@@ -59,20 +79,326 @@ fn checkout_compose_contains_all() {
     );
 }
 
-fn find_file(root: &std::path::Path, name: &str) -> Option<std::path::PathBuf> {
-    let mut stack = vec![root.to_path_buf()];
-    while let Some(dir) = stack.pop() {
-        let Ok(read) = std::fs::read_dir(&dir) else {
-            continue;
-        };
-        for entry in read.flatten() {
-            let path = entry.path();
-            if path.is_dir() {
-                stack.push(path);
-            } else if path.file_name().and_then(|x| x.to_str()) == Some(name) {
-                return Some(path);
-            }
-        }
+#[test]
+fn checkout_source_by_address() {
+    let fx = copy_fixture("hello_script");
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", "//hello_script/src", out.to_str().unwrap()])
+        .success();
+    assert!(
+        out.join("make_hello.sh").is_file(),
+        "make_hello.sh missing from staged source",
+    );
+}
+
+#[test]
+fn checkout_named_source_of_transform() {
+    let fx = copy_fixture("hello_script");
+    let out = fx.dir.path().join("out");
+    fx.edo(&[
+        "checkout",
+        "//hello_script/build",
+        out.to_str().unwrap(),
+        "--source",
+        "src",
+    ])
+    .success();
+    assert!(
+        out.join("make_hello.sh").is_file(),
+        "make_hello.sh missing from staged source",
+    );
+}
+
+#[test]
+fn checkout_unknown_source_fails() {
+    let fx = copy_fixture("hello_script");
+    let out = fx.dir.path().join("out");
+    fx.edo(&[
+        "checkout",
+        "//hello_script/build",
+        out.to_str().unwrap(),
+        "--source",
+        "missing",
+    ])
+    .failure()
+    .stderr(contains(
+        "//hello_script/build does not use a source named 'missing'",
+    ));
+    fx.edo(&["checkout", "//hello_script/missing", out.to_str().unwrap()])
+        .failure()
+        .stderr(contains(
+            "no transform or source found with addr '//hello_script/missing'",
+        ));
+}
+
+/// Returns the artifact id recorded for `addr` by the latest run.
+fn artifact_id(fx: &Fixture, addr: &str) -> String {
+    let output = fx.edo(&["runs", "show", "--json"]).success();
+    let summary: serde_json::Value =
+        serde_json::from_slice(&output.get_output().stdout).expect("parse summary");
+    summary["nodes"]
+        .as_array()
+        .expect("nodes")
+        .iter()
+        .find(|x| x["addr"] == addr)
+        .and_then(|x| x["id"].as_str())
+        .expect("artifact id")
+        .to_string()
+}
+
+#[test]
+fn checkout_by_artifact_id_skips_project() {
+    let fx = copy_fixture("hello_local");
+    fx.edo(&["run", "//hello_local/emit"]).success();
+    let id = artifact_id(&fx, "//hello_local/emit");
+    let (name, digest) = id.rsplit_once('-').unwrap();
+
+    // Run from a directory without an edo.toml to prove the project is never loaded
+    let elsewhere = tempfile::TempDir::new().unwrap();
+    for target in [id.clone(), format!("{name}#{digest}")] {
+        let out = fx.dir.path().join(format!("out-{}", target.len()));
+        fx.cmd()
+            .current_dir(elsewhere.path())
+            .arg("--storage")
+            .arg(&fx.storage)
+            .args(["checkout", &target, out.to_str().unwrap()])
+            .assert()
+            .success();
+        assert!(
+            find_file(&out, "greeting.txt").is_some(),
+            "greeting.txt missing when checking out {target}",
+        );
     }
-    None
+}
+
+#[test]
+fn checkout_unknown_artifact_id_fails() {
+    let fx = copy_fixture("hello_local");
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", "missing#0000", out.to_str().unwrap()])
+        .failure()
+        .stderr(contains(
+            "no artifact with id 'missing-0000' in the local or build cache",
+        ));
+}
+
+#[test]
+fn checkout_script_with_argv_and_command_tables() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str(
+        r#"
+[transform.argv]
+kind        = "script"
+interpreter = "sh"
+source      = ["src"]
+commands    = [
+  "mkdir -p {{install-root}}/sub",
+  ["sh", "-c", "printf '%s' \"$1\" > {{install-root}}/argv.txt", "sh", "it's a \"quoted\" $HOME; true"],
+  { run = "echo \"$MODE\" > mode.txt", interpreter = "sh", env = { MODE = "a b" }, dir = "{{install-root}}/sub" },
+]
+"#,
+    );
+    std::fs::write(&manifest, content).unwrap();
+
+    fx.edo(&["run", "//hello_script/argv"]).success();
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", "//hello_script/argv", out.to_str().unwrap()])
+        .success();
+    let argv = find_file(&out, "argv.txt").expect("argv.txt must exist");
+    assert_eq!(
+        std::fs::read_to_string(argv).unwrap(),
+        r#"it's a "quoted" $HOME; true"#
+    );
+    let mode = find_file(&out, "mode.txt").expect("mode.txt must exist");
+    assert_eq!(std::fs::read_to_string(mode).unwrap(), "a b\n");
+}
+
+#[test]
+fn checkout_script_with_quoted_placeholders() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str(
+        r#"
+[transform.greet]
+kind        = "script"
+interpreter = "sh"
+source      = ["src"]
+commands    = [
+  "mkdir -p {{install-root}}",
+  "printf '%s|%s' {{quote greeting}} {{artifact-id}} > {{install-root}}/greeting.txt",
+]
+"#,
+    );
+    std::fs::write(&manifest, content).unwrap();
+
+    fx.edo(&[
+        "run",
+        "//hello_script/greet",
+        "--arg",
+        "greeting=it's & <here>",
+    ])
+    .success();
+    let out = fx.dir.path().join("out");
+    fx.edo(&[
+        "checkout",
+        "//hello_script/greet",
+        out.to_str().unwrap(),
+        "--arg",
+        "greeting=it's & <here>",
+    ])
+    .success();
+    let greeting = find_file(&out, "greeting.txt").expect("greeting.txt must exist");
+    let text = std::fs::read_to_string(greeting).unwrap();
+    let (greeting, id) = text.split_once('|').unwrap();
+    assert_eq!(greeting, "it's & <here>");
+    assert!(id.contains("greet"), "unexpected artifact id {id}");
+}
+
+#[test]
+fn checkout_results_captured_from_failed_transform() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str(
+        r#"
+[transform.tests]
+kind        = "script"
+interpreter = "sh"
+source      = ["src"]
+capture     = ["results/*.xml"]
+commands    = [
+  "mkdir -p results",
+  "echo '<testsuite failures=\"1\"/>' > results/unit.xml",
+  "echo skipped > results/notes.txt",
+  "exit 1",
+]
+"#,
+    );
+    std::fs::write(&manifest, content).unwrap();
+
+    fx.edo(&["run", "//hello_script/tests"]).failure();
+    let out = fx.dir.path().join("out");
+    fx.edo(&[
+        "checkout",
+        "//hello_script/tests",
+        out.to_str().unwrap(),
+        "--results",
+    ])
+    .success();
+    let report = find_file(&out, "unit.xml").expect("unit.xml must be captured");
+    assert!(report.ends_with("results/unit.xml"));
+    assert_eq!(
+        std::fs::read_to_string(report).unwrap(),
+        "<testsuite failures=\"1\"/>\n"
+    );
+    assert!(find_file(&out, "notes.txt").is_none());
+}
+
+#[test]
+fn script_hooks_run_around_commands() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str(
+        r#"
+[transform.hooked]
+kind        = "script"
+interpreter = "sh"
+pre         = ["echo pre > {{build-root}}/order.txt"]
+commands    = ["echo main >> {{build-root}}/order.txt"]
+post        = ["echo post >> order.txt", "cp order.txt {{install-root}}/order.txt"]
+on_failure  = ["echo unexpected > {{install-root}}/failed.txt"]
+
+[transform.diagnosed]
+kind        = "script"
+interpreter = "sh"
+capture     = ["diagnostics/*"]
+commands    = ["echo building", "exit 3"]
+post        = ["echo never > post.txt"]
+on_failure  = ["mkdir -p diagnostics", "echo core dumped > diagnostics/core.txt"]
+"#,
+    );
+    std::fs::write(&manifest, content).unwrap();
+
+    fx.edo(&["run", "//hello_script/hooked"]).success();
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", "//hello_script/hooked", out.to_str().unwrap()])
+        .success();
+    let order = find_file(&out, "order.txt").expect("order.txt must exist");
+    assert_eq!(std::fs::read_to_string(order).unwrap(), "pre\nmain\npost\n");
+    assert!(find_file(&out, "failed.txt").is_none());
+
+    fx.edo(&["run", "//hello_script/diagnosed"])
+        .failure()
+        .stderr(contains("command 1 `exit 3` failed with exit code 3"));
+    let results = fx.dir.path().join("results");
+    fx.edo(&[
+        "checkout",
+        "//hello_script/diagnosed",
+        results.to_str().unwrap(),
+        "--results",
+    ])
+    .success();
+    let core = find_file(&results, "core.txt").expect("on_failure output must be captured");
+    assert_eq!(std::fs::read_to_string(core).unwrap(), "core dumped\n");
+    assert!(find_file(&results, "post.txt").is_none());
+}
+
+#[test]
+fn checkout_to_oci_layout() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["run", "//hello_script/build"]).success();
+    let layout = fx.dir.path().join("layout");
+    let args = [
+        "checkout",
+        "//hello_script/build",
+        "--oci-layout",
+        layout.to_str().unwrap(),
+    ];
+    fx.edo(&args).success().stdout(contains("sha256:"));
+    // A second export replaces the image rather than adding another
+    fx.edo(&args).success();
+
+    let read = |path: std::path::PathBuf| -> serde_json::Value {
+        serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+    };
+    let blob = |digest: &serde_json::Value| {
+        let digest = digest.as_str().unwrap();
+        layout
+            .join("blobs/sha256")
+            .join(digest.strip_prefix("sha256:").unwrap())
+    };
+    assert_eq!(
+        read(layout.join("oci-layout"))["imageLayoutVersion"],
+        "1.0.0"
+    );
+    let index = read(layout.join("index.json"));
+    let manifests = index["manifests"].as_array().unwrap();
+    assert_eq!(manifests.len(), 1, "unexpected index: {index}");
+    let manifest = read(blob(&manifests[0]["digest"]));
+    let layers = manifest["layers"].as_array().unwrap();
+    assert!(!layers.is_empty(), "image has no layers: {manifest}");
+    for layer in layers {
+        let path = blob(&layer["digest"]);
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            layer["size"].as_u64().unwrap()
+        );
+    }
+    let config = read(blob(&manifest["config"]["digest"]));
+    assert_eq!(
+        config["rootfs"]["diff_ids"].as_array().unwrap().len(),
+        layers.len()
+    );
+}
+
+#[test]
+fn checkout_oci_layout_rejects_output_directory() {
+    let fx = copy_fixture("hello_script");
+    let dir = fx.dir.path().to_str().unwrap();
+    fx.edo(&["checkout", "//hello_script/build", dir, "--oci-layout", dir])
+        .failure();
 }
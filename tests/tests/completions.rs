@@ -0,0 +1,39 @@
+use edo_integration_tests::common::*;
+use predicates::prelude::PredicateBooleanExt;
+use predicates::str::contains;
+
+#[test]
+fn completion_scripts_name_subcommands() {
+    let fx = copy_fixture("hello_script");
+    for shell in ["bash", "zsh", "fish"] {
+        fx.edo(&["completions", shell])
+            .success()
+            .stdout(contains("verify-repro"))
+            .stdout(contains("--keep-workspace").or(contains("-l keep-workspace")))
+            .stdout(contains("edo __complete"));
+    }
+}
+
+#[test]
+fn addresses_complete_from_a_cached_index() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["__complete", "//hello_s"])
+        .success()
+        .stdout("//hello_script/build\n");
+    assert!(fx.storage.join("index.json").is_file());
+
+    // Changing a manifest invalidates the index
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let content = std::fs::read_to_string(&manifest).unwrap();
+    std::fs::write(
+        &manifest,
+        content.replace(
+            "[transform.build]",
+            "[transform.build2]\nkind = \"import\"\nsource = [\"src\"]\n\n[transform.build]",
+        ),
+    )
+    .unwrap();
+    fx.edo(&["__complete", "//hello_script/b"])
+        .success()
+        .stdout("//hello_script/build\n//hello_script/build2\n");
+}
@@ -0,0 +1,100 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::os::unix::fs::PermissionsExt;
+
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+const CONTENT: &str = "behind a password\n";
+
+/// Serves `CONTENT` on a free port to requests authorized as `me:s3cret`,
+/// answering 401 to any other, and returns the port.
+fn serve_protected() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut authorized = false;
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 0) && line != "\r\n" {
+                // base64 of `me:s3cret`
+                authorized |= line.trim_end() == "authorization: Basic bWU6czNjcmV0";
+                line.clear();
+            }
+            let (status, body) = if authorized {
+                ("200 OK", CONTENT)
+            } else {
+                ("401 Unauthorized", "who are you?")
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+        }
+    });
+    port
+}
+
+/// `hello_local` plus a `//hello_local/downloaded` transform importing a file
+/// from the protected server, with a user config naming a helper that knows
+/// its password.
+fn with_protected_source() -> (Fixture, String) {
+    let port = serve_protected();
+    let fx = copy_fixture("hello_local").append_manifest(
+        "hello_local",
+        &format!(
+            "[source.protected]\nkind = \"remote\"\nurl  = \"http://127.0.0.1:{port}/file.txt\"\n\
+             ref  = \"{}\"\nout  = \"file.txt\"\n\n\
+             [transform.downloaded]\nkind   = \"import\"\nsource = [\"protected\"]",
+            blake3::hash(CONTENT.as_bytes()).to_hex()
+        ),
+    );
+
+    let helper = fx.write_file(
+        "helper",
+        "#!/bin/sh\nread server\ncase \"$server\" in\n  http://127.0.0.1:*) \
+         echo '{\"ServerURL\":\"'$server'\",\"Username\":\"me\",\"Secret\":\"s3cret\"}' ;;\n  \
+         *) echo 'credentials not found in native keychain'; exit 1 ;;\nesac\n",
+    );
+    std::fs::set_permissions(&helper, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let config = fx.write_file(
+        "config.toml",
+        &format!("[credentials]\nhosts = {{ \"127.0.0.1\" = \"{helper}\" }}\n"),
+    );
+    (fx, config)
+}
+
+#[test]
+fn remote_sources_ask_the_configured_helper() {
+    let (fx, config) = with_protected_source();
+    fx.edo(&["--config", &config, "fetch", "//hello_local/downloaded"])
+        .success();
+    let build_files = std::fs::read_to_string(fx.path.join("hello_local/edo.toml")).unwrap();
+    assert!(!build_files.contains("s3cret"));
+}
+
+#[test]
+fn remote_sources_without_a_helper_are_refused() {
+    let (fx, _) = with_protected_source();
+    fx.edo(&["fetch", "//hello_local/downloaded"])
+        .failure()
+        .stderr(contains("who are you?"));
+}
+
+#[test]
+fn failing_helpers_are_reported() {
+    let (fx, _) = with_protected_source();
+    let config = fx.dir.path().join("broken.toml");
+    std::fs::write(&config, "[credentials]\nhelper = \"false\"\n").unwrap();
+    fx.edo(&[
+        "--config",
+        &config.display().to_string(),
+        "fetch",
+        "//hello_local/downloaded",
+    ])
+    .failure()
+    .stderr(contains("credential helper 'false' failed"));
+}
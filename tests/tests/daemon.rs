@@ -0,0 +1,66 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+/// Stops the fixture's daemon even when an assertion fails.
+struct Stop<'a>(&'a Fixture);
+
+impl Drop for Stop<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.edo(&["daemon", "stop"]);
+    }
+}
+
+#[test]
+fn daemon_serves_list_and_run_and_reloads_changed_files() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["daemon", "start"])
+        .success()
+        .stdout(contains("daemon started"));
+    let _stop = Stop(&fx);
+    assert!(fx.storage.join("daemon.sock").exists());
+
+    fx.edo(&["list"])
+        .success()
+        .stdout(contains("//hello_script/build"));
+    fx.edo(&["daemon", "status"])
+        .success()
+        .stdout(contains("loads:    1"))
+        .stdout(contains("requests: 1"));
+
+    // A changed build file is picked up by the next request
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str(
+        "\n[transform.extra]\nkind        = \"script\"\ninterpreter = \"sh\"\ncommands    = [\"mkdir -p {{install-root}}\"]\n",
+    );
+    std::fs::write(&manifest, content).unwrap();
+    fx.edo(&["list"])
+        .success()
+        .stdout(contains("//hello_script/extra"));
+    fx.edo(&["daemon", "status"])
+        .success()
+        .stdout(contains("loads:    2"));
+
+    fx.edo(&["run", "//hello_script/build"])
+        .success()
+        .stdout(contains("built   //hello_script/build"));
+    fx.edo(&["run", "//hello_script/build"])
+        .success()
+        .stdout(contains("cached  //hello_script/build"));
+    fx.edo(&["run", "//hello_script/missing"]).failure();
+
+    fx.edo(&["daemon", "stop"])
+        .success()
+        .stdout(contains("daemon stopped"));
+}
+
+#[test]
+fn commands_run_locally_without_a_daemon() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["daemon", "status"])
+        .success()
+        .stdout(contains("no daemon is running"));
+    fx.edo(&["list"])
+        .success()
+        .stdout(contains("//hello_script/build"));
+}
@@ -0,0 +1,36 @@
+use edo_integration_tests::common::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+#[test]
+fn diff_same_artifact_is_identical() {
+    let fx = copy_fixture("hello_local");
+    fx.edo(&["run", "//hello_local/emit"]).success();
+    fx.edo(&["diff", "//hello_local/emit", "//hello_local/emit"])
+        .success()
+        .stdout(contains("artifacts are identical"));
+}
+
+#[test]
+fn diff_matrix_variants_reports_changed_file() {
+    let fx = copy_fixture("hello_matrix");
+    fx.edo(&["run", "//hello_matrix/build"]).success();
+    fx.edo(&[
+        "diff",
+        "//hello_matrix/build[profile=debug]",
+        "//hello_matrix/build[profile=release]",
+    ])
+    .success()
+    .stdout(contains("- layer 0:"))
+    .stdout(contains("+ layer 0:"))
+    .stdout(predicate::str::is_match(r"-   \S*profile\.txt \(regular, 6 bytes").unwrap())
+    .stdout(predicate::str::is_match(r"\+   \S*profile\.txt \(regular, 8 bytes").unwrap());
+}
+
+#[test]
+fn diff_unbuilt_artifact_fails() {
+    let fx = copy_fixture("hello_local");
+    fx.edo(&["diff", "//hello_local/emit", "//hello_local/emit"])
+        .failure()
+        .stderr(contains("in the local or build cache"));
+}
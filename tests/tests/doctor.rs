@@ -0,0 +1,49 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+#[test]
+fn doctor_checks_every_configured_cache() {
+    let fx = copy_fixture("hello_local");
+    let manifest = fx.path.join("hello_local/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str(&format!(
+        "\n[cache.build]\nkind = \"local\"\npath = \"{}\"\n",
+        fx.path.join("build-cache").display()
+    ));
+    std::fs::write(manifest, content).unwrap();
+    // The lock file written by the run is up to date for the doctor
+    fx.edo(&["run", "//hello_local/emit"]).success();
+
+    fx.edo(&["doctor"])
+        .success()
+        .stdout(contains("project\n  ok       load:"))
+        .stdout(contains("local cache\n  ok       writable:"))
+        .stdout(contains("disk space:"))
+        .stdout(contains("build cache\n"))
+        .stdout(contains("catalog: 1 artifacts"))
+        .stdout(contains("0 errors, 0 warnings"));
+
+    let output = fx
+        .edo(&["doctor", "--json"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let reports: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(reports[1]["subject"], "local cache");
+    assert_eq!(reports[1]["checks"][0]["status"], "ok");
+}
+
+#[test]
+fn doctor_explains_how_to_fix_a_failed_check() {
+    let fx = copy_fixture("hello_local");
+    fx.edo(&["run", "//hello_local/emit"]).success();
+    std::fs::write(fx.storage.join("storage/catalog.json"), "{").unwrap();
+
+    fx.edo(&["doctor"])
+        .failure()
+        .stdout(contains("  error    catalog:"))
+        .stdout(contains("fix: move"))
+        .stdout(contains("1 errors, 0 warnings"))
+        .stderr(contains("1 checks failed"));
+}
@@ -17,3 +17,44 @@ fn unresolved_source_nonzero_exit() {
     let fx = copy_from(&error_fixtures_root(), "unresolved_source");
     fx.edo(&["list"]).failure();
 }
+
+#[test]
+fn ambiguous_script_command_rejected() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str(
+        "\n[transform.both]\nkind = \"script\"\ncommands = [{ run = \"true\", argv = [\"true\"] }]\n",
+    );
+    std::fs::write(&manifest, content).unwrap();
+    fx.edo(&["run", "//hello_script/both"])
+        .failure()
+        .stderr(predicates::str::contains("exactly one of run or argv"));
+}
+
+#[test]
+fn undefined_placeholder_rejected_at_load() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str(
+        "\n[transform.typo]\nkind = \"script\"\ncommands = [\"touch {{instal-root}}/x\"]\n",
+    );
+    std::fs::write(&manifest, content).unwrap();
+    fx.edo(&["run", "//hello_script/typo"])
+        .failure()
+        .stderr(predicates::str::contains("'instal-root' is not defined"));
+}
+
+#[test]
+fn misspelled_kind_suggests_registered_kinds() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str("\n[transform.typo]\nkind = \"scrpt\"\ncommands = [\"true\"]\n");
+    std::fs::write(&manifest, content).unwrap();
+    fx.edo(&["run", "//hello_script/typo"])
+        .failure()
+        .stderr(predicates::str::contains("did you mean `script`?"))
+        .stderr(predicates::str::contains("supported transform kinds:"));
+}
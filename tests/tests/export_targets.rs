@@ -0,0 +1,41 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+#[test]
+fn transforms_are_exported_with_their_sources() {
+    let fx = copy_fixture("hello_script");
+    let output = fx
+        .edo(&["export-targets", "--format", "json"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let target = &json["targets"][0];
+    assert_eq!(target["addr"], "//hello_script/build");
+    assert_eq!(target["kind"], "script");
+    assert_eq!(target["sources"][0]["addr"], "//hello_script/src");
+    assert_eq!(target["sources"][0]["kind"], "local");
+    assert_eq!(target["sources"][0]["fields"]["path"], "hello_script/files");
+    assert!(
+        target["output"]
+            .as_str()
+            .unwrap()
+            .starts_with("hello_script_build")
+    );
+
+    fx.edo(&[
+        "export-targets",
+        "//hello_script/build",
+        "--format",
+        "bazel-query-xml",
+    ])
+    .success()
+    .stdout(contains(
+        "<rule class=\"script\" name=\"//hello_script/build\">",
+    ))
+    .stdout(contains("<rule-input name=\"//hello_script/src\"/>"))
+    .stdout(contains(
+        "<rule class=\"local\" name=\"//hello_script/src\">",
+    ));
+}
@@ -0,0 +1,125 @@
+use edo_integration_tests::common::*;
+use std::path::Path;
+use tempfile::TempDir;
+
+const CONTENT: &[u8] = b"hello from an external cache\n";
+
+/// Writes a project whose only source points at an unreachable url, so the
+/// build can only succeed if the source is served from the external cache.
+fn project(kind: &str, cache: &str) -> Fixture {
+    let dir = TempDir::new().expect("create tempdir");
+    let path = dir.path().to_path_buf();
+    let project = path.join("legacy");
+    std::fs::create_dir_all(&project).unwrap();
+    std::fs::write(
+        project.join("edo.toml"),
+        format!(
+            r#"schema-version = "1"
+
+[cache.source.legacy]
+kind = "{kind}"
+path = "{}"
+
+[source.blob]
+kind       = "remote"
+url        = "http://127.0.0.1:9/greeting.txt"
+ref        = "{}"
+out        = "greeting.txt"
+is_archive = false
+
+[transform.build]
+kind   = "import"
+source = ["blob"]
+"#,
+            path.join(cache).display(),
+            blake3::hash(CONTENT).to_hex()
+        ),
+    )
+    .unwrap();
+    let storage = path.join(".edo-test-store");
+    Fixture { dir, path, storage }
+}
+
+fn write_blob(path: &Path) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, CONTENT).unwrap();
+}
+
+fn assert_checkout(fx: &Fixture) {
+    fx.edo(&["run", "//legacy/build"]).success();
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", "//legacy/build", out.to_str().unwrap()])
+        .success();
+    let content = std::fs::read(out.join("greeting.txt")).expect("greeting.txt must exist");
+    assert_eq!(content, CONTENT);
+}
+
+// The sha256 names below are never checked: external blobs are matched by
+// the blake3 digest of their content.
+#[test]
+fn source_served_from_oci_layout() {
+    let fx = project("oci-layout", "buildkit");
+    write_blob(
+        &fx.path
+            .join(format!("buildkit/blobs/sha256/{}", "a".repeat(64))),
+    );
+    std::fs::write(
+        fx.path.join("buildkit/oci-layout"),
+        r#"{"imageLayoutVersion":"1.0.0"}"#,
+    )
+    .unwrap();
+    assert_checkout(&fx);
+}
+
+#[test]
+fn source_served_from_bazel_disk_cache() {
+    let fx = project("bazel-disk", "bazel");
+    let hex = "b".repeat(64);
+    write_blob(&fx.path.join(format!("bazel/cas/{}/{hex}", &hex[..2])));
+    assert_checkout(&fx);
+}
+
+#[test]
+fn missing_external_cache_fails() {
+    let fx = project("bazel-disk", "missing");
+    fx.edo(&["run", "//legacy/build"])
+        .failure()
+        .stderr(predicates::str::contains("does not exist"));
+}
+
+/// Restricts the external cache of `fx` to the sources matching `route`.
+fn route(fx: &Fixture, route: &str) {
+    let manifest = fx.path.join("legacy/edo.toml");
+    let content = std::fs::read_to_string(&manifest).unwrap();
+    std::fs::write(
+        &manifest,
+        content.replace(
+            "\n[source.blob]",
+            &format!("route = {route}\n\n[source.blob]"),
+        ),
+    )
+    .unwrap();
+}
+
+#[test]
+fn routed_source_cache_serves_matching_sources() {
+    let fx = project("bazel-disk", "bazel");
+    let hex = "b".repeat(64);
+    write_blob(&fx.path.join(format!("bazel/cas/{}/{hex}", &hex[..2])));
+    route(
+        &fx,
+        r#"{ kinds = ["remote"], prefixes = ["//legacy/"], urls = ["http://127.0.0.1:9/*.txt"] }"#,
+    );
+    assert_checkout(&fx);
+}
+
+#[test]
+fn routed_source_cache_skips_other_sources() {
+    let fx = project("bazel-disk", "bazel");
+    let hex = "b".repeat(64);
+    write_blob(&fx.path.join(format!("bazel/cas/{}/{hex}", &hex[..2])));
+    route(&fx, r#"{ kinds = ["image"] }"#);
+    // The source is not routed to the cache, so it has to be fetched from
+    // its unreachable url
+    fx.edo(&["run", "//legacy/build"]).failure();
+}
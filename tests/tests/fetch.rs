@@ -0,0 +1,105 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+use std::process::Command;
+
+fn git(dir: &std::path::Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=edo", "-c", "user.email=edo@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .expect("git must be installed");
+    assert!(status.success(), "git {args:?} failed");
+}
+
+/// `hello_local` plus a `//hello_local/cloned` transform importing a git
+/// repository that lives outside the project, standing in for a remote.
+fn with_git_source() -> Fixture {
+    let fx = copy_fixture("hello_local");
+    let upstream = fx.dir.path().join("upstream");
+    std::fs::create_dir_all(&upstream).unwrap();
+    std::fs::write(upstream.join("remote.txt"), "from upstream\n").unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    git(&upstream, &["add", "."]);
+    git(&upstream, &["commit", "-q", "-m", "initial"]);
+
+    fx.append_manifest(
+        "hello_local",
+        &format!(
+            "[source.upstream]\nkind = \"git\"\nurl  = \"{}\"\nref  = \"main\"\nout  = \".\"\n\n\
+             [transform.cloned]\nkind   = \"import\"\nsource = [\"upstream\"]",
+            upstream.display()
+        ),
+    )
+}
+
+#[test]
+fn offline_run_requires_fetched_sources() {
+    let fx = with_git_source();
+    fx.edo(&["run", "--offline", "//hello_local/cloned"])
+        .failure()
+        .stderr(contains("run `edo fetch` before building offline"));
+}
+
+#[test]
+fn offline_run_succeeds_after_fetch() {
+    let fx = with_git_source();
+    fx.edo(&["fetch", "//hello_local/cloned"])
+        .success()
+        .stdout(contains("fetched 1 transforms into the local cache"));
+    // The upstream is gone, so the build can only use the local cache
+    std::fs::remove_dir_all(fx.dir.path().join("upstream")).unwrap();
+    fx.edo(&["run", "--offline", "//hello_local/cloned"])
+        .success();
+}
+
+#[test]
+fn fetch_without_addrs_covers_every_transform() {
+    let fx = with_git_source();
+    fx.edo(&["fetch"])
+        .success()
+        .stdout(contains("fetched 2 transforms into the local cache"));
+}
+
+#[test]
+fn offline_run_builds_local_sources() {
+    let fx = copy_fixture("hello_local");
+    fx.edo(&["run", "--offline", "//hello_local/emit"])
+        .success();
+}
+
+#[test]
+fn moved_git_branch_fails_until_updated() {
+    let fx = with_git_source();
+    fx.edo(&["fetch", "//hello_local/cloned"]).success();
+    let lock = std::fs::read_to_string(fx.path.join("edo.lock.json")).unwrap();
+    assert!(lock.contains("//hello_local/upstream"), "no pin in {lock}");
+
+    // Move the branch and drop the cached checkout so it is cloned again
+    let upstream = fx.dir.path().join("upstream");
+    std::fs::write(upstream.join("remote.txt"), "moved on\n").unwrap();
+    git(&upstream, &["commit", "-q", "-am", "moved"]);
+    fx.edo(&["prune", "--all"]).success();
+    fx.edo(&["run", "//hello_local/cloned"])
+        .failure()
+        .stderr(contains("run `edo update` to accept the change"));
+
+    fx.edo(&["update"]).success();
+    fx.edo(&["run", "//hello_local/cloned"]).success();
+}
+
+#[test]
+fn vendored_sources_build_offline_without_upstream() {
+    let fx = with_git_source();
+    fx.edo(&["vendor"])
+        .success()
+        .stdout(contains("//hello_local/upstream"))
+        .stdout(contains("vendored 1 sources into"));
+    assert!(fx.path.join("vendor/index.json").exists());
+
+    // Neither the upstream nor the local cache are left, only the bundle
+    std::fs::remove_dir_all(fx.dir.path().join("upstream")).unwrap();
+    std::fs::remove_dir_all(&fx.storage).unwrap();
+    fx.edo(&["run", "--offline", "//hello_local/cloned"])
+        .success();
+}
@@ -0,0 +1,36 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+#[test]
+fn fmt_checks_and_formats_build_files() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["fmt", "--check"]).success().stdout("");
+
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let formatted = std::fs::read_to_string(&manifest).unwrap();
+    std::fs::write(
+        &manifest,
+        formatted.replace("kind        = \"script\"", "  kind=\"script\""),
+    )
+    .unwrap();
+    fx.edo(&["fmt", "--check"])
+        .failure()
+        .stdout(contains("hello_script/edo.toml"))
+        .stderr(contains("1 build files are not formatted"));
+    fx.edo(&["fmt"]).success();
+    assert_eq!(std::fs::read_to_string(&manifest).unwrap(), formatted);
+}
+
+#[test]
+fn fmt_leaves_unparseable_files_alone() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    std::fs::write(&manifest, "[transform.build\n").unwrap();
+    fx.edo(&["fmt", "hello_script/edo.toml"])
+        .failure()
+        .stderr(contains("failed to format hello_script/edo.toml"));
+    assert_eq!(
+        std::fs::read_to_string(&manifest).unwrap(),
+        "[transform.build\n"
+    );
+}
@@ -0,0 +1,100 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use edo_integration_tests::common::*;
+use predicates::prelude::PredicateBooleanExt;
+use predicates::str::contains;
+
+/// Sends a GET request for `path` and returns the whole response.
+fn get(port: u16, path: &str) -> Option<String> {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).ok()?;
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+    )
+    .ok()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok()?;
+    Some(response)
+}
+
+#[test]
+fn graph_prints_dot_colored_by_cache_state() {
+    let fx = copy_fixture("hello_compose");
+    fx.edo(&["run", "//hello_compose/left"]).success();
+
+    fx.edo(&["graph", "//hello_compose/bundle"])
+        .success()
+        .stdout(contains("digraph edo {"))
+        .stdout(contains(
+            r##""//hello_compose/left" [fillcolor="#9ee6f2", tooltip="cached"];"##,
+        ))
+        .stdout(contains(
+            r##""//hello_compose/bundle" [fillcolor="#dddddd", tooltip="missing"];"##,
+        ))
+        .stdout(contains(
+            r#""//hello_compose/right" -> "//hello_compose/bundle";"#,
+        ))
+        .stdout(contains("hello_local").not());
+    fx.edo(&["graph", "//hello_compose/nope"])
+        .failure()
+        .stderr(contains("//hello_compose/nope"));
+}
+
+#[test]
+fn graph_serves_nodes_with_their_last_failure() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str(
+        r#"
+[transform.broken]
+kind        = "script"
+interpreter = "sh"
+source      = ["src"]
+depends     = ["//hello_script/build"]
+commands    = ["exit 3"]
+"#,
+    );
+    std::fs::write(&manifest, content).unwrap();
+    fx.edo(&["run", "//hello_script/broken"]).failure();
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("edo-cli"))
+        .current_dir(&fx.path)
+        .arg("--storage")
+        .arg(&fx.storage)
+        .args(["graph", "--serve", &format!("127.0.0.1:{port}")])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let mut graph = None;
+    while graph.is_none() && Instant::now() < deadline {
+        graph = get(port, "/api/graph");
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let graph = graph.expect("the graph is served");
+    assert!(
+        graph.contains(r#""//hello_script/broken":["//hello_script/build"]"#),
+        "{graph}"
+    );
+    let nodes = get(port, "/api/nodes").unwrap();
+    let body = nodes.split("\r\n\r\n").nth(1).unwrap();
+    let nodes: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(nodes["//hello_script/build"]["state"], "cached");
+    assert_eq!(nodes["//hello_script/broken"]["state"], "failed");
+    assert!(nodes["//hello_script/broken"]["error"].is_string());
+    assert!(get(port, "/").unwrap().contains("<title>edo graph</title>"));
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
@@ -0,0 +1,64 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+/// Copies `hello_script` with a local build cache, adding `settings` to its
+/// transform and a command reading `license.txt` from a host directory
+/// outside the project.
+fn with_host_access(settings: impl Fn(&str) -> String) -> Fixture {
+    let fx = copy_fixture("hello_script");
+    let host = fx.dir.path().join("host");
+    std::fs::create_dir_all(&host).unwrap();
+    std::fs::write(host.join("license.txt"), "licensed\n").unwrap();
+    let host = host.display().to_string();
+    fx.edit_manifest("hello_script", |content| {
+        let content = replace_once(
+            &content,
+            "source      = [\"src\"]\n",
+            &format!("source      = [\"src\"]\n{}\n", settings(&host)),
+        );
+        replace_once(
+            &content,
+            "  \"mkdir -p {{install-root}}\",\n",
+            &format!("  \"mkdir -p {{{{install-root}}}}\",\n  \"cp {host}/license.txt {{{{install-root}}}}/\",\n"),
+        )
+    })
+    .with_local_build_cache("hello_script", "")
+}
+
+#[test]
+fn host_mounts_are_never_uploaded() {
+    let fx = with_host_access(|host| format!("mounts = [\"{host}\"]\nunsafe = true"));
+    fx.edo(&["run", "//hello_script/build"]).success();
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", "//hello_script/build", out.to_str().unwrap()])
+        .success();
+    assert!(find_file(&out, "license.txt").is_some());
+    let catalog =
+        std::fs::read_to_string(fx.path.join("build-cache/catalog.json")).unwrap_or_default();
+    assert!(!catalog.contains("build"), "{catalog}");
+}
+
+#[test]
+fn builds_without_host_access_are_uploaded() {
+    let fx = with_host_access(|_| String::new());
+    fx.edo(&["run", "//hello_script/build"]).success();
+    let catalog = std::fs::read_to_string(fx.path.join("build-cache/catalog.json"))
+        .expect("the build cache must hold a catalog after the run");
+    assert!(catalog.contains("build"), "{catalog}");
+}
+
+#[test]
+fn host_mounts_require_unsafe_acknowledgement() {
+    let fx = with_host_access(|host| format!("mounts = [\"{host}\"]"));
+    fx.edo(&["run", "//hello_script/build"])
+        .failure()
+        .stderr(contains("require `unsafe = true`"));
+}
+
+#[test]
+fn local_environments_reject_moved_mounts() {
+    let fx = with_host_access(|host| format!("mounts = [\"{host}:/license\"]\nunsafe = true"));
+    fx.edo(&["run", "//hello_script/build"])
+        .failure()
+        .stderr(contains("local environments cannot mount"));
+}
@@ -0,0 +1,72 @@
+use edo_integration_tests::common::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+#[test]
+fn init_templates_build() {
+    for template in ["c", "rust"] {
+        let fx = empty_fixture();
+        fx.edo(&["init", "--template", template, "--name", "demo"])
+            .success()
+            .stdout(contains("demo/edo.toml"));
+        assert!(fx.path.join(".edo").is_dir());
+        assert!(
+            std::fs::read_to_string(fx.path.join("edo.toml"))
+                .unwrap()
+                .contains("# [cache.build]")
+        );
+        fx.edo(&["run", "//demo/build"]).success();
+        let out = fx.dir.path().join("out");
+        fx.edo(&["checkout", "//demo/build", out.to_str().unwrap()])
+            .success();
+        assert!(
+            find_file(&out, "demo").is_some(),
+            "{template} template built nothing"
+        );
+    }
+}
+
+#[test]
+fn init_writes_s3_cache_and_never_overwrites() {
+    let fx = empty_fixture();
+    fx.edo(&[
+        "init",
+        "--template",
+        "core:image",
+        "--s3-bucket",
+        "team-cache",
+    ])
+    .success();
+    let root = std::fs::read_to_string(fx.path.join("edo.toml")).unwrap();
+    assert!(root.contains("[cache.build]") && root.contains("bucket = \"team-cache\""));
+    let package = std::fs::read_to_string(fx.path.join("hello/edo.toml")).unwrap();
+    assert!(package.contains("environment = \"//hello/gcc\""));
+
+    fx.edo(&["init"])
+        .failure()
+        .stderr(contains("already exists, refusing to overwrite it"));
+    fx.edo(&["init", "--name", "not/valid"])
+        .failure()
+        .stderr(contains("is not a valid package name"));
+    fx.edo(&["init", "--template", "acme:embedded-linux"])
+        .failure()
+        .stderr(contains("template of kind acme:embedded-linux"));
+    fx.edo(&["init", "--template", "core:embedded-linux"])
+        .failure()
+        .stderr(contains("has no template with that name"));
+    assert_eq!(
+        std::fs::read_to_string(fx.path.join("edo.toml")).unwrap(),
+        root
+    );
+}
+
+#[test]
+fn init_lists_templates() {
+    let fx = empty_fixture();
+    fx.edo(&["init", "--list"]).success().stdout(
+        contains("core:c ")
+            .and(contains("core:image "))
+            .and(contains("core:rust ")),
+    );
+    assert!(!fx.path.join("edo.toml").exists());
+}
@@ -0,0 +1,64 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+/// Copies `hello_script`, prepending `commands` to its script and appending
+/// `extra` to its manifest.
+fn script_with(commands: &str, extra: &str) -> Fixture {
+    copy_fixture("hello_script")
+        .edit_manifest("hello_script", |content| {
+            replace_once(
+                &content,
+                "commands    = [\n",
+                &format!("commands    = [\n{commands}"),
+            )
+        })
+        .append_manifest("hello_script", extra)
+}
+
+#[test]
+fn key_lists_each_component() {
+    let fx = script_with("", "");
+    let id = fx.inspect_id("//hello_script/build");
+    fx.edo(&["inspect", "--key", "//hello_script/build"])
+        .success()
+        .stdout(contains("sources   src"))
+        .stdout(contains("commands  script"))
+        .stdout(contains("env       interpreter"))
+        .stdout(contains(format!("id        {id}")));
+}
+
+#[test]
+fn key_matches_the_built_artifact() {
+    let fx = script_with("", "");
+    let id = fx.inspect_id("//hello_script/build");
+    fx.edo(&["run", "//hello_script/build"]).success();
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", &id, out.to_str().unwrap()]).success();
+}
+
+#[test]
+fn normalized_keys_ignore_comments() {
+    let policy = "[config.ids]\nnormalize = true";
+    let id = |commands, extra| script_with(commands, extra).inspect_id("//hello_script/build");
+    let plain = id("", policy);
+    let commented = id("  \"# generate the greeting\",\n", policy);
+    assert_eq!(plain, commented);
+    let strict = id("  \"# generate the greeting\",\n", "");
+    assert_ne!(id("", ""), strict);
+}
+
+#[test]
+fn excluded_components_are_marked() {
+    let fx = script_with("", "[config.ids]\nexclude = [\"env\"]");
+    fx.edo(&["inspect", "--key", "//hello_script/build"])
+        .success()
+        .stdout(contains("(excluded)"));
+}
+
+#[test]
+fn unknown_components_are_rejected() {
+    let fx = script_with("", "[config.ids]\nexclude = [\"everything\"]");
+    fx.edo(&["inspect", "//hello_script/build"])
+        .failure()
+        .stderr(contains("ids.exclude"));
+}
@@ -0,0 +1,71 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+#[test]
+fn source_licenses_are_detected_and_reported() {
+    let fx = copy_fixture("hello_script");
+    let report = |fx: &Fixture| -> serde_json::Value {
+        let output = fx
+            .edo(&["licenses", "//hello_script/build", "--json"])
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        serde_json::from_slice(&output).unwrap()
+    };
+    let json = report(&fx);
+    assert_eq!(json["sources"][0]["addr"], "//hello_script/src");
+    assert_eq!(json["sources"][0]["origin"], "unknown");
+    assert_eq!(json["licenses"][0], "NOASSERTION");
+
+    std::fs::write(
+        fx.path.join("hello_script/files/notice.sh"),
+        "# SPDX-License-Identifier: MIT\n",
+    )
+    .unwrap();
+    let json = report(&fx);
+    assert_eq!(json["sources"][0]["origin"], "detected");
+    assert_eq!(json["licenses"][0], "MIT");
+}
+
+#[test]
+fn publishing_enforces_the_license_policy() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let content = std::fs::read_to_string(&manifest).unwrap().replace(
+        "is_archive = false\n",
+        "is_archive = false\nlicense    = \"GPL-3.0-only OR MIT\"\n",
+    );
+    std::fs::write(
+        &manifest,
+        format!(
+            "{content}\n[cache.output]\nkind = \"local\"\npath = \"{}\"\n",
+            fx.path.join("output-cache").display()
+        ),
+    )
+    .unwrap();
+    let config = fx.dir.path().join("policy.toml");
+    std::fs::write(&config, "[policy]\nlicenses = { deny = [\"GPL-*\"] }\n").unwrap();
+    let config = config.display().to_string();
+
+    fx.edo(&["run", "//hello_script/build"]).success();
+    fx.edo(&["--config", &config, "publish", "//hello_script/build", "-t", "latest"])
+        .failure()
+        .stderr(contains(
+            "license 'GPL-3.0-only' of //hello_script/src is denied by policy.licenses.deny entry 'GPL-*'",
+        ));
+    assert!(!fx.path.join("output-cache/catalog.json").exists());
+
+    let report = fx.path.join("licenses.json");
+    fx.edo(&[
+        "publish",
+        "//hello_script/build",
+        "-t",
+        "latest",
+        "--license-report",
+        report.to_str().unwrap(),
+    ])
+    .success();
+    let json: serde_json::Value = serde_json::from_slice(&std::fs::read(report).unwrap()).unwrap();
+    assert_eq!(json["sources"][0]["origin"], "declared");
+}
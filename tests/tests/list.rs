@@ -21,3 +21,13 @@ fn list_umbrella_shows_all_offline() {
         .stdout(contains("//hello_compose/right"))
         .stdout(contains("//cross_project_consumer/final"));
 }
+
+#[test]
+fn list_expands_matrix_variants() {
+    let fx = copy_fixture("hello_matrix");
+    fx.edo(&["list"])
+        .success()
+        .stdout(contains("//hello_matrix/build[profile=debug]"))
+        .stdout(contains("//hello_matrix/build[profile=release]"))
+        .stdout(contains("//hello_matrix/build (2 variants)"));
+}
@@ -0,0 +1,39 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+/// Copies `hello_local`, adds a few extra files and narrows its source with `patterns`.
+fn globbed_local(patterns: &str) -> Fixture {
+    let fx = copy_fixture("hello_local");
+    let files = fx.path.join("hello_local/files");
+    std::fs::write(files.join("build.log"), "noise\n").unwrap();
+    std::fs::create_dir_all(files.join("nested")).unwrap();
+    std::fs::write(files.join("nested/notes.txt"), "nested notes\n").unwrap();
+    let path = fx.path.join("hello_local/edo.toml");
+    let content = std::fs::read_to_string(&path).unwrap();
+    let content = content.replace(
+        "is_archive = false\n",
+        &format!("is_archive = false\n{patterns}\n"),
+    );
+    std::fs::write(path, content).unwrap();
+    fx
+}
+
+#[test]
+fn include_and_exclude_select_files() {
+    let fx = globbed_local("include = [\"**/*.txt\"]\nexclude = [\"nested/**\"]");
+    fx.edo(&["run", "//hello_local/emit"]).success();
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", "//hello_local/emit", out.to_str().unwrap()])
+        .success();
+    assert!(find_file(&out, "greeting.txt").is_some());
+    assert!(find_file(&out, "build.log").is_none());
+    assert!(find_file(&out, "notes.txt").is_none());
+}
+
+#[test]
+fn pattern_escaping_the_source_is_rejected() {
+    let fx = globbed_local("include = [\"../*.toml\"]");
+    fx.edo(&["run", "//hello_local/emit"])
+        .failure()
+        .stderr(contains("must be relative to the source path"));
+}
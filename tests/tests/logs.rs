@@ -0,0 +1,49 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+#[test]
+fn logs_are_uploaded_next_to_the_artifact() {
+    let fx = copy_from(&cache_fixtures_root(), "hello_logs");
+    fx.edo(&["run", "//hello_logs/build"]).success();
+    let catalog = std::fs::read_to_string(fx.path.join("build-cache/catalog.json")).unwrap();
+    assert!(catalog.contains("build_log"), "{catalog}");
+    let local = fx
+        .edo(&["logs", "//hello_logs/build"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert!(String::from_utf8_lossy(&local).contains("=== [execution] ==="));
+
+    // Another machine only has the build cache
+    std::fs::remove_dir_all(&fx.storage).unwrap();
+    fx.edo(&["logs", "//hello_logs/build"])
+        .failure()
+        .stderr(contains(
+            "the local cache holds no log of //hello_logs/build",
+        ));
+    let remote = fx
+        .edo(&["logs", "//hello_logs/build", "--remote"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(remote, local);
+}
+
+#[test]
+fn logs_are_not_uploaded_unless_asked() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["run", "//hello_script/build"]).success();
+    fx.edo(&[
+        "logs",
+        "//hello_script/build",
+        "--remote",
+        "--cache",
+        "local",
+    ])
+    .failure()
+    .stderr(contains(
+        "the local cache holds no log of //hello_script/build",
+    ));
+}
@@ -0,0 +1,158 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{ChildStdin, ChildStdout, Command, Stdio};
+
+use edo_integration_tests::common::*;
+use serde_json::{Value, json};
+
+const BUILD: &str = r#"schema-version = "1"
+
+[source.code]
+kind       = "local"
+path       = "src"
+out        = "."
+is_archive = false
+
+[transform.build]
+kind     = "script"
+source   = ["code", "//lib/missing"]
+commands = ["make"]
+
+[transform.pack]
+kind    = "compose"
+depends = ["//app/build"]
+
+[transform.odd]
+kind = "nope"
+"#;
+
+fn send(stdin: &mut ChildStdin, message: Value) {
+    let body = message.to_string();
+    write!(stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+    stdin.flush().unwrap();
+}
+
+fn receive(stdout: &mut BufReader<ChildStdout>) -> Value {
+    let mut length = 0;
+    loop {
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse().unwrap();
+        }
+    }
+    let mut body = vec![0; length];
+    stdout.read_exact(&mut body).unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+/// Reads messages until the one `matches` accepts.
+fn receive_until(stdout: &mut BufReader<ChildStdout>, matches: impl Fn(&Value) -> bool) -> Value {
+    loop {
+        let message = receive(stdout);
+        if matches(&message) {
+            return message;
+        }
+    }
+}
+
+#[test]
+fn lsp_checks_build_files_and_finds_definitions() {
+    let fx = empty_fixture();
+    std::fs::create_dir_all(fx.path.join("app")).unwrap();
+    let file = fx.path.join("app/edo.toml");
+    std::fs::write(&file, BUILD).unwrap();
+    let root = format!("file://{}", fx.path.display());
+    let uri = format!("file://{}", file.display());
+
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("edo-cli"))
+        .current_dir(&fx.path)
+        .arg("--storage")
+        .arg(&fx.storage)
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+
+    send(
+        &mut stdin,
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                "params": { "capabilities": {}, "rootUri": root } }),
+    );
+    let init = receive_until(&mut stdout, |x| x["id"] == 1);
+    assert_eq!(init["result"]["capabilities"]["definitionProvider"], true);
+    send(
+        &mut stdin,
+        json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+    );
+    let published = receive_until(&mut stdout, |x| {
+        x["method"] == "textDocument/publishDiagnostics" && x["params"]["uri"] == uri
+    });
+    let messages: Vec<&str> = published["params"]["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["message"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "no transform kind named 'nope'",
+            "nothing is defined at //lib/missing"
+        ]
+    );
+
+    // `//app/build` in the depends of pack, on line 15
+    send(
+        &mut stdin,
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/definition",
+                "params": { "textDocument": { "uri": uri },
+                            "position": { "line": 15, "character": 14 } } }),
+    );
+    let definition = receive_until(&mut stdout, |x| x["id"] == 2);
+    assert_eq!(definition["result"]["uri"], uri);
+    assert_eq!(
+        definition["result"]["range"]["start"],
+        json!({ "line": 8, "character": 11 })
+    );
+
+    // Editing the file fixes the kind, and completes the kinds of transforms
+    let edited = BUILD.replace("\"nope\"", "\"");
+    send(
+        &mut stdin,
+        json!({ "jsonrpc": "2.0", "method": "textDocument/didChange",
+                "params": { "textDocument": { "uri": uri, "version": 2 },
+                            "contentChanges": [{ "text": edited }] } }),
+    );
+    send(
+        &mut stdin,
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "textDocument/completion",
+                "params": { "textDocument": { "uri": uri },
+                            "position": { "line": 18, "character": 8 } } }),
+    );
+    let completion = receive_until(&mut stdout, |x| x["id"] == 3);
+    let labels: Vec<&str> = completion["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["label"].as_str().unwrap())
+        .collect();
+    assert!(labels.contains(&"script"), "{labels:?}");
+    assert!(!labels.contains(&"git"), "{labels:?}");
+
+    send(
+        &mut stdin,
+        json!({ "jsonrpc": "2.0", "id": 4, "method": "shutdown" }),
+    );
+    receive_until(&mut stdout, |x| x["id"] == 4);
+    send(&mut stdin, json!({ "jsonrpc": "2.0", "method": "exit" }));
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
@@ -0,0 +1,53 @@
+use edo_integration_tests::common::*;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+/// Runs `//hello_metadata/<name>` and returns the soname it wrote.
+fn consumed_soname(name: &str) -> String {
+    let fx = copy_fixture("hello_metadata");
+    let addr = format!("//hello_metadata/{name}");
+    fx.edo(&["run", &addr]).success();
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", &addr, out.to_str().unwrap()])
+        .success();
+    let soname = find_file(&out, "soname.txt").expect("soname.txt must exist");
+    std::fs::read_to_string(soname).unwrap()
+}
+
+#[test]
+fn dependents_consume_declared_metadata() {
+    assert_eq!(consumed_soname("by_name"), "libfoo.so.1\n");
+}
+
+#[test]
+fn consume_names_the_entry_with_key() {
+    assert_eq!(consumed_soname("by_key"), "2\n");
+}
+
+#[test]
+fn consuming_undeclared_metadata_fails() {
+    let fx = copy_fixture("hello_metadata");
+    fx.edo(&["run", "//hello_metadata/undeclared"])
+        .failure()
+        .stderr(contains("does not declare the metadata 'abi_tag'"));
+}
+
+#[test]
+fn consume_must_name_a_dependency() {
+    let fx = copy_from(&error_fixtures_root(), "consume_without_depends");
+    fx.edo(&["inspect", "//consume_without_depends/build"])
+        .failure()
+        .stderr(contains("consume.soname"));
+}
+
+#[test]
+fn list_finds_transforms_by_capability() {
+    let fx = copy_fixture("hello_metadata");
+    fx.edo(&["list", "--provides", "lib:foo"])
+        .success()
+        .stdout(contains("//hello_metadata/libfoo"))
+        .stdout(contains("//hello_metadata/by_name").not());
+    fx.edo(&["list", "--provides", "lib:bar"])
+        .success()
+        .stdout(contains("//hello_metadata").not());
+}
@@ -0,0 +1,60 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+/// `PATH` with the directory of the `edo-cli` binary first, for the nested
+/// transform of `hello_nested` to run it.
+fn path_with_edo() -> std::ffi::OsString {
+    let edo = assert_cmd::cargo::cargo_bin("edo-cli");
+    let mut paths = vec![edo.parent().unwrap().to_path_buf()];
+    paths.extend(std::env::split_paths(
+        &std::env::var_os("PATH").unwrap_or_default(),
+    ));
+    std::env::join_paths(paths).unwrap()
+}
+
+#[test]
+fn nested_transforms_run_edo_with_their_own_storage() {
+    let fx = copy_fixture("hello_nested");
+    fx.cmd()
+        .env("PATH", path_with_edo())
+        .arg("--storage")
+        .arg(&fx.storage)
+        .args(["run", "//hello_nested/outer"])
+        .assert()
+        .success();
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", "//hello_nested/outer", out.to_str().unwrap()])
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(out.join("nested.txt")).unwrap(),
+        "nested hello\n"
+    );
+    // The nested edo never wrote to the outer cache
+    let catalog = std::fs::read_to_string(fx.storage.join("storage/catalog.json")).unwrap();
+    assert!(!catalog.contains("inner"), "{catalog}");
+}
+
+#[test]
+fn nested_edo_refuses_the_outer_storage() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["run", "//hello_script/build"]).success();
+    let storage = fx.storage.to_str().unwrap();
+    fx.cmd()
+        .env("EDO_NESTED_CACHE", fx.storage.join("storage"))
+        .args(["--storage", storage, "run", "//hello_script/build"])
+        .assert()
+        .failure()
+        .stderr(contains("pass another --storage"));
+}
+
+#[test]
+fn nested_edo_cannot_start_a_daemon() {
+    let fx = copy_fixture("hello_script");
+    let storage = fx.dir.path().join("nested");
+    fx.cmd()
+        .env("EDO_NESTED_CACHE", &fx.storage)
+        .args(["--storage", storage.to_str().unwrap(), "daemon", "start"])
+        .assert()
+        .failure()
+        .stderr(contains("cannot start a daemon inside a transform"));
+}
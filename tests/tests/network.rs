@@ -1,15 +1,24 @@
 use edo_integration_tests::common::*;
+use predicates::str::contains;
 
 #[test]
 fn run_git_source() {
     let fx = copy_from(&net_fixtures_root(), "net_git");
     fx.edo(&["run", "//net_git/build"]).success();
+    fx.edo(&["runs", "show", "--audit"])
+        .success()
+        .stdout(contains("fetch https://github.com/octocat/Hello-World.git"));
 }
 
 #[test]
 fn run_remote_source() {
     let fx = copy_from(&net_fixtures_root(), "net_remote");
     fx.edo(&["run", "//net_remote/build"]).success();
+    fx.edo(&["runs", "show", "--audit"])
+        .success()
+        .stdout(contains(
+            "fetch https://raw.githubusercontent.com/octocat/Hello-World/master/README",
+        ));
 }
 
 #[test]
@@ -33,3 +42,13 @@ fn run_go_vendor_build() {
     let fx = copy_from(&net_fixtures_root(), "go_src");
     fx.edo(&["run", "//go_src/build"]).success();
 }
+
+#[test]
+fn run_script_in_provisioned_container() {
+    if !container_enabled() {
+        eprintln!("skip: EDO_TEST_CONTAINER not set or no podman/docker on PATH");
+        return;
+    }
+    let fx = copy_from(&net_fixtures_root(), "net_container_packages");
+    fx.edo(&["run", "//net_container_packages/build"]).success();
+}
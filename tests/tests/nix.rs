@@ -0,0 +1,97 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+/// Stands in for `nix profile install`, linking the profile to a directory
+/// holding a `greet` command and recording the installables.
+const FAKE_NIX: &str = r#"#!/bin/sh
+while [ $# -gt 0 ]; do
+  case "$1" in
+    --profile) profile="$2"; shift ;;
+    *#*) echo "$1" >> "$(dirname "$0")/installed" ;;
+  esac
+  shift
+done
+mkdir -p "$profile-store/bin"
+printf '#!/bin/sh\necho hello from nix\n' > "$profile-store/bin/greet"
+chmod +x "$profile-store/bin/greet"
+ln -sfn "$profile-store" "$profile"
+"#;
+
+/// Copies `hello_script` with its transform running `greet` in a nix farm
+/// declared with `farm`.
+fn with_nix_farm(farm: &str) -> Fixture {
+    copy_fixture("hello_script")
+        .edit_manifest("hello_script", |content| {
+            let content = replace_once(
+                &content,
+                "source      = [\"src\"]\n",
+                "source      = [\"src\"]\nenvironment = \"//hello_script/nix\"\n",
+            );
+            replace_once(
+                &content,
+                "  \"mkdir -p {{install-root}}\",\n",
+                "  \"mkdir -p {{install-root}}\",\n  \"greet > {{install-root}}/greeting.txt\",\n",
+            )
+        })
+        .append_manifest(
+            "hello_script",
+            &format!("[environment.nix]\nkind = \"nix\"\n{farm}"),
+        )
+}
+
+/// Installs the fake `nix` into a directory of the fixture, returning it.
+fn fake_nix(fx: &Fixture) -> PathBuf {
+    let bin = fx.dir.path().join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let nix = bin.join("nix");
+    std::fs::write(&nix, FAKE_NIX).unwrap();
+    std::fs::set_permissions(&nix, std::fs::Permissions::from_mode(0o755)).unwrap();
+    bin
+}
+
+#[test]
+fn nix_farm_puts_the_profile_on_the_path() {
+    let fx = with_nix_farm("packages = [\"hello\", \"github:owner/repo#tool\"]");
+    let bin = fake_nix(&fx);
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap());
+    fx.cmd()
+        .env("PATH", &path)
+        .arg("--storage")
+        .arg(&fx.storage)
+        .args(["run", "//hello_script/build"])
+        .assert()
+        .success();
+    let installed = std::fs::read_to_string(bin.join("installed")).unwrap();
+    assert_eq!(installed, "nixpkgs#hello\ngithub:owner/repo#tool\n");
+
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", "//hello_script/build", out.to_str().unwrap()])
+        .success();
+    let greeting = find_file(&out, "greeting.txt").expect("greeting.txt must exist");
+    assert_eq!(
+        std::fs::read_to_string(greeting).unwrap(),
+        "hello from nix\n"
+    );
+}
+
+#[test]
+fn nix_farm_ids_follow_the_flake_lock() {
+    let fx = with_nix_farm("flake = \"./hello_script/env#toolchain\"");
+    let env = fx.path.join("hello_script/env");
+    std::fs::create_dir_all(&env).unwrap();
+    std::fs::write(env.join("flake.lock"), "{\"version\": 7}").unwrap();
+    let before = fx.inspect_id("//hello_script/build");
+    assert_eq!(fx.inspect_id("//hello_script/build"), before);
+    std::fs::write(env.join("flake.lock"), "{\"version\": 7, \"root\": \"x\"}").unwrap();
+    assert_ne!(fx.inspect_id("//hello_script/build"), before);
+}
+
+#[test]
+fn nix_farm_needs_packages_or_a_flake() {
+    let fx = with_nix_farm("");
+    fx.edo(&["inspect", "//hello_script/build"])
+        .failure()
+        .stderr(contains("nix environments need packages or a flake"));
+}
@@ -0,0 +1,95 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use edo_integration_tests::common::*;
+
+/// Accepts HTTP requests on a free port, sending each body to the returned
+/// channel.
+fn receiver() -> (u16, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (sender, bodies) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                .unwrap();
+            let _ = sender.send(String::from_utf8(body).unwrap());
+        }
+    });
+    (port, bodies)
+}
+
+#[test]
+fn webhooks_fire_on_first_failure_and_completion() {
+    let (port, bodies) = receiver();
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str(&format!(
+        r#"
+[transform.broken]
+kind        = "script"
+interpreter = "sh"
+source      = ["src"]
+commands    = ["exit 3"]
+
+[config.notify.first]
+kind = "webhook"
+url  = "http://127.0.0.1:{port}/first"
+on   = "first-failure"
+
+[config.notify.done]
+kind     = "webhook"
+url      = "http://127.0.0.1:{port}/done"
+on       = ["failure"]
+template = '{{"status": "{{{{summary.status}}}}", "summary": {{{{summary_json}}}}}}'
+"#
+    ));
+    std::fs::write(&manifest, content).unwrap();
+
+    fx.edo(&["run", "//hello_script/broken"]).failure();
+
+    let first: serde_json::Value =
+        serde_json::from_str(&bodies.recv_timeout(Duration::from_secs(10)).unwrap()).unwrap();
+    assert_eq!(first["trigger"], "first-failure");
+    assert_eq!(first["transform"], "//hello_script/broken");
+
+    let done: serde_json::Value =
+        serde_json::from_str(&bodies.recv_timeout(Duration::from_secs(10)).unwrap()).unwrap();
+    assert_eq!(done["status"], "failed");
+    assert_eq!(done["summary"]["targets"][0], "//hello_script/broken");
+    assert_eq!(done["summary"]["nodes"][0]["outcome"], "failed");
+}
+
+#[test]
+fn invalid_notifiers_are_rejected() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str("\n[config.notify.pager]\nkind = \"pager\"\n");
+    std::fs::write(&manifest, content).unwrap();
+
+    fx.edo(&["run", "//hello_script/build"])
+        .failure()
+        .stderr(predicates::str::contains("notify.pager.kind"));
+}
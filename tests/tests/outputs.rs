@@ -0,0 +1,73 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+/// The layers of the only artifact of `fx` with more than one layer.
+fn layers(fx: &Fixture) -> Vec<serde_json::Value> {
+    let catalog: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(fx.storage.join("storage/catalog.json")).unwrap(),
+    )
+    .unwrap();
+    catalog["manifests"]
+        .as_object()
+        .unwrap()
+        .values()
+        .filter_map(|x| x["layers"].as_array())
+        .find(|x| x.len() > 1)
+        .expect("an artifact with several layers")
+        .clone()
+}
+
+#[test]
+fn outputs_manifest_saves_one_layer_per_entry() {
+    let fx = copy_fixture("hello_outputs");
+    fx.edo(&["run", "//hello_outputs/layers"]).success();
+    let layers = layers(&fx);
+    assert_eq!(layers.len(), 3, "{layers:?}");
+    assert_eq!(layers[0]["platform"]["architecture"], "amd64");
+    assert_eq!(layers[1]["platform"]["architecture"], "arm64");
+    assert!(
+        layers[0]["media_type"].as_str().unwrap().ends_with(".tar"),
+        "{layers:?}"
+    );
+    assert!(
+        layers[2]["media_type"].as_str().unwrap().ends_with(".file"),
+        "{layers:?}"
+    );
+
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", "//hello_outputs/layers", out.to_str().unwrap()])
+        .success();
+    // Both architecture layers unpack into the output, the file is skipped
+    assert_eq!(
+        std::fs::read_to_string(out.join("arch.txt")).unwrap(),
+        "arm\n"
+    );
+}
+
+#[test]
+fn dependents_stage_the_layers_of_their_platform() {
+    let fx = copy_fixture("hello_outputs");
+    fx.edo(&["run", "//hello_outputs/use"]).success();
+
+    let out = fx.dir.path().join("out");
+    fx.edo(&["checkout", "//hello_outputs/use", out.to_str().unwrap()])
+        .success();
+    let expected = match std::env::consts::ARCH {
+        "aarch64" => "arm\n",
+        _ => "x86\n",
+    };
+    assert_eq!(
+        std::fs::read_to_string(out.join("staged.txt")).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn outputs_manifest_paths_stay_in_the_install_root() {
+    let fx = copy_fixture("hello_outputs");
+    fx.edo(&["run", "//hello_outputs/escape"])
+        .failure()
+        .stderr(contains(
+            "line 1 of the outputs manifest outputs.jsonl is invalid",
+        ));
+}
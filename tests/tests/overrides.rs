@@ -0,0 +1,69 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+/// Copies `hello_script` with a `use` transform copying the `hello.txt` of
+/// `//hello_script/build` into its own artifact.
+fn with_dependent() -> Fixture {
+    copy_fixture("hello_script").append_manifest(
+        "hello_script",
+        "[transform.use]\nkind        = \"script\"\ninterpreter = \"sh\"\ndepends     = [\"//hello_script/build\"]\ncommands    = [\n  \"mkdir -p {{install-root}}\",\n  \"cp {{build-root}}/hello.txt {{install-root}}/staged.txt\",\n]",
+    )
+}
+
+fn staged(fx: &Fixture, overrides: &[&str]) -> String {
+    let out = fx.dir.path().join(format!("out-{}", overrides.len()));
+    let mut args = vec!["checkout", "//hello_script/use", out.to_str().unwrap()];
+    for spec in overrides {
+        args.extend(["--override", spec]);
+    }
+    fx.edo(&args).success();
+    std::fs::read_to_string(out.join("staged.txt")).unwrap()
+}
+
+#[test]
+fn override_stages_a_directory_in_place_of_the_dependency() {
+    let fx = with_dependent();
+    let local = fx.dir.path().join("local");
+    std::fs::create_dir_all(&local).unwrap();
+    std::fs::write(local.join("hello.txt"), "local hello\n").unwrap();
+    let spec = format!("//hello_script/build={}", local.display());
+    fx.edo(&["run", "//hello_script/use", "--override", &spec])
+        .success()
+        .stdout(contains("will not be uploaded"));
+    assert_eq!(staged(&fx, &[&spec]), "local hello\n");
+
+    // Without the override the dependency is built again
+    fx.edo(&["run", "//hello_script/use"]).success();
+    assert_eq!(staged(&fx, &[]), "script-produced hello\n");
+}
+
+#[test]
+fn override_stages_a_tarball() {
+    let fx = with_dependent();
+    let local = fx.dir.path().join("local");
+    std::fs::create_dir_all(&local).unwrap();
+    std::fs::write(local.join("hello.txt"), "tarball hello\n").unwrap();
+    let tarball = fx.dir.path().join("local.tar");
+    let status = std::process::Command::new("tar")
+        .arg("-cf")
+        .arg(&tarball)
+        .arg("-C")
+        .arg(&local)
+        .arg("hello.txt")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let spec = format!("//hello_script/build={}", tarball.display());
+    fx.edo(&["run", "//hello_script/use", "--override", &spec])
+        .success();
+    assert_eq!(staged(&fx, &[&spec]), "tarball hello\n");
+}
+
+#[test]
+fn override_of_an_unknown_transform_fails() {
+    let fx = with_dependent();
+    let spec = format!("//hello_script/missing={}", fx.dir.path().display());
+    fx.edo(&["run", "//hello_script/use", "--override", &spec])
+        .failure()
+        .stderr(contains("//hello_script/missing"));
+}
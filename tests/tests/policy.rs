@@ -0,0 +1,39 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+/// Copies `hello_script` and writes a user config holding `policy`, returning
+/// the fixture and the config path to pass with `--config`.
+fn with_policy(policy: &str) -> (Fixture, String) {
+    let fx = copy_fixture("hello_script");
+    let config = fx.write_file("policy.toml", &format!("[policy]\n{policy}\n"));
+    (fx, config)
+}
+
+#[test]
+fn denied_source_kinds_fail_the_load() {
+    let (fx, config) = with_policy("sources = { deny = [\"local\"] }");
+    fx.edo(&["--config", &config, "run", "//hello_script/build"])
+        .failure()
+        .stderr(contains(
+            "source kind 'local' is denied by policy.sources.deny entry 'local'",
+        ));
+}
+
+#[test]
+fn allowed_source_kinds_build() {
+    let (fx, config) = with_policy("sources = { allow = [\"local\", \"git\"] }");
+    fx.edo(&["--config", &config, "run", "//hello_script/build"])
+        .success();
+}
+
+#[test]
+fn projects_cannot_relax_the_policy() {
+    let (fx, config) = with_policy("sources = { deny = [\"local\"] }");
+    let fx = fx.append_manifest(
+        "hello_script",
+        "[config.policy]\nsources = { allow = [\"local\"] }",
+    );
+    fx.edo(&["--config", &config, "run", "//hello_script/build"])
+        .failure()
+        .stderr(contains("violates the policy"));
+}
@@ -0,0 +1,56 @@
+use edo_integration_tests::common::*;
+
+/// Adds a read-through source cache backed by the directory `mirror`.
+fn add_proxy(fx: &Fixture, mirror: &std::path::Path) {
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str(&format!(
+        r#"
+[cache.source.mirror]
+kind  = "proxy"
+cache = {{ kind = "local", path = "{}" }}
+"#,
+        mirror.display()
+    ));
+    std::fs::write(&manifest, content).unwrap();
+}
+
+#[test]
+fn fetched_sources_populate_the_proxy() {
+    let fx = copy_fixture("hello_script");
+    let mirror = fx.dir.path().join("mirror");
+    add_proxy(&fx, &mirror);
+
+    fx.edo(&["run", "//hello_script/build"]).success();
+    let stored = std::fs::read_dir(mirror.join("blobs/blake3"))
+        .map(|x| x.count())
+        .unwrap_or_default();
+    assert!(stored > 0, "the mirror was not populated");
+
+    // A fresh local cache is now served from the mirror
+    let other = fx.dir.path().join("other-storage");
+    fx.cmd()
+        .arg("--storage")
+        .arg(&other)
+        .args(["run", "//hello_script/build"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn proxies_only_serve_sources() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str(&format!(
+        "\n[cache.build]\nkind = \"proxy\"\ncache = {{ kind = \"local\", path = \"{}\" }}\n",
+        fx.dir.path().join("mirror").display()
+    ));
+    std::fs::write(&manifest, content).unwrap();
+
+    fx.edo(&["run", "//hello_script/build"])
+        .failure()
+        .stderr(predicates::str::contains(
+            "a proxy can only be a source cache",
+        ));
+}
@@ -0,0 +1,44 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+#[test]
+fn published_artifacts_are_checked_out_by_alias() {
+    let fx = copy_from(&cache_fixtures_root(), "hello_publish");
+    fx.edo(&["run", "//hello_publish/build"]).success();
+    fx.edo(&["publish", "//hello_publish/build"])
+        .success()
+        .stdout(contains("hello_publish_build:latest"));
+    fx.edo(&["publish", "//hello_publish/build", "-t", "{name}:release"])
+        .success()
+        .stdout(contains("hello_publish_build:release"));
+
+    // Consumers only have the output cache
+    std::fs::remove_dir_all(&fx.storage).unwrap();
+    let out = fx.path.join("out");
+    fx.edo(&[
+        "checkout",
+        "hello_publish_build:release",
+        out.to_str().unwrap(),
+    ])
+    .success();
+    assert!(out.join("hello.txt").exists());
+    fx.edo(&[
+        "checkout",
+        "hello_publish_build:nightly",
+        out.to_str().unwrap(),
+    ])
+    .failure()
+    .stderr(contains(
+        "the output cache has no artifact tagged 'hello_publish_build:nightly'",
+    ));
+}
+
+#[test]
+fn invalid_templates_publish_nothing() {
+    let fx = copy_from(&cache_fixtures_root(), "hello_publish");
+    fx.edo(&["run", "//hello_publish/build"]).success();
+    fx.edo(&["publish", "//hello_publish/build", "-t", "{name}:{flavour}"])
+        .failure()
+        .stderr(contains("unknown placeholder {flavour}"));
+    assert!(!fx.path.join("output-cache/catalog.json").exists());
+}
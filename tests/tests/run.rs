@@ -1,4 +1,6 @@
 use edo_integration_tests::common::*;
+use predicates::prelude::PredicateBooleanExt;
+use predicates::str::contains;
 
 #[test]
 fn run_import_happy_path() {
@@ -12,12 +14,71 @@ fn run_script_happy_path() {
     fx.edo(&["run", "//hello_script/build"]).success();
 }
 
+#[test]
+fn run_ui_falls_back_without_a_terminal() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["run", "--ui", "//hello_script/build"])
+        .success()
+        .stderr(contains("\x1b[?1049h").not());
+}
+
+#[test]
+fn run_with_jobs_and_farm_limits() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str("\n[config.scheduler.farms]\n\"//default\" = 1\n");
+    std::fs::write(&manifest, content).unwrap();
+    fx.edo(&["run", "--jobs", "2", "//hello_script/build"])
+        .success();
+    fx.edo(&["run", "-j", "0", "//hello_script/build"])
+        .failure()
+        .stderr(contains("--jobs"));
+}
+
+#[test]
+fn run_transform_declared_in_yaml() {
+    let fx = copy_fixture("hello_script");
+    std::fs::write(
+        fx.path.join("hello_script/extra.edo.yaml"),
+        r#"schema-version: "1"
+transform:
+  from-yaml:
+    kind: script
+    interpreter: sh
+    source: [src]
+    commands:
+      - mkdir -p {{install-root}}
+      - sh {{build-root}}/make_hello.sh {{install-root}}/hello.txt
+"#,
+    )
+    .unwrap();
+    fx.edo(&["list"])
+        .success()
+        .stdout(contains("//hello_script/build"))
+        .stdout(contains("//hello_script/from-yaml"));
+    fx.edo(&["run", "//hello_script/from-yaml"]).success();
+}
+
 #[test]
 fn run_compose_merges_layers() {
     let fx = copy_fixture("hello_compose");
     fx.edo(&["run", "//hello_compose/bundle"]).success();
 }
 
+#[test]
+fn run_matrix_single_variant() {
+    let fx = copy_fixture("hello_matrix");
+    fx.edo(&["run", "//hello_matrix/build[profile=release]"])
+        .success();
+}
+
+#[test]
+fn run_matrix_group_builds_all_variants() {
+    let fx = copy_fixture("hello_matrix");
+    fx.edo(&["run", "//hello_matrix/build"]).success();
+}
+
 #[test]
 fn run_bad_cwd_has_no_edo_toml() {
     let dir = tempfile::TempDir::new().expect("tempdir");
@@ -32,3 +93,65 @@ fn run_bad_cwd_has_no_edo_toml() {
         .assert()
         .failure();
 }
+
+#[test]
+fn run_reports_injected_environment_failure() {
+    let fx = copy_fixture("hello_script");
+    fx.cmd()
+        .env("EDO_FAULTS", "env.up=fail")
+        .arg("--storage")
+        .arg(&fx.storage)
+        .args(["run", "//hello_script/build"])
+        .assert()
+        .failure()
+        .stderr(contains("injected failure at env.up"));
+}
+
+#[test]
+fn run_rejects_malformed_fault_plan() {
+    let fx = copy_fixture("hello_script");
+    fx.cmd()
+        .env("EDO_FAULTS", "env.up")
+        .arg("--storage")
+        .arg(&fx.storage)
+        .args(["run", "//hello_script/build"])
+        .assert()
+        .failure()
+        .stderr(contains("invalid fault rule 'env.up'"));
+}
+
+#[test]
+fn run_restores_staged_snapshot_until_inputs_change() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let content = std::fs::read_to_string(&manifest).unwrap();
+    std::fs::write(
+        &manifest,
+        format!("{content}\n[config.scheduler]\nsnapshots = true\n"),
+    )
+    .unwrap();
+    fx.edo(&["run", "//hello_script/build"]).success();
+    let snapshots = fx.storage.join("snapshots/hello_script-build");
+    assert!(
+        snapshots.join("key").exists(),
+        "the staged environment is snapshotted"
+    );
+    let staged = find_file(&snapshots, "make_hello.sh").expect("sources are in the snapshot");
+
+    // Only the snapshot holds the marker, so the edited commands see it
+    // when the staged environment is restored
+    std::fs::write(staged.with_file_name("marker.txt"), "restored").unwrap();
+    let edited = std::fs::read_to_string(&manifest).unwrap().replace(
+        "{{install-root}}/hello.txt\",\n",
+        "{{install-root}}/hello.txt\",\n  \"test -f {{build-root}}/marker.txt\",\n",
+    );
+    std::fs::write(&manifest, &edited).unwrap();
+    fx.edo(&["run", "//hello_script/build"]).success();
+
+    // Changed sources are staged again, replacing the snapshot
+    let script = fx.path.join("hello_script/files/make_hello.sh");
+    let source = std::fs::read_to_string(&script).unwrap();
+    std::fs::write(&script, format!("{source}\n# edited\n")).unwrap();
+    fx.edo(&["run", "//hello_script/build"]).failure();
+    assert!(find_file(&snapshots, "marker.txt").is_none());
+}
@@ -0,0 +1,210 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+#[test]
+fn run_writes_summary() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["run", "//hello_script/build"]).success();
+
+    let runs = fx.summaries();
+    assert_eq!(runs.len(), 1);
+    let run = &runs[0];
+    assert_eq!(run["status"], "success");
+    assert_eq!(run["targets"][0], "//hello_script/build");
+    let nodes = run["nodes"].as_array().expect("nodes");
+    let build = nodes
+        .iter()
+        .find(|x| x["addr"] == "//hello_script/build")
+        .expect("build node");
+    assert_eq!(build["outcome"], "built");
+    assert!(build["id"].is_string());
+    assert!(build["log"].is_string());
+}
+
+#[test]
+fn run_writes_audit() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["run", "//hello_script/build"]).success();
+
+    let id = fx.summaries()[0]["id"].as_str().unwrap().to_string();
+    let audit = fx.storage.join(format!("runs/audit/{id}.json"));
+    let content =
+        std::fs::read_to_string(&audit).unwrap_or_else(|e| panic!("read {}: {e}", audit.display()));
+    // Local sources and caches never leave the machine
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&content).unwrap(),
+        serde_json::json!([])
+    );
+    fx.edo(&["runs", "show", "--audit", "--json"])
+        .success()
+        .stdout(contains("[]"));
+}
+
+#[test]
+fn failed_run_summary_records_error() {
+    let fx = copy_fixture("hello_script");
+    fx.cmd()
+        .env("EDO_FAULTS", "env.up=fail")
+        .arg("--storage")
+        .arg(&fx.storage)
+        .args(["run", "//hello_script/build"])
+        .assert()
+        .failure();
+
+    let runs = fx.summaries();
+    assert_eq!(runs.len(), 1);
+    let run = &runs[0];
+    assert_eq!(run["status"], "failed");
+    let failed = run["nodes"]
+        .as_array()
+        .expect("nodes")
+        .iter()
+        .find(|x| x["outcome"] == "failed")
+        .expect("failed node");
+    assert_eq!(failed["error"], "injected failure at env.up");
+    assert!(failed["log"].is_string());
+}
+
+#[test]
+fn runs_list_and_show() {
+    let fx = copy_fixture("hello_local");
+    fx.edo(&["run", "//hello_local/emit"]).success();
+    fx.edo(&["run", "//hello_local/emit"]).success();
+
+    let runs = fx.summaries();
+    assert_eq!(runs.len(), 2);
+    let first = runs[0]["id"].as_str().unwrap();
+    let second = runs[1]["id"].as_str().unwrap();
+    // The second run finds everything in the local cache
+    assert_eq!(runs[1]["nodes"][0]["outcome"], "cached");
+
+    fx.edo(&["runs", "list"])
+        .success()
+        .stdout(contains(first))
+        .stdout(contains(second));
+    fx.edo(&["runs", "show"])
+        .success()
+        .stdout(contains(format!("run {second}")))
+        .stdout(contains("cached //hello_local/emit"));
+    fx.edo(&["runs", "show", first, "--json"])
+        .success()
+        .stdout(contains("\"status\": \"success\""));
+    fx.edo(&["runs", "show", "does-not-exist"])
+        .failure()
+        .stderr(contains("no run found matching 'does-not-exist'"));
+}
+
+#[test]
+fn failed_run_summary_keeps_last_output() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let content = std::fs::read_to_string(&manifest).unwrap().replace(
+        "  \"mkdir -p {{install-root}}\",\n",
+        "  \"echo 'compiler says no' >&2\",\n  \"exit 3\",\n",
+    );
+    std::fs::write(manifest, content).unwrap();
+    fx.edo(&["run", "//hello_script/build"]).failure();
+
+    let runs = fx.summaries();
+    let failed = runs[0]["nodes"]
+        .as_array()
+        .expect("nodes")
+        .iter()
+        .find(|x| x["outcome"] == "failed")
+        .expect("failed node");
+    let tail = failed["tail"].as_array().expect("tail");
+    assert!(tail.iter().any(|x| x == "compiler says no"), "{tail:?}");
+    fx.edo(&["runs", "show"])
+        .success()
+        .stdout(contains("last output:"))
+        .stdout(contains("      compiler says no"));
+}
+
+#[test]
+fn failed_run_summary_names_the_failing_command() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let content = std::fs::read_to_string(&manifest).unwrap().replace(
+        "  \"mkdir -p {{install-root}}\",\n",
+        "  \"echo 'compiler says no' >&2\",\n  \"exit 3\",\n",
+    );
+    std::fs::write(manifest, content).unwrap();
+    fx.edo(&["run", "//hello_script/build"])
+        .failure()
+        .stderr(contains("command 1 `exit 3` failed with exit code 3"));
+
+    let runs = fx.summaries();
+    let failed = runs[0]["nodes"]
+        .as_array()
+        .expect("nodes")
+        .iter()
+        .find(|x| x["outcome"] == "failed")
+        .expect("failed node");
+    assert_eq!(failed["failure"]["index"], 1);
+    assert_eq!(failed["failure"]["command"], "exit 3");
+    assert_eq!(failed["failure"]["code"], 3);
+    // Markers are kept out of the recorded output
+    let tail = failed["tail"].as_array().expect("tail");
+    assert!(
+        !tail.iter().any(|x| x.as_str().unwrap().contains("::edo::")),
+        "{tail:?}"
+    );
+    fx.edo(&["runs", "show"])
+        .success()
+        .stdout(contains("failed at command 1: exit 3 (exit code 3)"));
+}
+
+#[test]
+fn failed_run_keeps_workspace() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let content = std::fs::read_to_string(&manifest)
+        .unwrap()
+        .replace("  \"mkdir -p {{install-root}}\",\n", "  \"exit 3\",\n");
+    std::fs::write(manifest, content).unwrap();
+    fx.edo(&["run", "//hello_script/build"]).failure();
+    assert!(!fx.storage.join("debug").exists());
+
+    fx.edo(&["run", "--keep-workspace", "//hello_script/build"])
+        .failure();
+    let kept = std::fs::read_dir(fx.storage.join("debug"))
+        .expect("debug directory")
+        .flatten()
+        .map(|x| x.path())
+        .collect::<Vec<_>>();
+    assert_eq!(kept.len(), 1, "{kept:?}");
+    let name = kept[0].file_name().unwrap().to_string_lossy().to_string();
+    assert!(name.starts_with("hello_script-build-"), "{name}");
+    fx.edo(&["runs", "show"])
+        .success()
+        .stdout(contains(format!("workspace: {}", kept[0].display())));
+}
+
+#[test]
+fn rebuilds_are_estimated_from_earlier_runs() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["run", "//hello_script/build"]).success();
+    assert!(fx.storage.join("runs/durations/transforms.json").is_file());
+    assert!(fx.summaries()[0].get("estimate_ms").is_none());
+
+    // A changed input rebuilds the transform under the same id prefix
+    let script = fx.path.join("hello_script/files/make_hello.sh");
+    let content = std::fs::read_to_string(&script).unwrap();
+    std::fs::write(&script, format!("{content}# changed\n")).unwrap();
+    fx.edo(&["run", "//hello_script/build"]).success();
+
+    let runs = fx.summaries();
+    assert_eq!(runs.len(), 2);
+    assert!(runs[1]["estimate_ms"].is_u64(), "{}", runs[1]);
+    let build = runs[1]["nodes"]
+        .as_array()
+        .expect("nodes")
+        .iter()
+        .find(|x| x["addr"] == "//hello_script/build")
+        .expect("build node");
+    assert_eq!(build["outcome"], "built");
+    assert!(build["estimate_ms"].is_u64(), "{build}");
+    fx.edo(&["runs", "show"])
+        .success()
+        .stdout(contains("estimated"));
+}
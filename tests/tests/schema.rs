@@ -0,0 +1,53 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+fn schema(fx: &Fixture, args: &[&str]) -> serde_json::Value {
+    let mut full = vec!["schema"];
+    full.extend_from_slice(args);
+    let output = fx.edo(&full).success().get_output().stdout.clone();
+    serde_json::from_slice(&output).expect("schema is json")
+}
+
+#[test]
+fn schema_describes_every_core_kind() {
+    let fx = empty_fixture();
+    let file = schema(&fx, &[]);
+    let transforms = file["properties"]["transform"]["additionalProperties"]["anyOf"]
+        .as_array()
+        .unwrap();
+    let script = transforms
+        .iter()
+        .find(|x| x["properties"]["kind"]["const"] == "script")
+        .expect("script is described");
+    assert!(
+        script["required"]
+            .as_array()
+            .unwrap()
+            .contains(&"commands".into())
+    );
+
+    let kind = schema(&fx, &["transform:script"]);
+    assert_eq!(kind["properties"], script["properties"]);
+    assert_eq!(
+        schema(&fx, &["s3"])["properties"]["bucket"]["type"],
+        "string"
+    );
+    fx.edo(&["schema", "--list"])
+        .success()
+        .stdout(contains("source:git"))
+        .stdout(contains("storage-backend:proxy"));
+}
+
+#[test]
+fn schema_rejects_unknown_and_ambiguous_kinds() {
+    let fx = empty_fixture();
+    fx.edo(&["schema", "nope"])
+        .failure()
+        .stderr(contains("no component kind named 'nope'"));
+    fx.edo(&["schema", "widget:script"])
+        .failure()
+        .stderr(contains("no component kind named 'widget:script'"));
+    fx.edo(&["schema", "local"])
+        .failure()
+        .stderr(contains("source:local"));
+}
@@ -0,0 +1,78 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use edo_integration_tests::common::*;
+
+/// Sends a GET request for `path` and returns the whole response.
+fn get(port: u16, path: &str) -> Option<String> {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).ok()?;
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+    )
+    .ok()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok()?;
+    Some(response)
+}
+
+#[test]
+fn serve_reports_the_running_transform() {
+    let fx = copy_fixture("hello_script");
+    let manifest = fx.path.join("hello_script/edo.toml");
+    let mut content = std::fs::read_to_string(&manifest).unwrap();
+    content.push_str(
+        r#"
+[transform.slow]
+kind        = "script"
+interpreter = "sh"
+source      = ["src"]
+commands    = ["echo waiting-for-status", "sleep 3", "mkdir -p {{install-root}}"]
+"#,
+    );
+    std::fs::write(&manifest, content).unwrap();
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("edo-cli"))
+        .current_dir(&fx.path)
+        .arg("--storage")
+        .arg(&fx.storage)
+        .args(["run", "--serve", &format!("127.0.0.1:{port}")])
+        .arg("//hello_script/slow")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let mut status = String::new();
+    while Instant::now() < deadline {
+        if let Some(response) = get(port, "/api/status")
+            && response.contains(r#""state":"running""#)
+        {
+            status = response;
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    assert!(status.contains("//hello_script/slow"), "{status}");
+    assert!(status.contains("application/json"));
+
+    let page = get(port, "/").unwrap();
+    assert!(page.contains("edo build status"));
+    let log = get(port, "/api/logs?addr=%2F%2Fhello_script%2Fslow").unwrap();
+    assert!(log.starts_with("HTTP/1.1 200"), "{log}");
+    assert!(
+        get(port, "/api/logs?addr=//hello_script/missing")
+            .unwrap()
+            .starts_with("HTTP/1.1 404")
+    );
+
+    assert!(child.wait().unwrap().success());
+}
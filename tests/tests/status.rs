@@ -0,0 +1,39 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+#[test]
+fn status_reports_what_a_run_would_rebuild() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["status"])
+        .success()
+        .stdout(contains("local sources changed:\n  //hello_script/src"))
+        .stdout(contains(
+            "//hello_script/build: source //hello_script/src changed",
+        ));
+
+    fx.edo(&["run", "//hello_script/build"]).success();
+    fx.edo(&["status"])
+        .success()
+        .stdout(contains("lock file: up to date"))
+        .stdout(contains("local sources: unchanged"))
+        .stdout(contains("transforms: all built"));
+
+    let script = fx.path.join("hello_script/files/make_hello.sh");
+    let mut content = std::fs::read_to_string(&script).unwrap();
+    content.push_str("\n# edited\n");
+    std::fs::write(&script, content).unwrap();
+    fx.edo(&["status"]).success().stdout(contains(
+        "//hello_script/build: source //hello_script/src changed",
+    ));
+}
+
+#[test]
+fn status_reports_json() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["run", "//hello_script/build"]).success();
+    let output = fx.edo(&["status", "--json"]).success().get_output().clone();
+    let status: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(status["lock"], "current");
+    assert_eq!(status["dirty"], serde_json::json!([]));
+    assert_eq!(status["stale"], serde_json::json!([]));
+}
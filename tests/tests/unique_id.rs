@@ -0,0 +1,52 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+/// Copies `hello_script`, appending `extra` to its manifest.
+fn script_with(extra: &str) -> Fixture {
+    copy_fixture("hello_script").append_manifest("hello_script", extra)
+}
+
+/// Switches the build transform to `bash`, runs it again and returns the
+/// build node of the second run.
+fn rerun_with_bash(fx: Fixture) -> (serde_json::Value, serde_json::Value) {
+    fx.edo(&["run", "//hello_script/build"]).success();
+    let fx = fx.edit_manifest("hello_script", |content| {
+        replace_once(&content, "interpreter = \"sh\"", "interpreter = \"bash\"")
+    });
+    fx.edo(&["run", "//hello_script/build"]).success();
+    let runs = fx.summaries();
+    let build = |run: &serde_json::Value| {
+        run["nodes"]
+            .as_array()
+            .expect("nodes")
+            .iter()
+            .find(|x| x["addr"] == "//hello_script/build")
+            .expect("build node")
+            .clone()
+    };
+    (build(&runs[0]), build(&runs[1]))
+}
+
+#[test]
+fn environment_identity_changes_the_id() {
+    let fx = script_with("");
+    let (first, second) = rerun_with_bash(fx);
+    assert_ne!(first["id"], second["id"]);
+    assert_eq!(second["outcome"], "built");
+}
+
+#[test]
+fn environment_identity_can_be_disabled() {
+    let fx = script_with("[config.ids]\nenvironment = false");
+    let (first, second) = rerun_with_bash(fx);
+    assert_eq!(first["id"], second["id"]);
+    assert_eq!(second["outcome"], "cached");
+}
+
+#[test]
+fn environment_identity_switch_must_be_a_bool() {
+    let fx = script_with("[config.ids]\nenvironment = \"no\"");
+    fx.edo(&["run", "//hello_script/build"])
+        .failure()
+        .stderr(contains("ids.environment"));
+}
@@ -38,3 +38,11 @@ fn update_without_edits_then_run_ok() {
     // Running again without edits must remain successful.
     fx.edo(&["run", "//hello_local/emit"]).success();
 }
+
+#[test]
+fn update_explain_reports_unknown_package() {
+    let fx = copy_fixture("hello_local");
+    fx.edo(&["update", "--explain", "missing"])
+        .success()
+        .stdout(predicates::str::contains("no dependency named missing"));
+}
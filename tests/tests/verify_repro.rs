@@ -0,0 +1,36 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+#[test]
+fn verify_repro_accepts_import() {
+    let fx = copy_fixture("hello_local");
+    fx.edo(&["verify-repro", "//hello_local/emit"])
+        .success()
+        .stdout(contains("//hello_local/emit is reproducible"));
+    // The cached artifact is left in place for later commands
+    fx.edo(&["diff", "//hello_local/emit", "//hello_local/emit"])
+        .success()
+        .stdout(contains("artifacts are identical"));
+}
+
+#[test]
+fn verify_repro_accepts_import_built_apart() {
+    let fx = copy_fixture("hello_local");
+    fx.edo(&["run", "//hello_local/emit"]).success();
+    // verify-repro compares against the cached build, so the rebuild stages
+    // its directories in a later second than the first build did
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+    fx.edo(&["verify-repro", "//hello_local/emit"])
+        .success()
+        .stdout(contains("//hello_local/emit is reproducible"));
+}
+
+#[test]
+fn verify_repro_unknown_transform_fails() {
+    let fx = copy_fixture("hello_local");
+    fx.edo(&["verify-repro", "//hello_local/missing"])
+        .failure()
+        .stderr(contains(
+            "no transform found with addr '//hello_local/missing'",
+        ));
+}
@@ -0,0 +1,48 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+/// Rewrites `hello_local/edo.toml` in the umbrella with `edit`.
+fn edit_hello_local(fx: &Fixture, edit: impl Fn(String) -> String) {
+    let path = fx.path.join("hello_local/edo.toml");
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(path, edit(content)).unwrap();
+}
+
+#[test]
+fn private_package_rejects_other_namespaces() {
+    let fx = copy_umbrella();
+    edit_hello_local(&fx, |x| {
+        format!("{x}\n[package]\nvisibility = \"private\"\n")
+    });
+    fx.edo(&["run", "//cross_project_consumer/final"])
+        .failure()
+        .stderr(contains(
+            "//hello_local/emit is not visible to //cross_project_consumer/final",
+        ));
+}
+
+#[test]
+fn transform_visibility_overrides_the_package_default() {
+    let fx = copy_umbrella();
+    edit_hello_local(&fx, |x| {
+        x.replace(
+            "[transform.emit]\n",
+            "[transform.emit]\nvisibility = [\"//cross_project_consumer\"]\n",
+        ) + "\n[package]\nvisibility = \"private\"\n"
+    });
+    fx.edo(&["run", "//cross_project_consumer/final"]).success();
+}
+
+#[test]
+fn invalid_visibility_is_rejected() {
+    let fx = copy_fixture("hello_local");
+    let path = fx.path.join("hello_local/edo.toml");
+    let content = std::fs::read_to_string(&path).unwrap().replace(
+        "[transform.emit]\n",
+        "[transform.emit]\nvisibility = \"//a//b\"\n",
+    );
+    std::fs::write(path, content).unwrap();
+    fx.edo(&["list"])
+        .failure()
+        .stderr(contains("invalid visibility rule '//a//b'"));
+}
@@ -0,0 +1,46 @@
+use edo_integration_tests::common::*;
+use predicates::str::contains;
+
+#[test]
+fn why_names_the_inputs_that_changed_since_the_last_run() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["why", "//hello_script/build"])
+        .success()
+        .stdout(contains("//hello_script/build is not built"))
+        .stdout(contains("no recorded run computed its key"));
+
+    fx.edo(&["run", "//hello_script/build"]).success();
+    fx.edo(&["why", "//hello_script/build"])
+        .success()
+        .stdout(contains("//hello_script/build is a cache hit"));
+
+    let script = fx.path.join("hello_script/files/make_hello.sh");
+    let mut content = std::fs::read_to_string(&script).unwrap();
+    content.push_str("\n# edited\n");
+    std::fs::write(&script, content).unwrap();
+    let build = fx.path.join("hello_script/edo.toml");
+    let content = std::fs::read_to_string(&build)
+        .unwrap()
+        .replace("mkdir -p", "mkdir -pv");
+    std::fs::write(&build, content).unwrap();
+    fx.edo(&["why", "//hello_script/build"])
+        .success()
+        .stdout(contains("//hello_script/build is not built"))
+        .stdout(contains("source src digest changed"))
+        .stdout(contains("commands script changed"));
+}
+
+#[test]
+fn why_reports_json() {
+    let fx = copy_fixture("hello_script");
+    fx.edo(&["run", "//hello_script/build"]).success();
+    let output = fx
+        .edo(&["why", "//hello_script/build", "--json"])
+        .success()
+        .get_output()
+        .clone();
+    let cause: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(cause["built"], true);
+    assert_eq!(cause["changes"], serde_json::json!([]));
+    assert_eq!(cause["previous"], cause["id"]);
+}